@@ -0,0 +1,124 @@
+use std::collections::HashSet;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use crate::tools::ToolResult;
+
+/// Tool output kept in the audit log
+const MAX_LOGGED_OUTPUT: usize = 2000;
+
+/// What the user decided for a dangerous command
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Decision {
+    /// Approved for this call only
+    Approved,
+    /// Approved, and the same command runs without asking for the rest of the session
+    ApprovedForSession,
+    /// Ran without asking because it was approved earlier in the session
+    Remembered,
+    Denied,
+}
+
+/// One line of `.codestral/tool_audit.jsonl`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub timestamp: DateTime<Utc>,
+    pub user: String,
+    /// Chat mode the command was requested in
+    pub mode: String,
+    pub tool: String,
+    pub command: String,
+    pub decision: Decision,
+    /// None when the command was not run
+    pub success: Option<bool>,
+    #[serde(default)]
+    pub output: String,
+}
+
+/// Approvals of dangerous commands: audit log on disk and commands approved for the session.
+/// Approvals match the exact command text, so any change asks again
+pub struct ToolAudit {
+    project_root: PathBuf,
+    path: PathBuf,
+    session_approved: HashSet<(String, String)>,
+}
+
+impl ToolAudit {
+    pub fn new(project_root: &Path) -> Self {
+        Self {
+            project_root: project_root.to_path_buf(),
+            path: project_root.join(crate::project_dir::DIR_NAME).join("tool_audit.jsonl"),
+            session_approved: HashSet::new(),
+        }
+    }
+
+    /// Whether this exact command was approved for the session
+    pub fn is_remembered(&self, tool: &str, command: &str) -> bool {
+        self.session_approved.contains(&(tool.to_string(), command.to_string()))
+    }
+
+    pub fn remember(&mut self, tool: &str, command: &str) {
+        self.session_approved.insert((tool.to_string(), command.to_string()));
+    }
+
+    /// Append a decision and, if the command ran, its result
+    pub fn log(&self, mode: &str, tool: &str, command: &str, decision: Decision, result: Option<&ToolResult>) -> Result<(), String> {
+        let entry = AuditEntry {
+            timestamp: Utc::now(),
+            user: current_user(),
+            mode: mode.to_string(),
+            tool: tool.to_string(),
+            command: command.to_string(),
+            decision,
+            success: result.map(|r| r.success),
+            output: result.map(|r| r.output.chars().take(MAX_LOGGED_OUTPUT).collect()).unwrap_or_default(),
+        };
+
+        // With the .gitignore that keeps the log out of commits
+        crate::project_dir::ensure(&self.project_root)?;
+        let line = serde_json::to_string(&entry)
+            .map_err(|e| format!("Serialize error: {}", e))?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|e| format!("Cannot open audit log {}: {}", self.path.display(), e))?;
+        writeln!(file, "{}", line).map_err(|e| format!("Write error: {}", e))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+fn current_user() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| "inconnu".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_session_approval_matches_exact_command() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut audit = ToolAudit::new(dir.path());
+        audit.remember("execute_bash", "rm -rf build");
+
+        assert!(audit.is_remembered("execute_bash", "rm -rf build"));
+        assert!(!audit.is_remembered("execute_bash", "rm -rf build/ src"));
+
+        audit.log("AUTO", "execute_bash", "rm -rf build", Decision::Denied, None).unwrap();
+        let line = fs::read_to_string(audit.path()).unwrap();
+        let entry: AuditEntry = serde_json::from_str(line.trim()).unwrap();
+        assert_eq!(entry.decision, Decision::Denied);
+        assert_eq!(entry.success, None);
+        assert!(dir.path().join(".codestral/.gitignore").is_file());
+    }
+}
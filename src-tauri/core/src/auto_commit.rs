@@ -0,0 +1,224 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use git2::{DiffFormat, Index, Repository, Signature};
+use crate::settings::{settings_file, ProjectSettings};
+use crate::backend::LlmBackend;
+use crate::differ::DiffStats;
+use crate::mistral_client::Message;
+
+/// Staged diff sent to the model for the message, in characters
+const MAX_DIFF_CHARS: usize = 8000;
+
+/// Recent subjects shown to the model as the style to follow
+const STYLE_COMMITS: usize = 8;
+
+pub const MESSAGE_PROMPT: &str = "Tu écris le message de commit git de modifications faites par un assistant de code. \
+Réponds uniquement par le message : une première ligne de 72 caractères au plus, à l'impératif, qui dit ce que \
+fait le changement, puis si utile une ligne vide et quelques lignes d'explication. Suis la langue et le style des \
+commits récents du dépôt. Pas de guillemets, pas de bloc de code.";
+
+/// Whether every applied change set is committed: `"auto_commit": true` in `.codestral/settings.json`,
+/// else in the `config` section of the global settings.json. Off by default
+pub fn enabled(project_root: &Path) -> bool {
+    if let Some(enabled) = ProjectSettings::load(project_root).ok().and_then(|settings| settings.auto_commit) {
+        return enabled;
+    }
+    settings_file().ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+        .and_then(|json| json.get("config")?.get("auto_commit")?.as_bool())
+        .unwrap_or(false)
+}
+
+fn git_error(e: git2::Error) -> String {
+    format!("git: {}", e.message())
+}
+
+/// Whether git can stage the path: a file not ignored, or a deleted one it tracked
+fn committable(repo: &Repository, index: &Index, workdir: &Path, path: &str) -> bool {
+    if workdir.join(path).exists() {
+        !repo.is_path_ignored(path).unwrap_or(true)
+    } else {
+        index.get_path(Path::new(path), 0).is_some()
+    }
+}
+
+/// Lines of the diff between two trees, as `git diff` prints them
+fn patch_text(diff: &git2::Diff) -> Result<String, String> {
+    let mut text = String::new();
+    diff.print(DiffFormat::Patch, |_, _, line| {
+        if matches!(line.origin(), '+' | '-' | ' ') {
+            text.push(line.origin());
+        }
+        text.push_str(&String::from_utf8_lossy(line.content()));
+        true
+    })
+    .map_err(git_error)?;
+    Ok(text)
+}
+
+/// Subjects of the last commits, newest first
+fn recent_subjects(repo: &Repository) -> String {
+    let Ok(mut walk) = repo.revwalk() else {
+        return String::new();
+    };
+    if walk.push_head().is_err() {
+        return String::new();
+    }
+    walk.filter_map(|oid| repo.find_commit(oid.ok()?).ok())
+        .take(STYLE_COMMITS)
+        .filter_map(|commit| commit.summary().map(|s| format!("{}\n", s)))
+        .collect()
+}
+
+/// Author of the commit: the repository's user, or the companion when user.name/user.email are unset
+fn signature(repo: &Repository) -> Result<Signature<'static>, String> {
+    repo.signature()
+        .or_else(|_| Signature::now("Codestral Companion", "companion@localhost"))
+        .map_err(git_error)
+}
+
+/// The model's answer as a commit message: without code fences or quotes around it
+fn clean_message(response: &str) -> String {
+    let lines: Vec<&str> = response.trim().lines().filter(|line| !line.trim_start().starts_with("```")).collect();
+    lines.join("\n").trim().trim_matches(['"', '\'', '`']).trim().to_string()
+}
+
+/// Message when the model gives none, e.g. "Codestral Companion: update lib.rs, main.rs"
+fn fallback_message(paths: &[String]) -> String {
+    let names: Vec<&str> = paths.iter()
+        .map(|path| Path::new(path).file_name().and_then(|name| name.to_str()).unwrap_or(path))
+        .collect();
+    format!("Codestral Companion: update {}", names.join(", "))
+}
+
+/// Commit the files of an applied change set, and only them, with a message the model writes from
+/// their diff and the change set's `stats` as last paragraph. Returns "<short hash> <subject>", or
+/// None when they left nothing to commit
+pub async fn commit(root: &Path, paths: &[PathBuf], stats: &DiffStats, client: &dyn LlmBackend) -> Result<Option<String>, String> {
+    // Files of other workspace roots belong to other repositories
+    let canonical = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    let paths: Vec<String> = paths.iter()
+        .filter_map(|path| path.strip_prefix(root).or_else(|_| path.strip_prefix(&canonical)).ok())
+        .map(|path| path.to_string_lossy().to_string())
+        .collect();
+    if paths.is_empty() {
+        return Ok(None);
+    }
+    let repo = Repository::discover(root)
+        .map_err(|_| format!("Cannot commit: {} is not a git repository", root.display()))?;
+    let workdir = repo.workdir()
+        .ok_or_else(|| format!("Cannot commit: {} is a bare repository", root.display()))?;
+    let workdir = workdir.canonicalize().unwrap_or_else(|_| workdir.to_path_buf());
+    // Paths of the repository, which may start above the project root
+    let paths: Vec<String> = paths.iter()
+        .filter_map(|path| canonical.join(path).strip_prefix(&workdir).ok().map(|p| p.to_string_lossy().replace('\\', "/")))
+        .collect();
+    let mut staged = repo.index().map_err(git_error)?;
+    let paths: Vec<String> = paths.into_iter().filter(|path| committable(&repo, &staged, &workdir, path)).collect();
+    if paths.is_empty() {
+        return Ok(None);
+    }
+
+    // Stage the applied files, as `git add -A -- <paths>`
+    for path in &paths {
+        if workdir.join(path).exists() {
+            staged.add_path(Path::new(path)).map_err(git_error)?;
+        } else {
+            staged.remove_path(Path::new(path)).map_err(git_error)?;
+        }
+    }
+    staged.write().map_err(git_error)?;
+
+    // Tree of HEAD with only those files replaced: whatever else the user staged stays out
+    let head = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+    let head_tree = head.as_ref().map(|commit| commit.tree()).transpose().map_err(git_error)?;
+    let mut index = Index::new().map_err(git_error)?;
+    if let Some(tree) = &head_tree {
+        index.read_tree(tree).map_err(git_error)?;
+    }
+    for path in &paths {
+        match staged.get_path(Path::new(path), 0) {
+            Some(entry) => index.add(&entry).map_err(git_error)?,
+            None => index.remove_path(Path::new(path)).map_err(git_error)?,
+        }
+    }
+    let tree = repo.find_tree(index.write_tree_to(&repo).map_err(git_error)?).map_err(git_error)?;
+    let diff = repo.diff_tree_to_tree(head_tree.as_ref(), Some(&tree), None).map_err(git_error)?;
+    if diff.deltas().len() == 0 {
+        return Ok(None);
+    }
+
+    let diff: String = patch_text(&diff)?.chars().take(MAX_DIFF_CHARS).collect();
+    let messages = vec![
+        Message::system(MESSAGE_PROMPT),
+        Message::user(format!("Commits récents:\n{}\nDiff:\n{}", recent_subjects(&repo), diff)),
+    ];
+    let message = match client.chat(messages).await {
+        Ok(response) if !clean_message(&response).is_empty() => clean_message(&response),
+        _ => fallback_message(&paths),
+    };
+    let message = format!("{}\n\n{}", message, stats.header());
+
+    let signature = signature(&repo)?;
+    let parents: Vec<&git2::Commit> = head.iter().collect();
+    let oid = repo.commit(Some("HEAD"), &signature, &signature, &message, &tree, &parents).map_err(git_error)?;
+    let subject = message.lines().next().unwrap_or_default();
+    Ok(Some(format!("{} {}", &oid.to_string()[..7], subject)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mistral_client::{ApiProvider, MistralClient};
+
+    #[test]
+    fn test_commit_only_the_applied_files() {
+        assert_eq!(clean_message("```\nAdd a cache to the parser\n\nKeeps parsed files.\n```\n"), "Add a cache to the parser\n\nKeeps parsed files.");
+        assert_eq!(clean_message("\"Fix the retry delay\""), "Fix the retry delay");
+
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let repo = Repository::init(root).unwrap();
+        fs::write(root.join("lib.rs"), "fn a() {}\n").unwrap();
+        fs::write(root.join("old.rs"), "fn old() {}\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_all(["*"], git2::IndexAddOption::DEFAULT, None).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let dev = Signature::now("Dev", "dev@example.com").unwrap();
+        repo.commit(Some("HEAD"), &dev, &dev, "Initial", &tree, &[]).unwrap();
+
+        // Applied: lib.rs changed, new.rs created, old.rs deleted; notes.txt is the user's
+        fs::write(root.join("lib.rs"), "fn a2() {}\n").unwrap();
+        fs::write(root.join("new.rs"), "fn new() {}\n").unwrap();
+        fs::remove_file(root.join("old.rs")).unwrap();
+        fs::write(root.join("notes.txt"), "todo\n").unwrap();
+        let paths: Vec<PathBuf> = ["lib.rs", "new.rs", "old.rs"].iter().map(|p| root.join(p)).collect();
+
+        // Unreachable endpoint: the message falls back to the file names
+        let client = MistralClient::new(String::new(), ApiProvider::Custom {
+            base_url: "http://127.0.0.1:9/v1".to_string(),
+            model: "test".to_string(),
+        });
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let stats = DiffStats { files: 3, added: 2, removed: 2, languages: vec!["Rust".to_string()] };
+        let committed = runtime.block_on(commit(root, &paths, &stats, &client)).unwrap().unwrap();
+        assert!(committed.ends_with(" Codestral Companion: update lib.rs, new.rs, old.rs"));
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        assert_eq!(head.message().unwrap(), "Codestral Companion: update lib.rs, new.rs, old.rs\n\n3 fichiers, +2/−2 lignes (Rust)");
+        let diff = repo.diff_tree_to_tree(Some(&head.parent(0).unwrap().tree().unwrap()), Some(&head.tree().unwrap()), None).unwrap();
+        let changes: Vec<(git2::Delta, String)> = diff.deltas()
+            .map(|delta| (delta.status(), delta.new_file().path().unwrap().to_string_lossy().to_string()))
+            .collect();
+        assert_eq!(changes, [
+            (git2::Delta::Modified, "lib.rs".to_string()),
+            (git2::Delta::Added, "new.rs".to_string()),
+            (git2::Delta::Deleted, "old.rs".to_string()),
+        ]);
+        let statuses = repo.statuses(None).unwrap();
+        let left: Vec<(String, git2::Status)> = statuses.iter().map(|entry| (entry.path().unwrap().to_string(), entry.status())).collect();
+        assert_eq!(left, [("notes.txt".to_string(), git2::Status::WT_NEW)]);
+        assert_eq!(runtime.block_on(commit(root, &paths, &stats, &client)).unwrap(), None);
+    }
+}
@@ -0,0 +1,149 @@
+use crate::mistral_client::{retry_reason, ApiProvider, MistralClient, Message, OllamaSettings, RetryNotice, RetryPolicy, Usage};
+use crate::ollama_client::OllamaClient;
+use anyhow::Result;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+/// Future of an `LlmBackend` request; `Send`, so the request can run on its own task
+pub type BackendFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T>> + Send + 'a>>;
+
+/// Requests to a model provider. The agent, the chat, the TUI and the GUI only see this trait:
+/// `backend` picks the implementation from the settings, tests script one
+pub trait LlmBackend: Send + Sync {
+    /// Answer to the messages, with the token usage when the API reports it
+    fn chat_with_usage(&self, messages: Vec<Message>) -> BackendFuture<'_, (String, Option<Usage>)>;
+
+    fn chat(&self, messages: Vec<Message>) -> BackendFuture<'_, String> {
+        Box::pin(async move { self.chat_with_usage(messages).await.map(|(content, _)| content) })
+    }
+
+    /// Like `chat`, with the answer streamed: `on_delta` receives each piece as it arrives.
+    /// Not `Send`: the callback usually updates the terminal
+    fn chat_stream<'a>(&'a self, messages: Vec<Message>, on_delta: &'a mut dyn FnMut(&str)) -> Pin<Box<dyn Future<Output = Result<String>> + 'a>>;
+
+    /// Fill-in-the-middle: the code between `prefix` and `suffix`, e.g. at an editor's cursor
+    fn fim<'a>(&'a self, prefix: &'a str, suffix: &'a str, max_tokens: u32) -> BackendFuture<'a, String>;
+
+    /// Vectors of the inputs with an embedding model, in the order of the inputs
+    fn embed<'a>(&'a self, model: &'a str, inputs: &'a [String]) -> BackendFuture<'a, Vec<Vec<f32>>>;
+
+    /// Models available with the key, as listed by the provider
+    fn list_models(&self) -> BackendFuture<'_, Vec<String>>;
+
+    /// Tokens of the text for the model: the estimate of `context_guard` unless the backend
+    /// knows its tokenizer
+    fn count_tokens(&self, text: &str) -> usize {
+        crate::context_guard::count_tokens(text)
+    }
+
+    /// Model used for requests
+    fn get_model(&self) -> &str;
+
+    /// Context window of the model, in tokens
+    fn context_window(&self) -> u64;
+
+    fn provider(&self) -> &ApiProvider;
+
+    /// Embedding model when settings.json names none
+    fn default_embedding_model(&self) -> &'static str;
+
+    /// The same backend on another model
+    fn for_model(&self, model: &str) -> Arc<dyn LlmBackend>;
+
+    /// Rate limit and quota headers of the last response, when the provider sends any
+    fn rate_limits(&self) -> Vec<(String, String)> {
+        Vec::new()
+    }
+}
+
+/// Backend of a provider: Ollama's native API for Ollama; Mistral AI, Codestral and custom
+/// endpoints share the OpenAI-style chat completions API of `MistralClient`
+pub fn backend(api_key: String, provider: ApiProvider, temperature: Option<f32>) -> Arc<dyn LlmBackend> {
+    match provider {
+        ApiProvider::Ollama => {
+            let client = OllamaClient::new(api_key, OllamaSettings::load());
+            Arc::new(match temperature {
+                Some(temperature) => client.with_temperature(temperature),
+                None => client,
+            })
+        }
+        provider => {
+            let client = MistralClient::new(api_key, provider);
+            Arc::new(match temperature {
+                Some(temperature) => client.with_temperature(temperature),
+                None => client,
+            })
+        }
+    }
+}
+
+impl dyn LlmBackend {
+    /// Send a minimal request to check the key and endpoint
+    pub async fn test_connection(&self) -> Result<()> {
+        self.chat(vec![Message::user("Hello")]).await.map(|_| ())
+    }
+
+    /// `chat` retried according to the policy; `on_retry` is called before each wait
+    pub async fn chat_with_retry(&self, messages: Vec<Message>, policy: RetryPolicy, mut on_retry: impl FnMut(&RetryNotice)) -> Result<String> {
+        let mut attempt = 1;
+        loop {
+            let error = match self.chat(messages.clone()).await {
+                Ok(response) => return Ok(response),
+                Err(e) => e,
+            };
+            let reason = match retry_reason(&error) {
+                Some(reason) if attempt < policy.attempts => reason,
+                _ => return Err(error),
+            };
+            attempt += 1;
+            let notice = RetryNotice { attempt, attempts: policy.attempts, delay: policy.delay(attempt), reason };
+            on_retry(&notice);
+            tokio::time::sleep(notice.delay).await;
+        }
+    }
+
+    /// `chat_stream` retried according to the policy; a retry starts the answer over
+    pub async fn chat_stream_with_retry(
+        &self,
+        messages: Vec<Message>,
+        policy: RetryPolicy,
+        mut on_retry: impl FnMut(&RetryNotice),
+        mut on_delta: impl FnMut(&str),
+    ) -> Result<String> {
+        let mut attempt = 1;
+        loop {
+            let error = match self.chat_stream(messages.clone(), &mut on_delta).await {
+                Ok(response) => return Ok(response),
+                Err(e) => e,
+            };
+            let reason = match retry_reason(&error) {
+                Some(reason) if attempt < policy.attempts => reason,
+                _ => return Err(error),
+            };
+            attempt += 1;
+            let notice = RetryNotice { attempt, attempts: policy.attempts, delay: policy.delay(attempt), reason };
+            on_retry(&notice);
+            tokio::time::sleep(notice.delay).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backend_follows_the_provider() {
+        let codestral = backend("key".to_string(), ApiProvider::Codestral, None);
+        assert_eq!((codestral.get_model(), codestral.context_window()), ("codestral-latest", 256_000));
+        assert_eq!(codestral.for_model("mistral-small-latest").get_model(), "mistral-small-latest");
+
+        let custom = backend(String::new(), ApiProvider::Custom { base_url: "http://localhost:8000/v1".to_string(), model: "qwen2.5-coder".to_string() }, Some(0.2));
+        assert_eq!((custom.get_model(), custom.provider().name()), ("qwen2.5-coder", "Custom"));
+
+        let ollama = backend(String::new(), ApiProvider::Ollama, None);
+        assert_eq!((ollama.provider(), ollama.default_embedding_model()), (&ApiProvider::Ollama, "nomic-embed-text"));
+        assert_eq!(ollama.count_tokens("fn main() {}"), crate::context_guard::count_tokens("fn main() {}"));
+    }
+}
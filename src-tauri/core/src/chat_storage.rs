@@ -0,0 +1,381 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Utc, Duration};
+use crate::mistral_client::{Message, Role};
+
+/// Days a deleted chat stays in the trash before it is removed for good
+pub const TRASH_RETENTION_DAYS: i64 = 30;
+
+/// Interface a chat was started from
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ChatSource {
+    #[default]
+    Cli,
+    Gui,
+}
+
+/// Format the time elapsed since `at` ("il y a 3 min")
+pub fn time_ago(at: DateTime<Utc>) -> String {
+    let diff = Utc::now().signed_duration_since(at);
+    
+    if diff < Duration::minutes(1) {
+        "à l'instant".to_string()
+    } else if diff < Duration::hours(1) {
+        format!("il y a {} min", diff.num_minutes())
+    } else if diff < Duration::hours(24) {
+        format!("il y a {} h", diff.num_hours())
+    } else if diff < Duration::days(7) {
+        format!("il y a {} j", diff.num_days())
+    } else {
+        at.format("%d/%m/%Y").to_string()
+    }
+}
+
+/// When a message was sent, which model wrote it and its estimated size
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MessageMeta {
+    pub timestamp: DateTime<Utc>,
+    /// Model that produced an assistant message
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Estimated tokens (see `context_guard::count_tokens`)
+    pub tokens: usize,
+}
+
+impl MessageMeta {
+    pub fn now(tokens: usize) -> Self {
+        Self { timestamp: Utc::now(), model: None, tokens }
+    }
+
+    /// One-line summary, e.g. "il y a 3 min · 120 tokens · codestral-latest"
+    pub fn summary(&self) -> String {
+        let mut parts = vec![time_ago(self.timestamp), format!("{} tokens", self.tokens)];
+        if let Some(model) = &self.model {
+            parts.push(model.clone());
+        }
+        parts.join(" · ")
+    }
+}
+
+/// Saved chat session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedChat {
+    pub id: String,
+    pub title: String,
+    /// Empty for conversations not tied to a project (e.g. started from the GUI)
+    pub project_path: String,
+    pub messages: Vec<Message>,
+    /// Metadata of `messages`, in the same order (None for tool results and older chats)
+    #[serde(default)]
+    pub metadata: Vec<Option<MessageMeta>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    #[serde(default)]
+    pub source: ChatSource,
+    /// System prompt of the template the chat was created from, sent with every request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<String>,
+    /// Model of the chat, instead of the default one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// When the chat was moved to the trash
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+impl SavedChat {
+    pub fn new(project_path: &str) -> Self {
+        let id = uuid::Uuid::new_v4().to_string();
+        let now = Utc::now();
+        Self {
+            id,
+            title: "Nouvelle conversation".to_string(),
+            project_path: project_path.to_string(),
+            messages: Vec::new(),
+            metadata: Vec::new(),
+            created_at: now,
+            updated_at: now,
+            source: ChatSource::Cli,
+            system_prompt: None,
+            model: None,
+            deleted_at: None,
+        }
+    }
+
+    /// Messages to send to the API (roles of other frontends are mapped when the chat is loaded)
+    pub fn api_messages(&self) -> Vec<Message> {
+        self.messages.clone()
+    }
+
+    /// Generate title from first user message
+    pub fn auto_title(&mut self) {
+        if let Some(first_user_msg) = self.messages.iter().find(|m| m.role == Role::User) {
+            let content = &first_user_msg.content;
+            // Take first 40 chars or first sentence
+            let title = if let Some(dot_pos) = content.find('.') {
+                if dot_pos < 60 {
+                    &content[..dot_pos]
+                } else {
+                    &content[..content.len().min(40)]
+                }
+            } else {
+                &content[..content.len().min(40)]
+            };
+            self.title = title.trim().to_string();
+            if self.title.len() < content.len() {
+                self.title.push_str("...");
+            }
+        }
+    }
+
+    /// Format time elapsed since last message
+    pub fn time_ago(&self) -> String {
+        time_ago(self.updated_at)
+    }
+
+    /// Days left before a trashed chat is removed for good
+    pub fn days_left_in_trash(&self) -> i64 {
+        let deleted_at = self.deleted_at.unwrap_or(self.updated_at);
+        (TRASH_RETENTION_DAYS - Utc::now().signed_duration_since(deleted_at).num_days()).max(0)
+    }
+}
+
+/// Chat storage manager
+pub struct ChatStorage {
+    storage_dir: PathBuf,
+}
+
+impl ChatStorage {
+    pub fn new() -> Result<Self, String> {
+        let config_dir = dirs::config_dir()
+            .ok_or("Cannot find config directory")?
+            .join("com.rony.companion-chat")
+            .join("cli-chats");
+        
+        fs::create_dir_all(&config_dir)
+            .map_err(|e| format!("Cannot create chat storage dir: {}", e))?;
+        
+        Ok(Self { storage_dir: config_dir })
+    }
+
+    /// Save a chat session; its project becomes the most recent one, from whichever mode saved it
+    pub fn save(&self, chat: &SavedChat) -> Result<(), String> {
+        let path = self.storage_dir.join(format!("{}.json", chat.id));
+        let json = serde_json::to_string_pretty(chat)
+            .map_err(|e| format!("Serialize error: {}", e))?;
+        fs::write(&path, json)
+            .map_err(|e| format!("Write error: {}", e))?;
+        if !chat.project_path.is_empty() {
+            crate::recent_projects::remember_conversation(Path::new(&chat.project_path), &chat.id);
+        }
+        Ok(())
+    }
+
+    /// Load a chat session by ID
+    pub fn load(&self, id: &str) -> Result<SavedChat, String> {
+        let path = self.storage_dir.join(format!("{}.json", id));
+        let json = fs::read_to_string(&path)
+            .map_err(|e| format!("Read error: {}", e))?;
+        serde_json::from_str(&json)
+            .map_err(|e| format!("Parse error: {}", e))
+    }
+
+    /// List all saved chats, sorted by updated_at (most recent first)
+    pub fn list(&self) -> Result<Vec<SavedChat>, String> {
+        read_chats(&self.storage_dir)
+    }
+        
+    /// List chats for a specific project, including conversations not tied to any project
+    pub fn list_for_project(&self, project_path: &str) -> Result<Vec<SavedChat>, String> {
+        let all = self.list()?;
+        Ok(all.into_iter()
+            .filter(|c| c.project_path == project_path || c.project_path.is_empty())
+            .collect())
+    }
+
+    /// Distinct project paths that have saved chats, most recently used first
+    pub fn list_projects(&self) -> Result<Vec<String>, String> {
+        let mut projects: Vec<String> = Vec::new();
+        for chat in self.list()? {
+            if !chat.project_path.is_empty() && !projects.contains(&chat.project_path) {
+                projects.push(chat.project_path);
+            }
+        }
+        Ok(projects)
+    }
+
+    fn trash_dir(&self) -> PathBuf {
+        self.storage_dir.join("trash")
+    }
+
+    /// Move a chat to the trash, where it stays `TRASH_RETENTION_DAYS` days
+    pub fn delete(&self, id: &str) -> Result<(), String> {
+        let mut chat = self.load(id)?;
+        chat.deleted_at = Some(Utc::now());
+        let trash = self.trash_dir();
+        fs::create_dir_all(&trash)
+            .map_err(|e| format!("Cannot create trash dir: {}", e))?;
+        let json = serde_json::to_string_pretty(&chat)
+            .map_err(|e| format!("Serialize error: {}", e))?;
+        fs::write(trash.join(format!("{}.json", id)), json)
+            .map_err(|e| format!("Write error: {}", e))?;
+        fs::remove_file(self.storage_dir.join(format!("{}.json", id)))
+            .map_err(|e| format!("Delete error: {}", e))?;
+        self.list_trash().map(|_| ())
+    }
+
+    /// Trashed chats, most recently deleted first; the expired ones are removed on the way
+    pub fn list_trash(&self) -> Result<Vec<SavedChat>, String> {
+        let trash = self.trash_dir();
+        if !trash.exists() {
+            return Ok(Vec::new());
+        }
+        let mut chats = Vec::new();
+        for chat in read_chats(&trash)? {
+            let deleted_at = chat.deleted_at.unwrap_or(chat.updated_at);
+            if Utc::now().signed_duration_since(deleted_at) > Duration::days(TRASH_RETENTION_DAYS) {
+                let _ = fs::remove_file(trash.join(format!("{}.json", chat.id)));
+            } else {
+                chats.push(chat);
+            }
+        }
+        chats.sort_by_key(|c| std::cmp::Reverse(c.deleted_at));
+        Ok(chats)
+    }
+
+    /// Put a trashed chat back with the others
+    pub fn restore(&self, id: &str) -> Result<SavedChat, String> {
+        let path = self.trash_dir().join(format!("{}.json", id));
+        let json = fs::read_to_string(&path)
+            .map_err(|e| format!("Read error: {}", e))?;
+        let mut chat: SavedChat = serde_json::from_str(&json)
+            .map_err(|e| format!("Parse error: {}", e))?;
+        chat.deleted_at = None;
+        self.save(&chat)?;
+        fs::remove_file(&path)
+            .map_err(|e| format!("Delete error: {}", e))?;
+        Ok(chat)
+    }
+
+    /// Remove every trashed chat for good, returning how many there were
+    pub fn empty_trash(&self) -> Result<usize, String> {
+        let chats = self.list_trash()?;
+        for chat in &chats {
+            fs::remove_file(self.trash_dir().join(format!("{}.json", chat.id)))
+                .map_err(|e| format!("Delete error: {}", e))?;
+        }
+        Ok(chats.len())
+    }
+}
+
+/// Chats saved as JSON files in `dir`, sorted by updated_at (most recent first)
+fn read_chats(dir: &Path) -> Result<Vec<SavedChat>, String> {
+    let mut chats = Vec::new();
+
+    let entries = fs::read_dir(dir)
+        .map_err(|e| format!("Read dir error: {}", e))?;
+
+    for entry in entries.flatten() {
+        if entry.path().extension().map(|e| e == "json").unwrap_or(false) {
+            if let Ok(json) = fs::read_to_string(entry.path()) {
+                if let Ok(chat) = serde_json::from_str::<SavedChat>(&json) {
+                    chats.push(chat);
+                }
+            }
+        }
+    }
+
+    // Sort by updated_at descending
+    chats.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+
+    Ok(chats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trash_keeps_deleted_chats_until_restored_or_expired() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = ChatStorage { storage_dir: dir.path().to_path_buf() };
+        let kept = SavedChat::new("");
+        let mut expired = SavedChat::new("");
+        storage.save(&kept).unwrap();
+        storage.save(&expired).unwrap();
+
+        storage.delete(&kept.id).unwrap();
+        assert_eq!(storage.list().unwrap().len(), 1);
+        let trash = storage.list_trash().unwrap();
+        assert_eq!(trash[0].id, kept.id);
+        assert_eq!(trash[0].days_left_in_trash(), TRASH_RETENTION_DAYS);
+
+        let restored = storage.restore(&kept.id).unwrap();
+        assert!(restored.deleted_at.is_none());
+        assert!(storage.load(&kept.id).unwrap().deleted_at.is_none());
+        assert!(storage.list_trash().unwrap().is_empty());
+
+        // Deleted 31 days ago: removed the next time the trash is read
+        storage.delete(&expired.id).unwrap();
+        expired.deleted_at = Some(Utc::now() - Duration::days(TRASH_RETENTION_DAYS + 1));
+        fs::write(storage.trash_dir().join(format!("{}.json", expired.id)), serde_json::to_string(&expired).unwrap()).unwrap();
+        storage.delete(&kept.id).unwrap();
+        assert_eq!(storage.list_trash().unwrap().len(), 1);
+        assert_eq!(storage.empty_trash().unwrap(), 1);
+        assert!(storage.list().unwrap().is_empty() && storage.list_trash().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_gui_chats_are_listed_with_every_project() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = ChatStorage { storage_dir: dir.path().to_path_buf() };
+        // Written by the GUI: its own role names, no project
+        fs::write(dir.path().join("gui.json"), r#"{
+            "id": "gui", "title": "Bonjour", "project_path": "", "source": "gui",
+            "messages": [{ "role": "user", "content": "Salut" }, { "role": "ai", "content": "Bonjour" }],
+            "created_at": "2026-01-01T00:00:00Z", "updated_at": "2026-01-01T00:00:00Z"
+        }"#).unwrap();
+        // Saved directly: save() would also touch the user's recent projects
+        for (project, age) in [("/a", 1), ("/b", 2), ("/a", 3)] {
+            let mut chat = SavedChat::new(project);
+            chat.updated_at = Utc::now() - Duration::hours(age);
+            fs::write(dir.path().join(format!("{}.json", chat.id)), serde_json::to_string(&chat).unwrap()).unwrap();
+        }
+
+        let gui = storage.load("gui").unwrap();
+        assert_eq!(gui.source, ChatSource::Gui);
+        assert_eq!(gui.api_messages()[1].role, Role::Assistant);
+
+        let for_a = storage.list_for_project("/a").unwrap();
+        assert_eq!(for_a.len(), 3);
+        assert!(for_a.iter().all(|chat| chat.project_path != "/b"));
+        assert_eq!(storage.list_projects().unwrap(), ["/a", "/b"]);
+    }
+
+    #[test]
+    fn test_message_metadata_is_saved_and_summarized() {
+        let mut meta = MessageMeta::now(120);
+        meta.timestamp = Utc::now() - Duration::minutes(3);
+        meta.model = Some("codestral-latest".to_string());
+        assert_eq!(meta.summary(), "il y a 3 min · 120 tokens · codestral-latest");
+        assert_eq!(MessageMeta::now(5).summary(), "à l'instant · 5 tokens");
+        assert_eq!(time_ago(Utc::now() - Duration::hours(30)), "il y a 1 j");
+
+        let dir = tempfile::tempdir().unwrap();
+        let storage = ChatStorage { storage_dir: dir.path().to_path_buf() };
+        let mut chat = SavedChat::new("");
+        chat.messages = vec![Message { role: Role::User, content: "a".to_string() }, Message { role: Role::User, content: "b".to_string() }];
+        chat.metadata = vec![Some(meta.clone()), None];
+        storage.save(&chat).unwrap();
+        assert_eq!(storage.load(&chat.id).unwrap().metadata, [Some(meta), None]);
+
+        // Saved before metadata existed
+        let mut older = serde_json::to_value(&chat).unwrap();
+        older.as_object_mut().unwrap().remove("metadata");
+        fs::write(dir.path().join("older.json"), older.to_string()).unwrap();
+        assert!(storage.load("older").unwrap().metadata.is_empty());
+    }
+}
+
@@ -0,0 +1,171 @@
+use crate::indexer::CodebaseIndex;
+use crate::persistent_index::is_symbol_line;
+
+/// Tokens kept free for the conversation's answer, on top of the messages themselves
+pub const RESPONSE_RESERVE_TOKENS: usize = 8_000;
+
+/// Estimated token count of a text. Mistral's tokenizer is not available offline, so this counts
+/// cl100k_base BPE tokens, which split code and prose in nearly the same places; loaded once,
+/// then shared by every token counter
+pub fn count_tokens(text: &str) -> usize {
+    tiktoken_rs::cl100k_base_singleton().encode_ordinary(text).len()
+}
+
+/// How much of the codebase goes into the CODEBASE section, from the richest to the leanest
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ContextLevel {
+    /// File contents
+    Full,
+    /// Each file with its declarations (functions, types...) only
+    RepoMap,
+    /// File paths only
+    FileList,
+}
+
+impl ContextLevel {
+    pub fn next(self) -> Option<ContextLevel> {
+        match self {
+            ContextLevel::Full => Some(ContextLevel::RepoMap),
+            ContextLevel::RepoMap => Some(ContextLevel::FileList),
+            ContextLevel::FileList => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ContextLevel::Full => "contenu des fichiers",
+            ContextLevel::RepoMap => "carte du dépôt (déclarations)",
+            ContextLevel::FileList => "liste des fichiers",
+        }
+    }
+}
+
+/// CODEBASE section at every level, built once from the index
+#[derive(Debug, Clone, Default)]
+pub struct CodebaseContext {
+    full: String,
+    repo_map: String,
+    file_list: String,
+}
+
+impl CodebaseContext {
+    /// `max_tokens` bounds the full level, as `CodebaseIndex::build_context` does
+    pub fn from_index(index: &CodebaseIndex, max_tokens: usize) -> Self {
+        let repo_map: String = index.files.iter()
+            .map(|file| {
+                let symbols: Vec<&str> = file.content.lines()
+                    .filter(|line| is_symbol_line(line))
+                    .map(|line| line.trim_end().trim_end_matches('{').trim_end())
+                    .collect();
+                format!("\n--- {} ---\n{}", file.relative_path, symbols.join("\n"))
+            })
+            .collect();
+        let file_list = index.files.iter()
+            .map(|file| format!("- {}", file.relative_path))
+            .chain(index.generated_files().map(|path| format!("- {} (generated, content omitted)", path)))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let generated = generated_section(index);
+        Self {
+            full: index.build_context(max_tokens).first().cloned().unwrap_or_default() + &generated,
+            repo_map: repo_map + &generated,
+            file_list,
+        }
+    }
+
+    /// File contents limited to `paths`, in their order (e.g. by relevance); the repo map and
+    /// the file list still cover the whole index
+    pub fn focus(mut self, index: &CodebaseIndex, paths: &[String], max_tokens: usize) -> Self {
+        let mut focused = CodebaseIndex::new(index.root.clone());
+        focused.files = paths.iter()
+            .filter_map(|path| index.files.iter().find(|file| file.relative_path == *path).cloned())
+            .collect();
+        self.full = focused.build_context(max_tokens).first().cloned().unwrap_or_default() + &generated_section(index);
+        self
+    }
+
+    pub fn get(&self, level: ContextLevel) -> &str {
+        match level {
+            ContextLevel::Full => &self.full,
+            ContextLevel::RepoMap => &self.repo_map,
+            ContextLevel::FileList => &self.file_list,
+        }
+    }
+}
+
+/// Lockfiles and minified files of the index, whose content is left out
+fn generated_section(index: &CodebaseIndex) -> String {
+    let paths: Vec<String> = index.generated_files().map(|path| format!("- {}", path)).collect();
+    if paths.is_empty() {
+        return String::new();
+    }
+    format!("\n--- generated files (content omitted) ---\n{}", paths.join("\n"))
+}
+
+/// System prompt chosen by `fit`
+pub struct Fitted {
+    pub prompt: String,
+    pub level: ContextLevel,
+    pub tokens: usize,
+    /// False when even the leanest level is over the budget
+    pub fits: bool,
+}
+
+/// Build the prompt at `start`, then at leaner levels until it fits in `budget` tokens
+pub fn fit(start: ContextLevel, budget: usize, build: impl Fn(ContextLevel) -> String) -> Fitted {
+    let mut level = start;
+    loop {
+        let prompt = build(level);
+        let tokens = count_tokens(&prompt);
+        match level.next() {
+            Some(next) if tokens > budget => level = next,
+            _ => return Fitted { prompt, level, tokens, fits: tokens <= budget },
+        }
+    }
+}
+
+/// Warning shown when the prompt had to be reduced, e.g. from `start` to `fitted.level`
+pub fn downgrade_warning(start: ContextLevel, fitted: &Fitted, budget: usize) -> Option<String> {
+    if fitted.level == start && fitted.fits {
+        return None;
+    }
+    Some(if fitted.fits {
+        format!(
+            "⚠️ Prompt système trop long pour le modèle ({} tokens disponibles) : CODEBASE réduit à la {} (~{} tokens).",
+            budget, fitted.level.label(), fitted.tokens
+        )
+    } else {
+        let reduced = if fitted.level == start { String::new() } else { format!(", CODEBASE réduit à la {}", fitted.level.label()) };
+        format!(
+            "⚠️ Prompt système trop long (~{} tokens pour {} disponibles{}) : la requête risque d'être refusée. Essayez /context diff ou /clear.",
+            fitted.tokens, budget, reduced
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prompt_is_reduced_until_it_fits() {
+        assert_eq!(count_tokens(""), 0);
+        assert_eq!(count_tokens("hello world"), 2);
+        assert_eq!(count_tokens("fn main() {}"), 4);
+        assert_eq!(count_tokens("tiktoken is great!"), 6);
+
+        let sections = |level: ContextLevel| match level {
+            ContextLevel::Full => "x ".repeat(500),
+            ContextLevel::RepoMap => "x ".repeat(50),
+            ContextLevel::FileList => "x ".repeat(5),
+        };
+        let fitted = fit(ContextLevel::Full, 100, sections);
+        assert_eq!((fitted.level, fitted.tokens, fitted.fits), (ContextLevel::RepoMap, count_tokens(&"x ".repeat(50)), true));
+        assert!(downgrade_warning(ContextLevel::Full, &fitted, 100).unwrap().contains("carte du dépôt"));
+        assert!(downgrade_warning(ContextLevel::RepoMap, &fitted, 100).is_none());
+
+        let fitted = fit(ContextLevel::Full, 1, sections);
+        assert_eq!((fitted.level, fitted.fits), (ContextLevel::FileList, false));
+        assert!(downgrade_warning(ContextLevel::Full, &fitted, 1).unwrap().contains("réduit à la liste des fichiers"));
+    }
+}
@@ -0,0 +1,293 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::tools::ToolAccess;
+use crate::wsl;
+
+/// Permission tier of a custom tool
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolPermission {
+    /// Only reads the project (runs without confirmation)
+    #[default]
+    ReadOnly,
+    /// May modify files (runs without confirmation)
+    Write,
+    /// Requires explicit user confirmation, like dangerous bash commands
+    Dangerous,
+}
+
+/// Type of a custom tool parameter
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ParamType {
+    #[default]
+    String,
+    Number,
+    Integer,
+    Boolean,
+}
+
+impl ParamType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ParamType::String => "string",
+            ParamType::Number => "number",
+            ParamType::Integer => "integer",
+            ParamType::Boolean => "boolean",
+        }
+    }
+
+    /// Check a non-empty value against the type
+    pub fn check(&self, name: &str, value: &str) -> Result<(), String> {
+        match self {
+            ParamType::Number if value.trim().parse::<f64>().is_err() => {
+                Err(format!("Parameter '{}' must be a number, got '{}'", name, value))
+            }
+            ParamType::Integer if value.trim().parse::<i64>().is_err() => {
+                Err(format!("Parameter '{}' must be an integer, got '{}'", name, value))
+            }
+            ParamType::Boolean if !matches!(value.trim(), "true" | "false") => {
+                Err(format!("Parameter '{}' must be true or false, got '{}'", name, value))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Parameter declared in a tool definition
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomToolParam {
+    #[serde(default)]
+    pub description: String,
+    #[serde(default, rename = "type")]
+    pub param_type: ParamType,
+    #[serde(default)]
+    pub required: bool,
+    pub default: Option<String>,
+}
+
+/// Tool definition loaded from `.codestral/tools/<name>.toml`
+///
+/// ```toml
+/// name = "run_tests"
+/// description = "Run the test suite"
+/// command = "cargo test {{filter}}"
+/// permission = "read_only"
+///
+/// [params.filter]
+/// description = "Optional test name filter"
+/// default = ""
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomTool {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Shell command template, `{{param}}` placeholders are replaced by quoted values
+    pub command: String,
+    #[serde(default)]
+    pub permission: ToolPermission,
+    #[serde(default)]
+    pub params: BTreeMap<String, CustomToolParam>,
+}
+
+impl CustomTool {
+    /// Check parameters against the schema and return the values to substitute
+    pub fn resolve_params(&self, given: &HashMap<String, String>) -> Result<HashMap<String, String>, String> {
+        let mut values = HashMap::new();
+
+        for (name, param) in &self.params {
+            let value = match given.get(name) {
+                Some(v) => v.clone(),
+                None => match &param.default {
+                    Some(d) => d.clone(),
+                    None if param.required => {
+                        return Err(format!("Missing required parameter '{}' for tool {}", name, self.name));
+                    }
+                    None => String::new(),
+                },
+            };
+
+            if !value.is_empty() {
+                param.param_type.check(name, &value)?;
+            }
+
+            values.insert(name.clone(), value);
+        }
+
+        Ok(values)
+    }
+
+    /// Render the command template with shell-quoted parameter values
+    pub fn render_command(&self, values: &HashMap<String, String>) -> String {
+        let mut command = self.command.clone();
+        for (name, value) in values {
+            let quoted = if value.is_empty() { String::new() } else { shell_quote(value) };
+            command = command.replace(&format!("{{{{{}}}}}", name), &quoted);
+        }
+        command
+    }
+
+    /// Run the rendered command in the project directory
+    pub fn run(&self, command: &str, project_root: &Path) -> (bool, String) {
+        match wsl::shell_command(command, project_root).output() {
+            Ok(out) => {
+                let stdout = String::from_utf8_lossy(&out.stdout);
+                let stderr = String::from_utf8_lossy(&out.stderr);
+                let combined = if stderr.is_empty() {
+                    stdout.to_string()
+                } else if stdout.is_empty() {
+                    format!("STDERR:\n{}", stderr)
+                } else {
+                    format!("{}\nSTDERR:\n{}", stdout, stderr)
+                };
+                (out.status.success(), combined)
+            }
+            Err(e) => (false, format!("Error executing command: {}", e)),
+        }
+    }
+}
+
+/// Wrap a value in single quotes for safe use in a shell command
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// Custom tools registered for a project
+#[derive(Debug, Default)]
+pub struct CustomTools {
+    tools: Vec<CustomTool>,
+}
+
+impl CustomTools {
+    /// Load all `.codestral/tools/*.toml` definitions, returning load errors alongside
+    pub fn load(project_root: &Path, reserved_names: &[&str]) -> (Self, Vec<String>) {
+        let mut tools = Vec::new();
+        let mut errors = Vec::new();
+
+        let dir = project_root.join(".codestral").join("tools");
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            return (Self { tools }, errors);
+        };
+
+        let mut paths: Vec<_> = entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.extension().map(|e| e == "toml").unwrap_or(false))
+            .collect();
+        paths.sort();
+
+        for path in paths {
+            let parsed = std::fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|content| toml::from_str::<CustomTool>(&content).map_err(|e| e.to_string()));
+
+            match parsed {
+                Ok(tool) if reserved_names.contains(&tool.name.as_str()) || tool.name.starts_with("mcp_") => {
+                    errors.push(format!("{}: le nom '{}' est réservé", path.display(), tool.name));
+                }
+                Ok(tool) if tools.iter().any(|t: &CustomTool| t.name == tool.name) => {
+                    errors.push(format!("{}: outil '{}' déjà défini", path.display(), tool.name));
+                }
+                Ok(tool) => tools.push(tool),
+                Err(e) => errors.push(format!("{}: {}", path.display(), e)),
+            }
+        }
+
+        (Self { tools }, errors)
+    }
+
+    /// Register a tool unless the project already defines one with that name
+    pub fn add(&mut self, tool: CustomTool) -> bool {
+        if self.get(&tool.name).is_some() {
+            return false;
+        }
+        self.tools.push(tool);
+        true
+    }
+
+    pub fn get(&self, name: &str) -> Option<&CustomTool> {
+        self.tools.iter().find(|t| t.name == name)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tools.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.tools.len()
+    }
+
+    /// Generate tools documentation for system prompt, limited to the tools the mode allows
+    pub fn get_tools_documentation(&self, access: ToolAccess) -> String {
+        let tools: Vec<&CustomTool> = self.tools.iter()
+            .filter(|t| access.allows(t.permission != ToolPermission::ReadOnly, true))
+            .collect();
+        if tools.is_empty() {
+            return String::new();
+        }
+
+        let mut doc = String::from("\n## Project Tools\n\n");
+
+        for tool in tools {
+            doc.push_str(&format!("### {}\n", tool.name));
+            if !tool.description.is_empty() {
+                doc.push_str(&format!("{}\n", tool.description));
+            }
+            if tool.permission == ToolPermission::Dangerous {
+                doc.push_str("(requires user confirmation)\n");
+            }
+            for (name, param) in &tool.params {
+                let required = if param.required { "required" } else { "optional" };
+                doc.push_str(&format!("- `{}` ({:?}, {}): {}\n", name, param.param_type, required, param.description));
+            }
+            doc.push_str(&format!("```xml\n<tool_call>\n<name>{}</name>\n<params>\n", tool.name));
+            for name in tool.params.keys() {
+                doc.push_str(&format!("<{}>(value)</{}>\n", name, name));
+            }
+            doc.push_str("</params>\n</tool_call>\n```\n\n");
+        }
+
+        doc
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tool() -> CustomTool {
+        toml::from_str(r#"
+            name = "run_tests"
+            description = "Run tests"
+            command = "cargo test {{filter}} -- --test-threads {{threads}}"
+
+            [params.filter]
+            default = ""
+
+            [params.threads]
+            type = "number"
+            required = true
+        "#).unwrap()
+    }
+
+    #[test]
+    fn test_render_command_quotes_values() {
+        let tool = tool();
+        let given = HashMap::from([
+            ("filter".to_string(), "it's".to_string()),
+            ("threads".to_string(), "2".to_string()),
+        ]);
+        let values = tool.resolve_params(&given).unwrap();
+        assert_eq!(tool.render_command(&values), r#"cargo test 'it'\''s' -- --test-threads '2'"#);
+    }
+
+    #[test]
+    fn test_resolve_params_validates_schema() {
+        let tool = tool();
+        assert!(tool.resolve_params(&HashMap::new()).is_err());
+        let given = HashMap::from([("threads".to_string(), "many".to_string())]);
+        assert!(tool.resolve_params(&given).is_err());
+    }
+}
@@ -0,0 +1,1515 @@
+use similar::{ChangeTag, TextDiff};
+use colored::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::io::{self, IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use crate::unified_diff::{self, PatchKind};
+
+/// Represents a file modification
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileChange {
+    pub path: String,
+    pub original: String,
+    pub modified: String,
+    pub description: String,
+}
+
+impl FileChange {
+    /// Generate a colored unified diff
+    pub fn display_diff(&self) -> String {
+        let diff = TextDiff::from_lines(&self.original, &self.modified);
+        let mut output = String::new();
+        
+        output.push_str(&format!("\n{}\n", "─".repeat(60).dimmed()));
+        output.push_str(&format!("{} {}\n", "📄".to_string(), self.path.bold()));
+        if !self.description.is_empty() {
+            output.push_str(&format!("   {}\n", self.description.dimmed()));
+        }
+        output.push_str(&format!("{}\n", "─".repeat(60).dimmed()));
+
+        for change in diff.iter_all_changes() {
+            let sign = match change.tag() {
+                ChangeTag::Delete => format!("{}", format!("-{}", change).red()),
+                ChangeTag::Insert => format!("{}", format!("+{}", change).green()),
+                ChangeTag::Equal => format!(" {}", change),
+            };
+            output.push_str(&sign);
+        }
+
+        output
+    }
+
+    /// Generate a plain (uncolored) unified diff, suitable for logs and files
+    pub fn unified_diff(&self) -> String {
+        TextDiff::from_lines(&self.original, &self.modified)
+            .unified_diff()
+            .header(&self.path, &self.path)
+            .to_string()
+    }
+
+    /// Apply the change to the filesystem
+    pub fn apply(&self) -> Result<(), String> {
+        fs::write(&self.path, &self.modified)
+            .map_err(|e| format!("Failed to write {}: {}", self.path, e))
+    }
+
+    /// Version of the file in the git index when the file on disk has uncommitted edits;
+    /// None for a clean or untracked file, or outside a git repository
+    pub fn local_base(&self) -> Option<String> {
+        let path = Path::new(&self.path);
+        let dir = path.parent()?;
+        let name = path.file_name()?.to_str()?;
+        let status = git(dir, &["status", "--porcelain", "--", name]).ok()?;
+        if status.trim().is_empty() || status.starts_with("??") {
+            return None;
+        }
+        let base = git(dir, &["show", &format!(":./{}", name)]).ok()?;
+        let current = fs::read_to_string(path).ok()?;
+        (base != current).then_some(base)
+    }
+
+    /// Merge the AI version into the local edits (base: `local_base`) and open the result,
+    /// with diff3 conflict markers showing my version, the base and the AI version, so the
+    /// user finishes the merge by hand. Returns the resulting change, or None if the file
+    /// was left untouched.
+    pub fn merge_in_editor(&self, base: &str) -> Result<Option<FileChange>, String> {
+        let current = fs::read_to_string(&self.path)
+            .map_err(|e| format!("Failed to read {}: {}", self.path, e))?;
+        let (merged, conflicts) = three_way_merge(&current, base, &self.modified)?;
+
+        let file_name = Path::new(&self.path)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| "file".to_string());
+        let work_copy = std::env::temp_dir().join(format!("companion-{}-{}", uuid::Uuid::new_v4(), file_name));
+        fs::write(&work_copy, &merged)
+            .map_err(|e| format!("Failed to write {}: {}", work_copy.display(), e))?;
+        if conflicts > 0 {
+            println!("{}", format!("   {} conflit(s): gardez la bonne version entre <<<<<<< et >>>>>>>, puis enregistrez et quittez.", conflicts).yellow());
+        }
+        let result = open_in_editor(&[&work_copy])
+            .and_then(|_| fs::read_to_string(&work_copy).map_err(|e| e.to_string()));
+        let _ = fs::remove_file(&work_copy);
+        let edited = result?;
+
+        if edited.lines().any(|l| l.starts_with("<<<<<<< ") || l.starts_with(">>>>>>> ")) {
+            return Err(format!("Conflict markers left in {}: file not written", self.path));
+        }
+        if edited == current {
+            return Ok(None);
+        }
+        Ok(Some(FileChange {
+            path: self.path.clone(),
+            original: current,
+            modified: edited,
+            description: "Fusion à trois voies".to_string(),
+        }))
+    }
+
+    /// Change to write without prompting: this one while the file is as the AI saw it, else the
+    /// AI version merged into the edits made since (base: `original`, which already holds any
+    /// older uncommitted edits). Fails when they conflict, rather than overwriting them
+    pub fn keeping_local_edits(&self) -> Result<FileChange, String> {
+        let current = fs::read_to_string(&self.path)
+            .map_err(|e| format!("Failed to read {}: {}", self.path, e))?;
+        if current == self.original {
+            return Ok(self.clone());
+        }
+        match three_way_merge(&current, &self.original, &self.modified)? {
+            (merged, 0) => Ok(FileChange {
+                path: self.path.clone(),
+                original: current,
+                modified: merged,
+                description: "Fusion avec les modifications locales".to_string(),
+            }),
+            (_, conflicts) => Err(format!(
+                "{} has uncommitted edits in conflict with the AI version ({} conflict(s)): file skipped",
+                self.path, conflicts
+            )),
+        }
+    }
+
+    /// Hunks of the change, with three lines of context, for a review hunk by hunk
+    pub fn hunks(&self) -> Vec<Hunk> {
+        let diff = TextDiff::from_lines(&self.original, &self.modified);
+        diff.grouped_ops(HUNK_CONTEXT)
+            .iter()
+            .filter(|group| !group.is_empty())
+            .map(|group| {
+                let (first, last) = (&group[0], &group[group.len() - 1]);
+                let old = first.old_range().start..last.old_range().end;
+                let new = first.new_range().start..last.new_range().end;
+                let lines = group.iter()
+                    .flat_map(|op| diff.iter_changes(op))
+                    .map(|change| {
+                        let sign = match change.tag() {
+                            ChangeTag::Delete => '-',
+                            ChangeTag::Insert => '+',
+                            ChangeTag::Equal => ' ',
+                        };
+                        (sign, change.value().trim_end_matches(['\n', '\r']).to_string())
+                    })
+                    .collect();
+                Hunk {
+                    header: format!("@@ -{},{} +{},{} @@", old.start + 1, old.len(), new.start + 1, new.len()),
+                    lines,
+                }
+            })
+            .collect()
+    }
+
+    /// The change limited to the accepted hunks (`accepted[i]` for the i-th of `hunks`):
+    /// rejected hunks keep the original lines
+    pub fn with_hunks(&self, accepted: &[bool]) -> FileChange {
+        let diff = TextDiff::from_lines(&self.original, &self.modified);
+        let (old, new) = (diff.old_slices(), diff.new_slices());
+        let mut modified = String::new();
+        let mut cursor = 0;
+        for (i, group) in diff.grouped_ops(HUNK_CONTEXT).iter().filter(|group| !group.is_empty()).enumerate() {
+            let (first, last) = (&group[0], &group[group.len() - 1]);
+            let (start, end) = (first.old_range().start, last.old_range().end);
+            modified.extend(old[cursor..start].iter().copied());
+            if accepted.get(i).copied().unwrap_or(false) {
+                modified.extend(new[first.new_range().start..last.new_range().end].iter().copied());
+            } else {
+                modified.extend(old[start..end].iter().copied());
+            }
+            cursor = end;
+        }
+        modified.extend(old[cursor..].iter().copied());
+        FileChange { modified, ..self.clone() }
+    }
+}
+
+const HUNK_CONTEXT: usize = 3;
+
+/// One hunk of a `FileChange`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hunk {
+    /// Line ranges, e.g. "@@ -3,7 +3,8 @@"
+    pub header: String,
+    /// Lines without their ending, tagged ' ' (context), '-' (removed) or '+' (added)
+    pub lines: Vec<(char, String)>,
+}
+
+fn git(dir: &Path, args: &[&str]) -> Result<String, String> {
+    let output = std::process::Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .map_err(|e| format!("Cannot run git: {}", e))?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// `git merge-file --diff3` of the AI version into mine: the merged text and its number of conflicts
+pub fn three_way_merge(mine: &str, base: &str, theirs: &str) -> Result<(String, usize), String> {
+    let id = uuid::Uuid::new_v4();
+    let dir = std::env::temp_dir();
+    let files: Vec<PathBuf> = ["mine", "base", "ai"].iter()
+        .map(|label| dir.join(format!("companion-{}-{}", id, label)))
+        .collect();
+    let mut written = Ok(());
+    for (file, content) in files.iter().zip([mine, base, theirs]) {
+        written = written.and_then(|_| fs::write(file, content).map_err(|e| format!("Failed to write {}: {}", file.display(), e)));
+    }
+    let output = written.and_then(|_| std::process::Command::new("git")
+        .args(["merge-file", "-p", "--diff3", "-L", "mes modifications", "-L", "base (index git)", "-L", "IA"])
+        .args(&files)
+        .output()
+        .map_err(|e| format!("Cannot run git: {}", e)));
+    for file in &files {
+        let _ = fs::remove_file(file);
+    }
+    let output = output?;
+    // Exit code: the number of conflicts, negative on error
+    match output.status.code() {
+        Some(conflicts) if (0..128).contains(&conflicts) => {
+            Ok((String::from_utf8_lossy(&output.stdout).to_string(), conflicts as usize))
+        }
+        _ => Err(format!("git merge-file failed: {}", String::from_utf8_lossy(&output.stderr).trim())),
+    }
+}
+
+/// Ask before writing a modification (`prompt`, None when already approved). When the file has
+/// uncommitted edits, warn and offer a three-way merge instead of overwriting them.
+/// Returns the change to write, None if skipped
+pub fn confirm_change(change: &FileChange, prompt: Option<&str>) -> Option<FileChange> {
+    let Some(base) = change.local_base() else {
+        return prompt.is_none_or(confirm).then(|| change.clone());
+    };
+    println!("{}", format!("⚠️  {} contient des modifications locales non commitées", change.path).yellow().bold());
+    // Unattended (--yes, or no terminal to run an editor in): merge without the editor, and
+    // leave the file alone on conflict
+    if assume_yes() || !io::stdin().is_terminal() {
+        return match change.keeping_local_edits() {
+            Ok(merged) => Some(merged),
+            Err(e) => {
+                println!("  {} {}", "✗".red(), e);
+                None
+            }
+        };
+    }
+    if confirm("Fusionner à trois voies dans l'éditeur (mes modifications, IA, base de l'index git)?") {
+        return match change.merge_in_editor(&base) {
+            Ok(merged) => merged,
+            Err(e) => {
+                println!("  {} {}", "✗".red(), e);
+                None
+            }
+        };
+    }
+    confirm("Écraser vos modifications avec la version de l'IA?").then(|| change.clone())
+}
+
+/// Represents a new file to create
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewFile {
+    pub path: String,
+    pub content: String,
+    pub description: String,
+}
+
+impl NewFile {
+    pub fn display(&self) -> String {
+        let mut output = String::new();
+        
+        output.push_str(&format!("\n{}\n", "─".repeat(60).dimmed()));
+        output.push_str(&format!("📄 {} {}\n", "[NEW]".green().bold(), self.path.bold()));
+        if !self.description.is_empty() {
+            output.push_str(&format!("   {}\n", self.description.dimmed()));
+        }
+        output.push_str(&format!("{}\n", "─".repeat(60).dimmed()));
+        
+        for line in self.content.lines().take(20) {
+            output.push_str(&format!("{}\n", format!("+{}", line).green()));
+        }
+        
+        if self.content.lines().count() > 20 {
+            output.push_str(&format!("{}\n", "... (truncated)".dimmed()));
+        }
+
+        output
+    }
+
+    /// Generate a plain unified diff against an empty file
+    pub fn unified_diff(&self) -> String {
+        TextDiff::from_lines("", &self.content)
+            .unified_diff()
+            .header("/dev/null", &self.path)
+            .to_string()
+    }
+
+    pub fn apply(&self) -> Result<(), String> {
+        // Create parent directories if needed
+        if let Some(parent) = Path::new(&self.path).parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create directories: {}", e))?;
+        }
+        fs::write(&self.path, &self.content)
+            .map_err(|e| format!("Failed to write {}: {}", self.path, e))
+    }
+}
+
+/// Represents a file to delete
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileDeletion {
+    pub path: String,
+    /// Content being removed, shown before confirmation
+    pub content: String,
+    pub description: String,
+}
+
+impl FileDeletion {
+    pub fn display(&self) -> String {
+        let mut output = String::new();
+        
+        output.push_str(&format!("\n{}\n", "─".repeat(60).dimmed()));
+        output.push_str(&format!("📄 {} {}\n", "[DELETE]".red().bold(), self.path.bold()));
+        if !self.description.is_empty() {
+            output.push_str(&format!("   {}\n", self.description.dimmed()));
+        }
+        output.push_str(&format!("{}\n", "─".repeat(60).dimmed()));
+        
+        for line in self.content.lines().take(20) {
+            output.push_str(&format!("{}\n", format!("-{}", line).red()));
+        }
+        
+        if self.content.lines().count() > 20 {
+            output.push_str(&format!("{}\n", "... (truncated)".dimmed()));
+        }
+
+        output
+    }
+
+    /// Generate a plain unified diff against an empty file
+    pub fn unified_diff(&self) -> String {
+        TextDiff::from_lines(self.content.as_str(), "")
+            .unified_diff()
+            .header(&self.path, "/dev/null")
+            .to_string()
+    }
+
+    /// Move the file to `.codestral/trash/<timestamp>/` instead of removing it, and return where it went
+    pub fn trash(&self, base_path: &Path) -> Result<PathBuf, String> {
+        let path = Path::new(&self.path);
+        let relative = path.strip_prefix(base_path)
+            .unwrap_or_else(|_| Path::new(path.file_name().unwrap_or_default()));
+        let target = crate::project_dir::ensure(base_path)?
+            .join("trash")
+            .join(chrono::Local::now().format("%Y%m%d-%H%M%S").to_string())
+            .join(relative);
+
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create trash directory: {}", e))?;
+        }
+        // Across filesystems (a symlinked .codestral) rename fails: copy, then remove
+        if fs::rename(path, &target).is_err() {
+            fs::copy(path, &target)
+                .map_err(|e| format!("Failed to move {} to the trash: {}", self.path, e))?;
+            fs::remove_file(path)
+                .map_err(|e| format!("Failed to delete {}: {}", self.path, e))?;
+        }
+        Ok(target)
+    }
+}
+
+/// Represents a file to move or rename
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileRename {
+    pub from: String,
+    pub to: String,
+    /// Content of the moved file, unchanged by the move
+    pub content: String,
+    pub description: String,
+}
+
+impl FileRename {
+    pub fn display(&self) -> String {
+        let mut output = String::new();
+
+        output.push_str(&format!("\n{}\n", "─".repeat(60).dimmed()));
+        output.push_str(&format!("📄 {} {} → {}\n", "[RENAME]".cyan().bold(), self.from.bold(), self.to.bold()));
+        if !self.description.is_empty() {
+            output.push_str(&format!("   {}\n", self.description.dimmed()));
+        }
+        output.push_str(&format!("{}\n", "─".repeat(60).dimmed()));
+
+        output
+    }
+
+    /// Rename header in the git diff format, with no hunk: the content does not change
+    pub fn unified_diff(&self) -> String {
+        format!("rename from {}\nrename to {}\n", self.from, self.to)
+    }
+
+    /// Move the file, with `git mv` when git tracks it so the history follows the new path
+    pub fn apply(&self, base_path: &Path) -> Result<(), String> {
+        let (from, to) = (Path::new(&self.from), Path::new(&self.to));
+        if to.exists() {
+            return Err(format!("{} already exists", self.to));
+        }
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create directories: {}", e))?;
+        }
+        let tracked = git(base_path, &["ls-files", "--error-unmatch", "--", &self.from]).is_ok();
+        if tracked && git(base_path, &["mv", "--", &self.from, &self.to]).is_ok() {
+            return Ok(());
+        }
+        fs::rename(from, to)
+            .map_err(|e| format!("Failed to move {} to {}: {}", self.from, self.to, e))
+    }
+
+    /// Journal the move as a deletion and a creation, so a rollback moves the file back
+    pub fn record(&self, base_path: &Path, journal: &mut crate::journal::ChangeJournal) {
+        journal.record(base_path, Path::new(&self.from), Some(self.content.clone()), None);
+        journal.record(base_path, Path::new(&self.to), None, Some(self.content.clone()));
+    }
+}
+
+/// Number of follow-up requests sent when no patch of a response could be applied
+pub const MAX_PATCH_RETRIES: usize = 2;
+
+/// A `<file>` block whose ORIGINAL section does not match the file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PatchFailure {
+    /// Path as written by the model
+    pub path: String,
+    pub full_path: String,
+    /// ORIGINAL section sent by the model
+    pub expected: String,
+    /// MODIFIED section sent by the model
+    pub proposed: String,
+    /// Closest excerpt of the actual file (with line numbers)
+    pub excerpt: String,
+}
+
+impl PatchFailure {
+    fn new(path: &str, full_path: &Path, expected: &str, proposed: &str, current: Option<&str>) -> Self {
+        let excerpt = match current {
+            Some(content) => file_excerpt(content, expected),
+            None => "(fichier introuvable)".to_string(),
+        };
+        Self {
+            path: path.to_string(),
+            full_path: full_path.to_string_lossy().to_string(),
+            expected: expected.to_string(),
+            proposed: proposed.to_string(),
+            excerpt,
+        }
+    }
+
+    /// Open a copy of the file next to the proposed change so the user can place it by hand.
+    /// Returns the resulting change, or None if the file was left untouched.
+    pub fn resolve_in_editor(&self) -> Result<Option<FileChange>, String> {
+        let current = fs::read_to_string(&self.full_path)
+            .map_err(|e| format!("Failed to read {}: {}", self.full_path, e))?;
+
+        let file_name = Path::new(&self.full_path)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| "file".to_string());
+        let id = uuid::Uuid::new_v4();
+        let work_copy = std::env::temp_dir().join(format!("companion-{}-{}", id, file_name));
+        let proposal = std::env::temp_dir().join(format!("companion-{}-proposition.txt", id));
+
+        fs::write(&work_copy, &current)
+            .map_err(|e| format!("Failed to write {}: {}", work_copy.display(), e))?;
+        fs::write(&proposal, format!(
+            "# Changement proposé pour {}\n\
+             # Le bloc ORIGINAL ne correspond pas au fichier: placez le changement à la main\n\
+             # dans l'autre fenêtre, puis enregistrez et quittez.\n\n\
+             ----- ORIGINAL (attendu) -----\n{}\n\n----- MODIFIED (proposé) -----\n{}\n",
+            self.path, self.expected, self.proposed
+        )).map_err(|e| format!("Failed to write {}: {}", proposal.display(), e))?;
+
+        let result = open_in_editor(&[&work_copy, &proposal])
+            .and_then(|_| fs::read_to_string(&work_copy).map_err(|e| e.to_string()));
+        let _ = fs::remove_file(&work_copy);
+        let _ = fs::remove_file(&proposal);
+        let edited = result?;
+
+        if edited == current {
+            return Ok(None);
+        }
+        Ok(Some(FileChange {
+            path: self.full_path.clone(),
+            original: current,
+            modified: edited,
+            description: "Résolution manuelle".to_string(),
+        }))
+    }
+}
+
+/// Open files in $VISUAL/$EDITOR (or the first available of vim, nvim, nano, vi), side by side when supported
+pub fn open_in_editor(files: &[&Path]) -> Result<(), String> {
+    let configured = std::env::var("VISUAL").or_else(|_| std::env::var("EDITOR")).ok();
+    let candidates: Vec<String> = configured.into_iter()
+        .chain(["vim", "nvim", "nano", "vi"].iter().map(|e| e.to_string()))
+        .collect();
+
+    for editor in candidates {
+        let mut parts = editor.split_whitespace();
+        let Some(program) = parts.next() else {
+            continue;
+        };
+        let mut command = std::process::Command::new(program);
+        command.args(parts);
+        if program.ends_with("vim") {
+            command.arg("-O");
+        }
+        if let Ok(status) = command.args(files).status() {
+            return if status.success() {
+                Ok(())
+            } else {
+                Err(format!("{} exited with {}", program, status))
+            };
+        }
+    }
+
+    Err("No editor found (set $EDITOR)".to_string())
+}
+
+/// Lines around the first line of `expected`, or the start of the file if it is not found
+fn file_excerpt(content: &str, expected: &str) -> String {
+    let lines: Vec<&str> = content.lines().collect();
+    let first = expected.lines().map(str::trim).find(|l| !l.is_empty()).unwrap_or("");
+    let span = expected.lines().count().max(1);
+
+    let (start, end) = match lines.iter().position(|l| !first.is_empty() && l.trim() == first) {
+        Some(i) => (i.saturating_sub(3), (i + span + 3).min(lines.len())),
+        None => (0, lines.len().min(30)),
+    };
+
+    lines[start..end].iter()
+        .enumerate()
+        .map(|(i, l)| format!("{:>4} | {}", start + i + 1, l))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Represents all changes from an agent response
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChangeSet {
+    pub plan: Vec<String>,
+    pub modifications: Vec<FileChange>,
+    pub new_files: Vec<NewFile>,
+    pub deletions: Vec<FileDeletion>,
+    pub renames: Vec<FileRename>,
+    pub patch_failures: Vec<PatchFailure>,
+    /// Failed hunks placed by hand in the editor (already written to disk)
+    pub resolved: Vec<FileChange>,
+}
+
+impl ChangeSet {
+    pub fn is_empty(&self) -> bool {
+        self.modifications.is_empty() && self.new_files.is_empty() && self.deletions.is_empty()
+            && self.renames.is_empty()
+    }
+
+    pub fn display_plan(&self) {
+        if !self.plan.is_empty() {
+            println!("\n{}", "📋 PLAN D'ACTION".bold().cyan());
+            println!("{}", "─".repeat(40).dimmed());
+            for (i, step) in self.plan.iter().enumerate() {
+                println!("  {}. {}", (i + 1).to_string().bold(), step);
+            }
+            println!();
+        }
+    }
+
+    pub fn display_all_changes(&self) {
+        for rename in &self.renames {
+            println!("{}", rename.display());
+        }
+        for change in &self.modifications {
+            println!("{}", change.display_diff());
+        }
+        for new_file in &self.new_files {
+            println!("{}", new_file.display());
+        }
+        for deletion in &self.deletions {
+            println!("{}", deletion.display());
+        }
+    }
+
+    pub fn display_patch_failures(&self) {
+        for failure in &self.patch_failures {
+            println!("{} {} {}", "⚠️".yellow(), failure.path.bold(), "(ORIGINAL introuvable, patch ignoré)".yellow());
+        }
+    }
+
+    /// Offer to place each failed hunk by hand; resolved changes are written and moved to `resolved`
+    pub fn resolve_failures_interactive(&mut self) {
+        for failure in std::mem::take(&mut self.patch_failures) {
+            println!("\n{} {}", "⚠️  Patch non applicable:".yellow().bold(), failure.path.bold());
+            println!("{}", failure.excerpt.dimmed());
+            // Placing a hunk by hand needs someone at the keyboard, even with --yes
+            if assume_yes() || !confirm("Placer ce changement manuellement dans l'éditeur?") {
+                self.patch_failures.push(failure);
+                continue;
+            }
+            match failure.resolve_in_editor().and_then(|change| match change {
+                Some(change) => change.apply().map(|_| Some(change)),
+                None => Ok(None),
+            }) {
+                Ok(Some(change)) => {
+                    println!("  {} {}", "✓ Appliqué".green(), change.path);
+                    self.resolved.push(change);
+                }
+                Ok(None) => {
+                    println!("  {}", "✗ Fichier inchangé".yellow());
+                    self.patch_failures.push(failure);
+                }
+                Err(e) => {
+                    println!("  {} {}", "✗".red(), e);
+                    self.patch_failures.push(failure);
+                }
+            }
+        }
+    }
+
+    /// Follow-up asking for corrected patches, when none of the `<file>` blocks could be applied
+    pub fn patch_retry_prompt(&self) -> Option<String> {
+        if self.patch_failures.is_empty() || !self.modifications.is_empty() {
+            return None;
+        }
+
+        let mut prompt = String::from(
+            "Aucune modification n'a pu être appliquée: le bloc ORIGINAL (ou le contexte du diff) ne correspond pas exactement au contenu du fichier.\n\
+             Renvoie un patch corrigé en recopiant EXACTEMENT le code existant dans ORIGINAL (ou utilise <replace_file>).\n"
+        );
+        for failure in &self.patch_failures {
+            prompt.push_str(&format!(
+                "\n### {}\nORIGINAL attendu:\n```\n{}\n```\nExtrait réel du fichier:\n```\n{}\n```\n",
+                failure.path, failure.expected, failure.excerpt
+            ));
+        }
+        Some(prompt)
+    }
+
+    /// Files, lines and languages touched, resolved hunks included
+    pub fn stats(&self) -> DiffStats {
+        let mut stats = DiffStats::default();
+        let mut paths: Vec<&str> = Vec::new();
+        let mut languages: Vec<(&'static str, usize)> = Vec::new();
+        let mut count = |language: &'static str, added: usize, removed: usize, stats: &mut DiffStats| {
+            stats.added += added;
+            stats.removed += removed;
+            match languages.iter_mut().find(|(name, _)| *name == language) {
+                Some((_, lines)) => *lines += added + removed,
+                None => languages.push((language, added + removed)),
+            }
+        };
+
+        for change in self.modifications.iter().chain(&self.resolved) {
+            let diff = TextDiff::from_lines(&change.original, &change.modified);
+            let added = diff.iter_all_changes().filter(|c| c.tag() == ChangeTag::Insert).count();
+            let removed = diff.iter_all_changes().filter(|c| c.tag() == ChangeTag::Delete).count();
+            paths.push(&change.path);
+            count(language_name(&change.path).unwrap_or(""), added, removed, &mut stats);
+        }
+        for new_file in &self.new_files {
+            paths.push(&new_file.path);
+            count(language_name(&new_file.path).unwrap_or(""), new_file.content.lines().count(), 0, &mut stats);
+        }
+        for deletion in &self.deletions {
+            paths.push(&deletion.path);
+            count(language_name(&deletion.path).unwrap_or(""), 0, deletion.content.lines().count(), &mut stats);
+        }
+        // A move changes no line
+        for rename in &self.renames {
+            paths.push(&rename.to);
+        }
+
+        paths.sort_unstable();
+        paths.dedup();
+        stats.files = paths.len();
+        // Most changed language first
+        languages.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        stats.languages = languages.into_iter()
+            .filter(|(name, _)| !name.is_empty())
+            .map(|(name, _)| name.to_string())
+            .collect();
+        stats
+    }
+
+    pub fn summary(&self) -> String {
+        let mut summary = format!(
+            "{} modifications, {} nouveaux fichiers, {} suppressions",
+            self.modifications.len(),
+            self.new_files.len(),
+            self.deletions.len()
+        );
+        if !self.renames.is_empty() {
+            summary.push_str(&format!(", {} déplacements", self.renames.len()));
+        }
+        summary
+    }
+
+    /// Apply the changes whose path is approved, going on after a failure. A modified file that
+    /// no longer matches the `original` it was diffed against is left alone rather than overwritten,
+    /// and deleted files are moved to the project's trash. Moves go first, approved by their new
+    /// path, since changes to a moved file target that path. What was applied is journaled for rollback
+    pub fn apply_selected(&self, base_path: &Path, approved: impl Fn(&str) -> bool) -> ApplyReport {
+        let mut report = ApplyReport::default();
+        let mut journal = crate::journal::ChangeJournal::new();
+
+        for rename in self.renames.iter().filter(|rename| approved(&rename.to)) {
+            match rename.apply(base_path) {
+                Ok(()) => {
+                    rename.record(base_path, &mut journal);
+                    report.renamed.push((rename.from.clone(), rename.to.clone()));
+                }
+                Err(e) => report.errors.push(e),
+            }
+        }
+
+        for change in self.modifications.iter().filter(|change| approved(&change.path)) {
+            let result = match fs::read_to_string(&change.path) {
+                Ok(current) if current == change.original => change.apply(),
+                Ok(_) => Err(format!("{} changed on disk since the diff was made", change.path)),
+                Err(e) => Err(format!("Failed to read {}: {}", change.path, e)),
+            };
+            match result {
+                Ok(()) => {
+                    journal.record(base_path, Path::new(&change.path), Some(change.original.clone()), Some(change.modified.clone()));
+                    report.applied.push(change.path.clone());
+                }
+                Err(e) => report.errors.push(e),
+            }
+        }
+
+        for new_file in self.new_files.iter().filter(|new_file| approved(&new_file.path)) {
+            let result = if Path::new(&new_file.path).exists() {
+                Err(format!("{} already exists", new_file.path))
+            } else {
+                new_file.apply()
+            };
+            match result {
+                Ok(()) => {
+                    journal.record(base_path, Path::new(&new_file.path), None, Some(new_file.content.clone()));
+                    report.applied.push(new_file.path.clone());
+                }
+                Err(e) => report.errors.push(e),
+            }
+        }
+
+        for deletion in self.deletions.iter().filter(|deletion| approved(&deletion.path)) {
+            match deletion.trash(base_path) {
+                Ok(trashed) => {
+                    journal.record(base_path, Path::new(&deletion.path), Some(deletion.content.clone()), None);
+                    report.deleted.push(deletion.path.clone());
+                    report.trashed.push(trashed.to_string_lossy().to_string());
+                }
+                Err(e) => report.errors.push(e),
+            }
+        }
+
+        if let Err(e) = journal.save(base_path) {
+            report.errors.push(e);
+        }
+        report
+    }
+}
+
+/// Size of a change set, shown before its details and in the run reports
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct DiffStats {
+    pub files: usize,
+    pub added: usize,
+    pub removed: usize,
+    /// Languages of the changed files, the most changed first
+    pub languages: Vec<String>,
+}
+
+impl DiffStats {
+    /// e.g. "3 fichiers, +42/−7 lignes (Rust, TypeScript)"
+    pub fn header(&self) -> String {
+        let files = if self.files == 1 { "1 fichier".to_string() } else { format!("{} fichiers", self.files) };
+        let mut header = format!("{}, +{}/−{} lignes", files, self.added, self.removed);
+        if !self.languages.is_empty() {
+            header.push_str(&format!(" ({})", self.languages.join(", ")));
+        }
+        header
+    }
+}
+
+/// Language of a file for the summaries, from its extension
+fn language_name(path: &str) -> Option<&'static str> {
+    let extension = Path::new(path).extension()?.to_str()?.to_lowercase();
+    Some(match extension.as_str() {
+        "rs" => "Rust",
+        "ts" | "tsx" => "TypeScript",
+        "js" | "jsx" | "mjs" | "cjs" => "JavaScript",
+        "py" => "Python",
+        "go" => "Go",
+        "java" => "Java",
+        "kt" => "Kotlin",
+        "swift" => "Swift",
+        "c" | "h" => "C",
+        "cpp" | "cc" | "hpp" => "C++",
+        "cs" => "C#",
+        "rb" => "Ruby",
+        "php" => "PHP",
+        "vue" => "Vue",
+        "svelte" => "Svelte",
+        "html" => "HTML",
+        "css" | "scss" | "sass" | "less" => "CSS",
+        "json" => "JSON",
+        "yaml" | "yml" => "YAML",
+        "toml" => "TOML",
+        "md" => "Markdown",
+        "sql" => "SQL",
+        "sh" | "bash" | "zsh" | "fish" => "Shell",
+        _ => return None,
+    })
+}
+
+/// Outcome of `ChangeSet::apply_selected`
+#[derive(Debug, Default, Serialize)]
+pub struct ApplyReport {
+    /// Files modified or created
+    pub applied: Vec<String>,
+    pub deleted: Vec<String>,
+    /// (old path, new path) of the moved files
+    pub renamed: Vec<(String, String)>,
+    /// Where the deleted files were moved, under `.codestral/trash/`
+    pub trashed: Vec<String>,
+    /// One message per change that could not be applied
+    pub errors: Vec<String>,
+    /// "<short hash> <subject>" of the commit of the applied files, with `auto_commit` on
+    pub commit: Option<String>,
+}
+
+impl ApplyReport {
+    /// Paths written, deleted or moved (both ends of a move), e.g. to commit or reindex them
+    pub fn touched(&self) -> Vec<&str> {
+        self.applied.iter()
+            .chain(&self.deleted)
+            .map(String::as_str)
+            .chain(self.renamed.iter().flat_map(|(from, to)| [from.as_str(), to.as_str()]))
+            .collect()
+    }
+}
+
+/// Language of the confirmation prompts: `CODESTRAL_LOCALE`, else `LC_ALL`, `LC_MESSAGES` or `LANG`.
+/// French unless one of them names another language
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Locale {
+    Fr,
+    En,
+}
+
+impl Locale {
+    pub fn current() -> Self {
+        ["CODESTRAL_LOCALE", "LC_ALL", "LC_MESSAGES", "LANG"].iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find(|value| !value.is_empty())
+            .and_then(|value| Self::parse(&value))
+            .unwrap_or(Locale::Fr)
+    }
+
+    /// "fr_FR.UTF-8" is French, "en_US.UTF-8" or "de" is English; "C" and "POSIX" say nothing
+    fn parse(value: &str) -> Option<Self> {
+        let value = value.to_lowercase();
+        if value.starts_with("fr") {
+            Some(Locale::Fr)
+        } else if value == "c" || value.starts_with("c.") || value == "posix" {
+            None
+        } else {
+            Some(Locale::En)
+        }
+    }
+
+    fn yes_words(self) -> &'static [&'static str] {
+        match self {
+            Locale::Fr => &["o", "oui", "y", "yes"],
+            Locale::En => &["y", "yes"],
+        }
+    }
+
+    fn no_words(self) -> &'static [&'static str] {
+        match self {
+            Locale::Fr => &["n", "non", "no"],
+            Locale::En => &["n", "no"],
+        }
+    }
+
+    fn choices(self, default_yes: bool) -> &'static str {
+        match (self, default_yes) {
+            (Locale::Fr, false) => "[o/N]",
+            (Locale::Fr, true) => "[O/n]",
+            (Locale::En, false) => "[y/N]",
+            (Locale::En, true) => "[Y/n]",
+        }
+    }
+}
+
+/// Extra answers taken as yes, comma-separated (e.g. "ja,j,sí")
+pub const CONFIRM_WORDS_ENV: &str = "CODESTRAL_CONFIRM_WORDS";
+
+static ASSUME_YES: AtomicBool = AtomicBool::new(false);
+
+/// Answer yes to every confirmation without reading stdin (global `--yes`)
+pub fn set_assume_yes(yes: bool) {
+    ASSUME_YES.store(yes, Ordering::Relaxed);
+}
+
+pub fn assume_yes() -> bool {
+    ASSUME_YES.load(Ordering::Relaxed)
+}
+
+/// Ask for user confirmation; no unless answered yes
+pub fn confirm(prompt: &str) -> bool {
+    ask(prompt, false)
+}
+
+/// Ask for user confirmation; yes unless answered no
+pub fn confirm_default_yes(prompt: &str) -> bool {
+    ask(prompt, true)
+}
+
+fn ask(prompt: &str, default_yes: bool) -> bool {
+    let locale = Locale::current();
+    print!("{} {} ", prompt.yellow(), locale.choices(default_yes));
+    if assume_yes() {
+        println!("{}", locale.yes_words()[0].dimmed());
+        return true;
+    }
+    io::stdout().flush().unwrap();
+
+    let mut input = String::new();
+    // Closed stdin (pipe, CI): the default answer
+    if io::stdin().read_line(&mut input).unwrap_or(0) == 0 {
+        println!();
+        return default_yes;
+    }
+    let extra = std::env::var(CONFIRM_WORDS_ENV).unwrap_or_default();
+    answer(&input, locale, default_yes, &extra)
+}
+
+/// Meaning of an answer; anything not recognized is a no
+fn answer(input: &str, locale: Locale, default_yes: bool, extra_yes_words: &str) -> bool {
+    let input = input.trim().to_lowercase();
+    if input.is_empty() {
+        return default_yes;
+    }
+    if locale.no_words().contains(&input.as_str()) {
+        return false;
+    }
+    locale.yes_words().contains(&input.as_str())
+        || extra_yes_words.split(',').any(|word| !word.trim().is_empty() && word.trim().to_lowercase() == input)
+}
+
+/// Parse AI response to extract changes
+/// Expected format:
+/// <plan>
+/// 1. Step one
+/// 2. Step two
+/// </plan>
+/// 
+/// <file path="src/main.rs">
+/// <<<<<<< ORIGINAL
+/// old code
+/// =======
+/// new code
+/// >>>>>>> MODIFIED
+/// </file>
+/// 
+/// <new_file path="src/new.rs">
+/// content
+/// </new_file>
+///
+/// <replace_file path="src/lib.rs">
+/// full new content
+/// </replace_file>
+///
+/// <delete_file path="src/old.rs" />
+///
+/// <rename_file from="src/util.rs" to="src/utils/mod.rs" />
+pub fn parse_ai_response(response: &str, base_path: &Path) -> ChangeSet {
+    parse_ai_response_with(response, |path| base_path.join(path))
+}
+
+/// Whether a path of the response stays under its root: not absolute and without `..`
+fn is_inside_root(path: &str) -> bool {
+    Path::new(path).components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+}
+
+/// Like `parse_ai_response`, with a custom mapping of the paths of the response (e.g. workspace roots)
+pub fn parse_ai_response_with(response: &str, resolve: impl Fn(&str) -> PathBuf) -> ChangeSet {
+    let mut changes = ChangeSet::default();
+
+    // Extract plan
+    if let Some(plan_start) = response.find("<plan>") {
+        if let Some(plan_end) = response.find("</plan>") {
+            let plan_content = &response[plan_start + 6..plan_end];
+            for line in plan_content.lines() {
+                let trimmed = line.trim();
+                if !trimmed.is_empty() {
+                    // Remove leading numbers like "1. " or "- "
+                    let cleaned = trimmed
+                        .trim_start_matches(|c: char| c.is_ascii_digit() || c == '.' || c == '-' || c == ' ');
+                    if !cleaned.is_empty() {
+                        changes.plan.push(cleaned.to_string());
+                    }
+                }
+            }
+        }
+    }
+
+    // Extract moves first: changes to a moved file are written against its new path
+    let mut renames: Vec<FileRename> = Vec::new();
+    let rename_pattern = regex::Regex::new(r#"<rename_file\s+from="([^"]+)"\s+to="([^"]+)"\s*/?>"#).unwrap();
+    for cap in rename_pattern.captures_iter(response) {
+        // Both ends stay in the project: no file taken from or moved out of it
+        if !is_inside_root(&cap[1]) || !is_inside_root(&cap[2]) {
+            continue;
+        }
+        let (from, to) = (resolve(&cap[1]), resolve(&cap[2]));
+        if !from.is_file() || to.exists() || renames.iter().any(|r| Path::new(&r.from) == from) {
+            continue;
+        }
+        renames.push(FileRename {
+            content: fs::read_to_string(&from).unwrap_or_default(),
+            from: from.to_string_lossy().to_string(),
+            to: to.to_string_lossy().to_string(),
+            description: String::new(),
+        });
+    }
+    // Where a file is on disk until the moves are applied
+    let on_disk = |full_path: &Path| -> PathBuf {
+        renames.iter()
+            .find(|r| Path::new(&r.to) == full_path)
+            .map(|r| PathBuf::from(&r.from))
+            .unwrap_or_else(|| full_path.to_path_buf())
+    };
+
+    // Extract file modifications
+    let file_pattern = regex::Regex::new(r#"<file\s+path="([^"]+)">"#).unwrap();
+    for cap in file_pattern.captures_iter(response) {
+        let path = &cap[1];
+        let full_path = resolve(path);
+        
+        // Find the content between <file> and </file>
+        let tag_start = cap.get(0).unwrap().end();
+        if let Some(relative_end) = response[tag_start..].find("</file>") {
+            let content = &response[tag_start..tag_start + relative_end];
+            
+            // Parse ORIGINAL/MODIFIED markers
+            if let Some(orig_start) = content.find("<<<<<<< ORIGINAL") {
+                if let Some(sep) = content.find("=======") {
+                    if let Some(mod_end) = content.find(">>>>>>> MODIFIED") {
+                        let original = content[orig_start + 16..sep].trim();
+                        let modified = content[sep + 7..mod_end].trim();
+                        
+                        // Read current file content
+                        let current_content = match fs::read_to_string(on_disk(&full_path)) {
+                            Ok(content) => content,
+                            Err(_) => {
+                                changes.patch_failures.push(PatchFailure::new(path, &full_path, original, modified, None));
+                                continue;
+                            }
+                        };
+                        
+                        if !current_content.contains(original) {
+                            changes.patch_failures.push(PatchFailure::new(path, &full_path, original, modified, Some(&current_content)));
+                            continue;
+                        }
+                        
+                        // Replace the original with modified in current content
+                        let new_content = current_content.replace(original, modified);
+                        
+                        if new_content != current_content {
+                            changes.modifications.push(FileChange {
+                                path: full_path.to_string_lossy().to_string(),
+                                original: current_content,
+                                modified: new_content,
+                                description: String::new(),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // Unified diffs (```diff blocks), which models often send instead of <file> blocks
+    for patch in unified_diff::parse(response) {
+        let full_path = resolve(&patch.path);
+        let path = full_path.to_string_lossy().to_string();
+        match patch.kind {
+            PatchKind::Create => changes.new_files.push(NewFile {
+                path,
+                content: patch.new_content(),
+                description: String::new(),
+            }),
+            PatchKind::Delete => {
+                if full_path.is_file() && !changes.deletions.iter().any(|d| d.path == path) {
+                    changes.deletions.push(FileDeletion {
+                        content: fs::read_to_string(&full_path).unwrap_or_default(),
+                        path,
+                        description: String::new(),
+                    });
+                }
+            }
+            PatchKind::Modify => {
+                // Builds on an earlier change of the same file in the response
+                let earlier = changes.modifications.iter().position(|c| c.path == path);
+                let current = match earlier {
+                    Some(i) => changes.modifications[i].modified.clone(),
+                    None => match fs::read_to_string(on_disk(&full_path)) {
+                        Ok(content) => content,
+                        Err(_) => {
+                            let failure = patch.as_failure();
+                            changes.patch_failures.push(PatchFailure::new(&patch.path, &full_path, &failure.expected, &failure.proposed, None));
+                            continue;
+                        }
+                    },
+                };
+                match patch.apply(&current) {
+                    Ok(patched) if patched == current => {}
+                    Ok(patched) => match earlier {
+                        Some(i) => changes.modifications[i].modified = patched,
+                        None => changes.modifications.push(FileChange {
+                            path,
+                            original: current,
+                            modified: patched,
+                            description: String::new(),
+                        }),
+                    },
+                    Err(failure) => changes.patch_failures.push(
+                        PatchFailure::new(&patch.path, &full_path, &failure.expected, &failure.proposed, Some(&current))
+                    ),
+                }
+            }
+        }
+    }
+
+    // Extract new files
+    let new_file_pattern = regex::Regex::new(r#"<new_file\s+path="([^"]+)">"#).unwrap();
+    for cap in new_file_pattern.captures_iter(response) {
+        let path = &cap[1];
+        let full_path = resolve(path);
+        
+        let tag_start = cap.get(0).unwrap().end();
+        if let Some(relative_end) = response[tag_start..].find("</new_file>") {
+            let mut content = response[tag_start..tag_start + relative_end].trim().to_string();
+            if !content.is_empty() {
+                content.push('\n');
+            }
+            
+            changes.new_files.push(NewFile {
+                path: full_path.to_string_lossy().to_string(),
+                content,
+                description: String::new(),
+            });
+        }
+    }
+
+    // Extract whole-file replacements (no ORIGINAL matching needed)
+    let replace_pattern = regex::Regex::new(r#"<replace_file\s+path="([^"]+)">"#).unwrap();
+    for cap in replace_pattern.captures_iter(response) {
+        let path = &cap[1];
+        let full_path = resolve(path);
+        
+        let tag_start = cap.get(0).unwrap().end();
+        if let Some(relative_end) = response[tag_start..].find("</replace_file>") {
+            let mut content = response[tag_start..tag_start + relative_end].trim().to_string();
+            content.push('\n');
+            
+            if !on_disk(&full_path).exists() {
+                changes.new_files.push(NewFile {
+                    path: full_path.to_string_lossy().to_string(),
+                    content,
+                    description: String::new(),
+                });
+                continue;
+            }
+            
+            let current_content = fs::read_to_string(on_disk(&full_path)).unwrap_or_default();
+            if content != current_content {
+                changes.modifications.push(FileChange {
+                    path: full_path.to_string_lossy().to_string(),
+                    original: current_content,
+                    modified: content,
+                    description: "Réécriture complète du fichier".to_string(),
+                });
+            }
+        }
+    }
+
+    // Extract deletions (only files that actually exist)
+    let delete_pattern = regex::Regex::new(r#"<delete_file\s+path="([^"]+)"\s*/?>"#).unwrap();
+    for cap in delete_pattern.captures_iter(response) {
+        // Never offer to delete a file outside the project
+        if !is_inside_root(&cap[1]) {
+            continue;
+        }
+        let full_path = resolve(&cap[1]);
+        if !full_path.is_file() {
+            continue;
+        }
+        let path = full_path.to_string_lossy().to_string();
+        if changes.deletions.iter().any(|d| d.path == path) {
+            continue;
+        }
+        changes.deletions.push(FileDeletion {
+            path,
+            content: fs::read_to_string(&full_path).unwrap_or_default(),
+            description: String::new(),
+        });
+    }
+
+    changes.renames = renames;
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_delete_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path().join("project");
+        fs::create_dir_all(&base).unwrap();
+        fs::write(base.join("old.rs"), "fn old() {}\n").unwrap();
+        let outside = dir.path().join("outside.rs");
+        fs::write(&outside, "").unwrap();
+
+        let response = format!(r#"<delete_file path="old.rs" />
+<delete_file path="missing.rs"></delete_file>
+<delete_file path="../outside.rs" />
+<delete_file path="{}" />"#, outside.display());
+        let changes = parse_ai_response(&response, &base);
+
+        assert_eq!(changes.deletions.len(), 1);
+        assert_eq!(changes.deletions[0].content, "fn old() {}\n");
+
+        let trashed = changes.deletions[0].trash(&base).unwrap();
+        assert!(!base.join("old.rs").exists());
+        assert!(trashed.starts_with(base.join(".codestral/trash")) && trashed.ends_with("old.rs"));
+        assert_eq!(fs::read_to_string(&trashed).unwrap(), "fn old() {}\n");
+        assert!(outside.exists());
+    }
+
+    #[test]
+    fn test_rename_file_keeps_git_history_and_later_edits() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let run = |args: &[&str]| git(root, args).unwrap();
+        run(&["init", "-q"]);
+        fs::write(root.join("util.rs"), "fn a() {}\n").unwrap();
+        run(&["add", "util.rs"]);
+        run(&["-c", "user.name=t", "-c", "user.email=t@t", "commit", "-qm", "init"]);
+
+        let response = "<rename_file from=\"util.rs\" to=\"../util.rs\" />\n\
+            <rename_file from=\"/etc/hostname\" to=\"hostname\" />\n\
+            <rename_file from=\"util.rs\" to=\"src/utils.rs\" />\n\
+            <rename_file from=\"missing.rs\" to=\"other.rs\" />\n\
+            <file path=\"src/utils.rs\">\n<<<<<<< ORIGINAL\nfn a() {}\n=======\nfn b() {}\n>>>>>>> MODIFIED\n</file>";
+        let changes = parse_ai_response(response, root);
+        assert_eq!(changes.renames.len(), 1);
+        assert_eq!(changes.modifications[0].original, "fn a() {}\n");
+        assert_eq!(changes.stats().files, 1);
+
+        let report = changes.apply_selected(root, |_| true);
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+        assert!(!root.join("util.rs").exists());
+        assert_eq!(fs::read_to_string(root.join("src/utils.rs")).unwrap(), "fn b() {}\n");
+        assert_eq!(run(&["status", "--porcelain", "--", "util.rs", "src"]), "RM util.rs -> src/utils.rs\n");
+
+        crate::journal::ChangeJournal::last(root).unwrap().rollback(root).unwrap();
+        assert_eq!(fs::read_to_string(root.join("util.rs")).unwrap(), "fn a() {}\n");
+        assert!(!root.join("src/utils.rs").exists());
+    }
+
+    #[test]
+    fn test_patch_failure_builds_retry_prompt() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path();
+        fs::write(base.join("lib.rs"), "fn a() {\n    1\n}\n").unwrap();
+
+        let response = "<file path=\"lib.rs\">\n<<<<<<< ORIGINAL\nfn a() {\n    2\n}\n=======\nfn a() {\n    3\n}\n>>>>>>> MODIFIED\n</file>";
+        let changes = parse_ai_response(response, base);
+
+        assert!(changes.modifications.is_empty());
+        assert_eq!(changes.patch_failures.len(), 1);
+        let prompt = changes.patch_retry_prompt().unwrap();
+        assert!(prompt.contains("   2 |     1"));
+    }
+
+    #[test]
+    fn test_failed_hunk_is_placed_in_the_editor() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("lib.rs"), "fn a() {\n    1\n}\n").unwrap();
+        let response = "<file path=\"lib.rs\">\n<<<<<<< ORIGINAL\n    2\n=======\n    3\n>>>>>>> MODIFIED\n</file>";
+        let failure = parse_ai_response(response, dir.path()).patch_failures.remove(0);
+
+        // The only test that opens an editor: it edits the copy of the file in place
+        std::env::set_var("VISUAL", "perl -pi -e s/1/3/");
+        let placed = failure.resolve_in_editor();
+        std::env::set_var("VISUAL", "true");
+        let untouched = failure.resolve_in_editor();
+        std::env::remove_var("VISUAL");
+
+        let change = placed.unwrap().unwrap();
+        assert_eq!((change.original.as_str(), change.modified.as_str()), ("fn a() {\n    1\n}\n", "fn a() {\n    3\n}\n"));
+        assert_eq!(change.path, failure.full_path);
+        assert!(untouched.unwrap().is_none());
+        assert_eq!(fs::read_to_string(dir.path().join("lib.rs")).unwrap(), "fn a() {\n    1\n}\n");
+    }
+
+    #[test]
+    fn test_parse_replace_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path();
+        fs::write(base.join("lib.rs"), "fn a() {}\n").unwrap();
+
+        let response = "<replace_file path=\"lib.rs\">\nfn b() {}\n</replace_file>\n<replace_file path=\"new.rs\">\nfn c() {}\n</replace_file>";
+        let changes = parse_ai_response(response, base);
+
+        assert_eq!(changes.modifications.len(), 1);
+        assert_eq!(changes.modifications[0].original, "fn a() {}\n");
+        assert_eq!(changes.modifications[0].modified, "fn b() {}\n");
+        assert_eq!(changes.new_files.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_unified_diff_blocks() {
+        let base = tempfile::tempdir().unwrap();
+        fs::write(base.path().join("lib.rs"), "fn a() {\n    1\n}\n").unwrap();
+        fs::write(base.path().join("old.rs"), "fn old() {}\n").unwrap();
+
+        let response = "```diff\n--- a/lib.rs\n+++ b/lib.rs\n@@ -1,3 +1,3 @@\n fn a() {\n-    1\n+    2\n }\n\
+            --- a/old.rs\n+++ /dev/null\n@@ -1 +0,0 @@\n-fn old() {}\n\
+            --- /dev/null\n+++ b/new.rs\n@@ -0,0 +1 @@\n+fn new() {}\n```\n\
+            ```patch\n--- a/missing.rs\n+++ b/missing.rs\n@@ -1 +1 @@\n-x\n+y\n```\n";
+        let changes = parse_ai_response(response, base.path());
+
+        assert_eq!(changes.modifications.len(), 1);
+        assert_eq!(changes.modifications[0].modified, "fn a() {\n    2\n}\n");
+        assert_eq!(changes.deletions.len(), 1);
+        assert_eq!(changes.new_files[0].content, "fn new() {}\n");
+        assert_eq!(changes.patch_failures[0].path, "missing.rs");
+    }
+
+    #[test]
+    fn test_new_files_end_with_a_newline() {
+        let base = tempfile::tempdir().unwrap();
+        let response = "<new_file path=\"a.rs\">\nfn a() {}\n</new_file>\n<new_file path=\"b.rs\">fn b() {}</new_file>\n<new_file path=\".keep\">\n</new_file>";
+        let changes = parse_ai_response(response, base.path());
+
+        let contents: Vec<&str> = changes.new_files.iter().map(|file| file.content.as_str()).collect();
+        assert_eq!(contents, ["fn a() {}\n", "fn b() {}\n", ""]);
+    }
+
+    #[test]
+    fn test_apply_selected_skips_unapproved_and_stale_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path();
+        fs::write(base.join("a.rs"), "fn a() {}\n").unwrap();
+        fs::write(base.join("b.rs"), "fn b() {}\n").unwrap();
+
+        let response = "<replace_file path=\"a.rs\">\nfn a2() {}\n</replace_file>\n\
+            <replace_file path=\"b.rs\">\nfn b2() {}\n</replace_file>\n\
+            <new_file path=\"c.rs\">\nfn c() {}\n</new_file>";
+        let changes = parse_ai_response(response, base);
+        // Round trip through the GUI
+        let changes: ChangeSet = serde_json::from_str(&serde_json::to_string(&changes).unwrap()).unwrap();
+        fs::write(base.join("b.rs"), "fn edited() {}\n").unwrap();
+
+        let a = base.join("a.rs").to_string_lossy().to_string();
+        let b = base.join("b.rs").to_string_lossy().to_string();
+        let report = changes.apply_selected(base, |path| path == a || path == b);
+
+        assert_eq!(report.applied, vec![a]);
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(fs::read_to_string(base.join("a.rs")).unwrap(), "fn a2() {}\n");
+        assert_eq!(fs::read_to_string(base.join("b.rs")).unwrap(), "fn edited() {}\n");
+        assert!(!base.join("c.rs").exists());
+    }
+
+    #[test]
+    fn test_deletions_wait_for_approval_and_can_be_undone() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::write(root.join("old.rs"), "fn old() {}\n").unwrap();
+        fs::write(root.join("keep.rs"), "fn keep() {}\n").unwrap();
+
+        let response = "<delete_file path=\"old.rs\" />\n<delete_file path=\"keep.rs\" />";
+        let changes = parse_ai_response(response, root);
+        // Shown before each confirmation
+        let preview = changes.deletions[0].display();
+        assert!(preview.contains("old.rs") && preview.contains("fn old() {}"));
+
+        // Only the approved deletion is applied, the declined file stays
+        let old = root.join("old.rs").to_string_lossy().to_string();
+        let report = changes.apply_selected(root, |path| path == old);
+        assert_eq!(report.deleted, vec![old]);
+        assert!(!root.join("old.rs").exists());
+        assert_eq!(fs::read_to_string(root.join("keep.rs")).unwrap(), "fn keep() {}\n");
+
+        crate::journal::ChangeJournal::last(root).unwrap().rollback(root).unwrap();
+        assert_eq!(fs::read_to_string(root.join("old.rs")).unwrap(), "fn old() {}\n");
+    }
+
+    #[test]
+    fn test_diff_stats_header() {
+        let changes = ChangeSet {
+            modifications: vec![FileChange {
+                path: "src/lib.rs".to_string(),
+                original: "fn a() {}\nfn b() {}\n".to_string(),
+                modified: "fn a() {}\nfn b2() {}\nfn c() {}\n".to_string(),
+                description: String::new(),
+            }],
+            new_files: vec![NewFile { path: "web/app.ts".to_string(), content: "export {}\n".to_string(), description: String::new() }],
+            deletions: vec![FileDeletion { path: "notes".to_string(), content: "a\nb\n".to_string(), description: String::new() }],
+            ..Default::default()
+        };
+        let stats = changes.stats();
+        assert_eq!((stats.files, stats.added, stats.removed), (3, 3, 3));
+        assert_eq!(stats.header(), "3 fichiers, +3/−3 lignes (Rust, TypeScript)");
+        assert_eq!(ChangeSet::default().stats().header(), "0 fichiers, +0/−0 lignes");
+    }
+
+    #[test]
+    fn test_uncommitted_edits_are_merged_not_overwritten() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let run = |args: &[&str]| git(root, args).unwrap();
+        run(&["init", "-q"]);
+        let base = "fn a() {}\n\nfn b() {}\n\nfn c() {}\n";
+        fs::write(root.join("lib.rs"), base).unwrap();
+        run(&["add", "lib.rs"]);
+
+        let path = root.join("lib.rs").to_string_lossy().to_string();
+        let change = |modified: &str| FileChange {
+            path: path.clone(),
+            original: base.to_string(),
+            modified: modified.to_string(),
+            description: String::new(),
+        };
+        let ai = change("fn a() {}\n\nfn b() {}\n\nfn c2() {}\n");
+        assert_eq!(ai.local_base(), None);
+
+        // The AI edited c from the version it saw, the user edited a meanwhile
+        let mine = "fn a2() {}\n\nfn b() {}\n\nfn c() {}\n";
+        fs::write(root.join("lib.rs"), mine).unwrap();
+        assert_eq!(ai.local_base().as_deref(), Some(base));
+        let merged = ai.keeping_local_edits().unwrap();
+        assert_eq!(merged.original, mine);
+        assert_eq!(merged.modified, "fn a2() {}\n\nfn b() {}\n\nfn c2() {}\n");
+
+        let conflicting = change("fn a3() {}\n\nfn b() {}\n\nfn c() {}\n");
+        assert!(conflicting.keeping_local_edits().unwrap_err().contains("1 conflict(s)"));
+
+        // Edits the AI saw are its base, not a conflict
+        let seen = FileChange { original: mine.to_string(), ..change("fn a4() {}\n\nfn b() {}\n\nfn c() {}\n") };
+        assert_eq!(seen.keeping_local_edits().unwrap().modified, seen.modified);
+        let (text, conflicts) = three_way_merge(mine, base, &conflicting.modified).unwrap();
+        assert_eq!(conflicts, 1);
+        assert!(text.starts_with("<<<<<<< mes modifications\nfn a2() {}\n||||||| base (index git)\nfn a() {}\n=======\nfn a3() {}\n>>>>>>> IA\n"));
+    }
+
+    #[test]
+    fn test_partial_change_keeps_only_accepted_hunks() {
+        let original: String = (1..=20).map(|i| format!("line {}\n", i)).collect();
+        let modified = original.replace("line 2\n", "line two\n").replace("line 18\n", "line 18\nline 18b\n");
+        let change = FileChange { path: "a.txt".to_string(), original: original.clone(), modified: modified.clone(), description: String::new() };
+
+        let hunks = change.hunks();
+        assert_eq!(hunks.len(), 2);
+        assert_eq!(hunks[0].header, "@@ -1,5 +1,5 @@");
+        assert!(hunks[0].lines.contains(&('-', "line 2".to_string())) && hunks[0].lines.contains(&('+', "line two".to_string())));
+        assert_eq!(hunks[1].lines.iter().filter(|(sign, _)| *sign == '+').count(), 1);
+
+        assert_eq!(change.with_hunks(&[true, true]).modified, modified);
+        assert_eq!(change.with_hunks(&[false, false]).modified, original);
+        assert_eq!(change.with_hunks(&[false, true]).modified, original.replace("line 18\n", "line 18\nline 18b\n"));
+        assert!(change.with_hunks(&[]).modified == original && FileChange { modified: original.clone(), ..change }.hunks().is_empty());
+    }
+
+    #[test]
+    fn test_confirm_answers_follow_locale() {
+        assert_eq!(Locale::parse("fr_FR.UTF-8"), Some(Locale::Fr));
+        assert_eq!(Locale::parse("en_US.UTF-8"), Some(Locale::En));
+        assert_eq!(Locale::parse("C.UTF-8"), None);
+
+        assert!(answer("Oui\n", Locale::Fr, false, "") && answer("y", Locale::Fr, false, ""));
+        assert!(!answer("o", Locale::En, false, "") && answer("YES", Locale::En, false, ""));
+        assert!(!answer("\n", Locale::Fr, false, "") && answer("\n", Locale::En, true, ""));
+        assert!(!answer("non", Locale::Fr, true, "") && !answer("peut-être", Locale::Fr, true, ""));
+        assert!(answer("ja", Locale::En, false, "ja, j") && answer("J", Locale::En, false, "ja, j"));
+        assert_eq!(Locale::En.choices(true), "[Y/n]");
+    }
+}
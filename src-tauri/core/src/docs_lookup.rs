@@ -0,0 +1,383 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use regex::Regex;
+use walkdir::WalkDir;
+
+/// Entries returned for one symbol
+const MAX_RESULTS: usize = 5;
+
+/// Lines of description kept per entry (doc comment, docstring or README)
+const MAX_DESCRIPTION_LINES: usize = 20;
+
+/// Files read per source, so a huge node_modules cannot stall the tool
+const MAX_SCANNED_FILES: usize = 20_000;
+
+/// Bigger files are skipped (bundled declarations, generated code)
+const MAX_FILE_BYTES: u64 = 2_000_000;
+
+/// Virtual environments searched for Python packages, relative to the project
+const PYTHON_ENVS: &[&str] = &[".venv", "venv", "env"];
+
+/// Documentation of a symbol found in the project's installed dependencies
+#[derive(Debug, Clone, PartialEq)]
+pub struct DocEntry {
+    pub file: PathBuf,
+    /// 1-based; None for rustdoc JSON, where `signature` names the item
+    pub line: Option<usize>,
+    pub signature: String,
+    pub description: String,
+}
+
+/// Symbol as written by the model, e.g. `serde_json::from_str`, `fs.readFile` or `requests.get`:
+/// the name looked up and, when qualified, its crate, package or module
+fn split_symbol(symbol: &str) -> (Option<&str>, &str) {
+    let symbol = symbol.trim().trim_end_matches("()");
+    let separator = symbol.rfind("::").map(|i| (i, 2)).or_else(|| symbol.rfind('.').map(|i| (i, 1)));
+    match separator {
+        Some((i, len)) => {
+            let qualifier = &symbol[..i];
+            let package = qualifier.split("::").next().unwrap_or(qualifier).split('.').next().unwrap_or(qualifier);
+            (Some(package).filter(|p| !p.is_empty()), &symbol[i + len..])
+        }
+        None => (None, symbol),
+    }
+}
+
+/// Documentation of `symbol` in the locally installed docs of the project: rustdoc JSON
+/// (`target/doc/*.json`), npm packages (README, `.d.ts` declarations) and the Python
+/// packages of its virtual environment (docstrings)
+pub fn lookup(root: &Path, symbol: &str) -> Vec<DocEntry> {
+    let (package, name) = split_symbol(symbol);
+    if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || matches!(c, '_' | '$' | '-')) {
+        return Vec::new();
+    }
+    let mut entries = rustdoc_entries(root, package, name);
+    entries.extend(npm_entries(root, package, name));
+    entries.extend(python_entries(root, package, name));
+    entries.truncate(MAX_RESULTS);
+    entries
+}
+
+fn limited(text: &str) -> String {
+    let lines: Vec<&str> = text.trim().lines().collect();
+    let mut description = lines.iter().take(MAX_DESCRIPTION_LINES).copied().collect::<Vec<_>>().join("\n");
+    if lines.len() > MAX_DESCRIPTION_LINES {
+        description.push_str("\n[...]");
+    }
+    description
+}
+
+fn readable(path: &Path) -> Option<String> {
+    let size = fs::metadata(path).ok()?.len();
+    if size > MAX_FILE_BYTES {
+        return None;
+    }
+    fs::read_to_string(path).ok()
+}
+
+/// Rust type of rustdoc JSON, written back as source; `_` for the shapes not handled
+fn render_type(value: &serde_json::Value) -> String {
+    if let Some(name) = value.get("primitive").or_else(|| value.get("generic")).and_then(|v| v.as_str()) {
+        return name.to_string();
+    }
+    if let Some(path) = value.get("resolved_path") {
+        let name = path.get("path").or_else(|| path.get("name")).and_then(|v| v.as_str()).unwrap_or("_");
+        let args: Vec<String> = path.pointer("/args/angle_bracketed/args")
+            .and_then(|args| args.as_array())
+            .map(|args| args.iter().filter_map(|arg| arg.get("type")).map(render_type).collect())
+            .unwrap_or_default();
+        return if args.is_empty() { name.to_string() } else { format!("{}<{}>", name, args.join(", ")) };
+    }
+    if let Some(reference) = value.get("borrowed_ref") {
+        let mutable = reference.get("is_mutable").or_else(|| reference.get("mutable")).and_then(|v| v.as_bool()).unwrap_or(false);
+        let inner = reference.get("type").map(render_type).unwrap_or_else(|| "_".to_string());
+        return format!("&{}{}", if mutable { "mut " } else { "" }, inner);
+    }
+    if let Some(inner) = value.get("slice") {
+        return format!("[{}]", render_type(inner));
+    }
+    if let Some(types) = value.get("tuple").and_then(|v| v.as_array()) {
+        return format!("({})", types.iter().map(render_type).collect::<Vec<_>>().join(", "));
+    }
+    if value.get("impl_trait").is_some() {
+        return "impl _".to_string();
+    }
+    "_".to_string()
+}
+
+/// `fn name(a: T) -> U` of a function item (`sig` in recent rustdoc, `decl` before)
+fn rust_signature(name: &str, function: &serde_json::Value) -> String {
+    let Some(sig) = function.get("sig").or_else(|| function.get("decl")) else {
+        return format!("fn {}", name);
+    };
+    let inputs: Vec<String> = sig.get("inputs").and_then(|v| v.as_array()).map(|inputs| {
+        inputs.iter().filter_map(|input| {
+            let pair = input.as_array()?;
+            let arg = pair.first()?.as_str()?;
+            let ty = pair.get(1).map(render_type).unwrap_or_else(|| "_".to_string());
+            Some(match (arg, ty.as_str()) {
+                ("self", ty) if ty.starts_with("&mut ") => "&mut self".to_string(),
+                ("self", ty) if ty.starts_with('&') => "&self".to_string(),
+                ("self", _) => "self".to_string(),
+                (arg, ty) => format!("{}: {}", arg, ty),
+            })
+        }).collect()
+    }).unwrap_or_default();
+    let output = sig.get("output").filter(|v| !v.is_null()).map(|v| format!(" -> {}", render_type(v))).unwrap_or_default();
+    format!("fn {}({}){}", name, inputs.join(", "), output)
+}
+
+/// Items of the rustdoc JSON files (`cargo +nightly rustdoc -- -Z unstable-options --output-format json`)
+/// named `name`; `package` restricts them to one crate
+fn rustdoc_entries(root: &Path, package: Option<&str>, name: &str) -> Vec<DocEntry> {
+    let Ok(dir) = fs::read_dir(root.join("target").join("doc")) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = dir.filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .filter(|path| match package {
+            Some(package) => path.file_stem().is_some_and(|stem| stem.to_string_lossy() == package.replace('-', "_")),
+            None => true,
+        })
+        .collect();
+    files.sort();
+
+    let mut entries = Vec::new();
+    for file in files {
+        let Some(json) = fs::read_to_string(&file).ok().and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok()) else {
+            continue;
+        };
+        let Some(index) = json.get("index").and_then(|v| v.as_object()) else { continue };
+        let mut ids: Vec<&String> = index.keys().collect();
+        ids.sort();
+        for id in ids {
+            let item = &index[id];
+            if item.get("name").and_then(|v| v.as_str()) != Some(name) {
+                continue;
+            }
+            let Some((kind, inner)) = item.get("inner").and_then(|v| v.as_object()).and_then(|inner| inner.iter().next()) else {
+                continue;
+            };
+            let path = json.pointer(&format!("/paths/{}/path", id))
+                .and_then(|v| v.as_array())
+                .map(|parts| parts.iter().filter_map(|p| p.as_str()).collect::<Vec<_>>().join("::"))
+                .unwrap_or_else(|| name.to_string());
+            let signature = match kind.as_str() {
+                "function" => format!("{} ({})", rust_signature(name, inner), path),
+                kind => format!("{} {}", kind, path),
+            };
+            let docs = item.get("docs").and_then(|v| v.as_str()).unwrap_or_default();
+            entries.push(DocEntry { file: file.clone(), line: None, signature, description: limited(docs) });
+            if entries.len() == MAX_RESULTS {
+                return entries;
+            }
+        }
+    }
+    entries
+}
+
+/// Text of the comment block ending just above `line` (0-based): JSDoc `/** */` or Python `#` lines
+fn comment_above(lines: &[&str], line: usize) -> String {
+    let mut start = line;
+    if line > 0 && lines[line - 1].trim_end().ends_with("*/") {
+        while start > 0 {
+            start -= 1;
+            if lines[start].trim_start().starts_with("/*") {
+                break;
+            }
+        }
+        return lines[start..line].iter()
+            .map(|l| l.trim().trim_start_matches("/**").trim_start_matches("/*").trim_end_matches("*/").trim_start_matches('*').trim())
+            .filter(|l| !l.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n");
+    }
+    String::new()
+}
+
+/// Declarations of `name` in the `.d.ts` files of node_modules, with their JSDoc, and the
+/// README of the package called `name`. A qualifier restricts the search to that package
+fn npm_entries(root: &Path, package: Option<&str>, name: &str) -> Vec<DocEntry> {
+    let modules = root.join("node_modules");
+    if !modules.is_dir() {
+        return Vec::new();
+    }
+    let mut entries = Vec::new();
+    for readme in ["README.md", "readme.md", "Readme.md"] {
+        let path = modules.join(name).join(readme);
+        if let Some(content) = readable(&path) {
+            entries.push(DocEntry { file: path, line: Some(1), signature: format!("package {}", name), description: limited(&content) });
+            break;
+        }
+    }
+
+    let escaped = regex::escape(name);
+    let declaration = Regex::new(&format!(
+        r"^\s*(export\s+)?(declare\s+)?(default\s+)?(abstract\s+)?(async\s+)?(function\*?|class|interface|type|const|let|var|enum|namespace)\s+{}\b|^\s*(readonly\s+|static\s+)*{}\??\s*[(<]",
+        escaped, escaped
+    )).expect("valid declaration pattern");
+    let base = match package {
+        Some(package) if modules.join(package).is_dir() => modules.join(package),
+        _ => modules,
+    };
+    for entry in WalkDir::new(&base).sort_by_file_name().into_iter().filter_map(|e| e.ok()).take(MAX_SCANNED_FILES) {
+        if !entry.file_type().is_file() || !entry.file_name().to_string_lossy().ends_with(".d.ts") {
+            continue;
+        }
+        let Some(content) = readable(entry.path()) else { continue };
+        let lines: Vec<&str> = content.lines().collect();
+        for (i, line) in lines.iter().enumerate() {
+            if !declaration.is_match(line) {
+                continue;
+            }
+            entries.push(DocEntry {
+                file: entry.path().to_path_buf(),
+                line: Some(i + 1),
+                signature: line.trim().trim_end_matches('{').trim().to_string(),
+                description: limited(&comment_above(&lines, i)),
+            });
+            if entries.len() == MAX_RESULTS {
+                return entries;
+            }
+        }
+    }
+    entries
+}
+
+/// Docstring of the definition starting at `line` (0-based): the string opening its body
+fn docstring(lines: &[&str], line: usize) -> String {
+    // The signature may span several lines
+    let Some(end) = lines[line..].iter().position(|l| l.trim_end().ends_with(':')) else {
+        return String::new();
+    };
+    let Some(start) = (line + end + 1..lines.len()).find(|&i| !lines[i].trim().is_empty()) else {
+        return String::new();
+    };
+    let opening = lines[start].trim_start().trim_start_matches(['r', 'u', 'R', 'U']);
+    let Some(quote) = ["\"\"\"", "'''"].into_iter().find(|q| opening.starts_with(q)) else {
+        return String::new();
+    };
+    let rest = &opening[3..];
+    if let Some(end) = rest.find(quote) {
+        return rest[..end].trim().to_string();
+    }
+    let mut text = vec![rest.trim()];
+    for l in &lines[start + 1..] {
+        if let Some(end) = l.find(quote) {
+            text.push(&l[..end]);
+            break;
+        }
+        text.push(l);
+    }
+    // Docstrings are indented like the body
+    let indent = text.iter().skip(1).filter(|l| !l.trim().is_empty()).map(|l| l.len() - l.trim_start().len()).min().unwrap_or(0);
+    text.iter().enumerate()
+        .map(|(i, l)| if i == 0 { l.to_string() } else { l.get(indent..).unwrap_or(l.trim()).to_string() })
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+/// `site-packages` directories of the project's virtual environments
+fn site_packages(root: &Path) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    for env in PYTHON_ENVS {
+        // lib/python3.x/site-packages on Unix, Lib/site-packages on Windows
+        let windows = root.join(env).join("Lib").join("site-packages");
+        if windows.is_dir() {
+            dirs.push(windows);
+        }
+        let Ok(versions) = fs::read_dir(root.join(env).join("lib")) else { continue };
+        let mut versions: Vec<PathBuf> = versions.filter_map(|e| e.ok()).map(|e| e.path().join("site-packages")).filter(|p| p.is_dir()).collect();
+        versions.sort();
+        dirs.extend(versions);
+    }
+    dirs
+}
+
+/// `def` and `class` definitions of `name` in the installed Python packages, with their docstring.
+/// A qualifier restricts the search to that package
+fn python_entries(root: &Path, package: Option<&str>, name: &str) -> Vec<DocEntry> {
+    let definition = Regex::new(&format!(r"^\s*(async\s+)?(def|class)\s+{}\b", regex::escape(name))).expect("valid definition pattern");
+    let mut entries = Vec::new();
+    for site in site_packages(root) {
+        let base = match package {
+            Some(package) if site.join(package).is_dir() => site.join(package),
+            Some(_) => continue,
+            None => site,
+        };
+        for entry in WalkDir::new(&base).sort_by_file_name().into_iter().filter_map(|e| e.ok()).take(MAX_SCANNED_FILES) {
+            if !entry.file_type().is_file() || entry.path().extension().is_none_or(|ext| ext != "py") {
+                continue;
+            }
+            let Some(content) = readable(entry.path()) else { continue };
+            let lines: Vec<&str> = content.lines().collect();
+            for (i, line) in lines.iter().enumerate() {
+                if !definition.is_match(line) {
+                    continue;
+                }
+                let signature: Vec<&str> = lines[i..].iter()
+                    .take(10)
+                    .scan(false, |done, l| {
+                        if *done {
+                            return None;
+                        }
+                        *done = l.trim_end().ends_with(':');
+                        Some(l.trim())
+                    })
+                    .collect();
+                entries.push(DocEntry {
+                    file: entry.path().to_path_buf(),
+                    line: Some(i + 1),
+                    signature: signature.join(" ").trim_end_matches(':').to_string(),
+                    description: limited(&docstring(&lines, i)),
+                });
+                if entries.len() == MAX_RESULTS {
+                    return entries;
+                }
+            }
+        }
+    }
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_symbols_are_found_in_installed_docs() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let write = |path: &str, content: &str| {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        };
+        write("node_modules/left-pad/index.d.ts", "/**\n * Pad the start of a string.\n * @param str text to pad\n */\nexport declare function leftPad(str: string, len: number): string;\n");
+        write("node_modules/left-pad/README.md", "# left-pad\n\nString padding.\n");
+        write(".venv/lib/python3.12/site-packages/requests/api.py", "def get(url, params=None,\n        **kwargs):\n    r\"\"\"Sends a GET request.\n\n    :param url: URL for the new Request.\n    \"\"\"\n    return request(\"get\", url)\n");
+        write("target/doc/demo.json", r#"{"index": {"0:1": {"name": "parse", "docs": "Parse a config.", "inner": {"function": {"sig": {
+            "inputs": [["input", {"borrowed_ref": {"is_mutable": false, "type": {"primitive": "str"}}}]],
+            "output": {"resolved_path": {"path": "Result", "args": {"angle_bracketed": {"args": [{"type": {"resolved_path": {"path": "Config"}}}]}}}}}}}}},
+            "paths": {"0:1": {"path": ["demo", "parse"], "kind": "function"}}}"#);
+
+        let ts = lookup(root, "leftPad");
+        assert_eq!(ts.len(), 1);
+        assert_eq!((ts[0].line, ts[0].signature.as_str()), (Some(5), "export declare function leftPad(str: string, len: number): string;"));
+        assert_eq!(ts[0].description, "Pad the start of a string.\n@param str text to pad");
+        assert_eq!(lookup(root, "left-pad")[0].description, "# left-pad\n\nString padding.");
+
+        let py = lookup(root, "requests.get");
+        assert_eq!(py[0].signature, "def get(url, params=None, **kwargs)");
+        assert_eq!(py[0].description, "Sends a GET request.\n\n:param url: URL for the new Request.");
+        assert!(lookup(root, "flask.get").is_empty());
+
+        let rust = lookup(root, "demo::parse");
+        assert_eq!(rust[0].signature, "fn parse(input: &str) -> Result<Config> (demo::parse)");
+        assert_eq!(rust[0].description, "Parse a config.");
+        assert!(lookup(root, "no such thing").is_empty());
+    }
+}
@@ -0,0 +1,154 @@
+use reqwest::StatusCode;
+use thiserror::Error;
+use crate::mistral_client::ApiError;
+
+/// Failures callers tell apart: a rate limit is retried, a rejected key is not, a locked
+/// index is waited for. Functions that only report a message keep `Result<_, String>`;
+/// `?` converts both ways
+#[derive(Debug, Error)]
+pub enum Error {
+    /// No key saved and the setup wizard was left
+    #[error("No API key configured: {0}")]
+    MissingApiKey(String),
+    /// Key refused by the provider (401, 403)
+    #[error("{0}")]
+    Unauthorized(String),
+    #[error("{0}")]
+    RateLimited(String),
+    /// Prompt over the context window, or account quota exhausted
+    #[error("{0}")]
+    Budget(String),
+    /// 5xx answer
+    #[error("{0}")]
+    Server(String),
+    /// Any other error status
+    #[error("{0}")]
+    Api(String),
+    /// The provider could not be reached at all
+    #[error("{0}")]
+    Offline(String),
+    #[error("{0}")]
+    Timeout(String),
+    #[error("{0}")]
+    Network(String),
+    /// Answer that could not be read (truncated, unexpected JSON)
+    #[error("{0}")]
+    InvalidResponse(String),
+    /// Another connection (background sync, nightly reindex) holds the index database
+    #[error("{0}")]
+    IndexLocked(String),
+    #[error("{0}")]
+    Database(String),
+    #[error("{0}")]
+    Other(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// Kind of a failed API request, with its message unchanged
+    pub fn from_request(error: &anyhow::Error) -> Self {
+        let message = error.to_string();
+        if let Some(api) = error.downcast_ref::<ApiError>() {
+            let details = api.message.to_lowercase();
+            let over_budget = details.contains("context length")
+                || details.contains("too large for model")
+                || details.contains("quota");
+            return match api.status {
+                StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Error::Unauthorized(message),
+                StatusCode::PAYMENT_REQUIRED => Error::Budget(message),
+                _ if over_budget => Error::Budget(message),
+                StatusCode::TOO_MANY_REQUESTS => Error::RateLimited(message),
+                StatusCode::REQUEST_TIMEOUT => Error::Timeout(message),
+                status if status.is_server_error() => Error::Server(message),
+                _ => Error::Api(message),
+            };
+        }
+        match error.downcast_ref::<reqwest::Error>() {
+            Some(e) if e.is_timeout() => Error::Timeout(message),
+            Some(e) if e.is_connect() => Error::Offline(message),
+            Some(_) => Error::Network(message),
+            None => Error::InvalidResponse(message),
+        }
+    }
+
+    /// SQLite failure of the index, e.g. `Error::database("Query error", e)`
+    pub fn database(context: &str, error: rusqlite::Error) -> Self {
+        let locked = matches!(
+            error.sqlite_error_code(),
+            Some(rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked)
+        );
+        let message = format!("{}: {}", context, error);
+        if locked { Error::IndexLocked(message) } else { Error::Database(message) }
+    }
+
+    /// Why the request is worth sending again, shown in the retry notice; None when it would fail the same way
+    pub fn retry_reason(&self) -> Option<&'static str> {
+        match self {
+            Error::RateLimited(_) => Some("limite de débit"),
+            Error::Timeout(_) => Some("délai dépassé"),
+            Error::Server(_) => Some("erreur serveur"),
+            Error::Offline(_) => Some("connexion impossible"),
+            Error::Network(_) => Some("erreur réseau"),
+            Error::InvalidResponse(_) => Some("réponse invalide"),
+            _ => None,
+        }
+    }
+
+    /// What the user can do about it, shown under the error by the CLI and the TUI
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            Error::MissingApiKey(_) => Some("Relancez la commande pour saisir une clé, ou configurez-la depuis le GUI (companion-chat sans arguments)."),
+            Error::Unauthorized(_) => Some("Clé API refusée : vérifiez la clé et le fournisseur (settings.json ou paramètres du GUI)."),
+            Error::RateLimited(_) => Some("Limite de débit atteinte : réessayez plus tard, ou réduisez rate_limits dans settings.json."),
+            Error::Budget(_) => Some("Réduisez le contexte (--context diff, /context diff, /clear) ou vérifiez le quota du compte."),
+            Error::Offline(_) => Some("Vérifiez la connexion réseau, ou que le serveur local est lancé."),
+            Error::IndexLocked(_) => Some("L'index est utilisé par un autre processus (synchronisation, réindexation nocturne) : réessayez dans quelques secondes."),
+            _ => None,
+        }
+    }
+}
+
+impl From<String> for Error {
+    fn from(message: String) -> Self {
+        Error::Other(message)
+    }
+}
+
+impl From<Error> for String {
+    fn from(error: Error) -> Self {
+        error.to_string()
+    }
+}
+
+impl From<anyhow::Error> for Error {
+    fn from(error: anyhow::Error) -> Self {
+        Error::from_request(&error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_errors_are_classified() {
+        let api = |status: StatusCode, message: &str| Error::from(anyhow::Error::from(ApiError { status, message: message.to_string() }));
+        assert!(matches!(api(StatusCode::FORBIDDEN, "Forbidden"), Error::Unauthorized(_)));
+        assert!(matches!(api(StatusCode::TOO_MANY_REQUESTS, "Requests rate limit exceeded"), Error::RateLimited(_)));
+        // A spent quota fails the same way on every retry
+        let quota = api(StatusCode::TOO_MANY_REQUESTS, "Monthly quota exceeded");
+        assert!(matches!(quota, Error::Budget(_)) && quota.retry_reason().is_none());
+        assert_eq!(api(StatusCode::BAD_GATEWAY, "").retry_reason(), Some("erreur serveur"));
+        assert_eq!(api(StatusCode::BAD_REQUEST, "Invalid model").to_string(), "API Error (400 Bad Request): Invalid model");
+        assert!(matches!(Error::from(anyhow::anyhow!("expected value at line 1")), Error::InvalidResponse(_)));
+
+        let busy = rusqlite::Error::SqliteFailure(rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_BUSY), None);
+        let locked = Error::database("Cannot index file", busy);
+        assert!(matches!(locked, Error::IndexLocked(_)) && locked.hint().is_some());
+        assert!(locked.to_string().starts_with("Cannot index file: "));
+
+        let message: String = Error::MissingApiKey("Configuration annulée.".to_string()).into();
+        assert_eq!(message, "No API key configured: Configuration annulée.");
+    }
+}
@@ -0,0 +1,74 @@
+use crate::settings::{settings_file, ProjectSettings};
+use std::fs;
+use std::path::Path;
+
+/// Lockfiles: thousands of lines of hashes and versions, never written by hand
+const LOCKFILES: &[&str] = &[
+    "package-lock.json", "npm-shrinkwrap.json", "yarn.lock", "pnpm-lock.yaml", "bun.lock",
+    "Cargo.lock", "poetry.lock", "Pipfile.lock", "uv.lock", "composer.lock", "Gemfile.lock",
+    "go.sum", "flake.lock", "packages.lock.json", "pubspec.lock", "mix.lock",
+];
+
+/// Bundler and minifier outputs
+const GENERATED_SUFFIXES: &[&str] = &[
+    ".min.js", ".min.mjs", ".min.css", ".bundle.js", "-bundle.js", ".chunk.js", ".js.map", ".css.map",
+];
+
+/// Minified code packs everything on a few very long lines
+const MAX_AVERAGE_LINE: usize = 250;
+const MAX_LINE: usize = 5_000;
+/// Below this size a long line is just a long line
+const MIN_MINIFIED_SIZE: usize = 1_000;
+
+/// Whether the name alone marks a lockfile or a bundle, without reading the file
+pub fn is_generated_name(path: &Path) -> bool {
+    let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+        return false;
+    };
+    LOCKFILES.contains(&name) || GENERATED_SUFFIXES.iter().any(|suffix| name.ends_with(suffix))
+}
+
+/// Lockfiles, bundles and minified code: the indexes keep only their path and size, and the
+/// prompt leaves their content out
+pub fn is_generated(path: &Path, content: &str) -> bool {
+    is_generated_name(path) || is_minified(content)
+}
+
+fn is_minified(content: &str) -> bool {
+    if content.len() < MIN_MINIFIED_SIZE {
+        return false;
+    }
+    let lines = content.lines().count().max(1);
+    content.len() / lines > MAX_AVERAGE_LINE || content.lines().any(|line| line.len() > MAX_LINE)
+}
+
+/// Whether generated files are indexed and sent like the others: `"include_generated": true`
+/// in `.codestral/settings.json`, else in the `config` section of the global settings.json
+pub fn included(project_root: &Path) -> bool {
+    if let Some(included) = ProjectSettings::load(project_root).ok().and_then(|settings| settings.include_generated) {
+        return included;
+    }
+    settings_file().ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+        .and_then(|json| json.get("config")?.get("include_generated")?.as_bool())
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_lockfiles_and_minified_code() {
+        assert!(is_generated_name(Path::new("web/package-lock.json")));
+        assert!(is_generated_name(Path::new("static/app.min.js")));
+        assert!(!is_generated_name(Path::new("src/package.json")));
+
+        let minified = format!("!function(){{{}}}();", "var a=1;".repeat(400));
+        assert!(is_generated(Path::new("public/vendor.js"), &minified));
+        let code = "fn main() {\n    println!(\"hello\");\n}\n".repeat(100);
+        assert!(!is_generated(Path::new("src/main.rs"), &code));
+        assert!(!is_generated(Path::new("src/short.js"), "var a=1;"));
+    }
+}
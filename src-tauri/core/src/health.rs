@@ -0,0 +1,126 @@
+use crate::backend::LlmBackend;
+use crate::mistral_client::Message;
+use serde::Serialize;
+use std::time::Instant;
+
+/// State of the configured provider: reachable, how fast, whether it serves the model, quota left
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ProviderHealth {
+    pub provider: String,
+    pub host: String,
+    pub model: String,
+    /// Round trip of the model list request, in milliseconds
+    pub list_latency_ms: Option<u64>,
+    pub list_error: Option<String>,
+    /// Whether the provider lists the model; None when it lists none
+    pub model_available: Option<bool>,
+    /// Round trip of a minimal chat request, in milliseconds
+    pub chat_latency_ms: Option<u64>,
+    pub chat_error: Option<String>,
+    /// Rate limit and quota headers of the last response, when the provider sends any
+    pub rate_limits: Vec<(String, String)>,
+}
+
+impl ProviderHealth {
+    /// The chat request went through and nothing says the model is missing
+    pub fn is_healthy(&self) -> bool {
+        self.chat_latency_ms.is_some() && self.model_available != Some(false)
+    }
+
+    /// Report shown by `/health` in the TUI and the CLI chat
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = vec![format!("Fournisseur: {} ({})", self.provider, self.host)];
+        lines.push(match self.model_available {
+            Some(true) => format!("Modèle: {} ✓ disponible", self.model),
+            Some(false) => format!("Modèle: {} ✗ absent de la liste du fournisseur", self.model),
+            None => format!("Modèle: {} (liste des modèles indisponible)", self.model),
+        });
+        match (self.list_latency_ms, &self.list_error) {
+            (Some(ms), _) => lines.push(format!("Latence liste des modèles: {} ms", ms)),
+            (None, Some(e)) => lines.push(format!("❌ Liste des modèles: {}", e)),
+            (None, None) => {}
+        }
+        match (self.chat_latency_ms, &self.chat_error) {
+            (Some(ms), _) => lines.push(format!("Latence requête de chat: {} ms", ms)),
+            (None, Some(e)) => lines.push(format!("❌ Requête de chat: {}", e)),
+            (None, None) => {}
+        }
+        if self.rate_limits.is_empty() {
+            lines.push("Quota: aucun en-tête de limite renvoyé".to_string());
+        } else {
+            lines.push("Quota:".to_string());
+            lines.extend(self.rate_limits.iter().map(|(name, value)| format!("  {} = {}", name, value)));
+        }
+        lines.push(if self.is_healthy() { "✅ Fournisseur opérationnel".to_string() } else { "⚠️ Fournisseur en erreur".to_string() });
+        lines
+    }
+}
+
+/// Time a model list and a minimal chat request, and check the model is served
+pub async fn check(client: &dyn LlmBackend) -> ProviderHealth {
+    let mut health = ProviderHealth {
+        provider: client.provider().name().to_string(),
+        host: client.provider().host(),
+        model: client.get_model().to_string(),
+        ..Default::default()
+    };
+
+    let started = Instant::now();
+    match client.list_models().await {
+        Ok(models) => {
+            health.list_latency_ms = Some(started.elapsed().as_millis() as u64);
+            health.model_available = (!models.is_empty()).then(|| model_listed(&models, &health.model));
+        }
+        Err(e) => health.list_error = Some(e.to_string()),
+    }
+
+    let started = Instant::now();
+    match client.chat(vec![Message::user("Hello")]).await {
+        Ok(_) => health.chat_latency_ms = Some(started.elapsed().as_millis() as u64),
+        Err(e) => health.chat_error = Some(e.to_string()),
+    }
+    health.rate_limits = client.rate_limits();
+    health
+}
+
+/// Ollama lists "codestral:latest" for the model "codestral"
+fn model_listed(models: &[String], model: &str) -> bool {
+    models.iter().any(|listed| listed == model || listed.strip_suffix(":latest") == Some(model))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::{HeaderMap, HeaderValue};
+
+    #[test]
+    fn test_health_report() {
+        let models = vec!["codestral-latest".to_string(), "qwen2.5-coder:latest".to_string()];
+        assert!(model_listed(&models, "codestral-latest") && model_listed(&models, "qwen2.5-coder"));
+        assert!(!model_listed(&models, "codestral"));
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ratelimitbysize-remaining-minute", HeaderValue::from_static("49000"));
+        headers.insert("content-type", HeaderValue::from_static("application/json"));
+        let rate_limits = crate::mistral_client::rate_limit_headers(&headers);
+        assert_eq!(rate_limits, vec![("x-ratelimitbysize-remaining-minute".to_string(), "49000".to_string())]);
+
+        let health = ProviderHealth {
+            provider: "Codestral".to_string(),
+            host: "codestral.mistral.ai".to_string(),
+            model: "codestral-latest".to_string(),
+            list_latency_ms: Some(120),
+            model_available: Some(true),
+            chat_latency_ms: Some(640),
+            rate_limits,
+            ..Default::default()
+        };
+        assert!(health.is_healthy());
+        assert_eq!(health.lines()[3], "Latence requête de chat: 640 ms");
+        assert_eq!(health.lines()[5], "  x-ratelimitbysize-remaining-minute = 49000");
+
+        let missing = ProviderHealth { model_available: Some(false), ..health };
+        assert!(!missing.is_healthy());
+        assert_eq!(missing.lines().last().unwrap(), "⚠️ Fournisseur en erreur");
+    }
+}
@@ -0,0 +1,83 @@
+use crate::settings::{settings_file, ProjectSettings};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::Path;
+
+/// Key of the encrypted index instead of the keyring, e.g. on CI (64 hex digits)
+pub const KEY_ENV: &str = "CODESTRAL_INDEX_KEY";
+
+const KEYRING_SERVICE: &str = "codestral-companion";
+const KEYRING_ACCOUNT: &str = "index-key";
+
+/// Whether the project's index.db is encrypted: `"encrypt_index": true` in `.codestral/settings.json`,
+/// else in the `config` section of the global settings.json. Off by default
+pub fn enabled(project_root: &Path) -> bool {
+    if let Some(enabled) = ProjectSettings::load(project_root).ok().and_then(|settings| settings.encrypt_index) {
+        return enabled;
+    }
+    settings_file().ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+        .and_then(|json| json.get("config")?.get("encrypt_index")?.as_bool())
+        .unwrap_or(false)
+}
+
+/// Raw 256-bit key of the encrypted indexes, as hex: from `CODESTRAL_INDEX_KEY`, else from the
+/// OS keyring, where one is created on first use
+pub fn key() -> Result<String, String> {
+    if let Ok(key) = std::env::var(KEY_ENV) {
+        return checked(key.trim()).ok_or_else(|| format!("{} must be 64 hexadecimal digits", KEY_ENV));
+    }
+    if let Some(key) = keyring_lookup().as_deref().and_then(checked) {
+        return Ok(key);
+    }
+    let key = generate();
+    keyring_store(&key)?;
+    Ok(key)
+}
+
+fn checked(key: &str) -> Option<String> {
+    (key.len() == 64 && key.chars().all(|c| c.is_ascii_hexdigit())).then(|| key.to_lowercase())
+}
+
+/// 32 bytes out of the system's random generator (through v4 UUIDs)
+fn generate() -> String {
+    let mut hasher = Sha256::new();
+    for _ in 0..4 {
+        hasher.update(uuid::Uuid::new_v4().as_bytes());
+    }
+    hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn keyring_entry() -> keyring::Result<keyring::Entry> {
+    keyring::Entry::new(KEYRING_SERVICE, KEYRING_ACCOUNT)
+}
+
+/// Keychain on macOS, Credential Manager on Windows, Secret Service (GNOME Keyring, KWallet) on Linux
+fn keyring_lookup() -> Option<String> {
+    keyring_entry().and_then(|entry| entry.get_password()).ok()
+}
+
+fn keyring_store(key: &str) -> Result<(), String> {
+    keyring_entry()
+        .and_then(|entry| entry.set_password(key))
+        .map_err(|e| format!("Cannot store the index key in the OS keyring ({}): set {} instead", e, KEY_ENV))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_index_key_shape_and_setting() {
+        let key = generate();
+        assert!(checked(&key).is_some() && key != generate());
+        assert_eq!(checked(&key.to_uppercase()), Some(key));
+        assert_eq!(checked("abc"), None);
+
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join(".codestral")).unwrap();
+        fs::write(dir.path().join(".codestral/settings.json"), r#"{ "encrypt_index": true }"#).unwrap();
+        assert!(enabled(dir.path()));
+    }
+}
@@ -0,0 +1,506 @@
+use std::path::{Path, PathBuf};
+use std::fs;
+use std::process::Command;
+use ignore::WalkBuilder;
+use indicatif::{ProgressBar, ProgressStyle};
+use crate::workspace::Workspace;
+use crate::context_guard::count_tokens;
+
+/// Files the CODEBASE section of the prompt is built from
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum ContextScope {
+    /// Whole project
+    #[default]
+    Full,
+    /// Files changed on the current branch vs main, committed or not
+    Diff,
+}
+
+/// Supported file extensions for code analysis
+const DEFAULT_EXTENSIONS: &[&str] = &[
+    "rs", "ts", "tsx", "js", "jsx", "py", "go", "java", "kt", "swift",
+    "c", "cpp", "h", "hpp", "cs", "rb", "php", "vue", "svelte",
+    "html", "css", "scss", "sass", "less", "json", "yaml", "yml",
+    "toml", "md", "sql", "sh", "bash", "zsh", "fish",
+];
+
+/// Maximum file size to read (100KB)
+pub const MAX_FILE_SIZE: u64 = 100_000;
+
+/// Why a file of the project is missing from the index, or indexed without its content
+#[derive(Debug, Clone, PartialEq)]
+pub enum SkipReason {
+    /// Size in bytes, over `MAX_FILE_SIZE`
+    TooLarge(u64),
+    /// Not UTF-8 text
+    Binary,
+    /// Past the `max_files` first files
+    FileLimit(usize),
+    /// Lockfile or minified code: listed, content left out (see `generated`)
+    Generated,
+}
+
+impl SkipReason {
+    pub fn describe(&self) -> String {
+        match self {
+            SkipReason::TooLarge(size) => format!("trop volumineux ({} Ko, limite {} Ko)", size / 1000, MAX_FILE_SIZE / 1000),
+            SkipReason::Binary => "binaire ou non UTF-8".to_string(),
+            SkipReason::FileLimit(limit) => format!("au-delà des {} premiers fichiers (--max-files)", limit),
+            SkipReason::Generated => "généré (lockfile ou code minifié) : contenu exclu, voir include_generated".to_string(),
+        }
+    }
+
+    /// Shown when the user names the file
+    pub fn warning(&self, path: &str) -> String {
+        format!("⚠️  {} : {}, le modèle ne voit pas son contenu", path, self.describe())
+    }
+
+    /// Columns (reason, value) of the `skipped_files` table of index.db
+    pub fn to_row(&self) -> (&'static str, i64) {
+        match self {
+            SkipReason::TooLarge(size) => ("too_large", *size as i64),
+            SkipReason::Binary => ("binary", 0),
+            SkipReason::FileLimit(limit) => ("file_limit", *limit as i64),
+            SkipReason::Generated => ("generated", 0),
+        }
+    }
+
+    pub fn from_row(reason: &str, value: i64) -> Option<Self> {
+        match reason {
+            "too_large" => Some(SkipReason::TooLarge(value as u64)),
+            "binary" => Some(SkipReason::Binary),
+            "file_limit" => Some(SkipReason::FileLimit(value as usize)),
+            "generated" => Some(SkipReason::Generated),
+            _ => None,
+        }
+    }
+}
+
+/// Skipped files named in `text`, by relative path or file name (`data/big.json`, `@big.json`),
+/// so the user learns why the model cannot see them
+pub fn mentioned_skips<'a>(text: &str, skipped: &'a [(String, SkipReason)]) -> Vec<&'a (String, SkipReason)> {
+    let mut found: Vec<&'a (String, SkipReason)> = Vec::new();
+    for word in text.split_whitespace() {
+        let word = word
+            .trim_start_matches(['(', '[', '"', '\'', '`', '@'])
+            .trim_end_matches(['.', ',', ';', ':', '!', '?', ')', ']', '"', '\'', '`'])
+            .trim_start_matches("./");
+        if !word.contains('.') && !word.contains('/') {
+            continue;
+        }
+        let suffix = format!("/{}", word);
+        for entry in skipped.iter().filter(|(path, _)| *path == word || path.ends_with(&suffix)) {
+            if !found.iter().any(|f| f.0 == entry.0) {
+                found.push(entry);
+            }
+        }
+    }
+    found
+}
+
+#[derive(Debug, Clone)]
+pub struct IndexedFile {
+    pub path: PathBuf,
+    pub relative_path: String,
+    pub content: String,
+    pub extension: String,
+    pub size: u64,
+}
+
+#[derive(Debug)]
+pub struct CodebaseIndex {
+    pub root: PathBuf,
+    pub files: Vec<IndexedFile>,
+    /// Files left out or indexed without content, by relative path
+    pub skipped: Vec<(String, SkipReason)>,
+    pub total_tokens_estimate: usize,
+}
+
+impl CodebaseIndex {
+    pub fn new(root: PathBuf) -> Self {
+        Self {
+            root,
+            files: Vec::new(),
+            skipped: Vec::new(),
+            total_tokens_estimate: 0,
+        }
+    }
+
+    /// Index a codebase directory
+    pub fn index(
+        root: &Path,
+        include_extensions: Option<&[String]>,
+        exclude_dirs: &[String],
+        max_files: usize,
+    ) -> Result<Self, String> {
+        let pb = ProgressBar::new(0);
+        pb.set_style(ProgressStyle::default_bar()
+            .template("{spinner:.green} [{bar:40.cyan/blue}] {pos}/{len} fichiers indexés")
+            .unwrap()
+            .progress_chars("#>-"));
+
+        let index = Self::index_with_progress(root, include_extensions, exclude_dirs, max_files, |done, total| {
+            pb.set_length(total as u64);
+            pb.set_position(done as u64);
+        })?;
+
+        pb.finish_with_message(format!("{} fichiers indexés", index.files.len()));
+        Ok(index)
+    }
+
+    /// Index a codebase directory, reporting (indexed, total) instead of drawing a progress bar
+    pub fn index_with_progress(
+        root: &Path,
+        include_extensions: Option<&[String]>,
+        exclude_dirs: &[String],
+        max_files: usize,
+        progress: impl Fn(usize, usize),
+    ) -> Result<Self, String> {
+        let root = crate::wsl::canonicalize(root).map_err(|e| format!("Invalid path: {}", e))?;
+        
+        // Build the walker respecting .gitignore
+        let mut builder = WalkBuilder::new(&root);
+        builder.hidden(false)
+            .git_ignore(true)
+            .git_global(true)
+            .git_exclude(true);
+        
+        // Collect files first to show progress
+        let entries: Vec<PathBuf> = builder.build()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().map(|ft| ft.is_file()).unwrap_or(false))
+            .map(|e| e.into_path())
+            .collect();
+
+        Self::index_paths(root, entries, include_extensions, exclude_dirs, max_files, progress)
+    }
+
+    /// Index only the files changed on the current branch, see `branch_changes`
+    pub fn index_branch_changes(
+        root: &Path,
+        include_extensions: Option<&[String]>,
+        exclude_dirs: &[String],
+        max_files: usize,
+        progress: impl Fn(usize, usize),
+    ) -> Result<Self, String> {
+        let root = crate::wsl::canonicalize(root).map_err(|e| format!("Invalid path: {}", e))?;
+        let files = branch_changes(&root)?;
+        Self::index_paths(root, files, include_extensions, exclude_dirs, max_files, progress)
+    }
+
+    /// Read the supported files among `entries`, within the size and count limits
+    fn index_paths(
+        root: PathBuf,
+        entries: Vec<PathBuf>,
+        include_extensions: Option<&[String]>,
+        exclude_dirs: &[String],
+        max_files: usize,
+        progress: impl Fn(usize, usize),
+    ) -> Result<Self, String> {
+        let mut index = CodebaseIndex::new(root.clone());
+
+        // Add default excludes
+        let default_excludes: Vec<String> = vec![
+            "node_modules", "target", "dist", "build", ".git", "__pycache__",
+            "vendor", ".venv", "venv", ".idea", ".vscode", "coverage",
+        ].into_iter().map(|s| s.to_string()).collect();
+        
+        let mut all_excludes = default_excludes;
+        all_excludes.extend(exclude_dirs.iter().cloned());
+        let ignore = crate::project_dir::ProjectIgnore::load(&root);
+        let include_generated = crate::generated::included(&root);
+
+        let total = entries.len().min(max_files);
+        progress(0, total);
+
+        let mut file_count = 0;
+
+        for entry in entries {
+            let path = entry.as_path();
+            
+            // Check if in excluded directory
+            let path_str = path.to_string_lossy();
+            if all_excludes.iter().any(|exc| path_str.contains(exc.as_str())) || ignore.is_ignored(path, false) {
+                continue;
+            }
+
+            // Check extension
+            let ext = path.extension()
+                .and_then(|e| e.to_str())
+                .unwrap_or("")
+                .to_lowercase();
+
+            let should_include = if let Some(exts) = include_extensions {
+                exts.iter().any(|e| e.to_lowercase() == ext)
+            } else {
+                DEFAULT_EXTENSIONS.contains(&ext.as_str())
+            };
+
+            if !should_include {
+                continue;
+            }
+
+            let relative_path = path.strip_prefix(&root)
+                .unwrap_or(path)
+                .to_string_lossy()
+                .to_string();
+
+            // Lockfiles and bundles are known by name, whatever their size
+            if !include_generated && crate::generated::is_generated_name(path) {
+                index.skipped.push((relative_path, SkipReason::Generated));
+                continue;
+            }
+
+            // Check file size
+            let metadata = match fs::metadata(path) {
+                Ok(m) => m,
+                Err(_) => continue,
+            };
+
+            if metadata.len() > MAX_FILE_SIZE {
+                index.skipped.push((relative_path, SkipReason::TooLarge(metadata.len())));
+                continue;
+            }
+
+            if file_count >= max_files {
+                index.skipped.push((relative_path, SkipReason::FileLimit(max_files)));
+                continue;
+            }
+
+            // Read content
+            let content = match fs::read_to_string(path) {
+                Ok(c) => c,
+                Err(_) => {
+                    index.skipped.push((relative_path, SkipReason::Binary));
+                    continue;
+                }
+            };
+
+            if !include_generated && crate::generated::is_generated(path, &content) {
+                index.skipped.push((relative_path, SkipReason::Generated));
+                continue;
+            }
+
+            let token_estimate = count_tokens(&content);
+            index.total_tokens_estimate += token_estimate;
+
+            index.files.push(IndexedFile {
+                path: path.to_path_buf(),
+                relative_path,
+                content,
+                extension: ext,
+                size: metadata.len(),
+            });
+
+            file_count += 1;
+            progress(file_count, total);
+        }
+
+        Ok(index)
+    }
+
+    /// Index every root of a workspace (whole, or only the branch changes); with several roots,
+    /// relative paths are prefixed by the root name
+    pub fn index_workspace(
+        workspace: &Workspace,
+        scope: ContextScope,
+        include_extensions: Option<&[String]>,
+        exclude_dirs: &[String],
+        max_files: usize,
+        progress: impl Fn(usize, usize),
+    ) -> Result<Self, String> {
+        let mut index = CodebaseIndex::new(workspace.primary().to_path_buf());
+        for root in workspace.roots() {
+            let root_index = match scope {
+                ContextScope::Full => Self::index_with_progress(&root.path, include_extensions, exclude_dirs, max_files, &progress)?,
+                ContextScope::Diff => Self::index_branch_changes(&root.path, include_extensions, exclude_dirs, max_files, &progress)?,
+            };
+            index.total_tokens_estimate += root_index.total_tokens_estimate;
+            for (path, reason) in root_index.skipped {
+                let path = if workspace.is_multi_root() { format!("{}/{}", root.name, path) } else { path };
+                index.skipped.push((path, reason));
+            }
+            for mut file in root_index.files {
+                if workspace.is_multi_root() {
+                    file.relative_path = format!("{}/{}", root.name, file.relative_path);
+                }
+                index.files.push(file);
+            }
+        }
+        Ok(index)
+    }
+
+    /// Lockfiles and minified code: listed in the prompt, content left out
+    pub fn generated_files(&self) -> impl Iterator<Item = &str> {
+        self.skipped.iter().filter(|(_, reason)| *reason == SkipReason::Generated).map(|(path, _)| path.as_str())
+    }
+
+    /// Get a summary of the indexed codebase
+    pub fn summary(&self) -> String {
+        let mut by_ext: std::collections::HashMap<&str, usize> = std::collections::HashMap::new();
+        
+        for file in &self.files {
+            *by_ext.entry(&file.extension).or_insert(0) += 1;
+        }
+
+        let mut summary = format!("📁 Codebase: {}\n", self.root.display());
+        summary.push_str(&format!("📄 {} fichiers indexés\n", self.files.len()));
+        let generated = self.generated_files().count();
+        if generated > 0 {
+            summary.push_str(&format!("🧱 {} fichiers générés (lockfiles, code minifié) sans contenu\n", generated));
+        }
+        let left_out = self.skipped.len() - generated;
+        if left_out > 0 {
+            summary.push_str(&format!("⏭  {} fichiers non indexés (taille, binaire, limite de fichiers)\n", left_out));
+        }
+        summary.push_str(&format!("🔤 ~{} tokens estimés\n\n", self.total_tokens_estimate));
+        
+        summary.push_str("Par type:\n");
+        let mut sorted: Vec<_> = by_ext.iter().collect();
+        sorted.sort_by(|a, b| b.1.cmp(a.1));
+        
+        for (ext, count) in sorted.iter().take(10) {
+            summary.push_str(&format!("  .{}: {}\n", ext, count));
+        }
+
+        summary
+    }
+
+    /// Build context for AI with file contents (chunked if needed)
+    pub fn build_context(&self, max_tokens: usize) -> Vec<String> {
+        let mut chunks = Vec::new();
+        let mut current_chunk = String::new();
+        let mut current_tokens = 0;
+
+        for file in &self.files {
+            let file_header = format!("\n--- {} ---\n", file.relative_path);
+            let file_tokens = count_tokens(&file_header) + count_tokens(&file.content);
+
+            if current_tokens + file_tokens > max_tokens && !current_chunk.is_empty() {
+                chunks.push(current_chunk);
+                current_chunk = String::new();
+                current_tokens = 0;
+            }
+
+            current_chunk.push_str(&file_header);
+            current_chunk.push_str(&file.content);
+            current_tokens += file_tokens;
+        }
+
+        if !current_chunk.is_empty() {
+            chunks.push(current_chunk);
+        }
+
+        chunks
+    }
+}
+
+/// Run git in `root` and return its output lines
+fn git_lines(root: &Path, args: &[&str]) -> Result<Vec<String>, String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(root)
+        .output()
+        .map_err(|e| format!("Cannot run git: {}", e))?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|l| !l.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// Commit the current branch started from: merge base with main (or master, or their remote copies)
+fn diff_base(root: &Path) -> Result<String, String> {
+    git_lines(root, &["rev-parse", "--is-inside-work-tree"])
+        .map_err(|_| format!("{} is not a git repository", root.display()))?;
+    for branch in ["main", "master", "origin/main", "origin/master"] {
+        if git_lines(root, &["rev-parse", "--verify", "--quiet", branch]).is_ok() {
+            let base = git_lines(root, &["merge-base", "HEAD", branch])?;
+            return base.into_iter().next().ok_or_else(|| format!("No common commit with {}", branch));
+        }
+    }
+    Err("No main or master branch to compare with".to_string())
+}
+
+/// Files changed on the current branch vs main: committed, staged or not, and untracked ones.
+/// Deleted files are left out
+pub fn branch_changes(root: &Path) -> Result<Vec<PathBuf>, String> {
+    let base = diff_base(root)?;
+    let mut files = git_lines(root, &["diff", "--name-only", "--relative", &base])?;
+    for untracked in git_lines(root, &["ls-files", "--others", "--exclude-standard"])? {
+        if !files.contains(&untracked) {
+            files.push(untracked);
+        }
+    }
+    Ok(files.into_iter()
+        .map(|file| root.join(file))
+        .filter(|path| path.is_file())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_skipped_files_keep_their_reason() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("data")).unwrap();
+        fs::write(root.join("a.rs"), "fn a() {}").unwrap();
+        fs::write(root.join("b.rs"), "fn b() {}").unwrap();
+        fs::write(root.join("data/big.json"), "x".repeat(MAX_FILE_SIZE as usize + 1)).unwrap();
+        fs::write(root.join("data/blob.rs"), [0xff, 0xfe, 0x00]).unwrap();
+        fs::write(root.join("package-lock.json"), "{}").unwrap();
+
+        let index = CodebaseIndex::index_with_progress(root, None, &[], 1, |_, _| {}).unwrap();
+        assert_eq!(index.files.len(), 1);
+        let reason = |path: &str| index.skipped.iter().find(|(p, _)| p == path).map(|(_, r)| r.clone());
+        assert_eq!(reason("data/big.json"), Some(SkipReason::TooLarge(MAX_FILE_SIZE + 1)));
+        assert_eq!(reason("package-lock.json"), Some(SkipReason::Generated));
+        // a.rs, b.rs and blob.rs: one indexed, the others past the limit (or binary, first in the walk)
+        assert_eq!(index.skipped.len(), 4);
+        assert!(index.skipped.contains(&("b.rs".to_string(), SkipReason::FileLimit(1))) || index.skipped.contains(&("a.rs".to_string(), SkipReason::FileLimit(1))));
+
+        let skipped = vec![("data/big.json".to_string(), SkipReason::TooLarge(150_000))];
+        let mentioned = mentioned_skips("Pourquoi @big.json n'est pas lu ? data/big.json, big", &skipped);
+        assert_eq!(mentioned.len(), 1);
+        assert_eq!(mentioned[0].1.describe(), "trop volumineux (150 Ko, limite 100 Ko)");
+        assert!(mentioned_skips("big data", &skipped).is_empty());
+    }
+
+    #[test]
+    fn test_branch_changes_against_main() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        assert!(diff_base(root).unwrap_err().contains("is not a git repository"));
+
+        let run = |args: &[&str]| git_lines(root, args).unwrap();
+        let commit = |message: &str| run(&["-c", "user.name=t", "-c", "user.email=t@t", "commit", "-qam", message]);
+        run(&["init", "-q"]);
+        fs::write(root.join("a.rs"), "fn a() {}\n").unwrap();
+        fs::write(root.join("b.rs"), "fn b() {}\n").unwrap();
+        run(&["add", "."]);
+        commit("init");
+        run(&["branch", "-M", "dev"]);
+        assert_eq!(diff_base(root).unwrap_err(), "No main or master branch to compare with");
+
+        run(&["branch", "-M", "main"]);
+        let base = run(&["rev-parse", "HEAD"]);
+        run(&["checkout", "-qb", "feature"]);
+        fs::write(root.join("a.rs"), "fn a2() {}\n").unwrap();
+        run(&["rm", "-q", "b.rs"]);
+        commit("work");
+        fs::write(root.join("c.rs"), "fn c() {}\n").unwrap();
+        assert_eq!(vec![diff_base(root).unwrap()], base);
+
+        let mut changed = branch_changes(root).unwrap();
+        changed.sort();
+        // The deleted b.rs is left out, the untracked c.rs is included
+        assert_eq!(changed, vec![root.join("a.rs"), root.join("c.rs")]);
+    }
+}
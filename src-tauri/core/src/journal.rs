@@ -0,0 +1,186 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::differ::{ChangeSet, DiffStats, FileChange, FileDeletion, NewFile};
+
+/// File of a change set, in its directory under `.codestral/backups/`
+const JOURNAL_FILE: &str = "journal.json";
+
+/// File touched by an applied change set
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JournalFile {
+    /// Relative to the project root (absolute outside of it)
+    pub path: String,
+    /// Content before the change set (None = created by it)
+    pub before: Option<String>,
+    /// Content the change set left (None = deleted)
+    pub after: Option<String>,
+}
+
+/// Applied change set, `.codestral/backups/<timestamp>/journal.json`: the original content of
+/// every file it touched, so `/undo` and `companion-chat rollback` can put them back
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChangeJournal {
+    /// Directory name, e.g. 20260304-101530-123
+    pub id: String,
+    pub files: Vec<JournalFile>,
+    /// Rolled back already
+    #[serde(default)]
+    pub undone: bool,
+}
+
+fn backups_dir(project_root: &Path) -> PathBuf {
+    project_root.join(crate::project_dir::DIR_NAME).join("backups")
+}
+
+impl Default for ChangeJournal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ChangeJournal {
+    pub fn new() -> Self {
+        Self {
+            id: chrono::Local::now().format("%Y%m%d-%H%M%S-%3f").to_string(),
+            files: Vec::new(),
+            undone: false,
+        }
+    }
+
+    /// Record a written or deleted file; only its first snapshot is kept as the original
+    pub fn record(&mut self, project_root: &Path, path: &Path, before: Option<String>, after: Option<String>) {
+        let relative = path.strip_prefix(project_root).unwrap_or(path).to_string_lossy().to_string();
+        match self.files.iter_mut().find(|file| file.path == relative) {
+            Some(file) => file.after = after,
+            None => self.files.push(JournalFile { path: relative, before, after }),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Full paths of the files
+    pub fn paths(&self, project_root: &Path) -> Vec<PathBuf> {
+        self.files.iter().map(|file| project_root.join(&file.path)).collect()
+    }
+
+    /// Files, lines and languages of what was applied, as `ChangeSet::stats` counts them
+    pub fn stats(&self) -> DiffStats {
+        let mut changes = ChangeSet::default();
+        for file in &self.files {
+            let (path, description) = (file.path.clone(), String::new());
+            match (&file.before, &file.after) {
+                (Some(original), Some(modified)) => changes.modifications.push(FileChange { path, original: original.clone(), modified: modified.clone(), description }),
+                (None, Some(content)) => changes.new_files.push(NewFile { path, content: content.clone(), description }),
+                (Some(content), None) => changes.deletions.push(FileDeletion { path, content: content.clone(), description }),
+                (None, None) => {}
+            }
+        }
+        changes.stats()
+    }
+
+    /// Write the journal; nothing for a change set that touched no file
+    pub fn save(&self, project_root: &Path) -> Result<(), String> {
+        if self.files.is_empty() {
+            return Ok(());
+        }
+        crate::project_dir::ensure(project_root)?;
+        let dir = backups_dir(project_root).join(&self.id);
+        fs::create_dir_all(&dir).map_err(|e| format!("Failed to create backup directory: {}", e))?;
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        fs::write(dir.join(JOURNAL_FILE), json).map_err(|e| format!("Cannot save change journal: {}", e))
+    }
+
+    /// Latest change set not rolled back yet
+    pub fn last(project_root: &Path) -> Option<Self> {
+        let mut ids: Vec<String> = fs::read_dir(backups_dir(project_root)).ok()?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().join(JOURNAL_FILE).is_file())
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .collect();
+        ids.sort();
+        ids.iter().rev()
+            .filter_map(|id| fs::read_to_string(backups_dir(project_root).join(id).join(JOURNAL_FILE)).ok())
+            .filter_map(|content| serde_json::from_str::<Self>(&content).ok())
+            .find(|journal| !journal.undone)
+    }
+
+    /// Put the files back as they were before the change set, and mark it rolled back.
+    /// Refused when a file was edited since, so later work is not lost. Returns the restored paths
+    pub fn rollback(&mut self, project_root: &Path) -> Result<Vec<PathBuf>, String> {
+        let paths = self.paths(project_root);
+        let edited: Vec<&str> = self.files.iter().zip(&paths)
+            .filter(|(file, path)| fs::read_to_string(path).ok() != file.after)
+            .map(|(file, _)| file.path.as_str())
+            .collect();
+        if !edited.is_empty() {
+            return Err(format!("Changed since the change set was applied: {}", edited.join(", ")));
+        }
+
+        for (file, path) in self.files.iter().zip(&paths) {
+            match &file.before {
+                Some(content) => {
+                    if let Some(parent) = path.parent() {
+                        fs::create_dir_all(parent).map_err(|e| format!("Cannot create {}: {}", parent.display(), e))?;
+                    }
+                    fs::write(path, content).map_err(|e| format!("Failed to restore {}: {}", file.path, e))?;
+                }
+                None if path.exists() => {
+                    fs::remove_file(path).map_err(|e| format!("Failed to remove {}: {}", file.path, e))?;
+                }
+                None => {}
+            }
+        }
+        self.undone = true;
+        self.save(project_root)?;
+        Ok(paths)
+    }
+
+    /// What a rollback restores, e.g. "2 fichier(s) (src/lib.rs, src/new.rs)"
+    pub fn summary(&self) -> String {
+        let paths: Vec<&str> = self.files.iter().map(|file| file.path.as_str()).collect();
+        format!("{} fichier(s) ({})", paths.len(), paths.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rollback_restores_the_last_change_set() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let edited = root.join("src/lib.rs");
+        let created = root.join("new.rs");
+        let deleted = root.join("old.rs");
+        fs::create_dir_all(edited.parent().unwrap()).unwrap();
+
+        let mut first = ChangeJournal { id: "20260304-101530-001".to_string(), ..ChangeJournal::new() };
+        first.record(root, &edited, None, Some("v1".to_string()));
+        first.save(root).unwrap();
+        fs::write(&edited, "v2").unwrap();
+        fs::write(&created, "new").unwrap();
+        let mut second = ChangeJournal { id: "20260304-101530-002".to_string(), ..ChangeJournal::new() };
+        second.record(root, &edited, Some("v1".to_string()), Some("v2".to_string()));
+        second.record(root, &created, None, Some("new".to_string()));
+        second.record(root, &deleted, Some("old".to_string()), None);
+        second.save(root).unwrap();
+        ChangeJournal::new().save(root).unwrap();
+
+        let mut last = ChangeJournal::last(root).unwrap();
+        assert_eq!(last, second);
+        assert_eq!(last.summary(), "3 fichier(s) (src/lib.rs, new.rs, old.rs)");
+
+        fs::write(&created, "edited by hand").unwrap();
+        assert_eq!(last.rollback(root).unwrap_err(), "Changed since the change set was applied: new.rs");
+        fs::write(&created, "new").unwrap();
+        last.rollback(root).unwrap();
+        assert_eq!(fs::read_to_string(&edited).unwrap(), "v1");
+        assert_eq!(fs::read_to_string(&deleted).unwrap(), "old");
+        assert!(!created.exists());
+        assert_eq!(ChangeJournal::last(root).unwrap().id, first.id);
+    }
+}
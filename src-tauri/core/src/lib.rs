@@ -0,0 +1,31 @@
+//! Core of Codestral Companion, shared by the GUI, the TUI and the CLI: codebase indexing,
+//! diffs and change sets, tools, the Mistral client and the project/conversation storage
+
+pub mod error;
+pub mod settings;
+pub mod mode;
+pub mod mistral_client;
+pub mod ollama_client;
+pub mod backend;
+pub mod health;
+pub mod rate_limiter;
+pub mod context_guard;
+pub mod indexer;
+pub mod generated;
+pub mod persistent_index;
+pub mod index_key;
+pub mod project_dir;
+pub mod symbols;
+pub mod wsl;
+pub mod workspace;
+pub mod differ;
+pub mod unified_diff;
+pub mod journal;
+pub mod chat_storage;
+pub mod recent_projects;
+pub mod docs_lookup;
+pub mod rename;
+pub mod auto_commit;
+pub mod tools;
+pub mod custom_tools;
+pub mod audit;
@@ -0,0 +1,848 @@
+use serde::{Deserialize, Serialize};
+use reqwest::{Client, StatusCode};
+use crate::backend::{BackendFuture, LlmBackend};
+use crate::rate_limiter;
+use crate::context_guard::count_tokens;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+use anyhow::{Result, anyhow};
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum ApiProvider {
+    Codestral, // codestral.mistral.ai
+    MistralAi, // api.mistral.ai
+    Ollama,    // local server, see OllamaSettings
+    /// Any OpenAI-compatible endpoint (vLLM, LM Studio, OpenRouter...), e.g.
+    /// `"provider": { "Custom": { "base_url": "http://localhost:8000/v1", "model": "qwen2.5-coder" } }`
+    Custom { base_url: String, model: String },
+}
+
+impl Default for ApiProvider {
+    fn default() -> Self {
+        ApiProvider::MistralAi
+    }
+}
+
+impl ApiProvider {
+    /// Name of the provider in settings.json, e.g. for `rate_limits`
+    pub fn name(&self) -> &'static str {
+        match self {
+            ApiProvider::Codestral => "Codestral",
+            ApiProvider::MistralAi => "MistralAi",
+            ApiProvider::Ollama => "Ollama",
+            ApiProvider::Custom { .. } => "Custom",
+        }
+    }
+
+    /// Host shown in messages, e.g. "api.mistral.ai" or "localhost:11434"
+    pub fn host(&self) -> String {
+        match self {
+            ApiProvider::Codestral => "codestral.mistral.ai".to_string(),
+            ApiProvider::MistralAi => "api.mistral.ai".to_string(),
+            ApiProvider::Ollama => OllamaSettings::load().host(),
+            ApiProvider::Custom { base_url, .. } => url_host(base_url),
+        }
+    }
+
+    /// Local servers take requests without a key; a custom endpoint gets one only if set
+    pub fn needs_api_key(&self) -> bool {
+        matches!(self, ApiProvider::Codestral | ApiProvider::MistralAi)
+    }
+
+    /// URL probed by `check_connectivity`
+    fn probe_url(&self) -> String {
+        match self {
+            ApiProvider::Ollama => OllamaSettings::load().base_url,
+            ApiProvider::Custom { base_url, .. } => base_url.clone(),
+            provider => format!("https://{}", provider.host()),
+        }
+    }
+}
+
+impl fmt::Display for ApiProvider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiProvider::Custom { base_url, .. } => write!(f, "Custom ({})", base_url),
+            provider => f.write_str(provider.name()),
+        }
+    }
+}
+
+/// Host of a base URL, with its port unless it is the scheme's default
+fn url_host(base_url: &str) -> String {
+    match reqwest::Url::parse(base_url) {
+        Ok(url) => match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_string(),
+            (None, _) => base_url.to_string(),
+        },
+        Err(_) => base_url.to_string(),
+    }
+}
+
+/// Local Ollama server, read from the `ollama` section of settings.json, e.g.
+/// `"ollama": { "base_url": "http://192.168.1.20:11434", "model": "qwen2.5-coder:14b" }`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OllamaSettings {
+    pub base_url: String,
+    /// Model used when neither the project nor `config.model` sets one
+    pub model: String,
+    /// Context size requested from the server, which truncates prompts to 2048 tokens otherwise
+    pub context_window: u64,
+    /// Local models on a CPU can take minutes to answer
+    pub timeout_secs: u64,
+}
+
+impl Default for OllamaSettings {
+    fn default() -> Self {
+        Self {
+            base_url: "http://localhost:11434".to_string(),
+            model: "codestral".to_string(),
+            context_window: 32_768,
+            timeout_secs: 300,
+        }
+    }
+}
+
+impl OllamaSettings {
+    pub fn load() -> Self {
+        let Ok(path) = crate::settings::settings_file() else {
+            return Self::default();
+        };
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+            .and_then(|json| json.get("config")?.get("ollama").cloned())
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default()
+    }
+
+    /// `base_url` followed by an API path, e.g. "/api/chat"
+    pub(crate) fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url.trim_end_matches('/'), path)
+    }
+
+    pub fn host(&self) -> String {
+        url_host(&self.base_url)
+    }
+}
+
+/// Quick check that the API host answers, before any request. The probe goes through reqwest
+/// like the requests, so HTTPS_PROXY applies and name resolution is bounded by `timeout`;
+/// any HTTP answer, even an error status, means the network is up
+pub fn check_connectivity(provider: &ApiProvider, timeout: Duration) -> Result<(), String> {
+    let (url, host) = (provider.probe_url(), provider.host());
+    // Callers may be inside a runtime already: probe on a thread of its own
+    std::thread::spawn(move || {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| e.to_string())?;
+        let probe = runtime.block_on(async {
+            Client::builder()
+                .connect_timeout(timeout)
+                .timeout(timeout)
+                .build()?
+                .head(&url)
+                .send()
+                .await
+        });
+        match probe {
+            Err(e) if e.is_connect() => Err(format!("No network: cannot connect to {} ({})", host, e)),
+            // Slow answers and TLS quirks are left to the real request
+            _ => Ok(()),
+        }
+    })
+    .join()
+    .map_err(|_| "Connectivity check interrupted".to_string())?
+}
+
+/// Error status returned by the API
+#[derive(Debug)]
+pub struct ApiError {
+    pub status: StatusCode,
+    pub message: String,
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "API Error ({}): {}", self.status, self.message)
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+/// Retries of a failed request, read from the `retry` section of settings.json, e.g.
+/// `"retry": { "attempts": 6, "initial_backoff_secs": 2, "max_backoff_secs": 60 }`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Total attempts, the first one included
+    pub attempts: u32,
+    pub initial_backoff_secs: u64,
+    pub max_backoff_secs: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self { attempts: 4, initial_backoff_secs: 1, max_backoff_secs: 30 }
+    }
+}
+
+impl RetryPolicy {
+    pub fn load() -> Self {
+        let Ok(path) = crate::settings::settings_file() else {
+            return Self::default();
+        };
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+            .and_then(|json| json.get("config")?.get("retry").cloned())
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default()
+    }
+
+    /// Wait before the given attempt (2 = first retry): exponential backoff, capped
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = 1u64 << attempt.saturating_sub(2).min(20);
+        Duration::from_secs(self.initial_backoff_secs.saturating_mul(factor).min(self.max_backoff_secs))
+    }
+}
+
+/// Why a request is worth retrying, shown while waiting; None when retrying cannot help
+/// (invalid key, bad request...)
+pub fn retry_reason(error: &anyhow::Error) -> Option<&'static str> {
+    crate::error::Error::from_request(error).retry_reason()
+}
+
+/// Retry announced before waiting, e.g. "nouvel essai 2/4 dans 2s (limite de débit)"
+#[derive(Debug, Clone, Copy)]
+pub struct RetryNotice {
+    /// Attempt about to be made (2 = first retry)
+    pub attempt: u32,
+    pub attempts: u32,
+    pub delay: Duration,
+    pub reason: &'static str,
+}
+
+impl fmt::Display for RetryNotice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "nouvel essai {}/{} dans {}s ({})", self.attempt, self.attempts, self.delay.as_secs(), self.reason)
+    }
+}
+
+/// HTTP side shared by the backends: the key, the provider's rate limiter and readable errors
+#[derive(Clone)]
+pub(crate) struct Http {
+    client: Client,
+    api_key: String,
+    provider: ApiProvider,
+    /// Host shown in errors
+    host: String,
+    /// Rate limit and quota headers of the last response, shared by the clones
+    rate_limits: Arc<std::sync::Mutex<Vec<(String, String)>>>,
+}
+
+impl Http {
+    pub(crate) fn new(api_key: String, provider: ApiProvider, host: String, timeout_secs: u64) -> Self {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(timeout_secs))
+            .build()
+            .unwrap_or_default();
+        Self { client, api_key, provider, host, rate_limits: Arc::default() }
+    }
+
+    /// Rate limit and quota headers of the last response, e.g. `x-ratelimit-remaining-tokens-minute`
+    pub(crate) fn rate_limits(&self) -> Vec<(String, String)> {
+        self.rate_limits.lock().map(|limits| limits.clone()).unwrap_or_default()
+    }
+
+    fn remember_rate_limits(&self, response: &reqwest::Response) {
+        let limits = rate_limit_headers(response.headers());
+        if let (false, Ok(mut last)) = (limits.is_empty(), self.rate_limits.lock()) {
+            *last = limits;
+        }
+    }
+
+    /// Bearer header when there is a key; a local server may sit behind a proxy that wants one
+    fn authorize(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        if self.api_key.is_empty() && !self.provider.needs_api_key() {
+            return request;
+        }
+        request.header("Authorization", format!("Bearer {}", self.api_key))
+    }
+
+    pub(crate) async fn get(&self, url: &str) -> Result<reqwest::Response> {
+        let response = self.authorize(self.client.get(url))
+            .send()
+            .await?;
+        self.remember_rate_limits(&response);
+        if !response.status().is_success() {
+            let status = response.status();
+            let message = response.text().await?;
+            return Err(ApiError { status, message }.into());
+        }
+        Ok(response)
+    }
+
+    /// POST a request through the provider's rate limiter, with readable network errors
+    pub(crate) async fn post(&self, url: &str, request_body: &serde_json::Value, estimated_tokens: u64) -> Result<reqwest::Response> {
+        let limiter = rate_limiter::limiter_for(&self.provider);
+        limiter.acquire(estimated_tokens).await;
+
+        let response = self.authorize(self.client.post(url))
+            .header("Content-Type", "application/json")
+            .json(&request_body)
+            .send()
+            .await
+            .map_err(|e| {
+                // Keep the reqwest error underneath for retry_reason, with a readable message on top
+                let context = if e.is_connect() && self.provider == ApiProvider::Ollama {
+                    format!("Cannot connect to the Ollama server at {} (is `ollama serve` running?)", self.host)
+                } else if e.is_connect() {
+                    format!("No network: cannot connect to {}", self.host)
+                } else if e.is_timeout() {
+                    format!("No answer from {} within the timeout", self.host)
+                } else {
+                    return anyhow::Error::new(e);
+                };
+                anyhow::Error::new(e).context(context)
+            })?;
+        self.remember_rate_limits(&response);
+
+        if response.status() == StatusCode::TOO_MANY_REQUESTS {
+            // Hold back every client of this provider, not just the caller
+            let retry_after = response.headers()
+                .get("retry-after")
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.trim().parse::<u64>().ok())
+                .unwrap_or(10);
+            limiter.penalize(Duration::from_secs(retry_after));
+        }
+
+        if !response.status().is_success() {
+             let status = response.status();
+             let message = response.text().await?;
+             return Err(ApiError { status, message }.into());
+        }
+        Ok(response)
+    }
+}
+
+/// Headers of a response about rate limits and quotas (`x-ratelimit-*`, `ratelimitbysize-*`...), sorted
+pub(crate) fn rate_limit_headers(headers: &reqwest::header::HeaderMap) -> Vec<(String, String)> {
+    let mut limits: Vec<(String, String)> = headers.iter()
+        .filter(|(name, _)| name.as_str().contains("ratelimit") || name.as_str() == "retry-after")
+        .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+        .collect();
+    limits.sort();
+    limits
+}
+
+/// Read a streamed answer line by line; `delta` gives the text of each line, if any
+pub(crate) async fn read_stream(
+    mut response: reqwest::Response,
+    delta: impl Fn(&str) -> Result<Option<String>>,
+    mut on_delta: impl FnMut(&str),
+) -> Result<String> {
+    let mut answer = String::new();
+    let mut pending: Vec<u8> = Vec::new();
+    let mut handle = |line: &[u8], answer: &mut String| -> Result<()> {
+        if let Some(text) = delta(&String::from_utf8_lossy(line))? {
+            on_delta(&text);
+            answer.push_str(&text);
+        }
+        Ok(())
+    };
+    while let Some(chunk) = response.chunk().await? {
+        pending.extend_from_slice(&chunk);
+        // Only whole lines: a multi-byte character may be split between chunks
+        while let Some(end) = pending.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = pending.drain(..=end).collect();
+            handle(&line, &mut answer)?;
+        }
+    }
+    handle(&pending, &mut answer)?;
+    Ok(answer)
+}
+
+/// Client of the OpenAI-style chat completions API of Mistral AI and Codestral, also spoken by
+/// custom endpoints (vLLM, LM Studio, OpenRouter...) and by Ollama under `/v1`
+#[derive(Clone)]
+pub struct MistralClient {
+    http: Http,
+    provider: ApiProvider,
+    /// Model of the provider when none is set
+    default_model: String,
+    model: Option<String>,
+    temperature: Option<f32>,
+}
+
+#[derive(Serialize)]
+struct ChatRequest {
+    model: String,
+    messages: Vec<Message>,
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+}
+
+/// Body of `POST /v1/embeddings`, and of Ollama's `POST /api/embed`: one vector per input
+#[derive(Serialize)]
+pub(crate) struct EmbeddingRequest<'a> {
+    pub(crate) model: &'a str,
+    pub(crate) input: &'a [String],
+}
+
+#[derive(Deserialize, Debug)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingData>,
+}
+
+#[derive(Deserialize, Debug)]
+struct EmbeddingData {
+    embedding: Vec<f32>,
+    #[serde(default)]
+    index: usize,
+}
+
+/// Body of `POST /v1/fim/completions`: the model writes the code between `prompt` and `suffix`
+#[derive(Serialize)]
+struct FimRequest {
+    model: String,
+    prompt: String,
+    suffix: String,
+    max_tokens: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+}
+
+/// Author of a message, serialized as the API expects: "system", "user" or "assistant"
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase", from = "String")]
+pub enum Role {
+    System,
+    User,
+    Assistant,
+}
+
+impl Role {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Role::System => "system",
+            Role::User => "user",
+            Role::Assistant => "assistant",
+        }
+    }
+}
+
+/// Roles written by the different frontends and older saved chats ("ai", "bot", "tool"...)
+impl From<String> for Role {
+    fn from(role: String) -> Self {
+        match role.to_lowercase().as_str() {
+            "assistant" | "ai" | "bot" | "model" => Role::Assistant,
+            "system" => Role::System,
+            // Tool results are fed back to the model as user messages
+            _ => Role::User,
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Message {
+    pub role: Role,
+    pub content: String,
+}
+
+impl Message {
+    pub fn new(role: Role, content: impl Into<String>) -> Self {
+        Self { role, content: content.into() }
+    }
+
+    pub fn system(content: impl Into<String>) -> Self {
+        Self::new(Role::System, content)
+    }
+
+    pub fn user(content: impl Into<String>) -> Self {
+        Self::new(Role::User, content)
+    }
+
+    pub fn assistant(content: impl Into<String>) -> Self {
+        Self::new(Role::Assistant, content)
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct ChatResponse {
+    choices: Vec<Choice>,
+    #[serde(default)]
+    usage: Option<Usage>,
+}
+
+/// Token counts reported by the API for one request
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default)]
+pub struct Usage {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub total_tokens: u64,
+}
+
+#[derive(Deserialize, Debug)]
+struct Choice {
+    message: Message,
+}
+
+/// One event of a streamed chat completion: the next piece of the answer in `delta`
+#[derive(Deserialize, Debug)]
+struct StreamChunk {
+    choices: Vec<StreamChoice>,
+}
+
+#[derive(Deserialize, Debug)]
+struct StreamChoice {
+    #[serde(default)]
+    delta: StreamDelta,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct StreamDelta {
+    #[serde(default)]
+    content: Option<String>,
+}
+
+/// Text of one line of a streamed answer, sent as `data: {json}` server-sent events.
+/// None for blank lines, comments (keep-alives) and the final `[DONE]`
+fn stream_delta(line: &str) -> Result<Option<String>> {
+    let line = line.trim();
+    let Some(data) = line.strip_prefix("data:").map(str::trim) else {
+        return Ok(None);
+    };
+    if data == "[DONE]" {
+        return Ok(None);
+    }
+    let chunk: StreamChunk = serde_json::from_str(data)?;
+    Ok(chunk.choices.into_iter().next().and_then(|choice| choice.delta.content))
+}
+
+/// Response of `GET /v1/models`
+#[derive(Deserialize, Debug)]
+struct ModelList {
+    data: Vec<ModelInfo>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ModelInfo {
+    id: String,
+}
+
+impl MistralClient {
+    pub fn new(api_key: String, provider: ApiProvider) -> Self {
+        let (default_model, timeout) = match &provider {
+            ApiProvider::Codestral => ("codestral-latest".to_string(), 60),
+            ApiProvider::MistralAi => ("mistral-large-latest".to_string(), 60),
+            ApiProvider::Ollama => {
+                let ollama = OllamaSettings::load();
+                (ollama.model, ollama.timeout_secs)
+            }
+            // Self-hosted servers (vLLM, LM Studio) can be as slow as Ollama
+            ApiProvider::Custom { model, .. } => (model.clone(), 300),
+        };
+
+        Self {
+            http: Http::new(api_key, provider.clone(), provider.host(), timeout),
+            provider,
+            default_model,
+            model: None,
+            temperature: None,
+        }
+    }
+
+    /// Override the default model of the provider
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    /// Sampling temperature (provider default when unset)
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    pub fn provider(&self) -> &ApiProvider {
+        &self.provider
+    }
+
+    /// URL of an API path, e.g. "https://api.mistral.ai/v1/models" for "/models"
+    fn api_url(&self, path: &str) -> String {
+        match &self.provider {
+            ApiProvider::Custom { base_url, .. } => format!("{}{}", base_url.trim_end_matches('/'), path),
+            ApiProvider::Ollama => OllamaSettings::load().url(&format!("/v1{}", path)),
+            provider => format!("https://{}/v1{}", provider.host(), path),
+        }
+    }
+
+    fn get_base_url(&self) -> String {
+        self.api_url("/chat/completions")
+    }
+
+    /// Model used for requests (the override, or the provider's default)
+    pub fn get_model(&self) -> &str {
+        self.model.as_deref().unwrap_or(&self.default_model)
+    }
+
+    /// Context window of the model, in tokens
+    pub fn context_window(&self) -> u64 {
+        if self.get_model().starts_with("codestral") {
+            256_000
+        } else {
+            128_000
+        }
+    }
+
+    /// Models available with the key, as listed by the provider
+    pub async fn list_models(&self) -> Result<Vec<String>> {
+        let response = self.http.get(&self.api_url("/models")).await?;
+        let list: ModelList = response.json().await?;
+        let mut models: Vec<String> = list.data.into_iter().map(|model| model.id).collect();
+        models.sort();
+        models.dedup();
+        Ok(models)
+    }
+
+    /// Like `chat`, with the answer streamed: `on_delta` receives each piece as it arrives
+    pub async fn chat_stream(&self, messages: Vec<Message>, on_delta: impl FnMut(&str)) -> Result<String> {
+        let url = self.get_base_url();
+        let estimated_tokens = messages.iter().map(|m| count_tokens(&m.content) as u64).sum::<u64>();
+        let request_body = self.chat_request(messages, true)?;
+
+        let response = self.http.post(&url, &request_body, estimated_tokens).await?;
+        read_stream(response, stream_delta, on_delta).await
+    }
+
+    pub async fn chat(&self, messages: Vec<Message>) -> Result<String> {
+        self.chat_with_usage(messages).await.map(|(content, _)| content)
+    }
+
+    /// Like `chat`, with the token usage reported by the API
+    pub async fn chat_with_usage(&self, messages: Vec<Message>) -> Result<(String, Option<Usage>)> {
+        let url = self.get_base_url();
+
+        // Same count as the TUI token counter
+        let estimated_tokens = messages.iter().map(|m| count_tokens(&m.content) as u64).sum::<u64>();
+
+        let request_body = self.chat_request(messages, false)?;
+
+        let response = self.http.post(&url, &request_body, estimated_tokens).await?;
+
+        let chat_response: ChatResponse = response.json().await?;
+
+        if let Some(choice) = chat_response.choices.first() {
+            Ok((choice.message.content.clone(), chat_response.usage))
+        } else {
+            Err(anyhow!("No response content found"))
+        }
+    }
+
+    /// Fill-in-the-middle: the code between `prefix` and `suffix`, e.g. at an editor's cursor.
+    /// Codestral models only
+    pub async fn fim(&self, prefix: &str, suffix: &str, max_tokens: u32) -> Result<String> {
+        let estimated_tokens = (count_tokens(prefix) + count_tokens(suffix)) as u64;
+        let request_body = serde_json::to_value(FimRequest {
+            model: self.fim_model().to_string(),
+            prompt: prefix.to_string(),
+            suffix: suffix.to_string(),
+            max_tokens,
+            temperature: self.temperature,
+        })?;
+
+        let response = self.http.post(&self.api_url("/fim/completions"), &request_body, estimated_tokens).await?;
+
+        let chat_response: ChatResponse = response.json().await?;
+        chat_response.choices.into_iter()
+            .next()
+            .map(|choice| choice.message.content)
+            .ok_or_else(|| anyhow!("No response content found"))
+    }
+
+    /// Embedding model when settings.json names none: Codestral's, for code
+    pub fn default_embedding_model(&self) -> &'static str {
+        "codestral-embed"
+    }
+
+    /// Vectors of the inputs with an embedding model, in the order of the inputs
+    pub async fn embed(&self, model: &str, inputs: &[String]) -> Result<Vec<Vec<f32>>> {
+        let estimated_tokens = inputs.iter().map(|input| count_tokens(input) as u64).sum();
+        let request_body = serde_json::to_value(EmbeddingRequest { model, input: inputs })?;
+
+        let response = self.http.post(&self.api_url("/embeddings"), &request_body, estimated_tokens).await?;
+
+        let mut data = response.json::<EmbeddingResponse>().await?.data;
+        data.sort_by_key(|item| item.index);
+        let vectors: Vec<Vec<f32>> = data.into_iter().map(|item| item.embedding).collect();
+        if vectors.len() != inputs.len() {
+            return Err(anyhow!("Expected {} embeddings, got {}", inputs.len(), vectors.len()));
+        }
+        Ok(vectors)
+    }
+
+    fn chat_request(&self, messages: Vec<Message>, stream: bool) -> Result<serde_json::Value> {
+        Ok(serde_json::to_value(ChatRequest {
+            model: self.get_model().to_string(),
+            messages,
+            stream,
+            temperature: self.temperature,
+        })?)
+    }
+
+    /// Model of FIM requests: the endpoint only serves Codestral, so Mistral AI's chat models fall back to it
+    fn fim_model(&self) -> &str {
+        match (&self.provider, self.get_model()) {
+            (ApiProvider::Codestral | ApiProvider::MistralAi, model) if !model.starts_with("codestral") => "codestral-latest",
+            (_, model) => model,
+        }
+    }
+}
+
+impl LlmBackend for MistralClient {
+    fn chat_with_usage(&self, messages: Vec<Message>) -> BackendFuture<'_, (String, Option<Usage>)> {
+        Box::pin(MistralClient::chat_with_usage(self, messages))
+    }
+
+    fn chat_stream<'a>(&'a self, messages: Vec<Message>, on_delta: &'a mut dyn FnMut(&str)) -> Pin<Box<dyn Future<Output = Result<String>> + 'a>> {
+        Box::pin(MistralClient::chat_stream(self, messages, on_delta))
+    }
+
+    fn fim<'a>(&'a self, prefix: &'a str, suffix: &'a str, max_tokens: u32) -> BackendFuture<'a, String> {
+        Box::pin(MistralClient::fim(self, prefix, suffix, max_tokens))
+    }
+
+    fn embed<'a>(&'a self, model: &'a str, inputs: &'a [String]) -> BackendFuture<'a, Vec<Vec<f32>>> {
+        Box::pin(MistralClient::embed(self, model, inputs))
+    }
+
+    fn list_models(&self) -> BackendFuture<'_, Vec<String>> {
+        Box::pin(MistralClient::list_models(self))
+    }
+
+    fn get_model(&self) -> &str {
+        MistralClient::get_model(self)
+    }
+
+    fn context_window(&self) -> u64 {
+        MistralClient::context_window(self)
+    }
+
+    fn provider(&self) -> &ApiProvider {
+        MistralClient::provider(self)
+    }
+
+    fn default_embedding_model(&self) -> &'static str {
+        MistralClient::default_embedding_model(self)
+    }
+
+    fn for_model(&self, model: &str) -> Arc<dyn LlmBackend> {
+        Arc::new(self.clone().with_model(model))
+    }
+
+    fn rate_limits(&self) -> Vec<(String, String)> {
+        self.http.rate_limits()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_backoff_is_capped_and_bad_requests_are_not_retried() {
+        let policy = RetryPolicy { attempts: 6, initial_backoff_secs: 2, max_backoff_secs: 10 };
+        let delays: Vec<u64> = (2..=6).map(|attempt| policy.delay(attempt).as_secs()).collect();
+        assert_eq!(delays, vec![2, 4, 8, 10, 10]);
+
+        let rate_limited = anyhow::Error::from(ApiError { status: StatusCode::TOO_MANY_REQUESTS, message: String::new() });
+        let bad_key = anyhow::Error::from(ApiError { status: StatusCode::UNAUTHORIZED, message: String::new() });
+        assert_eq!(retry_reason(&rate_limited), Some("limite de débit"));
+        assert_eq!(retry_reason(&bad_key), None);
+
+        let notice = RetryNotice { attempt: 2, attempts: 4, delay: Duration::from_secs(2), reason: "limite de débit" };
+        assert_eq!(notice.to_string(), "nouvel essai 2/4 dans 2s (limite de débit)");
+    }
+
+    #[test]
+    fn test_fim_request_uses_a_codestral_model() {
+        let client = MistralClient::new("key".to_string(), ApiProvider::MistralAi);
+        assert_eq!(client.fim_model(), "codestral-latest");
+        let client = MistralClient::new("key".to_string(), ApiProvider::Codestral).with_model("codestral-2501");
+        assert_eq!(client.fim_model(), "codestral-2501");
+
+        let request = serde_json::to_value(FimRequest {
+            model: "codestral-latest".to_string(),
+            prompt: "fn add(a: i32, b: i32) -> i32 {\n".to_string(),
+            suffix: "\n}".to_string(),
+            max_tokens: 64,
+            temperature: None,
+        }).unwrap();
+        assert_eq!(request["suffix"], "\n}");
+        assert!(request.get("temperature").is_none());
+    }
+
+    #[test]
+    fn test_streamed_events_are_read_line_by_line() {
+        let event = r#"data: {"id":"1","choices":[{"index":0,"delta":{"role":"assistant","content":"fn "},"finish_reason":null}]}"#;
+        assert_eq!(stream_delta(event).unwrap().as_deref(), Some("fn "));
+        let last = r#"data: {"id":"1","choices":[{"index":0,"delta":{"content":null},"finish_reason":"stop"}]}"#;
+        assert_eq!(stream_delta(last).unwrap(), None);
+        assert_eq!(stream_delta("data: [DONE]").unwrap(), None);
+        assert_eq!(stream_delta(": keep-alive\r").unwrap(), None);
+        assert!(stream_delta("data: {\"choices\": [").is_err());
+    }
+
+    #[test]
+    fn test_custom_provider_endpoint() {
+        let provider: ApiProvider = serde_json::from_value(serde_json::json!({
+            "Custom": { "base_url": "http://localhost:8000/v1/", "model": "qwen2.5-coder" }
+        })).unwrap();
+        assert_eq!(serde_json::from_value::<ApiProvider>(serde_json::json!("Ollama")).unwrap(), ApiProvider::Ollama);
+        assert_eq!(provider.host(), "localhost:8000");
+        assert_eq!(provider.probe_url(), "http://localhost:8000/v1/");
+        assert_eq!(provider.to_string(), "Custom (http://localhost:8000/v1/)");
+        assert!(!provider.needs_api_key());
+
+        let client = MistralClient::new(String::new(), provider);
+        assert_eq!(client.get_base_url(), "http://localhost:8000/v1/chat/completions");
+        assert_eq!(client.get_model(), "qwen2.5-coder");
+        assert_eq!(client.with_model("other").get_model(), "other");
+
+        let openrouter = ApiProvider::Custom { base_url: "https://openrouter.ai/api/v1".to_string(), model: String::new() };
+        assert_eq!((openrouter.host(), openrouter.probe_url()), ("openrouter.ai".to_string(), "https://openrouter.ai/api/v1".to_string()));
+    }
+
+    #[test]
+    fn test_connectivity_needs_a_connection_not_a_success_status() {
+        use std::io::{Read, Write};
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let _ = stream.read(&mut [0; 1024]);
+            stream.write_all(b"HTTP/1.1 404 Not Found\r\ncontent-length: 0\r\n\r\n").unwrap();
+        });
+        let provider = ApiProvider::Custom { base_url: base_url.clone(), model: String::new() };
+        assert_eq!(check_connectivity(&provider, Duration::from_secs(5)), Ok(()));
+        server.join().unwrap();
+
+        // The listener is gone: connection refused
+        assert!(check_connectivity(&provider, Duration::from_secs(5)).unwrap_err().starts_with("No network"));
+    }
+}
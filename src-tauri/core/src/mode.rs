@@ -0,0 +1,31 @@
+/// Chat modes, from read-only questions to autonomous changes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChatMode {
+    Ask,
+    Plan,
+    Code,
+    Auto,
+}
+
+impl ChatMode {
+    /// Next mode in the Shift+Tab cycle
+    pub fn next(&self) -> ChatMode {
+        match self {
+            ChatMode::Ask => ChatMode::Plan,
+            ChatMode::Plan => ChatMode::Code,
+            ChatMode::Code => ChatMode::Auto,
+            ChatMode::Auto => ChatMode::Ask,
+        }
+    }
+}
+
+impl std::fmt::Display for ChatMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChatMode::Ask => write!(f, "ASK"),
+            ChatMode::Plan => write!(f, "PLAN"),
+            ChatMode::Code => write!(f, "CODE"),
+            ChatMode::Auto => write!(f, "AUTO"),
+        }
+    }
+}
@@ -0,0 +1,285 @@
+use crate::backend::{BackendFuture, LlmBackend};
+use crate::context_guard::count_tokens;
+use crate::mistral_client::{read_stream, ApiProvider, EmbeddingRequest, Http, Message, OllamaSettings, Usage};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+/// Body of Ollama's `POST /api/chat`: sampling settings go in `options`
+#[derive(Serialize)]
+struct OllamaChatRequest {
+    model: String,
+    messages: Vec<Message>,
+    stream: bool,
+    options: OllamaOptions,
+}
+
+#[derive(Serialize)]
+struct OllamaOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    num_ctx: u64,
+}
+
+/// Response of Ollama's `POST /api/chat`: one message instead of choices, token counts at the top level
+#[derive(Deserialize, Debug)]
+struct OllamaChatResponse {
+    message: Message,
+    #[serde(default)]
+    prompt_eval_count: u64,
+    #[serde(default)]
+    eval_count: u64,
+}
+
+impl OllamaChatResponse {
+    fn usage(&self) -> Usage {
+        Usage {
+            prompt_tokens: self.prompt_eval_count,
+            completion_tokens: self.eval_count,
+            total_tokens: self.prompt_eval_count + self.eval_count,
+        }
+    }
+}
+
+/// One line of Ollama's streamed `POST /api/chat`
+#[derive(Deserialize, Debug)]
+struct OllamaStreamChunk {
+    #[serde(default)]
+    message: OllamaDelta,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct OllamaDelta {
+    #[serde(default)]
+    content: Option<String>,
+}
+
+/// Text of one line of a streamed answer: one JSON object per line, None for blank lines
+fn stream_delta(line: &str) -> Result<Option<String>> {
+    let line = line.trim();
+    if line.is_empty() {
+        return Ok(None);
+    }
+    Ok(serde_json::from_str::<OllamaStreamChunk>(line)?.message.content)
+}
+
+#[derive(Deserialize, Debug)]
+struct OllamaEmbedResponse {
+    embeddings: Vec<Vec<f32>>,
+}
+
+/// Body of Ollama's `POST /api/generate`, which fills the middle when given a `suffix`
+#[derive(Serialize)]
+struct OllamaGenerateRequest {
+    model: String,
+    prompt: String,
+    suffix: String,
+    stream: bool,
+    options: OllamaGenerateOptions,
+}
+
+#[derive(Serialize)]
+struct OllamaGenerateOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    num_ctx: u64,
+    num_predict: u32,
+}
+
+#[derive(Deserialize, Debug)]
+struct OllamaGenerateResponse {
+    response: String,
+}
+
+/// Response of Ollama's `GET /api/tags`: the models pulled on the server
+#[derive(Deserialize, Debug)]
+struct OllamaTags {
+    models: Vec<OllamaModel>,
+}
+
+#[derive(Deserialize, Debug)]
+struct OllamaModel {
+    name: String,
+}
+
+/// Client of a local Ollama server, through its native API (`/api/chat`, `/api/generate`, `/api/embed`)
+#[derive(Clone)]
+pub struct OllamaClient {
+    http: Http,
+    settings: OllamaSettings,
+    model: Option<String>,
+    temperature: Option<f32>,
+}
+
+impl OllamaClient {
+    /// The key is only sent when set, for a server behind an authenticating proxy
+    pub fn new(api_key: String, settings: OllamaSettings) -> Self {
+        Self {
+            http: Http::new(api_key, ApiProvider::Ollama, settings.host(), settings.timeout_secs),
+            settings,
+            model: None,
+            temperature: None,
+        }
+    }
+
+    /// Override the model of the `ollama` settings
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    /// Sampling temperature (model default when unset)
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    pub fn get_model(&self) -> &str {
+        self.model.as_deref().unwrap_or(&self.settings.model)
+    }
+
+    /// Models pulled on the server
+    pub async fn list_models(&self) -> Result<Vec<String>> {
+        let response = self.http.get(&self.settings.url("/api/tags")).await?;
+        let tags: OllamaTags = response.json().await?;
+        let mut models: Vec<String> = tags.models.into_iter().map(|model| model.name).collect();
+        models.sort();
+        models.dedup();
+        Ok(models)
+    }
+
+    fn chat_request(&self, messages: Vec<Message>, stream: bool) -> OllamaChatRequest {
+        OllamaChatRequest {
+            model: self.get_model().to_string(),
+            messages,
+            stream,
+            options: OllamaOptions { temperature: self.temperature, num_ctx: self.settings.context_window },
+        }
+    }
+
+    pub async fn chat_with_usage(&self, messages: Vec<Message>) -> Result<(String, Option<Usage>)> {
+        let estimated_tokens = messages.iter().map(|m| count_tokens(&m.content) as u64).sum::<u64>();
+        let request_body = serde_json::to_value(self.chat_request(messages, false))?;
+        let response = self.http.post(&self.settings.url("/api/chat"), &request_body, estimated_tokens).await?;
+        let ollama_response: OllamaChatResponse = response.json().await?;
+        let usage = ollama_response.usage();
+        Ok((ollama_response.message.content, Some(usage)))
+    }
+
+    pub async fn chat_stream(&self, messages: Vec<Message>, on_delta: impl FnMut(&str)) -> Result<String> {
+        let estimated_tokens = messages.iter().map(|m| count_tokens(&m.content) as u64).sum::<u64>();
+        let request_body = serde_json::to_value(self.chat_request(messages, true))?;
+        let response = self.http.post(&self.settings.url("/api/chat"), &request_body, estimated_tokens).await?;
+        read_stream(response, stream_delta, on_delta).await
+    }
+
+    /// Fill-in-the-middle through `/api/generate`, for the FIM-capable models
+    pub async fn fim(&self, prefix: &str, suffix: &str, max_tokens: u32) -> Result<String> {
+        let estimated_tokens = (count_tokens(prefix) + count_tokens(suffix)) as u64;
+        let request_body = serde_json::to_value(OllamaGenerateRequest {
+            model: self.get_model().to_string(),
+            prompt: prefix.to_string(),
+            suffix: suffix.to_string(),
+            stream: false,
+            options: OllamaGenerateOptions {
+                temperature: self.temperature,
+                num_ctx: self.settings.context_window,
+                num_predict: max_tokens,
+            },
+        })?;
+        let response = self.http.post(&self.settings.url("/api/generate"), &request_body, estimated_tokens).await?;
+        Ok(response.json::<OllamaGenerateResponse>().await?.response)
+    }
+
+    pub async fn embed(&self, model: &str, inputs: &[String]) -> Result<Vec<Vec<f32>>> {
+        let estimated_tokens = inputs.iter().map(|input| count_tokens(input) as u64).sum();
+        let request_body = serde_json::to_value(EmbeddingRequest { model, input: inputs })?;
+        let response = self.http.post(&self.settings.url("/api/embed"), &request_body, estimated_tokens).await?;
+        let vectors = response.json::<OllamaEmbedResponse>().await?.embeddings;
+        if vectors.len() != inputs.len() {
+            return Err(anyhow!("Expected {} embeddings, got {}", inputs.len(), vectors.len()));
+        }
+        Ok(vectors)
+    }
+}
+
+impl LlmBackend for OllamaClient {
+    fn chat_with_usage(&self, messages: Vec<Message>) -> BackendFuture<'_, (String, Option<Usage>)> {
+        Box::pin(OllamaClient::chat_with_usage(self, messages))
+    }
+
+    fn chat_stream<'a>(&'a self, messages: Vec<Message>, on_delta: &'a mut dyn FnMut(&str)) -> Pin<Box<dyn Future<Output = Result<String>> + 'a>> {
+        Box::pin(OllamaClient::chat_stream(self, messages, on_delta))
+    }
+
+    fn fim<'a>(&'a self, prefix: &'a str, suffix: &'a str, max_tokens: u32) -> BackendFuture<'a, String> {
+        Box::pin(OllamaClient::fim(self, prefix, suffix, max_tokens))
+    }
+
+    fn embed<'a>(&'a self, model: &'a str, inputs: &'a [String]) -> BackendFuture<'a, Vec<Vec<f32>>> {
+        Box::pin(OllamaClient::embed(self, model, inputs))
+    }
+
+    fn list_models(&self) -> BackendFuture<'_, Vec<String>> {
+        Box::pin(OllamaClient::list_models(self))
+    }
+
+    fn get_model(&self) -> &str {
+        OllamaClient::get_model(self)
+    }
+
+    fn context_window(&self) -> u64 {
+        self.settings.context_window
+    }
+
+    fn provider(&self) -> &ApiProvider {
+        &ApiProvider::Ollama
+    }
+
+    fn default_embedding_model(&self) -> &'static str {
+        "nomic-embed-text"
+    }
+
+    fn for_model(&self, model: &str) -> Arc<dyn LlmBackend> {
+        Arc::new(self.clone().with_model(model))
+    }
+
+    fn rate_limits(&self) -> Vec<(String, String)> {
+        self.http.rate_limits()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ollama_request_and_response_shapes() {
+        let settings: OllamaSettings = serde_json::from_value(serde_json::json!({ "base_url": "http://192.168.1.20:11434/" })).unwrap();
+        assert_eq!(settings.url("/api/chat"), "http://192.168.1.20:11434/api/chat");
+        assert_eq!(settings.host(), "192.168.1.20:11434");
+        assert_eq!(settings.model, "codestral");
+        assert_eq!(OllamaSettings { base_url: "http://ollama.lan".to_string(), ..settings.clone() }.host(), "ollama.lan");
+
+        let client = OllamaClient::new(String::new(), settings);
+        let request = serde_json::to_value(client.chat_request(vec![Message::user("Hi")], false)).unwrap();
+        assert_eq!(request["options"], serde_json::json!({ "num_ctx": 32768 }));
+        assert_eq!(request["messages"][0]["role"], "user");
+        assert_eq!(client.with_model("qwen2.5-coder").get_model(), "qwen2.5-coder");
+
+        let response: OllamaChatResponse = serde_json::from_value(serde_json::json!({
+            "model": "codestral",
+            "message": { "role": "assistant", "content": "Hello" },
+            "done": true,
+            "prompt_eval_count": 12,
+            "eval_count": 3
+        })).unwrap();
+        assert_eq!(response.message.content, "Hello");
+        assert_eq!(response.usage().total_tokens, 15);
+
+        let line = r#"{"model":"codestral","message":{"role":"assistant","content":"Hel"},"done":false}"#;
+        assert_eq!(stream_delta(line).unwrap().as_deref(), Some("Hel"));
+    }
+}
@@ -0,0 +1,1031 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::fs;
+use std::process::Command;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant, SystemTime};
+use rusqlite::{Connection, Transaction, TransactionBehavior, params};
+use sha2::{Sha256, Digest};
+use walkdir::WalkDir;
+use regex::Regex;
+use crate::symbols::Symbol;
+use crate::error::{Error, Result};
+use crate::context_guard::count_tokens;
+use crate::indexer::{SkipReason, MAX_FILE_SIZE};
+
+/// Extensions stored in the SQLite index
+pub const INDEXED_EXTENSIONS: &[&str] = &[
+    "rs", "py", "js", "ts", "tsx", "jsx", "go", "java", "c", "cpp", "h", "hpp",
+    "php", "rb", "swift", "kt", "scala", "vue", "svelte", "html", "css", "scss",
+    "json", "yaml", "yml", "toml", "md", "sql",
+];
+
+/// Files written per transaction during a sync: one commit per batch instead of one per file
+const WRITE_BATCH: usize = 200;
+
+/// Tokens of the symbol map in the system prompt
+pub const SYMBOL_MAP_TOKENS: usize = 6_000;
+
+/// Whether a line declares a symbol (function, type, class...)
+pub fn is_symbol_line(line: &str) -> bool {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(
+        r"^\s*(pub(\([^)]*\))?\s+)?(export\s+)?(default\s+)?(async\s+)?(unsafe\s+)?(fn|struct|enum|trait|impl|mod|type|const|static|macro_rules!|function|class|interface|def|func)\b"
+    ).unwrap()).is_match(line)
+}
+
+/// Number of changes per file in the last commits (empty outside a git repository)
+pub fn git_churn(project_root: &Path) -> HashMap<String, usize> {
+    let mut churn = HashMap::new();
+    let Ok(output) = Command::new("git")
+        .args(["log", "-n", "300", "--name-only", "--format="])
+        .current_dir(project_root)
+        .output()
+    else {
+        return churn;
+    };
+    if !output.status.success() {
+        return churn;
+    }
+    for line in String::from_utf8_lossy(&output.stdout).lines().filter(|l| !l.is_empty()) {
+        *churn.entry(line.to_string()).or_insert(0) += 1;
+    }
+    churn
+}
+
+/// Importance of a file for the prompt: recent git changes weigh most, then recent edits and size of the API
+fn importance(symbols: usize, churn: usize, modified_at: i64, now: i64) -> usize {
+    let age_days = (now - modified_at).max(0) / 86_400;
+    let recency = match age_days {
+        0 => 20,
+        1..=7 => 10,
+        8..=30 => 5,
+        _ => 0,
+    };
+    churn.min(30) * 3 + recency + symbols.min(50)
+}
+
+/// Project files that belong in the index (hidden, dependency and build directories are skipped,
+/// and so are the paths of the project's ignore files)
+pub fn indexable_files(project_root: &Path) -> Vec<PathBuf> {
+    let ignore = crate::project_dir::ProjectIgnore::load(project_root);
+    WalkDir::new(project_root)
+        .into_iter()
+        .filter_entry(|e| !ignore.is_ignored(e.path(), e.file_type().is_dir()))
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.into_path())
+        .filter(|path| {
+            let relative = path.strip_prefix(project_root).unwrap_or(path);
+            !relative.components().any(|c| {
+                let s = c.as_os_str().to_string_lossy();
+                s.starts_with('.') || s == "node_modules" || s == "target" || s == "dist" || s == "build"
+            })
+        })
+        .filter(|path| {
+            let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+            INDEXED_EXTENSIONS.contains(&ext)
+        })
+        .collect()
+}
+
+/// Sync files on a separate connection in a background thread; sends the number of updated files.
+/// With `files` = None the whole project is walked first
+pub fn sync_in_background(project_root: PathBuf, files: Option<Vec<PathBuf>>) -> std::sync::mpsc::Receiver<Result<usize>> {
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let result = PersistentIndex::open(&project_root).and_then(|index| {
+            let files = files.unwrap_or_else(|| indexable_files(&project_root));
+            Ok(index.sync_files(&files, None, usize::MAX)?.0)
+        });
+        let _ = tx.send(result);
+    });
+    rx
+}
+
+/// Full pass over a project: index changed files and drop deleted ones; returns the files updated
+pub fn reindex_project(project_root: &Path) -> Result<(PersistentIndex, usize)> {
+    let index = PersistentIndex::open(project_root)?;
+    let files = indexable_files(project_root);
+    let (mut updated, _) = index.sync_files(&files, None, usize::MAX)?;
+    let current: Vec<String> = files.iter()
+        .filter_map(|f| f.strip_prefix(project_root).ok())
+        .map(|p| p.to_string_lossy().to_string())
+        .collect();
+    updated += index.cleanup_stale(&current)?;
+    Ok((index, updated))
+}
+
+/// Limits of the foreground index pass at startup; the rest continues in the background
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct IndexBudget {
+    pub seconds: u64,
+    pub max_files: usize,
+}
+
+impl Default for IndexBudget {
+    fn default() -> Self {
+        Self { seconds: 3, max_files: 2000 }
+    }
+}
+
+impl IndexBudget {
+    /// Read the `index_budget` section of settings.json, e.g.
+    /// `"index_budget": { "seconds": 5, "max_files": 5000 }`
+    pub fn load() -> Self {
+        let Ok(path) = crate::settings::settings_file() else {
+            return Self::default();
+        };
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+            .and_then(|json| json.get("config")?.get("index_budget").cloned())
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn deadline(&self) -> Instant {
+        Instant::now() + Duration::from_secs(self.seconds)
+    }
+}
+
+/// Persistent code index using SQLite
+pub struct PersistentIndex {
+    conn: Connection,
+    root: PathBuf,
+    /// Lockfiles and minified code are indexed whole, see `generated::included`
+    include_generated: bool,
+}
+
+/// File found by `PersistentIndex::search`, with its matching lines (1-based)
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchHit {
+    pub relative_path: String,
+    pub lines: Vec<(usize, String)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct IndexedFileInfo {
+    pub id: i64,
+    pub relative_path: String,
+    pub absolute_path: String,
+    pub extension: String,
+    pub content_hash: String,
+    pub size: u64,
+    pub modified_at: i64,
+    pub description: Option<String>,
+}
+
+impl PersistentIndex {
+    /// Open or create an index database in the project's .codestral folder
+    pub fn open(project_root: &Path) -> Result<Self> {
+        let key = if crate::index_key::enabled(project_root) {
+            // Plain SQLite ignores the key pragma: never store the contents in clear by mistake
+            if !cfg!(feature = "index-encryption") {
+                return Err(Error::Other("encrypt_index is on, but this build has no index encryption (cargo feature index-encryption)".to_string()));
+            }
+            Some(crate::index_key::key().map_err(Error::Other)?)
+        } else {
+            None
+        };
+        Self::open_with_key(project_root, key.as_deref())
+    }
+    
+    /// `open` with the key already resolved: the raw hex key of an encrypted index, or None
+    fn open_with_key(project_root: &Path, key: Option<&str>) -> Result<Self> {
+        let codestral_dir = crate::project_dir::ensure(project_root)?;
+        
+        let db_path = codestral_dir.join("index.db");
+        let conn = match Self::connect(&db_path, key) {
+            // Encryption turned on or off, or another key: the index is only a cache, rebuild it
+            Err(e) if e.sqlite_error_code() == Some(rusqlite::ErrorCode::NotADatabase) => {
+                for suffix in ["", "-wal", "-shm"] {
+                    let _ = fs::remove_file(format!("{}{}", db_path.display(), suffix));
+                }
+                Self::connect(&db_path, key)
+            }
+            result => result,
+        }
+        .map_err(|e| Error::database("Cannot open index database", e))?;
+        
+        // Databases created before the symbols table: reindex to fill it
+        let has_symbol_table = conn.prepare("SELECT name FROM symbols LIMIT 0").is_ok();
+        // Databases created before full-text search: filled from the stored contents below
+        let has_search_table = conn.prepare("SELECT relative_path FROM files_fts LIMIT 0").is_ok();
+        
+        // Create tables if needed
+        conn.execute_batch(r"
+            CREATE TABLE IF NOT EXISTS files (
+                id INTEGER PRIMARY KEY,
+                relative_path TEXT UNIQUE NOT NULL,
+                absolute_path TEXT NOT NULL,
+                extension TEXT,
+                content_hash TEXT NOT NULL,
+                size INTEGER,
+                modified_at INTEGER,
+                indexed_at INTEGER,
+                description TEXT,
+                content TEXT
+            );
+            
+            CREATE TABLE IF NOT EXISTS tags (
+                id INTEGER PRIMARY KEY,
+                file_id INTEGER REFERENCES files(id) ON DELETE CASCADE,
+                tag TEXT NOT NULL
+            );
+            
+            CREATE TABLE IF NOT EXISTS symbols (
+                id INTEGER PRIMARY KEY,
+                relative_path TEXT NOT NULL,
+                name TEXT NOT NULL,
+                parent TEXT,
+                kind TEXT NOT NULL,
+                start_line INTEGER NOT NULL,
+                end_line INTEGER NOT NULL
+            );
+            
+            CREATE TABLE IF NOT EXISTS embeddings (
+                relative_path TEXT PRIMARY KEY,
+                content_hash TEXT NOT NULL,
+                model TEXT NOT NULL,
+                vector BLOB NOT NULL
+            );
+            
+            CREATE VIRTUAL TABLE IF NOT EXISTS files_fts USING fts5(relative_path, content);
+            
+            CREATE TABLE IF NOT EXISTS skipped_files (
+                relative_path TEXT PRIMARY KEY,
+                reason TEXT NOT NULL,
+                value INTEGER NOT NULL
+            );
+            
+            CREATE INDEX IF NOT EXISTS idx_files_path ON files(relative_path);
+            CREATE INDEX IF NOT EXISTS idx_symbols_name ON symbols(name);
+            CREATE INDEX IF NOT EXISTS idx_symbols_path ON symbols(relative_path);
+            CREATE INDEX IF NOT EXISTS idx_files_hash ON files(content_hash);
+            CREATE INDEX IF NOT EXISTS idx_tags_tag ON tags(tag);
+        ").map_err(|e| Error::database("Cannot create tables", e))?;
+        
+        // Databases created before symbol counts: add the column and force a reindex
+        let has_symbols = conn.prepare("SELECT symbols FROM files LIMIT 0").is_ok();
+        if !has_symbols {
+            conn.execute_batch(r"
+                ALTER TABLE files ADD COLUMN symbols INTEGER NOT NULL DEFAULT 0;
+                UPDATE files SET content_hash = '';
+            ").map_err(|e| Error::database("Cannot migrate index", e))?;
+        }
+        if !has_symbol_table {
+            conn.execute("UPDATE files SET content_hash = ''", [])
+                .map_err(|e| Error::database("Cannot migrate index", e))?;
+        }
+        if !has_search_table {
+            conn.execute("INSERT INTO files_fts (relative_path, content) SELECT relative_path, content FROM files", [])
+                .map_err(|e| Error::database("Cannot migrate index", e))?;
+        }
+        
+        Ok(Self {
+            conn,
+            root: project_root.to_path_buf(),
+            include_generated: crate::generated::included(project_root),
+        })
+    }
+    
+    /// Connection to index.db, keyed with the raw hex key of an encrypted (SQLCipher) index
+    fn connect(db_path: &Path, key: Option<&str>) -> rusqlite::Result<Connection> {
+        let conn = Connection::open(db_path)?;
+        if let Some(key) = key {
+            // Hex digits only, checked by index_key
+            conn.execute_batch(&format!("PRAGMA key = \"x'{}'\";", key))?;
+        }
+        // The startup sync may write from a background thread while the TUI reads
+        conn.busy_timeout(Duration::from_secs(5))?;
+        // WAL: readers see the last commit instead of waiting for the writer. Also the first
+        // read, which fails with NotADatabase when the key does not match the file
+        conn.query_row("PRAGMA journal_mode = WAL", [], |row| row.get::<_, String>(0))?;
+        conn.execute_batch("PRAGMA synchronous = NORMAL")?;
+        Ok(conn)
+    }
+
+    /// Calculate SHA256 hash of file content
+    fn hash_content(content: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(content.as_bytes());
+        format!("{:x}", hasher.finalize())
+    }
+    
+    /// Get modification time as unix timestamp
+    fn get_mtime(path: &Path) -> i64 {
+        fs::metadata(path)
+            .and_then(|m| m.modified())
+            .and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).map(|d| d.as_secs() as i64).map_err(|_| std::io::Error::other("time error")))
+            .unwrap_or(0)
+    }
+    
+    /// Lockfiles and minified code are stored without content: their hash says so, and
+    /// `include_generated` being toggled reindexes them
+    fn is_metadata_only(&self, path: &Path, content: &str) -> bool {
+        !self.include_generated && crate::generated::is_generated(path, content)
+    }
+
+    fn stored_hash(&self, path: &Path, content: &str) -> String {
+        let hash = Self::hash_content(content);
+        if self.is_metadata_only(path, content) { format!("{}:generated", hash) } else { hash }
+    }
+
+    /// Check if a file needs reindexing (hash changed or not in db)
+    pub fn needs_reindex(&self, relative_path: &str, content: &str) -> bool {
+        let hash = self.stored_hash(Path::new(relative_path), content);
+        let result: std::result::Result<String, _> = self.conn.query_row(
+            "SELECT content_hash FROM files WHERE relative_path = ?",
+            params![relative_path],
+            |row| row.get(0),
+        );
+        
+        match result {
+            Ok(stored_hash) => stored_hash != hash,
+            Err(_) => true, // Not in DB
+        }
+    }
+    
+    /// Index or update a file
+    pub fn index_file(
+        &self,
+        absolute_path: &Path,
+        relative_path: &str,
+        content: &str,
+    ) -> Result<i64> {
+        let hash = self.stored_hash(absolute_path, content);
+        let extension = absolute_path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("")
+            .to_string();
+        let size = content.len() as u64;
+        // Lockfiles and minified code: metadata only, nothing to search or map
+        let content = if self.is_metadata_only(absolute_path, content) { "" } else { content };
+        let symbols = content.lines().filter(|l| is_symbol_line(l)).count() as i64;
+        let mtime = Self::get_mtime(absolute_path);
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+        
+        self.conn.prepare_cached(
+            r"INSERT INTO files (relative_path, absolute_path, extension, content_hash, size, modified_at, indexed_at, content, symbols)
+              VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+              ON CONFLICT(relative_path) DO UPDATE SET
+                absolute_path = excluded.absolute_path,
+                extension = excluded.extension,
+                content_hash = excluded.content_hash,
+                size = excluded.size,
+                modified_at = excluded.modified_at,
+                indexed_at = excluded.indexed_at,
+                content = excluded.content,
+                symbols = excluded.symbols",
+        ).and_then(|mut stmt| stmt.execute(params![
+                relative_path,
+                absolute_path.to_string_lossy().to_string(),
+                extension,
+                hash,
+                size as i64,
+                mtime,
+                now,
+                content,
+                symbols
+            ]))
+            .map_err(|e| Error::database("Cannot index file", e))?;
+        let file_id = self.conn.last_insert_rowid();
+        
+        self.conn.execute("DELETE FROM files_fts WHERE relative_path = ?", params![relative_path])
+            .and_then(|_| self.conn.execute("INSERT INTO files_fts (relative_path, content) VALUES (?1, ?2)", params![relative_path, content]))
+            .and_then(|_| self.conn.execute("DELETE FROM skipped_files WHERE relative_path = ?", params![relative_path]))
+            .map_err(|e| Error::database("Cannot index file", e))?;
+        
+        self.conn.execute("DELETE FROM symbols WHERE relative_path = ?", params![relative_path])
+            .map_err(|e| Error::database("Cannot index symbols", e))?;
+        let mut insert = self.conn.prepare_cached(
+            "INSERT INTO symbols (relative_path, name, parent, kind, start_line, end_line) VALUES (?1, ?2, ?3, ?4, ?5, ?6)"
+        ).map_err(|e| Error::database("Cannot index symbols", e))?;
+        for symbol in crate::symbols::extract(content, &extension) {
+            insert.execute(params![relative_path, symbol.name, symbol.parent, symbol.kind, symbol.start_line as i64, symbol.end_line as i64])
+                .map_err(|e| Error::database("Cannot index symbols", e))?;
+        }
+        
+        Ok(file_id)
+    }
+    
+    /// Declarations named `name`, restricted to those inside `parent` if given, as (relative path, symbol)
+    pub fn find_symbols(&self, parent: Option<&str>, name: &str) -> Result<Vec<(String, Symbol)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT relative_path, name, parent, kind, start_line, end_line FROM symbols
+             WHERE name = ?1 AND (?2 IS NULL OR parent = ?2) ORDER BY relative_path, start_line"
+        ).map_err(|e| Error::database("Query error", e))?;
+        
+        let rows = stmt.query_map(params![name, parent], |row| {
+            Ok((row.get(0)?, Symbol {
+                name: row.get(1)?,
+                parent: row.get(2)?,
+                kind: row.get(3)?,
+                start_line: row.get::<_, i64>(4)? as usize,
+                end_line: row.get::<_, i64>(5)? as usize,
+            }))
+        }).map_err(|e| Error::database("Query error", e))?;
+        
+        rows.collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| Error::database("Row error", e))
+    }
+    
+    /// Add tags to a file
+    pub fn add_tags(&self, file_id: i64, tags: &[&str]) -> Result<()> {
+        for tag in tags {
+            self.conn.execute(
+                "INSERT OR IGNORE INTO tags (file_id, tag) VALUES (?1, ?2)",
+                params![file_id, tag],
+            ).map_err(|e| Error::database("Cannot add tag", e))?;
+        }
+        Ok(())
+    }
+    
+    /// Set description for a file
+    pub fn set_description(&self, relative_path: &str, description: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE files SET description = ?1 WHERE relative_path = ?2",
+            params![description, relative_path],
+        ).map_err(|e| Error::database("Cannot set description", e))?;
+        Ok(())
+    }
+    
+    /// Get all indexed files
+    pub fn list_files(&self) -> Result<Vec<IndexedFileInfo>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, relative_path, absolute_path, extension, content_hash, size, modified_at, description FROM files ORDER BY relative_path"
+        ).map_err(|e| Error::database("Query error", e))?;
+        
+        let rows = stmt.query_map([], |row| {
+            Ok(IndexedFileInfo {
+                id: row.get(0)?,
+                relative_path: row.get(1)?,
+                absolute_path: row.get(2)?,
+                extension: row.get(3)?,
+                content_hash: row.get(4)?,
+                size: row.get::<_, i64>(5)? as u64,
+                modified_at: row.get(6)?,
+                description: row.get(7)?,
+            })
+        }).map_err(|e| Error::database("Query error", e))?;
+        
+        rows.collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| Error::database("Row error", e))
+    }
+    
+    /// Declarations of the whole project, one line per file in `ranked_files` order, e.g.
+    /// `- src/client.rs: struct Client 1, impl Client 3-7, fn Client::chat 4-6`.
+    /// Files past `max_tokens` are only counted
+    pub fn symbol_map(&self, max_tokens: usize) -> Result<String> {
+        let mut stmt = self.conn.prepare(
+            "SELECT relative_path, name, parent, kind, start_line, end_line FROM symbols ORDER BY relative_path, start_line"
+        ).map_err(|e| Error::database("Query error", e))?;
+        
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, Symbol {
+                name: row.get(1)?,
+                parent: row.get(2)?,
+                kind: row.get(3)?,
+                start_line: row.get::<_, i64>(4)? as usize,
+                end_line: row.get::<_, i64>(5)? as usize,
+            }))
+        }).map_err(|e| Error::database("Query error", e))?;
+        
+        let mut by_file: HashMap<String, Vec<String>> = HashMap::new();
+        for row in rows {
+            let (path, symbol) = row.map_err(|e| Error::database("Row error", e))?;
+            let lines = if symbol.end_line > symbol.start_line {
+                format!("{}-{}", symbol.start_line, symbol.end_line)
+            } else {
+                symbol.start_line.to_string()
+            };
+            by_file.entry(path).or_default().push(format!("{} {} {}", symbol.kind, symbol.qualified_name(), lines));
+        }
+        
+        let mut map = String::new();
+        let mut tokens = 0;
+        let mut omitted = 0;
+        for file in self.ranked_files(usize::MAX)? {
+            let line = match by_file.get(&file.relative_path) {
+                Some(symbols) => format!("- {}: {}\n", file.relative_path, symbols.join(", ")),
+                None => format!("- {}\n", file.relative_path),
+            };
+            let line_tokens = count_tokens(&line);
+            if tokens + line_tokens > max_tokens {
+                omitted += 1;
+                continue;
+            }
+            tokens += line_tokens;
+            map.push_str(&line);
+        }
+        if omitted > 0 {
+            map.push_str(&format!("(+{} fichiers non listés)\n", omitted));
+        }
+        Ok(map)
+    }
+    
+    /// Most important files first (git churn, recent edits, symbol count), at most `limit`
+    pub fn ranked_files(&self, limit: usize) -> Result<Vec<IndexedFileInfo>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, relative_path, absolute_path, extension, content_hash, size, modified_at, description, symbols FROM files"
+        ).map_err(|e| Error::database("Query error", e))?;
+        
+        let rows = stmt.query_map([], |row| {
+            Ok((IndexedFileInfo {
+                id: row.get(0)?,
+                relative_path: row.get(1)?,
+                absolute_path: row.get(2)?,
+                extension: row.get(3)?,
+                content_hash: row.get(4)?,
+                size: row.get::<_, i64>(5)? as u64,
+                modified_at: row.get(6)?,
+                description: row.get(7)?,
+            }, row.get::<_, i64>(8)? as usize))
+        }).map_err(|e| Error::database("Query error", e))?;
+        
+        let files = rows.collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| Error::database("Row error", e))?;
+        
+        let churn = git_churn(&self.root);
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+        
+        let mut scored: Vec<(usize, IndexedFileInfo)> = files.into_iter()
+            .map(|(file, symbols)| {
+                let changes = churn.get(&file.relative_path).copied().unwrap_or(0);
+                (importance(symbols, changes, file.modified_at, now), file)
+            })
+            .collect();
+        scored.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.relative_path.cmp(&b.1.relative_path)));
+        
+        Ok(scored.into_iter().take(limit).map(|(_, file)| file).collect())
+    }
+    
+    /// Remove a deleted file from the index
+    pub fn remove_file(&self, relative_path: &str) -> Result<()> {
+        self.conn.execute("DELETE FROM files WHERE relative_path = ?", params![relative_path])
+            .map_err(|e| Error::database("Delete error", e))?;
+        self.conn.execute("DELETE FROM symbols WHERE relative_path = ?", params![relative_path])
+            .map_err(|e| Error::database("Delete error", e))?;
+        self.conn.execute("DELETE FROM embeddings WHERE relative_path = ?", params![relative_path])
+            .map_err(|e| Error::database("Delete error", e))?;
+        self.conn.execute("DELETE FROM files_fts WHERE relative_path = ?", params![relative_path])
+            .map_err(|e| Error::database("Delete error", e))?;
+        self.conn.execute("DELETE FROM skipped_files WHERE relative_path = ?", params![relative_path])
+            .map_err(|e| Error::database("Delete error", e))?;
+        Ok(())
+    }
+
+    /// Drop a file from the index and remember why, for `index stats --skipped` and the
+    /// warning shown when it is mentioned
+    pub fn record_skip(&self, relative_path: &str, reason: &SkipReason) -> Result<()> {
+        self.remove_file(relative_path)?;
+        let (reason, value) = reason.to_row();
+        self.conn.execute(
+            "INSERT INTO skipped_files (relative_path, reason, value) VALUES (?1, ?2, ?3)",
+            params![relative_path, reason, value],
+        ).map_err(|e| Error::database("Cannot record skipped file", e))?;
+        Ok(())
+    }
+
+    /// Files left out of the index, by path
+    pub fn skipped_files(&self) -> Result<Vec<(String, SkipReason)>> {
+        let mut stmt = self.conn.prepare("SELECT relative_path, reason, value FROM skipped_files ORDER BY relative_path")
+            .map_err(|e| Error::database("Query error", e))?;
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, i64>(2)?)))
+            .map_err(|e| Error::database("Query error", e))?;
+        Ok(rows.filter_map(|row| row.ok())
+            .filter_map(|(path, reason, value)| Some((path, SkipReason::from_row(&reason, value)?)))
+            .collect())
+    }
+    
+    /// Full-text search of the indexed contents, best matches first (BM25): each file with its
+    /// first `lines_per_file` lines containing a word of the query, as (line number, line).
+    /// Words are matched whole, so `parse_ai_response` and "parse ai response" find the same files
+    pub fn search(&self, query: &str, limit: usize, lines_per_file: usize) -> Result<Vec<SearchHit>> {
+        let words: Vec<String> = query.split(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .flat_map(|word| word.split('_'))
+            .filter(|word| !word.is_empty())
+            .map(str::to_lowercase)
+            .collect();
+        if words.is_empty() {
+            return Ok(Vec::new());
+        }
+        // Quoted: the words of the query are never read as FTS5 operators
+        let expression = words.iter().map(|word| format!("\"{}\"", word)).collect::<Vec<_>>().join(" ");
+        
+        let mut stmt = self.conn.prepare(
+            "SELECT relative_path, content FROM files_fts WHERE files_fts MATCH ?1 ORDER BY rank LIMIT ?2"
+        ).map_err(|e| Error::database("Query error", e))?;
+        let rows = stmt.query_map(params![expression, limit as i64], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+            .map_err(|e| Error::database("Query error", e))?;
+        
+        let mut hits = Vec::new();
+        for row in rows {
+            let (relative_path, content) = row.map_err(|e| Error::database("Row error", e))?;
+            let lines = content.lines()
+                .enumerate()
+                .filter(|(_, line)| {
+                    let line = line.to_lowercase();
+                    words.iter().any(|word| line.contains(word.as_str()))
+                })
+                .take(lines_per_file)
+                .map(|(i, line)| (i + 1, line.trim().to_string()))
+                .collect();
+            hits.push(SearchHit { relative_path, lines });
+        }
+        Ok(hits)
+    }
+    
+    /// Whether the file has no vector from `model` for this content yet
+    pub fn needs_embedding(&self, relative_path: &str, content: &str, model: &str) -> bool {
+        let result: std::result::Result<(String, String), _> = self.conn.query_row(
+            "SELECT content_hash, model FROM embeddings WHERE relative_path = ?",
+            params![relative_path],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        );
+        
+        match result {
+            Ok((stored_hash, stored_model)) => stored_hash != Self::hash_content(content) || stored_model != model,
+            Err(_) => true,
+        }
+    }
+    
+    /// Store the vector of a file's content, as little-endian f32s
+    pub fn store_embedding(&self, relative_path: &str, content: &str, model: &str, vector: &[f32]) -> Result<()> {
+        let bytes: Vec<u8> = vector.iter().flat_map(|value| value.to_le_bytes()).collect();
+        self.conn.execute(
+            "INSERT OR REPLACE INTO embeddings (relative_path, content_hash, model, vector) VALUES (?1, ?2, ?3, ?4)",
+            params![relative_path, Self::hash_content(content), model, bytes],
+        ).map_err(|e| Error::database("Cannot store embedding", e))?;
+        Ok(())
+    }
+    
+    /// Stored vectors of `model`, as (relative path, vector)
+    pub fn embeddings(&self, model: &str) -> Result<Vec<(String, Vec<f32>)>> {
+        let mut stmt = self.conn.prepare("SELECT relative_path, vector FROM embeddings WHERE model = ?1")
+            .map_err(|e| Error::database("Query error", e))?;
+        
+        let rows = stmt.query_map(params![model], |row| {
+            let bytes: Vec<u8> = row.get(1)?;
+            let vector = bytes.chunks_exact(4)
+                .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+                .collect();
+            Ok((row.get(0)?, vector))
+        }).map_err(|e| Error::database("Query error", e))?;
+        
+        rows.collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| Error::database("Row error", e))
+    }
+    
+    /// Search files by path pattern
+    pub fn search_by_path(&self, pattern: &str) -> Result<Vec<IndexedFileInfo>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, relative_path, absolute_path, extension, content_hash, size, modified_at, description 
+             FROM files WHERE relative_path LIKE ?1 ORDER BY relative_path"
+        ).map_err(|e| Error::database("Query error", e))?;
+        
+        let rows = stmt.query_map(params![format!("%{}%", pattern)], |row| {
+            Ok(IndexedFileInfo {
+                id: row.get(0)?,
+                relative_path: row.get(1)?,
+                absolute_path: row.get(2)?,
+                extension: row.get(3)?,
+                content_hash: row.get(4)?,
+                size: row.get::<_, i64>(5)? as u64,
+                modified_at: row.get(6)?,
+                description: row.get(7)?,
+            })
+        }).map_err(|e| Error::database("Query error", e))?;
+        
+        rows.collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| Error::database("Row error", e))
+    }
+    
+    /// Search files by tag
+    pub fn search_by_tag(&self, tag: &str) -> Result<Vec<IndexedFileInfo>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT f.id, f.relative_path, f.absolute_path, f.extension, f.content_hash, f.size, f.modified_at, f.description 
+             FROM files f JOIN tags t ON f.id = t.file_id WHERE t.tag = ?1 ORDER BY f.relative_path"
+        ).map_err(|e| Error::database("Query error", e))?;
+        
+        let rows = stmt.query_map(params![tag], |row| {
+            Ok(IndexedFileInfo {
+                id: row.get(0)?,
+                relative_path: row.get(1)?,
+                absolute_path: row.get(2)?,
+                extension: row.get(3)?,
+                content_hash: row.get(4)?,
+                size: row.get::<_, i64>(5)? as u64,
+                modified_at: row.get(6)?,
+                description: row.get(7)?,
+            })
+        }).map_err(|e| Error::database("Query error", e))?;
+        
+        rows.collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| Error::database("Row error", e))
+    }
+    
+    /// Get file content by relative path
+    pub fn get_content(&self, relative_path: &str) -> Result<Option<String>> {
+        let result: std::result::Result<String, _> = self.conn.query_row(
+            "SELECT content FROM files WHERE relative_path = ?",
+            params![relative_path],
+            |row| row.get(0),
+        );
+        
+        match result {
+            Ok(content) => Ok(Some(content)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(Error::database("Query error", e)),
+        }
+    }
+    
+    /// Get statistics
+    pub fn stats(&self) -> Result<(usize, u64)> {
+        let count: i64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM files",
+            [],
+            |row| row.get(0),
+        ).map_err(|e| Error::database("Query error", e))?;
+        
+        let size: i64 = self.conn.query_row(
+            "SELECT COALESCE(SUM(size), 0) FROM files",
+            [],
+            |row| row.get(0),
+        ).map_err(|e| Error::database("Query error", e))?;
+        
+        Ok((count as usize, size as u64))
+    }
+    
+    /// Index changed files in order until the deadline or `max_files` files were processed,
+    /// committing every `WRITE_BATCH` written files. Returns (files updated, files processed)
+    pub fn sync_files(&self, files: &[PathBuf], deadline: Option<Instant>, max_files: usize) -> Result<(usize, usize)> {
+        let mut updated = 0;
+        let mut processed = 0;
+        // Write lock taken up front: a concurrent sync waits (busy_timeout) instead of failing
+        // at the first write. Without a transaction (e.g. one already open) each write commits on its own
+        let begin = || Transaction::new_unchecked(&self.conn, TransactionBehavior::Immediate).ok();
+        let mut batch = begin();
+        let mut written = 0;
+        
+        for path in files {
+            if processed >= max_files || deadline.map(|d| Instant::now() >= d).unwrap_or(false) {
+                break;
+            }
+            processed += 1;
+            
+            let relative = path.strip_prefix(&self.root)
+                .map(|p| p.to_string_lossy().to_string())
+                .unwrap_or_else(|_| path.to_string_lossy().to_string());
+            let size = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+            if size > MAX_FILE_SIZE {
+                let _ = self.record_skip(&relative, &SkipReason::TooLarge(size));
+                continue;
+            }
+            let Ok(content) = fs::read_to_string(path) else {
+                if !path.exists() {
+                    // Deleted since the last sync
+                    if self.remove_file(&relative).is_ok() {
+                        updated += 1;
+                    }
+                } else {
+                    let _ = self.record_skip(&relative, &SkipReason::Binary);
+                }
+                continue;
+            };
+            
+            if self.needs_reindex(&relative, &content) && self.index_file(path, &relative, &content).is_ok() {
+                updated += 1;
+                written += 1;
+            }
+            if written == WRITE_BATCH {
+                if let Some(transaction) = batch.take() {
+                    transaction.commit().map_err(|e| Error::database("Commit error", e))?;
+                }
+                batch = begin();
+                written = 0;
+            }
+        }
+        
+        if let Some(transaction) = batch {
+            transaction.commit().map_err(|e| Error::database("Commit error", e))?;
+        }
+        Ok((updated, processed))
+    }
+    
+    /// Remove files not in the provided list (cleanup stale entries)
+    pub fn cleanup_stale(&self, current_paths: &[String]) -> Result<usize> {
+        if current_paths.is_empty() {
+            return Ok(0);
+        }
+        
+        // Get all paths in DB
+        let mut stmt = self.conn.prepare("SELECT relative_path FROM files UNION SELECT relative_path FROM skipped_files")
+            .map_err(|e| Error::database("Query error", e))?;
+        
+        let db_paths: Vec<String> = stmt.query_map([], |row| row.get(0))
+            .map_err(|e| Error::database("Query error", e))?
+            .filter_map(|r| r.ok())
+            .collect();
+        
+        let current_set: std::collections::HashSet<&String> = current_paths.iter().collect();
+        let mut deleted = 0;
+        
+        let transaction = self.conn.unchecked_transaction()
+            .map_err(|e| Error::database("Delete error", e))?;
+        for path in db_paths {
+            if !current_set.contains(&path) {
+                self.remove_file(&path)?;
+                deleted += 1;
+            }
+        }
+        transaction.commit().map_err(|e| Error::database("Delete error", e))?;
+        
+        Ok(deleted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_encrypted_index() {
+        let dir = tempdir().unwrap();
+        let settings = dir.path().join(".codestral/settings.json");
+        fs::create_dir_all(settings.parent().unwrap()).unwrap();
+        fs::write(&settings, r#"{ "encrypt_index": true }"#).unwrap();
+
+        if !cfg!(feature = "index-encryption") {
+            assert!(matches!(PersistentIndex::open(dir.path()), Err(Error::Other(_))));
+            return;
+        }
+        let key = "ab".repeat(32);
+        let index = PersistentIndex::open_with_key(dir.path(), Some(&key)).unwrap();
+        index.index_file(Path::new("/x/secret.rs"), "secret.rs", "const TOKEN: &str = \"hunter2\";").unwrap();
+        drop(index);
+        for entry in fs::read_dir(dir.path().join(".codestral")).unwrap() {
+            assert!(!String::from_utf8_lossy(&fs::read(entry.unwrap().path()).unwrap()).contains("hunter2"));
+        }
+        let index = PersistentIndex::open_with_key(dir.path(), Some(&key)).unwrap();
+        assert_eq!(index.get_content("secret.rs").unwrap().as_deref(), Some("const TOKEN: &str = \"hunter2\";"));
+        assert_eq!(index.search("hunter2", 5, 1).unwrap().len(), 1);
+        drop(index);
+
+        // Turned off: the encrypted file cannot be read, the index starts over in clear
+        fs::write(&settings, r#"{ "encrypt_index": false }"#).unwrap();
+        assert_eq!(PersistentIndex::open(dir.path()).unwrap().stats().unwrap().0, 0);
+    }
+    
+    #[test]
+    fn test_persistent_index() {
+        let dir = tempdir().unwrap();
+        let index = PersistentIndex::open(dir.path()).unwrap();
+        
+        // Index a file
+        let id = index.index_file(
+            Path::new("/test/file.rs"),
+            "file.rs",
+            "fn main() {}"
+        ).unwrap();
+        
+        // Check stats
+        let (count, _) = index.stats().unwrap();
+        assert_eq!(count, 1);
+        
+        // Check content
+        let content = index.get_content("file.rs").unwrap();
+        assert_eq!(content, Some("fn main() {}".to_string()));
+        
+        // Check needs_reindex
+        assert!(!index.needs_reindex("file.rs", "fn main() {}"));
+        assert!(index.needs_reindex("file.rs", "fn main() { println!(); }"));
+        
+        // Full-text search follows updates and removals
+        index.index_file(Path::new("/test/parser.rs"), "src/parser.rs", "use x;\npub fn parse_ai_response() {}\n// AI response\n").unwrap();
+        index.index_file(Path::new("/test/file.rs"), "file.rs", "fn main() { parse(); }").unwrap();
+        let hits = index.search("parse_ai_response", 10, 5).unwrap();
+        assert_eq!(hits, vec![SearchHit {
+            relative_path: "src/parser.rs".to_string(),
+            lines: vec![(2, "pub fn parse_ai_response() {}".to_string()), (3, "// AI response".to_string())],
+        }]);
+        assert!(index.search("\"OR (", 10, 5).unwrap().is_empty());
+        index.remove_file("src/parser.rs").unwrap();
+        assert!(index.search("response", 10, 5).unwrap().is_empty());
+        assert_eq!(index.search("main", 10, 5).unwrap()[0].relative_path, "file.rs");
+
+        // A lockfile is listed, but its content is neither stored nor searchable
+        let lock = "{ \"lodash\": \"4.17.21\" }";
+        index.index_file(Path::new("/test/package-lock.json"), "package-lock.json", lock).unwrap();
+        assert_eq!(index.get_content("package-lock.json").unwrap().as_deref(), Some(""));
+        assert!(index.search("lodash", 10, 5).unwrap().is_empty());
+        assert!(!index.needs_reindex("package-lock.json", lock));
+    }
+    
+    #[test]
+    fn test_ranked_files_prefer_symbols() {
+        let dir = tempdir().unwrap();
+        let index = PersistentIndex::open(dir.path()).unwrap();
+        index.index_file(Path::new("/x/assets/a.json"), "assets/a.json", "{}").unwrap();
+        index.index_file(Path::new("/x/src/lib.rs"), "src/lib.rs", "pub fn a() {}\npub struct B;\n").unwrap();
+        
+        let ranked = index.ranked_files(1).unwrap();
+        assert_eq!(ranked.len(), 1);
+        assert_eq!(ranked[0].relative_path, "src/lib.rs");
+        
+        let map = index.symbol_map(1_000).unwrap();
+        assert_eq!(map, "- src/lib.rs: fn a 1, struct B 2\n- assets/a.json\n");
+        let first = count_tokens("- src/lib.rs: fn a 1, struct B 2\n");
+        assert_eq!(index.symbol_map(first).unwrap(), "- src/lib.rs: fn a 1, struct B 2\n(+1 fichiers non listés)\n");
+    }
+    
+    #[test]
+    fn test_sync_files_respects_budget() {
+        let dir = tempdir().unwrap();
+        for name in ["a.rs", "b.rs", "c.rs"] {
+            fs::write(dir.path().join(name), format!("// {}", name)).unwrap();
+        }
+        fs::create_dir(dir.path().join("node_modules")).unwrap();
+        fs::write(dir.path().join("node_modules").join("dep.js"), "x").unwrap();
+        
+        let index = PersistentIndex::open(dir.path()).unwrap();
+        let mut files = indexable_files(dir.path());
+        files.sort();
+        assert_eq!(files.len(), 3);
+        
+        assert_eq!(index.sync_files(&files, None, 2).unwrap(), (2, 2));
+        assert_eq!(index.sync_files(&files[2..], None, usize::MAX).unwrap(), (1, 1));
+        assert_eq!(index.sync_files(&files, None, usize::MAX).unwrap(), (0, 3));
+
+        // Too large or binary: left out, with the reason kept until the file is fixed or gone
+        let big = dir.path().join("big.json");
+        let binary = dir.path().join("blob.rs");
+        fs::write(&big, "x".repeat(MAX_FILE_SIZE as usize + 1)).unwrap();
+        fs::write(&binary, [0xff, 0xfe, 0x00]).unwrap();
+        index.sync_files(&[big.clone(), binary.clone()], None, usize::MAX).unwrap();
+        assert_eq!(index.skipped_files().unwrap(), vec![
+            ("big.json".to_string(), SkipReason::TooLarge(MAX_FILE_SIZE + 1)),
+            ("blob.rs".to_string(), SkipReason::Binary),
+        ]);
+        fs::write(&big, "{}").unwrap();
+        index.sync_files(&[big], None, usize::MAX).unwrap();
+        index.cleanup_stale(&["a.rs".to_string(), "big.json".to_string()]).unwrap();
+        assert!(index.skipped_files().unwrap().is_empty());
+        assert_eq!(index.stats().unwrap().0, 2);
+    }
+    
+    #[test]
+    fn test_reindex_project_reports_updates_and_stats() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("a.rs"), "fn a() {}").unwrap();
+        fs::write(dir.path().join("b.rs"), "fn bb() {}").unwrap();
+
+        let (index, updated) = reindex_project(dir.path()).unwrap();
+        assert_eq!(updated, 2);
+        assert_eq!(index.stats().unwrap(), (2, 19));
+        drop(index);
+        assert_eq!(reindex_project(dir.path()).unwrap().1, 0);
+
+        // A deleted file is removed from the index and counted as updated
+        fs::remove_file(dir.path().join("b.rs")).unwrap();
+        let (index, updated) = reindex_project(dir.path()).unwrap();
+        assert_eq!(updated, 1);
+        assert_eq!(index.stats().unwrap(), (1, 9));
+    }
+    
+    #[test]
+    fn test_sync_commits_in_batches_readable_meanwhile() {
+        let dir = tempdir().unwrap();
+        let files: Vec<PathBuf> = (0..WRITE_BATCH + 5)
+            .map(|i| {
+                let path = dir.path().join(format!("f{}.rs", i));
+                fs::write(&path, format!("fn f{}() {{}}", i)).unwrap();
+                path
+            })
+            .collect();
+        
+        let index = PersistentIndex::open(dir.path()).unwrap();
+        let mode: String = index.conn.query_row("PRAGMA journal_mode", [], |row| row.get(0)).unwrap();
+        assert_eq!(mode, "wal");
+        assert_eq!(index.sync_files(&files, None, usize::MAX).unwrap(), (WRITE_BATCH + 5, WRITE_BATCH + 5));
+        
+        // A reader on its own connection, while a write transaction is open on the first one
+        let writing = index.conn.unchecked_transaction().unwrap();
+        index.remove_file("f0.rs").unwrap();
+        let reader = PersistentIndex::open(dir.path()).unwrap();
+        assert_eq!(reader.stats().unwrap().0, WRITE_BATCH + 5);
+        writing.commit().unwrap();
+        assert_eq!(reader.stats().unwrap().0, WRITE_BATCH + 4);
+        assert_eq!(reader.find_symbols(None, "f7").unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,129 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+
+/// Per-project directory: SQLite index, backups, sessions, audit log and project config
+pub const DIR_NAME: &str = ".codestral";
+
+/// Shareable config, kept out of the generated .gitignore; everything else is local state
+const SHARED_ENTRIES: &[&str] = &[
+    "mcp_servers.json",
+    "settings.json",
+    "config.toml",
+    "memory.md",
+    "system_prompt.md",
+    "ignore",
+    "tools/",
+    "prompts/",
+];
+
+/// Files listing what the indexes leave out, in gitignore syntax, relative to the project root
+const IGNORE_FILES: &[&str] = &[".codestralignore", ".codestral/ignore"];
+
+/// Paths of `.codestralignore` and `.codestral/ignore` (fixtures, generated code...): both the
+/// in-memory index and the SQLite index skip them, so they never reach the AI context
+pub struct ProjectIgnore {
+    matcher: Gitignore,
+}
+
+impl ProjectIgnore {
+    pub fn load(project_root: &Path) -> Self {
+        let mut builder = GitignoreBuilder::new(project_root);
+        for file in IGNORE_FILES {
+            let path = project_root.join(file);
+            if path.is_file() {
+                // A malformed line is skipped, like git does
+                let _ = builder.add(path);
+            }
+        }
+        Self { matcher: builder.build().unwrap_or_else(|_| Gitignore::empty()) }
+    }
+
+    /// Whether `path`, under the project root, is excluded itself or through a parent directory
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        match path.strip_prefix(self.matcher.path()) {
+            Ok(relative) if !relative.as_os_str().is_empty() => {
+                self.matcher.matched_path_or_any_parents(relative, is_dir).is_ignore()
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Content of `.codestral/.gitignore`: ignore everything but the shareable config
+fn gitignore() -> String {
+    let mut content = String::from(
+        "# Generated by Codestral Companion: index.db, backups, sessions and logs stay local\n*\n!.gitignore\n",
+    );
+    for entry in SHARED_ENTRIES {
+        content.push_str(&format!("!{}\n", entry));
+        if entry.ends_with('/') {
+            content.push_str(&format!("!{}**\n", entry));
+        }
+    }
+    content
+}
+
+/// Create the `.codestral/` directory of a project if needed, with a .gitignore so local
+/// state is not committed by accident. An existing .gitignore is left as the user wrote it
+pub fn ensure(project_root: &Path) -> Result<PathBuf, String> {
+    let dir = project_root.join(DIR_NAME);
+    fs::create_dir_all(&dir)
+        .map_err(|e| format!("Cannot create {} directory: {}", DIR_NAME, e))?;
+
+    let ignore_file = dir.join(".gitignore");
+    if !ignore_file.exists() {
+        fs::write(&ignore_file, gitignore())
+            .map_err(|e| format!("Cannot write {}: {}", ignore_file.display(), e))?;
+    }
+    Ok(dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gitignore_keeps_only_shared_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let codestral = ensure(dir.path()).unwrap();
+        let content = fs::read_to_string(codestral.join(".gitignore")).unwrap();
+        assert!(content.contains("\n*\n"));
+        assert!(content.contains("!mcp_servers.json\n"));
+        assert!(content.contains("!prompts/\n!prompts/**\n"));
+        assert!(!content.contains("index.db\n"));
+
+        fs::write(codestral.join(".gitignore"), "custom\n").unwrap();
+        ensure(dir.path()).unwrap();
+        assert_eq!(fs::read_to_string(codestral.join(".gitignore")).unwrap(), "custom\n");
+    }
+
+    #[test]
+    fn test_ignore_files_keep_paths_out_of_both_indexes() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = crate::wsl::canonicalize(dir.path()).unwrap();
+        for file in ["src/main.rs", "src/api.generated.ts", "tests/fixtures/big.json", "tests/it.rs"] {
+            fs::create_dir_all(root.join(file).parent().unwrap()).unwrap();
+            fs::write(root.join(file), "content").unwrap();
+        }
+        fs::write(root.join(".codestralignore"), "fixtures/\n").unwrap();
+        ensure(&root).unwrap();
+        fs::write(root.join(DIR_NAME).join("ignore"), "# generated\n*.generated.ts\n").unwrap();
+
+        let ignore = ProjectIgnore::load(&root);
+        assert!(ignore.is_ignored(&root.join("tests/fixtures"), true));
+        assert!(ignore.is_ignored(&root.join("tests/fixtures/big.json"), false));
+        assert!(!ignore.is_ignored(&root, true) && !ignore.is_ignored(Path::new("/elsewhere/x.ts"), false));
+
+        let mut files: Vec<String> = crate::persistent_index::indexable_files(&root).iter()
+            .map(|path| path.strip_prefix(&root).unwrap().to_string_lossy().replace('\\', "/"))
+            .collect();
+        files.sort();
+        assert_eq!(files, ["src/main.rs", "tests/it.rs"]);
+
+        let index = crate::indexer::CodebaseIndex::index_with_progress(&root, None, &[], 100, |_, _| {}).unwrap();
+        let mut indexed: Vec<String> = index.files.iter().map(|file| file.relative_path.replace('\\', "/")).collect();
+        indexed.sort();
+        assert_eq!(indexed, ["src/main.rs", "tests/it.rs"]);
+    }
+}
@@ -0,0 +1,157 @@
+use crate::mistral_client::ApiProvider;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// Limits applied to one provider
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct RateLimits {
+    pub requests_per_minute: u32,
+    pub tokens_per_minute: u64,
+}
+
+impl RateLimits {
+    /// Defaults matching the Mistral free tier
+    pub fn default_for(provider: &ApiProvider) -> Self {
+        match provider {
+            ApiProvider::Codestral => Self { requests_per_minute: 30, tokens_per_minute: 500_000 },
+            ApiProvider::MistralAi => Self { requests_per_minute: 60, tokens_per_minute: 500_000 },
+            // No quota on a local server: the server queues requests itself
+            ApiProvider::Ollama => Self { requests_per_minute: 1_000, tokens_per_minute: 100_000_000 },
+            ApiProvider::Custom { .. } => Self { requests_per_minute: 60, tokens_per_minute: 1_000_000 },
+        }
+    }
+
+    /// Read overrides from the `rate_limits` section of settings.json, e.g.
+    /// `"rate_limits": { "MistralAi": { "requests_per_minute": 30, "tokens_per_minute": 200000 } }`
+    fn load(provider: &ApiProvider) -> Self {
+        let default = Self::default_for(provider);
+        let Ok(path) = crate::settings::settings_file() else {
+            return default;
+        };
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+            .and_then(|json| json.get("config")?.get("rate_limits")?.get(provider.name()).cloned())
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or(default)
+    }
+}
+
+#[derive(Debug, Default)]
+struct Window {
+    /// (sent at, estimated tokens) of requests in the last minute
+    requests: VecDeque<(Instant, u64)>,
+    /// Set after a 429 response: nobody sends before this instant
+    blocked_until: Option<Instant>,
+}
+
+/// Sliding-window limiter for one provider
+pub struct ProviderLimiter {
+    limits: Mutex<RateLimits>,
+    window: Mutex<Window>,
+    /// FIFO queue: tokio's mutex grants the lock in request order, so waiting callers are served fairly
+    queue: tokio::sync::Mutex<()>,
+}
+
+impl ProviderLimiter {
+    fn new(limits: RateLimits) -> Self {
+        Self {
+            limits: Mutex::new(limits),
+            window: Mutex::new(Window::default()),
+            queue: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// How long to wait before a request of `tokens` may be sent (None = send now and record it)
+    fn try_admit(&self, tokens: u64) -> Option<Duration> {
+        let limits = *self.limits.lock().unwrap();
+        let mut window = self.window.lock().unwrap();
+        let now = Instant::now();
+
+        if let Some(until) = window.blocked_until {
+            if until > now {
+                return Some(until - now);
+            }
+            window.blocked_until = None;
+        }
+
+        while let Some((sent, _)) = window.requests.front() {
+            if now.duration_since(*sent) >= WINDOW {
+                window.requests.pop_front();
+            } else {
+                break;
+            }
+        }
+
+        let used_tokens: u64 = window.requests.iter().map(|(_, t)| t).sum();
+        let under_requests = (window.requests.len() as u32) < limits.requests_per_minute;
+        // A single oversized request is still allowed once the window is empty
+        let under_tokens = window.requests.is_empty() || used_tokens + tokens <= limits.tokens_per_minute;
+
+        if under_requests && under_tokens {
+            window.requests.push_back((now, tokens));
+            return None;
+        }
+
+        // Wait until the oldest request leaves the window
+        let oldest = window.requests.front().map(|(sent, _)| *sent).unwrap_or(now);
+        Some((oldest + WINDOW).saturating_duration_since(now).max(Duration::from_millis(50)))
+    }
+
+    /// Wait for our turn in the queue, then until the limits allow the request
+    pub async fn acquire(&self, tokens: u64) {
+        let _turn = self.queue.lock().await;
+        while let Some(wait) = self.try_admit(tokens) {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Block every caller for the given duration (used after a 429 response)
+    pub fn penalize(&self, duration: Duration) {
+        let mut window = self.window.lock().unwrap();
+        let until = Instant::now() + duration;
+        if window.blocked_until.map(|b| b < until).unwrap_or(true) {
+            window.blocked_until = Some(until);
+        }
+    }
+
+    pub fn set_limits(&self, limits: RateLimits) {
+        *self.limits.lock().unwrap() = limits;
+    }
+}
+
+fn registry() -> &'static Mutex<HashMap<ApiProvider, Arc<ProviderLimiter>>> {
+    static LIMITERS: OnceLock<Mutex<HashMap<ApiProvider, Arc<ProviderLimiter>>>> = OnceLock::new();
+    LIMITERS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Process-wide limiter shared by every client of this provider
+pub fn limiter_for(provider: &ApiProvider) -> Arc<ProviderLimiter> {
+    let mut limiters = registry().lock().unwrap();
+    limiters
+        .entry(provider.clone())
+        .or_insert_with(|| Arc::new(ProviderLimiter::new(RateLimits::load(provider))))
+        .clone()
+}
+
+/// Override the limits of a provider at runtime
+pub fn configure(provider: &ApiProvider, limits: RateLimits) {
+    limiter_for(provider).set_limits(limits);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limits_requests_and_tokens() {
+        let limiter = ProviderLimiter::new(RateLimits { requests_per_minute: 2, tokens_per_minute: 100 });
+        assert!(limiter.try_admit(40).is_none());
+        assert!(limiter.try_admit(70).is_some());
+        assert!(limiter.try_admit(50).is_none());
+        assert!(limiter.try_admit(1).is_some());
+    }
+}
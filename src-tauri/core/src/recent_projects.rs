@@ -0,0 +1,156 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use crate::settings::settings_file;
+use crate::chat_storage::ChatStorage;
+
+/// Global database of the projects opened from any mode (TUI, chat, agent, GUI), next to settings.json
+const DB_NAME: &str = "projects.db";
+
+/// Projects offered by the picker and the tray
+pub const MAX_RECENT: usize = 10;
+
+/// Project of the `recent_projects` table
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecentProject {
+    pub path: PathBuf,
+    pub last_used: DateTime<Utc>,
+    /// ID of the last conversation saved for this project
+    pub last_conversation: Option<String>,
+}
+
+pub struct RecentProjects {
+    conn: Connection,
+}
+
+impl RecentProjects {
+    pub fn open() -> Result<Self, String> {
+        let settings = settings_file()?;
+        let dir = settings.parent().unwrap_or(Path::new("."));
+        std::fs::create_dir_all(dir).map_err(|e| format!("Cannot create config dir: {}", e))?;
+        Self::open_at(&dir.join(DB_NAME))
+    }
+
+    pub fn open_at(db_path: &Path) -> Result<Self, String> {
+        let conn = Connection::open(db_path)
+            .map_err(|e| format!("Cannot open projects database: {}", e))?;
+        // The GUI, a TUI and an agent run may write at the same time
+        conn.busy_timeout(Duration::from_secs(5))
+            .map_err(|e| format!("Cannot configure projects database: {}", e))?;
+        conn.execute_batch(r"
+            CREATE TABLE IF NOT EXISTS recent_projects (
+                path TEXT PRIMARY KEY,
+                last_used TEXT NOT NULL,
+                last_conversation TEXT
+            );
+        ").map_err(|e| format!("Cannot create projects table: {}", e))?;
+        Ok(Self { conn })
+    }
+
+    /// Mark a project as used now
+    pub fn touch(&self, project: &Path) -> Result<(), String> {
+        self.conn.execute(
+            "INSERT INTO recent_projects (path, last_used) VALUES (?1, ?2)
+             ON CONFLICT(path) DO UPDATE SET last_used = excluded.last_used",
+            params![project.to_string_lossy(), Utc::now().to_rfc3339()],
+        ).map_err(|e| format!("Cannot update recent projects: {}", e))?;
+        Ok(())
+    }
+
+    /// Mark a project as used now, with the conversation just saved
+    pub fn set_last_conversation(&self, project: &Path, conversation_id: &str) -> Result<(), String> {
+        self.conn.execute(
+            "INSERT INTO recent_projects (path, last_used, last_conversation) VALUES (?1, ?2, ?3)
+             ON CONFLICT(path) DO UPDATE SET last_used = excluded.last_used, last_conversation = excluded.last_conversation",
+            params![project.to_string_lossy(), Utc::now().to_rfc3339(), conversation_id],
+        ).map_err(|e| format!("Cannot update recent projects: {}", e))?;
+        Ok(())
+    }
+
+    /// Most recently used projects first
+    pub fn list(&self, limit: usize) -> Result<Vec<RecentProject>, String> {
+        let mut stmt = self.conn.prepare(
+            "SELECT path, last_used, last_conversation FROM recent_projects ORDER BY last_used DESC LIMIT ?1"
+        ).map_err(|e| e.to_string())?;
+        let rows = stmt.query_map(params![limit as i64], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, Option<String>>(2)?))
+        }).map_err(|e| e.to_string())?;
+
+        Ok(rows.flatten()
+            .map(|(path, last_used, last_conversation)| RecentProject {
+                path: PathBuf::from(path),
+                last_used: DateTime::parse_from_rfc3339(&last_used)
+                    .map(|at| at.with_timezone(&Utc))
+                    .unwrap_or_default(),
+                last_conversation,
+            })
+            .collect())
+    }
+
+    /// Last conversation of a project, if any
+    pub fn last_conversation(&self, project: &Path) -> Result<Option<String>, String> {
+        self.conn.query_row(
+            "SELECT last_conversation FROM recent_projects WHERE path = ?1",
+            params![project.to_string_lossy()],
+            |row| row.get::<_, Option<String>>(0),
+        ).optional()
+            .map(Option::flatten)
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// Record that a project was opened; failures only lose the history
+pub fn remember(project: &Path) {
+    if let Ok(recent) = RecentProjects::open() {
+        let _ = recent.touch(project);
+    }
+}
+
+/// Record the conversation saved for a project
+pub fn remember_conversation(project: &Path, conversation_id: &str) {
+    if let Ok(recent) = RecentProjects::open() {
+        let _ = recent.set_last_conversation(project, conversation_id);
+    }
+}
+
+/// Projects to offer: the recent ones, then those with saved chats from before the table.
+/// Directories that no longer exist are left out
+pub fn candidates() -> Vec<PathBuf> {
+    let recent = RecentProjects::open()
+        .and_then(|recent| recent.list(MAX_RECENT))
+        .unwrap_or_default();
+    let with_chats = ChatStorage::new()
+        .and_then(|storage| storage.list_projects())
+        .unwrap_or_default();
+
+    let mut projects: Vec<PathBuf> = Vec::new();
+    for path in recent.into_iter().map(|p| p.path).chain(with_chats.into_iter().map(PathBuf::from)) {
+        if path.is_dir() && !projects.contains(&path) {
+            projects.push(path);
+        }
+    }
+    projects
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recent_projects_keep_last_use_and_conversation() {
+        let dir = tempfile::tempdir().unwrap();
+        let recent = RecentProjects::open_at(&dir.path().join(DB_NAME)).unwrap();
+        recent.touch(Path::new("/projects/api")).unwrap();
+        recent.set_last_conversation(Path::new("/projects/front"), "chat-1").unwrap();
+        std::thread::sleep(Duration::from_millis(5));
+        recent.touch(Path::new("/projects/api")).unwrap();
+
+        let projects = recent.list(MAX_RECENT).unwrap();
+        let paths: Vec<&Path> = projects.iter().map(|p| p.path.as_path()).collect();
+        assert_eq!(paths, vec![Path::new("/projects/api"), Path::new("/projects/front")]);
+        assert_eq!(projects[1].last_conversation.as_deref(), Some("chat-1"));
+        assert_eq!(recent.last_conversation(Path::new("/projects/front")).unwrap().as_deref(), Some("chat-1"));
+        assert_eq!(recent.last_conversation(Path::new("/projects/none")).unwrap(), None);
+    }
+}
@@ -0,0 +1,141 @@
+use std::fs;
+use std::path::Path;
+use crate::differ::{ChangeSet, FileChange};
+use crate::persistent_index::PersistentIndex;
+use crate::symbols::{is_identifier, literal_ranges};
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// `content` with every occurrence of `name` as a whole identifier replaced by `new_name`,
+/// and the number of occurrences; None when there is none. With a grammar for `extension`,
+/// comments and string literals are left as they are
+fn replace_identifier(content: &str, extension: &str, name: &str, new_name: &str) -> Option<(String, usize)> {
+    let literals = literal_ranges(content, extension).unwrap_or_default();
+    let mut modified = String::with_capacity(content.len());
+    let mut count = 0;
+    let mut last = 0;
+    for (start, _) in content.match_indices(name) {
+        let end = start + name.len();
+        let before = content[..start].chars().next_back();
+        let after = content[end..].chars().next();
+        if before.is_some_and(is_identifier_char) || after.is_some_and(is_identifier_char) {
+            continue;
+        }
+        if literals.iter().any(|literal| literal.contains(&start)) {
+            continue;
+        }
+        modified.push_str(&content[last..start]);
+        modified.push_str(new_name);
+        last = end;
+        count += 1;
+    }
+    if count == 0 {
+        return None;
+    }
+    modified.push_str(&content[last..]);
+    Some((modified, count))
+}
+
+/// Project-wide rename of a declaration (`name` or `Parent::name`): its definition and every
+/// whole-word occurrence of the name in the code of the indexed files, one change per file, to preview
+/// before applying. References are matched by text, so a name also declared elsewhere, or a
+/// new name already taken, is refused rather than renamed across unrelated code
+pub fn rename(root: &Path, index: &PersistentIndex, symbol: &str, new_name: &str) -> Result<ChangeSet, String> {
+    let (parent, name) = match symbol.trim().rsplit_once("::") {
+        Some((parent, name)) => (Some(parent), name),
+        None => (None, symbol.trim()),
+    };
+    let new_name = new_name.trim();
+    if !is_identifier(name) || !is_identifier(new_name) {
+        return Err(format!("Cannot rename {} to {}: both must be identifiers", symbol, new_name));
+    }
+    if name == new_name {
+        return Err(format!("{} already has this name", symbol));
+    }
+
+    let declarations = index.find_symbols(parent, name)?;
+    if declarations.is_empty() {
+        return Err(format!("No declaration of {} in the index (run /reindex if it is new)", symbol));
+    }
+    let describe = |(path, symbol): &(String, crate::symbols::Symbol)| {
+        format!("{} {} ({}:{})", symbol.kind, symbol.qualified_name(), path, symbol.start_line)
+    };
+    let others: Vec<String> = index.find_symbols(None, name)?.iter()
+        .filter(|declaration| !declarations.contains(declaration))
+        .map(describe)
+        .collect();
+    if !others.is_empty() {
+        return Err(format!(
+            "{} is also declared as {}: its references cannot be told apart, edit them with write_file",
+            name, others.join(", ")
+        ));
+    }
+    let taken: Vec<String> = index.find_symbols(None, new_name)?.iter().map(describe).collect();
+    if !taken.is_empty() {
+        return Err(format!("{} is already declared as {}", new_name, taken.join(", ")));
+    }
+
+    let mut changes = ChangeSet::default();
+    changes.plan.push(format!("Renommer {} en {}", symbol, new_name));
+    for file in index.list_files()? {
+        let path = root.join(&file.relative_path);
+        let Ok(content) = fs::read_to_string(&path) else { continue };
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+        if let Some((modified, count)) = replace_identifier(&content, extension, name, new_name) {
+            changes.modifications.push(FileChange {
+                path: path.to_string_lossy().to_string(),
+                original: content,
+                modified,
+                description: format!("{} occurrence(s) de {}", count, name),
+            });
+        }
+    }
+    Ok(changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rename_changes_whole_identifiers_only() {
+        assert_eq!(replace_identifier("parse(x); parser; reparse; parse", "", "parse", "read"), Some(("read(x); parser; reparse; read".to_string(), 2)));
+        assert_eq!(replace_identifier("é parse_all", "", "parse", "read"), None);
+        // Comments and strings are prose, interpolations are code
+        assert_eq!(
+            replace_identifier("// parse the input\nlet s = \"parse\"; parse(s);\n", "rs", "parse", "read"),
+            Some(("// parse the input\nlet s = \"parse\"; read(s);\n".to_string(), 1))
+        );
+        assert_eq!(
+            replace_identifier("x = f\"parse: {parse(y)}\"  # parse\n", "py", "parse", "read"),
+            Some(("x = f\"parse: {read(y)}\"  # parse\n".to_string(), 1))
+        );
+        assert_eq!(replace_identifier("const s = `parse ${parse(y)}`;\n", "js", "parse", "read").unwrap().1, 1);
+
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let files = [
+            ("src/config.rs", "pub fn load_config() -> Config {\n    Config::default()\n}\n\npub struct Config;\n"),
+            ("src/main.rs", "use crate::config::load_config;\n\nfn main() {\n    let config = load_config();\n}\n"),
+            ("src/other.rs", "impl A {\n    fn run() {}\n}\n\nimpl B {\n    fn run() {}\n}\n"),
+        ];
+        let index = PersistentIndex::open(root).unwrap();
+        for (path, content) in files {
+            fs::create_dir_all(root.join(path).parent().unwrap()).unwrap();
+            fs::write(root.join(path), content).unwrap();
+            index.index_file(&root.join(path), path, content).unwrap();
+        }
+
+        let changes = rename(root, &index, "load_config", "read_config").unwrap();
+        assert_eq!(changes.modifications.len(), 2);
+        assert_eq!(changes.modifications[1].modified, "use crate::config::read_config;\n\nfn main() {\n    let config = read_config();\n}\n");
+        assert_eq!(changes.modifications[1].description, "2 occurrence(s) de load_config");
+
+        assert!(rename(root, &index, "A::run", "start").unwrap_err().contains("also declared as fn B::run (src/other.rs:6)"));
+        assert!(rename(root, &index, "load_config", "Config").unwrap_err().starts_with("Config is already declared"));
+        assert!(rename(root, &index, "missing", "other").is_err());
+        assert!(rename(root, &index, "load_config", "read-config").is_err());
+    }
+}
@@ -0,0 +1,328 @@
+use crate::backend::{backend, LlmBackend};
+use crate::mistral_client::ApiProvider;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Path of the settings.json shared with the GUI
+pub fn settings_file() -> Result<std::path::PathBuf, String> {
+    // tauri-plugin-store saves to data_dir, not config_dir
+    let data_dir = dirs::data_dir()
+        .ok_or("Cannot find data directory")?
+        .join("com.rony.companion-chat");
+    Ok(data_dir.join("settings.json"))
+}
+
+/// Key of custom endpoints, over `config.custom_api_key` of settings.json
+pub const CUSTOM_KEY_ENV: &str = "CODESTRAL_CUSTOM_API_KEY";
+
+/// `config` section of settings.json, if any
+fn saved_config() -> Option<serde_json::Value> {
+    let content = fs::read_to_string(settings_file().ok()?).ok()?;
+    serde_json::from_str::<serde_json::Value>(&content).ok()?.get("config").cloned()
+}
+
+/// Key for a custom endpoint chosen outside the saved settings (project or `--base-url`):
+/// `CODESTRAL_CUSTOM_API_KEY`, else `config.custom_api_key`, else none
+pub fn custom_api_key() -> String {
+    std::env::var(CUSTOM_KEY_ENV).ok()
+        .or_else(|| saved_config()?.get("custom_api_key")?.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+/// Whether `provider` is a custom endpoint at the same URL as `other`
+fn same_endpoint(provider: &ApiProvider, other: &ApiProvider) -> bool {
+    matches!((provider, other), (ApiProvider::Custom { base_url, .. }, ApiProvider::Custom { base_url: other, .. }) if base_url == other)
+}
+
+/// Key sent to `provider`: the saved key belongs to the saved provider, so a custom endpoint
+/// set elsewhere gets its own and the Mistral key never leaves for another host
+fn key_for(provider: &ApiProvider, saved_provider: &ApiProvider, saved_key: String, custom_key: impl FnOnce() -> String) -> String {
+    match provider {
+        ApiProvider::Custom { .. } if !same_endpoint(provider, saved_provider) => custom_key(),
+        _ => saved_key,
+    }
+}
+
+/// Custom endpoints the user accepted from project settings (`config.trusted_endpoints`)
+fn trusted_endpoints() -> Vec<String> {
+    saved_config()
+        .and_then(|config| serde_json::from_value(config.get("trusted_endpoints")?.clone()).ok())
+        .unwrap_or_default()
+}
+
+/// Whether requests may go to a custom endpoint: accepted before, or the saved provider
+fn is_trusted(base_url: &str) -> bool {
+    trusted_endpoints().iter().any(|url| url == base_url)
+        || load_saved_api_settings().is_some_and(|(_, provider)| {
+            same_endpoint(&provider, &ApiProvider::Custom { base_url: base_url.to_string(), model: String::new() })
+        })
+}
+
+/// Base URL of the custom endpoint a project's settings point to, when the user has not
+/// accepted it yet: a cloned repository could otherwise send its prompts, code included, anywhere
+pub fn unconfirmed_endpoint(project_root: &Path) -> Option<String> {
+    match ProjectSettings::load(project_root).ok()?.provider? {
+        ApiProvider::Custom { base_url, .. } if !is_trusted(&base_url) => Some(base_url),
+        _ => None,
+    }
+}
+
+/// Accept a custom endpoint for every project that points to it
+pub fn trust_endpoint(base_url: &str) -> Result<(), String> {
+    let mut trusted = trusted_endpoints();
+    if !trusted.iter().any(|url| url == base_url) {
+        trusted.push(base_url.to_string());
+    }
+    save_config("trusted_endpoints", serde_json::json!(trusted))
+}
+
+/// Overrides from `<project>/.codestral/settings.json`, merged over the global settings
+///
+/// ```json
+/// { "provider": "Codestral", "model": "codestral-latest", "temperature": 0.2, "language": "English" }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProjectSettings {
+    pub provider: Option<ApiProvider>,
+    pub model: Option<String>,
+    pub temperature: Option<f32>,
+    /// Answer language, instead of French; "auto" answers in the language of each message
+    pub language: Option<String>,
+    /// Commit every applied change set, instead of the global `auto_commit`
+    pub auto_commit: Option<bool>,
+    /// Encrypt index.db (SQLCipher), instead of the global `encrypt_index`
+    pub encrypt_index: Option<bool>,
+    /// Index and send lockfiles and minified code like other files, instead of the global `include_generated`
+    pub include_generated: Option<bool>,
+}
+
+impl ProjectSettings {
+    pub fn load(project_root: &Path) -> Result<Self, String> {
+        let path = project_root.join(".codestral").join("settings.json");
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(&path)
+            .map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+        serde_json::from_str(&content)
+            .map_err(|e| format!("Invalid {}: {}", path.display(), e))
+    }
+}
+
+/// Global API settings with the project overrides applied
+#[derive(Debug, Clone)]
+pub struct ApiSettings {
+    pub api_key: String,
+    pub provider: ApiProvider,
+    pub project: ProjectSettings,
+}
+
+impl ApiSettings {
+    /// Apply the overrides of the project (if any) over the global key, provider, language and model
+    pub fn merge(api_key: String, provider: ApiProvider, project_root: Option<&Path>) -> Result<Self, String> {
+        let mut project = match project_root {
+            Some(root) => ProjectSettings::load(root)?,
+            None => ProjectSettings::default(),
+        };
+        let defaults = GlobalDefaults::load();
+        if project.language.is_none() {
+            project.language = defaults.language;
+        }
+        // The global model was chosen for the global provider
+        if project.model.is_none() && project.provider.as_ref().is_none_or(|p| *p == provider) {
+            project.model = defaults.model;
+        }
+        if let Some(ApiProvider::Custom { base_url, .. }) = &project.provider {
+            if !is_trusted(base_url) {
+                return Err(format!(
+                    "The project settings send requests to {}, an endpoint not confirmed yet: open the project in the CLI or TUI to confirm it",
+                    base_url
+                ));
+            }
+        }
+        let saved_provider = provider;
+        let provider = project.provider.clone().unwrap_or_else(|| saved_provider.clone());
+        let api_key = key_for(&provider, &saved_provider, api_key, custom_api_key);
+        Ok(Self { api_key, provider, project })
+    }
+
+    /// `--base-url` and `--model` of the command line, over the global and project settings
+    pub fn apply_overrides(&mut self, base_url: Option<&str>, model: Option<&str>) {
+        if let Some(model) = model {
+            self.project.model = Some(model.to_string());
+        }
+        if let Some(base_url) = base_url {
+            let model = self.project.model.clone().unwrap_or_default();
+            let provider = ApiProvider::Custom { base_url: base_url.to_string(), model };
+            let api_key = std::mem::take(&mut self.api_key);
+            self.api_key = key_for(&provider, &self.provider, api_key, custom_api_key);
+            self.provider = provider;
+        }
+    }
+
+    /// Backend of the provider, with the project model and temperature
+    pub fn client(&self) -> Arc<dyn LlmBackend> {
+        let client = backend(self.api_key.clone(), self.provider.clone(), self.project.temperature);
+        match &self.project.model {
+            Some(model) => client.for_model(model),
+            None => client,
+        }
+    }
+
+    /// Base system prompt followed by the project language rule
+    pub fn system_prompt(&self, base: &str) -> String {
+        match &self.project.language {
+            // The rule depends on each message, see `language::reply_rule`
+            _ if self.auto_language() => base.to_string(),
+            Some(language) => format!("{}\nLANGUE: ce projet demande des réponses en {} (remplace la règle sur le français).\n", base, language),
+            None => base.to_string(),
+        }
+    }
+
+    /// `"language": "auto"`: the reply language follows the user's messages
+    pub fn auto_language(&self) -> bool {
+        self.project.language.as_deref().is_some_and(|l| l.eq_ignore_ascii_case("auto"))
+    }
+}
+
+/// Answer language and model chosen for every project (`config.language`, `config.model`
+/// of settings.json), used when the project does not set its own
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct GlobalDefaults {
+    pub language: Option<String>,
+    pub model: Option<String>,
+}
+
+impl GlobalDefaults {
+    pub fn load() -> Self {
+        let Ok(path) = settings_file() else {
+            return Self::default();
+        };
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+            .and_then(|json| json.get("config").cloned())
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// API key and provider saved in settings.json, if any
+pub fn load_saved_api_settings() -> Option<(String, ApiProvider)> {
+    api_settings(&saved_config()?)
+}
+
+/// API key and provider of a `config` section; None until a needed key is set
+fn api_settings(config: &serde_json::Value) -> Option<(String, ApiProvider)> {
+    let api_key = config.get("api_key")
+        .and_then(|v| v.as_str())
+        .unwrap_or("")
+        .to_string();
+    
+    // "Codestral", "Ollama"... or { "Custom": { "base_url": ..., "model": ... } }
+    let provider = config.get("provider")
+        .cloned()
+        .and_then(|v| serde_json::from_value::<ApiProvider>(v).ok())
+        .unwrap_or_default();
+    if api_key.is_empty() && provider.needs_api_key() {
+        return None;
+    }
+    
+    Some((api_key, provider))
+}
+
+/// Save the API key and provider, keeping the other settings of the file
+pub fn save_api_settings(api_key: &str, provider: &ApiProvider) -> Result<(), String> {
+    save_config("api_key", serde_json::json!(api_key))?;
+    save_config("provider", serde_json::json!(provider))
+}
+
+/// Set one entry of the `config` section, keeping the rest of the file
+fn save_config(key: &str, value: serde_json::Value) -> Result<(), String> {
+    write_config(&settings_file()?, key, value)
+}
+
+fn write_config(settings_path: &Path, key: &str, value: serde_json::Value) -> Result<(), String> {
+    if let Some(dir) = settings_path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Cannot create config dir: {}", e))?;
+    }
+    
+    let mut settings = fs::read_to_string(settings_path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+        .filter(|json| json.is_object())
+        .unwrap_or_else(|| serde_json::json!({}));
+    
+    if !settings["config"].is_object() {
+        settings["config"] = serde_json::json!({});
+    }
+    settings["config"][key] = value;
+    
+    let json = serde_json::to_string_pretty(&settings)
+        .map_err(|e| format!("Serialize error: {}", e))?;
+    
+    fs::write(settings_path, json)
+        .map_err(|e| format!("Write error: {}", e))
+}
+
+/// Check the key with a test request. Runs on its own thread and runtime,
+/// since callers may already be inside a Tokio runtime
+pub fn validate_api_key(api_key: &str, provider: &ApiProvider) -> Result<(), String> {
+    let client = backend(api_key.to_string(), provider.clone(), None);
+    std::thread::spawn(move || {
+        let runtime = tokio::runtime::Runtime::new().map_err(|e| e.to_string())?;
+        runtime.block_on(client.test_connection()).map_err(|e| e.to_string())
+    })
+    .join()
+    .map_err(|_| "Vérification interrompue".to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn custom(base_url: &str) -> ApiProvider {
+        ApiProvider::Custom { base_url: base_url.to_string(), model: "m".to_string() }
+    }
+
+    #[test]
+    fn test_mistral_key_never_sent_to_another_endpoint() {
+        let custom_key = || "custom".to_string();
+
+        // Configured Custom endpoint: the saved key is its own
+        let saved = custom("http://localhost:8000/v1");
+        assert_eq!(key_for(&custom("http://localhost:8000/v1"), &saved, "saved".to_string(), custom_key), "saved");
+        assert_eq!(key_for(&custom("https://evil.example/v1"), &saved, "saved".to_string(), custom_key), "custom");
+
+        // Saved Mistral key: kept for Mistral providers, withheld from custom ones
+        assert_eq!(key_for(&ApiProvider::Codestral, &ApiProvider::MistralAi, "mistral".to_string(), custom_key), "mistral");
+        assert_eq!(key_for(&custom("https://evil.example/v1"), &ApiProvider::MistralAi, "mistral".to_string(), String::new), "");
+    }
+
+    #[test]
+    fn test_saved_key_keeps_the_other_settings() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data/settings.json");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, r#"{ "theme": "dark", "config": { "language": "fr" } }"#).unwrap();
+
+        write_config(&path, "api_key", serde_json::json!("")).unwrap();
+        let read = || serde_json::from_str::<serde_json::Value>(&fs::read_to_string(&path).unwrap()).unwrap();
+        // A key is needed for Mistral: the form is shown again
+        assert_eq!(api_settings(&read()["config"]), None);
+
+        write_config(&path, "provider", serde_json::json!(custom("http://localhost:8000/v1"))).unwrap();
+        let settings = read();
+        assert_eq!(api_settings(&settings["config"]), Some((String::new(), custom("http://localhost:8000/v1"))));
+        assert_eq!((settings["theme"].as_str(), settings["config"]["language"].as_str()), (Some("dark"), Some("fr")));
+
+        // First run: neither the file nor its directory exist yet
+        let fresh = dir.path().join("new/settings.json");
+        write_config(&fresh, "api_key", serde_json::json!("sk")).unwrap();
+        let config = &serde_json::from_str::<serde_json::Value>(&fs::read_to_string(&fresh).unwrap()).unwrap()["config"];
+        assert_eq!(api_settings(config), Some(("sk".to_string(), ApiProvider::MistralAi)));
+    }
+}
@@ -0,0 +1,425 @@
+use std::ops::Range;
+use std::sync::OnceLock;
+use regex::Regex;
+use tree_sitter::{Language, Node, Parser};
+
+/// Declaration found in a file, with its line range (1-based, inclusive)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Symbol {
+    pub name: String,
+    /// Enclosing impl/class/trait/module, or the receiver of a Go method
+    pub parent: Option<String>,
+    /// Declaring keyword: fn, struct, impl, class, def...
+    pub kind: String,
+    pub start_line: usize,
+    pub end_line: usize,
+}
+
+impl Symbol {
+    /// `Parent::name`, or the bare name
+    pub fn qualified_name(&self) -> String {
+        match &self.parent {
+            Some(parent) => format!("{}::{}", parent, self.name),
+            None => self.name.clone(),
+        }
+    }
+}
+
+/// Kinds whose body holds other symbols
+const CONTAINER_KINDS: &[&str] = &["impl", "trait", "class", "mod", "interface", "namespace"];
+
+/// Grammar for an extension; other languages go through the line-based scan
+fn grammar(extension: &str) -> Option<Language> {
+    let language = match extension {
+        "rs" => tree_sitter_rust::LANGUAGE,
+        "py" => tree_sitter_python::LANGUAGE,
+        "js" | "jsx" | "mjs" | "cjs" => tree_sitter_javascript::LANGUAGE,
+        "ts" | "mts" | "cts" => tree_sitter_typescript::LANGUAGE_TYPESCRIPT,
+        "tsx" => tree_sitter_typescript::LANGUAGE_TSX,
+        _ => return None,
+    };
+    Some(language.into())
+}
+
+/// Declaring keyword of a syntax node, across the Rust, Python, JS and TS grammars
+fn node_keyword(kind: &str) -> Option<&'static str> {
+    Some(match kind {
+        "function_item" | "function_signature_item" => "fn",
+        "struct_item" => "struct",
+        "enum_item" | "enum_declaration" => "enum",
+        "trait_item" => "trait",
+        "impl_item" => "impl",
+        "mod_item" => "mod",
+        "type_item" | "type_alias_declaration" => "type",
+        "const_item" => "const",
+        "static_item" => "static",
+        "macro_definition" => "macro_rules",
+        "function_definition" => "def",
+        "class_definition" | "class_declaration" | "abstract_class_declaration" => "class",
+        "function_declaration" | "generator_function_declaration" | "method_definition"
+        | "method_signature" | "abstract_method_signature" => "function",
+        "interface_declaration" => "interface",
+        "internal_module" => "namespace",
+        _ => return None,
+    })
+}
+
+/// Name declared by a node; an impl is named after its type, without path or generics
+fn node_name(node: Node, keyword: &str, source: &[u8]) -> Option<String> {
+    let field = if keyword == "impl" { "type" } else { "name" };
+    let text = node.child_by_field_name(field)?.utf8_text(source).ok()?;
+    let name = text.split('<').next().unwrap_or("").rsplit("::").next().unwrap_or("").trim();
+    is_identifier(name).then(|| name.to_string())
+}
+
+/// 1-based line range of a node; a trailing line break does not count as a line
+fn line_range(node: Node) -> (usize, usize) {
+    let (start, end) = (node.start_position(), node.end_position());
+    let end_line = if end.column == 0 && end.row > start.row { end.row } else { end.row + 1 };
+    (start.row + 1, end_line)
+}
+
+/// Declarations in a syntax tree, each with the innermost container around it. Walks with a
+/// cursor rather than recursing, so deeply nested code cannot overflow the stack
+fn collect(root: Node, source: &[u8], symbols: &mut Vec<Symbol>) {
+    let mut cursor = root.walk();
+    // Containers open around the cursor, innermost last, with the node that opened each
+    let mut containers: Vec<(usize, String)> = Vec::new();
+    loop {
+        let node = cursor.node();
+        if node.is_named() {
+            let container = containers.last().map(|(_, name)| name.clone());
+            let declared = node_keyword(node.kind()).and_then(|keyword| Some((keyword, node_name(node, keyword, source)?)));
+            if let Some((keyword, name)) = declared {
+                // Python decorators belong to the definition they wrap
+                let outer = node.parent().filter(|parent| parent.kind() == "decorated_definition").unwrap_or(node);
+                symbols.push(Symbol {
+                    name: name.clone(),
+                    parent: container,
+                    kind: keyword.to_string(),
+                    start_line: line_range(outer).0,
+                    end_line: line_range(node).1,
+                });
+                if CONTAINER_KINDS.contains(&keyword) {
+                    containers.push((node.id(), name));
+                }
+            } else if is_module_const(node) {
+                // JS/TS `const handler = ...` at module level
+                let mut declarators = node.walk();
+                for declarator in node.named_children(&mut declarators) {
+                    let Some(name) = declarator.child_by_field_name("name").filter(|name| name.kind() == "identifier") else {
+                        continue;
+                    };
+                    let Ok(name) = name.utf8_text(source) else {
+                        continue;
+                    };
+                    let (start_line, end_line) = line_range(node);
+                    symbols.push(Symbol { name: name.to_string(), parent: None, kind: "const".to_string(), start_line, end_line });
+                }
+            }
+        }
+
+        if cursor.goto_first_child() {
+            continue;
+        }
+        // Climb out of finished nodes, closing the containers they opened
+        loop {
+            if containers.last().is_some_and(|(id, _)| *id == cursor.node().id()) {
+                containers.pop();
+            }
+            if cursor.goto_next_sibling() {
+                break;
+            }
+            if !cursor.goto_parent() {
+                return;
+            }
+        }
+    }
+}
+
+/// A `const` declaration directly in a JS/TS module
+fn is_module_const(node: Node) -> bool {
+    node.kind() == "lexical_declaration"
+        && node.child(0).is_some_and(|keyword| keyword.kind() == "const")
+        && node.parent().is_some_and(|parent| matches!(parent.kind(), "program" | "export_statement"))
+}
+
+/// Declarations found by parsing, or None when the language has no grammar here
+fn extract_parsed(content: &str, extension: &str) -> Option<Vec<Symbol>> {
+    let mut parser = Parser::new();
+    parser.set_language(&grammar(extension)?).ok()?;
+    let tree = parser.parse(content, None)?;
+    let mut symbols = Vec::new();
+    collect(tree.root_node(), content.as_bytes(), &mut symbols);
+    Some(symbols)
+}
+
+/// Byte ranges of the comments and string literals of a file, without the code of `${}` or
+/// f-string interpolations; None when the language has no grammar here
+pub fn literal_ranges(content: &str, extension: &str) -> Option<Vec<Range<usize>>> {
+    let mut parser = Parser::new();
+    parser.set_language(&grammar(extension)?).ok()?;
+    let tree = parser.parse(content, None)?;
+    let mut ranges = Vec::new();
+    collect_literals(tree.root_node(), &mut ranges);
+    Some(ranges)
+}
+
+fn collect_literals(node: Node, ranges: &mut Vec<Range<usize>>) {
+    let kind = node.kind();
+    let literal = kind.ends_with("comment")
+        || matches!(kind, "string_literal" | "raw_string_literal" | "char_literal" | "string" | "template_string");
+    let mut cursor = node.walk();
+    if !literal {
+        for child in node.children(&mut cursor) {
+            collect_literals(child, ranges);
+        }
+        return;
+    }
+    let mut start = node.start_byte();
+    for child in node.children(&mut cursor) {
+        if matches!(child.kind(), "interpolation" | "template_substitution") {
+            ranges.push(start..child.start_byte());
+            collect_literals(child, ranges);
+            start = child.end_byte();
+        }
+    }
+    ranges.push(start..node.end_byte());
+}
+
+/// Declaration line, same keywords as `persistent_index::is_symbol_line`
+fn declaration_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(
+        r"^(\s*)(pub(\([^)]*\))?\s+)?(export\s+)?(default\s+)?(async\s+)?(unsafe\s+)?(fn|struct|enum|trait|impl|mod|type|const|static|macro_rules!|function|class|interface|def|func)\b(.*)$"
+    ).unwrap())
+}
+
+/// Rust/Python/JS-style identifier
+pub fn is_identifier(word: &str) -> bool {
+    word.chars().next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && word.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Leading identifier of a string (after spaces)
+fn leading_identifier(text: &str) -> Option<String> {
+    let name: String = text.trim_start()
+        .chars()
+        .take_while(|c| c.is_alphanumeric() || *c == '_')
+        .collect();
+    is_identifier(&name).then_some(name)
+}
+
+/// Drop a leading `<...>` generic list
+fn skip_generics(text: &str) -> &str {
+    let text = text.trim_start();
+    if !text.starts_with('<') {
+        return text;
+    }
+    let mut depth = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '<' => depth += 1,
+            '>' => {
+                depth -= 1;
+                if depth == 0 {
+                    return &text[i + 1..];
+                }
+            }
+            _ => {}
+        }
+    }
+    ""
+}
+
+/// Name (and Go receiver) declared by the rest of a declaration line
+fn declared_name(kind: &str, rest: &str) -> Option<(String, Option<String>)> {
+    match kind {
+        "impl" => {
+            // impl<T> Trait for Type<T>: the methods belong to Type
+            let rest = skip_generics(rest);
+            let target = rest.split(" for ").nth(1).unwrap_or(rest);
+            let path: String = target.trim_start()
+                .chars()
+                .take_while(|c| c.is_alphanumeric() || *c == '_' || *c == ':')
+                .collect();
+            let name = path.rsplit("::").next().unwrap_or("").to_string();
+            is_identifier(&name).then_some((name, None))
+        }
+        "func" => {
+            // Go method: func (s *Server) Name(...)
+            let rest = rest.trim_start();
+            if let Some(receiver) = rest.strip_prefix('(') {
+                let end = receiver.find(')')?;
+                let receiver_type = receiver[..end].split_whitespace().last()?.trim_start_matches('*');
+                let name = leading_identifier(&receiver[end + 1..])?;
+                let parent = is_identifier(receiver_type).then(|| receiver_type.to_string());
+                Some((name, parent))
+            } else {
+                leading_identifier(rest).map(|name| (name, None))
+            }
+        }
+        _ => leading_identifier(rest).map(|name| (name, None)),
+    }
+}
+
+/// Last line of a brace-delimited declaration: the matching `}`, or the `;` of a bodiless one
+fn brace_end(lines: &[&str], start: usize) -> usize {
+    let mut depth = 0i32;
+    let mut opened = false;
+    for (i, line) in lines.iter().enumerate().skip(start) {
+        let code = line.split("//").next().unwrap_or("");
+        for c in code.chars() {
+            match c {
+                '{' => {
+                    depth += 1;
+                    opened = true;
+                }
+                '}' => depth -= 1,
+                ';' if !opened && depth == 0 => return i,
+                _ => {}
+            }
+            if opened && depth <= 0 {
+                return i;
+            }
+        }
+    }
+    start
+}
+
+/// Last line of an indentation-delimited declaration (Python)
+fn indent_end(lines: &[&str], start: usize, indent: usize) -> usize {
+    let mut end = start;
+    for (i, line) in lines.iter().enumerate().skip(start + 1) {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if line.len() - line.trim_start().len() <= indent {
+            break;
+        }
+        end = i;
+    }
+    end
+}
+
+/// Declarations of a file with their line ranges and enclosing container
+pub fn extract(content: &str, extension: &str) -> Vec<Symbol> {
+    extract_parsed(content, extension).unwrap_or_else(|| extract_lines(content, extension))
+}
+
+/// Line-based scan: declaration keywords, then braces or indentation for the end line
+fn extract_lines(content: &str, extension: &str) -> Vec<Symbol> {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut symbols: Vec<Symbol> = Vec::new();
+
+    for (i, line) in lines.iter().enumerate() {
+        let Some(caps) = declaration_re().captures(line) else {
+            continue;
+        };
+        let kind = &caps[8];
+        let Some((name, receiver)) = declared_name(kind, &caps[9]) else {
+            continue;
+        };
+        let end = if extension == "py" {
+            indent_end(&lines, i, caps[1].len())
+        } else {
+            brace_end(&lines, i)
+        };
+        symbols.push(Symbol {
+            name,
+            parent: receiver,
+            kind: kind.trim_end_matches('!').to_string(),
+            start_line: i + 1,
+            end_line: end + 1,
+        });
+    }
+
+    // Innermost container whose body holds the symbol
+    for i in 0..symbols.len() {
+        if symbols[i].parent.is_some() {
+            continue;
+        }
+        let (start, end) = (symbols[i].start_line, symbols[i].end_line);
+        symbols[i].parent = symbols.iter()
+            .filter(|s| CONTAINER_KINDS.contains(&s.kind.as_str()))
+            .filter(|s| s.start_line < start && end <= s.end_line)
+            .max_by_key(|s| s.start_line)
+            .map(|s| s.name.clone());
+    }
+
+    symbols
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extracts_methods_with_their_container() {
+        let rust = "pub struct Client;\n\nimpl<T> Send for Client {\n    pub async fn chat(&self) -> String {\n        if x { y }\n    }\n}\n\nconst LIMIT: usize = 3;\n";
+        let symbols = extract(rust, "rs");
+        let found: Vec<(String, usize, usize)> = symbols.iter()
+            .map(|s| (s.qualified_name(), s.start_line, s.end_line))
+            .collect();
+        assert_eq!(found, vec![
+            ("Client".to_string(), 1, 1),
+            ("Client".to_string(), 3, 7),
+            ("Client::chat".to_string(), 4, 6),
+            ("LIMIT".to_string(), 9, 9),
+        ]);
+
+        let python = "class Repo:\n    def save(self):\n        pass\n\n    def load(self):\n        return 1\n\ndef main():\n    pass\n";
+        let names: Vec<(String, usize, usize)> = extract(python, "py").iter()
+            .map(|s| (s.qualified_name(), s.start_line, s.end_line))
+            .collect();
+        assert_eq!(names[1], ("Repo::save".to_string(), 2, 3));
+        assert_eq!(names[2], ("Repo::load".to_string(), 5, 6));
+        assert_eq!(names[3], ("main".to_string(), 8, 9));
+
+        let go = extract("func (s *Server) Start() error {\n\treturn nil\n}\n", "go");
+        assert_eq!(go[0].qualified_name(), "Server::Start");
+    }
+
+    fn ranges(symbols: &[Symbol]) -> Vec<(String, String, usize, usize)> {
+        symbols.iter().map(|s| (s.kind.clone(), s.qualified_name(), s.start_line, s.end_line)).collect()
+    }
+
+    #[test]
+    fn test_parses_what_the_line_scan_gets_wrong() {
+        // Multi-line signature, a brace inside a string, an item nested in a function
+        let rust = "pub fn connect(\n    url: &str,\n) -> Result<(), String> {\n    let open = \"{\";\n    fn helper() {}\n    Ok(())\n}\n\nmod net {\n    pub struct Socket;\n}\n";
+        assert_eq!(ranges(&extract(rust, "rs")), vec![
+            ("fn".to_string(), "connect".to_string(), 1, 7),
+            ("fn".to_string(), "helper".to_string(), 5, 5),
+            ("mod".to_string(), "net".to_string(), 9, 11),
+            ("struct".to_string(), "net::Socket".to_string(), 10, 10),
+        ]);
+        assert_ne!(extract_lines(rust, "rs")[0].end_line, 7);
+
+        let python = "class Repo:\n    @property\n    def path(self):\n        return \"}\"\n";
+        assert_eq!(ranges(&extract(python, "py"))[1], ("def".to_string(), "Repo::path".to_string(), 2, 4));
+
+        let javascript = "export const API = '/v1';\n\nexport class Client {\n  async fetch(path) {\n    return `${API}{${path}`;\n  }\n}\n";
+        assert_eq!(ranges(&extract(javascript, "js")), vec![
+            ("const".to_string(), "API".to_string(), 1, 1),
+            ("class".to_string(), "Client".to_string(), 3, 7),
+            ("function".to_string(), "Client::fetch".to_string(), 4, 6),
+        ]);
+
+        let typescript = "interface Store<T> {\n  get(key: string): T;\n}\n\ntype Id = string;\n\nfunction load<T>(\n  store: Store<T>,\n): T {\n  return store.get('{');\n}\n";
+        assert_eq!(ranges(&extract(typescript, "ts")), vec![
+            ("interface".to_string(), "Store".to_string(), 1, 3),
+            ("function".to_string(), "Store::get".to_string(), 2, 2),
+            ("type".to_string(), "Id".to_string(), 5, 5),
+            ("function".to_string(), "load".to_string(), 7, 11),
+        ]);
+    }
+
+    #[test]
+    fn test_deeply_nested_code_does_not_overflow() {
+        let depth = 100_000;
+        let rust = format!("fn deep() -> i32 {{\n    {}1{}\n}}\n\nstruct After;\n", "(".repeat(depth), ")".repeat(depth));
+        let names: Vec<String> = extract(&rust, "rs").iter().map(Symbol::qualified_name).collect();
+        assert_eq!(names, ["deep", "After"]);
+    }
+}
@@ -0,0 +1,1202 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::fs;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use regex::Regex;
+use crate::mode::ChatMode;
+use crate::differ::ChangeSet;
+use crate::docs_lookup;
+use crate::rename;
+use crate::persistent_index::PersistentIndex;
+use crate::custom_tools::{CustomTools, ParamType, ToolPermission};
+use crate::workspace::Workspace;
+use crate::wsl;
+
+/// Tool call parsed from AI response
+#[derive(Debug, Clone)]
+pub struct ToolCall {
+    pub name: String,
+    pub params: std::collections::HashMap<String, String>,
+}
+
+/// Tool execution result
+#[derive(Debug)]
+pub struct ToolResult {
+    pub name: String,
+    pub success: bool,
+    pub output: String,
+    pub needs_confirmation: bool,
+}
+
+/// Tools a chat mode may use, in the documentation and when executing
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ToolAccess {
+    /// ASK: no tools
+    None,
+    /// PLAN: tools that do not modify the project
+    ReadOnly,
+    /// PLAN+: read-only tools, and writing tools that are only previewed
+    Preview,
+    /// CODE and AUTO: every tool
+    All,
+}
+
+impl ToolAccess {
+    pub fn for_mode(mode: ChatMode, preview: bool) -> Self {
+        match mode {
+            ChatMode::Ask => ToolAccess::None,
+            ChatMode::Plan if preview => ToolAccess::Preview,
+            ChatMode::Plan => ToolAccess::ReadOnly,
+            ChatMode::Code | ChatMode::Auto => ToolAccess::All,
+        }
+    }
+
+    /// Whether a tool may be used; `previewable` tools are intercepted by PLAN+ instead of running
+    pub fn allows(&self, writes: bool, previewable: bool) -> bool {
+        match self {
+            ToolAccess::None => false,
+            ToolAccess::ReadOnly => !writes,
+            ToolAccess::Preview => !writes || previewable,
+            ToolAccess::All => true,
+        }
+    }
+
+    /// Reason given to the model when it calls a tool the mode does not allow
+    pub fn denial(&self, tool: &str, mode: ChatMode) -> String {
+        match self {
+            ToolAccess::None => format!("Tool {} is not available: no tools in {} mode", tool, mode),
+            _ => format!("Tool {} is not available: {} mode is read-only", tool, mode),
+        }
+    }
+}
+
+/// Names of the built-in tools (custom tools cannot override them)
+pub const BUILTIN_TOOLS: &[&str] = &[
+    "read_file", "write_file", "list_directory", "search_in_files", "search_index", "lookup_docs", "rename_symbol", "execute_bash", "ask_user",
+];
+
+/// Dangerous commands that require user confirmation
+const DANGEROUS_COMMANDS: &[&str] = &[
+    "rm", "rmdir", "sudo", "chmod", "chown", "dd", "mkfs",
+    "kill", "pkill", "killall", "shutdown", "reboot", "halt",
+    "format", "fdisk", "parted", "mount", "umount",
+];
+
+/// Check if a command is potentially dangerous
+pub fn is_dangerous_command(command: &str) -> bool {
+    let first_word = command.split_whitespace().next().unwrap_or("");
+    
+    // Check if command starts with dangerous word
+    for dangerous in DANGEROUS_COMMANDS {
+        if first_word == *dangerous || first_word.ends_with(&format!("/{}", dangerous)) {
+            return true;
+        }
+    }
+    
+    // Check for piped dangerous commands
+    for part in command.split('|').chain(command.split("&&")).chain(command.split(";")) {
+        let trimmed = part.trim();
+        let first = trimmed.split_whitespace().next().unwrap_or("");
+        for dangerous in DANGEROUS_COMMANDS {
+            if first == *dangerous || first.ends_with(&format!("/{}", dangerous)) {
+                return true;
+            }
+        }
+    }
+    
+    false
+}
+
+/// Check if path is within project directory
+pub fn is_path_within_project(path: &Path, project_root: &Path) -> bool {
+    match wsl::canonicalize(path) {
+        Ok(canonical) => canonical.starts_with(project_root),
+        Err(_) => {
+            // Path doesn't exist yet, check parent
+            if let Some(parent) = path.parent() {
+                if parent.as_os_str().is_empty() {
+                    // Relative path, assume OK
+                    true
+                } else {
+                    is_path_within_project(parent, project_root)
+                }
+            } else {
+                false
+            }
+        }
+    }
+}
+
+/// Resolve path relative to project root
+pub fn resolve_path(path_str: &str, project_root: &Path) -> PathBuf {
+    if let Some(translated) = wsl::translate(path_str, project_root) {
+        return translated;
+    }
+    let path = Path::new(path_str);
+    if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        project_root.join(path)
+    }
+}
+
+/// Parse tool calls from AI response
+pub fn parse_tool_calls(response: &str) -> Vec<ToolCall> {
+    let mut tools = Vec::new();
+    
+    // Pattern: <tool_call>...</tool_call>
+    let tool_call_re = Regex::new(r"(?s)<tool_call>(.*?)</tool_call>").unwrap();
+    let name_re = Regex::new(r"(?s)<name>(.*?)</name>").unwrap();
+    let params_re = Regex::new(r"(?s)<params>(.*?)</params>").unwrap();
+    // No backreference in the regex crate: the closing tag is looked up after each opening one
+    let open_re = Regex::new(r"<(\w+)>").unwrap();
+    
+    for cap in tool_call_re.captures_iter(response) {
+        let content = &cap[1];
+        
+        let name = name_re.captures(content)
+            .map(|c| c[1].trim().to_string())
+            .unwrap_or_default();
+        
+        let mut params = std::collections::HashMap::new();
+        
+        if let Some(params_cap) = params_re.captures(content) {
+            let params_content = &params_cap[1];
+            let mut rest = params_content;
+            while let Some(open) = open_re.captures(rest) {
+                let key = &open[1];
+                let after = &rest[open.get(0).unwrap().end()..];
+                let close = format!("</{}>", key);
+                match after.find(&close) {
+                    Some(end) => {
+                        params.insert(key.to_string(), after[..end].trim().to_string());
+                        rest = &after[end + close.len()..];
+                    }
+                    None => rest = after,
+                }
+            }
+        }
+        
+        if !name.is_empty() {
+            tools.push(ToolCall { name, params });
+        }
+    }
+    
+    tools
+}
+
+/// Whether a built-in tool parameter must be given
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Presence {
+    Required,
+    /// Must be given, but may be empty (content of an empty file)
+    RequiredMayBeEmpty,
+    Optional,
+}
+
+/// A built-in tool and its parameters: (name, type, presence)
+type ParamSpec = (&'static str, &'static [(&'static str, ParamType, Presence)]);
+
+/// Parameters of the built-in tools
+const BUILTIN_PARAMS: &[ParamSpec] = &[
+    ("read_file", &[
+        ("path", ParamType::String, Presence::Required),
+        ("start_line", ParamType::Integer, Presence::Optional),
+        ("end_line", ParamType::Integer, Presence::Optional),
+    ]),
+    ("write_file", &[
+        ("path", ParamType::String, Presence::Required),
+        ("content", ParamType::String, Presence::RequiredMayBeEmpty),
+    ]),
+    ("list_directory", &[("path", ParamType::String, Presence::Optional)]),
+    ("search_in_files", &[
+        ("query", ParamType::String, Presence::Required),
+        ("path", ParamType::String, Presence::Optional),
+    ]),
+    ("search_index", &[
+        ("query", ParamType::String, Presence::Required),
+        ("limit", ParamType::Integer, Presence::Optional),
+    ]),
+    ("lookup_docs", &[("symbol", ParamType::String, Presence::Required)]),
+    ("rename_symbol", &[
+        ("symbol", ParamType::String, Presence::Required),
+        ("new_name", ParamType::String, Presence::Required),
+    ]),
+    ("execute_bash", &[("command", ParamType::String, Presence::Required)]),
+    ("ask_user", &[("fields", ParamType::String, Presence::Required)]),
+];
+
+/// Check a call against the parameters of its tool before running it. The error names the
+/// missing or malformed parameter and lists the expected ones, so the model can fix its call
+pub fn validate_params(tool: &ToolCall, custom_tools: &CustomTools) -> Result<(), String> {
+    let (specs, error): (Vec<(String, ParamType, bool)>, Option<String>) = if let Some((_, specs)) = BUILTIN_PARAMS.iter().find(|(name, _)| *name == tool.name) {
+        let error = specs.iter().find_map(|(name, param_type, presence)| match tool.params.get(*name) {
+            None if *presence != Presence::Optional => Some(missing_param(name, tool)),
+            Some(value) if value.trim().is_empty() => {
+                (*presence == Presence::Required).then(|| missing_param(name, tool))
+            }
+            Some(value) => param_type.check(name, value).err(),
+            None => None,
+        });
+        let specs = specs.iter()
+            .map(|(name, param_type, presence)| (name.to_string(), *param_type, *presence != Presence::Optional))
+            .collect();
+        (specs, error)
+    } else if let Some(custom) = custom_tools.get(&tool.name) {
+        let specs = custom.params.iter()
+            .map(|(name, param)| (name.clone(), param.param_type, param.required && param.default.is_none()))
+            .collect();
+        (specs, custom.resolve_params(&tool.params).err())
+    } else {
+        // Unknown and MCP tools are reported by their own executor
+        return Ok(());
+    };
+
+    match error {
+        None => Ok(()),
+        Some(error) => {
+            let expected: Vec<String> = specs.iter()
+                .map(|(name, param_type, required)| {
+                    format!("<{}> {}{}", name, param_type.name(), if *required { " (required)" } else { "" })
+                })
+                .collect();
+            Err(format!(
+                "Invalid call to {}: {}\nExpected parameters: {}",
+                tool.name, error,
+                if expected.is_empty() { "none".to_string() } else { expected.join(", ") }
+            ))
+        }
+    }
+}
+
+/// Error for a missing parameter, with the parameters given instead (`file` for `path`...)
+fn missing_param(name: &str, tool: &ToolCall) -> String {
+    let mut given: Vec<&str> = tool.params.keys().map(String::as_str).collect();
+    given.sort();
+    if given.is_empty() {
+        format!("missing required parameter '{}'", name)
+    } else {
+        format!("missing required parameter '{}' (got: {})", name, given.join(", "))
+    }
+}
+
+/// Execute a tool and return the result
+pub fn execute_tool(tool: &ToolCall, workspace: &Workspace, custom_tools: &CustomTools) -> ToolResult {
+    if let Err(output) = validate_params(tool, custom_tools) {
+        return ToolResult { name: tool.name.clone(), success: false, output, needs_confirmation: false };
+    }
+    match tool.name.as_str() {
+        "read_file" => execute_read_file(tool, workspace),
+        "write_file" => execute_write_file(tool, workspace),
+        "list_directory" => execute_list_directory(tool, workspace),
+        "search_in_files" => execute_search_in_files(tool, workspace),
+        "search_index" => execute_search_index(tool, workspace),
+        "lookup_docs" => execute_lookup_docs(tool, workspace),
+        "rename_symbol" => execute_rename_symbol(tool, workspace),
+        "execute_bash" => execute_bash(tool, workspace),
+        name if custom_tools.get(name).is_some() => execute_custom_tool(tool, workspace, custom_tools),
+        _ => ToolResult {
+            name: tool.name.clone(),
+            success: false,
+            output: format!("Unknown tool: {}", tool.name),
+            needs_confirmation: false,
+        },
+    }
+}
+
+/// Calls of a response running at the same time at most
+pub const MAX_PARALLEL_TOOLS: usize = 4;
+
+/// Built-in tools that only read the project, independent of each other
+pub fn is_parallel_safe(name: &str) -> bool {
+    matches!(name, "read_file" | "list_directory" | "search_in_files" | "search_index" | "lookup_docs")
+}
+
+/// Run the leading `selected` calls concurrently, at most MAX_PARALLEL_TOOLS at a time.
+/// The first other call ends the run: a read after a write must see it. Results are in
+/// the order of `calls`, None for the calls left to the caller
+pub fn execute_parallel(
+    calls: &[ToolCall],
+    selected: impl Fn(&ToolCall) -> bool,
+    workspace: &Workspace,
+    custom_tools: &CustomTools,
+) -> Vec<Option<ToolResult>> {
+    let queue: Vec<usize> = (0..calls.len()).take_while(|&i| selected(&calls[i])).collect();
+    let results: Mutex<Vec<Option<ToolResult>>> = Mutex::new(calls.iter().map(|_| None).collect());
+    // A single call gains nothing from a thread
+    if queue.len() < 2 {
+        return results.into_inner().unwrap_or_default();
+    }
+
+    let next = AtomicUsize::new(0);
+    std::thread::scope(|scope| {
+        for _ in 0..queue.len().min(MAX_PARALLEL_TOOLS) {
+            scope.spawn(|| {
+                while let Some(&i) = queue.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    let result = execute_tool(&calls[i], workspace, custom_tools);
+                    if let Ok(mut results) = results.lock() {
+                        results[i] = Some(result);
+                    }
+                }
+            });
+        }
+    });
+    results.into_inner().unwrap_or_default()
+}
+
+/// Built-in tools that may modify the project
+fn is_builtin_write_tool(name: &str) -> bool {
+    matches!(name, "write_file" | "rename_symbol" | "execute_bash")
+}
+
+/// Whether a tool may modify the project (intercepted in PLAN+ preview mode)
+pub fn is_write_tool(name: &str, custom_tools: &CustomTools) -> bool {
+    match name {
+        name if is_builtin_write_tool(name) => true,
+        _ => custom_tools.get(name)
+            .map(|t| t.permission != ToolPermission::ReadOnly)
+            .unwrap_or(false),
+    }
+}
+
+/// Describe what a writing tool would do without touching the disk
+pub fn preview_tool(tool: &ToolCall, workspace: &Workspace, custom_tools: &CustomTools) -> ToolResult {
+    if let Err(output) = validate_params(tool, custom_tools) {
+        return ToolResult { name: tool.name.clone(), success: false, output, needs_confirmation: false };
+    }
+    let output = match tool.name.as_str() {
+        "write_file" => {
+            let path_str = tool.params.get("path").cloned().unwrap_or_default();
+            let content = tool.params.get("content").cloned().unwrap_or_default();
+            let path = workspace.resolve(&path_str);
+            if !workspace.contains(&path) {
+                return ToolResult {
+                    name: tool.name.clone(),
+                    success: false,
+                    output: format!("Access denied: {} is outside the workspace", path_str),
+                    needs_confirmation: false,
+                };
+            }
+            let current = fs::read_to_string(&path).unwrap_or_default();
+            let original_header = if path.exists() { path_str.as_str() } else { "/dev/null" };
+            let diff = similar::TextDiff::from_lines(current.as_str(), content.as_str())
+                .unified_diff()
+                .header(original_header, &path_str)
+                .to_string();
+            format!("write_file {} (not written)\n{}", path_str, diff)
+        }
+        "rename_symbol" => match rename_changes(tool, workspace) {
+            Ok(changes) => format!("rename_symbol (not written)\n{}", rename_diffs(&changes, workspace)),
+            Err(output) => return ToolResult { name: tool.name.clone(), success: false, output, needs_confirmation: false },
+        },
+        "execute_bash" => format!(
+            "execute_bash (not run): {}",
+            tool.params.get("command").cloned().unwrap_or_default()
+        ),
+        name => {
+            let command = custom_tools.get(name)
+                .and_then(|custom| custom.resolve_params(&tool.params).ok().map(|v| custom.render_command(&v)))
+                .unwrap_or_default();
+            format!("{} (not run): {}", name, command)
+        }
+    };
+
+    ToolResult {
+        name: tool.name.clone(),
+        success: true,
+        output: format!("[DRY-RUN] {}", output),
+        needs_confirmation: false,
+    }
+}
+
+fn execute_custom_tool(tool: &ToolCall, workspace: &Workspace, custom_tools: &CustomTools) -> ToolResult {
+    let Some(custom) = custom_tools.get(&tool.name) else {
+        return ToolResult {
+            name: tool.name.clone(),
+            success: false,
+            output: format!("Unknown tool: {}", tool.name),
+            needs_confirmation: false,
+        };
+    };
+
+    let values = match custom.resolve_params(&tool.params) {
+        Ok(v) => v,
+        Err(e) => {
+            return ToolResult {
+                name: tool.name.clone(),
+                success: false,
+                output: e,
+                needs_confirmation: false,
+            };
+        }
+    };
+    let command = custom.render_command(&values);
+
+    // Dangerous tools go through the same confirmation flow as bash commands
+    if custom.permission == ToolPermission::Dangerous {
+        return ToolResult {
+            name: tool.name.clone(),
+            success: false,
+            output: command,
+            needs_confirmation: true,
+        };
+    }
+
+    let (success, output) = custom.run(&command, workspace.primary());
+    ToolResult {
+        name: tool.name.clone(),
+        success,
+        output,
+        needs_confirmation: false,
+    }
+}
+
+fn execute_read_file(tool: &ToolCall, workspace: &Workspace) -> ToolResult {
+    let path_str = tool.params.get("path").cloned().unwrap_or_default();
+    let path = workspace.resolve(&path_str);
+    
+    if !workspace.contains(&path) {
+        return ToolResult {
+            name: tool.name.clone(),
+            success: false,
+            output: format!("Access denied: {} is outside the workspace", path_str),
+            needs_confirmation: false,
+        };
+    }
+    
+    let output = fs::read_to_string(&path)
+        .map_err(|e| format!("Error reading file: {}", e))
+        .and_then(|content| read_page(
+            &content,
+            tool.params.get("start_line").map(String::as_str),
+            tool.params.get("end_line").map(String::as_str),
+        ));
+    ToolResult {
+        name: tool.name.clone(),
+        success: output.is_ok(),
+        output: output.unwrap_or_else(|e| e),
+        needs_confirmation: false,
+    }
+}
+
+/// Lines returned by read_file when no range is given
+pub const READ_FILE_PAGE_LINES: usize = 200;
+
+/// Lines `start..=end` (1-based) of a file. Without a range, files longer than
+/// READ_FILE_PAGE_LINES are returned page by page with a note telling how to read on
+fn read_page(content: &str, start: Option<&str>, end: Option<&str>) -> Result<String, String> {
+    let parse = |value: Option<&str>, param: &str| -> Result<Option<usize>, String> {
+        match value.map(str::trim).filter(|v| !v.is_empty()) {
+            None => Ok(None),
+            Some(v) => v.parse::<usize>().ok().filter(|n| *n > 0).map(Some)
+                .ok_or_else(|| format!("Invalid {}: {} (expected a line number from 1)", param, v)),
+        }
+    };
+    let (start, end) = (parse(start, "start_line")?, parse(end, "end_line")?);
+
+    let lines: Vec<&str> = content.lines().collect();
+    let total = lines.len();
+    if start.is_none() && end.is_none() && total <= READ_FILE_PAGE_LINES {
+        return Ok(content.to_string());
+    }
+
+    let first = start.unwrap_or(1);
+    if first > total {
+        return Err(format!("start_line {} is past the end of the file ({} lines)", first, total));
+    }
+    let last = end.unwrap_or(first.saturating_add(READ_FILE_PAGE_LINES - 1)).min(total);
+    if first > last {
+        return Err(format!("start_line {} is after end_line {}", first, last));
+    }
+
+    let page = lines[first - 1..last].join("\n");
+    if first == 1 && last == total {
+        return Ok(page);
+    }
+    let more = if last < total {
+        format!(" Request more with <start_line>{}</start_line>.", last + 1)
+    } else {
+        String::new()
+    };
+    Ok(format!("[File is {} lines; returning {}-{}.{}]\n{}", total, first, last, more, page))
+}
+
+fn execute_write_file(tool: &ToolCall, workspace: &Workspace) -> ToolResult {
+    let path_str = tool.params.get("path").cloned().unwrap_or_default();
+    let content = tool.params.get("content").cloned().unwrap_or_default();
+    let path = workspace.resolve(&path_str);
+    
+    if !workspace.contains(&path) {
+        return ToolResult {
+            name: tool.name.clone(),
+            success: false,
+            output: format!("Access denied: {} is outside the workspace", path_str),
+            needs_confirmation: false,
+        };
+    }
+    
+    // Create parent directories if needed
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    
+    match fs::write(&path, &content) {
+        Ok(_) => ToolResult {
+            name: tool.name.clone(),
+            success: true,
+            output: format!("File written: {} ({} bytes)", path_str, content.len()),
+            needs_confirmation: false,
+        },
+        Err(e) => ToolResult {
+            name: tool.name.clone(),
+            success: false,
+            output: format!("Error writing file: {}", e),
+            needs_confirmation: false,
+        },
+    }
+}
+
+fn execute_list_directory(tool: &ToolCall, workspace: &Workspace) -> ToolResult {
+    let path_str = tool.params.get("path").cloned().unwrap_or(".".to_string());
+    let path = workspace.resolve(&path_str);
+    
+    if !workspace.contains(&path) {
+        return ToolResult {
+            name: tool.name.clone(),
+            success: false,
+            output: format!("Access denied: {} is outside the workspace", path_str),
+            needs_confirmation: false,
+        };
+    }
+    
+    match fs::read_dir(&path) {
+        Ok(entries) => {
+            let mut items: Vec<String> = entries
+                .filter_map(|e| e.ok())
+                .map(|e| {
+                    let name = e.file_name().to_string_lossy().to_string();
+                    let is_dir = e.file_type().map(|t| t.is_dir()).unwrap_or(false);
+                    if is_dir {
+                        format!("{}/", name)
+                    } else {
+                        name
+                    }
+                })
+                .collect();
+            items.sort();
+            ToolResult {
+                name: tool.name.clone(),
+                success: true,
+                output: items.join("\n"),
+                needs_confirmation: false,
+            }
+        }
+        Err(e) => ToolResult {
+            name: tool.name.clone(),
+            success: false,
+            output: format!("Error listing directory: {}", e),
+            needs_confirmation: false,
+        },
+    }
+}
+
+fn execute_search_in_files(tool: &ToolCall, workspace: &Workspace) -> ToolResult {
+    let query = tool.params.get("query").cloned().unwrap_or_default();
+    let path_str = tool.params.get("path").cloned().unwrap_or(".".to_string());
+    let path = workspace.resolve(&path_str);
+    
+    if !workspace.contains(&path) {
+        return ToolResult {
+            name: tool.name.clone(),
+            success: false,
+            output: format!("Access denied: {} is outside the workspace", path_str),
+            needs_confirmation: false,
+        };
+    }
+    
+    // Use grep for searching
+    let output = Command::new("grep")
+        .args(["-rn", "--include=*", &query])
+        .current_dir(&path)
+        .output();
+    
+    match output {
+        Ok(out) => {
+            let stdout = String::from_utf8_lossy(&out.stdout);
+            let result = if stdout.is_empty() {
+                "No matches found".to_string()
+            } else {
+                // Limit output to first 50 matches
+                stdout.lines().take(50).collect::<Vec<_>>().join("\n")
+            };
+            ToolResult {
+                name: tool.name.clone(),
+                success: true,
+                output: result,
+                needs_confirmation: false,
+            }
+        }
+        Err(e) => ToolResult {
+            name: tool.name.clone(),
+            success: false,
+            output: format!("Error searching: {}", e),
+            needs_confirmation: false,
+        },
+    }
+}
+
+/// Files of the search_index results by default, and matching lines shown per file
+const SEARCH_INDEX_LIMIT: usize = 10;
+const SEARCH_INDEX_LINES: usize = 5;
+
+fn execute_search_index(tool: &ToolCall, workspace: &Workspace) -> ToolResult {
+    let query = tool.params.get("query").cloned().unwrap_or_default();
+    let limit = tool.params.get("limit")
+        .and_then(|limit| limit.trim().parse::<usize>().ok())
+        .filter(|limit| *limit > 0)
+        .unwrap_or(SEARCH_INDEX_LIMIT);
+    
+    let hits = PersistentIndex::open(workspace.primary())
+        .and_then(|index| index.search(&query, limit, SEARCH_INDEX_LINES));
+    match hits {
+        Ok(hits) if hits.is_empty() => ToolResult {
+            name: tool.name.clone(),
+            success: true,
+            output: "No matches in the index (it may still be syncing: use search_in_files)".to_string(),
+            needs_confirmation: false,
+        },
+        Ok(hits) => {
+            let output = hits.iter()
+                .flat_map(|hit| hit.lines.iter().map(move |(line, text)| format!("{}:{}: {}", hit.relative_path, line, text)))
+                .collect::<Vec<_>>()
+                .join("\n");
+            ToolResult {
+                name: tool.name.clone(),
+                success: true,
+                output,
+                needs_confirmation: false,
+            }
+        }
+        Err(e) => ToolResult {
+            name: tool.name.clone(),
+            success: false,
+            output: format!("Error searching the index: {}", e),
+            needs_confirmation: false,
+        },
+    }
+}
+
+fn execute_lookup_docs(tool: &ToolCall, workspace: &Workspace) -> ToolResult {
+    let symbol = tool.params.get("symbol").cloned().unwrap_or_default();
+    let sections: Vec<String> = workspace.roots().iter()
+        .flat_map(|root| docs_lookup::lookup(&root.path, &symbol))
+        .map(|entry| {
+            let location = match entry.line {
+                Some(line) => format!("{}:{}", workspace.display(&entry.file), line),
+                None => workspace.display(&entry.file),
+            };
+            let mut section = format!("## {}\n{}", entry.signature, location);
+            if !entry.description.is_empty() {
+                section.push_str(&format!("\n{}", entry.description));
+            }
+            section
+        })
+        .collect();
+    let output = if sections.is_empty() {
+        format!("No local documentation found for {} (searched target/doc/*.json, node_modules and the .venv/venv site-packages)", symbol)
+    } else {
+        sections.join("\n\n")
+    };
+    ToolResult {
+        name: tool.name.clone(),
+        success: true,
+        output,
+        needs_confirmation: false,
+    }
+}
+
+/// Changes of a rename_symbol call, computed from the SQLite index of the primary root
+pub fn rename_changes(tool: &ToolCall, workspace: &Workspace) -> Result<ChangeSet, String> {
+    let symbol = tool.params.get("symbol").cloned().unwrap_or_default();
+    let new_name = tool.params.get("new_name").cloned().unwrap_or_default();
+    let index = PersistentIndex::open(workspace.primary())?;
+    rename::rename(workspace.primary(), &index, &symbol, &new_name)
+}
+
+/// Unified diffs of a rename, with workspace paths
+fn rename_diffs(changes: &ChangeSet, workspace: &Workspace) -> String {
+    changes.modifications.iter()
+        .map(|change| {
+            let path = workspace.display(Path::new(&change.path));
+            similar::TextDiff::from_lines(change.original.as_str(), change.modified.as_str())
+                .unified_diff()
+                .header(&path, &path)
+                .to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// A rename is only proposed: the user sees the files it touches and confirms before any is
+/// written (`execute_approved_tool`)
+fn execute_rename_symbol(tool: &ToolCall, workspace: &Workspace) -> ToolResult {
+    match rename_changes(tool, workspace) {
+        Ok(changes) => ToolResult {
+            name: tool.name.clone(),
+            success: false,
+            output: format!(
+                "rename {} → {}: {}",
+                tool.params.get("symbol").cloned().unwrap_or_default(),
+                tool.params.get("new_name").cloned().unwrap_or_default(),
+                changes.modifications.iter()
+                    .map(|change| workspace.display(Path::new(&change.path)))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            needs_confirmation: true,
+        },
+        Err(output) => ToolResult { name: tool.name.clone(), success: false, output, needs_confirmation: false },
+    }
+}
+
+/// Write a confirmed rename, computed again in case the files changed meanwhile
+fn apply_rename(tool: &ToolCall, workspace: &Workspace) -> ToolResult {
+    let changes = match rename_changes(tool, workspace) {
+        Ok(changes) => changes,
+        Err(output) => return ToolResult { name: tool.name.clone(), success: false, output, needs_confirmation: false },
+    };
+    // Every diff is computed before writing: only a write error can leave the rename half done
+    for change in &changes.modifications {
+        if let Err(e) = change.apply() {
+            return ToolResult {
+                name: tool.name.clone(),
+                success: false,
+                output: format!("{} (rename stopped, earlier files were written)", e),
+                needs_confirmation: false,
+            };
+        }
+    }
+    ToolResult {
+        name: tool.name.clone(),
+        success: true,
+        output: format!(
+            "Renamed {} to {} in {} file(s)\n{}",
+            tool.params.get("symbol").cloned().unwrap_or_default(),
+            tool.params.get("new_name").cloned().unwrap_or_default(),
+            changes.modifications.len(),
+            rename_diffs(&changes, workspace)
+        ),
+        needs_confirmation: false,
+    }
+}
+
+fn execute_bash(tool: &ToolCall, workspace: &Workspace) -> ToolResult {
+    let command = tool.params.get("command").cloned().unwrap_or_default();
+    
+    if command.is_empty() {
+        return ToolResult {
+            name: tool.name.clone(),
+            success: false,
+            output: "No command provided".to_string(),
+            needs_confirmation: false,
+        };
+    }
+    
+    // Check if dangerous - return early, needs confirmation
+    if is_dangerous_command(&command) {
+        return ToolResult {
+            name: tool.name.clone(),
+            success: false,
+            output: format!("DANGEROUS COMMAND DETECTED: {}", command),
+            needs_confirmation: true,
+        };
+    }
+    
+    // Execute safe command
+    let output = wsl::shell_command(&command, workspace.primary()).output();
+    
+    match output {
+        Ok(out) => {
+            let stdout = String::from_utf8_lossy(&out.stdout);
+            let stderr = String::from_utf8_lossy(&out.stderr);
+            let combined = if stderr.is_empty() {
+                stdout.to_string()
+            } else if stdout.is_empty() {
+                format!("STDERR:\n{}", stderr)
+            } else {
+                format!("{}\nSTDERR:\n{}", stdout, stderr)
+            };
+            ToolResult {
+                name: tool.name.clone(),
+                success: out.status.success(),
+                output: combined,
+                needs_confirmation: false,
+            }
+        }
+        Err(e) => ToolResult {
+            name: tool.name.clone(),
+            success: false,
+            output: format!("Error executing command: {}", e),
+            needs_confirmation: false,
+        },
+    }
+}
+
+/// Execute a dangerous command after user confirmation
+pub fn execute_dangerous_bash(command: &str, project_root: &Path) -> ToolResult {
+    let output = wsl::shell_command(command, project_root).output();
+    
+    match output {
+        Ok(out) => {
+            let stdout = String::from_utf8_lossy(&out.stdout);
+            let stderr = String::from_utf8_lossy(&out.stderr);
+            let combined = if stderr.is_empty() {
+                stdout.to_string()
+            } else if stdout.is_empty() {
+                format!("STDERR:\n{}", stderr)
+            } else {
+                format!("{}\nSTDERR:\n{}", stdout, stderr)
+            };
+            ToolResult {
+                name: "execute_bash".to_string(),
+                success: out.status.success(),
+                output: combined,
+                needs_confirmation: false,
+            }
+        }
+        Err(e) => ToolResult {
+            name: "execute_bash".to_string(),
+            success: false,
+            output: format!("Error executing command: {}", e),
+            needs_confirmation: false,
+        },
+    }
+}
+
+/// Run a tool call the user approved after it asked for confirmation
+pub fn execute_approved_tool(tool: &ToolCall, workspace: &Workspace, custom_tools: &CustomTools) -> ToolResult {
+    if tool.name == "rename_symbol" {
+        return apply_rename(tool, workspace);
+    }
+    let Some(custom) = custom_tools.get(&tool.name) else {
+        let command = tool.params.get("command").cloned().unwrap_or_default();
+        return execute_dangerous_bash(&command, workspace.primary());
+    };
+
+    let (success, output) = match custom.resolve_params(&tool.params) {
+        Ok(values) => custom.run(&custom.render_command(&values), workspace.primary()),
+        Err(e) => (false, e),
+    };
+    ToolResult {
+        name: tool.name.clone(),
+        success,
+        output,
+        needs_confirmation: false,
+    }
+}
+
+/// Format tool result for sending back to AI
+pub fn format_tool_result(result: &ToolResult) -> String {
+    format!(
+        "<tool_result>\n<name>{}</name>\n<success>{}</success>\n<output>\n{}\n</output>\n</tool_result>",
+        result.name,
+        result.success,
+        result.output
+    )
+}
+
+/// Start of the tools documentation
+const TOOLS_DOC_HEADER: &str = r#"
+## Available Tools
+
+You can use the following tools by including tool_call blocks in your response:
+
+"#;
+
+/// Documentation of each built-in tool
+const BUILTIN_TOOL_DOCS: &[(&str, &str)] = &[
+    ("read_file", r#"### read_file
+Read the content of a file. Files over 200 lines are returned 200 lines at a time;
+use the optional start_line/end_line (1-based, inclusive) to read a given range.
+```xml
+<tool_call>
+<name>read_file</name>
+<params>
+<path>src/main.rs</path>
+<start_line>120</start_line>
+<end_line>180</end_line>
+</params>
+</tool_call>
+```
+
+"#),
+    ("write_file", r#"### write_file
+Create or overwrite a file.
+```xml
+<tool_call>
+<name>write_file</name>
+<params>
+<path>src/new_file.rs</path>
+<content>
+// Your code here
+fn hello() {}
+</content>
+</params>
+</tool_call>
+```
+
+"#),
+    ("list_directory", r#"### list_directory
+List files and directories.
+```xml
+<tool_call>
+<name>list_directory</name>
+<params>
+<path>src/</path>
+</params>
+</tool_call>
+```
+
+"#),
+    ("search_in_files", r#"### search_in_files
+Search for text in project files.
+```xml
+<tool_call>
+<name>search_in_files</name>
+<params>
+<query>fn main</query>
+<path>src/</path>
+</params>
+</tool_call>
+```
+
+"#),
+    ("search_index", r#"### search_index
+Ranked full-text search of the indexed project files, best matches first (faster than
+search_in_files on large projects). Matches whole words; `limit` caps the files (default 10).
+```xml
+<tool_call>
+<name>search_index</name>
+<params>
+<query>parse ai response</query>
+<limit>5</limit>
+</params>
+</tool_call>
+```
+
+"#),
+    ("lookup_docs", r#"### lookup_docs
+Signature and documentation of a dependency's symbol from the docs installed locally
+(rustdoc JSON in target/doc, node_modules README and .d.ts files, Python docstrings of
+.venv/venv). Qualify the symbol with its crate, package or module to narrow the search.
+```xml
+<tool_call>
+<name>lookup_docs</name>
+<params>
+<symbol>serde_json::from_str</symbol>
+</params>
+</tool_call>
+```
+
+"#),
+    ("rename_symbol", r#"### rename_symbol
+Rename a declaration across the project: its definition and every whole-word occurrence of
+the name in the code of the indexed files (comments and strings are left as they are). The
+user confirms the rename first; the result then has the diff of each file. Prefer it to many
+write_file edits; it refuses names declared more than once.
+```xml
+<tool_call>
+<name>rename_symbol</name>
+<params>
+<symbol>Client::send_request</symbol>
+<new_name>send</new_name>
+</params>
+</tool_call>
+```
+
+"#),
+    ("execute_bash", r#"### execute_bash
+Execute a shell command.
+```xml
+<tool_call>
+<name>execute_bash</name>
+<params>
+<command>cargo build</command>
+</params>
+</tool_call>
+```
+
+"#),
+    ("ask_user", r#"### ask_user
+Ask the user one or more questions in a form. `fields` is a JSON array; `type` is one of
+text, choice, multi_select, boolean, number (with optional min, max, integer) or file_path.
+```xml
+<tool_call>
+<name>ask_user</name>
+<params>
+<fields>[{"question": "Quelle base de données?", "type": "choice", "choices": ["PostgreSQL", "SQLite"]},
+{"question": "Nombre de workers", "type": "number", "min": 1, "max": 16, "integer": true, "required": true}]</fields>
+</params>
+</tool_call>
+```
+
+"#),
+];
+
+const TOOLS_DOC_RULES: &str = r#"## Important Rules
+1. File access is limited to the project directory
+2. You can make multiple tool calls in one response
+3. After tool calls, you will receive tool_result blocks with outputs
+4. Continue your work based on tool results
+5. Dangerous commands (rm, sudo, etc.) require user confirmation
+"#;
+
+/// Get tools documentation for system prompt, limited to the tools the mode allows
+pub fn get_tools_documentation(access: ToolAccess) -> String {
+    let docs: Vec<&str> = BUILTIN_TOOL_DOCS.iter()
+        .filter(|(name, _)| access.allows(is_builtin_write_tool(name), true))
+        .map(|(_, doc)| *doc)
+        .collect();
+    if docs.is_empty() {
+        return String::new();
+    }
+    format!("{}{}{}", TOOLS_DOC_HEADER, docs.concat(), TOOLS_DOC_RULES)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_mode_only_documents_read_only_tools() {
+        assert!(get_tools_documentation(ToolAccess::for_mode(ChatMode::Ask, false)).is_empty());
+
+        let plan = get_tools_documentation(ToolAccess::for_mode(ChatMode::Plan, false));
+        assert!(plan.contains("### read_file") && plan.contains("### ask_user"));
+        assert!(!plan.contains("### write_file") && !plan.contains("### execute_bash"));
+
+        let preview = get_tools_documentation(ToolAccess::for_mode(ChatMode::Plan, true));
+        assert!(preview.contains("### write_file"));
+        assert!(!ToolAccess::Preview.allows(true, false));
+    }
+
+    #[test]
+    fn test_validate_params_names_the_faulty_parameter() {
+        let call = |name: &str, params: &[(&str, &str)]| ToolCall {
+            name: name.to_string(),
+            params: params.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+        };
+        let tools = CustomTools::default();
+
+        let missing = validate_params(&call("read_file", &[("file", "src/main.rs")]), &tools).unwrap_err();
+        assert_eq!(missing, "Invalid call to read_file: missing required parameter 'path' (got: file)\n\
+            Expected parameters: <path> string (required), <start_line> integer, <end_line> integer");
+        let malformed = validate_params(&call("read_file", &[("path", "a.rs"), ("start_line", "ten")]), &tools).unwrap_err();
+        assert!(malformed.contains("Parameter 'start_line' must be an integer, got 'ten'"));
+
+        assert!(validate_params(&call("write_file", &[("path", "empty.txt"), ("content", "")]), &tools).is_ok());
+        assert!(validate_params(&call("write_file", &[("path", "a.txt")]), &tools).is_err());
+        assert!(validate_params(&call("execute_bash", &[("command", "  ")]), &tools).is_err());
+        assert!(validate_params(&call("mcp_git_status", &[]), &tools).is_ok());
+    }
+
+    #[test]
+    fn test_parse_tool_calls_reads_each_param() {
+        let response = "<tool_call><name>write_file</name><params><path>src/a.rs</path>\
+            <content>\nfn a() -> Vec<u8> { vec![] }\n</content><start_line>3</params></tool_call>";
+        let calls = parse_tool_calls(response);
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].name, "write_file");
+        assert_eq!(calls[0].params["path"], "src/a.rs");
+        assert_eq!(calls[0].params["content"], "fn a() -> Vec<u8> { vec![] }");
+        // Never closed: left out
+        assert!(!calls[0].params.contains_key("start_line"));
+    }
+
+    #[test]
+    fn test_read_file_pages_long_files() {
+        let long: String = (1..=450).map(|i| format!("line {}\n", i)).collect();
+        let first = read_page(&long, None, None).unwrap();
+        assert!(first.starts_with("[File is 450 lines; returning 1-200. Request more with <start_line>201</start_line>.]\nline 1\n"));
+        assert!(first.ends_with("line 200"));
+
+        let last = read_page(&long, Some("401"), None).unwrap();
+        assert!(last.starts_with("[File is 450 lines; returning 401-450.]\nline 401"));
+        assert_eq!(read_page(&long, Some("10"), Some("11")).unwrap(), "[File is 450 lines; returning 10-11. Request more with <start_line>12</start_line>.]\nline 10\nline 11");
+
+        assert_eq!(read_page("a\nb\n", None, None).unwrap(), "a\nb\n");
+        assert!(read_page(&long, Some("500"), None).is_err());
+        assert!(read_page(&long, Some(&usize::MAX.to_string()), None).is_err());
+        assert!(read_page(&long, Some("1"), Some(&usize::MAX.to_string())).is_ok());
+        assert!(read_page(&long, Some("0"), None).is_err());
+    }
+
+    #[test]
+    fn test_rename_waits_for_confirmation() {
+        let dir = tempfile::tempdir().unwrap();
+        let content = "fn load() {}\n\nfn main() {\n    load(); // load first\n}\n";
+        fs::write(dir.path().join("main.rs"), content).unwrap();
+        PersistentIndex::open(dir.path()).unwrap().index_file(&dir.path().join("main.rs"), "main.rs", content).unwrap();
+
+        let mut params = std::collections::HashMap::new();
+        params.insert("symbol".to_string(), "load".to_string());
+        params.insert("new_name".to_string(), "read".to_string());
+        let call = ToolCall { name: "rename_symbol".to_string(), params };
+        let workspace = Workspace::single(dir.path());
+
+        let proposed = execute_tool(&call, &workspace, &CustomTools::default());
+        assert!(proposed.needs_confirmation && proposed.output.ends_with("main.rs"), "{}", proposed.output);
+        assert_eq!(fs::read_to_string(dir.path().join("main.rs")).unwrap(), content);
+
+        assert!(execute_approved_tool(&call, &workspace, &CustomTools::default()).success);
+        assert_eq!(fs::read_to_string(dir.path().join("main.rs")).unwrap(), "fn read() {}\n\nfn main() {\n    read(); // load first\n}\n");
+    }
+
+    #[test]
+    fn test_preview_shows_writes_without_touching_disk() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.rs"), "fn a() {}\n").unwrap();
+        let workspace = Workspace::single(dir.path());
+        let tools = CustomTools::default();
+        let call = |name: &str, params: &[(&str, &str)]| ToolCall {
+            name: name.to_string(),
+            params: params.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+        };
+
+        let edit = preview_tool(&call("write_file", &[("path", "a.rs"), ("content", "fn b() {}\n")]), &workspace, &tools);
+        assert!(edit.success);
+        assert!(edit.output.starts_with("[DRY-RUN] write_file a.rs (not written)\n--- a.rs\n+++ a.rs\n"));
+        assert!(edit.output.contains("-fn a() {}\n+fn b() {}\n"));
+        let created = preview_tool(&call("write_file", &[("path", "new.rs"), ("content", "x\n")]), &workspace, &tools);
+        assert!(created.output.contains("--- /dev/null\n+++ new.rs"));
+        let command = preview_tool(&call("execute_bash", &[("command", "rm -rf target")]), &workspace, &tools);
+        assert_eq!(command.output, "[DRY-RUN] execute_bash (not run): rm -rf target");
+        assert!(!preview_tool(&call("write_file", &[("path", "../out.rs"), ("content", "")]), &workspace, &tools).success);
+
+        assert_eq!(fs::read_to_string(dir.path().join("a.rs")).unwrap(), "fn a() {}\n");
+        assert!(!dir.path().join("new.rs").exists());
+        assert!(is_write_tool("write_file", &tools) && !is_write_tool("read_file", &tools));
+    }
+
+    #[test]
+    fn test_read_only_calls_run_in_parallel_until_first_write() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut calls = Vec::new();
+        for i in 0..6 {
+            fs::write(dir.path().join(format!("f{}.txt", i)), format!("content {}", i)).unwrap();
+            let mut params = std::collections::HashMap::new();
+            params.insert("path".to_string(), format!("f{}.txt", i));
+            calls.push(ToolCall { name: "read_file".to_string(), params });
+        }
+        calls.insert(4, ToolCall { name: "execute_bash".to_string(), params: Default::default() });
+
+        let workspace = Workspace::single(dir.path());
+        let results = execute_parallel(&calls, |call| is_parallel_safe(&call.name), &workspace, &CustomTools::default());
+        // Reads after the command wait for it, in order
+        assert!(results[4..].iter().all(Option::is_none));
+        let outputs: Vec<&str> = results.iter().flatten().map(|r| r.output.as_str()).collect();
+        assert_eq!(outputs.len(), 4);
+        assert!(outputs[0].contains("content 0") && outputs[3].contains("content 3"));
+    }
+}
@@ -0,0 +1,246 @@
+use regex::Regex;
+
+/// What a file section of a unified diff does to its file
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PatchKind {
+    Modify,
+    /// `--- /dev/null`
+    Create,
+    /// `+++ /dev/null`
+    Delete,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HunkLine {
+    Context(String),
+    Removed(String),
+    Added(String),
+}
+
+/// One `@@` section
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Hunk {
+    /// 1-based first line of the old side, when the header gives one
+    pub old_start: Option<usize>,
+    pub lines: Vec<HunkLine>,
+}
+
+impl Hunk {
+    /// Context and removed lines: what the file must contain
+    fn old_lines(&self) -> Vec<&str> {
+        self.lines.iter().filter_map(|line| match line {
+            HunkLine::Context(text) | HunkLine::Removed(text) => Some(text.as_str()),
+            HunkLine::Added(_) => None,
+        }).collect()
+    }
+
+    /// Context and added lines: what replaces them
+    fn new_lines(&self) -> Vec<&str> {
+        self.lines.iter().filter_map(|line| match line {
+            HunkLine::Context(text) | HunkLine::Added(text) => Some(text.as_str()),
+            HunkLine::Removed(_) => None,
+        }).collect()
+    }
+}
+
+/// The changes of one file in a unified diff
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilePatch {
+    /// Path of the `+++` header (`---` for deletions), without the git `a/` `b/` prefixes
+    pub path: String,
+    pub kind: PatchKind,
+    pub hunks: Vec<Hunk>,
+}
+
+/// A hunk whose lines are not in the file
+#[derive(Debug, Clone, PartialEq)]
+pub struct HunkFailure {
+    pub expected: String,
+    pub proposed: String,
+}
+
+impl FilePatch {
+    /// Content of a created file: the added lines
+    pub fn new_content(&self) -> String {
+        let lines: Vec<&str> = self.hunks.iter().flat_map(Hunk::new_lines).collect();
+        format!("{}\n", lines.join("\n"))
+    }
+
+    /// Old and new lines of all the hunks, to report a file that cannot be patched
+    pub fn as_failure(&self) -> HunkFailure {
+        let join = |side: fn(&Hunk) -> Vec<&str>| self.hunks.iter().map(|h| side(h).join("\n")).collect::<Vec<_>>().join("\n...\n");
+        HunkFailure { expected: join(Hunk::old_lines), proposed: join(Hunk::new_lines) }
+    }
+
+    /// Apply the hunks to `content`. A hunk is placed at the closest match of its old lines
+    /// to the line given by its header, so wrong line numbers and shifted code still apply;
+    /// trailing then surrounding whitespace is ignored when no exact match exists.
+    /// Hunks already present in the file are skipped.
+    pub fn apply(&self, content: &str) -> Result<String, HunkFailure> {
+        let eol = if content.contains("\r\n") { "\r\n" } else { "\n" };
+        let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+        // Lines added or removed by the previous hunks
+        let mut shift: isize = 0;
+
+        for hunk in &self.hunks {
+            let old = hunk.old_lines();
+            let new = hunk.new_lines();
+            let hint = hunk.old_start
+                .map(|start| (start.saturating_sub(1) as isize + shift).max(0) as usize)
+                .unwrap_or(0);
+
+            let position = if old.is_empty() {
+                Some(hint.min(lines.len()))
+            } else {
+                find_lines(&lines, &old, hint)
+            };
+            let Some(position) = position else {
+                if !new.is_empty() && find_lines(&lines, &new, hint).is_some() {
+                    continue;
+                }
+                return Err(HunkFailure { expected: old.join("\n"), proposed: new.join("\n") });
+            };
+            // Additions whose lines already follow the context
+            if new.len() > old.len() && lines.get(position..position + new.len()).is_some_and(|found| found.iter().zip(&new).all(|(l, n)| l == n)) {
+                continue;
+            }
+
+            lines.splice(position..position + old.len(), new.iter().map(|line| line.to_string()));
+            shift += new.len() as isize - old.len() as isize;
+        }
+
+        let mut patched = lines.join(eol);
+        if content.ends_with('\n') || (content.is_empty() && !patched.is_empty()) {
+            patched.push_str(eol);
+        }
+        Ok(patched)
+    }
+}
+
+/// Start of the occurrence of `needle` closest to `hint`: exact, else ignoring trailing
+/// whitespace, else ignoring indentation
+fn find_lines(lines: &[String], needle: &[&str], hint: usize) -> Option<usize> {
+    if needle.len() > lines.len() {
+        return None;
+    }
+    let comparisons: [fn(&str, &str) -> bool; 3] = [
+        |a, b| a == b,
+        |a, b| a.trim_end() == b.trim_end(),
+        |a, b| a.trim() == b.trim(),
+    ];
+    comparisons.iter().find_map(|same| {
+        (0..=lines.len() - needle.len())
+            .filter(|&start| needle.iter().zip(&lines[start..]).all(|(n, l)| same(l, n)))
+            .min_by_key(|&start| start.abs_diff(hint))
+    })
+}
+
+/// `a/src/main.rs\t2024-01-01 ...` → `src/main.rs`
+fn header_path(header: &str) -> String {
+    let path = header.split('\t').next().unwrap_or_default().trim();
+    path.strip_prefix("a/")
+        .or_else(|| path.strip_prefix("b/"))
+        .unwrap_or(path)
+        .to_string()
+}
+
+/// File patches of the ```diff / ```patch blocks of a response
+pub fn parse(response: &str) -> Vec<FilePatch> {
+    let block_pattern = Regex::new(r"(?ms)^```(?:diff|patch)[^\n]*\n(.*?)^```").unwrap();
+    let hunk_pattern = Regex::new(r"^@@ -(\d+)").unwrap();
+    let mut patches = Vec::new();
+
+    for block in block_pattern.captures_iter(response) {
+        let lines: Vec<&str> = block[1].lines().collect();
+        let mut current: Option<FilePatch> = None;
+        let mut i = 0;
+        while i < lines.len() {
+            let line = lines[i];
+            i += 1;
+
+            if let (Some(old), Some(new)) = (line.strip_prefix("--- "), lines.get(i).and_then(|next| next.strip_prefix("+++ "))) {
+                i += 1;
+                patches.extend(current.take());
+                let (old, new) = (header_path(old), header_path(new));
+                let (path, kind) = if old == "/dev/null" {
+                    (new, PatchKind::Create)
+                } else if new == "/dev/null" {
+                    (old, PatchKind::Delete)
+                } else {
+                    (new, PatchKind::Modify)
+                };
+                current = Some(FilePatch { path, kind, hunks: Vec::new() });
+                continue;
+            }
+            let Some(patch) = current.as_mut() else {
+                // `diff --git`, `index` and other lines before the first file
+                continue;
+            };
+
+            if line.starts_with("@@") {
+                let old_start = hunk_pattern.captures(line).and_then(|c| c[1].parse().ok());
+                patch.hunks.push(Hunk { old_start, lines: Vec::new() });
+                continue;
+            }
+            let Some(hunk) = patch.hunks.last_mut() else {
+                continue;
+            };
+            let hunk_line = match line.chars().next() {
+                Some(' ') => HunkLine::Context(line[1..].to_string()),
+                Some('-') => HunkLine::Removed(line[1..].to_string()),
+                Some('+') => HunkLine::Added(line[1..].to_string()),
+                // Blank context line whose leading space was trimmed
+                None => HunkLine::Context(String::new()),
+                // "\ No newline at end of file", or the `diff --git` line of the next file
+                _ => continue,
+            };
+            hunk.lines.push(hunk_line);
+        }
+        patches.extend(current);
+    }
+
+    patches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unified_diff_applies_despite_wrong_line_numbers() {
+        let response = "Voici le correctif :\n\n```diff\n\
+            diff --git a/src/lib.rs b/src/lib.rs\n\
+            index 1234567..89abcde 100644\n\
+            --- a/src/lib.rs\n\
+            +++ b/src/lib.rs\n\
+            @@ -40,3 +40,3 @@\n \
+            fn add(a: i32, b: i32) -> i32 {\n\
+            -    a - b\n\
+            +    a + b\n \
+            }\n\
+            @@ -90,2 +90,3 @@ fn main() {\n     \
+            let x = add(1, 2);\n\
+            +    println!(\"{}\", x);\n \
+            }\n\
+            --- /dev/null\n\
+            +++ b/NOTES.md\n\
+            @@ -0,0 +1,2 @@\n\
+            +# Notes\n\
+            +Addition corrigée\n\
+            ```\n";
+        let patches = parse(response);
+        assert_eq!(patches.len(), 2);
+        assert_eq!((patches[0].path.as_str(), patches[0].kind), ("src/lib.rs", PatchKind::Modify));
+        assert_eq!((patches[1].path.as_str(), patches[1].kind), ("NOTES.md", PatchKind::Create));
+        assert_eq!(patches[1].new_content(), "# Notes\nAddition corrigée\n");
+
+        let content = "// header\nfn add(a: i32, b: i32) -> i32 {\n    a - b\n}\n\nfn main() {\n    let x = add(1, 2);\n}\n";
+        let patched = patches[0].apply(content).unwrap();
+        assert_eq!(patched, "// header\nfn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n\nfn main() {\n    let x = add(1, 2);\n    println!(\"{}\", x);\n}\n");
+
+        // Already applied: nothing to do; unrelated file: the hunk is reported
+        assert_eq!(patches[0].apply(&patched).unwrap(), patched);
+        let failure = patches[0].apply("fn other() {}\n").unwrap_err();
+        assert_eq!(failure.expected, "fn add(a: i32, b: i32) -> i32 {\n    a - b\n}");
+    }
+}
@@ -0,0 +1,180 @@
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use serde::Deserialize;
+use crate::tools::{is_path_within_project, resolve_path};
+use crate::wsl;
+
+/// Root of a workspace; `name` prefixes its paths when there are several roots
+#[derive(Debug, Clone)]
+pub struct Root {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+/// Entry of a workspace file: a path, or a path with an explicit name
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RootEntry {
+    Path(PathBuf),
+    Named { path: PathBuf, name: Option<String> },
+}
+
+/// Workspace file, e.g. `{ "roots": ["../frontend", { "path": "../backend", "name": "api" }] }`.
+/// Relative paths are resolved from the directory of the file
+#[derive(Deserialize)]
+struct WorkspaceFile {
+    roots: Vec<RootEntry>,
+}
+
+/// Project roots the tools can reach. The first root is the primary one: it holds
+/// `.codestral/` (settings, memory, SQLite index, sessions) and runs the commands
+#[derive(Debug, Clone)]
+pub struct Workspace {
+    roots: Vec<Root>,
+}
+
+impl Workspace {
+    /// Single-root workspace, paths stay relative to the project as before
+    pub fn single(root: &Path) -> Self {
+        let path = wsl::canonicalize(root).unwrap_or_else(|_| root.to_path_buf());
+        Self { roots: vec![Root { name: root_name(&path), path }] }
+    }
+
+    /// Workspace of the given directories, named after their last component
+    pub fn new(paths: &[PathBuf]) -> Result<Self, String> {
+        Self::from_entries(paths.iter().map(|path| (path.clone(), None)).collect())
+    }
+
+    /// Load a workspace file listing the roots
+    pub fn load(file: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(file)
+            .map_err(|e| format!("Cannot read {}: {}", file.display(), e))?;
+        let parsed: WorkspaceFile = serde_json::from_str(&content)
+            .map_err(|e| format!("Invalid workspace file {}: {}", file.display(), e))?;
+        let base = file.parent().unwrap_or(Path::new("."));
+        let entries = parsed.roots.into_iter()
+            .map(|entry| match entry {
+                RootEntry::Path(path) => (base.join(path), None),
+                RootEntry::Named { path, name } => (base.join(path), name),
+            })
+            .collect();
+        Self::from_entries(entries)
+    }
+
+    fn from_entries(entries: Vec<(PathBuf, Option<String>)>) -> Result<Self, String> {
+        if entries.is_empty() {
+            return Err("Workspace has no root".to_string());
+        }
+        let mut roots: Vec<Root> = Vec::new();
+        for (path, name) in entries {
+            let path = wsl::canonicalize(&path)
+                .map_err(|e| format!("Invalid root {}: {}", path.display(), e))?;
+            if roots.iter().any(|root| root.path == path) {
+                continue;
+            }
+            let base = name.unwrap_or_else(|| root_name(&path));
+            if base.is_empty() || base.contains(['/', '\\']) || base == "." || base == ".." {
+                return Err(format!("Invalid root name \"{}\"", base));
+            }
+            // Same directory name in two places: frontend, frontend-2...
+            let mut name = base.clone();
+            let mut suffix = 2;
+            while roots.iter().any(|root| root.name == name) {
+                name = format!("{}-{}", base, suffix);
+                suffix += 1;
+            }
+            roots.push(Root { name, path });
+        }
+        Ok(Self { roots })
+    }
+
+    pub fn roots(&self) -> &[Root] {
+        &self.roots
+    }
+
+    pub fn primary(&self) -> &Path {
+        &self.roots[0].path
+    }
+
+    pub fn is_multi_root(&self) -> bool {
+        self.roots.len() > 1
+    }
+
+    /// Resolve a tool path: `<root name>/...` targets that root, other relative paths the primary root
+    pub fn resolve(&self, path_str: &str) -> PathBuf {
+        if self.is_multi_root() {
+            let path = Path::new(path_str);
+            let mut components = path.components();
+            if let Some(Component::Normal(first)) = components.next() {
+                if let Some(root) = self.roots.iter().find(|root| first == root.name.as_str()) {
+                    return root.path.join(components.as_path());
+                }
+            }
+        }
+        resolve_path(path_str, self.primary())
+    }
+
+    /// Whether a path is inside one of the roots
+    pub fn contains(&self, path: &Path) -> bool {
+        self.roots.iter().any(|root| is_path_within_project(path, &root.path))
+    }
+
+    /// Path shown to the model and the user: relative to its root, prefixed by the root name in a multi-root workspace
+    pub fn display(&self, path: &Path) -> String {
+        for root in &self.roots {
+            if let Ok(relative) = path.strip_prefix(&root.path) {
+                let relative = relative.to_string_lossy();
+                return if self.is_multi_root() { format!("{}/{}", root.name, relative) } else { relative.to_string() };
+            }
+        }
+        path.to_string_lossy().to_string()
+    }
+
+    /// System prompt section listing the roots (empty for a single root)
+    pub fn prompt_section(&self) -> String {
+        if !self.is_multi_root() {
+            return String::new();
+        }
+        let roots: Vec<String> = self.roots.iter()
+            .map(|root| format!("- {}/ → {}", root.name, root.path.display()))
+            .collect();
+        format!(
+            "WORKSPACE: plusieurs racines de projet. Préfixe TOUS les chemins par le nom de la racine \
+             (ex: {}/src/main.rs) ; les commandes s'exécutent dans {}/.\n{}",
+            self.roots[1].name, self.roots[0].name, roots.join("\n")
+        )
+    }
+}
+
+fn root_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "root".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roots_are_namespaced_and_contained() {
+        let dir = tempfile::tempdir().unwrap();
+        for root in ["front/app", "back/app", "other"] {
+            fs::create_dir_all(dir.path().join(root)).unwrap();
+        }
+        let file = dir.path().join("workspace.json");
+        fs::write(&file, r#"{ "roots": ["front/app", "back/app", { "path": "front/app" }] }"#).unwrap();
+
+        let workspace = Workspace::load(&file).unwrap();
+        let names: Vec<&str> = workspace.roots().iter().map(|root| root.name.as_str()).collect();
+        assert_eq!(names, vec!["app", "app-2"]);
+
+        let back = workspace.resolve("app-2/src/lib.rs");
+        assert_eq!(back, workspace.roots()[1].path.join("src/lib.rs"));
+        assert_eq!(workspace.display(&back), "app-2/src/lib.rs");
+        assert_eq!(workspace.resolve("README.md"), workspace.primary().join("README.md"));
+
+        assert!(workspace.contains(&back));
+        assert!(!workspace.contains(&workspace.resolve("../../other/secret")));
+    }
+}
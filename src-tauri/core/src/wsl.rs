@@ -0,0 +1,193 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Where the companion runs, for paths and commands crossing the Windows/WSL boundary
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Host {
+    Native,
+    Windows,
+    /// Linux inside WSL: Windows drives are mounted under /mnt
+    Wsl,
+}
+
+pub fn host() -> Host {
+    if cfg!(windows) {
+        Host::Windows
+    } else if cfg!(target_os = "linux")
+        && (std::env::var_os("WSL_DISTRO_NAME").is_some() || Path::new("/proc/sys/fs/binfmt_misc/WSLInterop").exists())
+    {
+        Host::Wsl
+    } else {
+        Host::Native
+    }
+}
+
+/// Drop the `\\?\` prefix of canonical Windows paths and write WSL shares as `\\wsl$\`
+pub fn clean(path: &str) -> String {
+    let path = if let Some(rest) = path.strip_prefix(r"\\?\UNC\") {
+        format!(r"\\{}", rest)
+    } else {
+        path.strip_prefix(r"\\?\").unwrap_or(path).to_string()
+    };
+    let path = path.replace('/', "\\");
+    let lower = path.to_lowercase();
+    if lower.starts_with(r"\\wsl.localhost\") {
+        format!(r"\\wsl$\{}", &path[r"\\wsl.localhost\".len()..])
+    } else if path.starts_with(r"\\") || lower.chars().nth(1) == Some(':') {
+        path
+    } else {
+        // Not a Windows path: keep the slashes
+        path.replace('\\', "/")
+    }
+}
+
+/// `\\wsl$\Ubuntu\home\me` → ("Ubuntu", "/home/me")
+pub fn wsl_share(path: &str) -> Option<(String, String)> {
+    let path = clean(path);
+    let rest = path.get(r"\\wsl$\".len()..).filter(|_| path.to_lowercase().starts_with(r"\\wsl$\"))?;
+    let (distro, linux) = rest.split_once('\\').unwrap_or((rest, ""));
+    if distro.is_empty() {
+        return None;
+    }
+    Some((distro.to_string(), format!("/{}", linux.replace('\\', "/").trim_matches('/'))))
+}
+
+/// `C:\Users\me` → `/mnt/c/Users/me`
+pub fn drive_to_mount(path: &str) -> Option<String> {
+    let mut chars = path.chars();
+    let drive = chars.next().filter(char::is_ascii_alphabetic)?;
+    if chars.next() != Some(':') {
+        return None;
+    }
+    let rest = path[2..].replace('\\', "/");
+    if !rest.is_empty() && !rest.starts_with('/') {
+        return None;
+    }
+    Some(format!("/mnt/{}{}", drive.to_ascii_lowercase(), rest.trim_end_matches('/')))
+}
+
+/// `/mnt/c/Users/me` → `C:\Users\me`
+pub fn mount_to_drive(path: &str) -> Option<String> {
+    let rest = path.strip_prefix("/mnt/")?;
+    let (drive, rest) = rest.split_once('/').unwrap_or((rest, ""));
+    if drive.len() != 1 || !drive.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    Some(format!(r"{}:\{}", drive.to_ascii_uppercase(), rest.replace('/', "\\")))
+}
+
+/// Resolve `.` and `..` of a Windows path without touching the file system
+fn lexical(path: &str) -> String {
+    let path = clean(path);
+    let (prefix, rest) = if let Some(rest) = path.strip_prefix(r"\\") {
+        // \\server\share is the root of a UNC path
+        let mut parts = rest.splitn(3, '\\');
+        let server = parts.next().unwrap_or("");
+        let share = parts.next().unwrap_or("");
+        (format!(r"\\{}\{}", server, share), parts.next().unwrap_or("").to_string())
+    } else {
+        let (drive, rest) = path.split_at(path.len().min(2));
+        (drive.to_string(), rest.to_string())
+    };
+    let mut parts: Vec<&str> = Vec::new();
+    for part in rest.split('\\') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part),
+        }
+    }
+    format!(r"{}\{}", prefix, parts.join("\\"))
+}
+
+/// `fs::canonicalize` that also works on the `\\wsl$` shares seen from Windows,
+/// where it fails, and without the `\\?\` prefix
+pub fn canonicalize(path: &Path) -> io::Result<PathBuf> {
+    if host() != Host::Windows {
+        return fs::canonicalize(path);
+    }
+    match fs::canonicalize(path) {
+        Ok(canonical) => Ok(PathBuf::from(clean(&canonical.to_string_lossy()))),
+        Err(_) if wsl_share(&path.to_string_lossy()).is_some() && path.exists() => {
+            Ok(PathBuf::from(lexical(&path.to_string_lossy())))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Absolute path written for the other side of the boundary (a model used to Linux paths
+/// in a WSL project opened from Windows, a Windows path given inside WSL), rewritten for this host
+pub fn translate(path_str: &str, project_root: &Path) -> Option<PathBuf> {
+    match host() {
+        Host::Wsl => drive_to_mount(path_str)
+            .or_else(|| wsl_share(path_str).map(|(_, linux)| linux))
+            .map(PathBuf::from),
+        Host::Windows => {
+            if let Some(drive) = mount_to_drive(path_str) {
+                return Some(PathBuf::from(drive));
+            }
+            if !path_str.starts_with('/') {
+                return None;
+            }
+            let (distro, _) = wsl_share(&project_root.to_string_lossy())?;
+            Some(PathBuf::from(format!(r"\\wsl$\{}{}", distro, path_str.replace('/', "\\"))))
+        }
+        Host::Native => None,
+    }
+}
+
+/// Mixed setup shown at startup, e.g. "projet WSL (Ubuntu) ouvert depuis Windows"
+pub fn mixed_setup(project_root: &Path) -> Option<String> {
+    let root = project_root.to_string_lossy();
+    match host() {
+        Host::Windows => wsl_share(&root)
+            .map(|(distro, _)| format!("projet WSL ({}) ouvert depuis Windows : commandes exécutées via wsl.exe", distro)),
+        Host::Wsl if mount_to_drive(&root).is_some() => {
+            Some("projet Windows ouvert depuis WSL : chemins C:\\ convertis en /mnt/c".to_string())
+        }
+        _ => None,
+    }
+}
+
+/// Shell command run in a directory: through `wsl.exe` in the project's distribution for a
+/// WSL project seen from Windows, where bash cannot enter a `\\wsl$` share; else `bash -c`
+pub fn shell_command(command: &str, cwd: &Path) -> Command {
+    if host() == Host::Windows {
+        if let Some((distro, linux_cwd)) = wsl_share(&cwd.to_string_lossy()) {
+            let mut wsl = Command::new("wsl.exe");
+            wsl.args(["-d", &distro, "--cd", &linux_cwd, "--", "bash", "-c", command]);
+            return wsl;
+        }
+    }
+    let mut bash = Command::new("bash");
+    bash.args(["-c", command]).current_dir(cwd);
+    bash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_windows_and_wsl_path_forms() {
+        assert_eq!(clean(r"\\?\UNC\wsl.localhost\Ubuntu\home\me"), r"\\wsl$\Ubuntu\home\me");
+        assert_eq!(clean(r"\\?\C:\Users\me"), r"C:\Users\me");
+        assert_eq!(clean("/home/me/app"), "/home/me/app");
+
+        assert_eq!(wsl_share("//wsl$/Ubuntu/home/me/app"), Some(("Ubuntu".to_string(), "/home/me/app".to_string())));
+        assert_eq!(wsl_share(r"\\wsl$\Debian"), Some(("Debian".to_string(), "/".to_string())));
+        assert_eq!(wsl_share(r"\\server\share\x"), None);
+
+        assert_eq!(drive_to_mount(r"C:\Users\me\app\"), Some("/mnt/c/Users/me/app".to_string()));
+        assert_eq!(drive_to_mount("src/main.rs"), None);
+        assert_eq!(mount_to_drive("/mnt/d/work/app"), Some(r"D:\work\app".to_string()));
+        assert_eq!(mount_to_drive("/mnt/data/app"), None);
+
+        assert_eq!(lexical(r"\\wsl$\Ubuntu\home\me\app\src\..\..\.\other"), r"\\wsl$\Ubuntu\home\me\other");
+        assert_eq!(lexical(r"\\wsl$\Ubuntu\..\..\etc"), r"\\wsl$\Ubuntu\etc");
+    }
+}
@@ -0,0 +1,134 @@
+//! Shared by the integration tests of the core and of the app: fixture projects and a
+//! scripted chat client, so the pipeline runs end to end without an API key
+
+#![allow(dead_code)]
+
+use companion_core::backend::{BackendFuture, LlmBackend};
+use companion_core::mistral_client::{ApiProvider, Message, Usage};
+use std::collections::VecDeque;
+use std::fs;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+
+/// `tests/fixtures` of the core, from its own tests and from the app's
+fn fixtures_dir() -> PathBuf {
+    let manifest = Path::new(env!("CARGO_MANIFEST_DIR"));
+    [manifest.join("tests/fixtures"), manifest.join("core/tests/fixtures")]
+        .into_iter()
+        .find(|dir| dir.is_dir())
+        .expect("tests/fixtures not found")
+}
+
+fn copy_dir(from: &Path, to: &Path) {
+    fs::create_dir_all(to).unwrap();
+    for entry in fs::read_dir(from).unwrap() {
+        let entry = entry.unwrap();
+        let target = to.join(entry.file_name());
+        if entry.file_type().unwrap().is_dir() {
+            copy_dir(&entry.path(), &target);
+        } else {
+            fs::copy(entry.path(), target).unwrap();
+        }
+    }
+}
+
+/// Fresh copy of the fixture project `tests/fixtures/<name>`
+pub fn fixture(name: &str) -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    copy_dir(&fixtures_dir().join(name), dir.path());
+    dir
+}
+
+pub fn read(root: &Path, path: &str) -> String {
+    fs::read_to_string(root.join(path)).unwrap()
+}
+
+#[derive(Default)]
+struct Script {
+    responses: VecDeque<String>,
+    requests: Vec<Vec<Message>>,
+}
+
+/// Answers with the scripted responses in order and records the requests; fails once
+/// the script is exhausted. Clones share the script
+#[derive(Clone)]
+pub struct ScriptedClient {
+    script: Arc<Mutex<Script>>,
+    model: String,
+    provider: ApiProvider,
+}
+
+impl ScriptedClient {
+    pub fn new(responses: &[&str]) -> Self {
+        let script = Script { responses: responses.iter().map(|r| r.to_string()).collect(), requests: Vec::new() };
+        Self {
+            script: Arc::new(Mutex::new(script)),
+            model: "scripted".to_string(),
+            // Nothing listens there: a request reaching the network fails at once
+            provider: ApiProvider::Custom { base_url: "http://127.0.0.1:9/v1".to_string(), model: "scripted".to_string() },
+        }
+    }
+
+    /// Messages of each request, in order
+    pub fn requests(&self) -> Vec<Vec<Message>> {
+        self.script.lock().unwrap().requests.clone()
+    }
+
+    fn next(&self, messages: Vec<Message>) -> anyhow::Result<String> {
+        let mut script = self.script.lock().unwrap();
+        script.requests.push(messages);
+        script.responses.pop_front().ok_or_else(|| anyhow::anyhow!("No scripted response left"))
+    }
+}
+
+impl LlmBackend for ScriptedClient {
+    fn chat_with_usage(&self, messages: Vec<Message>) -> BackendFuture<'_, (String, Option<Usage>)> {
+        let response = self.next(messages).map(|text| (text, None));
+        Box::pin(async move { response })
+    }
+
+    fn chat_stream<'a>(&'a self, messages: Vec<Message>, on_delta: &'a mut dyn FnMut(&str)) -> Pin<Box<dyn Future<Output = anyhow::Result<String>> + 'a>> {
+        let response = self.next(messages);
+        if let Ok(text) = &response {
+            on_delta(text);
+        }
+        Box::pin(async move { response })
+    }
+
+    /// The next scripted response, sent with the prefix as the only message
+    fn fim<'a>(&'a self, prefix: &'a str, _suffix: &'a str, _max_tokens: u32) -> BackendFuture<'a, String> {
+        let response = self.next(vec![Message::user(prefix)]);
+        Box::pin(async move { response })
+    }
+
+    fn embed<'a>(&'a self, _model: &'a str, _inputs: &'a [String]) -> BackendFuture<'a, Vec<Vec<f32>>> {
+        Box::pin(async { Err(anyhow::anyhow!("No embeddings in scripted tests")) })
+    }
+
+    fn list_models(&self) -> BackendFuture<'_, Vec<String>> {
+        let models = vec![self.model.clone()];
+        Box::pin(async move { Ok(models) })
+    }
+
+    fn get_model(&self) -> &str {
+        &self.model
+    }
+
+    fn context_window(&self) -> u64 {
+        32_000
+    }
+
+    fn provider(&self) -> &ApiProvider {
+        &self.provider
+    }
+
+    fn default_embedding_model(&self) -> &'static str {
+        "scripted-embed"
+    }
+
+    fn for_model(&self, model: &str) -> Arc<dyn LlmBackend> {
+        Arc::new(Self { model: model.to_string(), ..self.clone() })
+    }
+}
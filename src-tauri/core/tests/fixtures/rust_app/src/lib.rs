@@ -0,0 +1,9 @@
+/// Sum of two numbers
+pub fn add(a: i32, b: i32) -> i32 {
+    a + b
+}
+
+/// Difference of two numbers
+pub fn subtract(a: i32, b: i32) -> i32 {
+    a + b
+}
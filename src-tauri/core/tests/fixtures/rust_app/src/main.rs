@@ -0,0 +1,5 @@
+use calc::{add, subtract};
+
+fn main() {
+    println!("{} {}", add(2, 1), subtract(2, 1));
+}
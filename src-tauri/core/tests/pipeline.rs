@@ -0,0 +1,139 @@
+mod common;
+
+use common::{fixture, read, ScriptedClient};
+use companion_core::custom_tools::CustomTools;
+use companion_core::differ::parse_ai_response;
+use companion_core::indexer::CodebaseIndex;
+use companion_core::journal::ChangeJournal;
+use companion_core::persistent_index::reindex_project;
+use companion_core::tools::{execute_tool, parse_tool_calls};
+use companion_core::workspace::Workspace;
+use std::path::Path;
+use std::process::Command;
+
+const FIX_SUBTRACT: &str = "Je corrige `subtract`, je documente et je supprime les notes.
+
+<file path=\"src/lib.rs\">
+<<<<<<< ORIGINAL
+pub fn subtract(a: i32, b: i32) -> i32 {
+    a + b
+}
+=======
+pub fn subtract(a: i32, b: i32) -> i32 {
+    a - b
+}
+>>>>>>> MODIFIED
+</file>
+
+```diff
+--- a/src/main.rs
++++ b/src/main.rs
+@@ -3,3 +3,4 @@
+ fn main() {
+     println!(\"{} {}\", add(2, 1), subtract(2, 1));
++    println!(\"done\");
+ }
+```
+
+<new_file path=\"CHANGELOG.md\">
+# Changelog
+
+- subtract subtracts
+</new_file>
+
+<delete_file path=\"docs/notes.md\" />
+";
+
+#[test]
+fn test_response_is_applied_then_rolled_back() {
+    let project = fixture("rust_app");
+    let root = project.path();
+    let original_lib = read(root, "src/lib.rs");
+
+    let changes = parse_ai_response(FIX_SUBTRACT, root);
+    assert!(changes.patch_failures.is_empty(), "{:?}", changes.patch_failures);
+    assert_eq!((changes.modifications.len(), changes.new_files.len(), changes.deletions.len()), (2, 1, 1));
+
+    let report = changes.apply_selected(root, |_| true);
+    assert!(report.errors.is_empty(), "{:?}", report.errors);
+    assert!(read(root, "src/lib.rs").contains("    a - b\n"));
+    assert!(read(root, "src/main.rs").contains("    println!(\"done\");\n}\n"));
+    assert_eq!(read(root, "CHANGELOG.md"), "# Changelog\n\n- subtract subtracts\n");
+    assert!(!root.join("docs/notes.md").exists());
+    assert!(report.trashed[0].ends_with("docs/notes.md") && Path::new(&report.trashed[0]).exists());
+
+    // The same response again: ORIGINAL no longer matches, the diff is already applied
+    let again = parse_ai_response(FIX_SUBTRACT, root);
+    assert_eq!(again.patch_failures.len(), 1);
+    assert!(again.modifications.is_empty());
+
+    let mut journal = ChangeJournal::last(root).expect("change set journaled");
+    journal.rollback(root).unwrap();
+    assert_eq!(read(root, "src/lib.rs"), original_lib);
+    assert!(root.join("docs/notes.md").exists());
+    assert!(!root.join("CHANGELOG.md").exists());
+}
+
+#[test]
+fn test_tool_calls_run_in_the_project() {
+    let project = fixture("rust_app");
+    let root = project.path();
+    let workspace = Workspace::single(root);
+    let custom_tools = CustomTools::default();
+
+    let response = "<tool_call><name>search_in_files</name><params><query>fn subtract</query></params></tool_call>\n\
+        <tool_call><name>write_file</name><params><path>src/util.rs</path><content>pub fn double(x: i32) -> i32 { x * 2 }</content></params></tool_call>\n\
+        <tool_call><name>read_file</name><params><path>../outside.txt</path></params></tool_call>";
+    let calls = parse_tool_calls(response);
+    assert_eq!(calls.iter().map(|c| c.name.as_str()).collect::<Vec<_>>(), ["search_in_files", "write_file", "read_file"]);
+
+    let results: Vec<_> = calls.iter().map(|call| execute_tool(call, &workspace, &custom_tools)).collect();
+    assert!(results[0].success && results[0].output.contains("src/lib.rs"), "{}", results[0].output);
+    assert!(results[1].success);
+    assert_eq!(read(root, "src/util.rs"), "pub fn double(x: i32) -> i32 { x * 2 }");
+    assert!(!results[2].success, "paths outside the project are refused");
+}
+
+#[test]
+fn test_index_covers_the_project() {
+    let project = fixture("rust_app");
+    let root = project.path();
+
+    let index = CodebaseIndex::index(root, None, &["docs".to_string()], 100).unwrap();
+    let mut paths: Vec<&str> = index.files.iter().map(|f| f.relative_path.as_str()).collect();
+    paths.sort();
+    assert_eq!(paths, ["Cargo.toml", "src/lib.rs", "src/main.rs"]);
+
+    let (store, updated) = reindex_project(root).unwrap();
+    assert_eq!(updated, 4);
+    let hits = store.search("subtract", 10, 3).unwrap();
+    assert!(hits.iter().any(|hit| hit.relative_path == "src/lib.rs"));
+}
+
+#[test]
+fn test_applied_change_set_is_committed_with_the_model_message() {
+    let project = fixture("rust_app");
+    let root = project.path();
+    let git = |args: &[&str]| {
+        let output = Command::new("git").args(args).current_dir(root).output().unwrap();
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+    git(&["init", "-q"]);
+    git(&["config", "user.email", "test@example.com"]);
+    git(&["config", "user.name", "Test"]);
+    git(&["add", "."]);
+    git(&["commit", "-q", "-m", "Initial"]);
+
+    parse_ai_response(FIX_SUBTRACT, root).apply_selected(root, |_| true);
+    let journal = ChangeJournal::last(root).unwrap();
+    let client = ScriptedClient::new(&["Fix subtract and log the run"]);
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let committed = runtime.block_on(companion_core::auto_commit::commit(root, &journal.paths(root), &journal.stats(), &client)).unwrap();
+
+    assert!(committed.unwrap().ends_with(" Fix subtract and log the run"));
+    let message = git(&["log", "-1", "--format=%B"]);
+    assert!(message.trim_end().ends_with(&journal.stats().header()), "{}", message);
+    assert!(client.requests()[0].iter().any(|m| m.content.contains("a - b")), "the diff is sent to the model");
+    // Only the journal's directory is left, never part of a change set
+    assert_eq!(git(&["status", "--porcelain"]), "?? .codestral/\n");
+}
@@ -1,10 +1,23 @@
-use crate::cli::{AgentConfig, ExecutionMode};
-use crate::indexer::CodebaseIndex;
-use crate::differ::{parse_ai_response, confirm, ChangeSet};
-use crate::mistral_client::{MistralClient, ApiProvider, Message};
+use crate::cli::{AgentConfig, ContextScope, ExecutionMode, OutputFormat};
+use crate::indexer::{mentioned_skips, CodebaseIndex};
+use crate::differ::{assume_yes, parse_ai_response, confirm, confirm_change, ChangeSet, DiffStats, MAX_PATCH_RETRIES};
+use crate::journal::ChangeJournal;
+use crate::auto_commit;
+use crate::backend::LlmBackend;
+use crate::mistral_client::{ApiProvider, Message, RetryPolicy, Role};
+use crate::error::Error;
+use crate::prompt::{self, PromptBuilder};
+use crate::mode::ChatMode;
+use crate::settings::{load_saved_api_settings, save_api_settings, trust_endpoint, unconfirmed_endpoint, validate_api_key, ApiSettings};
+use crate::context_guard::{self, CodebaseContext, ContextLevel, RESPONSE_RESERVE_TOKENS};
+use crate::semantic_search::{self, EmbeddingSettings};
 use colored::*;
 use indicatif::{ProgressBar, ProgressStyle};
+use serde::Serialize;
+use std::fmt;
 use std::fs;
+use std::path::Path;
+use std::sync::Arc;
 
 const SYSTEM_PROMPT: &str = r#"Tu es un assistant de programmation expert. Tu analyses des codebases et proposes des modifications.
 
@@ -34,32 +47,209 @@ Pour créer un nouveau fichier:
 contenu complet du nouveau fichier
 </new_file>
 
+Pour réécrire entièrement un fichier (grosses modifications):
+<replace_file path="chemin/relatif/fichier.ext">
+nouveau contenu complet du fichier
+</replace_file>
+
+Pour supprimer un fichier:
+<delete_file path="chemin/relatif/fichier.ext" />
+
+Pour déplacer ou renommer un fichier (l'historique git est conservé; les modifications du fichier utilisent ensuite le nouveau chemin):
+<rename_file from="chemin/relatif/fichier.ext" to="chemin/relatif/nouveau_nom.ext" />
+
 IMPORTANT: Le code dans ORIGINAL doit correspondre EXACTEMENT au code existant pour que le remplacement fonctionne.
 "#;
 
+/// Tokens of file contents in the CODEBASE section
+const CODEBASE_TOKENS: usize = 30_000;
+
+/// Exit codes of `plan`, `interactive` and `auto`, so CI can gate on the outcome
+pub const EXIT_SUCCESS: i32 = 0;
+/// Invalid configuration, missing API key, nothing to analyse
+pub const EXIT_SETUP: i32 = 1;
+/// Changes proposed but not applied (plan, dry-run, declined)
+pub const EXIT_NOT_APPLIED: i32 = 2;
+/// A change could not be written, or a patch did not match the file
+pub const EXIT_APPLY_FAILED: i32 = 3;
+/// The prompt does not fit the context window, or the API quota is exhausted
+pub const EXIT_BUDGET_EXCEEDED: i32 = 4;
+/// The API request failed (network, key, server)
+pub const EXIT_API_ERROR: i32 = 5;
+
+/// Outcome of an agent run
+#[derive(Debug, Default)]
+pub struct AgentReport {
+    /// Raw response of the model
+    pub response: String,
+    /// Files written to disk
+    pub applied: Vec<String>,
+    /// Files deleted from disk
+    pub deleted: Vec<String>,
+    /// (old path, new path) of the moved files
+    pub renamed: Vec<(String, String)>,
+    /// Number of changes proposed by the model
+    pub proposed: usize,
+    /// Patches of an AUTO run that still did not match their file after the retries
+    pub failed: usize,
+    /// Steps of the `<plan>` block
+    pub plan: Vec<String>,
+    /// Files the proposed changes target
+    pub files: Vec<ProposedFile>,
+    /// Lines and languages of the proposed changes
+    pub stats: DiffStats,
+}
+
+/// File targeted by a proposed change
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProposedFile {
+    pub path: String,
+    /// modify, create, delete or rename (the new path)
+    pub action: &'static str,
+}
+
+/// `plan --format json|yaml` output
+#[derive(Serialize)]
+struct PlanOutput<'a> {
+    instruction: &'a str,
+    steps: &'a [String],
+    files: &'a [ProposedFile],
+    stats: &'a DiffStats,
+}
+
+impl AgentReport {
+    /// Plan steps and proposed files for other tools; None in text format
+    pub fn render(&self, instruction: &str, format: OutputFormat) -> Option<String> {
+        let output = PlanOutput { instruction, steps: &self.plan, files: &self.files, stats: &self.stats };
+        match format {
+            OutputFormat::Text => None,
+            OutputFormat::Json => serde_json::to_string_pretty(&output).ok(),
+            OutputFormat::Yaml => serde_yaml::to_string(&output).ok(),
+        }
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        if self.failed > 0 {
+            EXIT_APPLY_FAILED
+        } else if self.applied.len() + self.deleted.len() + self.renamed.len() < self.proposed {
+            EXIT_NOT_APPLIED
+        } else {
+            EXIT_SUCCESS
+        }
+    }
+}
+
+/// Why an agent run failed, each kind with its exit code
+#[derive(Debug, Clone, PartialEq)]
+pub enum AgentError {
+    Setup(String),
+    Apply(String),
+    Budget(String),
+    Api(String),
+}
+
+impl AgentError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            AgentError::Setup(_) => EXIT_SETUP,
+            AgentError::Apply(_) => EXIT_APPLY_FAILED,
+            AgentError::Budget(_) => EXIT_BUDGET_EXCEEDED,
+            AgentError::Api(_) => EXIT_API_ERROR,
+        }
+    }
+
+    /// API failure; context length and quota errors are budget errors
+    fn from_api(error: anyhow::Error) -> Self {
+        Error::from_request(&error).into()
+    }
+}
+
+impl From<Error> for AgentError {
+    fn from(error: Error) -> Self {
+        match error {
+            Error::Budget(message) => AgentError::Budget(message),
+            Error::MissingApiKey(_) | Error::IndexLocked(_) | Error::Database(_) | Error::Other(_) => AgentError::Setup(error.to_string()),
+            error => AgentError::Api(error.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for AgentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AgentError::Setup(message) | AgentError::Apply(message)
+            | AgentError::Budget(message) | AgentError::Api(message) => write!(f, "{}", message),
+        }
+    }
+}
+
 pub struct Agent {
     config: AgentConfig,
-    client: MistralClient,
+    client: Arc<dyn LlmBackend>,
+    /// SYSTEM_PROMPT with the project language rule
+    base_prompt: String,
+    /// `system_prompt.md` template replacing SYSTEM_PROMPT, if any
+    prompt_template: Option<String>,
+    /// Answer in the language of the instruction
+    auto_language: bool,
+    /// Semantic search choosing the files of the CODEBASE section
+    embeddings: EmbeddingSettings,
 }
 
 impl Agent {
-    pub fn new(config: AgentConfig, api_key: String, provider: ApiProvider) -> Self {
+    pub fn new(config: AgentConfig, settings: &ApiSettings) -> Self {
+        let prompt_template = prompt::load_template(Some(&config.cwd)).unwrap_or_else(|e| {
+            eprintln!("{} {}", "⚠️  Prompt système personnalisé ignoré:".yellow(), e);
+            None
+        });
         Self {
             config,
-            client: MistralClient::new(api_key, provider),
+            client: settings.client(),
+            base_prompt: settings.system_prompt(SYSTEM_PROMPT),
+            prompt_template,
+            auto_language: settings.auto_language(),
+            embeddings: EmbeddingSettings::load(),
         }
     }
 
-    pub async fn run(&self) -> Result<(), String> {
-        println!("\n{}", "🤖 COMPANION CHAT - Mode Agent".bold().cyan());
-        println!("{}", "─".repeat(40).dimmed());
-        println!("📁 Projet: {}", self.config.cwd.display());
-        println!("📝 Instruction: {}", self.config.instruction.italic());
-        println!("⚙️  Mode: {:?}", self.config.mode);
-        println!();
+    /// Use a specific model instead of the provider default
+    pub fn with_model(mut self, model: &str) -> Self {
+        self.client = self.client.for_model(model);
+        self
+    }
+
+    /// Send the requests to another backend, e.g. a scripted client in tests
+    pub fn with_client(mut self, client: Arc<dyn LlmBackend>) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Replace the built-in system prompt (and any `system_prompt.md` template)
+    pub fn with_system_prompt(mut self, prompt: String) -> Self {
+        self.base_prompt = prompt;
+        self.prompt_template = None;
+        self
+    }
+
+    /// Progress line: stdout in text format, stderr when stdout carries the JSON/YAML plan
+    fn say(&self, line: impl fmt::Display) {
+        if self.config.format == OutputFormat::Text {
+            println!("{}", line);
+        } else {
+            eprintln!("{}", line);
+        }
+    }
+
+    pub async fn run(&self) -> Result<AgentReport, AgentError> {
+        self.say(format!("\n{}", "🤖 COMPANION CHAT - Mode Agent".bold().cyan()));
+        self.say("─".repeat(40).dimmed());
+        self.say(format!("📁 Projet: {}", self.config.cwd.display()));
+        self.say(format!("📝 Instruction: {}", self.config.instruction.italic()));
+        self.say(format!("⚙️  Mode: {:?}", self.config.mode));
+        self.say("");
 
         // Phase 1: Index the codebase
-        println!("{}", "📂 Indexation du projet...".bold());
+        self.say("📂 Indexation du projet...".bold());
         let ext_refs: Vec<String>;
         let include = if let Some(exts) = &self.config.include_extensions {
             ext_refs = exts.clone();
@@ -68,113 +258,280 @@ impl Agent {
             None
         };
 
-        let index = CodebaseIndex::index(
-            &self.config.cwd,
-            include,
-            &self.config.exclude_dirs,
-            self.config.max_files,
-        )?;
+        let index = match self.config.context {
+            ContextScope::Full => CodebaseIndex::index(
+                &self.config.cwd,
+                include,
+                &self.config.exclude_dirs,
+                self.config.max_files,
+            ).map_err(AgentError::Setup)?,
+            ContextScope::Diff => CodebaseIndex::index_branch_changes(
+                &self.config.cwd,
+                include,
+                &self.config.exclude_dirs,
+                self.config.max_files,
+                |_, _| {},
+            ).map_err(AgentError::Setup)?,
+        };
 
-        println!("{}", index.summary());
+        self.say(index.summary());
+        for (path, reason) in mentioned_skips(&self.config.instruction, &index.skipped) {
+            self.say(reason.warning(path).yellow());
+        }
 
         if index.files.is_empty() {
-            return Err("Aucun fichier trouvé à analyser".to_string());
+            return Err(AgentError::Setup(match self.config.context {
+                ContextScope::Full => "Aucun fichier trouvé à analyser".to_string(),
+                ContextScope::Diff => "Aucun fichier modifié par rapport à main".to_string(),
+            }));
         }
 
         // Phase 2: Build context and send to AI
-        println!("{}", "🧠 Analyse en cours...".bold());
+        self.say("🧠 Analyse en cours...".bold());
         
-        let context_chunks = index.build_context(30000); // ~30k tokens max per chunk
-        
-        let pb = ProgressBar::new_spinner();
-        pb.set_style(ProgressStyle::default_spinner()
-            .template("{spinner:.green} {msg}")
-            .unwrap());
-        pb.set_message("Envoi à l'IA...");
+        let mut codebase = CodebaseContext::from_index(&index, CODEBASE_TOKENS);
+        // Too big to send whole: the contents of the files closest to the instruction instead of the first ones
+        if self.embeddings.enabled && self.config.context == ContextScope::Full && index.total_tokens_estimate > CODEBASE_TOKENS {
+            match semantic_search::relevant_files(&index, self.client.as_ref(), &self.embeddings, &self.config.instruction).await {
+                Ok(paths) => {
+                    self.say(format!("🔎 Fichiers pertinents: {}", paths.join(", ")).dimmed());
+                    codebase = codebase.focus(&index, &paths, CODEBASE_TOKENS);
+                }
+                Err(e) => self.say(format!("⚠️  Recherche sémantique indisponible, premiers fichiers envoyés: {}", e).yellow()),
+            }
+        }
 
         // Build the prompt
-        let mut prompt = format!("CODEBASE:\n{}\n\n", context_chunks.first().unwrap_or(&String::new()));
-        prompt.push_str(&format!("INSTRUCTION: {}\n", self.config.instruction));
-        
-        if self.config.mode == ExecutionMode::Plan {
-            prompt.push_str("\nNOTE: Mode PLAN uniquement. Propose un plan détaillé sans fournir de modifications de code.");
+        let language_rule = self.auto_language
+            .then(|| crate::language::reply_rule(&self.config.instruction))
+            .flatten()
+            .unwrap_or_default();
+        let mode = match self.config.mode {
+            ExecutionMode::Plan => ChatMode::Plan,
+            // The agent answers once: no AUTO continuation loop
+            ExecutionMode::Interactive | ExecutionMode::Auto => ChatMode::Code,
+        };
+        let memory = prompt::load_memory(&self.config.cwd);
+        let pinned = crate::pinned::context(&self.config.cwd, &crate::pinned::load(&self.config.cwd));
+        let history = if self.config.with_history {
+            let history = crate::git_history::context(&self.config.cwd, &self.config.instruction, &index.files);
+            if history.is_empty() {
+                self.say("🕓 Aucun historique git trouvé pour les fonctions de l'instruction".dimmed());
+            }
+            history
+        } else {
+            String::new()
+        };
+        let instruction = Message {
+            role: Role::User,
+            content: format!("INSTRUCTION: {}\n", self.config.instruction),
+        };
+
+        // Too big for the model: the file contents give way to a repo map, then to the file list
+        let window = self.client.context_window() as usize;
+        let budget = window.saturating_sub(self.client.count_tokens(&instruction.content) + RESPONSE_RESERVE_TOKENS);
+        let fitted = context_guard::fit(ContextLevel::Full, budget, |level| {
+            PromptBuilder::new(&self.base_prompt)
+                .template(self.prompt_template.as_deref())
+                .codebase(codebase.get(level))
+                .history(&history)
+                .pinned(&pinned)
+                .memory(&memory)
+                .append(&language_rule)
+                .mode(mode)
+                .build()
+        });
+        if !fitted.fits {
+            return Err(AgentError::Budget(format!(
+                "Prompt of ~{} tokens exceeds the {} token context window of {}, even with only the file list",
+                fitted.tokens + self.client.count_tokens(&instruction.content), window, self.client.get_model()
+            )));
+        }
+        if let Some(warning) = context_guard::downgrade_warning(ContextLevel::Full, &fitted, budget) {
+            self.say(warning.yellow());
         }
 
-        let messages = vec![
+        let mut messages = vec![
             Message {
-                role: "system".to_string(),
-                content: SYSTEM_PROMPT.to_string(),
-            },
-            Message {
-                role: "user".to_string(),
-                content: prompt,
+                role: Role::System,
+                content: fitted.prompt,
             },
+            instruction,
         ];
 
-        let response = self.client.chat(messages).await.map_err(|e| e.to_string())?;
+        let pb = ProgressBar::new_spinner();
+        pb.set_style(ProgressStyle::default_spinner()
+            .template("{spinner:.green} {msg}")
+            .unwrap());
+        pb.set_message("Envoi à l'IA...");
+
+        let retry_policy = RetryPolicy::load();
+        let mut response = self.client
+            .chat_with_retry(messages.clone(), retry_policy, |notice| pb.set_message(format!("Envoi à l'IA... {}", notice)))
+            .await
+            .map_err(AgentError::from_api)?;
         pb.finish_and_clear();
 
         // Phase 3: Parse and display changes
-        let changes = parse_ai_response(&response, &self.config.cwd);
+        let mut changes = parse_ai_response(&response, &self.config.cwd);
+
+        // No patch matched the files: send the mismatch back and ask for a corrected one
+        let mut retries = 0;
+        while self.config.mode != ExecutionMode::Plan && retries < MAX_PATCH_RETRIES {
+            let Some(retry_prompt) = changes.patch_retry_prompt() else {
+                break;
+            };
+            retries += 1;
+            println!("{}", format!("🔁 Patch non applicable, nouvelle tentative ({}/{})...", retries, MAX_PATCH_RETRIES).yellow());
+
+            messages.push(Message { role: Role::Assistant, content: response });
+            messages.push(Message { role: Role::User, content: retry_prompt });
+            response = self.client
+                .chat_with_retry(messages.clone(), retry_policy, |notice| println!("{}", format!("⟳ {}", notice).yellow()))
+                .await
+                .map_err(AgentError::from_api)?;
+            changes = parse_ai_response(&response, &self.config.cwd);
+        }
+        if self.config.mode == ExecutionMode::Interactive && !self.config.dry_run {
+            changes.resolve_failures_interactive();
+        }
+        let text_output = self.config.format == OutputFormat::Text;
+        if text_output {
+            changes.display_patch_failures();
+        }
+        let mut report = AgentReport {
+            response: response.clone(),
+            proposed: changes.modifications.len() + changes.new_files.len() + changes.deletions.len()
+                + changes.renames.len() + changes.resolved.len(),
+            applied: changes.resolved.iter().map(|c| c.path.clone()).collect(),
+            failed: if self.config.mode == ExecutionMode::Auto && !self.config.dry_run {
+                changes.patch_failures.len()
+            } else {
+                0
+            },
+            plan: changes.plan.clone(),
+            files: changes.modifications.iter().map(|c| ProposedFile { path: c.path.clone(), action: "modify" })
+                .chain(changes.new_files.iter().map(|f| ProposedFile { path: f.path.clone(), action: "create" }))
+                .chain(changes.deletions.iter().map(|d| ProposedFile { path: d.path.clone(), action: "delete" }))
+                .chain(changes.renames.iter().map(|r| ProposedFile { path: r.to.clone(), action: "rename" }))
+                .collect(),
+            stats: changes.stats(),
+            ..Default::default()
+        };
         
-        changes.display_plan();
+        if text_output {
+            changes.display_plan();
+        }
 
         if self.config.mode == ExecutionMode::Plan {
-            println!("{}", "✅ Plan généré (mode plan, aucune modification appliquée)".green());
-            return Ok(());
+            self.say("✅ Plan généré (mode plan, aucune modification appliquée)".green());
+            return Ok(report);
         }
 
         if changes.is_empty() {
             println!("{}", "ℹ️  Aucune modification de fichier proposée.".yellow());
-            return Ok(());
+            return Ok(report);
         }
 
-        println!("\n{}", format!("📊 Changements proposés: {}", changes.summary()).bold());
+        println!("\n{}", format!("📊 Changements proposés: {}", report.stats.header()).bold());
+        println!("{}", changes.summary().dimmed());
         changes.display_all_changes();
 
         // Phase 4: Apply changes based on mode
         if self.config.dry_run {
             println!("\n{}", "🔍 Mode dry-run: aucune modification appliquée".yellow());
-            return Ok(());
+            return Ok(report);
         }
 
-        match self.config.mode {
-            ExecutionMode::Auto => {
-                self.apply_all_changes(&changes)?;
-            }
-            ExecutionMode::Interactive => {
-                self.apply_changes_interactive(&changes)?;
-            }
+        // Journaled even when a write fails midway, so what was applied can be rolled back
+        let mut journal = ChangeJournal::new();
+        let applied = match self.config.mode {
+            ExecutionMode::Auto => self.apply_all_changes(&changes, &mut report, &mut journal),
+            ExecutionMode::Interactive => self.apply_changes_interactive(&changes, &mut report, &mut journal),
             ExecutionMode::Plan => unreachable!(),
+        };
+        if let Err(e) = journal.save(&self.config.cwd) {
+            println!("{} {}", "⚠️".yellow(), e);
+        } else if !journal.is_empty() {
+            println!("{}", "↩️  Annulable avec: companion-chat rollback".dimmed());
         }
+        if !journal.is_empty() && auto_commit::enabled(&self.config.cwd) {
+            match auto_commit::commit(&self.config.cwd, &journal.paths(&self.config.cwd), &journal.stats(), self.client.as_ref()).await {
+                Ok(Some(commit)) => println!("{} {}", "📝 Commit:".green(), commit),
+                Ok(None) => {}
+                Err(e) => println!("{} {}", "⚠️".yellow(), e),
+            }
+        }
+        applied?;
 
-        Ok(())
+        Ok(report)
     }
 
-    fn apply_all_changes(&self, changes: &ChangeSet) -> Result<(), String> {
+    fn apply_all_changes(&self, changes: &ChangeSet, report: &mut AgentReport, journal: &mut ChangeJournal) -> Result<(), AgentError> {
         println!("\n{}", "⚡ Application automatique des changements...".bold());
+
+        for rename in &changes.renames {
+            rename.apply(&self.config.cwd).map_err(AgentError::Apply)?;
+            rename.record(&self.config.cwd, journal);
+            report.renamed.push((rename.from.clone(), rename.to.clone()));
+            println!("  {} {} → {}", "✓".green(), rename.from, rename.to);
+        }
         
         for change in &changes.modifications {
-            change.apply()?;
+            let change = match change.keeping_local_edits() {
+                Ok(change) => change,
+                Err(e) => {
+                    println!("  {} {}", "⚠️".yellow(), e);
+                    continue;
+                }
+            };
+            change.apply().map_err(AgentError::Apply)?;
+            journal.record(&self.config.cwd, Path::new(&change.path), Some(change.original.clone()), Some(change.modified.clone()));
+            report.applied.push(change.path.clone());
             println!("  {} {}", "✓".green(), change.path);
         }
         
         for new_file in &changes.new_files {
-            new_file.apply()?;
+            let before = fs::read_to_string(&new_file.path).ok();
+            new_file.apply().map_err(AgentError::Apply)?;
+            journal.record(&self.config.cwd, Path::new(&new_file.path), before, Some(new_file.content.clone()));
+            report.applied.push(new_file.path.clone());
             println!("  {} {} (nouveau)", "✓".green(), new_file.path);
         }
 
+        for deletion in &changes.deletions {
+            let trashed = deletion.trash(&self.config.cwd).map_err(AgentError::Apply)?;
+            journal.record(&self.config.cwd, Path::new(&deletion.path), Some(deletion.content.clone()), None);
+            report.deleted.push(deletion.path.clone());
+            println!("  {} {} (supprimé, corbeille: {})", "✓".green(), deletion.path, trashed.display());
+        }
+
         println!("\n{}", "✅ Toutes les modifications ont été appliquées!".green().bold());
         Ok(())
     }
 
-    fn apply_changes_interactive(&self, changes: &ChangeSet) -> Result<(), String> {
+    fn apply_changes_interactive(&self, changes: &ChangeSet, report: &mut AgentReport, journal: &mut ChangeJournal) -> Result<(), AgentError> {
         println!();
 
+        // Moves first: the other changes of a moved file target its new path
+        for rename in &changes.renames {
+            println!("{}", rename.display());
+            if confirm("Déplacer ce fichier?") {
+                rename.apply(&self.config.cwd).map_err(AgentError::Apply)?;
+                rename.record(&self.config.cwd, journal);
+                report.renamed.push((rename.from.clone(), rename.to.clone()));
+                println!("  {}", "✓ Déplacé".green());
+            } else {
+                println!("  {}", "✗ Ignoré".yellow());
+            }
+        }
+
         for change in &changes.modifications {
             println!("{}", change.display_diff());
-            if confirm("Appliquer cette modification?") {
-                change.apply()?;
+            if let Some(change) = confirm_change(change, Some("Appliquer cette modification?")) {
+                change.apply().map_err(AgentError::Apply)?;
+                journal.record(&self.config.cwd, Path::new(&change.path), Some(change.original.clone()), Some(change.modified.clone()));
+                report.applied.push(change.path.clone());
                 println!("  {}", "✓ Appliqué".green());
             } else {
                 println!("  {}", "✗ Ignoré".yellow());
@@ -184,60 +541,97 @@ impl Agent {
         for new_file in &changes.new_files {
             println!("{}", new_file.display());
             if confirm("Créer ce fichier?") {
-                new_file.apply()?;
+                let before = fs::read_to_string(&new_file.path).ok();
+                new_file.apply().map_err(AgentError::Apply)?;
+                journal.record(&self.config.cwd, Path::new(&new_file.path), before, Some(new_file.content.clone()));
+                report.applied.push(new_file.path.clone());
                 println!("  {}", "✓ Créé".green());
             } else {
                 println!("  {}", "✗ Ignoré".yellow());
             }
         }
 
+        for deletion in &changes.deletions {
+            println!("{}", deletion.display());
+            if confirm("Supprimer ce fichier (déplacé dans .codestral/trash)?") {
+                let trashed = deletion.trash(&self.config.cwd).map_err(AgentError::Apply)?;
+                journal.record(&self.config.cwd, Path::new(&deletion.path), Some(deletion.content.clone()), None);
+                report.deleted.push(deletion.path.clone());
+                println!("  {} {}", "✓ Supprimé, corbeille:".green(), trashed.display());
+            } else {
+                println!("  {}", "✗ Ignoré".yellow());
+            }
+        }
+
         println!("\n{}", "✅ Terminé!".green().bold());
         Ok(())
     }
 }
 
-/// Load API settings from store
-pub fn load_api_settings() -> Result<(String, ApiProvider), String> {
-    // tauri-plugin-store saves to data_dir, not config_dir
-    let data_dir = dirs::data_dir()
-        .ok_or("Cannot find data directory")?
-        .join("com.rony.companion-chat");
+/// Load API settings from store, merged with the project overrides
+pub fn load_api_settings(project_root: Option<&Path>) -> crate::error::Result<ApiSettings> {
+    let (api_key, provider) = match load_saved_api_settings() {
+        Some(settings) => settings,
+        // No valid API key found - start setup wizard
+        None => setup_api_key_wizard().map_err(Error::MissingApiKey)?,
+    };
+    if let Some(root) = project_root {
+        confirm_project_endpoint(root)?;
+    }
     
-    let settings_path = data_dir.join("settings.json");
+    Ok(ApiSettings::merge(api_key, provider, project_root)?)
+}
+
+/// Ask before following a project's settings to an endpoint never used before. `--yes` does
+/// not answer for the user here: the project may come from anyone
+fn confirm_project_endpoint(project_root: &Path) -> Result<(), String> {
+    let Some(base_url) = unconfirmed_endpoint(project_root) else {
+        return Ok(());
+    };
+    println!("{}", format!("⚠️  Les paramètres du projet envoient les requêtes (code compris) à {}", base_url).yellow().bold());
+    if assume_yes() {
+        println!("{}", "   Point d'accès à confirmer sans --yes".dimmed());
+    } else if confirm("Faire confiance à ce point d'accès?") {
+        trust_endpoint(&base_url)?;
+    }
+    Ok(())
+}
+
+/// Read a line without echoing it, showing • for each character
+fn read_hidden_line() -> Result<String, String> {
+    use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+    use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
+    use std::io::{self, Write};
     
-    // Try to load existing settings
-    if settings_path.exists() {
-        if let Ok(content) = fs::read_to_string(&settings_path) {
-            if let Ok(json) = serde_json::from_str::<serde_json::Value>(&content) {
-                if let Some(config) = json.get("config") {
-                    let api_key = config.get("api_key")
-                        .and_then(|v| v.as_str())
-                        .unwrap_or("")
-                        .to_string();
-                    
-                    if !api_key.is_empty() {
-                        let provider_str = config.get("provider")
-                            .and_then(|v| v.as_str())
-                            .unwrap_or("MistralAi");
-                        
-                        let provider = match provider_str {
-                            "Codestral" => ApiProvider::Codestral,
-                            _ => ApiProvider::MistralAi,
-                        };
-                        
-                        return Ok((api_key, provider));
-                    }
-                }
+    enable_raw_mode().map_err(|e| e.to_string())?;
+    let mut line = String::new();
+    let result = loop {
+        let key = match event::read() {
+            Ok(Event::Key(key)) if key.kind != KeyEventKind::Release => key,
+            Ok(_) => continue,
+            Err(e) => break Err(e.to_string()),
+        };
+        match key.code {
+            KeyCode::Enter => break Ok(line),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                break Err("Configuration annulée.".to_string());
+            }
+            KeyCode::Backspace if line.pop().is_some() => print!("\u{8} \u{8}"),
+            KeyCode::Char(c) => {
+                line.push(c);
+                print!("•");
             }
+            _ => {}
         }
-    }
-    
-    // No valid API key found - start setup wizard
-    setup_api_key_wizard(&data_dir, &settings_path)
+        io::stdout().flush().ok();
+    };
+    disable_raw_mode().map_err(|e| e.to_string())?;
+    println!();
+    result
 }
 
 /// Interactive API key setup wizard
-fn setup_api_key_wizard(config_dir: &std::path::Path, settings_path: &std::path::Path) -> Result<(String, ApiProvider), String> {
+fn setup_api_key_wizard() -> Result<(String, ApiProvider), String> {
     use std::io::{self, Write};
     
     println!();
@@ -250,9 +644,10 @@ fn setup_api_key_wizard(config_dir: &std::path::Path, settings_path: &std::path:
     println!("{}", "Choisissez votre endpoint:".bold());
     println!("  {} Mistral AI (api.mistral.ai)", "[1]".cyan());
     println!("  {} Codestral (codestral.mistral.ai)", "[2]".cyan());
+    println!("  {} Ollama (serveur local, sans clé)", "[3]".cyan());
     println!();
     
-    print!("{} ", "Votre choix [1/2]:".yellow());
+    print!("{} ", "Votre choix [1/2/3]:".yellow());
     io::stdout().flush().unwrap();
     
     let mut choice = String::new();
@@ -263,49 +658,53 @@ fn setup_api_key_wizard(config_dir: &std::path::Path, settings_path: &std::path:
             println!("{}", "→ Codestral sélectionné".green());
             ApiProvider::Codestral
         }
+        "3" => {
+            println!("{}", "→ Ollama sélectionné".green());
+            ApiProvider::Ollama
+        }
         _ => {
             println!("{}", "→ Mistral AI sélectionné".green());
             ApiProvider::MistralAi
         }
     };
     
-    // Enter API key
+    // Local server: no key, only check that it answers
+    if !provider.needs_api_key() {
+        println!("{}", "⏳ Connexion au serveur Ollama...".dimmed());
+        if let Err(e) = validate_api_key("", &provider) {
+            println!("{} {}", "⚠️ Serveur Ollama injoignable:".yellow(), e);
+        }
+        save_api_settings("", &provider)?;
+        println!("{}", "✅ Configuration sauvegardée!".green().bold());
+        println!();
+        return Ok((String::new(), provider));
+    }
+    
+    // Enter API key (hidden), until the endpoint accepts it
     println!();
     println!("{}", "Entrez votre clé API:".bold());
     println!("{}", "(Obtenez-la sur https://console.mistral.ai)".dimmed());
-    println!();
-    
-    print!("{} ", "Clé API:".yellow());
-    io::stdout().flush().unwrap();
-    
-    let mut api_key = String::new();
-    io::stdin().read_line(&mut api_key).map_err(|e| e.to_string())?;
-    let api_key = api_key.trim().to_string();
-    
-    if api_key.is_empty() {
-        return Err("Clé API vide. Annulé.".to_string());
-    }
-    
-    // Save settings
-    fs::create_dir_all(config_dir).map_err(|e| format!("Cannot create config dir: {}", e))?;
-    
-    let provider_str = match provider {
-        ApiProvider::Codestral => "Codestral",
-        ApiProvider::MistralAi => "MistralAi",
-    };
     
-    let settings = serde_json::json!({
-        "config": {
-            "api_key": api_key,
-            "provider": provider_str
+    let api_key = loop {
+        println!();
+        print!("{} ", "Clé API:".yellow());
+        io::stdout().flush().unwrap();
+        
+        let api_key = read_hidden_line()?.trim().to_string();
+        if api_key.is_empty() {
+            println!("{}", "❌ Clé API vide.".red());
+            continue;
         }
-    });
-    
-    let json = serde_json::to_string_pretty(&settings)
-        .map_err(|e| format!("Serialize error: {}", e))?;
+        
+        println!("{}", "⏳ Vérification de la clé...".dimmed());
+        match validate_api_key(&api_key, &provider) {
+            Ok(()) => break api_key,
+            Err(e) => println!("{} {}", "❌ Clé refusée:".red(), e),
+        }
+    };
     
-    fs::write(settings_path, json)
-        .map_err(|e| format!("Write error: {}", e))?;
+    // Save settings
+    save_api_settings(&api_key, &provider)?;
     
     println!();
     println!("{}", "✅ Configuration sauvegardée!".green().bold());
@@ -314,3 +713,47 @@ fn setup_api_key_wizard(config_dir: &std::path::Path, settings_path: &std::path:
     Ok((api_key, provider))
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mistral_client::ApiError;
+    use reqwest::StatusCode;
+
+    #[test]
+    fn test_exit_codes() {
+        let report = |proposed: usize, applied: usize, failed: usize| AgentReport {
+            proposed,
+            applied: vec!["src/main.rs".to_string(); applied],
+            failed,
+            ..Default::default()
+        };
+        assert_eq!(report(0, 0, 0).exit_code(), EXIT_SUCCESS);
+        assert_eq!(report(2, 2, 0).exit_code(), EXIT_SUCCESS);
+        assert_eq!(report(2, 0, 0).exit_code(), EXIT_NOT_APPLIED);
+        assert_eq!(report(2, 1, 1).exit_code(), EXIT_APPLY_FAILED);
+
+        let api = |status: StatusCode, message: &str| {
+            AgentError::from_api(ApiError { status, message: message.to_string() }.into()).exit_code()
+        };
+        assert_eq!(api(StatusCode::BAD_REQUEST, "Prompt contains 300000 tokens, too large for model with 256000 maximum context length"), EXIT_BUDGET_EXCEEDED);
+        assert_eq!(api(StatusCode::UNAUTHORIZED, "Unauthorized"), EXIT_API_ERROR);
+        assert_eq!(AgentError::from(Error::Other("Aucun fichier trouvé à analyser".to_string())).exit_code(), EXIT_SETUP);
+    }
+
+    #[test]
+    fn test_plan_output_formats() {
+        let report = AgentReport {
+            plan: vec!["Ajouter \"retry\" au client".to_string()],
+            files: vec![ProposedFile { path: "src/client.rs".to_string(), action: "modify" }],
+            ..Default::default()
+        };
+        assert_eq!(report.render("retry", OutputFormat::Text), None);
+        let json: serde_json::Value = serde_json::from_str(&report.render("retry", OutputFormat::Json).unwrap()).unwrap();
+        assert_eq!(json["files"][0]["action"], "modify");
+        // Both formats come from the same Serialize derive
+        let yaml: serde_json::Value = serde_yaml::from_str(&report.render("retry", OutputFormat::Yaml).unwrap()).unwrap();
+        assert_eq!(yaml, json);
+        assert_eq!(yaml["steps"][0], "Ajouter \"retry\" au client");
+        assert_eq!(yaml["stats"]["languages"], serde_json::json!([]));
+    }
+}
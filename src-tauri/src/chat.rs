@@ -1,13 +1,24 @@
 use crate::cli::ChatConfig;
-use crate::indexer::CodebaseIndex;
-use crate::differ::{parse_ai_response, confirm};
-use crate::mistral_client::{MistralClient, ApiProvider, Message};
+use crate::mode::ChatMode;
+use crate::indexer::{mentioned_skips, CodebaseIndex};
+use crate::context_guard::count_tokens;
+use crate::differ::{parse_ai_response, confirm, confirm_default_yes, confirm_change, MAX_PATCH_RETRIES};
+use crate::journal::ChangeJournal;
+use crate::auto_commit;
+use crate::backend::LlmBackend;
+use crate::mistral_client::{check_connectivity, Message, RetryNotice, RetryPolicy, Role};
 use crate::agent::load_api_settings;
-use crate::chat_storage::{ChatStorage, SavedChat};
+use crate::settings::ApiSettings;
+use crate::chat_storage::{ChatSource, ChatStorage, SavedChat};
+use crate::prompt::{self, PromptBuilder};
 use colored::*;
-use std::io::{self, Write};
+use std::cell::RefCell;
+use std::path::Path;
+use std::sync::Arc;
+use std::io::{self, IsTerminal, Write};
+use std::time::{Duration, Instant};
 use crossterm::event::{self, Event, KeyCode, KeyModifiers};
-use crossterm::terminal;
+use crossterm::terminal::{self, Clear, ClearType};
 use chrono::Utc;
 
 const CHAT_SYSTEM_PROMPT: &str = r#"Tu es un assistant de programmation expert intégré dans un terminal. Tu analyses des codebases et proposes des modifications.
@@ -31,54 +42,113 @@ Pour créer un nouveau fichier:
 contenu
 </new_file>
 
+Pour réécrire entièrement un fichier (grosses modifications):
+<replace_file path="chemin/fichier.ext">
+nouveau contenu complet du fichier
+</replace_file>
+
+Pour supprimer un fichier:
+<delete_file path="chemin/fichier.ext" />
+
+Pour déplacer ou renommer un fichier (l'historique git est conservé; les modifications du fichier utilisent ensuite le nouveau chemin):
+<rename_file from="chemin/fichier.ext" to="autre/chemin/fichier.ext" />
+
 Si tu ne proposes pas de modifications, réponds simplement en texte.
 "#;
 
 const MAX_CONTEXT_TOKENS: usize = 32000;
+/// The history is summarized past 90% of the context, like in the TUI
+const COMPACT_THRESHOLD: usize = (MAX_CONTEXT_TOKENS * 90) / 100;
 const MODES: [ChatMode; 4] = [ChatMode::Ask, ChatMode::Plan, ChatMode::Code, ChatMode::Auto];
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub enum ChatMode {
-    Ask,
-    Plan,
-    Code,
-    Auto,
+/// Mode name in its color: ASK blue, PLAN yellow, CODE green, AUTO red
+fn color_name(mode: ChatMode) -> colored::ColoredString {
+    match mode {
+        ChatMode::Ask => "ASK".blue(),
+        ChatMode::Plan => "PLAN".yellow(),
+        ChatMode::Code => "CODE".green(),
+        ChatMode::Auto => "AUTO".red(),
+    }
 }
 
-impl ChatMode {
-    fn next(&self) -> ChatMode {
-        match self {
-            ChatMode::Ask => ChatMode::Plan,
-            ChatMode::Plan => ChatMode::Code,
-            ChatMode::Code => ChatMode::Auto,
-            ChatMode::Auto => ChatMode::Ask,
-        }
+/// Announce a retry on its own line, after the "🤖" prompt
+fn print_retry(notice: &RetryNotice) {
+    println!("{}", format!("⟳ {}", notice).yellow());
+}
+
+/// Redraws of the streaming footer at most
+const PROGRESS_REFRESH: Duration = Duration::from_millis(100);
+
+/// Footer of a streaming answer, e.g. "⏳ 312 tokens · 44.6 tok/s · 7s"
+fn progress_footer(tokens: usize, elapsed: Duration) -> String {
+    let seconds = elapsed.as_secs_f64();
+    let rate = if seconds > 0.0 { tokens as f64 / seconds } else { 0.0 };
+    format!("⏳ {} tokens · {:.1} tok/s · {}s", tokens, rate, elapsed.as_secs())
+}
+
+/// Live footer on the "🤖" line while an answer streams, cleared once it is complete.
+/// Each streamed piece is one token of the model; nothing is drawn outside a terminal
+struct StreamProgress {
+    started: Instant,
+    tokens: usize,
+    drawn: Option<Instant>,
+    enabled: bool,
+}
+
+impl StreamProgress {
+    fn new() -> Self {
+        Self { started: Instant::now(), tokens: 0, drawn: None, enabled: io::stdout().is_terminal() }
     }
 
-    fn color_name(&self) -> colored::ColoredString {
-        match self {
-            ChatMode::Ask => "ASK".blue(),
-            ChatMode::Plan => "PLAN".yellow(),
-            ChatMode::Code => "CODE".green(),
-            ChatMode::Auto => "AUTO".red(),
+    fn add(&mut self, delta: &str) {
+        if delta.is_empty() {
+            return;
         }
+        self.tokens += 1;
+        if !self.enabled || self.drawn.is_some_and(|drawn| drawn.elapsed() < PROGRESS_REFRESH) {
+            return;
+        }
+        let mut stdout = io::stdout();
+        let _ = crossterm::queue!(stdout, crossterm::cursor::MoveToColumn(0), Clear(ClearType::CurrentLine));
+        print!("{}{}", "🤖 ".dimmed(), progress_footer(self.tokens, self.started.elapsed()).dimmed());
+        let _ = stdout.flush();
+        self.drawn = Some(Instant::now());
     }
-}
 
-impl std::fmt::Display for ChatMode {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            ChatMode::Ask => write!(f, "ASK"),
-            ChatMode::Plan => write!(f, "PLAN"),
-            ChatMode::Code => write!(f, "CODE"),
-            ChatMode::Auto => write!(f, "AUTO"),
+    /// Erase the footer, leaving the "🤖" prompt as it was
+    fn clear(&mut self) {
+        if self.drawn.take().is_none() {
+            return;
         }
+        let mut stdout = io::stdout();
+        let _ = crossterm::queue!(stdout, crossterm::cursor::MoveToColumn(0), Clear(ClearType::CurrentLine));
+        print!("{}", "🤖 ".dimmed());
+        let _ = stdout.flush();
+    }
+
+    /// A retry starts the answer over
+    fn restart(&mut self) {
+        self.clear();
+        self.started = Instant::now();
+        self.tokens = 0;
     }
 }
 
 pub struct ChatSession {
     config: ChatConfig,
-    client: MistralClient,
+    client: Arc<dyn LlmBackend>,
+    retry_policy: RetryPolicy,
+    /// Chat prompt with the project language rule
+    base_prompt: String,
+    /// `system_prompt.md` template replacing the chat prompt, if any
+    prompt_template: Option<String>,
+    /// File contents sent outside ASK mode, once the project is indexed
+    codebase_context: String,
+    project_memory: String,
+    /// Paths pinned with `/pin` in the TUI (`.codestral/config.toml`)
+    pinned: Vec<String>,
+    /// Answer in the language of each message
+    auto_language: bool,
     messages: Vec<Message>,
     index: Option<CodebaseIndex>,
     mode: ChatMode,
@@ -87,54 +157,150 @@ pub struct ChatSession {
 }
 
 impl ChatSession {
-    pub fn new(config: ChatConfig, api_key: String, provider: ApiProvider) -> Result<Self, String> {
+    pub fn new(config: ChatConfig, settings: &ApiSettings) -> Result<Self, String> {
         let storage = ChatStorage::new()?;
         let project_path = config.cwd.to_string_lossy().to_string();
         let current_chat = SavedChat::new(&project_path);
+        let project_memory = prompt::load_memory(&config.cwd);
         
-        Ok(Self {
+        let mut session = Self {
+            client: settings.client(),
+            retry_policy: RetryPolicy::load(),
+            base_prompt: settings.system_prompt(CHAT_SYSTEM_PROMPT),
+            prompt_template: prompt::load_template(Some(&config.cwd))?,
+            codebase_context: String::new(),
+            project_memory,
+            pinned: crate::pinned::load(&config.cwd),
             config,
-            client: MistralClient::new(api_key, provider),
-            messages: vec![Message {
-                role: "system".to_string(),
-                content: CHAT_SYSTEM_PROMPT.to_string(),
-            }],
+            messages: Vec::new(),
+            auto_language: settings.auto_language(),
             index: None,
             mode: ChatMode::Code,
             storage,
             current_chat,
-        })
+        };
+        session.messages.push(Message {
+            role: Role::System,
+            content: session.system_prompt(None),
+        });
+        Ok(session)
+    }
+
+    /// Send the requests to another backend, e.g. a scripted client in tests
+    pub fn with_client(mut self, client: Arc<dyn LlmBackend>) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// System prompt for the current mode, with the reply language of `user_message`
+    /// when the language is "auto"
+    fn system_prompt(&self, user_message: Option<&str>) -> String {
+        let language_rule = user_message
+            .filter(|_| self.auto_language)
+            .and_then(crate::language::reply_rule)
+            .unwrap_or_default();
+        // Like the TUI, no code in ASK; AUTO applies the changes without the TUI continuation loop
+        let (codebase, mode) = match self.mode {
+            ChatMode::Ask => ("", ChatMode::Ask),
+            ChatMode::Auto => (self.codebase_context.as_str(), ChatMode::Code),
+            mode => (self.codebase_context.as_str(), mode),
+        };
+        PromptBuilder::new(&self.base_prompt)
+            .template(self.prompt_template.as_deref())
+            .codebase(codebase)
+            .pinned(&crate::pinned::context(&self.config.cwd, &self.pinned))
+            .memory(&self.project_memory)
+            .append(&language_rule)
+            .mode(mode)
+            .build()
+    }
+
+    /// Messages to send, with the system prompt of the current mode
+    fn request_messages(&self, user_message: &str) -> Vec<Message> {
+        let mut messages = self.messages.clone();
+        messages[0].content = self.system_prompt(Some(user_message));
+        messages
+    }
+
+    /// Send the conversation with the answer streamed, showing its progress under the prompt
+    async fn send(&self, user_message: &str) -> anyhow::Result<String> {
+        let progress = RefCell::new(StreamProgress::new());
+        let result = self.client.chat_stream_with_retry(
+            self.request_messages(user_message),
+            self.retry_policy,
+            |notice| {
+                progress.borrow_mut().restart();
+                print_retry(notice);
+            },
+            |delta| progress.borrow_mut().add(delta),
+        ).await;
+        progress.borrow_mut().clear();
+        result
     }
 
     fn estimate_tokens(&self) -> usize {
-        self.messages.iter().map(|m| m.content.len() / 4).sum()
+        self.messages.iter().map(|m| count_tokens(&m.content)).sum()
     }
 
     fn print_status_bar(&self) {
         let tokens = self.estimate_tokens();
         let remaining = MAX_CONTEXT_TOKENS.saturating_sub(tokens);
+        let pinned = if self.pinned.is_empty() { String::new() } else { format!(" │ 📌 {}", self.pinned.join(", ")) };
         
         println!(
             "{}",
             format!(
-                "─── {} │ Tokens: ~{}/{} (~{}%) │ Shift+Tab: changer mode ───",
-                self.mode.color_name(),
+                "─── {} │ Tokens: ~{}/{} (~{}%){} │ Shift+Tab: changer mode ───",
+                color_name(self.mode),
                 tokens,
                 MAX_CONTEXT_TOKENS,
-                (remaining * 100) / MAX_CONTEXT_TOKENS
+                (remaining * 100) / MAX_CONTEXT_TOKENS,
+                pinned
             ).dimmed()
         );
     }
 
+    /// Replace the history with a summary, keeping the system prompt; false when the summary
+    /// request failed and the history was kept as it was
+    async fn compact(&mut self) -> bool {
+        let history: String = self.messages.iter()
+            .filter(|m| m.role != Role::System)
+            .map(|m| format!("{}: {}", if m.role == Role::User { "User" } else { "AI" }, m.content))
+            .collect::<Vec<_>>()
+            .join("\n");
+        if history.is_empty() {
+            println!("{}", "Rien à compacter.".dimmed());
+            return false;
+        }
+
+        println!("{}", "🗜️  Compactage du contexte...".dimmed());
+        let before = self.estimate_tokens();
+        match self.client.chat_with_retry(crate::prompt::compact_messages(&history), self.retry_policy, print_retry).await {
+            Ok(summary) => {
+                self.messages.truncate(1);
+                self.messages.push(Message {
+                    role: Role::Assistant,
+                    content: format!("📝 Contexte compacté:\n{}", summary),
+                });
+                println!("{}", format!("📝 Contexte compacté (~{} → ~{} tokens)", before, self.estimate_tokens()).green());
+                true
+            }
+            Err(e) => {
+                println!("{} {}", "Compactage impossible:".red(), e);
+                false
+            }
+        }
+    }
+
     fn cycle_mode(&mut self) {
         self.mode = self.mode.next();
-        println!("\n{} Mode {} activé", "⚡".bold(), self.mode.color_name());
+        println!("\n{} Mode {} activé", "⚡".bold(), color_name(self.mode));
     }
 
     fn save_current_chat(&mut self) {
         // Copy messages (skip system prompt)
         self.current_chat.messages = self.messages.iter()
-            .filter(|m| m.role != "system")
+            .filter(|m| m.role != Role::System)
             .cloned()
             .collect();
         self.current_chat.updated_at = Utc::now();
@@ -167,9 +333,14 @@ impl ChatSession {
                 
                 for (i, chat) in chats.iter().take(10).enumerate() {
                     let msg_count = chat.messages.len();
+                    let origin = match chat.source {
+                        ChatSource::Gui => "[GUI] ".magenta().to_string(),
+                        ChatSource::Cli => String::new(),
+                    };
                     println!(
-                        "  {} {} {} ({})",
+                        "  {} {}{} {} ({})",
                         format!("[{}]", i + 1).cyan(),
+                        origin,
                         chat.title.bold(),
                         chat.time_ago().dimmed(),
                         format!("{} msgs", msg_count).dimmed()
@@ -206,10 +377,10 @@ impl ChatSession {
             Ok(chat) => {
                 // Rebuild messages with system prompt
                 self.messages = vec![Message {
-                    role: "system".to_string(),
-                    content: CHAT_SYSTEM_PROMPT.to_string(),
+                    role: Role::System,
+                    content: self.system_prompt(None),
                 }];
-                self.messages.extend(chat.messages.clone());
+                self.messages.extend(chat.api_messages());
                 self.current_chat = chat;
                 
                 println!("{} \"{}\"", "✅ Conversation reprise:".green(), self.current_chat.title);
@@ -217,9 +388,9 @@ impl ChatSession {
                 // Show last 3 messages for context
                 let recent: Vec<_> = self.messages.iter().rev().take(4).collect();
                 for msg in recent.into_iter().rev() {
-                    if msg.role == "user" {
+                    if msg.role == Role::User {
                         println!("  {} {}", "Vous:".cyan(), &msg.content[..msg.content.len().min(60)]);
-                    } else if msg.role == "assistant" {
+                    } else if msg.role == Role::Assistant {
                         let preview = &msg.content[..msg.content.len().min(60)];
                         println!("  {} {}...", "IA:".green(), preview);
                     }
@@ -236,13 +407,7 @@ impl ChatSession {
         
         // Confirm working directory
         println!("📁 Répertoire: {}", self.config.cwd.display().to_string().cyan());
-        print!("{} ", "Correct? [O/n]".yellow());
-        io::stdout().flush().unwrap();
-        
-        let mut input = String::new();
-        io::stdin().read_line(&mut input).unwrap();
-        
-        if input.trim().to_lowercase() == "n" {
+        if !confirm_default_yes("Correct?") {
             println!("{}", "Utilisez: companion-chat chat -c /chemin/projet".dimmed());
             return Ok(());
         }
@@ -268,10 +433,16 @@ impl ChatSession {
             println!("{}", idx.summary());
             let context = idx.build_context(20000);
             if let Some(first_chunk) = context.first() {
-                self.messages[0].content = format!("{}\n\nCODEBASE:\n{}", CHAT_SYSTEM_PROMPT, first_chunk);
+                self.codebase_context = first_chunk.clone();
+                self.messages[0].content = self.system_prompt(None);
             }
         }
 
+        if let Err(e) = check_connectivity(self.client.provider(), Duration::from_secs(3)) {
+            println!("{} {}", "📴 Hors ligne:".red().bold(), e);
+            println!("{}", "/resume et /reindex restent disponibles ; les messages échoueront jusqu'au retour du réseau.".dimmed());
+        }
+
         println!("{}", "─".repeat(60).dimmed());
         println!("{}", "💬 Chat interactif. Tapez /aide pour les commandes.".green().bold());
         self.print_status_bar();
@@ -337,6 +508,24 @@ impl ChatSession {
                     self.print_status_bar();
                     continue;
                 }
+                "/compact" => {
+                    self.compact().await;
+                    self.print_status_bar();
+                    continue;
+                }
+                "/undo" => {
+                    self.undo();
+                    self.print_status_bar();
+                    continue;
+                }
+                "/health" => {
+                    println!("{}", "🩺 Vérification du fournisseur...".bold());
+                    let health = crate::health::check(self.client.as_ref()).await;
+                    for line in health.lines() {
+                        println!("  {}", line);
+                    }
+                    continue;
+                }
                 "/clear" => {
                     self.messages.truncate(1);
                     println!("{}", "🗑️  Historique effacé.".yellow());
@@ -346,21 +535,56 @@ impl ChatSession {
                 _ => {}
             }
 
+            // Nearly full: summarize the history before adding the message
+            if self.messages.len() > 2 && self.estimate_tokens() + count_tokens(trimmed) > COMPACT_THRESHOLD {
+                self.compact().await;
+            }
+
             // Send to AI
             self.messages.push(Message {
-                role: "user".to_string(),
+                role: Role::User,
                 content: trimmed.to_string(),
             });
 
+            if let Some(index) = &self.index {
+                for (path, reason) in mentioned_skips(trimmed, &index.skipped) {
+                    println!("{}", reason.warning(path).yellow());
+                }
+            }
+
             print!("{}", "🤖 ".dimmed());
             io::stdout().flush().unwrap();
 
-            match self.client.chat(self.messages.clone()).await {
+            let mut result = self.send(trimmed).await;
+            
+            // No patch matched the files: send the mismatch back and ask for a corrected one
+            let mut retries = 0;
+            while let Ok(response) = &result {
+                if matches!(self.mode, ChatMode::Ask | ChatMode::Plan) || retries >= MAX_PATCH_RETRIES {
+                    break;
+                }
+                let Some(retry_prompt) = parse_ai_response(response, &self.config.cwd).patch_retry_prompt() else {
+                    break;
+                };
+                retries += 1;
+                println!("{}", format!("🔁 Patch non applicable, nouvelle tentative ({}/{})...", retries, MAX_PATCH_RETRIES).yellow());
+                
+                self.messages.push(Message { role: Role::Assistant, content: response.clone() });
+                self.messages.push(Message { role: Role::User, content: retry_prompt });
+                result = self.send(trimmed).await;
+            }
+            
+            match result {
                 Ok(response) => {
-                    let changes = parse_ai_response(&response, &self.config.cwd);
+                    let mut changes = parse_ai_response(&response, &self.config.cwd);
+                    if self.mode == ChatMode::Code {
+                        changes.resolve_failures_interactive();
+                    }
+                    changes.display_patch_failures();
                     
                     if !changes.is_empty() && self.mode != ChatMode::Ask {
                         changes.display_plan();
+                        println!("\n{}", format!("📊 {}", changes.stats().header()).bold());
                         changes.display_all_changes();
                         
                         match self.mode {
@@ -370,14 +594,14 @@ impl ChatSession {
                             ChatMode::Code => {
                                 println!();
                                 if confirm("Appliquer?") {
-                                    self.apply_changes(&changes);
+                                    self.apply_changes(&changes).await;
                                 } else {
                                     println!("{}", "Ignoré.".yellow());
                                 }
                             }
                             ChatMode::Auto => {
                                 println!("\n{}", "⚡ Application...".bold());
-                                self.apply_changes(&changes);
+                                self.apply_changes(&changes).await;
                             }
                             ChatMode::Ask => {}
                         }
@@ -386,7 +610,7 @@ impl ChatSession {
                     }
 
                     self.messages.push(Message {
-                        role: "assistant".to_string(),
+                        role: Role::Assistant,
                         content: response,
                     });
                     
@@ -459,21 +683,102 @@ impl ChatSession {
         }
     }
 
-    fn apply_changes(&self, changes: &crate::differ::ChangeSet) {
+    async fn apply_changes(&self, changes: &crate::differ::ChangeSet) {
+        let root = self.config.cwd.as_path();
+        let mut journal = ChangeJournal::new();
+        // Moves first: the other changes of a moved file target its new path
+        for rename in &changes.renames {
+            if self.mode != ChatMode::Auto {
+                println!("{}", rename.display());
+                if !confirm("Déplacer ce fichier?") {
+                    println!("  {} {}", "✗ Ignoré:".yellow(), rename.from);
+                    continue;
+                }
+            }
+            match rename.apply(root) {
+                Ok(()) => {
+                    rename.record(root, &mut journal);
+                    println!("  {} {} → {}", "✓".green(), rename.from, rename.to);
+                }
+                Err(e) => println!("  {} {}", "✗".red(), e),
+            }
+        }
         for change in &changes.modifications {
+            // Uncommitted edits are merged rather than overwritten: by hand in CODE mode, when clean in AUTO
+            let change = if self.mode == ChatMode::Auto {
+                match change.keeping_local_edits() {
+                    Ok(change) => change,
+                    Err(e) => {
+                        println!("  {} {}", "⚠️".yellow(), e);
+                        continue;
+                    }
+                }
+            } else {
+                match confirm_change(change, None) {
+                    Some(change) => change,
+                    None => {
+                        println!("  {} {}", "✗ Ignoré:".yellow(), change.path);
+                        continue;
+                    }
+                }
+            };
             if let Err(e) = change.apply() {
                 println!("  {} {}", "✗".red(), e);
             } else {
+                journal.record(root, Path::new(&change.path), Some(change.original.clone()), Some(change.modified.clone()));
                 println!("  {} {}", "✓".green(), change.path);
             }
         }
         for new_file in &changes.new_files {
+            let before = std::fs::read_to_string(&new_file.path).ok();
             if let Err(e) = new_file.apply() {
                 println!("  {} {}", "✗".red(), e);
             } else {
+                journal.record(root, Path::new(&new_file.path), before, Some(new_file.content.clone()));
                 println!("  {} {} (créé)", "✓".green(), new_file.path);
             }
         }
+        for deletion in &changes.deletions {
+            // Asked one by one in CODE mode; either way the file goes to .codestral/trash
+            if self.mode != ChatMode::Auto {
+                println!("{}", deletion.display());
+                if !confirm("Supprimer ce fichier?") {
+                    println!("  {} {}", "✗ Ignoré:".yellow(), deletion.path);
+                    continue;
+                }
+            }
+            match deletion.trash(root) {
+                Ok(trashed) => {
+                    journal.record(root, Path::new(&deletion.path), Some(deletion.content.clone()), None);
+                    println!("  {} {} (corbeille: {})", "✓".green(), deletion.path, trashed.display());
+                }
+                Err(e) => println!("  {} {}", "✗".red(), e),
+            }
+        }
+        if let Err(e) = journal.save(root) {
+            println!("  {} {}", "⚠️".yellow(), e);
+        } else if !journal.is_empty() {
+            println!("  {}", "↩️  /undo pour annuler".dimmed());
+        }
+        if !journal.is_empty() && auto_commit::enabled(root) {
+            match auto_commit::commit(root, &journal.paths(root), &journal.stats(), self.client.as_ref()).await {
+                Ok(Some(commit)) => println!("  {} {}", "📝".green(), commit),
+                Ok(None) => {}
+                Err(e) => println!("  {} {}", "⚠️".yellow(), e),
+            }
+        }
+    }
+
+    /// `/undo`: roll back the last change set applied in the project
+    fn undo(&self) {
+        let Some(mut journal) = ChangeJournal::last(&self.config.cwd) else {
+            println!("{}", "📭 Aucune modification à annuler.".yellow());
+            return;
+        };
+        match journal.rollback(&self.config.cwd) {
+            Ok(_) => println!("{} {}", "↩️  Annulé:".green(), journal.summary()),
+            Err(e) => println!("{} {}", "✗".red(), e),
+        }
     }
 
     fn print_header(&self) {
@@ -491,6 +796,9 @@ impl ChatSession {
         println!("  {} Quitter   {} Aide", "/quit".cyan(), "/aide".cyan());
         println!("  {} Nouvelle  {} Reprendre", "/new".cyan(), "/resume".cyan());
         println!("  {} Réindexer {} Effacer", "/reindex".cyan(), "/clear".cyan());
+        println!("  {} Résumer l'historique (automatique à 90%)", "/compact".cyan());
+        println!("  {} Annuler les dernières modifications appliquées", "/undo".cyan());
+        println!("  {} État du fournisseur: latence, modèle, quota", "/health".cyan());
         println!();
         println!("{}", "🔄 MODES (Shift+Tab pour cycler)".bold());
         println!("{}", "─".repeat(40).dimmed());
@@ -500,7 +808,19 @@ impl ChatSession {
 }
 
 pub async fn run_chat_session(config: ChatConfig) -> Result<(), String> {
-    let (api_key, provider) = load_api_settings()?;
-    let mut session = ChatSession::new(config, api_key, provider)?;
+    let mut settings = load_api_settings(Some(&config.cwd))?;
+    settings.apply_overrides(config.base_url.as_deref(), config.model.as_deref());
+    let mut session = ChatSession::new(config, &settings)?;
     session.start().await
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress_footer() {
+        assert_eq!(progress_footer(312, Duration::from_millis(7_000)), "⏳ 312 tokens · 44.6 tok/s · 7s");
+        assert_eq!(progress_footer(0, Duration::ZERO), "⏳ 0 tokens · 0.0 tok/s · 0s");
+    }
+}
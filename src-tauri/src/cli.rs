@@ -1,5 +1,7 @@
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 use std::path::PathBuf;
+use crate::workspace::Workspace;
+pub use crate::indexer::ContextScope;
 
 #[derive(Parser, Debug)]
 #[command(name = "companion-chat")]
@@ -11,6 +13,19 @@ pub struct Cli {
     #[arg(long, short = 'c')]
     pub cwd: Option<PathBuf>,
 
+    /// OpenAI-compatible endpoint to use instead of the configured provider (vLLM, LM Studio, OpenRouter...),
+    /// e.g. http://localhost:8000/v1
+    #[arg(long, global = true, requires = "model")]
+    pub base_url: Option<String>,
+
+    /// Model to request, instead of the configured one
+    #[arg(long, global = true)]
+    pub model: Option<String>,
+
+    /// Answer yes to every confirmation (apply, create, move, delete), for scripts and CI
+    #[arg(long, short = 'y', global = true)]
+    pub yes: bool,
+
     /// Instruction for the AI agent
     #[arg(trailing_var_arg = true)]
     pub instruction: Vec<String>,
@@ -41,6 +56,18 @@ pub enum Commands {
         /// Maximum files to analyze
         #[arg(long, default_value = "50")]
         max_files: usize,
+        
+        /// Files sent as CODEBASE: the whole project, or only those changed on the branch vs main
+        #[arg(long, value_enum, default_value_t = ContextScope::Full)]
+        context: ContextScope,
+        
+        /// Add the git blame/log of the functions named in the instruction (when and why their lines changed)
+        #[arg(long)]
+        with_history: bool,
+        
+        /// Output: readable text, or the plan steps and proposed files for other tools
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
     },
     
     /// Interactive mode: show diffs and ask for confirmation
@@ -63,6 +90,14 @@ pub enum Commands {
         /// Maximum files to analyze
         #[arg(long, default_value = "50")]
         max_files: usize,
+        
+        /// Files sent as CODEBASE: the whole project, or only those changed on the branch vs main
+        #[arg(long, value_enum, default_value_t = ContextScope::Full)]
+        context: ContextScope,
+        
+        /// Add the git blame/log of the functions named in the instruction (when and why their lines changed)
+        #[arg(long)]
+        with_history: bool,
     },
     
     /// Auto mode: apply changes immediately after showing diffs
@@ -86,6 +121,14 @@ pub enum Commands {
         #[arg(long, default_value = "50")]
         max_files: usize,
         
+        /// Files sent as CODEBASE: the whole project, or only those changed on the branch vs main
+        #[arg(long, value_enum, default_value_t = ContextScope::Full)]
+        context: ContextScope,
+        
+        /// Add the git blame/log of the functions named in the instruction (when and why their lines changed)
+        #[arg(long)]
+        with_history: bool,
+        
     /// Dry run - show what would be done without making changes
         #[arg(long)]
         dry_run: bool,
@@ -93,9 +136,13 @@ pub enum Commands {
     
     /// Interactive chat mode: REPL-like interface for continuous interaction
     Chat {
-        /// Working directory (defaults to current directory)
+        /// Working directory (defaults to current directory); repeat it to work on several project roots
         #[arg(long, short = 'c')]
-        cwd: Option<PathBuf>,
+        cwd: Vec<PathBuf>,
+        
+        /// Workspace file listing the project roots, instead of --cwd
+        #[arg(long, short = 'w', conflicts_with = "cwd")]
+        workspace: Option<PathBuf>,
         
         /// File extensions to include
         #[arg(long, short = 'e')]
@@ -108,12 +155,117 @@ pub enum Commands {
         /// Maximum files to analyze
         #[arg(long, default_value = "50")]
         max_files: usize,
+        
+        /// Files sent as CODEBASE: the whole project, or only those changed on the branch vs main
+        #[arg(long, value_enum, default_value_t = ContextScope::Full)]
+        context: ContextScope,
+
+        /// Record the session to .codestral/sessions/ for later replay
+        #[arg(long)]
+        record: bool,
+    },
+    
+    /// Evaluation harness: run task fixtures against models and modes
+    Eval {
+        /// Directory containing fixtures (<name>/task.json + <name>/project/)
+        dir: PathBuf,
+        
+        /// Models to evaluate, comma-separated ("default" = provider default)
+        #[arg(long, short = 'm', default_value = "default")]
+        models: String,
+        
+        /// Modes to evaluate, comma-separated (plan, auto)
+        #[arg(long, default_value = "auto")]
+        modes: String,
+        
+        /// File replacing the built-in system prompt
+        #[arg(long)]
+        system_prompt: Option<PathBuf>,
+        
+        /// Write the results as JSON
+        #[arg(long, short = 'o')]
+        output: Option<PathBuf>,
+    },
+    
+    /// Replay a recorded TUI session step by step
+    Replay {
+        /// Recording file (.jsonl)
+        file: PathBuf,
+    },
+    
+    /// List the recently opened projects, from every mode
+    Recent {
+        /// Number of projects to show
+        #[arg(long, short = 'n', default_value = "10")]
+        limit: usize,
+    },
+    
+    /// Complete the code at a cursor position (fill-in-the-middle), for editor integrations
+    Fim {
+        /// File to complete
+        #[arg(long, short = 'f')]
+        file: PathBuf,
+        
+        /// Line of the cursor (1-based)
+        #[arg(long, short = 'l')]
+        line: usize,
+        
+        /// Column of the cursor (1-based); the end of the line by default
+        #[arg(long)]
+        column: Option<usize>,
+        
+        /// Maximum tokens of the completion
+        #[arg(long, default_value = "256")]
+        max_tokens: u32,
+    },
+    
+    /// Roll back the last change set applied in the project, from .codestral/backups/
+    Rollback {
+        /// Project directory (defaults to current directory)
+        #[arg(long, short = 'c')]
+        cwd: Option<PathBuf>,
+    },
+    
+    /// Inspect the project index
+    Index {
+        #[command(subcommand)]
+        command: IndexCommand,
     },
     
     /// Start the GUI application (default if no command given)
     Gui,
 }
 
+#[derive(Subcommand, Debug)]
+pub enum IndexCommand {
+    /// Files and size of the index, and the files it leaves out
+    Stats {
+        /// Project directory (defaults to current directory)
+        #[arg(long, short = 'c')]
+        cwd: Option<PathBuf>,
+        
+        /// List the skipped files with the reason (too large, binary, past --max-files, generated)
+        #[arg(long)]
+        skipped: bool,
+        
+        /// Maximum files of the prompt context, as given to the agent commands
+        #[arg(long, default_value = "50")]
+        max_files: usize,
+    },
+}
+
+/// Output of the plan command
+#[derive(Debug, Clone, Copy, PartialEq, Default, ValueEnum)]
+pub enum OutputFormat {
+    /// Readable output in the terminal
+    #[default]
+    Text,
+    /// Plan steps and proposed files as JSON on stdout, progress on stderr
+    Json,
+    /// Same as json, in YAML
+    Yaml,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ExecutionMode {
     Plan,
@@ -128,13 +280,17 @@ pub struct AgentConfig {
     pub include_extensions: Option<Vec<String>>,
     pub exclude_dirs: Vec<String>,
     pub max_files: usize,
+    pub context: ContextScope,
     pub dry_run: bool,
+    pub format: OutputFormat,
+    /// Git history of the declarations named in the instruction, in the prompt
+    pub with_history: bool,
 }
 
 impl AgentConfig {
     pub fn from_cli(cli: &Cli) -> Option<Self> {
         match &cli.command {
-            Some(Commands::Plan { cwd, instruction, include, exclude, max_files }) => {
+            Some(Commands::Plan { cwd, instruction, include, exclude, max_files, context, with_history, format }) => {
                 Some(AgentConfig {
                     cwd: cwd.clone(),
                     instruction: instruction.join(" "),
@@ -142,10 +298,13 @@ impl AgentConfig {
                     include_extensions: include.as_ref().map(|s| s.split(',').map(|x| x.trim().to_string()).collect()),
                     exclude_dirs: exclude.clone().unwrap_or_default(),
                     max_files: *max_files,
+                    context: *context,
                     dry_run: true, // Plan mode is always dry-run
+                    format: *format,
+                    with_history: *with_history,
                 })
             }
-            Some(Commands::Interactive { cwd, instruction, include, exclude, max_files }) => {
+            Some(Commands::Interactive { cwd, instruction, include, exclude, max_files, context, with_history }) => {
                 Some(AgentConfig {
                     cwd: cwd.clone(),
                     instruction: instruction.join(" "),
@@ -153,10 +312,13 @@ impl AgentConfig {
                     include_extensions: include.as_ref().map(|s| s.split(',').map(|x| x.trim().to_string()).collect()),
                     exclude_dirs: exclude.clone().unwrap_or_default(),
                     max_files: *max_files,
+                    context: *context,
                     dry_run: false,
+                    format: OutputFormat::Text,
+                    with_history: *with_history,
                 })
             }
-            Some(Commands::Auto { cwd, instruction, include, exclude, max_files, dry_run }) => {
+            Some(Commands::Auto { cwd, instruction, include, exclude, max_files, context, with_history, dry_run }) => {
                 Some(AgentConfig {
                     cwd: cwd.clone(),
                     instruction: instruction.join(" "),
@@ -164,10 +326,14 @@ impl AgentConfig {
                     include_extensions: include.as_ref().map(|s| s.split(',').map(|x| x.trim().to_string()).collect()),
                     exclude_dirs: exclude.clone().unwrap_or_default(),
                     max_files: *max_files,
+                    context: *context,
                     dry_run: *dry_run,
+                    format: OutputFormat::Text,
+                    with_history: *with_history,
                 })
             }
-            Some(Commands::Gui) | Some(Commands::Chat { .. }) | None => None,
+            Some(Commands::Gui) | Some(Commands::Chat { .. }) | Some(Commands::Replay { .. }) | Some(Commands::Eval { .. })
+            | Some(Commands::Recent { .. }) | Some(Commands::Fim { .. }) | Some(Commands::Rollback { .. }) | Some(Commands::Index { .. }) | None => None,
         }
     }
 }
@@ -184,26 +350,141 @@ pub fn is_chat_mode(cli: &Cli) -> bool {
     matches!(cli.command, Some(Commands::Chat { .. }))
 }
 
+/// Build the eval configuration if the eval subcommand was given
+pub fn eval_config(cli: &Cli) -> Option<Result<crate::eval::EvalConfig, String>> {
+    match &cli.command {
+        Some(Commands::Eval { dir, models, modes, system_prompt, output }) => {
+            let config = (|| {
+                let system_prompt = match system_prompt {
+                    Some(path) => Some(std::fs::read_to_string(path)
+                        .map_err(|e| format!("Cannot read {}: {}", path.display(), e))?),
+                    None => None,
+                };
+                Ok(crate::eval::EvalConfig {
+                    fixtures_dir: dir.clone(),
+                    models: models.split(',').map(|m| m.trim().to_string()).filter(|m| !m.is_empty()).collect(),
+                    modes: crate::eval::parse_modes(modes)?,
+                    system_prompt,
+                    output: output.clone(),
+                })
+            })();
+            Some(config)
+        }
+        _ => None,
+    }
+}
+
+/// Get the recording file if the replay subcommand was given
+pub fn replay_file(cli: &Cli) -> Option<&PathBuf> {
+    match &cli.command {
+        Some(Commands::Replay { file }) => Some(file),
+        _ => None,
+    }
+}
+
+/// Number of projects to list if the recent subcommand was given
+pub fn recent_limit(cli: &Cli) -> Option<usize> {
+    match &cli.command {
+        Some(Commands::Recent { limit }) => Some(*limit),
+        _ => None,
+    }
+}
+
+/// Build the cursor to complete if the fim subcommand was given
+pub fn fim_config(cli: &Cli) -> Option<crate::fim::FimConfig> {
+    match &cli.command {
+        Some(Commands::Fim { file, line, column, max_tokens }) => Some(crate::fim::FimConfig {
+            file: file.clone(),
+            line: *line,
+            column: *column,
+            max_tokens: *max_tokens,
+        }),
+        _ => None,
+    }
+}
+
+/// Project to roll back if the rollback subcommand was given
+pub fn rollback_dir(cli: &Cli) -> Option<PathBuf> {
+    match &cli.command {
+        Some(Commands::Rollback { cwd }) => Some(cwd.clone().unwrap_or_else(|| std::env::current_dir().unwrap_or_default())),
+        _ => None,
+    }
+}
+
+/// (project, list the skipped files, max files) if `index stats` was given
+pub fn index_stats_config(cli: &Cli) -> Option<(PathBuf, bool, usize)> {
+    match &cli.command {
+        Some(Commands::Index { command: IndexCommand::Stats { cwd, skipped, max_files } }) => {
+            Some((cwd.clone().unwrap_or_else(|| std::env::current_dir().unwrap_or_default()), *skipped, *max_files))
+        }
+        _ => None,
+    }
+}
+
 pub struct ChatConfig {
+    /// Primary project root
     pub cwd: PathBuf,
+    /// Additional roots given with repeated --cwd
+    pub extra_roots: Vec<PathBuf>,
+    pub workspace_file: Option<PathBuf>,
     pub include_extensions: Option<Vec<String>>,
     pub exclude_dirs: Vec<String>,
     pub max_files: usize,
+    pub context: ContextScope,
+    pub record: bool,
+    /// Neither --cwd nor --workspace given: the TUI asks for the project
+    pub pick_project: bool,
+    /// --base-url and --model
+    pub base_url: Option<String>,
+    pub model: Option<String>,
 }
 
 impl ChatConfig {
     pub fn from_cli(cli: &Cli) -> Option<Self> {
         match &cli.command {
-            Some(Commands::Chat { cwd, include, exclude, max_files }) => {
-                let working_dir = cwd.clone().unwrap_or_else(|| std::env::current_dir().unwrap_or_default());
+            Some(Commands::Chat { cwd, workspace, include, exclude, max_files, context, record }) => {
+                let working_dir = cwd.first().cloned().unwrap_or_else(|| std::env::current_dir().unwrap_or_default());
                 Some(ChatConfig {
                     cwd: working_dir,
+                    extra_roots: cwd.iter().skip(1).cloned().collect(),
+                    workspace_file: workspace.clone(),
                     include_extensions: include.as_ref().map(|s| s.split(',').map(|x| x.trim().to_string()).collect()),
                     exclude_dirs: exclude.clone().unwrap_or_default(),
                     max_files: *max_files,
+                    context: *context,
+                    record: *record,
+                    pick_project: cwd.is_empty() && workspace.is_none(),
+                    base_url: cli.base_url.clone(),
+                    model: cli.model.clone(),
                 })
             }
+            // companion-chat-cli without a subcommand
+            None => Some(ChatConfig {
+                cwd: cli.cwd.clone().unwrap_or_else(|| std::env::current_dir().unwrap_or_default()),
+                extra_roots: Vec::new(),
+                workspace_file: None,
+                include_extensions: None,
+                exclude_dirs: Vec::new(),
+                max_files: 50,
+                context: ContextScope::Full,
+                record: false,
+                pick_project: cli.cwd.is_none(),
+                base_url: cli.base_url.clone(),
+                model: cli.model.clone(),
+            }),
             _ => None,
         }
     }
+
+    /// Project roots: the workspace file, or the --cwd directories
+    pub fn workspace(&self) -> Result<Workspace, String> {
+        match &self.workspace_file {
+            Some(file) => Workspace::load(file),
+            None => {
+                let mut roots = vec![self.cwd.clone()];
+                roots.extend(self.extra_roots.iter().cloned());
+                Workspace::new(&roots)
+            }
+        }
+    }
 }
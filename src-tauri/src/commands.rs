@@ -1,13 +1,25 @@
-use crate::mistral_client::{MistralClient, ApiProvider, Message};
+use crate::backend::backend;
+use crate::health::{self, ProviderHealth};
+use crate::mistral_client::{ApiProvider, Message, Role};
+use crate::error::Error;
+use crate::chat_storage::{ChatSource, ChatStorage, MessageMeta, SavedChat};
+use crate::context_guard::count_tokens;
+use crate::persistent_index::{reindex_project, PersistentIndex};
+use crate::differ::{parse_ai_response_with, ApplyReport, ChangeSet};
+use crate::journal::ChangeJournal;
+use crate::workspace::Workspace;
+use crate::templates::{self, ConversationTemplate};
+use crate::audit::{Decision, ToolAudit};
+use crate::tools::{execute_dangerous_bash, is_dangerous_command, ToolResult};
 use tauri::{State, AppHandle};
 use tauri_plugin_store::StoreExt;
 use serde_json::json;
 use std::sync::Mutex;
-use uuid::Uuid;
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
-// Using a simple in-memory cache for now for active conversations state, 
-// relying on store plugin for persistence.
+// Conversations are persisted in the ChatStorage shared with the TUI/CLI,
+// settings still live in the store plugin.
 
 #[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
 pub struct Conversation {
@@ -15,12 +27,83 @@ pub struct Conversation {
     pub title: String,
     pub messages: Vec<Message>,
     pub created_at: i64,
+    #[serde(default)]
+    pub project_path: String,
+    #[serde(default)]
+    pub source: ChatSource,
+    #[serde(default)]
+    pub system_prompt: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+impl From<SavedChat> for Conversation {
+    fn from(chat: SavedChat) -> Self {
+        Self {
+            id: chat.id.clone(),
+            title: chat.title.clone(),
+            messages: chat.api_messages(),
+            created_at: chat.created_at.timestamp(),
+            project_path: chat.project_path,
+            source: chat.source,
+            system_prompt: chat.system_prompt,
+            model: chat.model,
+        }
+    }
+}
+
+/// Conversation in the trash, with how long it is kept
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct TrashedConversation {
+    #[serde(flatten)]
+    pub conversation: Conversation,
+    pub deleted_at: i64,
+    pub days_left: i64,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default)]
 pub struct AppSettings {
     pub api_key: String,
     pub provider: ApiProvider,
+    /// Summarize older messages when the context is nearly full, like the TUI
+    #[serde(default)]
+    pub auto_compact: bool,
+    /// Daily reindex of the projects, so the first prompt does not wait for the index
+    #[serde(default)]
+    pub scheduled_reindex: crate::scheduler::ReindexSchedule,
+}
+
+/// Share of the context window that triggers auto-compaction (same as the TUI)
+const COMPACT_RATIO: f64 = 0.9;
+
+/// Context usage of a conversation after a response
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct ContextBudget {
+    /// Tokens of the last request and its response, as counted by the API
+    pub used_tokens: u64,
+    pub context_window: u64,
+    pub remaining_tokens: u64,
+    /// Older messages were summarized to free the context
+    pub compacted: bool,
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct SendMessageResponse {
+    pub content: String,
+    pub budget: ContextBudget,
+}
+
+/// Message of a failed API request, with what to do about it when the kind tells
+fn request_error(error: anyhow::Error) -> String {
+    let error = Error::from(error);
+    let advice = match &error {
+        Error::Unauthorized(_) => "Check the API key and provider in Settings.",
+        Error::RateLimited(_) => "Rate limit reached: wait a moment before sending again.",
+        Error::Budget(_) => "The conversation is too long for the model, or the account quota is spent: compact it or start a new one.",
+        Error::Offline(_) => "Check the network connection, or that the local server is running.",
+        _ => return error.to_string(),
+    };
+    format!("{}\n{}", error, advice)
 }
 
 #[derive(Default)]
@@ -29,132 +112,325 @@ pub struct AppState {
     // For now we will read/write from disk/store directly to ensure persistence
 }
 
+/// Move conversations from the legacy conversations.json store into the shared storage
+pub fn migrate_legacy_store(app: &AppHandle) -> Result<usize, String> {
+    let store = app.store("conversations.json").map_err(|e| e.to_string())?;
+    let storage = ChatStorage::new()?;
+    let mut migrated = 0;
+
+    for (_, value) in store.entries() {
+        let Ok(conv) = serde_json::from_value::<Conversation>(value) else {
+            continue;
+        };
+        if storage.load(&conv.id).is_ok() {
+            continue;
+        }
+        let created_at = chrono::DateTime::from_timestamp(conv.created_at, 0).unwrap_or_else(chrono::Utc::now);
+        let chat = SavedChat {
+            id: conv.id,
+            title: conv.title,
+            project_path: conv.project_path,
+            messages: conv.messages,
+            metadata: Vec::new(),
+            created_at,
+            updated_at: created_at,
+            source: ChatSource::Gui,
+            system_prompt: conv.system_prompt,
+            model: conv.model,
+            deleted_at: None,
+        };
+        storage.save(&chat)?;
+        migrated += 1;
+    }
+
+    store.clear();
+    store.save().map_err(|e| e.to_string())?;
+    Ok(migrated)
+}
+
+/// Move every conversation started from the GUI to the trash (CLI/TUI chats are kept)
+pub fn clear_gui_history() -> Result<(), String> {
+    let storage = ChatStorage::new()?;
+    for chat in storage.list()? {
+        if chat.source == ChatSource::Gui {
+            storage.delete(&chat.id)?;
+        }
+    }
+    Ok(())
+}
+
 #[tauri::command]
 pub async fn send_message(
-    app: AppHandle,
     conversation_id: String,
     content: String,
     api_key: String,
-    provider: ApiProvider
-) -> Result<String, String> {
-    let client = MistralClient::new(api_key, provider);
-    
-    // Load conversation history using the store
-    let store = app.store("conversations.json").map_err(|e| e.to_string())?;
-    
-    let mut messages = Vec::new();
-    let mut current_conversation: Option<Conversation> = None;
+    provider: ApiProvider,
+    auto_compact: Option<bool>,
+) -> Result<SendMessageResponse, String> {
+    // Default model and language chosen at onboarding
+    let settings = crate::settings::ApiSettings::merge(api_key, provider, None)?;
+    let storage = ChatStorage::new()?;
 
-    if let Some(val) = store.get(&conversation_id) {
-         if let Ok(conv) = serde_json::from_value::<Conversation>(val) {
-             messages = conv.messages.clone();
-             current_conversation = Some(conv);
-         }
-    }
+    let mut conv = storage.load(&conversation_id)
+        .map_err(|_| "Conversation not found".to_string())?;
+    // Model of the conversation's template
+    let client = match &conv.model {
+        Some(model) => settings.client().for_model(model),
+        None => settings.client(),
+    };
 
-    if current_conversation.is_none() {
-        return Err("Conversation not found".to_string());
-    }
+    // History may come from the TUI: normalize roles before sending
+    let mut messages = conv.api_messages();
+    let mut metadata = std::mem::take(&mut conv.metadata);
+    metadata.resize(messages.len(), None);
 
     // Add user message
-    messages.push(Message { role: "user".to_string(), content: content.clone() });
+    messages.push(Message { role: Role::User, content: content.clone() });
+    metadata.push(Some(MessageMeta::now(count_tokens(&content))));
+
+    // The template prompt and the language rule are sent, not saved with the messages
+    let mut request = messages.clone();
+    let system_prompt = settings.system_prompt(conv.system_prompt.as_deref().unwrap_or(""));
+    if !system_prompt.trim().is_empty() {
+        request.insert(0, Message { role: Role::System, content: system_prompt.trim().to_string() });
+    }
 
     // Call API
-    let response_content = client.chat(messages.clone())
+    let (response_content, usage) = client.chat_with_usage(request)
         .await
-        .map_err(|e| e.to_string())?;
+        .map_err(request_error)?;
 
     // Add assistant message
-    messages.push(Message { role: "assistant".to_string(), content: response_content.clone() });
-
-    // Update conversation
-    if let Some(mut conv) = current_conversation {
-        // Auto-name conversation based on first user message if still default title
-        if conv.title == "New Conversation" && !content.is_empty() {
-            // Take first 50 chars of the user message as the title
-            let auto_title: String = content.chars().take(50).collect();
-            conv.title = if auto_title.len() < content.len() {
-                format!("{}...", auto_title.trim())
-            } else {
-                auto_title.trim().to_string()
-            };
+    messages.push(Message { role: Role::Assistant, content: response_content.clone() });
+    let mut response_meta = MessageMeta::now(count_tokens(&response_content));
+    response_meta.model = Some(client.get_model().to_string());
+    if let Some(usage) = usage {
+        response_meta.tokens = usage.completion_tokens as usize;
+    }
+    metadata.push(Some(response_meta));
+
+    // Real count from the API, or the local tokenizer's count
+    let context_window = client.context_window();
+    let mut used_tokens = usage
+        .map(|u| u.total_tokens)
+        .unwrap_or_else(|| messages.iter().map(|m| count_tokens(&m.content) as u64).sum());
+
+    // Auto-name conversation based on first user message if still default title
+    if conv.title == "New Conversation" && !content.is_empty() {
+        // Take first 50 chars of the user message as the title
+        let auto_title: String = content.chars().take(50).collect();
+        conv.title = if auto_title.len() < content.len() {
+            format!("{}...", auto_title.trim())
+        } else {
+            auto_title.trim().to_string()
+        };
+    }
+
+    // Saved before compacting: a failed summary request loses nothing
+    conv.messages = messages;
+    conv.metadata = metadata;
+    conv.updated_at = chrono::Utc::now();
+    storage.save(&conv)?;
+
+    // Nearly full: summarize everything but the last exchange. If the summary request
+    // fails, the conversation stays as saved and is compacted on a later message
+    let mut compacted = false;
+    if auto_compact.unwrap_or(false) && used_tokens as f64 >= context_window as f64 * COMPACT_RATIO && conv.messages.len() > 2 {
+        let older = &conv.messages[..conv.messages.len() - 2];
+        let history = older.iter()
+            .map(|m| format!("{}: {}", if m.role == Role::User { "User" } else { "AI" }, m.content))
+            .collect::<Vec<_>>()
+            .join("\n");
+        match client.chat(crate::prompt::compact_messages(&history)).await {
+            Ok(summary) => {
+                let recent = conv.messages.split_off(conv.messages.len() - 2);
+                let recent_meta = conv.metadata.split_off(conv.metadata.len() - 2);
+                conv.messages = vec![Message { role: Role::Assistant, content: format!("📝 Contexte compacté:\n{}", summary) }];
+                conv.metadata = vec![Some(MessageMeta::now(count_tokens(&conv.messages[0].content)))];
+                conv.messages.extend(recent);
+                conv.metadata.extend(recent_meta);
+                storage.save(&conv)?;
+                used_tokens = conv.messages.iter().map(|m| count_tokens(&m.content) as u64).sum();
+                compacted = true;
+            }
+            Err(e) => eprintln!("Auto-compact failed, conversation kept whole: {}", e),
         }
-        
-        conv.messages = messages;
-        store.set(conversation_id, json!(conv));
-        store.save().map_err(|e| e.to_string())?;
     }
 
-    Ok(response_content)
+    Ok(SendMessageResponse {
+        content: response_content,
+        budget: ContextBudget {
+            used_tokens,
+            context_window,
+            remaining_tokens: context_window.saturating_sub(used_tokens),
+            compacted,
+        },
+    })
 }
 
+/// Command output attached to a conversation (the end is kept, where build errors usually are)
+const MAX_ATTACHED_OUTPUT: usize = 20_000;
+
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct AttachResult {
+    /// Dangerous command not run: ask the user, then call again with `confirmed`
+    pub needs_confirmation: bool,
+    /// Message appended to the conversation
+    pub message: Option<Message>,
+}
+
+/// Run a shell command in the conversation's project and append its output as a user message.
+/// Dangerous commands follow the TUI policy: they need confirmation and are logged in the audit file
 #[tauri::command]
-pub async fn create_conversation(app: AppHandle, title: Option<String>) -> Result<Conversation, String> {
-    let store = app.store("conversations.json").map_err(|e| e.to_string())?;
-    let id = Uuid::new_v4().to_string();
-    
-    let conversation = Conversation {
-        id: id.clone(),
-        title: title.unwrap_or_else(|| "New Conversation".to_string()),
-        messages: Vec::new(),
-         created_at: chrono::Utc::now().timestamp(),
+pub async fn run_and_attach(conversation_id: String, command: String, confirmed: Option<bool>) -> Result<AttachResult, String> {
+    let command = command.trim().to_string();
+    if command.is_empty() {
+        return Err("No command provided".to_string());
+    }
+
+    let storage = ChatStorage::new()?;
+    let mut conv = storage.load(&conversation_id)
+        .map_err(|_| "Conversation not found".to_string())?;
+    if conv.project_path.is_empty() {
+        return Err("This conversation has no project: select a project to run commands".to_string());
+    }
+    let root = PathBuf::from(&conv.project_path);
+
+    let dangerous = is_dangerous_command(&command);
+    if dangerous && !confirmed.unwrap_or(false) {
+        return Ok(AttachResult { needs_confirmation: true, message: None });
+    }
+
+    let result = {
+        let (command, root) = (command.clone(), root.clone());
+        tauri::async_runtime::spawn_blocking(move || execute_dangerous_bash(&command, &root))
+            .await
+            .map_err(|e| e.to_string())?
+    };
+    if dangerous {
+        ToolAudit::new(&root).log("GUI", "execute_bash", &command, Decision::Approved, Some(&result))?;
+    }
+
+    let message = Message {
+        role: Role::User,
+        content: attached_output(&command, &result),
     };
 
-    store.set(id, json!(conversation));
-    store.save().map_err(|e| e.to_string())?;
+    conv.metadata.resize(conv.messages.len(), None);
+    conv.metadata.push(Some(MessageMeta::now(count_tokens(&message.content))));
+    conv.messages.push(message.clone());
+    conv.updated_at = chrono::Utc::now();
+    storage.save(&conv)?;
 
-    Ok(conversation)
+    Ok(AttachResult { needs_confirmation: false, message: Some(message) })
+}
+
+/// Message sharing a command output, cut to its last `MAX_ATTACHED_OUTPUT` characters
+fn attached_output(command: &str, result: &ToolResult) -> String {
+    let output = result.output.trim_end();
+    let skipped = output.chars().count().saturating_sub(MAX_ATTACHED_OUTPUT);
+    let output = match output.char_indices().nth(skipped) {
+        Some((start, _)) if skipped > 0 => format!("[... {} characters omitted]\n{}", skipped, &output[start..]),
+        _ => output.to_string(),
+    };
+    let status = if result.success { "success" } else { "failed" };
+    format!("Output of `{}` ({}):\n```\n{}\n```", command, status, output)
 }
 
 #[tauri::command]
-pub async fn get_conversations(app: AppHandle) -> Result<Vec<Conversation>, String> {
-    let store = app.store("conversations.json").map_err(|e| e.to_string())?;
-    let mut conversations = Vec::new();
+pub async fn create_conversation(title: Option<String>, project_path: Option<String>, template: Option<String>) -> Result<Conversation, String> {
+    let storage = ChatStorage::new()?;
 
-    // Iterate over all keys in the store
-    // Note: The store API might need to be used carefully. 
-    // If the store is large, this is inefficient, but for a local chat app it's fine.
-    // simpler: The store entries method gives us key-values.
-    
-    for (key, value) in store.entries() {
-         if let Ok(conv) = serde_json::from_value::<Conversation>(value.clone()) {
-             conversations.push(conv);
-         }
+    let mut chat = SavedChat::new(&project_path.unwrap_or_default());
+    chat.title = title.unwrap_or_else(|| "New Conversation".to_string());
+    chat.source = ChatSource::Gui;
+
+    // Templates give the conversation its system prompt, model and first messages
+    if let Some(template) = template.filter(|name| !name.is_empty()) {
+        let template = templates::find(&template)?;
+        if chat.title == "New Conversation" {
+            chat.title = template.name.clone();
+        }
+        chat.system_prompt = Some(template.system_prompt).filter(|prompt| !prompt.trim().is_empty());
+        chat.model = template.model;
+        chat.metadata = template.starter_messages.iter()
+            .map(|message| Some(MessageMeta::now(count_tokens(&message.content))))
+            .collect();
+        chat.messages = template.starter_messages;
     }
-    
-    // Sort by created_at desc
-    conversations.sort_by(|a, b| b.created_at.cmp(&a.created_at));
 
-    Ok(conversations)
+    storage.save(&chat)?;
+
+    Ok(chat.into())
 }
 
+/// List conversations, optionally scoped to a project (unscoped conversations are always included)
 #[tauri::command]
-pub async fn delete_conversation(app: AppHandle, conversation_id: String) -> Result<(), String> {
-    let store = app.store("conversations.json").map_err(|e| e.to_string())?;
-    store.delete(&conversation_id);
-    store.save().map_err(|e| e.to_string())?;
-    Ok(())
+pub async fn get_conversations(project_path: Option<String>) -> Result<Vec<Conversation>, String> {
+    let storage = ChatStorage::new()?;
+    let chats = match project_path {
+        Some(path) if !path.is_empty() => storage.list_for_project(&path)?,
+        _ => storage.list()?,
+    };
+
+    // Already sorted by last activity
+    Ok(chats.into_iter().map(Conversation::from).collect())
 }
 
 #[tauri::command]
-pub async fn rename_conversation(app: AppHandle, conversation_id: String, new_title: String) -> Result<(), String> {
-    let store = app.store("conversations.json").map_err(|e| e.to_string())?;
-    if let Some(val) = store.get(&conversation_id) {
-         if let Ok(mut conv) = serde_json::from_value::<Conversation>(val) {
-             conv.title = new_title;
-             store.set(conversation_id, json!(conv));
-             store.save().map_err(|e| e.to_string())?;
-         }
+pub async fn get_projects() -> Result<Vec<String>, String> {
+    Ok(crate::recent_projects::candidates().iter()
+        .map(|path| path.to_string_lossy().to_string())
+        .collect())
+}
+
+/// Move a conversation to the trash, where it stays 30 days
+#[tauri::command]
+pub async fn delete_conversation(conversation_id: String) -> Result<(), String> {
+    ChatStorage::new()?.delete(&conversation_id)
+}
+
+#[tauri::command]
+pub async fn get_trash() -> Result<Vec<TrashedConversation>, String> {
+    Ok(ChatStorage::new()?.list_trash()?.into_iter()
+        .map(|chat| TrashedConversation {
+            days_left: chat.days_left_in_trash(),
+            deleted_at: chat.deleted_at.unwrap_or(chat.updated_at).timestamp(),
+            conversation: Conversation::from(chat),
+        })
+        .collect())
+}
+
+#[tauri::command]
+pub async fn restore_conversation(conversation_id: String) -> Result<Conversation, String> {
+    ChatStorage::new()?.restore(&conversation_id).map(Conversation::from)
+}
+
+/// Remove the trashed conversations for good, returning how many there were
+#[tauri::command]
+pub async fn empty_trash() -> Result<usize, String> {
+    ChatStorage::new()?.empty_trash()
+}
+
+#[tauri::command]
+pub async fn rename_conversation(conversation_id: String, new_title: String) -> Result<(), String> {
+    let storage = ChatStorage::new()?;
+    if let Ok(mut conv) = storage.load(&conversation_id) {
+        conv.title = new_title;
+        storage.save(&conv)?;
     }
     Ok(())
 }
 
+/// Import the conversations of a ChatGPT or Claude export file, returning how many were added
 #[tauri::command]
-pub async fn clear_history(app: AppHandle) -> Result<(), String> {
-    let store = app.store("conversations.json").map_err(|e| e.to_string())?;
-    store.clear();
-    store.save().map_err(|e| e.to_string())?;
-    Ok(())
+pub async fn import_external(path: String, format: crate::import::ExternalFormat) -> Result<usize, String> {
+    crate::import::import_file(Path::new(path.trim()), format)
+}
+
+#[tauri::command]
+pub async fn clear_history() -> Result<(), String> {
+    clear_gui_history()
 }
 
 #[tauri::command]
@@ -173,22 +449,284 @@ pub async fn get_app_settings(app: AppHandle) -> Result<AppSettings, String> {
     Ok(settings)
 }
 
+/// Write keys of the `config` section, keeping the others (retry, notifications, language...)
+fn merge_config(app: &AppHandle, values: serde_json::Value) -> Result<(), String> {
+    let store = app.store("settings.json").map_err(|e| e.to_string())?;
+    let mut config = store.get("config").filter(|config| config.is_object()).unwrap_or_else(|| json!({}));
+    if let (Some(config), Some(values)) = (config.as_object_mut(), values.as_object()) {
+        for (key, value) in values {
+            if value.is_null() {
+                config.remove(key);
+            } else {
+                config.insert(key.clone(), value.clone());
+            }
+        }
+    }
+    store.set("config", config);
+    store.save().map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub async fn update_settings(app: AppHandle, settings: AppSettings) -> Result<(), String> {
-    let store = app.store("settings.json").map_err(|e| e.to_string())?;
-    store.set("config", json!(settings));
-    store.save().map_err(|e| e.to_string())?;
-    Ok(())
+    merge_config(&app, json!(settings))
+}
+
+/// What the first-run wizard still has to ask
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct OnboardingStatus {
+    pub has_api_key: bool,
+    pub provider: ApiProvider,
+    pub language: Option<String>,
+    pub model: Option<String>,
+}
+
+/// Whether an API key is configured, without calling the API
+#[tauri::command]
+pub async fn check_api_key(app: AppHandle) -> Result<OnboardingStatus, String> {
+    let settings = get_app_settings(app).await?;
+    let defaults = crate::settings::GlobalDefaults::load();
+    Ok(OnboardingStatus {
+        has_api_key: !settings.api_key.trim().is_empty() || !settings.provider.needs_api_key(),
+        provider: settings.provider,
+        language: defaults.language,
+        model: defaults.model,
+    })
+}
+
+/// Check a key against the API and return the models it can use
+#[tauri::command]
+pub async fn validate_key_live(api_key: String, provider: ApiProvider) -> Result<Vec<String>, String> {
+    let api_key = api_key.trim().to_string();
+    if api_key.is_empty() && provider.needs_api_key() {
+        return Err("The API key is empty".to_string());
+    }
+    let client = backend(api_key, provider, None);
+    match client.list_models().await {
+        Ok(models) if !models.is_empty() => Ok(models),
+        Err(e) if matches!(Error::from_request(&e), Error::Unauthorized(_)) => Err("Invalid API key".to_string()),
+        // Endpoint without a model list: a minimal chat request checks the key
+        _ => {
+            client.test_connection().await.map_err(|e| format!("Connection failed: {}", e))?;
+            Ok(vec![client.get_model().to_string()])
+        }
+    }
+}
+
+/// Answer language for every project without its own; empty goes back to French
+#[tauri::command]
+pub async fn set_language(app: AppHandle, language: String) -> Result<(), String> {
+    let language = language.trim();
+    let value = if language.is_empty() { serde_json::Value::Null } else { json!(language) };
+    merge_config(&app, json!({ "language": value }))
+}
+
+/// Model for every project without its own; empty goes back to the provider default
+#[tauri::command]
+pub async fn choose_default_model(app: AppHandle, model: String) -> Result<(), String> {
+    let model = model.trim();
+    let value = if model.is_empty() { serde_json::Value::Null } else { json!(model) };
+    merge_config(&app, json!({ "model": value }))
+}
+
+/// Templates offered when creating a conversation
+#[tauri::command]
+pub async fn get_conversation_templates() -> Result<Vec<ConversationTemplate>, String> {
+    Ok(templates::load())
+}
+
+/// Replace the templates; an empty list goes back to the built-in ones
+#[tauri::command]
+pub async fn save_conversation_templates(app: AppHandle, templates: Vec<ConversationTemplate>) -> Result<(), String> {
+    if templates.iter().any(|template| template.name.trim().is_empty()) {
+        return Err("Template names cannot be empty".to_string());
+    }
+    let value = if templates.is_empty() { serde_json::Value::Null } else { json!(templates) };
+    merge_config(&app, json!({ "conversation_templates": value }))
 }
 
 #[tauri::command]
 pub async fn test_api_connection(api_key: String, provider: ApiProvider) -> Result<String, String> {
-    let client = MistralClient::new(api_key, provider);
-    // Simple test message
-    let messages = vec![Message { role: "user".to_string(), content: "Hello".to_string() }];
+    let client = backend(api_key, provider, None);
     
-    match client.chat(messages).await {
+    match client.test_connection().await {
         Ok(_) => Ok("Connection successful".to_string()),
         Err(e) => Err(format!("Connection failed: {}", e)),
     }
 }
+
+/// Latency, model and quota headers of a provider, with the default model of settings.json
+#[tauri::command]
+pub async fn provider_health(api_key: String, provider: ApiProvider) -> Result<ProviderHealth, String> {
+    let settings = crate::settings::ApiSettings::merge(api_key.trim().to_string(), provider, None)?;
+    Ok(health::check(settings.client().as_ref()).await)
+}
+
+/// Whether the API host is reachable; the GUI switches to offline browsing when it is not
+#[tauri::command]
+pub async fn check_connectivity(provider: ApiProvider) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        crate::mistral_client::check_connectivity(&provider, std::time::Duration::from_secs(3))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// SQLite index of a project, as shown in the GUI
+#[derive(serde::Serialize, Clone, Debug, Default)]
+pub struct IndexStats {
+    pub files: usize,
+    pub size_bytes: u64,
+    /// Files indexed or removed by the last `index_project` call
+    pub updated: usize,
+}
+
+#[tauri::command]
+pub async fn index_project(path: String) -> Result<IndexStats, String> {
+    let root = PathBuf::from(&path);
+    if !root.is_dir() {
+        return Err(format!("Not a directory: {}", path));
+    }
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let (index, updated) = reindex_project(&root)?;
+        let (files, size_bytes) = index.stats()?;
+        Ok(IndexStats { files, size_bytes, updated })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn get_index_stats(path: String) -> Result<IndexStats, String> {
+    // Do not create an index just to report that it is empty
+    if !Path::new(&path).join(".codestral").join("index.db").exists() {
+        return Ok(IndexStats::default());
+    }
+    let (files, size_bytes) = PersistentIndex::open(Path::new(&path))?.stats()?;
+    Ok(IndexStats { files, size_bytes, updated: 0 })
+}
+
+/// Diff of one change of a response, keyed by the absolute path `apply_changes` expects
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct FileDiff {
+    pub path: String,
+    /// Path relative to the project, for display
+    pub display: String,
+    /// "modify", "create", "delete" or "rename" (`path` is then the new path)
+    pub kind: &'static str,
+    pub diff: String,
+}
+
+#[derive(serde::Serialize, Debug)]
+pub struct ParsedChanges {
+    /// Passed back as is to `apply_changes`
+    pub change_set: ChangeSet,
+    pub diffs: Vec<FileDiff>,
+}
+
+/// Changes proposed by a model response (`<file>`, `<new_file>`, `<replace_file>`, `<delete_file>`,
+/// `<rename_file>` blocks), with their unified diffs; a move is listed under its new path. Patches whose ORIGINAL does not match are in `patch_failures`
+#[tauri::command]
+pub async fn parse_changes(response_text: String, project_path: String) -> Result<ParsedChanges, String> {
+    let root = PathBuf::from(&project_path);
+    if !root.is_dir() {
+        return Err(format!("Not a directory: {}", project_path));
+    }
+    let workspace = Workspace::single(&root);
+    let change_set = parse_ai_response_with(&response_text, |path| workspace.resolve(path));
+
+    let diff = |path: &str, kind: &'static str, diff: String| FileDiff {
+        path: path.to_string(),
+        display: workspace.display(Path::new(path)),
+        kind,
+        diff,
+    };
+    let mut diffs: Vec<FileDiff> = change_set.modifications.iter()
+        .map(|change| diff(&change.path, "modify", change.unified_diff()))
+        .collect();
+    diffs.extend(change_set.new_files.iter().map(|new_file| diff(&new_file.path, "create", new_file.unified_diff())));
+    diffs.extend(change_set.deletions.iter().map(|deletion| diff(&deletion.path, "delete", deletion.unified_diff())));
+    diffs.extend(change_set.renames.iter().map(|rename| FileDiff {
+        display: format!("{} → {}", workspace.display(Path::new(&rename.from)), workspace.display(Path::new(&rename.to))),
+        ..diff(&rename.to, "rename", rename.unified_diff())
+    }));
+
+    Ok(ParsedChanges { change_set, diffs })
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct ApplyOptions {
+    pub project_path: String,
+    /// Paths (as in `FileDiff::path`) the user approved; the other changes are left out
+    pub approved: Vec<String>,
+}
+
+/// Apply the approved files of a change set returned by `parse_changes`.
+/// Every approved path must be inside the project
+#[tauri::command]
+pub async fn apply_changes(change_set: ChangeSet, options: ApplyOptions) -> Result<ApplyReport, String> {
+    let workspace = Workspace::single(Path::new(&options.project_path));
+    if let Some(outside) = options.approved.iter().find(|path| !workspace.contains(Path::new(path))) {
+        return Err(format!("{} is outside the project", outside));
+    }
+    if let Some(rename) = change_set.renames.iter()
+        .find(|rename| options.approved.contains(&rename.to) && !workspace.contains(Path::new(&rename.from)))
+    {
+        return Err(format!("{} is outside the project", rename.from));
+    }
+
+    let root = workspace.primary().to_path_buf();
+    let mut report = tauri::async_runtime::spawn_blocking(move || {
+        let report = change_set.apply_selected(workspace.primary(), |path| options.approved.iter().any(|approved| approved == path));
+        // Keep an existing SQLite index in step with the files written or deleted
+        let root = workspace.primary();
+        if root.join(".codestral").join("index.db").exists() {
+            let touched: Vec<PathBuf> = report.touched().into_iter().map(PathBuf::from).collect();
+            PersistentIndex::open(root)?.sync_files(&touched, None, usize::MAX)?;
+        }
+        Ok::<_, String>(report)
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    if crate::auto_commit::enabled(&root) {
+        let touched: Vec<PathBuf> = report.touched().into_iter().map(PathBuf::from).collect();
+        // No setup wizard from the GUI: the key comes from the settings only
+        let settings = crate::settings::load_saved_api_settings()
+            .ok_or_else(|| "No API key configured: commit skipped".to_string())
+            .and_then(|(api_key, provider)| crate::settings::ApiSettings::merge(api_key, provider, Some(&root)));
+        let committed = match settings {
+            Ok(settings) => {
+                // apply_selected journaled what it applied
+                let stats = ChangeJournal::last(&root).map(|journal| journal.stats()).unwrap_or_default();
+                crate::auto_commit::commit(&root, &touched, &stats, settings.client().as_ref()).await
+            }
+            Err(e) => Err(e),
+        };
+        match committed {
+            Ok(commit) => report.commit = commit,
+            Err(e) => report.errors.push(e),
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attached_output_keeps_the_end() {
+        let result = |output: String, success: bool| ToolResult { name: "execute_bash".to_string(), success, output, needs_confirmation: false };
+        assert_eq!(
+            attached_output("cargo build", &result("error[E0425]\n\n".to_string(), false)),
+            "Output of `cargo build` (failed):\n```\nerror[E0425]\n```"
+        );
+
+        let long = format!("{}é fin", "é".repeat(MAX_ATTACHED_OUTPUT));
+        let attached = attached_output("make", &result(long, true));
+        assert!(attached.starts_with("Output of `make` (success):\n```\n[... 5 characters omitted]\né"));
+        assert!(attached.ends_with("é fin\n```"));
+        assert!(is_dangerous_command("rm -rf target") && !is_dangerous_command("cargo test"));
+    }
+}
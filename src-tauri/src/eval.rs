@@ -0,0 +1,362 @@
+use crate::agent::{Agent, AgentReport};
+use crate::settings::ApiSettings;
+use crate::cli::{AgentConfig, ContextScope, ExecutionMode, OutputFormat};
+use colored::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+use crate::wsl;
+
+/// Expected outcome of a task, checked after the agent ran
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Check {
+    /// A file exists in the project after the run
+    FileExists { path: String },
+    /// A file contains the given text
+    FileContains { path: String, text: String },
+    /// A file does not contain the given text
+    FileNotContains { path: String, text: String },
+    /// The model response contains the given text
+    ResponseContains { text: String },
+    /// A shell command exits successfully (e.g. "cargo test")
+    Command { command: String },
+}
+
+impl Check {
+    fn label(&self) -> String {
+        match self {
+            Check::FileExists { path } => format!("file_exists {}", path),
+            Check::FileContains { path, text } => format!("file_contains {} \"{}\"", path, text),
+            Check::FileNotContains { path, text } => format!("file_not_contains {} \"{}\"", path, text),
+            Check::ResponseContains { text } => format!("response_contains \"{}\"", text),
+            Check::Command { command } => format!("command `{}`", command),
+        }
+    }
+
+    fn run(&self, project: &Path, report: &AgentReport) -> bool {
+        match self {
+            Check::FileExists { path } => project.join(path).exists(),
+            Check::FileContains { path, text } => fs::read_to_string(project.join(path))
+                .map(|c| c.contains(text.as_str()))
+                .unwrap_or(false),
+            Check::FileNotContains { path, text } => fs::read_to_string(project.join(path))
+                .map(|c| !c.contains(text.as_str()))
+                .unwrap_or(true),
+            Check::ResponseContains { text } => report.response.contains(text.as_str()),
+            Check::Command { command } => wsl::shell_command(command, project)
+                .output()
+                .map(|out| out.status.success())
+                .unwrap_or(false),
+        }
+    }
+}
+
+/// A task fixture: `<dir>/task.json` plus an optional `<dir>/project/` copied before each run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskFixture {
+    #[serde(skip)]
+    pub name: String,
+    #[serde(skip)]
+    pub dir: PathBuf,
+    pub instruction: String,
+    pub checks: Vec<Check>,
+}
+
+/// Result of one fixture for one model/mode combination
+#[derive(Debug, Clone, Serialize)]
+pub struct EvalResult {
+    pub fixture: String,
+    pub model: String,
+    pub mode: String,
+    pub passed: bool,
+    pub failed_checks: Vec<String>,
+    pub error: Option<String>,
+}
+
+pub struct EvalConfig {
+    pub fixtures_dir: PathBuf,
+    pub models: Vec<String>,
+    pub modes: Vec<ExecutionMode>,
+    pub system_prompt: Option<String>,
+    pub output: Option<PathBuf>,
+}
+
+/// Load every fixture found in the directory (sorted by name)
+pub fn load_fixtures(dir: &Path) -> Result<Vec<TaskFixture>, String> {
+    let entries = fs::read_dir(dir)
+        .map_err(|e| format!("Cannot read fixtures directory {}: {}", dir.display(), e))?;
+
+    let mut fixtures = Vec::new();
+    for entry in entries.flatten() {
+        let task_file = entry.path().join("task.json");
+        if !task_file.exists() {
+            continue;
+        }
+        let content = fs::read_to_string(&task_file)
+            .map_err(|e| format!("Cannot read {}: {}", task_file.display(), e))?;
+        let mut fixture: TaskFixture = serde_json::from_str(&content)
+            .map_err(|e| format!("Invalid fixture {}: {}", task_file.display(), e))?;
+        fixture.name = entry.file_name().to_string_lossy().to_string();
+        fixture.dir = entry.path();
+        fixtures.push(fixture);
+    }
+
+    fixtures.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(fixtures)
+}
+
+/// Copy the fixture project into a fresh temporary working directory
+fn prepare_workdir(fixture: &TaskFixture) -> Result<PathBuf, String> {
+    let workdir = std::env::temp_dir()
+        .join(format!("companion-eval-{}", uuid::Uuid::new_v4()));
+    fs::create_dir_all(&workdir)
+        .map_err(|e| format!("Cannot create work directory: {}", e))?;
+
+    let source = fixture.dir.join("project");
+    if source.exists() {
+        for entry in WalkDir::new(&source).into_iter().filter_map(|e| e.ok()) {
+            let relative = entry.path().strip_prefix(&source).unwrap_or(entry.path());
+            let target = workdir.join(relative);
+            if entry.file_type().is_dir() {
+                fs::create_dir_all(&target)
+                    .map_err(|e| format!("Cannot create {}: {}", target.display(), e))?;
+            } else {
+                fs::copy(entry.path(), &target)
+                    .map_err(|e| format!("Cannot copy {}: {}", entry.path().display(), e))?;
+            }
+        }
+    }
+
+    Ok(workdir)
+}
+
+fn mode_name(mode: ExecutionMode) -> &'static str {
+    match mode {
+        ExecutionMode::Plan => "plan",
+        ExecutionMode::Interactive => "interactive",
+        ExecutionMode::Auto => "auto",
+    }
+}
+
+/// Parse a comma-separated list of modes (interactive is not supported: it needs a human)
+pub fn parse_modes(modes: &str) -> Result<Vec<ExecutionMode>, String> {
+    modes.split(',')
+        .map(|m| match m.trim().to_lowercase().as_str() {
+            "plan" => Ok(ExecutionMode::Plan),
+            "auto" => Ok(ExecutionMode::Auto),
+            other => Err(format!("Unsupported eval mode: {} (use plan or auto)", other)),
+        })
+        .collect()
+}
+
+async fn run_one(
+    fixture: &TaskFixture,
+    model: &str,
+    mode: ExecutionMode,
+    config: &EvalConfig,
+    settings: &ApiSettings,
+) -> EvalResult {
+    let mut result = EvalResult {
+        fixture: fixture.name.clone(),
+        model: model.to_string(),
+        mode: mode_name(mode).to_string(),
+        passed: false,
+        failed_checks: Vec::new(),
+        error: None,
+    };
+
+    let workdir = match prepare_workdir(fixture) {
+        Ok(dir) => dir,
+        Err(e) => {
+            result.error = Some(e);
+            return result;
+        }
+    };
+
+    let agent_config = AgentConfig {
+        cwd: workdir.clone(),
+        instruction: fixture.instruction.clone(),
+        mode,
+        include_extensions: None,
+        exclude_dirs: Vec::new(),
+        max_files: 50,
+        context: ContextScope::Full,
+        dry_run: false,
+        format: OutputFormat::Text,
+        with_history: false,
+    };
+
+    let mut agent = Agent::new(agent_config, settings);
+    if model != "default" {
+        agent = agent.with_model(model);
+    }
+    if let Some(prompt) = &config.system_prompt {
+        agent = agent.with_system_prompt(prompt.clone());
+    }
+
+    match agent.run().await {
+        Ok(report) => {
+            for check in &fixture.checks {
+                if !check.run(&workdir, &report) {
+                    result.failed_checks.push(check.label());
+                }
+            }
+            result.passed = result.failed_checks.is_empty();
+        }
+        Err(e) => result.error = Some(e.to_string()),
+    }
+
+    let _ = fs::remove_dir_all(&workdir);
+    result
+}
+
+/// Print the pass/fail matrix: one row per fixture, one column per model/mode
+fn print_matrix(fixtures: &[TaskFixture], columns: &[(String, ExecutionMode)], results: &[EvalResult]) {
+    let name_width = fixtures.iter().map(|f| f.name.len()).max().unwrap_or(7).max(7);
+    let headers: Vec<String> = columns.iter()
+        .map(|(model, mode)| format!("{}/{}", model, mode_name(*mode)))
+        .collect();
+
+    println!("\n{}", "📊 RÉSULTATS".bold().cyan());
+    println!("{}", "─".repeat(60).dimmed());
+
+    let mut header = format!("{:<width$}", "fixture", width = name_width);
+    for h in &headers {
+        header.push_str(&format!(" │ {}", h));
+    }
+    println!("{}", header.bold());
+
+    for fixture in fixtures {
+        let mut row = format!("{:<width$}", fixture.name, width = name_width);
+        for ((model, mode), h) in columns.iter().zip(&headers) {
+            let result = results.iter()
+                .find(|r| r.fixture == fixture.name && &r.model == model && r.mode == mode_name(*mode));
+            let (text, passed, errored) = match result {
+                Some(r) if r.passed => ("✓ pass", true, false),
+                Some(r) if r.error.is_some() => ("! error", false, true),
+                Some(_) => ("✗ fail", false, false),
+                None => ("-", false, false),
+            };
+            let padded = format!("{:<width$}", text, width = h.chars().count());
+            let cell = if passed {
+                padded.green()
+            } else if errored {
+                padded.yellow()
+            } else {
+                padded.red()
+            };
+            row.push_str(&format!(" │ {}", cell));
+        }
+        println!("{}", row);
+    }
+
+    println!("{}", "─".repeat(60).dimmed());
+    for ((model, mode), h) in columns.iter().zip(&headers) {
+        let column: Vec<&EvalResult> = results.iter()
+            .filter(|r| &r.model == model && r.mode == mode_name(*mode))
+            .collect();
+        let passed = column.iter().filter(|r| r.passed).count();
+        println!("  {}: {}/{} réussis", h.bold(), passed, column.len());
+    }
+
+    let failures: Vec<&EvalResult> = results.iter().filter(|r| !r.passed).collect();
+    if !failures.is_empty() {
+        println!("\n{}", "Échecs:".bold());
+        for r in failures {
+            println!("  {} [{}/{}]", r.fixture.bold(), r.model, r.mode);
+            if let Some(e) = &r.error {
+                println!("    {} {}", "erreur:".yellow(), e);
+            }
+            for check in &r.failed_checks {
+                println!("    {} {}", "✗".red(), check);
+            }
+        }
+    }
+}
+
+/// Run every fixture against every model/mode combination
+pub async fn run_eval(config: EvalConfig, settings: ApiSettings) -> Result<Vec<EvalResult>, String> {
+    let fixtures = load_fixtures(&config.fixtures_dir)?;
+    if fixtures.is_empty() {
+        return Err(format!("Aucune fixture trouvée dans {}", config.fixtures_dir.display()));
+    }
+
+    let columns: Vec<(String, ExecutionMode)> = config.models.iter()
+        .flat_map(|model| config.modes.iter().map(move |mode| (model.clone(), *mode)))
+        .collect();
+
+    println!("\n{}", "🧪 COMPANION CHAT - Évaluation".bold().cyan());
+    println!("{}", "─".repeat(40).dimmed());
+    println!("📁 Fixtures: {} ({})", config.fixtures_dir.display(), fixtures.len());
+    println!("🔀 Combinaisons: {}", columns.len());
+
+    let mut results = Vec::new();
+    for fixture in &fixtures {
+        for (model, mode) in &columns {
+            println!("\n{} {} [{}/{}]", "▶".cyan(), fixture.name.bold(), model, mode_name(*mode));
+            let result = run_one(fixture, model, *mode, &config, &settings).await;
+            results.push(result);
+        }
+    }
+
+    print_matrix(&fixtures, &columns, &results);
+
+    if let Some(output) = &config.output {
+        let json = serde_json::to_string_pretty(&results)
+            .map_err(|e| format!("Serialize error: {}", e))?;
+        fs::write(output, json)
+            .map_err(|e| format!("Write error: {}", e))?;
+        println!("\n💾 Résultats écrits dans {}", output.display());
+    }
+
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixtures_are_loaded_copied_and_checked() {
+        let dir = tempfile::tempdir().unwrap();
+        let task = dir.path().join("b-rename");
+        fs::create_dir_all(task.join("project/src")).unwrap();
+        fs::write(task.join("project/src/lib.rs"), "fn old() {}\n").unwrap();
+        fs::write(task.join("task.json"), r#"{
+            "instruction": "Rename old to new",
+            "checks": [
+                { "type": "file_contains", "path": "src/lib.rs", "text": "fn new" },
+                { "type": "file_not_contains", "path": "src/missing.rs", "text": "old" },
+                { "type": "response_contains", "text": "renamed" }
+            ]
+        }"#).unwrap();
+        fs::create_dir_all(dir.path().join("a-empty")).unwrap();
+        fs::write(dir.path().join("a-empty/task.json"), r#"{ "instruction": "Nothing", "checks": [] }"#).unwrap();
+        fs::create_dir_all(dir.path().join("notes")).unwrap();
+
+        let fixtures = load_fixtures(dir.path()).unwrap();
+        let names: Vec<&str> = fixtures.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, ["a-empty", "b-rename"]);
+
+        let fixture = &fixtures[1];
+        let workdir = prepare_workdir(fixture).unwrap();
+        assert_eq!(fs::read_to_string(workdir.join("src/lib.rs")).unwrap(), "fn old() {}\n");
+        fs::write(workdir.join("src/lib.rs"), "fn new() {}\n").unwrap();
+        let report = AgentReport { response: "I renamed it".to_string(), ..Default::default() };
+        let failed: Vec<String> = fixture.checks.iter()
+            .filter(|check| !check.run(&workdir, &report))
+            .map(Check::label)
+            .collect();
+        fs::remove_dir_all(&workdir).unwrap();
+        assert!(failed.is_empty(), "{:?}", failed);
+        assert!(!fixture.checks[2].run(dir.path(), &AgentReport::default()));
+    }
+
+    #[test]
+    fn test_parse_modes() {
+        assert_eq!(parse_modes("plan, AUTO").unwrap(), vec![ExecutionMode::Plan, ExecutionMode::Auto]);
+        assert!(parse_modes("plan,interactive").unwrap_err().contains("interactive"));
+    }
+}
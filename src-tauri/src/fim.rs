@@ -0,0 +1,63 @@
+use std::fs;
+use std::path::PathBuf;
+use crate::settings::ApiSettings;
+
+/// Cursor of the fim subcommand, e.g. `companion-chat fim --file src/main.rs --line 42`
+pub struct FimConfig {
+    pub file: PathBuf,
+    /// 1-based
+    pub line: usize,
+    /// 1-based character of the line; its end when unset
+    pub column: Option<usize>,
+    pub max_tokens: u32,
+}
+
+/// Code before and after the cursor; `line` and `column` are 1-based, the cursor sits before `column`
+pub fn split_at_cursor(content: &str, line: usize, column: Option<usize>) -> Result<(String, String), String> {
+    let lines: Vec<&str> = content.split_inclusive('\n').collect();
+    // After a final line break, editors show one more, empty line
+    let count = lines.len() + usize::from(content.is_empty() || content.ends_with('\n'));
+    if line == 0 || line > count {
+        return Err(format!("Line {} is out of range (1-{})", line, count));
+    }
+    let start: usize = lines[..line - 1].iter().map(|l| l.len()).sum();
+    let text = lines.get(line - 1).copied().unwrap_or("");
+    let text = text.strip_suffix('\n').map(|t| t.strip_suffix('\r').unwrap_or(t)).unwrap_or(text);
+    let length = text.chars().count();
+    let offset = match column {
+        None => text.len(),
+        Some(column) if column == 0 || column > length + 1 => {
+            return Err(format!("Column {} is out of range (1-{})", column, length + 1));
+        }
+        Some(column) => text.char_indices().nth(column - 1).map(|(i, _)| i).unwrap_or(text.len()),
+    };
+    let cursor = start + offset;
+    Ok((content[..cursor].to_string(), content[cursor..].to_string()))
+}
+
+/// Code the model writes at the cursor of the file
+pub async fn complete(config: &FimConfig, settings: &ApiSettings) -> crate::error::Result<String> {
+    let content = fs::read_to_string(&config.file)
+        .map_err(|e| format!("Cannot read {}: {}", config.file.display(), e))?;
+    let (prefix, suffix) = split_at_cursor(&content, config.line, config.column)?;
+    Ok(settings.client().fim(&prefix, &suffix, config.max_tokens).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cursor_splits_the_file() {
+        let content = "fn main() {\r\n    let é = 1;\r\n}\n";
+        let (prefix, suffix) = split_at_cursor(content, 2, None).unwrap();
+        assert_eq!((prefix.as_str(), suffix.as_str()), ("fn main() {\r\n    let é = 1;", "\r\n}\n"));
+        let (prefix, suffix) = split_at_cursor(content, 2, Some(10)).unwrap();
+        assert_eq!((prefix.as_str(), suffix.as_str()), ("fn main() {\r\n    let é", " = 1;\r\n}\n"));
+        assert_eq!(split_at_cursor(content, 1, Some(1)).unwrap().0, "");
+        assert_eq!(split_at_cursor(content, 4, None).unwrap().0, content);
+        assert!(split_at_cursor(content, 5, None).is_err());
+        assert!(split_at_cursor(content, 1, Some(13)).is_err());
+        assert_eq!(split_at_cursor("", 1, None).unwrap(), (String::new(), String::new()));
+    }
+}
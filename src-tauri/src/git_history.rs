@@ -0,0 +1,134 @@
+use std::path::Path;
+use std::process::Command;
+use crate::indexer::IndexedFile;
+use crate::symbols::{self, Symbol, is_identifier};
+
+/// Declarations of the instruction whose history is looked up
+const MAX_TARGETS: usize = 5;
+
+/// Commits shown per declaration, the most recent first
+const MAX_COMMITS: usize = 5;
+
+/// Kinds worth a history: code, not the `impl` or `mod` blocks around it
+const TARGET_KINDS: &[&str] = &["fn", "function", "def", "func", "struct", "enum", "trait", "class", "interface", "type", "const", "static"];
+
+/// Declarations the instruction names, as (relative path, symbol): `Parent::name` when written
+/// so, else the bare name (3 characters or more)
+pub fn targets(instruction: &str, files: &[IndexedFile]) -> Vec<(String, Symbol)> {
+    let words: Vec<&str> = instruction
+        .split(|c: char| !(c.is_alphanumeric() || c == '_' || c == ':'))
+        .map(|word| word.trim_matches(':'))
+        .filter(|word| !word.is_empty())
+        .collect();
+    let named = |symbol: &Symbol| words.iter().any(|word| match word.rsplit_once("::") {
+        Some(_) => *word == symbol.qualified_name(),
+        None => is_identifier(word) && word.len() >= 3 && *word == symbol.name,
+    });
+
+    let mut targets = Vec::new();
+    for file in files {
+        for symbol in symbols::extract(&file.content, &file.extension) {
+            if TARGET_KINDS.contains(&symbol.kind.as_str()) && named(&symbol) {
+                targets.push((file.relative_path.clone(), symbol));
+                if targets.len() == MAX_TARGETS {
+                    return targets;
+                }
+            }
+        }
+    }
+    targets
+}
+
+fn git(root: &Path, args: &[&str]) -> Result<String, String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(root)
+        .output()
+        .map_err(|e| format!("Cannot run git: {}", e))?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Commits of `git blame --porcelain`, in order of first appearance; uncommitted lines are left out
+fn blamed_commits(porcelain: &str) -> Vec<String> {
+    let mut commits: Vec<String> = Vec::new();
+    for line in porcelain.lines() {
+        let Some(hash) = line.split(' ').next() else { continue };
+        let is_header = hash.len() == 40 && hash.chars().all(|c| c.is_ascii_hexdigit());
+        if is_header && hash.chars().any(|c| c != '0') && !commits.iter().any(|c| c == hash) {
+            commits.push(hash.to_string());
+        }
+    }
+    commits
+}
+
+/// Last commits of the declaration's lines, e.g. "- 3f2a1c9 2026-03-04 Rony: Retry on 429"
+fn history(root: &Path, path: &str, symbol: &Symbol) -> Result<Vec<String>, String> {
+    let range = format!("{},{}", symbol.start_line, symbol.end_line);
+    let commits = blamed_commits(&git(root, &["blame", "--porcelain", "-L", &range, "--", path])?);
+    if commits.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut args = vec!["log", "--no-walk=sorted", "--format=- %h %as %an: %s"];
+    args.extend(commits.iter().map(String::as_str));
+    Ok(git(root, &args)?.lines().take(MAX_COMMITS).map(str::to_string).collect())
+}
+
+/// GIT HISTORY section: for each declaration the instruction names, the commits that last
+/// touched its lines. Empty outside a git repository or when nothing is named
+pub fn context(root: &Path, instruction: &str, files: &[IndexedFile]) -> String {
+    let mut sections = Vec::new();
+    for (path, symbol) in targets(instruction, files) {
+        let Ok(commits) = history(root, &path, &symbol) else { continue };
+        if commits.is_empty() {
+            continue;
+        }
+        sections.push(format!(
+            "{} {} ({}, lignes {}-{}):\n{}",
+            symbol.kind, symbol.qualified_name(), path, symbol.start_line, symbol.end_line, commits.join("\n")
+        ));
+    }
+    sections.join("\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_history_of_the_named_function() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let run = |args: &[&str]| git(root, args).unwrap();
+        run(&["init", "-q"]);
+        run(&["config", "user.email", "dev@example.com"]);
+        run(&["config", "user.name", "Dev"]);
+        fs::write(root.join("lib.rs"), "pub fn parse() {\n    todo!()\n}\n\npub fn render() {}\n").unwrap();
+        run(&["add", "."]);
+        run(&["commit", "-q", "-m", "Add the parser"]);
+        fs::write(root.join("lib.rs"), "pub fn parse() {\n    Vec::new()\n}\n\npub fn render() {}\n").unwrap();
+        run(&["commit", "-q", "-am", "Parse empty input"]);
+
+        let content = fs::read_to_string(root.join("lib.rs")).unwrap();
+        let files = vec![IndexedFile {
+            path: root.join("lib.rs"),
+            relative_path: "lib.rs".to_string(),
+            content: content.clone(),
+            extension: "rs".to_string(),
+            size: content.len() as u64,
+        }];
+        let found: Vec<String> = targets("Fix the bug in parse() when the input is empty", &files).iter()
+            .map(|(_, symbol)| symbol.name.clone())
+            .collect();
+        assert_eq!(found, ["parse"]);
+
+        let section = context(root, "Fix parse", &files);
+        assert!(section.starts_with("fn parse (lib.rs, lignes 1-3):\n- "));
+        assert!(section.contains(" Dev: Parse empty input") && section.contains(" Dev: Add the parser"));
+        assert_eq!(section.lines().count(), 3);
+        assert!(context(root, "Rename things", &files).is_empty());
+    }
+}
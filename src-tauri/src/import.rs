@@ -0,0 +1,250 @@
+use std::fs;
+use std::path::Path;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::Value;
+use crate::chat_storage::{ChatSource, ChatStorage, MessageMeta, SavedChat};
+use crate::mistral_client::{Message, Role};
+use crate::context_guard::count_tokens;
+
+/// Export file of another assistant (`conversations.json` of the export archive)
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExternalFormat {
+    /// ChatGPT: each conversation is a tree of messages (`mapping`) ending at `current_node`
+    #[serde(alias = "openai")]
+    ChatGpt,
+    /// Claude: each conversation has a flat `chat_messages` list
+    #[serde(alias = "anthropic")]
+    Claude,
+}
+
+impl ExternalFormat {
+    fn prefix(&self) -> &'static str {
+        match self {
+            ExternalFormat::ChatGpt => "chatgpt",
+            ExternalFormat::Claude => "claude",
+        }
+    }
+}
+
+/// Conversations of an export, already in the storage format
+pub fn parse(content: &str, format: ExternalFormat) -> Result<Vec<SavedChat>, String> {
+    let json: Value = serde_json::from_str(content)
+        .map_err(|e| format!("Invalid export file: {}", e))?;
+    let conversations = json.as_array()
+        .ok_or("Invalid export file: expected a list of conversations")?;
+
+    let chats: Vec<SavedChat> = conversations.iter()
+        .filter_map(|conversation| {
+            let (id, messages) = match format {
+                ExternalFormat::ChatGpt => (str_field(conversation, "id")?, chatgpt_messages(conversation)),
+                ExternalFormat::Claude => (str_field(conversation, "uuid")?, claude_messages(conversation)),
+            };
+            // Nothing but system prompts or attachments: nothing to keep
+            if messages.is_empty() {
+                return None;
+            }
+            Some(to_chat(format, &id, conversation, messages))
+        })
+        .collect();
+
+    if chats.is_empty() && !conversations.is_empty() {
+        return Err(format!("No {} conversation found in the export file", format.prefix()));
+    }
+    Ok(chats)
+}
+
+/// Save the conversations of an export file; conversations imported before are skipped
+pub fn import_file(path: &Path, format: ExternalFormat) -> Result<usize, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+    let storage = ChatStorage::new()?;
+    let mut imported = 0;
+    for chat in parse(&content, format)? {
+        if storage.load(&chat.id).is_ok() {
+            continue;
+        }
+        storage.save(&chat)?;
+        imported += 1;
+    }
+    Ok(imported)
+}
+
+fn str_field(value: &Value, key: &str) -> Option<String> {
+    value.get(key)?.as_str().map(str::to_string)
+}
+
+/// ChatGPT times are seconds since the epoch, as floats
+fn epoch_time(value: Option<&Value>) -> Option<DateTime<Utc>> {
+    let secs = value?.as_f64()?;
+    DateTime::from_timestamp(secs.trunc() as i64, (secs.fract() * 1e9) as u32)
+}
+
+/// Claude times are RFC 3339 strings
+fn rfc3339_time(value: Option<&Value>) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value?.as_str()?).ok().map(|at| at.with_timezone(&Utc))
+}
+
+/// Message of the export, with its time and model when known
+struct Imported {
+    message: Message,
+    at: Option<DateTime<Utc>>,
+    model: Option<String>,
+}
+
+/// Branch shown in ChatGPT: from `current_node` up to the root, then reversed
+fn chatgpt_messages(conversation: &Value) -> Vec<Imported> {
+    let Some(mapping) = conversation.get("mapping").and_then(Value::as_object) else {
+        return Vec::new();
+    };
+    let mut messages = Vec::new();
+    let mut node_id = str_field(conversation, "current_node");
+    // The parent links come from the file: never walk more nodes than there are
+    for _ in 0..mapping.len() {
+        let Some(node) = node_id.as_deref().and_then(|id| mapping.get(id)) else {
+            break;
+        };
+        if let Some(message) = node.get("message") {
+            let role = message.pointer("/author/role").and_then(Value::as_str).unwrap_or("");
+            // Text parts only: images and files are objects
+            let text: Vec<&str> = message.pointer("/content/parts")
+                .and_then(Value::as_array)
+                .map(|parts| parts.iter().filter_map(Value::as_str).collect())
+                .unwrap_or_default();
+            let text = text.join("\n");
+            let role = match role {
+                "user" => Some(Role::User),
+                "assistant" => Some(Role::Assistant),
+                _ => None,
+            };
+            if let Some(role) = role.filter(|_| !text.trim().is_empty()) {
+                messages.push(Imported {
+                    message: Message::new(role, text),
+                    at: epoch_time(message.get("create_time")),
+                    model: message.pointer("/metadata/model_slug").and_then(Value::as_str).map(str::to_string),
+                });
+            }
+        }
+        node_id = str_field(node, "parent");
+    }
+    messages.reverse();
+    messages
+}
+
+fn claude_messages(conversation: &Value) -> Vec<Imported> {
+    let Some(list) = conversation.get("chat_messages").and_then(Value::as_array) else {
+        return Vec::new();
+    };
+    list.iter()
+        .filter_map(|message| {
+            let role = match message.get("sender").and_then(Value::as_str)? {
+                "human" => Role::User,
+                "assistant" => Role::Assistant,
+                _ => return None,
+            };
+            // Older exports only have `text`, newer ones a list of content blocks
+            let mut text = str_field(message, "text").unwrap_or_default();
+            if text.trim().is_empty() {
+                let blocks: Vec<&str> = message.get("content")
+                    .and_then(Value::as_array)
+                    .map(|blocks| blocks.iter()
+                        .filter(|block| block.get("type").and_then(Value::as_str) == Some("text"))
+                        .filter_map(|block| block.get("text").and_then(Value::as_str))
+                        .collect())
+                    .unwrap_or_default();
+                text = blocks.join("\n");
+            }
+            if text.trim().is_empty() {
+                return None;
+            }
+            Some(Imported {
+                message: Message::new(role, text),
+                at: rfc3339_time(message.get("created_at")),
+                model: None,
+            })
+        })
+        .collect()
+}
+
+fn to_chat(format: ExternalFormat, id: &str, conversation: &Value, messages: Vec<Imported>) -> SavedChat {
+    let (created_at, updated_at) = match format {
+        ExternalFormat::ChatGpt => (epoch_time(conversation.get("create_time")), epoch_time(conversation.get("update_time"))),
+        ExternalFormat::Claude => (rfc3339_time(conversation.get("created_at")), rfc3339_time(conversation.get("updated_at"))),
+    };
+    let created_at = created_at.or_else(|| messages.first().and_then(|m| m.at)).unwrap_or_else(Utc::now);
+    let updated_at = updated_at.or_else(|| messages.last().and_then(|m| m.at)).unwrap_or(created_at);
+    let title_key = if format == ExternalFormat::Claude { "name" } else { "title" };
+
+    // Same id on every import of the file, so importing it again adds only the new conversations
+    let source_id: String = id.chars().filter(|c| c.is_ascii_alphanumeric() || *c == '-').collect();
+    let mut chat = SavedChat {
+        id: format!("{}-{}", format.prefix(), source_id),
+        title: String::new(),
+        project_path: String::new(),
+        messages: Vec::new(),
+        metadata: Vec::new(),
+        created_at,
+        updated_at,
+        source: ChatSource::Gui,
+        system_prompt: None,
+        model: None,
+        deleted_at: None,
+    };
+    for imported in messages {
+        let mut meta = MessageMeta::now(count_tokens(&imported.message.content));
+        meta.timestamp = imported.at.unwrap_or(created_at);
+        meta.model = imported.model;
+        chat.messages.push(imported.message);
+        chat.metadata.push(Some(meta));
+    }
+    match str_field(conversation, title_key).filter(|title| !title.trim().is_empty()) {
+        Some(title) => chat.title = title,
+        None => chat.auto_title(),
+    }
+    chat
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_chatgpt_and_claude_exports() {
+        let chatgpt = r#"[{
+            "id": "abc-1", "title": "Regex help", "create_time": 1700000000.5, "update_time": 1700000100.0,
+            "current_node": "n3",
+            "mapping": {
+                "root": { "message": null, "parent": null },
+                "n1": { "parent": "root", "message": { "author": { "role": "system" }, "content": { "parts": ["You are ChatGPT"] } } },
+                "n2": { "parent": "n1", "message": { "author": { "role": "user" }, "content": { "parts": ["Match digits?", { "image": 1 }] }, "create_time": 1700000001.0 } },
+                "old": { "parent": "n2", "message": { "author": { "role": "assistant" }, "content": { "parts": ["Abandoned branch"] } } },
+                "n3": { "parent": "n2", "message": { "author": { "role": "assistant" }, "content": { "parts": ["Use \\d+"] }, "metadata": { "model_slug": "gpt-4o" } } }
+            }
+        }]"#;
+        let chats = parse(chatgpt, ExternalFormat::ChatGpt).unwrap();
+        assert_eq!(chats.len(), 1);
+        assert_eq!(chats[0].id, "chatgpt-abc-1");
+        assert_eq!(chats[0].title, "Regex help");
+        let contents: Vec<(&str, &str)> = chats[0].messages.iter().map(|m| (m.role.as_str(), m.content.as_str())).collect();
+        assert_eq!(contents, vec![("user", "Match digits?"), ("assistant", "Use \\d+")]);
+        assert_eq!(chats[0].metadata[1].as_ref().unwrap().model.as_deref(), Some("gpt-4o"));
+        assert_eq!(chats[0].created_at.timestamp(), 1700000000);
+
+        let claude = r#"[
+            { "uuid": "c-2", "name": "", "created_at": "2024-05-01T10:00:00Z", "updated_at": "2024-05-01T10:05:00Z",
+              "chat_messages": [
+                { "sender": "human", "text": "Explain lifetimes. Briefly", "created_at": "2024-05-01T10:00:00Z" },
+                { "sender": "assistant", "text": "", "content": [{ "type": "text", "text": "They bound borrows." }] }
+              ] },
+            { "uuid": "empty", "name": "Nothing", "chat_messages": [] }
+        ]"#;
+        let chats = parse(claude, ExternalFormat::Claude).unwrap();
+        assert_eq!(chats.len(), 1);
+        assert_eq!(chats[0].id, "claude-c-2");
+        assert_eq!(chats[0].title, "Explain lifetimes...");
+        assert_eq!(chats[0].messages[1].content, "They bound borrows.");
+
+        assert!(parse(claude, ExternalFormat::ChatGpt).is_err());
+    }
+}
@@ -0,0 +1,76 @@
+/// Languages recognised in user messages, for `"language": "auto"`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Language {
+    French,
+    English,
+    Spanish,
+    German,
+    Italian,
+}
+
+impl Language {
+    /// Name used in the (French) system prompt
+    pub fn name(&self) -> &'static str {
+        match self {
+            Language::French => "français",
+            Language::English => "anglais",
+            Language::Spanish => "espagnol",
+            Language::German => "allemand",
+            Language::Italian => "italien",
+        }
+    }
+}
+
+/// Frequent short words of each language (a word shared by two languages counts for both)
+const STOPWORDS: &[(Language, &[&str])] = &[
+    (Language::French, &["le", "les", "des", "est", "une", "pour", "dans", "avec", "pas", "que", "qui", "sur", "ce", "cette", "je", "tu", "vous", "nous", "mais", "ou", "au", "aux", "du", "et", "fais", "peux", "fichier", "ajoute", "corrige", "pourquoi", "comment"]),
+    (Language::English, &["the", "is", "are", "and", "to", "of", "for", "with", "this", "that", "what", "how", "why", "can", "you", "it", "in", "on", "be", "not", "add", "fix", "file", "please", "should", "does", "do", "my"]),
+    (Language::Spanish, &["el", "los", "las", "es", "y", "para", "con", "por", "una", "que", "qué", "cómo", "porque", "puedes", "archivo", "esto", "pero", "del", "al"]),
+    (Language::German, &["der", "die", "das", "und", "ist", "nicht", "mit", "für", "ein", "eine", "ich", "du", "sie", "wie", "warum", "bitte", "datei", "auf", "zu", "den", "dem"]),
+    (Language::Italian, &["il", "gli", "è", "e", "per", "con", "non", "che", "una", "perché", "come", "puoi", "questo", "della", "nel", "del"]),
+];
+
+/// Hits needed before trusting the detection (short or code-only messages stay undetected)
+const MIN_HITS: usize = 2;
+
+/// Language of a user message, from its stopwords; None when unsure
+pub fn detect(text: &str) -> Option<Language> {
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_lowercase())
+        .collect();
+
+    let mut scores: Vec<(Language, usize)> = STOPWORDS.iter()
+        .map(|(language, stopwords)| {
+            let hits = words.iter().filter(|w| stopwords.contains(&w.as_str())).count();
+            (*language, hits)
+        })
+        .collect();
+    scores.sort_by(|a, b| b.1.cmp(&a.1));
+
+    let (best, hits) = scores[0];
+    (hits >= MIN_HITS && hits > scores[1].1).then_some(best)
+}
+
+/// System prompt rule asking to answer in the language of the message, if it was detected
+pub fn reply_rule(user_message: &str) -> Option<String> {
+    detect(user_message).map(|language| format!(
+        "LANGUE: le dernier message de l'utilisateur est en {0} : réponds en {0} (remplace la règle sur le français).",
+        language.name()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_message_language() {
+        assert_eq!(detect("Can you fix the bug in this file and add a test?"), Some(Language::English));
+        assert_eq!(detect("Corrige le bug dans le fichier main.rs et ajoute un test"), Some(Language::French));
+        assert_eq!(detect("Warum ist die Datei nicht kompiliert?"), Some(Language::German));
+        assert_eq!(detect("cargo build"), None);
+        assert!(reply_rule("What does this function do?").unwrap().contains("en anglais"));
+    }
+}
@@ -1,18 +1,43 @@
+#[cfg(feature = "gui")]
 mod commands;
-mod mistral_client;
+#[cfg(feature = "gui")]
 mod tray;
+#[cfg(feature = "cli")]
 pub mod cli;
-pub mod indexer;
-pub mod differ;
+#[cfg(feature = "cli")]
 pub mod agent;
+#[cfg(feature = "cli")]
 pub mod chat;
-pub mod chat_storage;
+#[cfg(feature = "tui")]
 pub mod tui;
-pub mod persistent_index;
+#[cfg(feature = "cli")]
+pub mod eval;
+pub mod project_type;
+pub mod language;
+pub mod prompt;
+#[cfg(feature = "cli")]
+pub mod notify;
+pub mod import;
+pub mod scheduler;
+pub mod templates;
+pub mod pinned;
+pub mod memory_update;
+pub mod fim;
+pub mod semantic_search;
+pub mod git_history;
 
+// The core crate, under the paths the frontends have always used
+use companion_core::mistral_client;
+pub use companion_core::{
+    audit, auto_commit, backend, chat_storage, context_guard, custom_tools, differ, docs_lookup, error,
+    generated, health, indexer, journal, mode, persistent_index, project_dir, rate_limiter, recent_projects, rename,
+    settings, symbols, tools, workspace, wsl,
+};
+
+#[cfg(feature = "gui")]
 use tauri::{Manager, Listener};
-use tauri_plugin_store::StoreExt;
 
+#[cfg(feature = "gui")]
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
@@ -23,14 +48,17 @@ pub fn run() {
             // Initialize tray
             tray::create_tray(app)?;
             
+            // Conversations now live in the storage shared with the TUI/CLI
+            if let Err(e) = commands::migrate_legacy_store(app.handle()) {
+                eprintln!("Conversation migration failed: {}", e);
+            }
+            
+            // Nightly reindex of the configured projects, while the app sits in the tray
+            scheduler::start();
+            
             // Handle requests from tray to clear history
-            let app_handle = app.handle().clone();
             app.listen("request-clear-history", move |_| {
-                let store = app_handle.store("conversations.json");
-                 if let Ok(store) = store {
-                     let _ = store.clear();
-                     let _ = store.save();
-                 }
+                let _ = commands::clear_gui_history();
             });
             
             Ok(())
@@ -44,15 +72,40 @@ pub fn run() {
         })
         .invoke_handler(tauri::generate_handler![
             commands::send_message,
+            commands::run_and_attach,
             commands::create_conversation,
             commands::get_conversations,
+            commands::get_projects,
             commands::delete_conversation,
+            commands::get_trash,
+            commands::restore_conversation,
+            commands::empty_trash,
             commands::rename_conversation,
             commands::clear_history,
+            commands::import_external,
             commands::get_app_settings,
             commands::update_settings,
             commands::test_api_connection,
+            commands::provider_health,
+            commands::check_connectivity,
+            commands::check_api_key,
+            commands::validate_key_live,
+            commands::set_language,
+            commands::choose_default_model,
+            commands::get_conversation_templates,
+            commands::save_conversation_templates,
+            commands::index_project,
+            commands::get_index_stats,
+            commands::parse_changes,
+            commands::apply_changes,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+/// Built without the `gui` feature: only the TUI and the CLI are available
+#[cfg(not(feature = "gui"))]
+pub fn run() {
+    eprintln!("Interface graphique absente de ce build (feature « gui »): utilisez companion-chat chat ou --help");
+    std::process::exit(1);
+}
@@ -1,20 +1,66 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use companion_chat_lib::cli::{parse_args, is_cli_mode, is_chat_mode, AgentConfig, ChatConfig};
+#[cfg(feature = "cli")]
+use companion_chat_lib::cli::{parse_args, is_cli_mode, is_chat_mode, replay_file, recent_limit, eval_config, fim_config, rollback_dir, index_stats_config, AgentConfig, ExecutionMode};
+#[cfg(feature = "cli")]
+use companion_chat_lib::chat_storage::{time_ago, ChatStorage};
+#[cfg(feature = "cli")]
+use companion_chat_lib::recent_projects::{self, RecentProjects};
+#[cfg(feature = "cli")]
+use companion_chat_lib::notify::{self, NotifySettings};
+#[cfg(feature = "cli")]
+use companion_chat_lib::journal::ChangeJournal;
+#[cfg(feature = "cli")]
 use companion_chat_lib::agent::{Agent, load_api_settings};
+#[cfg(feature = "tui")]
 use companion_chat_lib::tui::runner::run_tui;
+#[cfg(feature = "tui")]
+use companion_chat_lib::tui::replay::run_replay;
+#[cfg(feature = "cli")]
 use colored::*;
 
+#[cfg(feature = "cli")]
 fn main() {
     let cli = parse_args();
+    companion_chat_lib::differ::set_assume_yes(cli.yes);
     
     // Check if invoked as companion-chat-cli (launched via symlink)
     let is_cli_binary = std::env::args().next()
         .map(|arg0| arg0.contains("companion-chat-cli"))
         .unwrap_or(false);
     
-    if is_chat_mode(&cli) {
+    if let Some(config) = eval_config(&cli) {
+        // Evaluation harness
+        run_eval(&cli, config);
+    } else if let Some(file) = replay_file(&cli) {
+        // Replay a recorded TUI session
+        if let Err(e) = run_replay(file) {
+            eprintln!("\n{} {}", "Erreur:".red().bold(), e);
+            std::process::exit(1);
+        }
+    } else if let Some(limit) = recent_limit(&cli) {
+        // Recently opened projects
+        if let Err(e) = print_recent_projects(limit) {
+            eprintln!("\n{} {}", "Erreur:".red().bold(), e);
+            std::process::exit(1);
+        }
+    } else if let Some(project) = rollback_dir(&cli) {
+        // Undo the last applied change set
+        if let Err(e) = rollback(&project) {
+            eprintln!("\n{} {}", "Erreur:".red().bold(), e);
+            std::process::exit(1);
+        }
+    } else if let Some((project, list_skipped, max_files)) = index_stats_config(&cli) {
+        // What the index holds and what it leaves out
+        if let Err(e) = print_index_stats(&project, list_skipped, max_files) {
+            eprintln!("\n{} {}", "Erreur:".red().bold(), e);
+            std::process::exit(1);
+        }
+    } else if let Some(config) = fim_config(&cli) {
+        // Fill-in-the-middle completion, printed for an editor
+        run_fim(&cli, config);
+    } else if is_chat_mode(&cli) {
         // Interactive TUI Mode
         run_chat_mode(&cli);
     } else if is_cli_mode(&cli) {
@@ -29,8 +75,15 @@ fn main() {
     }
 }
 
+/// Built without the `cli` feature: the GUI only
+#[cfg(not(feature = "cli"))]
+fn main() {
+    companion_chat_lib::run()
+}
+
+#[cfg(feature = "tui")]
 fn run_chat_mode(cli: &companion_chat_lib::cli::Cli) {
-    let config = match ChatConfig::from_cli(cli) {
+    let config = match companion_chat_lib::cli::ChatConfig::from_cli(cli) {
         Some(c) => c,
         None => {
             eprintln!("{}", "Erreur: Configuration invalide".red());
@@ -40,12 +93,160 @@ fn run_chat_mode(cli: &companion_chat_lib::cli::Cli) {
 
     let runtime = tokio::runtime::Runtime::new().expect("Failed to create Tokio runtime");
     
-    if let Err(e) = runtime.block_on(run_tui(config.cwd)) {
+    if let Err(e) = runtime.block_on(run_tui(config)) {
         eprintln!("\n{} {}", "Erreur:".red().bold(), e);
         std::process::exit(1);
     }
 }
 
+/// Built without the `tui` feature: chat and replay need the TUI
+#[cfg(all(feature = "cli", not(feature = "tui")))]
+fn run_chat_mode(_cli: &companion_chat_lib::cli::Cli) {
+    eprintln!("{} TUI absente de ce build (feature « tui »)", "Erreur:".red().bold());
+    std::process::exit(1);
+}
+
+#[cfg(all(feature = "cli", not(feature = "tui")))]
+fn run_replay(_file: &std::path::Path) -> Result<(), String> {
+    Err("TUI absente de ce build (feature « tui »)".to_string())
+}
+
+#[cfg(feature = "cli")]
+fn run_eval(cli: &companion_chat_lib::cli::Cli, config: Result<companion_chat_lib::eval::EvalConfig, String>) {
+    let config = match config {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("{} {}", "Erreur:".red().bold(), e);
+            std::process::exit(1);
+        }
+    };
+
+    // Fixtures run in temporary copies: only the global settings apply
+    let settings = match load_api_settings(None) {
+        Ok(mut settings) => {
+            settings.apply_overrides(cli.base_url.as_deref(), cli.model.as_deref());
+            settings
+        }
+        Err(e) => exit_with_error(e),
+    };
+
+    let runtime = tokio::runtime::Runtime::new().expect("Failed to create Tokio runtime");
+
+    match runtime.block_on(companion_chat_lib::eval::run_eval(config, settings)) {
+        Ok(results) => {
+            if results.iter().any(|r| !r.passed) {
+                std::process::exit(1);
+            }
+        }
+        Err(e) => {
+            eprintln!("\n{} {}", "Erreur:".red().bold(), e);
+            std::process::exit(1);
+        }
+    }
+}
+
+#[cfg(feature = "cli")]
+fn run_fim(cli: &companion_chat_lib::cli::Cli, config: companion_chat_lib::fim::FimConfig) {
+    // Settings of the project the file belongs to
+    let project = cli.cwd.clone()
+        .or_else(|| std::env::current_dir().ok())
+        .unwrap_or_default();
+    let settings = match load_api_settings(Some(&project)) {
+        Ok(mut settings) => {
+            settings.apply_overrides(cli.base_url.as_deref(), cli.model.as_deref());
+            settings
+        }
+        Err(e) => exit_with_error(e),
+    };
+
+    let runtime = tokio::runtime::Runtime::new().expect("Failed to create Tokio runtime");
+
+    match runtime.block_on(companion_chat_lib::fim::complete(&config, &settings)) {
+        // Only the completion on stdout, for the editor to insert
+        Ok(completion) => print!("{}", completion),
+        Err(e) => exit_with_error(e),
+    }
+}
+
+/// Print the error and what the user can do about it, then exit with 1
+#[cfg(feature = "cli")]
+fn exit_with_error(e: companion_chat_lib::error::Error) -> ! {
+    eprintln!("{} {}", "Erreur:".red().bold(), e);
+    if let Some(hint) = e.hint() {
+        eprintln!("{}", hint.yellow());
+    }
+    std::process::exit(1);
+}
+
+#[cfg(feature = "cli")]
+fn rollback(project: &std::path::Path) -> Result<(), String> {
+    let Some(mut journal) = ChangeJournal::last(project) else {
+        println!("{}", "Aucune modification à annuler.".yellow());
+        return Ok(());
+    };
+    journal.rollback(project)?;
+    println!("{} {}", "↩️  Annulé:".green(), journal.summary());
+    Ok(())
+}
+
+#[cfg(feature = "cli")]
+fn print_index_stats(project: &std::path::Path, list_skipped: bool, max_files: usize) -> Result<(), String> {
+    use companion_chat_lib::indexer::{CodebaseIndex, SkipReason};
+    use companion_chat_lib::persistent_index::reindex_project;
+
+    let context = CodebaseIndex::index_with_progress(project, None, &[], max_files, |_, _| {})?;
+    let (pindex, _) = reindex_project(project).map_err(|e| e.to_string())?;
+    let (count, size) = pindex.stats().map_err(|e| e.to_string())?;
+
+    // Prompt context first, then what only the SQLite index skipped
+    let mut skipped: Vec<(String, SkipReason)> = context.skipped.clone();
+    for (path, reason) in pindex.skipped_files().map_err(|e| e.to_string())? {
+        if !skipped.iter().any(|(p, _)| *p == path) {
+            skipped.push((path, reason));
+        }
+    }
+    skipped.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let generated = context.generated_files().count();
+    println!("{} {}", "📊 Index de".bold().cyan(), project.display().to_string().bold());
+    println!("   Contexte du prompt : {} fichiers (~{} tokens), {} générés sans contenu", context.files.len(), context.total_tokens_estimate, generated);
+    println!("   Index SQLite : {} fichiers, {} Ko", count, size / 1000);
+    println!("   Fichiers ignorés : {}", skipped.len());
+    if !list_skipped {
+        if !skipped.is_empty() {
+            println!("{}", "   --skipped pour la liste et les raisons".dimmed());
+        }
+        return Ok(());
+    }
+    for (path, reason) in &skipped {
+        println!("   {}  {}", path, reason.describe().dimmed());
+    }
+    Ok(())
+}
+
+#[cfg(feature = "cli")]
+fn print_recent_projects(limit: usize) -> Result<(), String> {
+    let projects = RecentProjects::open()?.list(limit)?;
+    if projects.is_empty() {
+        println!("{}", "Aucun projet récent.".yellow());
+        return Ok(());
+    }
+    let storage = ChatStorage::new().ok();
+    println!("{}", "📂 Projets récents".bold().cyan());
+    for (i, project) in projects.iter().enumerate() {
+        let missing = if project.path.is_dir() { String::new() } else { " (introuvable)".red().to_string() };
+        println!("{:>3}. {}{}  {}", i + 1, project.path.display().to_string().bold(), missing, time_ago(project.last_used).dimmed());
+        let title = project.last_conversation.as_ref()
+            .and_then(|id| storage.as_ref()?.load(id).ok())
+            .map(|chat| chat.title);
+        if let Some(title) = title {
+            println!("     💬 {}", title.dimmed());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(feature = "cli")]
 fn run_cli_agent(cli: &companion_chat_lib::cli::Cli) {
     let config = match AgentConfig::from_cli(cli) {
         Some(c) => c,
@@ -56,22 +257,55 @@ fn run_cli_agent(cli: &companion_chat_lib::cli::Cli) {
     };
 
     // Load API settings
-    let (api_key, provider) = match load_api_settings() {
-        Ok((key, prov)) => (key, prov),
-        Err(e) => {
-            eprintln!("{} {}", "Erreur:".red().bold(), e);
-            eprintln!("{}", "Conseil: Lancez 'companion-chat' sans arguments pour ouvrir le GUI et configurer votre clé API.".yellow());
-            std::process::exit(1);
+    let settings = match load_api_settings(Some(&config.cwd)) {
+        Ok(mut settings) => {
+            settings.apply_overrides(cli.base_url.as_deref(), cli.model.as_deref());
+            settings
         }
+        Err(e) => exit_with_error(e),
     };
 
+    let format = config.format;
+    let instruction = config.instruction.clone();
+
+    recent_projects::remember(&config.cwd.canonicalize().unwrap_or_else(|_| config.cwd.clone()));
+
+    // Long auto runs end with a desktop notification
+    let notify_settings = (config.mode == ExecutionMode::Auto && !config.dry_run).then(NotifySettings::load);
+    let project_root = config.cwd.clone();
+    let started = std::time::Instant::now();
+
     // Create and run the agent
-    let agent = Agent::new(config, api_key, provider);
+    let agent = Agent::new(config, &settings);
     
     let runtime = tokio::runtime::Runtime::new().expect("Failed to create Tokio runtime");
     
-    if let Err(e) = runtime.block_on(agent.run()) {
-        eprintln!("\n{} {}", "Erreur:".red().bold(), e);
-        std::process::exit(1);
+    let result = runtime.block_on(agent.run());
+    if let Some(notify_settings) = notify_settings.filter(|n| n.should_notify(started.elapsed())) {
+        let changed = result.as_ref().is_ok_and(|report| !report.applied.is_empty());
+        let tests = if notify_settings.run_tests && changed {
+            println!("{}", "🧪 Lancement des tests...".bold());
+            notify::run_project_tests(&project_root)
+        } else {
+            None
+        };
+        let summary = notify::agent_summary(&result, started.elapsed(), tests);
+        println!("{}", summary);
+        if let Err(e) = notify::send(&summary) {
+            eprintln!("{} {}", "⚠️  Notification non envoyée:".yellow(), e);
+        }
+    }
+    // Exit codes documented in the README, for CI
+    match result {
+        Ok(report) => {
+            if let Some(output) = report.render(&instruction, format) {
+                println!("{}", output);
+            }
+            std::process::exit(report.exit_code())
+        }
+        Err(e) => {
+            eprintln!("\n{} {}", "Erreur:".red().bold(), e);
+            std::process::exit(e.exit_code());
+        }
     }
 }
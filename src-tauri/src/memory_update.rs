@@ -0,0 +1,100 @@
+use std::path::Path;
+use crate::differ::FileChange;
+use crate::mistral_client::{Message, Role};
+
+/// Exchanges a session needs before facts are proposed for memory.md on its own
+pub const MIN_EXCHANGES: usize = 3;
+
+/// Heading the distilled facts are appended under
+const HEADING: &str = "## Appris des conversations";
+
+pub const DISTILL_PROMPT: &str = "Tu maintiens le fichier memory.md d'un projet, lu avec chaque prompt. \
+À partir de la conversation, liste les faits durables sur le projet qui n'y figurent pas encore : \
+dépendances et versions, commandes de build et de test, conventions, décisions d'architecture. \
+Ignore ce qui ne concerne que la tâche en cours. Une ligne par fait, commençant par \"- \", courte et factuelle \
+(ex: \"- Les tests se lancent avec make test\"). Réponds AUCUN s'il n'y a rien à ajouter.";
+
+/// Request asking for the durable facts of `history` ("User: ..." / "AI: ..." lines) missing from `memory`
+pub fn request(memory: &str, history: &str) -> Vec<Message> {
+    vec![
+        Message { role: Role::System, content: DISTILL_PROMPT.to_string() },
+        Message {
+            role: Role::User,
+            content: format!("memory.md actuel:\n{}\n\nConversation:\n{}", memory.trim(), history),
+        },
+    ]
+}
+
+/// Facts of the answer: its "- " lines not already in memory.md
+pub fn parse_facts(response: &str, memory: &str) -> Vec<String> {
+    let known: Vec<String> = memory.lines().map(normalize).collect();
+    let mut facts: Vec<String> = Vec::new();
+    for line in response.lines() {
+        let Some(fact) = line.trim().strip_prefix("- ").or_else(|| line.trim().strip_prefix("* ")) else {
+            continue;
+        };
+        let fact = fact.trim();
+        if fact.is_empty() || known.contains(&normalize(fact)) || facts.iter().any(|f| normalize(f) == normalize(fact)) {
+            continue;
+        }
+        facts.push(fact.to_string());
+    }
+    facts
+}
+
+/// A memory.md line compared without its bullet, case and final period
+fn normalize(line: &str) -> String {
+    line.trim().trim_start_matches(['-', '*']).trim().trim_end_matches('.').to_lowercase()
+}
+
+/// Change of memory.md appending the facts under their heading, to review as a diff
+pub fn proposal(memory_file: &Path, memory: &str, facts: &[String]) -> FileChange {
+    let mut modified = memory.trim_end().to_string();
+    let lines: String = facts.iter().map(|fact| format!("\n- {}", fact)).collect();
+    match modified.find(HEADING) {
+        Some(start) => {
+            // End of the section: the next heading, or the end of the file
+            let after = start + HEADING.len();
+            let end = modified[after..].find("\n#").map(|i| after + i).unwrap_or(modified.len());
+            let end = modified[..end].trim_end().len();
+            modified.insert_str(end, &lines);
+        }
+        None => {
+            if !modified.is_empty() {
+                modified.push_str("\n\n");
+            }
+            modified.push_str(HEADING);
+            modified.push_str(&lines);
+        }
+    }
+    modified.push('\n');
+    FileChange {
+        path: memory_file.to_string_lossy().to_string(),
+        original: memory.to_string(),
+        modified,
+        description: format!("{} fait(s) tiré(s) de la conversation", facts.len()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_facts_are_appended_under_their_heading() {
+        let memory = "# Projet\n- Les tests se lancent avec make test\n";
+        let response = "Voici:\n- Les tests se lancent avec make test.\n- Le projet utilise sqlx 0.8\n* Le projet utilise sqlx 0.8\n- ";
+        let facts = parse_facts(response, memory);
+        assert_eq!(facts, ["Le projet utilise sqlx 0.8"]);
+        assert!(parse_facts("AUCUN", memory).is_empty());
+
+        let change = proposal(Path::new("memory.md"), memory, &facts);
+        assert_eq!(change.modified, "# Projet\n- Les tests se lancent avec make test\n\n## Appris des conversations\n- Le projet utilise sqlx 0.8\n");
+        assert!(change.unified_diff().contains("+- Le projet utilise sqlx 0.8"));
+
+        let edited = format!("{}\n## Style\n- 4 espaces\n", change.modified);
+        let again = proposal(Path::new("memory.md"), &edited, &["Node 20".to_string()]);
+        assert!(again.modified.ends_with("sqlx 0.8\n- Node 20\n\n## Style\n- 4 espaces\n"));
+        assert_eq!(again.modified.matches(HEADING).count(), 1);
+    }
+}
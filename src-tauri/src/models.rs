@@ -1,21 +1,15 @@
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
+use crate::mistral_client::Role;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Message {
     pub id: String,
-    pub role: MessageRole,
+    pub role: Role,
     pub content: String,
     pub timestamp: u64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum MessageRole {
-    User,
-    Assistant,
-    System,
-}
-
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Conversation {
     pub id: String,
@@ -56,7 +50,7 @@ pub struct MistralRequest {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct MistralMessage {
-    pub role: String,
+    pub role: Role,
     pub content: String,
 }
 
@@ -79,7 +73,7 @@ pub struct MistralChoice {
 
 #[derive(Debug, Deserialize)]
 pub struct MistralResponseMessage {
-    pub role: String,
+    pub role: Role,
     pub content: String,
 }
 
@@ -107,7 +101,7 @@ impl Conversation {
         }
     }
 
-    pub fn add_message(&mut self, role: MessageRole, content: String) {
+    pub fn add_message(&mut self, role: Role, content: String) {
         let message = Message {
             id: Uuid::new_v4().to_string(),
             role,
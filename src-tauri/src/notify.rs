@@ -0,0 +1,134 @@
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::time::Duration;
+use serde::Deserialize;
+use crate::agent::{AgentError, AgentReport};
+use crate::settings::settings_file;
+use crate::project_type;
+use crate::wsl;
+
+/// Title of the desktop notifications
+const TITLE: &str = "Codestral Companion";
+
+/// `notifications` section of settings.json, e.g.
+/// `"notifications": { "enabled": true, "min_seconds": 60, "run_tests": true }`
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct NotifySettings {
+    pub enabled: bool,
+    /// Shorter runs are not notified: the terminal is probably still in front of the user
+    pub min_seconds: u64,
+    /// Run the detected test command after the changes are applied, to report it.
+    /// Off by default: it executes the project's own code
+    pub run_tests: bool,
+}
+
+impl Default for NotifySettings {
+    fn default() -> Self {
+        Self { enabled: true, min_seconds: 30, run_tests: false }
+    }
+}
+
+impl NotifySettings {
+    pub fn load() -> Self {
+        let Ok(path) = settings_file() else {
+            return Self::default();
+        };
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+            .and_then(|json| json.get("config")?.get("notifications").cloned())
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default()
+    }
+
+    /// Whether a run of this duration is notified
+    pub fn should_notify(&self, elapsed: Duration) -> bool {
+        self.enabled && elapsed.as_secs() >= self.min_seconds
+    }
+}
+
+/// Run the test command of the project (Cargo.toml, package.json...); None when there is none
+pub fn run_project_tests(project_root: &Path) -> Option<bool> {
+    let command = project_type::detect(project_root).into_iter().find_map(|project| project.test)?;
+    wsl::shell_command(&command, project_root)
+        .output()
+        .map(|out| out.status.success())
+        .ok()
+}
+
+/// Notification text of an agent run: outcome, duration, files changed, tests
+pub fn agent_summary(result: &Result<AgentReport, AgentError>, elapsed: Duration, tests: Option<bool>) -> String {
+    let secs = elapsed.as_secs();
+    let duration = if secs >= 60 { format!("{} min {:02} s", secs / 60, secs % 60) } else { format!("{} s", secs) };
+    let report = match result {
+        Ok(report) => report,
+        Err(e) => return format!("❌ Échec après {}: {}", duration, e),
+    };
+
+    let mut parts = vec![match report.applied.len() {
+        0 => "aucun fichier modifié".to_string(),
+        1 => "1 fichier modifié".to_string(),
+        n => format!("{} fichiers modifiés", n),
+    }];
+    if !report.applied.is_empty() && report.stats.added + report.stats.removed > 0 {
+        parts[0].push_str(&format!(" (+{}/−{} lignes)", report.stats.added, report.stats.removed));
+    }
+    if !report.deleted.is_empty() {
+        parts.push(format!("{} supprimé(s)", report.deleted.len()));
+    }
+    if !report.renamed.is_empty() {
+        parts.push(format!("{} déplacé(s)", report.renamed.len()));
+    }
+    match tests {
+        Some(true) => parts.push("tests OK".to_string()),
+        Some(false) => parts.push("tests en échec".to_string()),
+        None => {}
+    }
+    let icon = if tests == Some(false) { "⚠️" } else { "✅" };
+    format!("{} Terminé en {}: {}", icon, duration, parts.join(", "))
+}
+
+/// Show a desktop notification (notify-send on Linux, osascript on macOS)
+pub fn send(body: &str) -> Result<(), String> {
+    let status = if cfg!(target_os = "macos") {
+        let quote = |text: &str| text.replace('\\', "\\\\").replace('"', "\\\"");
+        Command::new("osascript")
+            .args(["-e", &format!("display notification \"{}\" with title \"{}\"", quote(body), TITLE)])
+            .status()
+    } else if cfg!(target_os = "linux") {
+        Command::new("notify-send").args(["--app-name", TITLE, TITLE, body]).status()
+    } else {
+        return Err("Desktop notifications are not supported on this platform".to_string());
+    };
+    match status {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(format!("Notification command failed ({})", status)),
+        Err(e) => Err(format!("Cannot send notification: {}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_agent_summary() {
+        let report = AgentReport {
+            applied: vec!["src/a.rs".to_string(), "src/b.rs".to_string()],
+            deleted: vec!["old.rs".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            agent_summary(&Ok(report), Duration::from_secs(125), Some(false)),
+            "⚠️ Terminé en 2 min 05 s: 2 fichiers modifiés, 1 supprimé(s), tests en échec"
+        );
+        assert_eq!(
+            agent_summary(&Err(AgentError::Api("quota".to_string())), Duration::from_secs(42), None),
+            "❌ Échec après 42 s: quota"
+        );
+        assert!(!NotifySettings::default().should_notify(Duration::from_secs(5)));
+        assert!(!NotifySettings::default().run_tests);
+    }
+}
@@ -0,0 +1,185 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use ignore::WalkBuilder;
+use crate::context_guard::count_tokens;
+use crate::project_dir;
+
+/// Tokens of pinned file contents per prompt; the files past it are only named
+pub const MAX_PINNED_TOKENS: usize = 8_000;
+
+/// Shared project config: `pinned = ["docs/architecture.md", "src/api"]`
+fn config_path(project_root: &Path) -> PathBuf {
+    project_root.join(project_dir::DIR_NAME).join("config.toml")
+}
+
+fn read_config(project_root: &Path) -> toml::Table {
+    fs::read_to_string(config_path(project_root))
+        .ok()
+        .and_then(|content| content.parse::<toml::Table>().ok())
+        .unwrap_or_default()
+}
+
+/// Files and directories pinned in `.codestral/config.toml`, relative to the project.
+/// The file is shared with the repository: pins outside the project or gone are dropped
+pub fn load(project_root: &Path) -> Vec<String> {
+    read_config(project_root)
+        .get("pinned")
+        .and_then(|pinned| pinned.as_array())
+        .map(|paths| paths.iter()
+            .filter_map(|path| path.as_str())
+            .filter_map(|path| relative(project_root, path).ok())
+            .collect())
+        .unwrap_or_default()
+}
+
+/// Write the pins, keeping the other keys of config.toml
+fn save(project_root: &Path, pins: &[String]) -> Result<(), String> {
+    project_dir::ensure(project_root)?;
+    let mut config = read_config(project_root);
+    if pins.is_empty() {
+        config.remove("pinned");
+    } else {
+        config.insert("pinned".to_string(), toml::Value::Array(pins.iter().cloned().map(toml::Value::String).collect()));
+    }
+    let content = toml::to_string(&config).map_err(|e| format!("Serialize error: {}", e))?;
+    fs::write(config_path(project_root), content)
+        .map_err(|e| format!("Cannot write {}: {}", config_path(project_root).display(), e))
+}
+
+/// Path of the project as written in config.toml ("docs/architecture.md"), from a relative or absolute path
+fn relative(project_root: &Path, path: &str) -> Result<String, String> {
+    let path = path.trim().trim_end_matches(['/', '\\']);
+    let full = project_root.join(path);
+    let canonical = crate::wsl::canonicalize(&full)
+        .map_err(|_| format!("No such file or directory: {}", path))?;
+    let root = crate::wsl::canonicalize(project_root).unwrap_or_else(|_| project_root.to_path_buf());
+    let relative = canonical.strip_prefix(&root)
+        .map_err(|_| format!("{} is outside the project", path))?
+        .to_string_lossy()
+        .replace('\\', "/");
+    if relative.is_empty() {
+        return Err("Pin files or directories, not the whole project".to_string());
+    }
+    Ok(relative)
+}
+
+/// Pin a file or directory of the project, returning all the pins
+pub fn pin(project_root: &Path, path: &str) -> Result<Vec<String>, String> {
+    let path = relative(project_root, path)?;
+    let mut pins = load(project_root);
+    if !pins.contains(&path) {
+        pins.push(path);
+        save(project_root, &pins)?;
+    }
+    Ok(pins)
+}
+
+/// Unpin a path, as written by `pin` or as given to it; returns the remaining pins
+pub fn unpin(project_root: &Path, path: &str) -> Result<Vec<String>, String> {
+    let mut pins = load(project_root);
+    let given = path.trim().trim_end_matches(['/', '\\']).trim_start_matches("./").to_string();
+    let resolved = relative(project_root, path).unwrap_or_else(|_| given.clone());
+    let before = pins.len();
+    pins.retain(|pin| *pin != given && *pin != resolved);
+    if pins.len() == before {
+        return Err(format!("{} is not pinned", given));
+    }
+    save(project_root, &pins)?;
+    Ok(pins)
+}
+
+/// Files of the pins, directories walked as the indexer does (.gitignore applies)
+fn files(project_root: &Path, pins: &[String]) -> Vec<String> {
+    let mut files: Vec<String> = Vec::new();
+    for pin in pins {
+        // Checked again: the pin may have become a link out of the project
+        let Ok(pin) = relative(project_root, pin) else { continue };
+        let mut walk: Vec<PathBuf> = WalkBuilder::new(project_root.join(pin))
+            .hidden(false)
+            .build()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_some_and(|kind| kind.is_file()))
+            .map(|entry| entry.into_path())
+            .collect();
+        walk.sort();
+        for path in walk {
+            let Ok(relative) = path.strip_prefix(project_root) else { continue };
+            let relative = relative.to_string_lossy().replace('\\', "/");
+            if !files.contains(&relative) {
+                files.push(relative);
+            }
+        }
+    }
+    files
+}
+
+/// Contents of the pinned files for the prompt, read again on each call so edits show up;
+/// past `MAX_PINNED_TOKENS` the remaining files are only listed
+pub fn context(project_root: &Path, pins: &[String]) -> String {
+    let mut context = String::new();
+    let mut tokens = 0;
+    let mut omitted: Vec<String> = Vec::new();
+    for file in files(project_root, pins) {
+        // Binary files are skipped
+        let Ok(content) = fs::read_to_string(project_root.join(&file)) else { continue };
+        let section = format!("\n--- {} ---\n{}\n", file, content.trim_end());
+        let section_tokens = count_tokens(&section);
+        if tokens + section_tokens > MAX_PINNED_TOKENS {
+            omitted.push(file);
+            continue;
+        }
+        tokens += section_tokens;
+        context.push_str(&section);
+    }
+    if !omitted.is_empty() {
+        context.push_str(&format!("\n(Non inclus, au-delà de {} tokens : {})\n", MAX_PINNED_TOKENS, omitted.join(", ")));
+    }
+    context
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pins_are_saved_and_expanded() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("docs/adr")).unwrap();
+        fs::write(root.join("docs/architecture.md"), "# Layers").unwrap();
+        fs::write(root.join("docs/adr/001.md"), "Use SQLite").unwrap();
+        fs::create_dir_all(root.join(".codestral")).unwrap();
+        fs::write(root.join(".codestral/config.toml"), "other = 1\n").unwrap();
+
+        assert_eq!(pin(root, "./docs/architecture.md").unwrap(), ["docs/architecture.md"]);
+        assert_eq!(pin(root, "docs/").unwrap(), ["docs/architecture.md", "docs"]);
+        assert!(pin(root, "missing.md").is_err());
+        assert!(pin(root, ".").is_err());
+        let config = read_config(root);
+        assert_eq!(config.get("other").and_then(|v| v.as_integer()), Some(1));
+
+        let context = context(root, &load(root));
+        assert_eq!(context.matches("--- docs/architecture.md ---").count(), 1);
+        assert!(context.contains("--- docs/adr/001.md ---\nUse SQLite"));
+
+        assert_eq!(unpin(root, "docs").unwrap(), ["docs/architecture.md"]);
+        assert!(unpin(root, "docs").is_err());
+    }
+
+    #[test]
+    fn test_pins_outside_the_project_are_dropped() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("project");
+        fs::create_dir_all(root.join(".codestral")).unwrap();
+        fs::write(root.join("notes.md"), "notes").unwrap();
+        fs::write(dir.path().join("secret.txt"), "secret").unwrap();
+        let secret = dir.path().join("secret.txt").to_string_lossy().replace('\\', "/");
+        fs::write(
+            root.join(".codestral/config.toml"),
+            format!("pinned = [\"../secret.txt\", \"{}\", \"..\", \"notes.md\"]\n", secret),
+        ).unwrap();
+
+        assert_eq!(load(&root), ["notes.md"]);
+        assert!(!context(&root, &load(&root)).contains("secret"));
+    }
+}
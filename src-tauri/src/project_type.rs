@@ -0,0 +1,170 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use crate::custom_tools::{CustomTool, CustomToolParam, ParamType, ToolPermission};
+
+/// Build system recognised from a manifest at the project root
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProjectKind {
+    Rust,
+    Node,
+    Python,
+    Go,
+}
+
+impl ProjectKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ProjectKind::Rust => "Rust (Cargo.toml)",
+            ProjectKind::Node => "Node.js (package.json)",
+            ProjectKind::Python => "Python (pyproject.toml)",
+            ProjectKind::Go => "Go (go.mod)",
+        }
+    }
+}
+
+/// Canonical commands of a detected project
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProjectCommands {
+    pub kind: ProjectKind,
+    pub build: Option<String>,
+    pub test: Option<String>,
+    pub lint: Option<String>,
+    /// Test command template for the run_tests tool, with a `{{target}}` placeholder
+    test_template: Option<String>,
+}
+
+/// Detect the project types at the root (a repository can have several, e.g. Cargo.toml and package.json)
+pub fn detect(project_root: &Path) -> Vec<ProjectCommands> {
+    let mut detected = Vec::new();
+
+    if project_root.join("Cargo.toml").is_file() {
+        detected.push(ProjectCommands {
+            kind: ProjectKind::Rust,
+            build: Some("cargo build".to_string()),
+            test: Some("cargo test".to_string()),
+            lint: Some("cargo clippy --all-targets -- -D warnings".to_string()),
+            test_template: Some("cargo test {{target}}".to_string()),
+        });
+    }
+
+    if let Ok(content) = fs::read_to_string(project_root.join("package.json")) {
+        let scripts = serde_json::from_str::<serde_json::Value>(&content)
+            .ok()
+            .and_then(|json| json.get("scripts").cloned())
+            .unwrap_or_default();
+        let manager = node_package_manager(project_root);
+        let script = |name: &str| scripts.get(name).map(|_| match name {
+            "test" => format!("{} test", manager),
+            _ => format!("{} run {}", manager, name),
+        });
+        detected.push(ProjectCommands {
+            kind: ProjectKind::Node,
+            build: script("build"),
+            lint: script("lint"),
+            test_template: script("test").map(|test| format!("{} {{{{target}}}}", test)),
+            test: script("test"),
+        });
+    }
+
+    if let Ok(content) = fs::read_to_string(project_root.join("pyproject.toml")) {
+        let runner = if project_root.join("uv.lock").exists() {
+            "uv run "
+        } else if project_root.join("poetry.lock").exists() {
+            "poetry run "
+        } else {
+            ""
+        };
+        detected.push(ProjectCommands {
+            kind: ProjectKind::Python,
+            build: None,
+            test: Some(format!("{}pytest", runner)),
+            lint: content.contains("[tool.ruff").then(|| format!("{}ruff check .", runner)),
+            test_template: Some(format!("{}pytest {{{{target}}}}", runner)),
+        });
+    }
+
+    if project_root.join("go.mod").is_file() {
+        detected.push(ProjectCommands {
+            kind: ProjectKind::Go,
+            build: Some("go build ./...".to_string()),
+            test: Some("go test ./...".to_string()),
+            lint: Some("go vet ./...".to_string()),
+            test_template: Some("go test {{target}}".to_string()),
+        });
+    }
+
+    detected
+}
+
+fn node_package_manager(project_root: &Path) -> &'static str {
+    if project_root.join("pnpm-lock.yaml").exists() {
+        "pnpm"
+    } else if project_root.join("yarn.lock").exists() {
+        "yarn"
+    } else if project_root.join("bun.lockb").exists() || project_root.join("bun.lock").exists() {
+        "bun"
+    } else {
+        "npm"
+    }
+}
+
+/// System prompt section listing the commands to use
+pub fn prompt_section(projects: &[ProjectCommands]) -> String {
+    if projects.is_empty() {
+        return String::new();
+    }
+
+    let mut section = String::from("PROJET DÉTECTÉ:\n");
+    for project in projects {
+        section.push_str(&format!("- {}\n", project.kind.label()));
+        for (label, command) in [("build", &project.build), ("test", &project.test), ("lint", &project.lint)] {
+            if let Some(command) = command {
+                section.push_str(&format!("  - {}: `{}`\n", label, command));
+            }
+        }
+    }
+    section.push_str("Utilise ces commandes (ou l'outil run_tests) au lieu d'en deviner d'autres.");
+    section
+}
+
+/// run_tests tool for the first detected project that has tests
+pub fn run_tests_tool(projects: &[ProjectCommands]) -> Option<CustomTool> {
+    let project = projects.iter().find(|p| p.test_template.is_some())?;
+    let target = CustomToolParam {
+        description: "Test, fichier ou paquet à lancer (tous si vide)".to_string(),
+        param_type: ParamType::String,
+        required: false,
+        default: Some(if project.kind == ProjectKind::Go { "./...".to_string() } else { String::new() }),
+    };
+
+    Some(CustomTool {
+        name: "run_tests".to_string(),
+        description: format!("Lance les tests du projet {}", project.kind.label()),
+        command: project.test_template.clone()?,
+        permission: ToolPermission::ReadOnly,
+        params: BTreeMap::from([("target".to_string(), target)]),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_cargo_and_node_scripts() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("Cargo.toml"), "[package]\nname = \"x\"\n").unwrap();
+        fs::write(dir.path().join("package.json"), r#"{"scripts": {"build": "vite build"}}"#).unwrap();
+        fs::write(dir.path().join("pnpm-lock.yaml"), "").unwrap();
+
+        let projects = detect(dir.path());
+        assert_eq!(projects.len(), 2);
+        assert_eq!(projects[1].build.as_deref(), Some("pnpm run build"));
+        assert_eq!(projects[1].test, None);
+
+        let tool = run_tests_tool(&projects).unwrap();
+        assert_eq!(tool.command, "cargo test {{target}}");
+        assert!(prompt_section(&projects).contains("`cargo clippy --all-targets -- -D warnings`"));
+    }
+}
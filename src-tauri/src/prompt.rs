@@ -0,0 +1,355 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::settings::settings_file;
+use crate::mode::ChatMode;
+use crate::mistral_client::{Message, Role};
+use serde::Deserialize;
+
+/// User template replacing the built-in system prompt, in `.codestral/` or next to settings.json
+pub const TEMPLATE_FILE: &str = "system_prompt.md";
+
+/// Part of the system prompt that a template can place with a variable
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Section {
+    /// Tools documentation
+    Tools,
+    /// File contents of the codebase
+    Codebase,
+    /// Git blame/log of the declarations named in the instruction (`--with-history`)
+    History,
+    /// Files pinned with `/pin`, in every mode
+    Pinned,
+    /// File list of the SQLite index
+    Index,
+    /// `.codestral/memory.md`
+    Memory,
+}
+
+impl Section {
+    pub fn variable(&self) -> &'static str {
+        match self {
+            Section::Tools => "{{tools}}",
+            Section::Codebase => "{{codebase}}",
+            Section::History => "{{history}}",
+            Section::Pinned => "{{pinned}}",
+            Section::Index => "{{index}}",
+            Section::Memory => "{{memory}}",
+        }
+    }
+}
+
+/// Sent in PLAN mode: describe the changes without making them
+pub const PLAN_MODE_SUFFIX: &str = r#"
+
+MODE PLAN: Propose un plan détaillé sans fournir de modifications de code.
+"#;
+
+/// Markers ending an AUTO response, `config.auto_markers` in settings.json, e.g.
+/// `{ "done": ["[DONE]"], "continue": ["[NEXT]"] }`: the prompt asks for the first of each
+/// list, any of them is recognized, whatever its case
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct AutoMarkers {
+    /// The task is complete
+    pub done: Vec<String>,
+    /// More work to do: the TUI relaunches the model with "Continue."
+    #[serde(rename = "continue")]
+    pub next: Vec<String>,
+}
+
+impl Default for AutoMarkers {
+    fn default() -> Self {
+        Self {
+            done: vec!["[TERMINÉ]".to_string(), "[TERMINE]".to_string(), "[DONE]".to_string()],
+            next: vec!["[CONTINUE]".to_string()],
+        }
+    }
+}
+
+impl AutoMarkers {
+    pub fn load() -> Self {
+        let Ok(path) = settings_file() else {
+            return Self::default();
+        };
+        let markers: Self = fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+            .and_then(|json| json.get("config")?.get("auto_markers").cloned())
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default();
+        // An emptied list falls back to the built-in markers rather than never matching
+        let defaults = Self::default();
+        Self {
+            done: if markers.done.is_empty() { defaults.done } else { markers.done },
+            next: if markers.next.is_empty() { defaults.next } else { markers.next },
+        }
+    }
+
+    /// Sent in AUTO mode by the TUI, which relaunches the model until the task is done
+    pub fn suffix(&self) -> String {
+        format!(
+            "\n\nMODE AUTO ACTIVÉ:\n\
+             - Tu DOIS continuer à travailler jusqu'à ce que la tâche soit COMPLÈTEMENT terminée\n\
+             - Fais TOUTES les modifications nécessaires en une seule réponse\n\
+             - Ne demande PAS de confirmation, applique directement\n\
+             - Si la tâche nécessite plusieurs étapes, fais-les TOUTES\n\
+             - Termine ta réponse par {} quand la tâche est complète\n\
+             - Si tu dois continuer, termine par {} et je te relancerai automatiquement\n",
+            self.done[0], self.next[0]
+        )
+    }
+
+    /// Whether the AUTO loop relaunches the model after this response: the last marker it
+    /// contains asks to continue, and the response did something (`acted`: tool calls or file
+    /// changes). A response without markers, or that only talks, ends the loop
+    pub fn should_continue(&self, response: &str, acted: bool) -> bool {
+        let response = response.to_lowercase();
+        let last = |markers: &[String]| markers.iter()
+            .filter_map(|marker| response.rfind(&marker.to_lowercase()))
+            .max();
+        match (last(&self.next), last(&self.done)) {
+            (Some(next), Some(done)) => acted && next > done,
+            (Some(_), None) => acted,
+            _ => false,
+        }
+    }
+}
+
+/// Canonical order of the sections
+const SECTIONS: [Section; 6] = [Section::Tools, Section::Codebase, Section::History, Section::Pinned, Section::Index, Section::Memory];
+
+/// Variable replaced by the built-in prompt, to extend it instead of replacing it
+pub const BASE_VARIABLE: &str = "{{base_prompt}}";
+
+/// Assemble a system prompt: base prompt (or user template), sections, then the additions and mode suffix.
+/// Sections the template does not place are appended in the order tools, codebase, history, pinned, index, memory
+pub struct PromptBuilder {
+    base: String,
+    template: Option<String>,
+    sections: Vec<(Section, String)>,
+    additions: Vec<String>,
+    suffix: String,
+}
+
+impl PromptBuilder {
+    pub fn new(base: &str) -> Self {
+        Self {
+            base: base.to_string(),
+            template: None,
+            sections: Vec::new(),
+            additions: Vec::new(),
+            suffix: String::new(),
+        }
+    }
+
+    /// Use a user template instead of the base prompt
+    pub fn template(mut self, template: Option<&str>) -> Self {
+        self.template = template.map(str::to_string);
+        self
+    }
+
+    /// Set a section, replacing any previous content
+    fn section(mut self, section: Section, content: &str) -> Self {
+        self.sections.retain(|(s, _)| *s != section);
+        self.sections.push((section, content.trim().to_string()));
+        self
+    }
+
+    pub fn tools(self, docs: &str) -> Self {
+        self.section(Section::Tools, docs)
+    }
+
+    pub fn codebase(self, context: &str) -> Self {
+        let context = if context.trim().is_empty() { String::new() } else { format!("CODEBASE:\n{}", context) };
+        self.section(Section::Codebase, &context)
+    }
+
+    pub fn history(self, history: &str) -> Self {
+        let history = if history.trim().is_empty() { String::new() } else { format!("GIT HISTORY:\n{}", history) };
+        self.section(Section::History, &history)
+    }
+
+    pub fn pinned(self, context: &str) -> Self {
+        let context = if context.trim().is_empty() { String::new() } else { format!("PINNED FILES:\n{}", context) };
+        self.section(Section::Pinned, &context)
+    }
+
+    pub fn index(self, file_map: &str) -> Self {
+        self.section(Section::Index, file_map)
+    }
+
+    pub fn memory(self, memory: &str) -> Self {
+        let memory = if memory.trim().is_empty() { String::new() } else { format!("PROJECT MEMORY:\n{}", memory) };
+        self.section(Section::Memory, &memory)
+    }
+
+    /// Project context the mode needs: no code in ASK (general questions), the file map in PLAN,
+    /// file contents and the file map in CODE and AUTO
+    pub fn project_context(self, mode: ChatMode, codebase: &str, file_map: &str) -> Self {
+        match mode {
+            ChatMode::Ask => self,
+            ChatMode::Plan => self.index(file_map),
+            ChatMode::Code | ChatMode::Auto => self.codebase(codebase).index(file_map),
+        }
+    }
+
+    /// Per-request text after the sections (mentioned files, language rule...)
+    pub fn append(mut self, text: &str) -> Self {
+        if !text.trim().is_empty() {
+            self.additions.push(text.trim().to_string());
+        }
+        self
+    }
+
+    /// Text added at the very end, as is
+    pub fn suffix(mut self, suffix: &str) -> Self {
+        self.suffix = suffix.to_string();
+        self
+    }
+
+    /// Instructions of the mode, at the very end
+    pub fn mode(self, mode: ChatMode) -> Self {
+        match mode {
+            ChatMode::Plan => self.suffix(PLAN_MODE_SUFFIX),
+            ChatMode::Auto => self.suffix(&AutoMarkers::load().suffix()),
+            ChatMode::Ask | ChatMode::Code => self.suffix(""),
+        }
+    }
+
+    pub fn build(&self) -> String {
+        let content = |section: Section| self.sections.iter()
+            .find(|(s, _)| *s == section)
+            .map(|(_, content)| content.as_str())
+            .unwrap_or("");
+
+        let mut prompt = match &self.template {
+            Some(template) => {
+                // Variables of sections this entry point does not provide are removed
+                let mut values = vec![(BASE_VARIABLE, self.base.as_str())];
+                values.extend(SECTIONS.iter().map(|section| (section.variable(), content(*section))));
+                render(template, &values)
+            }
+            None => self.base.clone(),
+        };
+
+        let placed = |section: &Section| self.template.as_ref().is_some_and(|t| t.contains(section.variable()));
+        let appended = SECTIONS.iter().filter(|s| !placed(s)).map(|s| content(*s));
+        for text in appended.chain(self.additions.iter().map(String::as_str)) {
+            if !text.is_empty() {
+                prompt = format!("{}\n\n{}", prompt.trim_end(), text);
+            }
+        }
+        prompt.push_str(&self.suffix);
+        prompt
+    }
+}
+
+/// Replace the variables in one pass, so inserted content (code, memory) is never rendered again
+fn render(template: &str, values: &[(&str, &str)]) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        rendered.push_str(&rest[..start]);
+        let tail = &rest[start..];
+        match values.iter().find(|(variable, _)| tail.starts_with(variable)) {
+            Some((variable, value)) => {
+                rendered.push_str(value);
+                rest = &tail[variable.len()..];
+            }
+            None => {
+                rendered.push_str("{{");
+                rest = &tail[2..];
+            }
+        }
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+/// Global template, next to settings.json
+fn global_template_path() -> Option<PathBuf> {
+    Some(settings_file().ok()?.parent()?.join(TEMPLATE_FILE))
+}
+
+pub const COMPACT_PROMPT: &str = "Résume en 2-3 phrases les échanges précédents pour garder le contexte essentiel. Sois très concis.";
+
+/// Request summarizing a history of "User: ..." / "AI: ..." lines, shared by every frontend
+pub fn compact_messages(history: &str) -> Vec<Message> {
+    vec![
+        Message { role: Role::System, content: COMPACT_PROMPT.to_string() },
+        Message { role: Role::User, content: format!("Historique à résumer:\n{}", history) },
+    ]
+}
+
+/// Project instructions of `.codestral/memory.md` (empty if missing)
+pub fn load_memory(project_root: &Path) -> String {
+    fs::read_to_string(project_root.join(".codestral").join("memory.md")).unwrap_or_default()
+}
+
+/// System prompt template of the project (`.codestral/system_prompt.md`), else the global one
+pub fn load_template(project_root: Option<&Path>) -> Result<Option<String>, String> {
+    let project = project_root.map(|root| root.join(".codestral").join(TEMPLATE_FILE));
+    for path in project.into_iter().chain(global_template_path()) {
+        if path.is_file() {
+            let template = fs::read_to_string(&path)
+                .map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+            return Ok(Some(template));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_layout_and_mode_suffix() {
+        let builder = PromptBuilder::new("BASE")
+            .memory("notes")
+            .codebase("fn main() {}")
+            .memory("")
+            .append("LANGUE: anglais");
+
+        assert_eq!(builder.build(), "BASE\n\nCODEBASE:\nfn main() {}\n\nLANGUE: anglais");
+        let plan = builder.mode(ChatMode::Plan).build();
+        assert!(plan.ends_with(PLAN_MODE_SUFFIX));
+        assert!(plan.starts_with("BASE\n\nCODEBASE:"));
+    }
+
+    #[test]
+    fn test_project_context_follows_the_mode() {
+        let prompt = |mode| PromptBuilder::new("BASE").project_context(mode, "fn main() {}", "\n\nINDEX: main.rs").build();
+        assert_eq!(prompt(ChatMode::Ask), "BASE");
+        assert_eq!(prompt(ChatMode::Plan), "BASE\n\nINDEX: main.rs");
+        assert_eq!(prompt(ChatMode::Code), "BASE\n\nCODEBASE:\nfn main() {}\n\nINDEX: main.rs");
+        assert_eq!(prompt(ChatMode::Auto), prompt(ChatMode::Code));
+    }
+
+    #[test]
+    fn test_template_places_sections_and_appends_the_rest() {
+        let prompt = PromptBuilder::new("BASE")
+            .template(Some("Équipe X.\n{{base_prompt}}\n\n{{memory}}\n{{codebase}}{{other}}"))
+            .tools("TOOLS")
+            .index("\n\nINDEX")
+            .memory("{{tools}} notes")
+            .append("extra")
+            .suffix("\nSUFFIX")
+            .build();
+
+        assert_eq!(prompt, "Équipe X.\nBASE\n\nPROJECT MEMORY:\n{{tools}} notes\n{{other}}\n\nTOOLS\n\nINDEX\n\nextra\nSUFFIX");
+    }
+
+    #[test]
+    fn test_auto_markers_end_the_loop() {
+        let markers: AutoMarkers = serde_json::from_str(r#"{ "continue": ["[NEXT]"] }"#).unwrap();
+        assert_eq!(markers.done, AutoMarkers::default().done);
+        assert!(markers.suffix().contains("termine par [NEXT] et je te relancerai"));
+        assert!(markers.should_continue("Étape 1 faite.\n**[next]**", true));
+        assert!(!markers.should_continue("Étape 1 faite.\n[NEXT]", false));
+        assert!(!markers.should_continue("[NEXT] puis tout est fait. [Done]", true));
+        assert!(markers.should_continue("Le [TERMINÉ] viendra plus tard. [NEXT]", true));
+        assert!(!markers.should_continue("Voici la réponse.", true));
+        assert!(!AutoMarkers::default().should_continue("[terminé]", true));
+    }
+}
@@ -0,0 +1,155 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use chrono::{DateTime, Local, NaiveDate, Timelike};
+use serde::{Deserialize, Serialize};
+use crate::settings::{load_saved_api_settings, settings_file, ApiSettings};
+use crate::indexer::CodebaseIndex;
+use crate::persistent_index::{reindex_project, PersistentIndex};
+use crate::recent_projects::{RecentProjects, MAX_RECENT};
+use crate::semantic_search::{self, EmbeddingSettings};
+
+/// How often the scheduler wakes up to check the settings and the time
+const TICK: Duration = Duration::from_secs(5 * 60);
+
+/// `scheduled_reindex` section of settings.json, e.g.
+/// `"scheduled_reindex": { "enabled": true, "hour": 4, "projects": ["/home/me/app"] }`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReindexSchedule {
+    pub enabled: bool,
+    /// Local hour (0-23) after which the daily pass runs
+    pub hour: u32,
+    /// Projects to reindex; empty means the recent projects
+    pub projects: Vec<String>,
+}
+
+impl Default for ReindexSchedule {
+    fn default() -> Self {
+        Self { enabled: false, hour: 3, projects: Vec::new() }
+    }
+}
+
+impl ReindexSchedule {
+    pub fn load() -> Self {
+        let Ok(path) = settings_file() else {
+            return Self::default();
+        };
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+            .and_then(|json| json.get("config")?.get("scheduled_reindex").cloned())
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default()
+    }
+
+    /// Whether the daily pass is due: past the hour and not run yet today.
+    /// A pass missed while the machine was off runs at the next check of the day
+    pub fn is_due(&self, now: DateTime<Local>, last_run: Option<NaiveDate>) -> bool {
+        self.enabled && now.hour() >= self.hour.min(23) && last_run != Some(now.date_naive())
+    }
+
+    /// Existing project directories to reindex
+    pub fn project_roots(&self) -> Vec<PathBuf> {
+        let roots: Vec<PathBuf> = if self.projects.is_empty() {
+            RecentProjects::open()
+                .and_then(|recent| recent.list(MAX_RECENT))
+                .map(|projects| projects.into_iter().map(|project| project.path).collect())
+                .unwrap_or_default()
+        } else {
+            self.projects.iter().map(PathBuf::from).collect()
+        };
+        roots.into_iter().filter(|root| root.is_dir()).collect()
+    }
+}
+
+/// Reindex every scheduled project, and embed its changed files when semantic search is on;
+/// errors are reported and do not stop the others
+pub fn run_once(schedule: &ReindexSchedule) {
+    let embeddings = EmbeddingSettings::load();
+    for root in schedule.project_roots() {
+        match reindex_project(&root) {
+            Ok((_, updated)) => eprintln!("Scheduled reindex of {}: {} file(s) updated", root.display(), updated),
+            Err(e) => {
+                eprintln!("Scheduled reindex of {} failed: {}", root.display(), e);
+                continue;
+            }
+        }
+        if embeddings.enabled {
+            match embed_project(&root, &embeddings) {
+                Ok(embedded) => eprintln!("Scheduled embeddings of {}: {} file(s) embedded", root.display(), embedded),
+                Err(e) => eprintln!("Scheduled embeddings of {} failed: {}", root.display(), e),
+            }
+        }
+    }
+}
+
+/// Vectors of the files changed since the last pass, so the first semantic search is quick
+fn embed_project(root: &Path, settings: &EmbeddingSettings) -> Result<usize, String> {
+    let (api_key, provider) = load_saved_api_settings().ok_or("No API key configured")?;
+    let client = ApiSettings::merge(api_key, provider, Some(root))?.client();
+    let index = CodebaseIndex::index_with_progress(root, None, &[], usize::MAX, |_, _| {})?;
+    let store = PersistentIndex::open(root)?;
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| e.to_string())?;
+    runtime.block_on(semantic_search::embed_missing(&store, &index, client.as_ref(), settings))
+}
+
+/// Date of the last pass, next to settings.json so a restart does not run it again
+fn last_run_file() -> Option<PathBuf> {
+    Some(settings_file().ok()?.with_file_name("scheduled_reindex_last_run"))
+}
+
+fn load_last_run() -> Option<NaiveDate> {
+    fs::read_to_string(last_run_file()?).ok()?.trim().parse().ok()
+}
+
+fn save_last_run(date: NaiveDate) {
+    let Some(path) = last_run_file() else { return };
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    if let Err(e) = fs::write(&path, date.to_string()) {
+        eprintln!("Cannot write {}: {}", path.display(), e);
+    }
+}
+
+/// Run the daily reindex in a background thread for as long as the app lives.
+/// The settings are read again at each check, so changes apply without a restart
+pub fn start() {
+    std::thread::spawn(|| {
+        let mut last_run = load_last_run();
+        loop {
+            let schedule = ReindexSchedule::load();
+            let now = Local::now();
+            if schedule.is_due(now, last_run) {
+                run_once(&schedule);
+                last_run = Some(now.date_naive());
+                save_last_run(now.date_naive());
+            }
+            std::thread::sleep(TICK);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_daily_pass_is_due_once_after_the_hour() {
+        let schedule = ReindexSchedule { enabled: true, hour: 3, projects: Vec::new() };
+        let night = Local.with_ymd_and_hms(2024, 5, 2, 2, 55, 0).unwrap();
+        let morning = Local.with_ymd_and_hms(2024, 5, 2, 9, 0, 0).unwrap();
+        let yesterday = NaiveDate::from_ymd_opt(2024, 5, 1);
+
+        assert!(!schedule.is_due(night, yesterday));
+        assert!(schedule.is_due(morning, yesterday));
+        assert!(schedule.is_due(morning, None));
+        assert!(!schedule.is_due(morning, Some(morning.date_naive())));
+        assert!(!ReindexSchedule::default().is_due(morning, None));
+    }
+}
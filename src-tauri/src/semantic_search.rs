@@ -0,0 +1,165 @@
+use std::fs;
+use serde::{Deserialize, Serialize};
+use crate::context_guard::count_tokens;
+use crate::indexer::{CodebaseIndex, IndexedFile};
+use crate::backend::LlmBackend;
+use crate::persistent_index::PersistentIndex;
+
+/// Tokens of a file embedded; the rest of a long file does not count in its vector
+const MAX_INPUT_TOKENS: usize = 4_000;
+
+/// Tokens sent per embeddings request, under the API limit
+const MAX_BATCH_TOKENS: usize = 12_000;
+
+/// `embeddings` section of settings.json, e.g. `"embeddings": { "enabled": true, "top_k": 12 }`.
+/// Off by default: embedding a project sends all its files once, then the changed ones
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EmbeddingSettings {
+    pub enabled: bool,
+    /// Embedding model; the provider's default when unset
+    pub model: Option<String>,
+    /// Files whose contents go into the CODEBASE section
+    pub top_k: usize,
+}
+
+impl Default for EmbeddingSettings {
+    fn default() -> Self {
+        Self { enabled: false, model: None, top_k: 8 }
+    }
+}
+
+impl EmbeddingSettings {
+    pub fn load() -> Self {
+        let Ok(path) = crate::settings::settings_file() else {
+            return Self::default();
+        };
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+            .and_then(|json| json.get("config")?.get("embeddings").cloned())
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default()
+    }
+
+    /// Model of the vectors: the configured one, else the provider's default
+    pub fn model<'a>(&'a self, client: &'a dyn LlmBackend) -> &'a str {
+        self.model.as_deref().unwrap_or(client.default_embedding_model())
+    }
+}
+
+/// Text embedded for a file: its path, then its first lines up to `MAX_INPUT_TOKENS`
+fn embedding_input(file: &IndexedFile) -> String {
+    let mut input = format!("{}\n", file.relative_path);
+    let mut tokens = count_tokens(&input);
+    for line in file.content.split_inclusive('\n') {
+        tokens += count_tokens(line);
+        if tokens > MAX_INPUT_TOKENS {
+            break;
+        }
+        input.push_str(line);
+    }
+    input
+}
+
+/// Inputs grouped into requests of at most `max_tokens` (a longer input goes alone)
+fn batches(inputs: &[String], max_tokens: usize) -> Vec<&[String]> {
+    let mut batches = Vec::new();
+    let mut start = 0;
+    let mut tokens = 0;
+    for (i, input) in inputs.iter().enumerate() {
+        let input_tokens = count_tokens(input);
+        if i > start && tokens + input_tokens > max_tokens {
+            batches.push(&inputs[start..i]);
+            start = i;
+            tokens = 0;
+        }
+        tokens += input_tokens;
+    }
+    if start < inputs.len() {
+        batches.push(&inputs[start..]);
+    }
+    batches
+}
+
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norms = norm(a) * norm(b);
+    if norms == 0.0 { 0.0 } else { dot / norms }
+}
+
+/// Paths of the `k` vectors closest to the query, closest first
+fn nearest(query: &[f32], candidates: Vec<(String, Vec<f32>)>, k: usize) -> Vec<String> {
+    let mut scored: Vec<(f32, String)> = candidates.into_iter()
+        .map(|(path, vector)| (cosine_similarity(query, &vector), path))
+        .collect();
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+    scored.into_iter().take(k).map(|(_, path)| path).collect()
+}
+
+/// Embed the files of the index without a vector for their current content and store them
+/// in the persistent index; returns the number of files embedded
+pub async fn embed_missing(store: &PersistentIndex, index: &CodebaseIndex, client: &dyn LlmBackend, settings: &EmbeddingSettings) -> Result<usize, String> {
+    let model = settings.model(client);
+    let missing: Vec<&IndexedFile> = index.files.iter()
+        .filter(|file| store.needs_embedding(&file.relative_path, &file.content, model))
+        .collect();
+    let inputs: Vec<String> = missing.iter().map(|file| embedding_input(file)).collect();
+    let mut done = 0;
+    for batch in batches(&inputs, MAX_BATCH_TOKENS) {
+        let vectors = client.embed(model, batch).await.map_err(|e| e.to_string())?;
+        for (file, vector) in missing[done..done + batch.len()].iter().zip(vectors) {
+            store.store_embedding(&file.relative_path, &file.content, model, &vector)?;
+        }
+        done += batch.len();
+    }
+    Ok(done)
+}
+
+/// Files of the index most relevant to `query`, most relevant first. Files without a vector
+/// for their current content are embedded first
+pub async fn relevant_files(index: &CodebaseIndex, client: &dyn LlmBackend, settings: &EmbeddingSettings, query: &str) -> Result<Vec<String>, String> {
+    let store = PersistentIndex::open(&index.root)?;
+    embed_missing(&store, index, client, settings).await?;
+
+    let model = settings.model(client);
+    let query = client.embed(model, &[query.to_string()]).await
+        .map_err(|e| e.to_string())?
+        .pop()
+        .unwrap_or_default();
+    let candidates: Vec<(String, Vec<f32>)> = store.embeddings(model)?
+        .into_iter()
+        .filter(|(path, _)| index.files.iter().any(|file| file.relative_path == *path))
+        .collect();
+    Ok(nearest(&query, candidates, settings.top_k))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_closest_files_come_first() {
+        let candidates = vec![
+            ("src/db.rs".to_string(), vec![0.0, 1.0]),
+            ("src/http.rs".to_string(), vec![1.0, 0.1]),
+            ("README.md".to_string(), vec![-1.0, 0.0]),
+        ];
+        assert_eq!(nearest(&[1.0, 0.0], candidates, 2), ["src/http.rs", "src/db.rs"]);
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 0.0]), 0.0);
+
+        let inputs: Vec<String> = ["a ".repeat(6), "b ".repeat(6), "c ".repeat(20)].into_iter().collect();
+        let grouped = batches(&inputs, count_tokens(&inputs[0]) + count_tokens(&inputs[1]));
+        assert_eq!(grouped.iter().map(|batch| batch.len()).collect::<Vec<_>>(), [2, 1]);
+
+        let dir = tempfile::tempdir().unwrap();
+        let store = PersistentIndex::open(dir.path()).unwrap();
+        assert!(store.needs_embedding("src/db.rs", "fn query() {}", "codestral-embed"));
+        store.store_embedding("src/db.rs", "fn query() {}", "codestral-embed", &[0.5, -1.25]).unwrap();
+        assert!(!store.needs_embedding("src/db.rs", "fn query() {}", "codestral-embed"));
+        assert!(store.needs_embedding("src/db.rs", "fn query() { todo!() }", "codestral-embed"));
+        assert!(store.needs_embedding("src/db.rs", "fn query() {}", "mistral-embed"));
+        assert_eq!(store.embeddings("codestral-embed").unwrap(), [("src/db.rs".to_string(), vec![0.5, -1.25])]);
+    }
+}
@@ -0,0 +1,100 @@
+use std::fs;
+use serde::{Deserialize, Serialize};
+use crate::settings::settings_file;
+use crate::mistral_client::Message;
+
+/// Starting point of a GUI conversation, e.g.
+/// `"conversation_templates": [{ "name": "Review", "system_prompt": "...", "model": "codestral-latest" }]`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversationTemplate {
+    pub name: String,
+    /// Sent before the messages of every request of the conversation
+    #[serde(default)]
+    pub system_prompt: String,
+    /// Messages the conversation starts with
+    #[serde(default)]
+    pub starter_messages: Vec<Message>,
+    /// Model of the conversation; the default model when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+}
+
+impl ConversationTemplate {
+    fn new(name: &str, system_prompt: &str, starter: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            system_prompt: system_prompt.to_string(),
+            starter_messages: vec![Message::assistant(starter)],
+            model: None,
+        }
+    }
+}
+
+/// Templates offered until `conversation_templates` is set
+pub fn builtin() -> Vec<ConversationTemplate> {
+    vec![
+        ConversationTemplate::new(
+            "Code review",
+            "You are a senior reviewer. Review the code or diff you are given: point out bugs, \
+             unsafe or unclear code and missing tests first, then style. Quote the lines you comment on \
+             and suggest a fix for each issue.",
+            "Paste the code or the diff to review.",
+        ),
+        ConversationTemplate::new(
+            "Explain code",
+            "You explain code to a developer new to it. Start with what the code does as a whole, then \
+             walk through its parts in order. Mention the non-obvious behavior and the assumptions it makes.",
+            "Paste the code to explain, and tell me what you already know about it.",
+        ),
+        ConversationTemplate::new(
+            "Write tests",
+            "You write unit tests. Use the test framework and conventions of the code you are given, \
+             cover the normal cases, the edge cases and the errors, and keep each test focused on one behavior.",
+            "Paste the code to test and name the test framework if it is not obvious.",
+        ),
+    ]
+}
+
+/// Templates of settings.json, or the built-in ones
+pub fn load() -> Vec<ConversationTemplate> {
+    let Ok(path) = settings_file() else {
+        return builtin();
+    };
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+        .and_then(|json| json.get("config")?.get("conversation_templates").cloned())
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_else(builtin)
+}
+
+pub fn find(name: &str) -> Result<ConversationTemplate, String> {
+    load().into_iter()
+        .find(|template| template.name == name)
+        .ok_or_else(|| format!("No conversation template named \"{}\"", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mistral_client::Role;
+
+    #[test]
+    fn test_templates_from_settings() {
+        let templates: Vec<ConversationTemplate> = serde_json::from_value(serde_json::json!([
+            { "name": "Plain" },
+            {
+                "name": "SQL",
+                "system_prompt": "Answer with PostgreSQL.",
+                "starter_messages": [{ "role": "user", "content": "Schema:" }],
+                "model": "codestral-latest"
+            }
+        ])).unwrap();
+        assert!(templates[0].system_prompt.is_empty() && templates[0].starter_messages.is_empty());
+        assert_eq!(templates[1].starter_messages[0].role, Role::User);
+        assert_eq!(templates[1].model.as_deref(), Some("codestral-latest"));
+
+        let names: Vec<String> = builtin().into_iter().map(|template| template.name).collect();
+        assert_eq!(names, ["Code review", "Explain code", "Write tests"]);
+    }
+}
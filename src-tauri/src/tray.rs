@@ -1,11 +1,30 @@
+use std::path::Path;
 use tauri::{
-    menu::{Menu, MenuItem},
+    menu::{IsMenuItem, Menu, MenuItem, Submenu},
     tray::{MouseButton, TrayIconBuilder, TrayIconEvent},
     App, Runtime,
     Manager,
     Listener,
     Emitter,
 };
+use crate::recent_projects::{self, MAX_RECENT};
+
+/// Menu ID prefix of the recent projects, followed by the project path
+const RECENT_PREFIX: &str = "recent:";
+
+/// "Projets récents" submenu, opening the project in the window
+fn recent_projects_menu<R: Runtime>(app: &App<R>) -> tauri::Result<Submenu<R>> {
+    let label = |path: &Path| match path.file_name() {
+        Some(name) => format!("{}  ({})", name.to_string_lossy(), path.display()),
+        None => path.display().to_string(),
+    };
+    let items = recent_projects::candidates().iter()
+        .take(MAX_RECENT)
+        .map(|path| MenuItem::with_id(app, format!("{}{}", RECENT_PREFIX, path.display()), label(path), true, None::<&str>))
+        .collect::<tauri::Result<Vec<_>>>()?;
+    let items: Vec<&dyn IsMenuItem<R>> = items.iter().map(|item| item as &dyn IsMenuItem<R>).collect();
+    Submenu::with_items(app, "Projets récents", !items.is_empty(), &items)
+}
 
 pub fn create_tray<R: Runtime>(app: &App<R>) -> tauri::Result<tauri::tray::TrayIcon<R>> {
     let toggle_i = MenuItem::with_id(app, "toggle", "Afficher/Masquer", true, None::<&str>)?;
@@ -13,7 +32,9 @@ pub fn create_tray<R: Runtime>(app: &App<R>) -> tauri::Result<tauri::tray::TrayI
     let settings_i = MenuItem::with_id(app, "settings", "Paramètres", true, None::<&str>)?;
     let clear_i = MenuItem::with_id(app, "clear_history", "Effacer l'historique", true, None::<&str>)?;
     
-    let menu = Menu::with_items(app, &[&toggle_i, &settings_i, &clear_i, &quit_i])?;
+    let recent_i = recent_projects_menu(app)?;
+    
+    let menu = Menu::with_items(app, &[&toggle_i, &recent_i, &settings_i, &clear_i, &quit_i])?;
 
     TrayIconBuilder::with_id("main-tray")
         .icon(app.default_window_icon().unwrap().clone())
@@ -39,6 +60,13 @@ pub fn create_tray<R: Runtime>(app: &App<R>) -> tauri::Result<tauri::tray::TrayI
                         let _ = window.emit("open-settings", ());
                     }
                 }
+                id if id.starts_with(RECENT_PREFIX) => {
+                    if let Some(window) = app.get_webview_window("main") {
+                        let _ = window.show();
+                        let _ = window.set_focus();
+                        let _ = window.emit("open-project", &id[RECENT_PREFIX.len()..]);
+                    }
+                }
                 "clear_history" => {
                      if let Some(window) = app.get_webview_window("main") {
                         let _ = window.emit("request-clear-history", ());
@@ -1,6 +1,13 @@
-use crate::mistral_client::Message;
-use crate::chat::ChatMode;
+use crate::mistral_client::{Message, Role};
+use crate::chat_storage::{MessageMeta, SavedChat};
+use crate::context_guard::count_tokens;
+use crate::mode::ChatMode;
+use crate::tui::question_form::FormField;
+use std::fs;
 use std::path::PathBuf;
+use serde::Deserialize;
+use crate::settings::settings_file;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 
 /// Application state for TUI
 pub struct App {
@@ -14,28 +21,107 @@ pub struct App {
     pub cursor_pos: usize,
     /// Current mode
     pub mode: ChatMode,
+    /// PLAN+ preview: writing tool calls and file changes are shown, never applied
+    pub dry_run: bool,
     /// Scroll offset for messages
     pub scroll: u16,
     /// Total estimated tokens
     pub tokens: usize,
     /// Is waiting for AI response
     pub loading: bool,
+    /// Pending retry of a failed request, shown in the status bar
+    pub retry_status: Option<String>,
+    /// The API was unreachable: saved chats, index and exports still work, requests are not sent
+    pub offline: bool,
     /// Spinner animation frame
     pub spinner_frame: usize,
     /// Pending questions from AI (to show in tabbed form)
-    pub pending_questions: Vec<String>,
+    pub pending_questions: Vec<FormField>,
     /// Should quit
     pub should_quit: bool,
     /// Input history for up/down navigation
     pub input_history: Vec<String>,
     pub history_index: Option<usize>,
+    /// Index of the first message after a resumed history (a divider is drawn there)
+    pub resumed_at: Option<usize>,
+    /// Responses longer than this many lines are shown folded (0: never)
+    pub fold_lines: usize,
+    /// Paths pinned with /pin, sent with every prompt
+    pub pinned: Vec<String>,
+}
+
+/// `tui` section of settings.json, e.g.
+/// `"tui": { "fold_lines": 60, "compare_models": ["codestral-latest", "mistral-large-latest"] }`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct TuiSettings {
+    pub fold_lines: usize,
+    /// Models answering `/compare`, shown side by side in this order
+    pub compare_models: Vec<String>,
+    /// Offer additions to memory.md at /exit and /new after a long enough conversation
+    pub memory_updates: bool,
+}
+
+impl Default for TuiSettings {
+    fn default() -> Self {
+        Self {
+            fold_lines: 40,
+            compare_models: vec!["codestral-latest".to_string(), "mistral-large-latest".to_string()],
+            memory_updates: true,
+        }
+    }
+}
+
+impl TuiSettings {
+    pub fn load() -> Self {
+        let Ok(path) = settings_file() else {
+            return Self::default();
+        };
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+            .and_then(|json| json.get("config")?.get("tui").cloned())
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default()
+    }
 }
 
 #[derive(Clone)]
 pub struct ChatMessage {
-    pub role: String,
+    pub role: Role,
     pub content: String,
     pub is_user: bool,
+    /// Results of the tool calls made by this assistant message, sent back as the next user turn
+    pub tool_results: Vec<String>,
+    pub meta: MessageMeta,
+    /// Unfolded by the user despite its length
+    pub expanded: bool,
+    /// Answers of `/compare` as (model, answer), shown side by side instead of `content`
+    pub comparison: Vec<(String, String)>,
+    /// Size of the file changes of a response, shown above it, e.g. "2 fichiers, +12/−3 lignes (Rust)"
+    pub diff_summary: Option<String>,
+}
+
+impl ChatMessage {
+    /// Message sent now; user messages are shown as typed by the user
+    pub fn new(role: Role, content: String) -> Self {
+        let meta = MessageMeta::now(count_tokens(&content));
+        Self {
+            role,
+            is_user: role == Role::User,
+            content,
+            tool_results: Vec::new(),
+            meta,
+            expanded: false,
+            comparison: Vec::new(),
+            diff_summary: None,
+        }
+    }
+
+    /// Estimated tokens of the message and its tool results
+    pub fn estimated_tokens(&self) -> usize {
+        count_tokens(&self.content) + self.tool_results.iter().map(|result| count_tokens(result)).sum::<usize>()
+    }
 }
 
 impl App {
@@ -46,14 +132,45 @@ impl App {
             input: String::new(),
             cursor_pos: 0,
             mode: ChatMode::Code,
+            dry_run: false,
             scroll: 0,
             tokens: 0,
             loading: false,
+            retry_status: None,
+            offline: false,
             spinner_frame: 0,
             pending_questions: Vec::new(),
             should_quit: false,
             input_history: Vec::new(),
             history_index: None,
+            resumed_at: None,
+            fold_lines: TuiSettings::default().fold_lines,
+            pinned: Vec::new(),
+        }
+    }
+
+    /// Lines hidden by the fold of a long response (0 when shown in full)
+    pub fn folded_lines(&self, message: &ChatMessage) -> usize {
+        if message.is_user || message.expanded || self.fold_lines == 0 || !message.comparison.is_empty() {
+            return 0;
+        }
+        message.content.lines().count().saturating_sub(self.fold_lines)
+    }
+
+    /// Unfold the most recent folded response; when none is left, fold them all again.
+    /// Returns whether a response was unfolded
+    pub fn toggle_fold(&mut self) -> bool {
+        match (0..self.messages.len()).rev().find(|&i| self.folded_lines(&self.messages[i]) > 0) {
+            Some(i) => {
+                self.messages[i].expanded = true;
+                true
+            }
+            None => {
+                for message in &mut self.messages {
+                    message.expanded = false;
+                }
+                false
+            }
         }
     }
 
@@ -67,11 +184,7 @@ impl App {
     }
 
     pub fn add_user_message(&mut self, content: String) {
-        self.messages.push(ChatMessage {
-            role: "user".to_string(),
-            content: content.clone(),
-            is_user: true,
-        });
+        self.messages.push(ChatMessage::new(Role::User, content.clone()));
         self.input_history.push(content);
         self.input.clear();
         self.cursor_pos = 0;
@@ -80,18 +193,98 @@ impl App {
     }
 
     pub fn add_ai_message(&mut self, content: String) {
-        self.messages.push(ChatMessage {
-            role: "assistant".to_string(),
-            content,
-            is_user: false,
-        });
+        self.messages.push(ChatMessage::new(Role::Assistant, content));
+        self.update_tokens();
+    }
+
+    /// Answers of the same prompt by several models; the transcript keeps them one after the other
+    pub fn add_comparison(&mut self, answers: Vec<(String, String)>) {
+        let content = answers.iter()
+            .map(|(model, answer)| format!("### {}\n{}", model, answer))
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        let mut message = ChatMessage::new(Role::Assistant, content);
+        message.meta.model = Some(answers.iter().map(|(model, _)| model.as_str()).collect::<Vec<_>>().join(" vs "));
+        message.comparison = answers;
+        self.messages.push(message);
+        self.update_tokens();
+    }
+
+    /// Attach tool results to the assistant message at `index`
+    pub fn attach_tool_results(&mut self, index: usize, results: Vec<String>) {
+        if let Some(message) = self.messages.get_mut(index) {
+            message.tool_results.extend(results);
+            message.meta.tokens = message.estimated_tokens();
+        }
+        self.update_tokens();
+    }
+
+    /// Remove all messages (and the resume divider)
+    pub fn clear_messages(&mut self) {
+        self.messages.clear();
+        self.resumed_at = None;
+        self.update_tokens();
+    }
+
+    /// Show a saved conversation in place of the current one, with the divider after its history
+    pub fn load_saved(&mut self, chat: &SavedChat) {
+        self.clear_messages();
+        for (i, msg) in chat.messages.iter().enumerate() {
+            // Tool results were saved as a user turn: attach them back to their message
+            if let Some(results) = msg.content.strip_prefix("Résultats des outils:\n") {
+                if msg.role == Role::User && self.messages.last().map(|m| !m.is_user).unwrap_or(false) {
+                    let index = self.messages.len() - 1;
+                    self.messages[index].tool_results.push(results.to_string());
+                    continue;
+                }
+            }
+            let mut message = ChatMessage::new(msg.role, msg.content.clone());
+            // Chats saved before metadata existed: date them with the last update
+            message.meta = chat.metadata.get(i).cloned().flatten()
+                .unwrap_or_else(|| MessageMeta { timestamp: chat.updated_at, ..message.meta });
+            self.messages.push(message);
+        }
+        self.scroll = 0;
+        self.loading = false;
+        self.input.clear();
+        self.cursor_pos = 0;
+        self.resumed_at = Some(self.messages.len());
+        self.update_tokens();
+    }
+
+    /// Remove the last user message and everything answered after it.
+    /// Returns the number of messages removed
+    pub fn drop_last_exchange(&mut self) -> usize {
+        let Some(start) = self.messages.iter().rposition(|m| m.is_user) else {
+            return 0;
+        };
+        let removed = self.messages.len() - start;
+        self.messages.truncate(start);
+        self.scroll = 0;
         self.update_tokens();
+        removed
+    }
+
+    /// Key pressed while a request runs: typing goes on in the input, Esc or Ctrl+C asks to
+    /// cancel it (true). Ctrl+C only quits once the TUI is idle again
+    pub fn key_while_waiting(&mut self, key: KeyEvent) -> bool {
+        match key.code {
+            KeyCode::Esc => return true,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return true,
+            KeyCode::Char(c) => self.insert_char(c),
+            KeyCode::Backspace => self.delete_char(),
+            KeyCode::Left => self.move_cursor_left(),
+            KeyCode::Right => self.move_cursor_right(),
+            KeyCode::PageUp => for _ in 0..5 { self.scroll_up(); },
+            KeyCode::PageDown => for _ in 0..5 { self.scroll_down(); },
+            _ => {}
+        }
+        false
     }
 
-    fn update_tokens(&mut self) {
-        self.tokens = self.messages.iter()
-            .map(|m| m.content.len() / 4)
-            .sum();
+    /// Recompute the token estimate from the messages and their tool results
+    pub fn update_tokens(&mut self) {
+        self.tokens = self.messages.iter().map(ChatMessage::estimated_tokens).sum();
     }
 
     pub fn scroll_up(&mut self) {
@@ -126,6 +319,18 @@ impl App {
         self.cursor_pos += 1;
     }
 
+    /// Insert a pasted block at the cursor in one go: its line breaks stay in the input
+    /// instead of sending it, and tabs become spaces
+    pub fn insert_text(&mut self, text: &str) {
+        let text = text.replace("\r\n", "\n").replace('\r', "\n").replace('\t', "    ");
+        let byte_pos = self.input.char_indices()
+            .nth(self.cursor_pos)
+            .map(|(i, _)| i)
+            .unwrap_or(self.input.len());
+        self.input.insert_str(byte_pos, &text);
+        self.cursor_pos += text.chars().count();
+    }
+
     pub fn delete_char(&mut self) {
         if self.cursor_pos > 0 && !self.input.is_empty() {
             self.cursor_pos -= 1;
@@ -171,11 +376,163 @@ impl App {
     }
 
     pub fn to_api_messages(&self) -> Vec<Message> {
-        self.messages.iter()
-            .map(|m| Message {
-                role: m.role.clone(),
+        self.to_saved_messages().0
+    }
+
+    /// API messages with the metadata of each one (None for the tool results turns)
+    pub fn to_saved_messages(&self) -> (Vec<Message>, Vec<Option<MessageMeta>>) {
+        let mut messages = Vec::new();
+        let mut metadata = Vec::new();
+        for m in &self.messages {
+            messages.push(Message {
+                role: m.role,
                 content: m.content.clone(),
-            })
-            .collect()
+            });
+            metadata.push(Some(m.meta.clone()));
+            if !m.tool_results.is_empty() {
+                messages.push(Message {
+                    role: Role::User,
+                    content: format!("Résultats des outils:\n{}", m.tool_results.join("\n\n")),
+                });
+                metadata.push(None);
+            }
+        }
+        (messages, metadata)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_long_responses_fold_and_unfold_from_the_latest() {
+        let mut app = App::new(PathBuf::from("/tmp"));
+        app.fold_lines = 3;
+        app.add_user_message("1\n2\n3\n4\n5".to_string());
+        app.add_ai_message("a\nb\nc\nd\ne".to_string());
+        app.add_ai_message("short".to_string());
+        app.add_ai_message("a\nb\nc\nd".to_string());
+
+        assert_eq!(app.folded_lines(&app.messages[0]), 0);
+        assert_eq!(app.folded_lines(&app.messages[1]), 2);
+        assert_eq!(app.folded_lines(&app.messages[3]), 1);
+
+        assert!(app.toggle_fold());
+        assert!(app.messages[3].expanded && !app.messages[1].expanded);
+        assert!(app.toggle_fold());
+        assert!(!app.toggle_fold());
+        assert_eq!(app.folded_lines(&app.messages[3]), 1);
+    }
+
+    #[test]
+    fn test_tool_results_follow_their_response_once() {
+        let mut app = App::new(PathBuf::from("/tmp"));
+        app.add_user_message("Lis main.rs".to_string());
+        app.add_ai_message("<tool_call>read_file</tool_call>".to_string());
+        let tokens = app.tokens;
+        app.attach_tool_results(1, vec!["fn main() {}".to_string(), "ok".to_string()]);
+        app.add_ai_message("Voilà".to_string());
+
+        assert_eq!(app.messages.len(), 3);
+        assert!(app.tokens > tokens);
+        let (messages, metadata) = app.to_saved_messages();
+        let turns: Vec<(Role, &str)> = messages.iter().map(|m| (m.role, m.content.as_str())).collect();
+        assert_eq!(turns, [
+            (Role::User, "Lis main.rs"),
+            (Role::Assistant, "<tool_call>read_file</tool_call>"),
+            (Role::User, "Résultats des outils:\nfn main() {}\n\nok"),
+            (Role::Assistant, "Voilà"),
+        ]);
+        assert!(metadata[2].is_none() && metadata[3].is_some());
+    }
+
+    #[test]
+    fn test_drop_removes_only_the_last_exchange() {
+        let mut app = App::new(PathBuf::from("/tmp"));
+        assert_eq!(app.drop_last_exchange(), 0);
+        app.add_user_message("Première question".to_string());
+        app.add_ai_message("Réponse".to_string());
+        let tokens = app.tokens;
+        app.add_user_message("Question déraillée".to_string());
+        app.add_ai_message("<tool_call>read_file</tool_call>".to_string());
+        app.attach_tool_results(3, vec!["fn main() {}".to_string()]);
+        app.add_ai_message("Hors sujet".to_string());
+
+        assert_eq!(app.drop_last_exchange(), 3);
+        assert_eq!(app.messages.len(), 2);
+        assert_eq!(app.messages[1].content, "Réponse");
+        assert_eq!(app.tokens, tokens);
+    }
+
+    #[test]
+    fn test_resumed_chat_gets_its_tool_results_and_divider_back() {
+        let mut app = App::new(PathBuf::from("/tmp"));
+        app.add_user_message("Lis main.rs".to_string());
+        app.add_ai_message("<tool_call>read_file</tool_call>".to_string());
+        app.attach_tool_results(1, vec!["fn main() {}".to_string()]);
+        app.add_ai_message("Voilà".to_string());
+        let mut chat = SavedChat::new("/tmp");
+        (chat.messages, chat.metadata) = app.to_saved_messages();
+        // The last message was saved before metadata existed
+        chat.metadata.pop();
+
+        let mut resumed = App::new(PathBuf::from("/tmp"));
+        resumed.add_ai_message("Conversation précédente".to_string());
+        resumed.load_saved(&chat);
+        assert_eq!(resumed.messages.len(), 3);
+        assert_eq!(resumed.messages[1].tool_results, ["fn main() {}"]);
+        assert_eq!(resumed.messages[0].meta, app.messages[0].meta);
+        assert_eq!(resumed.messages[2].meta.timestamp, chat.updated_at);
+        assert_eq!(resumed.resumed_at, Some(3));
+        assert_eq!(resumed.tokens, app.tokens);
+        assert_eq!(resumed.to_api_messages().len(), chat.messages.len());
+
+        resumed.clear_messages();
+        assert_eq!(resumed.resumed_at, None);
+    }
+
+    #[test]
+    fn test_ctrl_c_cancels_the_request_without_quitting() {
+        let mut app = App::new(PathBuf::from("/tmp"));
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        assert!(!app.key_while_waiting(key(KeyCode::Char('c'))));
+        assert!(!app.key_while_waiting(key(KeyCode::Char('d'))));
+        assert!(!app.key_while_waiting(key(KeyCode::Backspace)));
+        assert!(!app.key_while_waiting(key(KeyCode::PageUp)));
+        assert_eq!((app.input.as_str(), app.scroll), ("c", 5));
+
+        assert!(app.key_while_waiting(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL)));
+        assert!(app.key_while_waiting(key(KeyCode::Esc)));
+        assert!(!app.should_quit);
+        assert_eq!(app.input, "c");
+    }
+
+    #[test]
+    fn test_comparison_keeps_both_answers() {
+        let mut app = App::new(PathBuf::from("/tmp"));
+        app.fold_lines = 1;
+        app.add_comparison(vec![
+            ("codestral-latest".to_string(), "fn a()\nfn b()".to_string()),
+            ("mistral-large-latest".to_string(), "Use a loop".to_string()),
+        ]);
+
+        let message = &app.messages[0];
+        assert_eq!(message.content, "### codestral-latest\nfn a()\nfn b()\n\n### mistral-large-latest\nUse a loop");
+        assert_eq!(message.meta.model.as_deref(), Some("codestral-latest vs mistral-large-latest"));
+        assert_eq!(app.folded_lines(message), 0);
+    }
+
+    #[test]
+    fn test_paste_lands_in_the_input_intact() {
+        let mut app = App::new(PathBuf::from("/tmp"));
+        app.insert_char('é');
+        app.insert_char(']');
+        app.move_cursor_left();
+        app.insert_text("fn a() {\r\n\tb()\r\n}");
+        assert_eq!(app.input, "éfn a() {\n    b()\n}]");
+        assert_eq!(app.cursor_pos, 19);
+        app.insert_char(';');
+        assert_eq!(app.input, "éfn a() {\n    b()\n};]");
     }
 }
@@ -0,0 +1,86 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Answers to the resume offer that restart the loop
+const YES: &[&str] = &["o", "oui", "y", "yes"];
+
+/// AUTO loop in progress, `.codestral/auto_run.json`: written before each iteration and
+/// removed once the loop ends. Left behind when a request failed (or the TUI was closed),
+/// so the next prompt can offer to send the pending message again
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AutoRun {
+    /// Saved conversation of the loop
+    pub chat_id: Option<String>,
+    /// 1-based
+    pub iteration: usize,
+    /// Last user message, sent by the iteration: the instruction, then "Continue."
+    pub pending: String,
+    /// Why the iteration failed, once it did
+    #[serde(default)]
+    pub error: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+fn path(project_root: &Path) -> PathBuf {
+    project_root.join(crate::project_dir::DIR_NAME).join("auto_run.json")
+}
+
+impl AutoRun {
+    pub fn new(chat_id: Option<String>, iteration: usize, pending: &str) -> Self {
+        Self { chat_id, iteration, pending: pending.to_string(), error: None, updated_at: Utc::now() }
+    }
+
+    pub fn load(project_root: &Path) -> Option<Self> {
+        let content = fs::read_to_string(path(project_root)).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    pub fn save(&self, project_root: &Path) -> Result<(), String> {
+        crate::project_dir::ensure(project_root)?;
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        fs::write(path(project_root), json).map_err(|e| format!("Cannot save AUTO run: {}", e))
+    }
+
+    /// The loop ended (done, cancelled, or not resumed)
+    pub fn clear(project_root: &Path) {
+        let _ = fs::remove_file(path(project_root));
+    }
+
+    /// Offer shown in the conversation, answered by the next prompt
+    pub fn offer(&self) -> String {
+        let cause = self.error.as_deref().map(|e| format!(" ({})", e)).unwrap_or_default();
+        format!(
+            "⏸ Boucle AUTO interrompue à l'itération {}{}.\nReprendre la boucle AUTO ? Répondez « o » pour renvoyer « {} » ; tout autre message l'abandonne.",
+            self.iteration, cause, self.pending.lines().next().unwrap_or_default()
+        )
+    }
+}
+
+/// Whether the answer to the offer resumes the loop
+pub fn accepts(answer: &str) -> bool {
+    YES.contains(&answer.trim().to_lowercase().as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interrupted_run_survives_until_cleared() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(AutoRun::load(dir.path()), None);
+
+        let mut run = AutoRun::new(Some("chat-1".to_string()), 3, "Continue.");
+        run.error = Some("No network: cannot connect to api.mistral.ai".to_string());
+        run.save(dir.path()).unwrap();
+        let loaded = AutoRun::load(dir.path()).unwrap();
+        assert_eq!(loaded, run);
+        assert!(loaded.offer().starts_with("⏸ Boucle AUTO interrompue à l'itération 3 (No network: cannot connect to api.mistral.ai)."));
+
+        assert!(accepts(" Oui ") && accepts("o") && !accepts("non") && !accepts("Corrige le test"));
+        AutoRun::clear(dir.path());
+        assert_eq!(AutoRun::load(dir.path()), None);
+    }
+}
@@ -0,0 +1,114 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use similar::TextDiff;
+
+/// A file changed during the session, with its content before the first change
+pub struct ChangedFile {
+    pub path: PathBuf,
+    /// Path shown to the user (relative to the project)
+    pub display: String,
+    /// Content before the session touched it (None = the file did not exist)
+    pub original: Option<String>,
+    /// Content after the latest change (None = deleted)
+    pub current: Option<String>,
+    pub reverted: bool,
+}
+
+impl ChangedFile {
+    /// Unified diff from the original content to the current one
+    pub fn diff(&self) -> String {
+        let original = self.original.as_deref().unwrap_or("");
+        let current = self.current.as_deref().unwrap_or("");
+        TextDiff::from_lines(original, current)
+            .unified_diff()
+            .header(&self.display, &self.display)
+            .to_string()
+    }
+
+    /// Short status shown in the list
+    pub fn status(&self) -> &'static str {
+        match (&self.original, &self.current) {
+            _ if self.reverted => "annulé",
+            (None, _) => "créé",
+            (_, None) => "supprimé",
+            _ => "modifié",
+        }
+    }
+}
+
+/// Files written or deleted by the AI in this session, so they can be reviewed and reverted
+#[derive(Default)]
+pub struct SessionChanges {
+    pub files: Vec<ChangedFile>,
+}
+
+impl SessionChanges {
+    /// Record a change; only the first snapshot of a file is kept as its original
+    pub fn record(&mut self, project_root: &Path, path: &Path, before: Option<String>, after: Option<String>) {
+        if let Some(file) = self.files.iter_mut().find(|f| f.path == path) {
+            file.current = after;
+            file.reverted = false;
+            return;
+        }
+        let display = path.strip_prefix(project_root).unwrap_or(path).to_string_lossy().to_string();
+        self.files.push(ChangedFile {
+            path: path.to_path_buf(),
+            display,
+            original: before,
+            current: after,
+            reverted: false,
+        });
+    }
+
+    /// Put a file back in its state before the session: restore its content, or remove it if it was created
+    pub fn revert(&mut self, index: usize) -> Result<(), String> {
+        let file = self.files.get_mut(index).ok_or("Fichier inconnu")?;
+        match &file.original {
+            Some(content) => {
+                if let Some(parent) = file.path.parent() {
+                    fs::create_dir_all(parent).map_err(|e| format!("Cannot create {}: {}", parent.display(), e))?;
+                }
+                fs::write(&file.path, content).map_err(|e| format!("Failed to restore {}: {}", file.display, e))?;
+            }
+            None if file.path.exists() => {
+                fs::remove_file(&file.path).map_err(|e| format!("Failed to remove {}: {}", file.display, e))?;
+            }
+            None => {}
+        }
+        file.current = file.original.clone();
+        file.reverted = true;
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_revert_restores_first_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let edited = dir.path().join("src/lib.rs");
+        let created = dir.path().join("new.rs");
+        fs::create_dir_all(edited.parent().unwrap()).unwrap();
+        fs::write(&edited, "v3").unwrap();
+        fs::write(&created, "new").unwrap();
+
+        let mut changes = SessionChanges::default();
+        changes.record(dir.path(), &edited, Some("v1".to_string()), Some("v2".to_string()));
+        changes.record(dir.path(), &edited, Some("v2".to_string()), Some("v3".to_string()));
+        changes.record(dir.path(), &created, None, Some("new".to_string()));
+        assert_eq!(changes.files.len(), 2);
+        assert_eq!(changes.files[0].display, "src/lib.rs");
+
+        changes.revert(0).unwrap();
+        changes.revert(1).unwrap();
+        assert_eq!(fs::read_to_string(&edited).unwrap(), "v1");
+        assert!(!created.exists());
+        assert_eq!(changes.files[1].status(), "annulé");
+    }
+}
@@ -0,0 +1,130 @@
+use std::sync::OnceLock;
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{self, FontStyle, Theme, ThemeSet};
+use syntect::parsing::{SyntaxReference, SyntaxSet};
+
+/// Theme of the code previews, among syntect's bundled ones
+const THEME: &str = "base16-ocean.dark";
+
+/// Bundled grammars, loaded once for the whole session
+fn syntax_set() -> &'static SyntaxSet {
+    static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+    SYNTAXES.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+fn theme() -> &'static Theme {
+    static THEMES: OnceLock<ThemeSet> = OnceLock::new();
+    &THEMES.get_or_init(ThemeSet::load_defaults).themes[THEME]
+}
+
+/// Grammar of a file extension; TypeScript has none bundled and reads as JavaScript
+fn syntax(extension: &str) -> &'static SyntaxReference {
+    let syntaxes = syntax_set();
+    let extension = match extension {
+        "ts" | "tsx" | "mts" | "cts" => "js",
+        other => other,
+    };
+    syntaxes.find_syntax_by_extension(extension).unwrap_or_else(|| syntaxes.find_syntax_plain_text())
+}
+
+/// Foreground color and font style of a syntect token; the background stays the terminal's
+fn to_style(style: highlighting::Style) -> Style {
+    let color = style.foreground;
+    let mut converted = Style::default().fg(Color::Rgb(color.r, color.g, color.b));
+    if style.font_style.contains(FontStyle::BOLD) {
+        converted = converted.add_modifier(Modifier::BOLD);
+    }
+    if style.font_style.contains(FontStyle::ITALIC) {
+        converted = converted.add_modifier(Modifier::ITALIC);
+    }
+    if style.font_style.contains(FontStyle::UNDERLINE) {
+        converted = converted.add_modifier(Modifier::UNDERLINED);
+    }
+    converted
+}
+
+/// Extension `CodeHighlighter::new` expects for the info string of a fenced code block
+/// ("rust", "py title=x", "tsx", "src/main.rs"...)
+pub fn fence_extension(info: &str) -> String {
+    let language = info.trim().split(|c: char| c.is_whitespace() || c == ',' || c == '{').next().unwrap_or("").to_lowercase();
+    match language.as_str() {
+        "rust" => "rs",
+        "python" | "python3" => "py",
+        "javascript" | "jsx" | "node" | "mjs" => "js",
+        "typescript" | "tsx" => "ts",
+        "bash" | "shell" | "zsh" | "console" => "sh",
+        "ruby" => "rb",
+        "golang" => "go",
+        "c++" => "cpp",
+        "kotlin" => "kt",
+        "markdown" => "md",
+        "" | "text" | "plain" | "plaintext" => "txt",
+        _ => return language.rsplit('.').next().unwrap_or("").to_string(),
+    }
+    .to_string()
+}
+
+/// Colors the lines of one piece of code in order, carrying strings and comments over lines
+pub struct CodeHighlighter {
+    lines: HighlightLines<'static>,
+}
+
+impl CodeHighlighter {
+    pub fn new(extension: &str) -> Self {
+        Self { lines: HighlightLines::new(syntax(extension), theme()) }
+    }
+
+    /// Next line of the code, colored; left plain if the grammar fails on it
+    pub fn line(&mut self, line: &str) -> Line<'static> {
+        let text = format!("{}\n", line);
+        match self.lines.highlight_line(&text, syntax_set()) {
+            Ok(tokens) => Line::from(
+                tokens.into_iter()
+                    .map(|(style, token)| (style, token.trim_end_matches('\n')))
+                    .filter(|(_, token)| !token.is_empty())
+                    .map(|(style, token)| Span::styled(token.to_string(), to_style(style)))
+                    .collect::<Vec<_>>(),
+            ),
+            Err(_) => Line::from(line.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Color of the span holding `text`
+    fn color_of(line: &Line, text: &str) -> Option<Color> {
+        line.spans.iter().find(|span| span.content.contains(text)).and_then(|span| span.style.fg)
+    }
+
+    #[test]
+    fn test_highlights_keywords_strings_and_comments() {
+        let mut rust = CodeHighlighter::new("rs");
+        let line = rust.line("pub fn a<'b>() { \"x // y\" } // done");
+        assert_eq!(line.spans.iter().map(|span| span.content.as_ref()).collect::<String>(), "pub fn a<'b>() { \"x // y\" } // done");
+        let comment = color_of(&line, "done");
+        assert!(comment.is_some());
+        assert_ne!(color_of(&line, "fn"), comment);
+        assert_ne!(color_of(&line, "x // y"), comment);
+        assert_ne!(color_of(&line, "x // y"), color_of(&line, "fn"));
+
+        // A block comment opened on one line is still a comment on the next
+        let mut typescript = CodeHighlighter::new("ts");
+        let open = typescript.line("let x = 1; /* start");
+        let close = typescript.line("end */ return");
+        assert_eq!(color_of(&close, "end"), color_of(&open, "start"));
+        assert_ne!(color_of(&close, "return"), color_of(&close, "end"));
+
+        assert_eq!((fence_extension("rust"), fence_extension("Python title=x"), fence_extension("src/App.tsx")), ("rs".to_string(), "py".to_string(), "tsx".to_string()));
+        assert_eq!(fence_extension(""), "txt");
+
+        let line = CodeHighlighter::new("py").line("x = 'a' # note");
+        assert!(color_of(&line, "note").is_some());
+        assert_ne!(color_of(&line, "note"), color_of(&line, "x"));
+        assert_eq!(CodeHighlighter::new("unknown").line("plain text").spans.len(), 1);
+    }
+}
@@ -0,0 +1,272 @@
+use crate::persistent_index::is_symbol_line;
+use crate::symbols::{is_identifier, Symbol};
+
+/// Files up to this size are injected whole
+pub const MAX_FILE_CHARS: usize = 5000;
+/// Total size of everything injected in one message (~5000 tokens)
+pub const INJECTION_BUDGET_CHARS: usize = 20_000;
+/// Definitions injected per mentioned symbol; a bare name defined more often is ignored as ambiguous
+pub const MAX_SYMBOL_MATCHES: usize = 3;
+
+/// File mentioned by the user, with its indexed content
+pub struct MentionedFile {
+    pub relative_path: String,
+    pub extension: String,
+    pub content: String,
+}
+
+/// Symbol mentioned by the user, with the lines of its definition
+pub struct MentionedSymbol {
+    pub qualified_name: String,
+    pub relative_path: String,
+    pub extension: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub code: String,
+}
+
+impl MentionedSymbol {
+    pub fn new(relative_path: &str, symbol: &Symbol, content: &str) -> Self {
+        let code = content.lines()
+            .skip(symbol.start_line.saturating_sub(1))
+            .take(symbol.end_line + 1 - symbol.start_line)
+            .collect::<Vec<_>>()
+            .join("\n");
+        Self {
+            qualified_name: symbol.qualified_name(),
+            relative_path: relative_path.to_string(),
+            extension: relative_path.rsplit('.').next().unwrap_or("").to_string(),
+            start_line: symbol.start_line,
+            end_line: symbol.end_line,
+            code,
+        }
+    }
+}
+
+/// Symbols referenced in the input, as (parent, name): `Type::method` or `Type.method`
+/// (call parentheses allowed), and bare names in backticks like `send_message`
+pub fn referenced_symbols(input: &str) -> Vec<(Option<&str>, &str)> {
+    let mut found = Vec::new();
+
+    for raw in input.split_whitespace() {
+        let backticked = raw.trim_start_matches(['(', '[', '"', '\'']).starts_with('`');
+        let word = raw
+            .trim_start_matches(['(', '[', '"', '\'', '`'])
+            .trim_end_matches(['.', ',', ';', ':', '!', '?', ')', ']', '"', '\'', '`']);
+        // MistralClient::chat(messages) -> MistralClient::chat
+        let word = word.split('(').next().unwrap_or("");
+        if word.contains('/') {
+            continue;
+        }
+
+        let separator = if word.contains("::") { Some("::") } else if word.contains('.') { Some(".") } else { None };
+        let symbol = match separator {
+            Some(separator) => {
+                let mut parts = word.rsplit(separator);
+                let name = parts.next().unwrap_or("");
+                let parent = parts.next().unwrap_or("");
+                (is_identifier(parent) && is_identifier(name)).then_some((Some(parent), name))
+            }
+            None => (backticked && word.len() >= 3 && is_identifier(word)).then_some((None, word)),
+        };
+
+        if let Some(symbol) = symbol {
+            if !found.contains(&symbol) {
+                found.push(symbol);
+            }
+        }
+    }
+
+    found
+}
+
+/// Indexed files explicitly referenced in the input: `@path` (exact path, or a unique
+/// file name / path suffix) or an exact relative path. Bare words like "main" are ignored,
+/// the model can look those up with its tools
+pub fn referenced_paths<'a>(input: &str, indexed: &'a [String]) -> Vec<&'a str> {
+    let mut found: Vec<&'a str> = Vec::new();
+
+    for word in input.split_whitespace() {
+        let word = word
+            .trim_start_matches(['(', '[', '"', '\'', '`'])
+            .trim_end_matches(['.', ',', ';', ':', '!', '?', ')', ']', '"', '\'', '`']);
+
+        let (explicit, path) = match word.strip_prefix('@') {
+            Some(rest) => (true, rest),
+            None => (false, word),
+        };
+        let path = path.trim_start_matches("./");
+        if path.is_empty() {
+            continue;
+        }
+
+        let exact = indexed.iter().find(|p| p.as_str() == path);
+        let matched = match exact {
+            Some(p) => Some(p.as_str()),
+            None if explicit => {
+                let suffix = format!("/{}", path);
+                let mut candidates = indexed.iter().filter(|p| p.ends_with(&suffix));
+                match (candidates.next(), candidates.next()) {
+                    (Some(p), None) => Some(p.as_str()),
+                    _ => None,
+                }
+            }
+            None => None,
+        };
+
+        if let Some(p) = matched {
+            if !found.contains(&p) {
+                found.push(p);
+            }
+        }
+    }
+
+    found
+}
+
+/// Numbered declaration lines of a file (functions, types, classes...)
+pub fn outline(content: &str) -> String {
+    content.lines()
+        .enumerate()
+        .filter(|(_, line)| is_symbol_line(line))
+        .map(|(i, line)| format!("{:>5}: {}", i + 1, line.trim_end()))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// First `max` characters of a string, cut on a char boundary
+fn truncate_chars(content: &str, max: usize) -> &str {
+    match content.char_indices().nth(max) {
+        Some((i, _)) => &content[..i],
+        None => content,
+    }
+}
+
+/// Build the injected context within the budget: mentioned symbols, small files whole, big files
+/// as an outline of their declarations, and a note listing what was shortened or left out
+pub fn build_injection(files: &[MentionedFile], symbols: &[MentionedSymbol], budget: usize) -> String {
+    let mut remaining = budget;
+    let mut injected = Vec::new();
+    let mut notes = Vec::new();
+
+    // Symbols first: they are what the question targets, and much smaller than their files
+    for symbol in symbols {
+        let size = symbol.code.chars().count();
+        let limit = remaining.min(MAX_FILE_CHARS);
+        let location = format!("{}, lignes {}-{}", symbol.relative_path, symbol.start_line, symbol.end_line);
+
+        if size <= limit {
+            remaining -= size;
+            injected.push(format!(
+                "🔎 SYMBOLE DEMANDÉ: {} ({})\n```{}\n{}\n```",
+                symbol.qualified_name, location, symbol.extension, symbol.code
+            ));
+        } else if limit > 0 {
+            let excerpt = truncate_chars(&symbol.code, limit);
+            remaining -= excerpt.chars().count();
+            injected.push(format!(
+                "🔎 SYMBOLE DEMANDÉ (tronqué): {} ({})\n```{}\n{}\n```",
+                symbol.qualified_name, location, symbol.extension, excerpt
+            ));
+            notes.push(format!("- {} ({}): tronqué à {} caractères sur {}", symbol.qualified_name, location, excerpt.chars().count(), size));
+        } else {
+            notes.push(format!("- {} ({}): non inclus (budget de contexte atteint)", symbol.qualified_name, location));
+        }
+    }
+
+    for file in files {
+        let size = file.content.chars().count();
+
+        if size <= MAX_FILE_CHARS && size <= remaining {
+            remaining -= size;
+            injected.push(format!(
+                "📁 FICHIER DEMANDÉ: {}\n```{}\n{}\n```",
+                file.relative_path, file.extension, file.content
+            ));
+            continue;
+        }
+
+        let symbols = outline(&file.content);
+        let symbols_size = symbols.chars().count();
+        if !symbols.is_empty() && symbols_size <= remaining {
+            remaining -= symbols_size;
+            injected.push(format!(
+                "📁 FICHIER DEMANDÉ (déclarations seulement): {}\n```{}\n{}\n```",
+                file.relative_path, file.extension, symbols
+            ));
+            notes.push(format!("- {} ({} lignes): seules les déclarations sont incluses", file.relative_path, file.content.lines().count()));
+        } else if remaining > 0 {
+            let excerpt = truncate_chars(&file.content, remaining.min(MAX_FILE_CHARS));
+            remaining -= excerpt.chars().count();
+            injected.push(format!(
+                "📁 FICHIER DEMANDÉ (tronqué): {}\n```{}\n{}\n```",
+                file.relative_path, file.extension, excerpt
+            ));
+            notes.push(format!("- {}: tronqué à {} caractères sur {}", file.relative_path, excerpt.chars().count(), size));
+        } else {
+            notes.push(format!("- {}: non inclus (budget de contexte atteint)", file.relative_path));
+        }
+    }
+
+    if injected.is_empty() && notes.is_empty() {
+        return String::new();
+    }
+
+    let mut result = format!("Voici le contenu des fichiers mentionnés:\n\n{}", injected.join("\n\n"));
+    if !notes.is_empty() {
+        result.push_str(&format!(
+            "\n\n⚠️ CONTENU PARTIEL:\n{}\nUtilise l'outil read_file pour lire les parties manquantes.",
+            notes.join("\n")
+        ));
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(path: &str, content: String) -> MentionedFile {
+        MentionedFile { relative_path: path.to_string(), extension: "rs".to_string(), content }
+    }
+
+    #[test]
+    fn test_only_explicit_references_are_detected() {
+        let indexed: Vec<String> = ["src/main.rs", "lib/main.rs", "web/src/main.ts", "src/tui/app.rs", "Cargo.toml"]
+            .iter().map(|s| s.to_string()).collect();
+
+        assert!(referenced_paths("fix the main loop in main.rs", &indexed).is_empty());
+        assert_eq!(referenced_paths("see (src/main.rs) and `Cargo.toml`.", &indexed), vec!["src/main.rs", "Cargo.toml"]);
+        assert_eq!(referenced_paths("@app.rs @main.ts @main.rs @tui/app.rs", &indexed), vec!["src/tui/app.rs", "web/src/main.ts"]);
+    }
+
+    #[test]
+    fn test_symbol_references() {
+        assert_eq!(
+            referenced_symbols("why does MistralClient::chat(messages) fail, and `send_message`? see main.rs, `x`"),
+            vec![(Some("MistralClient"), "chat"), (None, "send_message"), (Some("main"), "rs")]
+        );
+
+        let symbol = Symbol { name: "chat".to_string(), parent: Some("Client".to_string()), kind: "fn".to_string(), start_line: 2, end_line: 3 };
+        let mentioned = MentionedSymbol::new("src/client.rs", &symbol, "struct Client;\nfn chat() {\n}\nfn other() {}\n");
+        let result = build_injection(&[], &[mentioned], 1000);
+        assert!(result.contains("SYMBOLE DEMANDÉ: Client::chat (src/client.rs, lignes 2-3)\n```rs\nfn chat() {\n}\n```"));
+        assert!(!result.contains("other"));
+    }
+
+    #[test]
+    fn test_budget_prefers_outline_and_reports_truncation() {
+        let big = (0..400).map(|i| format!("pub fn f{}() {{\n    let x = {};\n}}\n", i, i)).collect::<String>();
+        let files = vec![
+            file("src/small.rs", "fn main() {}".to_string()),
+            file("src/big.rs", big),
+            file("src/other.rs", "x".repeat(3000)),
+        ];
+
+        let result = build_injection(&files, &[], 12_000);
+        assert!(result.contains("fn main() {}"));
+        assert!(result.contains("déclarations seulement): src/big.rs"));
+        assert!(!result.contains("let x = 1;"));
+        assert!(result.contains("src/other.rs: tronqué à 2899 caractères sur 3000"));
+    }
+}
@@ -0,0 +1,263 @@
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use crate::tui::highlight::{fence_extension, CodeHighlighter};
+
+/// First `shown` lines of an assistant message, rendered: headings, bold, italic, inline code,
+/// links, lists and quotes are styled and word-wrapped to `max_width`; fenced code blocks are
+/// syntax-colored after their language and left unwrapped
+pub fn render(content: &str, shown: usize, max_width: usize) -> Vec<Line<'static>> {
+    let dim = Style::default().fg(Color::DarkGray);
+    // Highlighter of the open code block
+    let mut code: Option<CodeHighlighter> = None;
+    let mut lines = Vec::new();
+    for line in content.lines().take(shown) {
+        let trimmed = line.trim_start();
+        if let Some(info) = trimmed.strip_prefix("```") {
+            code = match code {
+                Some(_) => None,
+                None => Some(CodeHighlighter::new(&fence_extension(info.trim_start_matches('`')))),
+            };
+            lines.push(Line::from(Span::styled(line.to_string(), dim)));
+            continue;
+        }
+        if let Some(highlighter) = code.as_mut() {
+            lines.push(highlighter.line(line));
+            continue;
+        }
+        if trimmed.is_empty() {
+            lines.push(Line::from(""));
+            continue;
+        }
+
+        let indent = " ".repeat(line.len() - trimmed.len());
+        if let Some((level, title)) = heading(trimmed) {
+            let style = match level {
+                1 => Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+                2 => Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+                _ => Style::default().add_modifier(Modifier::BOLD),
+            };
+            lines.extend(wrap(inline(title, style), "", "", max_width));
+        } else if is_rule(trimmed) {
+            lines.push(Line::from(Span::styled("─".repeat(max_width.min(60)), dim)));
+        } else if let Some(quote) = trimmed.strip_prefix('>') {
+            let style = Style::default().fg(Color::Gray).add_modifier(Modifier::ITALIC);
+            let bar = format!("{}│ ", indent);
+            lines.extend(wrap(inline(quote.trim_start(), style), &bar, &bar, max_width));
+        } else if let Some((marker, item)) = list_item(trimmed) {
+            let first = format!("{}{} ", indent, marker);
+            let rest = " ".repeat(first.chars().count());
+            lines.extend(wrap(inline(item, Style::default()), &first, &rest, max_width));
+        } else {
+            lines.extend(wrap(inline(trimmed, Style::default()), &indent, &indent, max_width));
+        }
+    }
+    lines
+}
+
+/// `## Title` as (2, "Title")
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    let title = line[level..].strip_prefix(' ')?;
+    (1..=6).contains(&level).then_some((level, title.trim()))
+}
+
+/// `---`, `***` or `___`
+fn is_rule(line: &str) -> bool {
+    let line = line.trim_end();
+    line.len() >= 3 && ['-', '*', '_'].iter().any(|c| line.chars().all(|x| x == *c))
+}
+
+/// Bullet ("•") or number ("2.") of a list item, and its text
+fn list_item(line: &str) -> Option<(String, &str)> {
+    for bullet in ["- ", "* ", "+ "] {
+        if let Some(item) = line.strip_prefix(bullet) {
+            return Some(("•".to_string(), item));
+        }
+    }
+    let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+    if digits == 0 || digits > 3 {
+        return None;
+    }
+    let item = line[digits..].strip_prefix(". ").or_else(|| line[digits..].strip_prefix(") "))?;
+    Some((format!("{}.", &line[..digits]), item))
+}
+
+/// Inline markup: **bold**, __bold__, *italic*, ~~struck~~, `code` and [links](url).
+/// A marker without its closing one is kept as text
+fn inline(text: &str, base: Style) -> Vec<Span<'static>> {
+    let code = Style::default().fg(Color::Yellow).bg(Color::Rgb(40, 40, 40));
+    let mut spans = Vec::new();
+    let mut plain = String::new();
+    let (mut bold, mut italic, mut struck) = (false, false, false);
+    let style = |bold: bool, italic: bool, struck: bool| {
+        let mut style = base;
+        if bold {
+            style = style.add_modifier(Modifier::BOLD);
+        }
+        if italic {
+            style = style.add_modifier(Modifier::ITALIC);
+        }
+        if struck {
+            style = style.add_modifier(Modifier::CROSSED_OUT);
+        }
+        style
+    };
+    let flush = |plain: &mut String, spans: &mut Vec<Span<'static>>, style: Style| {
+        if !plain.is_empty() {
+            spans.push(Span::styled(std::mem::take(plain), style));
+        }
+    };
+
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        let current = style(bold, italic, struck);
+        if c == '`' {
+            if let Some(end) = rest[1..].find('`') {
+                flush(&mut plain, &mut spans, current);
+                spans.push(Span::styled(rest[1..1 + end].to_string(), code));
+                rest = &rest[end + 2..];
+                continue;
+            }
+        } else if rest.starts_with("**") || rest.starts_with("__") || rest.starts_with("~~") {
+            let marker = &rest[..2];
+            let on = if marker == "~~" { struck } else { bold };
+            if on || rest[2..].contains(marker) {
+                flush(&mut plain, &mut spans, current);
+                if marker == "~~" { struck = !struck } else { bold = !bold }
+                rest = &rest[2..];
+                continue;
+            }
+        } else if c == '*' {
+            // Opening only before a word, so "a * b" stays a product
+            let opens = rest[1..].starts_with(|n: char| !n.is_whitespace()) && rest[1..].contains('*');
+            if italic || opens {
+                flush(&mut plain, &mut spans, current);
+                italic = !italic;
+                rest = &rest[1..];
+                continue;
+            }
+        } else if c == '[' {
+            if let Some((label, url, len)) = link(rest) {
+                flush(&mut plain, &mut spans, current);
+                spans.push(Span::styled(label.to_string(), current.fg(Color::Blue).add_modifier(Modifier::UNDERLINED)));
+                spans.push(Span::styled(format!(" ({})", url), Style::default().fg(Color::DarkGray)));
+                rest = &rest[len..];
+                continue;
+            }
+        }
+        plain.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    flush(&mut plain, &mut spans, style(bold, italic, struck));
+    spans
+}
+
+/// `[label](url)` at the start of `text`: (label, url, length in bytes)
+fn link(text: &str) -> Option<(&str, &str, usize)> {
+    let close = text.find("](")?;
+    let end = text[close + 2..].find(')')? + close + 2;
+    let (label, url) = (&text[1..close], &text[close + 2..end]);
+    (!label.is_empty() && !label.contains(']') && !url.contains(' ')).then_some((label, url, end + 1))
+}
+
+/// Word-wrap styled text to `max_width` columns, `first` prefixing the first line and `rest` the others
+fn wrap(spans: Vec<Span<'static>>, first: &str, rest: &str, max_width: usize) -> Vec<Line<'static>> {
+    let width = |s: &str| s.chars().count();
+    let total: usize = spans.iter().map(|s| width(&s.content)).sum();
+    if width(first) + total <= max_width {
+        let mut line = vec![Span::raw(first.to_string())];
+        line.extend(spans);
+        return vec![Line::from(line)];
+    }
+
+    // Words made of the pieces of one or more spans, e.g. "**un**jour"
+    let mut words: Vec<Vec<Span<'static>>> = Vec::new();
+    let mut word: Vec<Span<'static>> = Vec::new();
+    for span in spans {
+        let style = span.style;
+        let mut pieces = span.content.split(' ').peekable();
+        while let Some(piece) = pieces.next() {
+            if !piece.is_empty() {
+                word.push(Span::styled(piece.to_string(), style));
+            }
+            if pieces.peek().is_some() && !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+
+    let mut lines = Vec::new();
+    let mut line = vec![Span::raw(first.to_string())];
+    let mut used = width(first);
+    let mut empty = true;
+    for word in words {
+        let word_width: usize = word.iter().map(|s| width(&s.content)).sum();
+        if !empty && used + 1 + word_width > max_width {
+            lines.push(Line::from(std::mem::take(&mut line)));
+            line.push(Span::raw(rest.to_string()));
+            used = width(rest);
+            empty = true;
+        }
+        if !empty {
+            // The space inside a bold or code run keeps its style
+            let previous = line.last().map(|s| s.style).unwrap_or_default();
+            let space = if previous == word[0].style { previous } else { Style::default() };
+            line.push(Span::styled(" ", space));
+            used += 1;
+        }
+        used += word_width;
+        line.extend(word);
+        empty = false;
+    }
+    lines.push(Line::from(line));
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(lines: &[Line]) -> Vec<String> {
+        lines.iter().map(|l| l.spans.iter().map(|s| s.content.as_ref()).collect()).collect()
+    }
+
+    #[test]
+    fn test_code_blocks_are_highlighted_not_wrapped() {
+        let content = "Voici le correctif pour la fonction principale:\n```rust\npub fn main() { let answer = 42; println!(\"{}\", answer); }\n```\nFin.";
+        let lines = render(content, usize::MAX, 20);
+        let texts = texts(&lines);
+        assert_eq!(texts[..3], ["Voici le correctif", "pour la fonction", "principale:"]);
+        assert_eq!(texts[3], "```rust");
+        assert_eq!(texts[4], "pub fn main() { let answer = 42; println!(\"{}\", answer); }");
+        // `pub` is a keyword, colored apart from the space after it
+        assert!(matches!(lines[4].spans[0].style.fg, Some(Color::Rgb(..))));
+        assert_ne!(lines[4].spans[0].style.fg, lines[4].spans[1].style.fg);
+        assert_eq!(texts[5..], ["```", "Fin."]);
+        assert_eq!(render(content, 2, 80).len(), 2);
+    }
+
+    #[test]
+    fn test_markdown_is_styled_and_wrapped() {
+        let content = "## Étapes\n1. Lire **le fichier** `main.rs` puis le corriger\n- un *point*\n> note\n---\nvoir [la doc](https://docs.rs) ou 2 * 3";
+        let lines = render(content, usize::MAX, 30);
+        assert_eq!(texts(&lines), [
+            "Étapes",
+            "1. Lire le fichier main.rs",
+            "   puis le corriger",
+            "• un point",
+            "│ note",
+            &"─".repeat(30),
+            "voir la doc (https://docs.rs)",
+            "ou 2 * 3",
+        ]);
+        assert!(lines[0].spans[1].style.add_modifier.contains(Modifier::BOLD));
+        let bold = lines[1].spans.iter().find(|s| s.content == "le").unwrap();
+        assert!(bold.style.add_modifier.contains(Modifier::BOLD));
+        assert_eq!(lines[1].spans.iter().find(|s| s.content == "main.rs").unwrap().style.fg, Some(Color::Yellow));
+        assert!(lines[3].spans.iter().any(|s| s.content == "point" && s.style.add_modifier.contains(Modifier::ITALIC)));
+        assert_eq!(texts(&render("**pas fermé et snake_case", usize::MAX, 80)), ["**pas fermé et snake_case"]);
+    }
+}
@@ -5,6 +5,7 @@ use std::process::{Child, Command, Stdio};
 use std::sync::{Arc, Mutex};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
+use crate::tools::ToolAccess;
 
 /// MCP Server configuration (matches standard MCP config format)
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -38,8 +39,7 @@ impl McpConfig {
     
     /// Create default configuration file
     pub fn create_default(project_path: &Path) -> std::io::Result<()> {
-        let config_dir = project_path.join(".codestral");
-        std::fs::create_dir_all(&config_dir)?;
+        let config_dir = crate::project_dir::ensure(project_path).map_err(std::io::Error::other)?;
         
         let default_config = McpConfig {
             mcp_servers: HashMap::from([
@@ -64,6 +64,22 @@ pub struct McpTool {
     pub description: Option<String>,
     #[serde(rename = "inputSchema")]
     pub input_schema: Option<Value>,
+    #[serde(default)]
+    pub annotations: Option<McpToolAnnotations>,
+}
+
+/// Behaviour hints declared by the server; only `readOnlyHint` is used (tools allowed in PLAN)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct McpToolAnnotations {
+    #[serde(rename = "readOnlyHint", default)]
+    pub read_only_hint: bool,
+}
+
+impl McpTool {
+    /// Tools without the hint are assumed to modify something
+    pub fn is_read_only(&self) -> bool {
+        self.annotations.as_ref().is_some_and(|a| a.read_only_hint)
+    }
 }
 
 /// Active MCP server process
@@ -272,6 +288,11 @@ impl McpManager {
         started
     }
     
+    /// Register a server started elsewhere (e.g. on the startup screen)
+    pub fn add_server(&mut self, server: McpServer) {
+        self.servers.push(server);
+    }
+    
     /// Get all available tools from all servers
     pub fn get_all_tools(&self) -> Vec<(String, McpTool)> {
         let mut all_tools = Vec::new();
@@ -293,18 +314,35 @@ impl McpManager {
         Err(format!("Server '{}' not found", server_name))
     }
     
-    /// Generate tools documentation for system prompt
-    pub fn get_tools_documentation(&self) -> String {
-        if self.servers.is_empty() {
+    /// Whether `mcp_<server>_<tool>` is declared read-only by its server
+    pub fn is_read_only(&self, full_name: &str) -> bool {
+        let Some((server_name, tool_name)) = full_name.strip_prefix("mcp_").and_then(|n| n.split_once('_')) else {
+            return false;
+        };
+        self.servers.iter()
+            .filter(|s| s.name() == server_name)
+            .flat_map(|s| s.get_tools())
+            .any(|t| t.name == tool_name && t.is_read_only())
+    }
+    
+    /// Generate tools documentation for system prompt, limited to the tools the mode allows
+    pub fn get_tools_documentation(&self, access: ToolAccess) -> String {
+        // MCP calls are never previewed: PLAN+ only gets the read-only ones
+        let allowed = |tool: &McpTool| access.allows(!tool.is_read_only(), false);
+        if !self.servers.iter().any(|s| s.get_tools().iter().any(allowed)) {
             return String::new();
         }
         
         let mut doc = String::from("\n## MCP External Tools\n\n");
         
         for server in &self.servers {
+            let tools: Vec<&McpTool> = server.get_tools().iter().filter(|t| allowed(t)).collect();
+            if tools.is_empty() {
+                continue;
+            }
             doc.push_str(&format!("### {} Server\n\n", server.name()));
             
-            for tool in server.get_tools() {
+            for tool in tools {
                 doc.push_str(&format!("#### {}\n", tool.name));
                 if let Some(desc) = &tool.description {
                     doc.push_str(&format!("{}\n", desc));
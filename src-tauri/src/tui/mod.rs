@@ -2,5 +2,14 @@ pub mod app;
 pub mod ui;
 pub mod logo;
 pub mod runner;
-pub mod tools;
 pub mod mcp;
+pub mod recording;
+pub mod replay;
+pub mod question_form;
+pub mod injection;
+pub mod startup;
+pub mod auto_run;
+pub mod changes;
+pub mod highlight;
+pub mod markdown;
+pub mod review;
@@ -0,0 +1,386 @@
+use serde::{Deserialize, Serialize};
+
+const BOOLEAN_CHOICES: [&str; 2] = ["oui", "non"];
+const MAX_COMPLETIONS: usize = 6;
+
+/// Kind of answer expected for a question
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldKind {
+    /// Free text
+    #[default]
+    Text,
+    /// One of `choices`, or a custom answer
+    Choice,
+    /// Any subset of `choices`
+    MultiSelect,
+    /// oui / non
+    Boolean,
+    /// Number, checked against `min`/`max`
+    Number,
+    /// Project file, completed from the index
+    FilePath,
+}
+
+/// One question of the form, as sent by the AI through the `ask_user` tool
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FormField {
+    pub question: String,
+    #[serde(default, rename = "type")]
+    pub kind: FieldKind,
+    #[serde(default)]
+    pub choices: Vec<String>,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    /// Only accept whole numbers
+    #[serde(default)]
+    pub integer: bool,
+    #[serde(default)]
+    pub required: bool,
+}
+
+impl FormField {
+    pub fn text(question: impl Into<String>) -> Self {
+        Self {
+            question: question.into(),
+            kind: FieldKind::Text,
+            choices: Vec::new(),
+            min: None,
+            max: None,
+            integer: false,
+            required: false,
+        }
+    }
+
+    /// Parse the JSON array passed in the `fields` param of `ask_user`
+    pub fn parse_list(json: &str) -> Result<Vec<FormField>, String> {
+        let fields: Vec<FormField> = serde_json::from_str(json.trim())
+            .map_err(|e| format!("Invalid ask_user fields: {}", e))?;
+        if fields.is_empty() {
+            return Err("ask_user needs at least one field".to_string());
+        }
+        for field in &fields {
+            if matches!(field.kind, FieldKind::Choice | FieldKind::MultiSelect) && field.choices.is_empty() {
+                return Err(format!("Field '{}' needs choices", field.question));
+            }
+        }
+        Ok(fields)
+    }
+
+    /// Short hint shown next to the question
+    pub fn hint(&self) -> String {
+        match self.kind {
+            FieldKind::Text => String::new(),
+            FieldKind::Choice => " (↑↓: choix, ou tapez)".to_string(),
+            FieldKind::MultiSelect => " (↑↓: naviguer, Espace: cocher)".to_string(),
+            FieldKind::Boolean => " (↑↓ ou o/n)".to_string(),
+            FieldKind::FilePath => " (↑↓: complétion)".to_string(),
+            FieldKind::Number => match self.range_text() {
+                Some(range) => format!(" (nombre {})", range),
+                None => " (nombre)".to_string(),
+            },
+        }
+    }
+
+    fn range_text(&self) -> Option<String> {
+        match (self.min, self.max) {
+            (Some(min), Some(max)) => Some(format!("{}–{}", min, max)),
+            (Some(min), None) => Some(format!("≥ {}", min)),
+            (None, Some(max)) => Some(format!("≤ {}", max)),
+            (None, None) => None,
+        }
+    }
+
+    /// Whether the field shows a line of options under the answer
+    pub fn has_options_line(&self) -> bool {
+        !matches!(self.kind, FieldKind::Text | FieldKind::Number)
+    }
+}
+
+/// Multi-question form with Tab navigation and typed fields
+pub struct QuestionForm {
+    pub fields: Vec<FormField>,
+    pub selected_choice: Vec<Option<usize>>,  // Highlighted option (None = custom input mode)
+    pub checked: Vec<Vec<bool>>,  // Checked options of multi-select fields
+    pub answers: Vec<String>,
+    /// Text typed by the user, used to filter file completions
+    pub typed: Vec<String>,
+    pub current_field: usize,
+    pub cursor_pos: usize,
+    pub in_choice_mode: bool,  // true = selecting from choices, false = typing custom
+    /// Files offered for completion in file-path fields
+    pub files: Vec<String>,
+    /// Validation error shown at the bottom of the form
+    pub error: Option<String>,
+}
+
+impl QuestionForm {
+    pub fn new(fields: Vec<FormField>, files: Vec<String>) -> Self {
+        let count = fields.len();
+        let checked = fields.iter().map(|f| vec![false; f.choices.len()]).collect();
+        let mut form = Self {
+            fields,
+            selected_choice: vec![None; count],
+            checked,
+            answers: vec![String::new(); count],
+            typed: vec![String::new(); count],
+            current_field: 0,
+            cursor_pos: 0,
+            in_choice_mode: false,
+            files,
+            error: None,
+        };
+        for i in 0..count {
+            if form.fields[i].kind == FieldKind::Boolean {
+                form.selected_choice[i] = Some(0);
+                form.answers[i] = BOOLEAN_CHOICES[0].to_string();
+            }
+        }
+        form
+    }
+
+    fn field(&self) -> &FormField {
+        &self.fields[self.current_field]
+    }
+
+    /// Options of a field: its choices, oui/non, or file completions
+    pub fn options(&self, index: usize) -> Vec<String> {
+        let field = &self.fields[index];
+        match field.kind {
+            FieldKind::Boolean => BOOLEAN_CHOICES.iter().map(|c| c.to_string()).collect(),
+            FieldKind::FilePath => {
+                let typed = self.typed[index].to_lowercase();
+                self.files.iter()
+                    .filter(|f| f.to_lowercase().contains(&typed))
+                    .take(MAX_COMPLETIONS)
+                    .cloned()
+                    .collect()
+            }
+            _ => field.choices.clone(),
+        }
+    }
+
+    pub fn current_choices(&self) -> Vec<String> {
+        self.options(self.current_field)
+    }
+
+    pub fn has_choices(&self) -> bool {
+        !self.current_choices().is_empty()
+    }
+
+    fn enter_field(&mut self) {
+        self.cursor_pos = self.answers[self.current_field].chars().count();
+        self.in_choice_mode = self.has_choices() && self.selected_choice[self.current_field].is_some();
+    }
+
+    pub fn next_field(&mut self) {
+        if self.current_field < self.fields.len() - 1 {
+            self.current_field += 1;
+            self.enter_field();
+        }
+    }
+
+    pub fn prev_field(&mut self) {
+        if self.current_field > 0 {
+            self.current_field -= 1;
+            self.enter_field();
+        }
+    }
+
+    fn move_selection(&mut self, down: bool) {
+        let choices = self.current_choices();
+        if choices.is_empty() { return; }
+        self.in_choice_mode = true;
+        let last = choices.len() - 1;
+        let selected = &mut self.selected_choice[self.current_field];
+        *selected = match *selected {
+            None => Some(0),
+            Some(i) if down && i < last => Some(i + 1),
+            Some(i) if !down && i > 0 => Some(i - 1),
+            Some(i) => Some(i.min(last)),
+        };
+        // Multi-select only moves the highlight, other kinds take the option as answer
+        if self.field().kind != FieldKind::MultiSelect {
+            if let Some(i) = self.selected_choice[self.current_field] {
+                self.answers[self.current_field] = choices[i].clone();
+                self.cursor_pos = self.answers[self.current_field].chars().count();
+            }
+        }
+    }
+
+    pub fn select_choice_up(&mut self) {
+        self.move_selection(false);
+    }
+
+    pub fn select_choice_down(&mut self) {
+        self.move_selection(true);
+    }
+
+    /// Check or uncheck the highlighted option of a multi-select field
+    pub fn toggle_choice(&mut self) {
+        let field = self.current_field;
+        let index = self.selected_choice[field].unwrap_or(0);
+        if let Some(checked) = self.checked[field].get_mut(index) {
+            *checked = !*checked;
+            self.selected_choice[field] = Some(index);
+        }
+    }
+
+    pub fn insert_char(&mut self, c: char) {
+        self.error = None;
+        match self.field().kind {
+            FieldKind::MultiSelect => {
+                if c == ' ' {
+                    self.toggle_choice();
+                }
+                return;
+            }
+            FieldKind::Boolean => {
+                let choice = match c.to_ascii_lowercase() {
+                    'o' | 'y' => 0,
+                    'n' => 1,
+                    _ => return,
+                };
+                self.selected_choice[self.current_field] = Some(choice);
+                self.answers[self.current_field] = BOOLEAN_CHOICES[choice].to_string();
+                return;
+            }
+            FieldKind::Number if !(c.is_ascii_digit() || matches!(c, '-' | '.' | ',')) => return,
+            _ => {}
+        }
+
+        // Switch to custom input mode
+        self.in_choice_mode = false;
+        self.selected_choice[self.current_field] = None;
+
+        let answer = &mut self.answers[self.current_field];
+        let byte_pos = answer.char_indices()
+            .nth(self.cursor_pos)
+            .map(|(i, _)| i)
+            .unwrap_or(answer.len());
+        answer.insert(byte_pos, c);
+        self.typed[self.current_field] = answer.clone();
+        self.cursor_pos += 1;
+    }
+
+    pub fn delete_char(&mut self) {
+        if matches!(self.field().kind, FieldKind::MultiSelect | FieldKind::Boolean) {
+            return;
+        }
+        if self.cursor_pos > 0 {
+            self.error = None;
+            self.in_choice_mode = false;
+            self.selected_choice[self.current_field] = None;
+
+            self.cursor_pos -= 1;
+            let answer = &mut self.answers[self.current_field];
+            let byte_pos = answer.char_indices()
+                .nth(self.cursor_pos)
+                .map(|(i, _)| i)
+                .unwrap_or(0);
+            answer.remove(byte_pos);
+            self.typed[self.current_field] = answer.clone();
+        }
+    }
+
+    /// Final answer of a field
+    pub fn answer(&self, index: usize) -> String {
+        let field = &self.fields[index];
+        match field.kind {
+            FieldKind::MultiSelect => field.choices.iter()
+                .zip(&self.checked[index])
+                .filter(|(_, checked)| **checked)
+                .map(|(choice, _)| choice.as_str())
+                .collect::<Vec<_>>()
+                .join(", "),
+            _ => self.answers[index].trim().to_string(),
+        }
+    }
+
+    fn validate_field(&self, index: usize) -> Result<(), String> {
+        let field = &self.fields[index];
+        let answer = self.answer(index);
+
+        if answer.is_empty() {
+            return if field.required {
+                Err(format!("« {} » est obligatoire", field.question))
+            } else {
+                Ok(())
+            };
+        }
+
+        if field.kind == FieldKind::Number {
+            let value: f64 = answer.replace(',', ".").parse()
+                .map_err(|_| format!("« {} » doit être un nombre", field.question))?;
+            if field.integer && value.fract() != 0.0 {
+                return Err(format!("« {} » doit être un nombre entier", field.question));
+            }
+            if field.min.map(|min| value < min).unwrap_or(false) || field.max.map(|max| value > max).unwrap_or(false) {
+                return Err(format!("« {} » hors limites ({})", field.question, field.range_text().unwrap_or_default()));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check every field; on error, focus the faulty field and keep the message
+    pub fn validate(&mut self) -> bool {
+        for index in 0..self.fields.len() {
+            if let Err(e) = self.validate_field(index) {
+                self.current_field = index;
+                self.enter_field();
+                self.error = Some(e);
+                return false;
+            }
+        }
+        self.error = None;
+        true
+    }
+
+    pub fn format_responses(&self) -> String {
+        self.fields.iter()
+            .enumerate()
+            .map(|(i, f)| format!("**{}**\n{}", f.question, self.answer(i)))
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_validate_typed_fields() {
+        let fields = FormField::parse_list(r#"[
+            {"question": "Workers", "type": "number", "min": 1, "max": 8, "integer": true, "required": true},
+            {"question": "Features", "type": "multi_select", "choices": ["auth", "api", "ui"]},
+            {"question": "Tests", "type": "boolean"}
+        ]"#).unwrap();
+        let mut form = QuestionForm::new(fields, Vec::new());
+
+        assert!(!form.validate());
+        for c in "12".chars() { form.insert_char(c); }
+        assert!(!form.validate());
+        form.delete_char();
+        assert!(form.validate());
+
+        form.next_field();
+        form.select_choice_down();
+        form.toggle_choice();
+        form.select_choice_down();
+        form.select_choice_down();
+        form.toggle_choice();
+        form.next_field();
+        form.insert_char('n');
+
+        assert_eq!(form.answer(0), "1");
+        assert_eq!(form.answer(1), "auth, ui");
+        assert_eq!(form.answer(2), "non");
+    }
+
+    #[test]
+    fn test_choice_fields_require_choices() {
+        assert!(FormField::parse_list(r#"[{"question": "Which?", "type": "choice"}]"#).is_err());
+    }
+}
@@ -0,0 +1,135 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A single event captured during a TUI session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SessionEvent {
+    /// First line of every recording
+    Start {
+        project_path: String,
+        mode: String,
+        started_at: DateTime<Utc>,
+    },
+    /// Message typed by the user (or injected, e.g. tool results)
+    Prompt { content: String },
+    /// Raw model response with the time it took to arrive
+    Response {
+        content: String,
+        duration_ms: u64,
+        #[serde(default)]
+        model: Option<String>,
+    },
+    /// Tool invoked by the model and its result
+    ToolCall {
+        name: String,
+        params: std::collections::HashMap<String, String>,
+        success: bool,
+        output: String,
+    },
+    /// File change proposed by the model (plain unified diff)
+    Diff { path: String, diff: String, applied: bool },
+    /// API failure after all retries
+    Error { message: String },
+    /// Mode switch during the session
+    Mode { mode: String },
+}
+
+/// Event with its offset from the start of the recording
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedEvent {
+    pub offset_ms: u64,
+    #[serde(flatten)]
+    pub event: SessionEvent,
+}
+
+/// Appends session events to a JSON Lines file as they happen
+pub struct SessionRecorder {
+    path: PathBuf,
+    file: File,
+    started: Instant,
+}
+
+impl SessionRecorder {
+    /// Start a new recording at the given path
+    pub fn start(path: &Path, project_path: &Path, mode: &str) -> Result<Self, String> {
+        // Recordings hold the prompts and responses: .codestral/.gitignore keeps them out of commits
+        crate::project_dir::ensure(project_path)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Cannot create recording directory: {}", e))?;
+        }
+
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(path)
+            .map_err(|e| format!("Cannot create recording {}: {}", path.display(), e))?;
+
+        let mut recorder = Self {
+            path: path.to_path_buf(),
+            file,
+            started: Instant::now(),
+        };
+
+        recorder.record(SessionEvent::Start {
+            project_path: project_path.to_string_lossy().to_string(),
+            mode: mode.to_string(),
+            started_at: Utc::now(),
+        });
+
+        Ok(recorder)
+    }
+
+    /// Default location for a new recording: .codestral/sessions/session-<timestamp>.jsonl
+    pub fn default_path(project_path: &Path) -> PathBuf {
+        project_path
+            .join(".codestral")
+            .join("sessions")
+            .join(format!("session-{}.jsonl", Utc::now().format("%Y%m%d-%H%M%S")))
+    }
+
+    /// Append an event (errors are ignored so recording never breaks the session)
+    pub fn record(&mut self, event: SessionEvent) {
+        let recorded = RecordedEvent {
+            offset_ms: self.started.elapsed().as_millis() as u64,
+            event,
+        };
+        if let Ok(line) = serde_json::to_string(&recorded) {
+            let _ = writeln!(self.file, "{}", line);
+            let _ = self.file.flush();
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Load all events from a recording file
+pub fn load_recording(path: &Path) -> Result<Vec<RecordedEvent>, String> {
+    let file = File::open(path)
+        .map_err(|e| format!("Cannot open recording {}: {}", path.display(), e))?;
+
+    let mut events = Vec::new();
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(|e| format!("Read error: {}", e))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let event: RecordedEvent = serde_json::from_str(&line)
+            .map_err(|e| format!("Invalid event at line {}: {}", i + 1, e))?;
+        events.push(event);
+    }
+
+    if events.is_empty() {
+        return Err("Recording is empty".to_string());
+    }
+
+    Ok(events)
+}
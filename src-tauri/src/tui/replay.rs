@@ -0,0 +1,204 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use crossterm::{
+    event::{self, Event, KeyCode},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::Paragraph,
+    Terminal,
+};
+use crate::mode::ChatMode;
+use crate::mistral_client::Role;
+use crate::tui::app::{App, ChatMessage};
+use crate::tui::recording::{load_recording, RecordedEvent, SessionEvent};
+use crate::tui::ui;
+
+/// Steps through a recorded session inside the TUI
+pub struct ReplayViewer {
+    events: Vec<RecordedEvent>,
+    position: usize,
+    app: App,
+}
+
+impl ReplayViewer {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let events = load_recording(path)?;
+
+        let project_path = match &events[0].event {
+            SessionEvent::Start { project_path, .. } => PathBuf::from(project_path),
+            _ => PathBuf::from(path.parent().unwrap_or(Path::new("."))),
+        };
+
+        let mut viewer = Self {
+            events,
+            position: 0,
+            app: App::new(project_path),
+        };
+        viewer.rebuild();
+        Ok(viewer)
+    }
+
+    /// Rebuild the chat state from the first event up to the current position
+    fn rebuild(&mut self) {
+        self.app.messages.clear();
+        let started_at = match &self.events[0].event {
+            SessionEvent::Start { started_at, .. } => Some(*started_at),
+            _ => None,
+        };
+
+        for recorded in &self.events[..=self.position] {
+            let count = self.app.messages.len();
+            match &recorded.event {
+                SessionEvent::Start { mode, .. } | SessionEvent::Mode { mode } => {
+                    self.app.mode = parse_mode(mode);
+                }
+                SessionEvent::Prompt { content } => {
+                    self.app.messages.push(ChatMessage::new(Role::User, content.clone()));
+                }
+                SessionEvent::Response { content, duration_ms, model } => {
+                    let mut message = ChatMessage::new(
+                        Role::Assistant,
+                        format!("{}\n⏱ {:.1}s", content, *duration_ms as f64 / 1000.0),
+                    );
+                    message.meta.model = model.clone();
+                    self.app.messages.push(message);
+                }
+                SessionEvent::ToolCall { name, params, success, output } => {
+                    let mut keys: Vec<_> = params.iter().collect();
+                    keys.sort();
+                    let params_text = keys.iter()
+                        .map(|(k, v)| format!("{}={}", k, v.lines().next().unwrap_or("")))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    let status = if *success { "✓" } else { "✗" };
+                    self.app.messages.push(ChatMessage::new(
+                        Role::Assistant,
+                        format!("🔧 {} {}({})\n{}", status, name, params_text, output),
+                    ));
+                }
+                SessionEvent::Diff { path, diff, applied } => {
+                    let status = if *applied { "appliqué" } else { "non appliqué" };
+                    self.app.messages.push(ChatMessage::new(Role::Assistant, format!("📄 {} ({})\n{}", path, status, diff)));
+                }
+                SessionEvent::Error { message } => {
+                    self.app.messages.push(ChatMessage::new(Role::Assistant, format!("❌ {}", message)));
+                }
+            }
+
+            // Date the message from the recording rather than now
+            if let (Some(started), Some(message)) = (started_at, self.app.messages.get_mut(count)) {
+                message.meta.timestamp = started + chrono::Duration::milliseconds(recorded.offset_ms as i64);
+            }
+        }
+
+        self.app.scroll = 0;
+        self.app.update_tokens();
+    }
+
+    fn next(&mut self) {
+        if self.position + 1 < self.events.len() {
+            self.position += 1;
+            self.rebuild();
+        }
+    }
+
+    fn prev(&mut self) {
+        if self.position > 0 {
+            self.position -= 1;
+            self.rebuild();
+        }
+    }
+
+    pub fn run(&mut self) -> Result<(), String> {
+        enable_raw_mode().map_err(|e| e.to_string())?;
+        let mut stdout = io::stdout();
+        execute!(stdout, EnterAlternateScreen).map_err(|e| e.to_string())?;
+        let backend = CrosstermBackend::new(stdout);
+        let mut terminal = Terminal::new(backend).map_err(|e| e.to_string())?;
+
+        let result = self.run_loop(&mut terminal);
+
+        disable_raw_mode().map_err(|e| e.to_string())?;
+        execute!(terminal.backend_mut(), LeaveAlternateScreen).map_err(|e| e.to_string())?;
+        terminal.show_cursor().map_err(|e| e.to_string())?;
+
+        result
+    }
+
+    fn run_loop(&mut self, terminal: &mut Terminal<CrosstermBackend<io::Stdout>>) -> Result<(), String> {
+        loop {
+            terminal.draw(|frame| {
+                ui::draw(frame, &self.app);
+
+                // Replace the status bar with replay controls
+                let area = frame.area();
+                let status_area = Rect {
+                    x: area.x,
+                    y: area.y + area.height.saturating_sub(1),
+                    width: area.width,
+                    height: 1,
+                };
+                let offset = self.events[self.position].offset_ms as f64 / 1000.0;
+                let status = Line::from(vec![
+                    Span::styled(" ▶ REPLAY ", Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD)),
+                    Span::raw(format!("{}/{}", self.position + 1, self.events.len())),
+                    Span::styled(" │ ", Style::default().fg(Color::DarkGray)),
+                    Span::raw(format!("+{:.1}s", offset)),
+                    Span::styled(" │ ←/→: étape │ Home/End │ PgUp/PgDn: scroll │ q: quitter", Style::default().fg(Color::DarkGray)),
+                ]);
+                frame.render_widget(
+                    Paragraph::new(status).style(Style::default().bg(Color::Rgb(30, 30, 30))),
+                    status_area,
+                );
+            }).map_err(|e| e.to_string())?;
+
+            if event::poll(Duration::from_millis(100)).map_err(|e| e.to_string())? {
+                if let Event::Key(key) = event::read().map_err(|e| e.to_string())? {
+                    match key.code {
+                        KeyCode::Char('q') | KeyCode::Esc => break,
+                        KeyCode::Right | KeyCode::Char(' ') | KeyCode::Char('n') => self.next(),
+                        KeyCode::Left | KeyCode::Char('p') => self.prev(),
+                        KeyCode::Home => {
+                            self.position = 0;
+                            self.rebuild();
+                        }
+                        KeyCode::End => {
+                            self.position = self.events.len() - 1;
+                            self.rebuild();
+                        }
+                        KeyCode::PageUp => {
+                            for _ in 0..5 { self.app.scroll_up(); }
+                        }
+                        KeyCode::PageDown => {
+                            for _ in 0..5 { self.app.scroll_down(); }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn parse_mode(mode: &str) -> ChatMode {
+    match mode {
+        "ASK" => ChatMode::Ask,
+        "PLAN" => ChatMode::Plan,
+        "AUTO" => ChatMode::Auto,
+        _ => ChatMode::Code,
+    }
+}
+
+pub fn run_replay(path: &Path) -> Result<(), String> {
+    let mut viewer = ReplayViewer::open(path)?;
+    viewer.run()
+}
@@ -0,0 +1,193 @@
+use std::path::Path;
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use crate::differ::{FileChange, Hunk};
+use crate::tui::highlight::CodeHighlighter;
+
+/// A file proposed in CODE mode, with the decision taken on each of its hunks
+pub struct ReviewFile {
+    /// Change against the content on disk (empty original for a new file)
+    pub change: FileChange,
+    /// The file does not exist yet: its parent directories are created on apply
+    pub new_file: bool,
+    /// Path shown to the user (relative to the project)
+    pub display: String,
+    pub hunks: Vec<Hunk>,
+    /// None while the hunk is not reviewed yet
+    pub decisions: Vec<Option<bool>>,
+}
+
+impl ReviewFile {
+    pub fn accepted_count(&self) -> usize {
+        self.decisions.iter().filter(|d| **d == Some(true)).count()
+    }
+
+    /// The change limited to the accepted hunks, or None when all of them were rejected
+    pub fn accepted_change(&self) -> Option<FileChange> {
+        if self.accepted_count() == 0 {
+            return None;
+        }
+        let accepted: Vec<bool> = self.decisions.iter().map(|d| *d == Some(true)).collect();
+        Some(self.change.with_hunks(&accepted))
+    }
+}
+
+/// Hunk by hunk review of the files proposed in CODE mode: y accepts the current hunk,
+/// n rejects it, a accepts the rest of the file, Échap rejects everything left
+pub struct HunkReview {
+    pub files: Vec<ReviewFile>,
+    /// Current file and hunk; `file == files.len()` once everything is decided
+    pub file: usize,
+    pub hunk: usize,
+}
+
+impl HunkReview {
+    pub fn new(project_root: &Path, changes: Vec<(FileChange, bool)>) -> Self {
+        let files = changes.into_iter()
+            .map(|(change, new_file)| {
+                let hunks = change.hunks();
+                let path = Path::new(&change.path);
+                ReviewFile {
+                    display: path.strip_prefix(project_root).unwrap_or(path).to_string_lossy().to_string(),
+                    decisions: vec![None; hunks.len()],
+                    hunks,
+                    change,
+                    new_file,
+                }
+            })
+            .filter(|file| !file.hunks.is_empty())
+            .collect();
+        Self { files, file: 0, hunk: 0 }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.file >= self.files.len()
+    }
+
+    pub fn total_hunks(&self) -> usize {
+        self.files.iter().map(|file| file.hunks.len()).sum()
+    }
+
+    pub fn decided_hunks(&self) -> usize {
+        self.files.iter().flat_map(|file| &file.decisions).filter(|d| d.is_some()).count()
+    }
+
+    /// Decide the current hunk and move to the next one
+    pub fn decide(&mut self, accept: bool) {
+        if let Some(file) = self.files.get_mut(self.file) {
+            file.decisions[self.hunk] = Some(accept);
+            self.advance();
+        }
+    }
+
+    /// Decide the current hunk and the following ones of the same file
+    pub fn decide_file(&mut self, accept: bool) {
+        if let Some(file) = self.files.get_mut(self.file) {
+            for decision in &mut file.decisions[self.hunk..] {
+                *decision = Some(accept);
+            }
+            self.file += 1;
+            self.hunk = 0;
+        }
+    }
+
+    /// Reject every hunk not decided yet
+    pub fn reject_rest(&mut self) {
+        while !self.is_done() {
+            self.decide_file(false);
+        }
+    }
+
+    fn advance(&mut self) {
+        self.hunk += 1;
+        if self.hunk >= self.files[self.file].hunks.len() {
+            self.file += 1;
+            self.hunk = 0;
+        }
+    }
+
+    /// Syntax-colored diff of the current file, and the index of the current hunk's header
+    pub fn lines(&self) -> (Vec<Line<'static>>, usize) {
+        let Some(file) = self.files.get(self.file) else {
+            return (Vec::new(), 0);
+        };
+        let extension = Path::new(&file.change.path).extension().and_then(|e| e.to_str()).unwrap_or("");
+        let mut lines = Vec::new();
+        let mut current = 0;
+        for (i, hunk) in file.hunks.iter().enumerate() {
+            let (mark, style) = match file.decisions[i] {
+                _ if i == self.hunk => ("▶ ", Style::default().fg(Color::Black).bg(Color::Yellow).add_modifier(Modifier::BOLD)),
+                Some(true) => ("✓ ", Style::default().fg(Color::Green)),
+                Some(false) => ("✗ ", Style::default().fg(Color::Red)),
+                None => ("  ", Style::default().fg(Color::Cyan)),
+            };
+            if i == self.hunk {
+                current = lines.len();
+            }
+            lines.push(Line::from(Span::styled(format!("{}{}", mark, hunk.header), style)));
+
+            // Highlighting restarts with each hunk: the lines in between are not shown
+            let mut highlighter = CodeHighlighter::new(extension);
+            for (sign, text) in &hunk.lines {
+                let mut line = highlighter.line(text);
+                let background = match sign {
+                    '+' => Some(Color::Rgb(0, 50, 0)),
+                    '-' => Some(Color::Rgb(60, 0, 0)),
+                    _ => None,
+                };
+                let sign_style = match sign {
+                    '+' => Style::default().fg(Color::Green).add_modifier(Modifier::BOLD),
+                    '-' => Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                    _ => Style::default().fg(Color::DarkGray),
+                };
+                if let Some(background) = background {
+                    for span in &mut line.spans {
+                        span.style = span.style.bg(background);
+                    }
+                }
+                line.spans.insert(0, Span::styled(format!("  {} ", sign), sign_style));
+                lines.push(line);
+            }
+            lines.push(Line::from(""));
+        }
+        (lines, current)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hunk_decisions_build_partial_changes() {
+        let original: String = (1..=20).map(|i| format!("line {}\n", i)).collect();
+        let change = FileChange {
+            path: "/p/src/a.txt".to_string(),
+            original: original.clone(),
+            modified: original.replace("line 2\n", "line two\n").replace("line 18\n", "line eighteen\n"),
+            description: String::new(),
+        };
+        let new_file = FileChange {
+            path: "/p/b.txt".to_string(),
+            original: String::new(),
+            modified: "b\n".to_string(),
+            description: String::new(),
+        };
+        let unchanged = FileChange { modified: original.clone(), ..change.clone() };
+        let mut review = HunkReview::new(Path::new("/p"), vec![(change, false), (unchanged, false), (new_file, true)]);
+        assert_eq!((review.files.len(), review.total_hunks()), (2, 3));
+        assert_eq!(review.files[0].display, "src/a.txt");
+        assert!(review.lines().0[0].spans[0].content.starts_with("▶ @@ -1,5 +1,5 @@"));
+
+        review.decide(false);
+        assert_eq!((review.file, review.hunk, review.lines().1), (0, 1, 8));
+        review.decide(true);
+        assert_eq!(review.file, 1);
+        review.reject_rest();
+        assert!(review.is_done() && review.decided_hunks() == 3);
+
+        let partial = review.files[0].accepted_change().unwrap();
+        assert_eq!(partial.modified, original.replace("line 18\n", "line eighteen\n"));
+        assert!(review.files[1].accepted_change().is_none());
+    }
+}
@@ -1,8 +1,9 @@
 use std::io;
 use std::path::PathBuf;
+use std::sync::{mpsc, Arc};
 use std::time::Duration;
 use crossterm::{
-    event::{self, Event, KeyCode, KeyModifiers},
+    event::{self, DisableBracketedPaste, EnableBracketedPaste, Event, KeyCode, KeyModifiers},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
@@ -10,16 +11,40 @@ use ratatui::{
     backend::CrosstermBackend,
     Terminal,
 };
-use crate::tui::app::App;
+use crate::tui::app::{App, TuiSettings};
+use crate::audit::{Decision, ToolAudit};
+use crate::tui::auto_run::{self, AutoRun};
+use crate::tui::changes::SessionChanges;
+use crate::journal::ChangeJournal;
+use crate::auto_commit;
+use crate::tui::highlight::CodeHighlighter;
+use crate::tui::review::HunkReview;
 use crate::tui::ui;
-use crate::tui::tools;
-use crate::tui::mcp::McpManager;
-use crate::mistral_client::{MistralClient, ApiProvider, Message};
-use crate::agent::load_api_settings;
-use crate::indexer::CodebaseIndex;
-use crate::persistent_index::PersistentIndex;
-use crate::differ::parse_ai_response;
-use crate::chat::ChatMode;
+use crate::tools::{self, ToolAccess};
+use crate::tui::mcp::{McpConfig, McpManager, McpServer};
+use crate::custom_tools::CustomTools;
+use crate::tui::injection::{build_injection, referenced_paths, referenced_symbols, MentionedFile, MentionedSymbol, INJECTION_BUDGET_CHARS, MAX_SYMBOL_MATCHES};
+use crate::tui::question_form::{FieldKind, FormField, QuestionForm};
+use crate::tui::recording::{SessionEvent, SessionRecorder};
+use crate::tui::startup::StartupScreen;
+use crate::backend::LlmBackend;
+use crate::mistral_client::{check_connectivity, ApiProvider, Message, RetryNotice, RetryPolicy, Role};
+use crate::settings::{
+    load_saved_api_settings, save_api_settings, trust_endpoint, unconfirmed_endpoint, validate_api_key, ApiSettings,
+};
+use crate::indexer::{mentioned_skips, CodebaseIndex};
+use crate::project_type;
+use crate::pinned;
+use crate::memory_update;
+use crate::prompt::{self, AutoMarkers, PromptBuilder};
+use crate::persistent_index::{indexable_files, sync_in_background, IndexBudget, PersistentIndex, SYMBOL_MAP_TOKENS};
+use crate::differ::{parse_ai_response_with, ChangeSet, FileChange, FileDeletion, NewFile, PatchFailure, MAX_PATCH_RETRIES};
+use crate::mode::ChatMode;
+use crate::context_guard::{self, CodebaseContext, ContextLevel, RESPONSE_RESERVE_TOKENS};
+use crate::chat_storage::SavedChat;
+use crate::cli::{ChatConfig, ContextScope};
+use crate::workspace::Workspace;
+use crate::recent_projects;
 
 const SYSTEM_PROMPT: &str = r#"Tu es un assistant de programmation expert. Tu analyses des codebases et proposes des modifications.
 
@@ -42,6 +67,17 @@ Pour créer un fichier (sans outil):
 contenu
 </new_file>
 
+Pour réécrire entièrement un fichier (grosses modifications):
+<replace_file path="chemin/fichier.ext">
+nouveau contenu complet du fichier
+</replace_file>
+
+Pour supprimer un fichier:
+<delete_file path="chemin/fichier.ext" />
+
+Pour déplacer ou renommer un fichier (l'historique git est conservé; les modifications du fichier utilisent ensuite le nouveau chemin):
+<rename_file from="chemin/fichier.ext" to="autre/chemin/fichier.ext" />
+
 BASE DE DONNÉES SQLite:
 - Tu as accès à un index SQLite du projet qui contient tous les fichiers avec leur contenu
 - L'index est automatiquement mis à jour lorsque des fichiers changent (vérification par hash SHA256)
@@ -49,18 +85,10 @@ BASE DE DONNÉES SQLite:
 - Utilise ces informations pour comprendre la structure du projet
 "#;
 
-const AUTO_MODE_SUFFIX: &str = r#"
 
-MODE AUTO ACTIVÉ:
-- Tu DOIS continuer à travailler jusqu'à ce que la tâche soit COMPLÈTEMENT terminée
-- Fais TOUTES les modifications nécessaires en une seule réponse
-- Ne demande PAS de confirmation, applique directement
-- Si la tâche nécessite plusieurs étapes, fais-les TOUTES
-- Termine ta réponse par [TERMINÉ] quand la tâche est complète
-- Si tu dois continuer, termine par [CONTINUE] et je te relancerai automatiquement
-"#;
-
-const COMPACT_PROMPT: &str = "Résume en 2-3 phrases les échanges précédents pour garder le contexte essentiel. Sois très concis.";
+/// Shown when the API cannot be reached
+const OFFLINE_MESSAGE: &str = "📴 Hors ligne: l'API Mistral est injoignable. /resume, /save, /changes, /undo et /reindex restent disponibles ; la connexion est revérifiée à chaque message.";
+const CONNECTIVITY_TIMEOUT: Duration = Duration::from_secs(3);
 
 const MAX_TOKENS: usize = 32000;
 const COMPACT_THRESHOLD: usize = (MAX_TOKENS * 90) / 100; // 90%
@@ -69,114 +97,387 @@ const COMPACT_THRESHOLD: usize = (MAX_TOKENS * 90) / 100; // 90%
 pub const COMMANDS: &[(&str, &str)] = &[
     ("new", "Nouvelle conversation"),
     ("resume", "Reprendre une conversation"),
+    ("trash", "Corbeille: restaurer une conversation supprimée"),
     ("save", "Sauvegarder la conversation"),
     ("memory", "Éditer les instructions projet (vim)"),
+    ("remember", "Proposer d'ajouter à memory.md les faits de la conversation"),
     ("questions", "Test formulaire tabbé"),
     ("clear", "Effacer l'historique"),
+    ("drop", "Supprimer le dernier échange"),
+    ("changes", "Fichiers modifiés: diff, annulation, éditeur"),
+    ("undo", "Annuler les dernières modifications appliquées"),
+    ("open", "Aperçu d'un fichier: /open <chemin> (sans chemin: dernier @fichier)"),
+    ("pin", "Toujours joindre un fichier/dossier: /pin <chemin> (sans chemin: liste)"),
+    ("unpin", "Ne plus joindre: /unpin <chemin>"),
+    ("compare", "Comparer deux modèles: /compare <question>"),
     ("reindex", "Réindexer le projet"),
+    ("health", "État du fournisseur: latence, modèle, quota"),
+    ("context diff", "CODEBASE: fichiers modifiés sur la branche (vs main)"),
+    ("context full", "CODEBASE: projet entier"),
+    ("record", "Démarrer/arrêter l'enregistrement"),
     ("ask", "Mode ASK - Questions simples"),
     ("plan", "Mode PLAN - Planification"),
+    ("plan+", "PLAN+ - Aperçu des écritures sans toucher au disque (on/off)"),
     ("code", "Mode CODE - Modifications avec confirmation"),
     ("auto", "Mode AUTO - Application automatique"),
     ("exit", "Sauvegarder et quitter"),
     ("quit", "Quitter sans sauvegarder"),
 ];
 
+/// Commands followed by an argument in the menu filter, e.g. `/open src/main.rs`
+const ARGUMENT_COMMANDS: &[&str] = &["open", "compare", "pin", "unpin"];
+
 pub struct TuiRunner {
     app: App,
-    client: MistralClient,
-    system_prompt: String,
+    client: Arc<dyn LlmBackend>,
+    /// Attempts and backoff of API requests (`retry` in settings.json)
+    retry_policy: RetryPolicy,
+    /// Models answering `/compare` (`tui.compare_models` in settings.json)
+    compare_models: Vec<String>,
+    /// Offer memory.md additions when a conversation ends (`tui.memory_updates`)
+    memory_updates: bool,
+    /// Messages of the conversation already searched for facts to remember
+    memory_reviewed: usize,
+    /// SYSTEM_PROMPT with the project language rule
+    base_prompt: String,
+    /// Answer in the language of each message ("language": "auto")
+    auto_language: bool,
+    /// `system_prompt.md` template replacing SYSTEM_PROMPT, if any
+    prompt_template: Option<String>,
+    /// File contents of the in-memory index, sent in CODE and AUTO modes
+    codebase_context: CodebaseContext,
+    /// Leanest CODEBASE level the system prompt needed so far to fit the model's window
+    context_level: ContextLevel,
+    /// Whole project, or only the files changed on the branch (`--context diff`)
+    context_scope: ContextScope,
+    /// Ranked file list of the SQLite index, sent in PLAN, CODE and AUTO modes
+    sqlite_info: String,
     project_memory: String,
+    /// Contents of the pinned files, read again before each request
+    pinned_context: String,
     memory_file: PathBuf,
     show_command_menu: bool,
     command_filter: String,
     selected_command: usize,
     persistent_index: Option<PersistentIndex>,
     mcp_manager: McpManager,
+    custom_tools: CustomTools,
+    /// Project roots the tools can reach, the primary one being `app.project_path`
+    workspace: Workspace,
+    recorder: Option<SessionRecorder>,
+    /// Chat being continued, updated in place on save
+    current_chat: Option<SavedChat>,
+    /// Hunks that still failed after retries, offered for manual placement
+    pending_conflicts: Vec<PatchFailure>,
+    /// SQLite sync continuing in the background after the startup budget ran out
+    index_sync: Option<mpsc::Receiver<crate::error::Result<usize>>>,
+    /// Dangerous tool calls waiting for the user's approval
+    pending_approvals: Vec<PendingApproval>,
+    /// Modifications and new files proposed in CODE mode, reviewed hunk by hunk before any write
+    pending_reviews: Vec<(FileChange, bool)>,
+    /// Deletions proposed in CODE mode, confirmed one by one before they go to the trash
+    pending_deletions: Vec<FileDeletion>,
+    audit: ToolAudit,
+    /// Files written or deleted by the AI in this session, listed by /changes
+    session_changes: SessionChanges,
+    /// AUTO loop stopped by a failed request (or a closed TUI), offered again on the next prompt
+    interrupted_auto: Option<AutoRun>,
+    /// Iterations done by the interrupted AUTO loop being resumed, counted on by the next send
+    resumed_iteration: usize,
+}
+
+/// Dangerous tool call held until the user approves or denies it
+struct PendingApproval {
+    call: tools::ToolCall,
+    /// Command shown to the user and matched against session approvals
+    command: String,
+    /// Assistant message the result is attached to
+    message_index: usize,
 }
 
 impl TuiRunner {
-    pub fn new(project_path: PathBuf) -> Result<Self, String> {
-        let (api_key, provider) = load_api_settings()?;
+    /// Without a workspace, the project is chosen in the startup picker
+    pub fn new(workspace: Option<Workspace>, config: &ChatConfig) -> Result<Self, String> {
+        let context_scope = config.context;
+        let mut screen = StartupScreen::start()?;
+        let workspace = match workspace {
+            Some(workspace) => workspace,
+            None => {
+                let current_dir = std::env::current_dir().map_err(|e| e.to_string())?;
+                Workspace::single(&screen.pick_project(&recent_projects::candidates(), &current_dir)?)
+            }
+        };
+        let project_path = workspace.primary().to_path_buf();
+        // Offered first by the picker next time
+        recent_projects::remember(&project_path);
+        
+        screen.begin("Chargement des paramètres");
+        // First run: configure the key in a form instead of the stdin wizard
+        let (api_key, provider) = match load_saved_api_settings() {
+            Some(settings) => settings,
+            None => {
+                let (api_key, provider) = screen.setup_api_key(validate_api_key)?;
+                save_api_settings(&api_key, &provider)?;
+                (api_key, provider)
+            }
+        };
+        // A project's own endpoint receives the code: the user accepts it once
+        if let Some(base_url) = unconfirmed_endpoint(&project_path) {
+            let question = format!("Les paramètres du projet envoient les requêtes (code compris) à {}. Faire confiance à ce point d'accès?", base_url);
+            if screen.confirm("Point d'accès du projet", &question)? {
+                trust_endpoint(&base_url)?;
+            }
+        }
+        let settings = match ApiSettings::merge(api_key, provider, Some(&project_path)) {
+            Ok(mut settings) => {
+                settings.apply_overrides(config.base_url.as_deref(), config.model.as_deref());
+                settings
+            }
+            Err(e) => {
+                screen.fail(e.clone());
+                return Err(e);
+            }
+        };
+        match &settings.project.model {
+            Some(model) => screen.finish(format!("{} ({})", settings.provider, model)),
+            None => screen.finish(settings.provider.to_string()),
+        }
+        // No network: start anyway, the API is checked again before each request
+        let connectivity_provider = settings.provider.clone();
+        let offline = match screen.run_stage("Vérification de la connexion", move |_| {
+            check_connectivity(&connectivity_provider, CONNECTIVITY_TIMEOUT)
+        }) {
+            Some(Ok(())) => {
+                screen.finish(settings.provider.host());
+                false
+            }
+            Some(Err(e)) => {
+                screen.fail(e);
+                true
+            }
+            None => false,
+        };
+
+        // Build/test/lint commands of the detected project type, so the model does not guess them
+        let projects = project_type::detect(&project_path);
+        let mut base_prompt = settings.system_prompt(SYSTEM_PROMPT);
+        let project_commands = project_type::prompt_section(&projects);
+        if !project_commands.is_empty() {
+            base_prompt = format!("{}\n\n{}", base_prompt, project_commands);
+        }
+        let workspace_section = workspace.prompt_section();
+        if !workspace_section.is_empty() {
+            base_prompt = format!("{}\n\n{}", base_prompt, workspace_section);
+        }
         
-        // Index codebase for context (in-memory, quick)
-        let index = CodebaseIndex::index(&project_path, None, &[], 50)?;
-        let context = index.build_context(20000);
-        let codebase_context = context.first().cloned().unwrap_or_default();
+        // Index codebase for context (in-memory, skippable), every root of the workspace
+        let index_workspace = workspace.clone();
+        let index = screen.run_stage("Indexation du projet", move |progress| {
+            CodebaseIndex::index_workspace(&index_workspace, context_scope, None, &[], 50, |done, total| {
+                progress.set(format!("{}/{} fichiers", done, total));
+            })
+        });
+        let codebase_context = match index {
+            Some(Ok(index)) => {
+                screen.finish(match context_scope {
+                    ContextScope::Full => format!("{} fichiers", index.files.len()),
+                    ContextScope::Diff => format!("{} fichiers modifiés", index.files.len()),
+                });
+                CodebaseContext::from_index(&index, 20000)
+            }
+            // No git branch to compare with: start without the CODEBASE section
+            Some(Err(e)) if context_scope == ContextScope::Diff => {
+                screen.fail(e);
+                CodebaseContext::default()
+            }
+            Some(Err(e)) => return Err(e),
+            None => CodebaseContext::default(),
+        };
         
         // Open or create persistent SQLite index
+        screen.begin("Ouverture de l'index SQLite");
         let persistent_index = PersistentIndex::open(&project_path).ok();
+        match persistent_index.as_ref().and_then(|p| p.stats().ok()) {
+            Some((files, _)) => screen.finish(format!("{} fichiers", files)),
+            None => screen.fail("indisponible"),
+        }
         
-        // Build SQLite index info for system prompt
-        let sqlite_info = if let Some(ref pindex) = persistent_index {
-            if let Ok(files) = pindex.list_files() {
-                let file_list: Vec<String> = files.iter()
-                    .take(100)
-                    .map(|f| format!("- {} ({})", f.relative_path, f.extension))
-                    .collect();
-                if !file_list.is_empty() {
-                    format!("\n\nINDEX SQLITE ({} fichiers):\n{}", 
-                        files.len(), 
-                        file_list.join("\n"))
-                } else {
-                    String::new()
+        // Sync changed files within the startup budget, the rest continues in the background
+        let mut index_sync = None;
+        if persistent_index.is_some() {
+            let budget = IndexBudget::load();
+            let sync_root = project_path.clone();
+            let sync = screen.run_stage("Synchronisation de l'index SQLite", move |progress| {
+                let files = indexable_files(&sync_root);
+                progress.set(format!("{} fichiers", files.len()));
+                let index = PersistentIndex::open(&sync_root)?;
+                let (updated, processed) = index.sync_files(&files, Some(budget.deadline()), budget.max_files)?;
+                Ok::<_, String>((updated, files[processed..].to_vec()))
+            });
+            match sync {
+                Some(Ok((updated, remaining))) => {
+                    if remaining.is_empty() {
+                        screen.finish(format!("{} fichiers mis à jour", updated));
+                    } else {
+                        screen.finish(format!("{} mis à jour, {} restants en arrière-plan", updated, remaining.len()));
+                        index_sync = Some(sync_in_background(project_path.clone(), Some(remaining)));
+                    }
                 }
-            } else {
-                String::new()
+                Some(Err(e)) => screen.fail(e),
+                None => index_sync = Some(sync_in_background(project_path.clone(), None)),
             }
-        } else {
-            String::new()
-        };
+        }
+        
+        // Build SQLite index info for system prompt
+        let sqlite_info = sqlite_summary(persistent_index.as_ref());
         
         // Load project memory file
         let memory_file = project_path.join(".codestral").join("memory.md");
-        let project_memory = if memory_file.exists() {
-            std::fs::read_to_string(&memory_file).unwrap_or_default()
-        } else {
-            String::new()
-        };
-        
-        let mut system_prompt = format!("{}\n\n{}\n\nCODEBASE:\n{}{}", 
-            SYSTEM_PROMPT, 
-            tools::get_tools_documentation(),
-            codebase_context, 
-            sqlite_info
-        );
+        let project_memory = prompt::load_memory(&project_path);
         
         // Initialize MCP servers - create default config if not exists
         let mcp_config_path = project_path.join(".codestral").join("mcp_servers.json");
         if !mcp_config_path.exists() {
-            let _ = crate::tui::mcp::McpConfig::create_default(&project_path);
+            let _ = McpConfig::create_default(&project_path);
         }
         
+        // Start servers one by one so a slow download (npx) can be skipped
         let mut mcp_manager = McpManager::new();
-        let started_servers = mcp_manager.start_from_config(&project_path);
+        let mut servers: Vec<_> = McpConfig::load(&project_path)
+            .map(|config| config.mcp_servers.into_iter().collect())
+            .unwrap_or_default();
+        servers.sort_by(|a, b| a.0.cmp(&b.0));
+        for (name, server_config) in servers {
+            let root = project_path.clone();
+            let server_name = name.clone();
+            let started = screen.run_stage(format!("Démarrage du serveur MCP {}", name), move |_| {
+                McpServer::start(&server_name, &server_config, &root)
+            });
+            match started {
+                Some(Ok(server)) => {
+                    screen.finish(format!("{} outils", server.get_tools().len()));
+                    mcp_manager.add_server(server);
+                }
+                Some(Err(e)) => screen.fail(e),
+                None => {}
+            }
+        }
+        
+        // Load project tools from .codestral/tools/*.toml
+        screen.begin("Chargement des outils projet");
+        let (mut custom_tools, custom_tool_errors) = CustomTools::load(&project_path, tools::BUILTIN_TOOLS);
+        if let Some(run_tests) = project_type::run_tests_tool(&projects) {
+            custom_tools.add(run_tests);
+        }
+        screen.finish(format!("{} outils", custom_tools.len()));
+        
+        let (prompt_template, prompt_template_error) = match prompt::load_template(Some(&project_path)) {
+            Ok(template) => (template, None),
+            Err(e) => (None, Some(e)),
+        };
         
-        // Add MCP tools documentation to system prompt
-        let mcp_docs = mcp_manager.get_tools_documentation();
-        if !mcp_docs.is_empty() {
-            system_prompt = format!("{}\n{}", system_prompt, mcp_docs);
+        let audit = ToolAudit::new(&project_path);
+        let mut app = App::new(project_path);
+        app.pinned = pinned::load(&app.project_path);
+        let tui_settings = TuiSettings::load();
+        app.fold_lines = tui_settings.fold_lines;
+        app.offline = offline;
+        if offline {
+            app.add_ai_message(OFFLINE_MESSAGE.to_string());
+        }
+        if let Some(setup) = crate::wsl::mixed_setup(&app.project_path) {
+            app.add_ai_message(format!("ℹ️ Environnement mixte: {}", setup));
+        }
+        if !custom_tool_errors.is_empty() {
+            app.add_ai_message(format!(
+                "⚠️ Outils projet ignorés:\n{}",
+                custom_tool_errors.join("\n")
+            ));
+        }
+        if let Some(e) = prompt_template_error {
+            app.add_ai_message(format!("⚠️ Prompt système personnalisé ignoré: {}", e));
+        }
+        let interrupted_auto = AutoRun::load(&app.project_path);
+        if let Some(run) = &interrupted_auto {
+            app.add_ai_message(run.offer());
         }
         
         Ok(Self {
-            app: App::new(project_path),
-            client: MistralClient::new(api_key, provider),
-            system_prompt,
+            app,
+            client: settings.client(),
+            retry_policy: RetryPolicy::load(),
+            compare_models: tui_settings.compare_models,
+            memory_updates: tui_settings.memory_updates,
+            memory_reviewed: 0,
+            base_prompt,
+            auto_language: settings.auto_language(),
+            prompt_template,
+            codebase_context,
+            context_level: ContextLevel::Full,
+            context_scope,
+            sqlite_info,
             project_memory,
+            pinned_context: String::new(),
             memory_file,
             show_command_menu: false,
             command_filter: String::new(),
             selected_command: 0,
             persistent_index,
             mcp_manager,
+            custom_tools,
+            workspace,
+            recorder: None,
+            current_chat: None,
+            pending_conflicts: Vec::new(),
+            index_sync,
+            pending_approvals: Vec::new(),
+            pending_reviews: Vec::new(),
+            pending_deletions: Vec::new(),
+            audit,
+            session_changes: SessionChanges::default(),
+            interrupted_auto,
+            resumed_iteration: 0,
         })
     }
 
+    /// Send the requests to another backend, e.g. a scripted client in tests
+    pub fn with_client(mut self, client: Arc<dyn LlmBackend>) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Start recording the session to a replayable file
+    pub fn start_recording(&mut self, path: &std::path::Path) -> Result<(), String> {
+        let recorder = SessionRecorder::start(path, &self.app.project_path, &self.app.mode.to_string())?;
+        self.recorder = Some(recorder);
+        Ok(())
+    }
+
+    /// Record a session event if recording is active
+    fn record(&mut self, event: SessionEvent) {
+        if let Some(recorder) = self.recorder.as_mut() {
+            recorder.record(event);
+        }
+    }
+
+    fn toggle_recording(&mut self) {
+        if let Some(recorder) = self.recorder.take() {
+            self.app.add_ai_message(format!("⏹ Enregistrement terminé: {}", recorder.path().display()));
+            return;
+        }
+
+        let path = SessionRecorder::default_path(&self.app.project_path);
+        match self.start_recording(&path) {
+            Ok(()) => self.app.add_ai_message(format!("⏺ Enregistrement démarré: {}", path.display())),
+            Err(e) => self.app.add_ai_message(format!("❌ {}", e)),
+        }
+    }
+
     pub async fn run(&mut self) -> Result<(), String> {
         // Setup terminal
         enable_raw_mode().map_err(|e| e.to_string())?;
         let mut stdout = io::stdout();
-        execute!(stdout, EnterAlternateScreen).map_err(|e| e.to_string())?;
+        execute!(stdout, EnterAlternateScreen, EnableBracketedPaste).map_err(|e| e.to_string())?;
         let backend = CrosstermBackend::new(stdout);
         let mut terminal = Terminal::new(backend).map_err(|e| e.to_string())?;
 
@@ -184,7 +485,7 @@ impl TuiRunner {
 
         // Restore terminal
         disable_raw_mode().map_err(|e| e.to_string())?;
-        execute!(terminal.backend_mut(), LeaveAlternateScreen).map_err(|e| e.to_string())?;
+        execute!(terminal.backend_mut(), DisableBracketedPaste, LeaveAlternateScreen).map_err(|e| e.to_string())?;
         terminal.show_cursor().map_err(|e| e.to_string())?;
 
         result
@@ -206,33 +507,92 @@ impl TuiRunner {
                 }
             }).map_err(|e| e.to_string())?;
 
+            // Background index sync finished: refresh the prompt with the complete file list
+            if let Some(rx) = &self.index_sync {
+                match rx.try_recv() {
+                    Ok(result) => {
+                        self.index_sync = None;
+                        match result {
+                            Ok(updated) => {
+                                self.refresh_system_prompt();
+                                if updated > 0 {
+                                    self.app.add_ai_message(format!("✅ Index SQLite synchronisé en arrière-plan ({} fichiers mis à jour).", updated));
+                                }
+                            }
+                            Err(e) => {
+                                self.app.add_ai_message(format!("⚠️ Synchronisation de l'index échouée: {}", e));
+                                if let Some(hint) = e.hint() {
+                                    self.app.add_ai_message(hint.to_string());
+                                }
+                            }
+                        }
+                    }
+                    Err(mpsc::TryRecvError::Disconnected) => self.index_sync = None,
+                    Err(mpsc::TryRecvError::Empty) => {}
+                }
+            }
+            
+            // Dangerous commands: ask, run the approved ones and let the AI continue
+            if !self.pending_approvals.is_empty() {
+                self.confirm_dangerous_tools(terminal).await?;
+                continue;
+            }
+            
+            // Files proposed in CODE mode: review their hunks in the diff viewer
+            if !self.pending_reviews.is_empty() {
+                self.review_changes(terminal).await?;
+                continue;
+            }
+
+            // Deletions proposed in CODE mode: ask before moving each file to the trash
+            if !self.pending_deletions.is_empty() {
+                self.confirm_deletions(terminal).await?;
+                continue;
+            }
+
+            // Failed hunks: let the user place them by hand in the editor
+            if !self.pending_conflicts.is_empty() {
+                self.resolve_conflicts(terminal)?;
+                continue;
+            }
+
             // Check for pending questions from AI - show tabbed form
             if !self.app.pending_questions.is_empty() {
                 let questions = std::mem::take(&mut self.app.pending_questions);
                 if let Ok(Some(responses)) = self.show_question_form(questions, terminal).await {
                     // Send responses as new message
                     self.app.add_user_message(responses.clone());
+                    self.record(SessionEvent::Prompt { content: responses.clone() });
                     self.app.loading = true;
                     self.app.scroll = 0;
                     terminal.draw(|f| ui::draw(f, &self.app)).map_err(|e| e.to_string())?;
-                    self.send_message_internal(responses).await?;
+                    self.send_message_internal(responses, terminal).await?;
                 }
             }
 
             // Handle events
             if event::poll(Duration::from_millis(100)).map_err(|e| e.to_string())? {
-                if let Event::Key(key) = event::read().map_err(|e| e.to_string())? {
+                let event = event::read().map_err(|e| e.to_string())?;
+                if let Event::Paste(text) = &event {
+                    // Bracketed paste: the block arrives whole, its newlines do not send it
+                    if !self.show_command_menu {
+                        self.app.insert_text(text);
+                    }
+                } else if let Event::Key(key) = event {
                     if self.show_command_menu {
                         if let Some(action) = self.handle_command_menu_key(key.code) {
                             match action {
                                 CommandAction::New => {
                                     // Save current and start fresh
+                                    self.offer_memory_update(terminal, false).await?;
                                     self.save_conversation();
-                                    self.app.messages.clear();
+                                    self.app.clear_messages();
+                                    self.current_chat = None;
+                                    self.memory_reviewed = 0;
                                 }
-                                CommandAction::Resume => {
+                                CommandAction::Resume(trash) => {
                                     // Show resume menu
-                                    self.show_resume_menu(terminal).await?;
+                                    self.show_resume_menu(terminal, trash).await?;
                                     // Clear terminal and flush events
                                     terminal.clear().map_err(|e| e.to_string())?;
                                     while event::poll(Duration::from_millis(50)).unwrap_or(false) {
@@ -244,16 +604,17 @@ impl TuiRunner {
                                 }
                                 CommandAction::Exit => {
                                     // Save and quit
+                                    self.offer_memory_update(terminal, false).await?;
                                     self.save_conversation();
                                     self.app.should_quit = true;
                                 }
                                 CommandAction::Memory => {
                                     // Exit TUI temporarily for editor
                                     disable_raw_mode().map_err(|e| e.to_string())?;
-                                    execute!(terminal.backend_mut(), LeaveAlternateScreen).map_err(|e| e.to_string())?;
+                                    execute!(terminal.backend_mut(), DisableBracketedPaste, LeaveAlternateScreen).map_err(|e| e.to_string())?;
                                     self.open_memory_editor();
                                     enable_raw_mode().map_err(|e| e.to_string())?;
-                                    execute!(terminal.backend_mut(), EnterAlternateScreen).map_err(|e| e.to_string())?;
+                                    execute!(terminal.backend_mut(), EnterAlternateScreen, EnableBracketedPaste).map_err(|e| e.to_string())?;
                                     terminal.clear().map_err(|e| e.to_string())?;
                                     // Flush events
                                     while event::poll(Duration::from_millis(10)).unwrap_or(false) {
@@ -261,11 +622,28 @@ impl TuiRunner {
                                     }
                                 }
                                 CommandAction::Questions => {
-                                    // Demo tabbed form
+                                    // Demo tabbed form with every field type
                                     let questions = vec![
-                                        "Quel est le nom du projet?".to_string(),
-                                        "Quel langage utilisez-vous?".to_string(),
-                                        "Décrivez le problème à résoudre:".to_string(),
+                                        FormField::text("Quel est le nom du projet?"),
+                                        FormField {
+                                            kind: FieldKind::Choice,
+                                            choices: vec!["Rust".to_string(), "TypeScript".to_string(), "Python".to_string()],
+                                            ..FormField::text("Quel langage utilisez-vous?")
+                                        },
+                                        FormField {
+                                            kind: FieldKind::MultiSelect,
+                                            choices: vec!["tests".to_string(), "docs".to_string(), "CI".to_string()],
+                                            ..FormField::text("Que faut-il ajouter?")
+                                        },
+                                        FormField { kind: FieldKind::Boolean, ..FormField::text("Projet open source?") },
+                                        FormField {
+                                            kind: FieldKind::Number,
+                                            min: Some(1.0),
+                                            max: Some(10.0),
+                                            integer: true,
+                                            ..FormField::text("Priorité")
+                                        },
+                                        FormField { kind: FieldKind::FilePath, ..FormField::text("Fichier principal") },
                                     ];
                                     if let Ok(Some(response)) = self.show_question_form(questions, terminal).await {
                                         self.app.add_user_message(response);
@@ -275,6 +653,21 @@ impl TuiRunner {
                                     // Reindex project to SQLite with progress
                                     self.reindex_with_progress(terminal).await?;
                                 }
+                                CommandAction::Changes => {
+                                    self.show_changes(terminal)?;
+                                }
+                                CommandAction::Health => {
+                                    self.show_health(terminal).await?;
+                                }
+                                CommandAction::Open(argument) => {
+                                    self.show_file_preview(&argument, terminal)?;
+                                }
+                                CommandAction::Compare(prompt) => {
+                                    self.compare_models(prompt, terminal).await?;
+                                }
+                                CommandAction::Remember => {
+                                    self.offer_memory_update(terminal, true).await?;
+                                }
                             }
                         }
                     } else {
@@ -288,9 +681,14 @@ impl TuiRunner {
                             // BackTab (Shift+Tab) or Alt+M cycles mode
                             KeyCode::BackTab => {
                                 self.app.cycle_mode();
+                                self.record(SessionEvent::Mode { mode: self.app.mode.to_string() });
                             }
                             KeyCode::Char('m') if key.modifiers.contains(KeyModifiers::ALT) => {
                                 self.app.cycle_mode();
+                                self.record(SessionEvent::Mode { mode: self.app.mode.to_string() });
+                            }
+                            KeyCode::Char('e') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                                self.app.toggle_fold();
                             }
                             KeyCode::Char('/') if self.app.input.is_empty() => {
                                 self.show_command_menu = true;
@@ -303,15 +701,24 @@ impl TuiRunner {
                                     let input = self.app.input.clone();
                                     self.app.input.clear();
                                     self.app.cursor_pos = 0;
+                                    // Answer to the resume offer of an interrupted AUTO loop
+                                    if let Some(run) = self.interrupted_auto.take() {
+                                        if auto_run::accepts(&input) {
+                                            self.resume_auto_run(run, terminal).await?;
+                                            continue;
+                                        }
+                                        AutoRun::clear(&self.app.project_path);
+                                    }
                                     self.app.add_user_message(input.clone());
+                                    self.record(SessionEvent::Prompt { content: input.clone() });
                                     self.app.loading = true;
                                     self.app.scroll = 0; // Scroll to bottom
                                     
                                     // Redraw immediately to show user message + thinking indicator
                                     terminal.draw(|f| ui::draw(f, &self.app)).map_err(|e| e.to_string())?;
                                     
-                                    // Now send to API (this will block but user sees their message)
-                                    self.send_message_internal(input).await?;
+                                    // The request runs in the background; Esc cancels it
+                                    self.send_message_internal(input, terminal).await?;
                                 }
                             }
                             KeyCode::Char(c) => {
@@ -391,13 +798,24 @@ impl TuiRunner {
     }
 
     fn filtered_commands(&self) -> Vec<(&str, &str)> {
+        let filter = match self.command_argument() {
+            Some((name, _)) => name,
+            None => self.command_filter.as_str(),
+        };
         COMMANDS.iter()
-            .filter(|(cmd, _)| cmd.contains(&self.command_filter.as_str()))
+            .filter(|(cmd, _)| cmd.contains(filter))
             .cloned()
             .collect()
     }
 
+    /// Command name and argument typed in the menu filter ("open src/main.rs")
+    fn command_argument(&self) -> Option<(&str, &str)> {
+        let (name, argument) = self.command_filter.split_once(' ')?;
+        ARGUMENT_COMMANDS.contains(&name).then(|| (name, argument.trim()))
+    }
+
     fn execute_selected_command(&mut self) -> Option<CommandAction> {
+        let previous_mode = self.app.mode;
         let filtered = self.filtered_commands();
         let action = if let Some((cmd, _)) = filtered.get(self.selected_command) {
             match *cmd {
@@ -406,18 +824,59 @@ impl TuiRunner {
                     None
                 }
                 "clear" => {
-                    self.app.messages.clear();
+                    self.app.clear_messages();
+                    None
+                }
+                "drop" => {
+                    self.app.drop_last_exchange();
                     None
                 }
                 "new" => Some(CommandAction::New),
-                "resume" => Some(CommandAction::Resume),
+                "resume" => Some(CommandAction::Resume(false)),
+                "trash" => Some(CommandAction::Resume(true)),
                 "save" => Some(CommandAction::Save),
                 "memory" => Some(CommandAction::Memory),
+                "remember" => Some(CommandAction::Remember),
                 "questions" => Some(CommandAction::Questions),
                 "exit" => Some(CommandAction::Exit),
                 "reindex" => Some(CommandAction::Reindex),
+                "health" => Some(CommandAction::Health),
+                "context diff" => { self.set_context_scope(ContextScope::Diff); None }
+                "context full" => { self.set_context_scope(ContextScope::Full); None }
+                "changes" => Some(CommandAction::Changes),
+                "undo" => {
+                    self.undo_last_change_set();
+                    None
+                }
+                "open" => {
+                    let argument = self.command_argument().map(|(_, argument)| argument.to_string());
+                    Some(CommandAction::Open(argument.unwrap_or_default()))
+                }
+                "compare" => {
+                    let prompt = self.command_argument().map(|(_, prompt)| prompt.to_string());
+                    Some(CommandAction::Compare(prompt.unwrap_or_default()))
+                }
+                "pin" | "unpin" => {
+                    let pin = *cmd == "pin";
+                    let argument = self.command_argument().map(|(_, argument)| argument.to_string()).unwrap_or_default();
+                    self.pin_command(pin, &argument);
+                    None
+                }
+                "record" => {
+                    self.toggle_recording();
+                    None
+                }
                 "ask" => { self.app.mode = ChatMode::Ask; None }
                 "plan" => { self.app.mode = ChatMode::Plan; None }
+                "plan+" => {
+                    self.app.dry_run = !self.app.dry_run;
+                    self.app.add_ai_message(if self.app.dry_run {
+                        "👁 PLAN+ activé: les écritures sont affichées en aperçu, rien n'est modifié sur le disque.".to_string()
+                    } else {
+                        "PLAN+ désactivé.".to_string()
+                    });
+                    None
+                }
                 "code" => { self.app.mode = ChatMode::Code; None }
                 "auto" => { self.app.mode = ChatMode::Auto; None }
                 _ => None
@@ -425,28 +884,34 @@ impl TuiRunner {
         } else {
             None
         };
+        if self.app.mode != previous_mode {
+            self.record(SessionEvent::Mode { mode: self.app.mode.to_string() });
+        }
         self.command_filter.clear();
         action
     }
 
-    fn save_conversation(&self) {
-        use crate::chat_storage::{ChatStorage, SavedChat};
+    fn save_conversation(&mut self) {
+        use crate::chat_storage::ChatStorage;
+        
+        if self.app.messages.is_empty() {
+            return;
+        }
         
         if let Ok(storage) = ChatStorage::new() {
-            let mut chat = SavedChat::new(&self.app.project_path.to_string_lossy());
-            for msg in &self.app.messages {
-                chat.messages.push(crate::mistral_client::Message {
-                    role: msg.role.clone(),
-                    content: msg.content.clone(),
-                });
+            let is_new = self.current_chat.is_none();
+            let chat = self.current_chat
+                .get_or_insert_with(|| SavedChat::new(&self.app.project_path.to_string_lossy()));
+            (chat.messages, chat.metadata) = self.app.to_saved_messages();
+            chat.updated_at = chrono::Utc::now();
+            if is_new {
+                chat.auto_title();
             }
-            chat.auto_title();
-            let _ = storage.save(&chat);
+            let _ = storage.save(chat);
         }
     }
 
     fn reindex_to_sqlite(&mut self) -> usize {
-        use walkdir::WalkDir;
         
         // Recreate persistent index
         let project_path = self.app.project_path.clone();
@@ -456,31 +921,10 @@ impl TuiRunner {
             return 0;
         };
         
-        let extensions = ["rs", "py", "js", "ts", "tsx", "jsx", "go", "java", "c", "cpp", "h", "hpp", 
-                          "php", "rb", "swift", "kt", "scala", "vue", "svelte", "html", "css", "scss",
-                          "json", "yaml", "yml", "toml", "md", "sql"];
         let mut count = 0;
         
-        for entry in WalkDir::new(&project_path)
-            .into_iter()
-            .filter_map(|e| e.ok())
-            .filter(|e| e.file_type().is_file())
-        {
-            let path = entry.path();
-            
-            // Skip hidden directories and common exclusions
-            if path.components().any(|c| {
-                let s = c.as_os_str().to_string_lossy();
-                s.starts_with('.') || s == "node_modules" || s == "target" || s == "dist" || s == "build"
-            }) {
-                continue;
-            }
-            
-            // Check extension
-            let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
-            if !extensions.contains(&ext) {
-                continue;
-            }
+        for entry in indexable_files(&project_path) {
+            let path = entry.as_path();
             
             // Read and index
             if let Ok(content) = std::fs::read_to_string(path) {
@@ -499,7 +943,6 @@ impl TuiRunner {
 
     /// Reindex with TUI progress bar
     async fn reindex_with_progress(&mut self, terminal: &mut Terminal<CrosstermBackend<io::Stdout>>) -> Result<(), String> {
-        use walkdir::WalkDir;
         use ratatui::layout::{Constraint, Direction, Layout, Rect};
         use ratatui::style::{Color, Style};
         use ratatui::widgets::{Block, Borders, Clear, Gauge, Paragraph};
@@ -513,34 +956,14 @@ impl TuiRunner {
             return Ok(());
         };
         
-        let extensions = ["rs", "py", "js", "ts", "tsx", "jsx", "go", "java", "c", "cpp", "h", "hpp", 
-                          "php", "rb", "swift", "kt", "scala", "vue", "svelte", "html", "css", "scss",
-                          "json", "yaml", "yml", "toml", "md", "sql"];
-        
         // First pass: count files to index
-        let files_to_index: Vec<_> = WalkDir::new(&project_path)
-            .into_iter()
-            .filter_map(|e| e.ok())
-            .filter(|e| e.file_type().is_file())
-            .filter(|e| {
-                let path = e.path();
-                // Skip exclusions
-                !path.components().any(|c| {
-                    let s = c.as_os_str().to_string_lossy();
-                    s.starts_with('.') || s == "node_modules" || s == "target" || s == "dist" || s == "build"
-                })
-            })
-            .filter(|e| {
-                let ext = e.path().extension().and_then(|e| e.to_str()).unwrap_or("");
-                extensions.contains(&ext)
-            })
-            .collect();
+        let files_to_index = indexable_files(&project_path);
         
         let total = files_to_index.len();
         let mut indexed = 0;
         
         for (i, entry) in files_to_index.iter().enumerate() {
-            let path = entry.path();
+            let path = entry.as_path();
             let relative = path.strip_prefix(&project_path)
                 .map(|p| p.to_string_lossy().to_string())
                 .unwrap_or_else(|_| path.to_string_lossy().to_string());
@@ -597,8 +1020,9 @@ impl TuiRunner {
             }
         }
         
-        // Refresh system prompt
+        // Refresh system prompt, measured again from the file contents
         self.refresh_system_prompt();
+        self.context_level = ContextLevel::Full;
         
         self.app.add_ai_message(format!("✅ {} fichiers indexés dans SQLite.", indexed));
         Ok(())
@@ -606,82 +1030,139 @@ impl TuiRunner {
 
     /// Incremental reindex: only update files that have changed (hash mismatch)
     fn incremental_reindex(&mut self) -> usize {
-        use walkdir::WalkDir;
-        
         let Some(ref pindex) = self.persistent_index else {
             return 0;
         };
         
-        let project_path = self.app.project_path.clone();
-        let extensions = ["rs", "py", "js", "ts", "tsx", "jsx", "go", "java", "c", "cpp", "h", "hpp", 
-                          "php", "rb", "swift", "kt", "scala", "vue", "svelte", "html", "css", "scss",
-                          "json", "yaml", "yml", "toml", "md", "sql"];
-        let mut updated = 0;
-        
-        for entry in WalkDir::new(&project_path)
-            .into_iter()
-            .filter_map(|e| e.ok())
-            .filter(|e| e.file_type().is_file())
-        {
-            let path = entry.path();
-            
-            // Skip exclusions
-            if path.components().any(|c| {
-                let s = c.as_os_str().to_string_lossy();
-                s.starts_with('.') || s == "node_modules" || s == "target" || s == "dist" || s == "build"
-            }) {
-                continue;
+        // Only reindex files whose hash changed
+        let files = indexable_files(&self.app.project_path);
+        match pindex.sync_files(&files, None, usize::MAX) {
+            Ok((updated, _)) => updated,
+            Err(e) => {
+                self.app.add_ai_message(format!("⚠️ Synchronisation de l'index échouée: {}", e));
+                0
             }
-            
-            let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
-            if !extensions.contains(&ext) {
-                continue;
+        }
+    }
+
+    /// Refresh the project context and template of the system prompt
+    fn refresh_system_prompt(&mut self) {
+        self.codebase_context = self.build_codebase_context().map(|(context, _)| context).unwrap_or_default();
+        self.sqlite_info = sqlite_summary(self.persistent_index.as_ref());
+        if let Ok(template) = prompt::load_template(Some(&self.app.project_path)) {
+            self.prompt_template = template;
+        }
+    }
+
+    /// CODEBASE section of the current scope, with its number of files
+    fn build_codebase_context(&self) -> Result<(CodebaseContext, usize), String> {
+        let index = CodebaseIndex::index_workspace(&self.workspace, self.context_scope, None, &[], 50, |_, _| {})?;
+        Ok((CodebaseContext::from_index(&index, 20000), index.files.len()))
+    }
+
+    /// Switch the CODEBASE section between the whole project and the branch changes
+    fn set_context_scope(&mut self, scope: ContextScope) {
+        let previous = self.context_scope;
+        self.context_scope = scope;
+        match self.build_codebase_context() {
+            Ok((context, files)) => {
+                self.codebase_context = context;
+                self.context_level = ContextLevel::Full;
+                self.app.add_ai_message(match scope {
+                    ContextScope::Diff => format!("🔀 Contexte: {} fichiers modifiés par rapport à main.", files),
+                    ContextScope::Full => "📂 Contexte: projet entier.".to_string(),
+                });
             }
-            
-            if let Ok(content) = std::fs::read_to_string(path) {
-                let relative = path.strip_prefix(&project_path)
-                    .map(|p| p.to_string_lossy().to_string())
-                    .unwrap_or_else(|_| path.to_string_lossy().to_string());
-                
-                // Only reindex if hash changed
-                if pindex.needs_reindex(&relative, &content) {
-                    if pindex.index_file(path, &relative, &content).is_ok() {
-                        updated += 1;
-                    }
-                }
+            Err(e) => {
+                self.context_scope = previous;
+                self.app.add_ai_message(format!("❌ Contexte inchangé: {}", e));
             }
         }
-        
-        updated
     }
 
-    /// Refresh system prompt with current SQLite index info
-    fn refresh_system_prompt(&mut self) {
-        let codebase_context = {
-            let index = CodebaseIndex::index(&self.app.project_path, None, &[], 50).ok();
-            index.map(|i| i.build_context(20000).first().cloned().unwrap_or_default())
-                .unwrap_or_default()
-        };
-        
-        let sqlite_info = if let Some(ref pindex) = self.persistent_index {
-            if let Ok(files) = pindex.list_files() {
-                let file_list: Vec<String> = files.iter()
-                    .take(100)
-                    .map(|f| format!("- {} ({})", f.relative_path, f.extension))
-                    .collect();
-                if !file_list.is_empty() {
-                    format!("\n\nINDEX SQLITE ({} fichiers):\n{}", files.len(), file_list.join("\n"))
-                } else {
-                    String::new()
-                }
-            } else {
-                String::new()
+    /// Tools the current mode may use: none in ASK, read-only ones in PLAN (plus previews in PLAN+), all in CODE and AUTO
+    fn tool_access(&self) -> ToolAccess {
+        ToolAccess::for_mode(self.app.mode, self.app.dry_run)
+    }
+
+    /// Built-in, MCP and project tools documentation, limited to the tools of the mode
+    fn tool_docs(&self) -> String {
+        let access = self.tool_access();
+        let mut docs = tools::get_tools_documentation(access);
+        for extra in [self.mcp_manager.get_tools_documentation(access), self.custom_tools.get_tools_documentation(access)] {
+            if !extra.is_empty() {
+                docs = format!("{}\n{}", docs, extra);
             }
+        }
+        docs
+    }
+
+    /// Whether the current mode lets the model run this tool
+    fn tool_allowed(&self, name: &str) -> bool {
+        let access = self.tool_access();
+        if name.starts_with("mcp_") {
+            access.allows(!self.mcp_manager.is_read_only(name), false)
         } else {
-            String::new()
+            access.allows(tools::is_write_tool(name, &self.custom_tools), true)
+        }
+    }
+
+    /// System prompt with the project context the mode needs and the project memory
+    fn prompt_builder(&self, level: ContextLevel) -> PromptBuilder {
+        PromptBuilder::new(&self.base_prompt)
+            .template(self.prompt_template.as_deref())
+            .tools(&self.tool_docs())
+            .project_context(self.app.mode, self.codebase_context.get(level), &self.sqlite_info)
+            .pinned(&self.pinned_context)
+            .memory(&self.project_memory)
+    }
+
+    /// `/pin <path>`, `/unpin <path>`, or the list of pins for `/pin` alone
+    fn pin_command(&mut self, pin: bool, path: &str) {
+        let root = self.app.project_path.clone();
+        let result = match (pin, path.is_empty()) {
+            (true, true) => Ok(self.app.pinned.clone()),
+            (_, true) => Err("Chemin manquant : /unpin <chemin>".to_string()),
+            (true, false) => pinned::pin(&root, path),
+            (false, false) => pinned::unpin(&root, path),
         };
-        
-        self.system_prompt = format!("{}\n\nCODEBASE:\n{}{}", SYSTEM_PROMPT, codebase_context, sqlite_info);
+        match result {
+            Ok(pins) if pins.is_empty() => {
+                self.app.pinned = pins;
+                self.app.add_ai_message("📌 Aucun fichier épinglé. /pin <chemin> en ajoute un.".to_string());
+            }
+            Ok(pins) => {
+                let list = pins.iter().map(|pin| format!("  - {}", pin)).collect::<Vec<_>>().join("\n");
+                self.app.add_ai_message(format!("📌 Joints à chaque prompt (.codestral/config.toml) :\n{}", list));
+                self.app.pinned = pins;
+            }
+            Err(e) => self.app.add_ai_message(format!("❌ {}", e)),
+        }
+    }
+
+    /// Complete system prompt, with a leaner CODEBASE (repo map, then file list) when it would not
+    /// leave room in the model's window for the conversation and the answer, with the warning to
+    /// show once the request is built
+    fn system_prompt(&mut self, file_context: &str, language_rule: Option<&str>) -> (String, Option<String>) {
+        let window = self.client.context_window() as usize;
+        let budget = window.saturating_sub(self.app.tokens + RESPONSE_RESERVE_TOKENS);
+        // Read again for each request, so edits of the pinned files are sent
+        self.pinned_context = pinned::context(&self.app.project_path, &self.app.pinned);
+        // Only CODE and AUTO have a CODEBASE section to reduce
+        let reducible = matches!(self.app.mode, ChatMode::Code | ChatMode::Auto);
+        let start = if reducible { self.context_level } else { ContextLevel::FileList };
+        let fitted = context_guard::fit(start, budget, |level| {
+            self.prompt_builder(level)
+                .append(file_context)
+                .append(language_rule.unwrap_or(""))
+                .mode(self.app.mode)
+                .build()
+        });
+        let warning = context_guard::downgrade_warning(start, &fitted, budget);
+        if reducible {
+            self.context_level = fitted.level;
+        }
+        (fitted.prompt, warning)
     }
 
     /// Detect file paths in user input and inject their content from SQLite
@@ -696,38 +1177,159 @@ impl TuiRunner {
             Err(_) => return String::new(),
         };
         
-        let mut injected = Vec::new();
-        let input_lower = user_input.to_lowercase();
+        // Only explicit references (@path or exact relative path) are injected
+        let paths: Vec<String> = files.iter().map(|f| f.relative_path.clone()).collect();
+        let mut mentioned = Vec::new();
+        for path in referenced_paths(user_input, &paths) {
+            let Some(file) = files.iter().find(|f| f.relative_path == path) else {
+                continue;
+            };
+            // Retrieve content from SQLite
+            if let Ok(Some(content)) = pindex.get_content(path) {
+                mentioned.push(MentionedFile {
+                    relative_path: file.relative_path.clone(),
+                    extension: file.extension.clone(),
+                    content,
+                });
+            }
+        }
         
-        // Check if user message mentions any indexed file
-        for file in &files {
-            let filename = file.relative_path.split('/').last().unwrap_or(&file.relative_path);
-            let path_lower = file.relative_path.to_lowercase();
-            
-            // Check if file is mentioned (by full path, partial path, or filename)
-            if input_lower.contains(&path_lower) || input_lower.contains(&filename.to_lowercase()) {
-                // Retrieve content from SQLite
-                if let Ok(Some(content)) = pindex.get_content(&file.relative_path) {
-                    // Limit content size (max 5000 chars per file)
-                    let truncated = if content.len() > 5000 {
-                        format!("{}...\n[Contenu tronqué à 5000 caractères]", &content[..5000])
-                    } else {
-                        content
-                    };
-                    injected.push(format!(
-                        "📁 FICHIER DEMANDÉ: {}\n```{}\n{}\n```",
-                        file.relative_path,
-                        file.extension,
-                        truncated
-                    ));
+        // Mentioned symbols (MistralClient::chat, `send_message`): only their definition
+        let mut symbols: Vec<MentionedSymbol> = Vec::new();
+        for (parent, name) in referenced_symbols(user_input) {
+            let Ok(definitions) = pindex.find_symbols(parent, name) else {
+                continue;
+            };
+            if parent.is_none() && definitions.len() > MAX_SYMBOL_MATCHES {
+                continue;
+            }
+            for (path, symbol) in definitions.iter().take(MAX_SYMBOL_MATCHES) {
+                // Already injected whole, or found through another spelling
+                if mentioned.iter().any(|f| &f.relative_path == path)
+                    || symbols.iter().any(|s| &s.relative_path == path && s.start_line == symbol.start_line)
+                {
+                    continue;
+                }
+                if let Ok(Some(content)) = pindex.get_content(path) {
+                    symbols.push(MentionedSymbol::new(path, symbol, &content));
                 }
             }
         }
         
-        if injected.is_empty() {
-            String::new()
-        } else {
-            format!("Voici le contenu des fichiers mentionnés:\n\n{}", injected.join("\n\n"))
+        // Symbols, whole files, outlines of big ones, or truncation notes within the total budget
+        build_injection(&mentioned, &symbols, INJECTION_BUDGET_CHARS)
+    }
+
+    /// Ask the model for the durable facts of the conversation and offer them as a memory.md diff.
+    /// Automatic offers (`on_demand` false) wait for `MIN_EXCHANGES` exchanges not reviewed yet
+    async fn offer_memory_update(&mut self, terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, on_demand: bool) -> Result<(), String> {
+        let exchanges = self.app.messages.iter().filter(|m| m.is_user).count();
+        if !on_demand && (!self.memory_updates || self.app.offline || exchanges < memory_update::MIN_EXCHANGES
+            || self.app.messages.len() <= self.memory_reviewed)
+        {
+            return Ok(());
+        }
+        if exchanges == 0 {
+            self.app.add_ai_message("📭 Rien à retenir : la conversation est vide".to_string());
+            return Ok(());
+        }
+        self.memory_reviewed = self.app.messages.len();
+
+        let history: String = self.app.messages.iter()
+            .map(|m| format!("{}: {}", if m.is_user { "User" } else { "AI" }, m.content))
+            .collect::<Vec<_>>()
+            .join("\n");
+        // Read again: the file may have been edited since startup
+        let memory = prompt::load_memory(&self.app.project_path);
+        self.app.loading = true;
+        let response = self.chat_in_background(memory_update::request(&memory, &history), terminal).await?;
+        self.app.loading = false;
+        let response = match response {
+            Some(Ok(response)) => response,
+            Some(Err(e)) => {
+                self.app.add_ai_message(format!("❌ Mise à jour de memory.md impossible : {}", e));
+                return Ok(());
+            }
+            None => return Ok(()),
+        };
+
+        let facts = memory_update::parse_facts(&response, &memory);
+        if facts.is_empty() {
+            if on_demand {
+                self.app.add_ai_message("🧠 Rien de nouveau à retenir pour memory.md".to_string());
+            }
+            return Ok(());
+        }
+        let change = memory_update::proposal(&self.memory_file, &memory, &facts);
+        if !self.confirm_memory_update(terminal, &change)? {
+            self.app.add_ai_message("🧠 memory.md inchangé".to_string());
+            return Ok(());
+        }
+        let _ = crate::project_dir::ensure(&self.app.project_path);
+        match change.apply() {
+            Ok(()) => {
+                self.project_memory = change.modified.clone();
+                self.app.add_ai_message(format!("🧠 memory.md mis à jour ({} fait(s) ajouté(s))", facts.len()));
+            }
+            Err(e) => self.app.add_ai_message(format!("❌ {}", e)),
+        }
+        Ok(())
+    }
+
+    /// Diff of the proposed memory.md: o/Enter applies it, n/Esc drops it
+    fn confirm_memory_update(&mut self, terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, change: &crate::differ::FileChange) -> Result<bool, String> {
+        use ratatui::layout::Rect;
+        use ratatui::style::{Color, Modifier, Style};
+        use ratatui::text::{Line, Span};
+        use ratatui::widgets::{Block, Borders, Clear, Paragraph};
+
+        let diff = change.unified_diff();
+        let mut scroll: u16 = 0;
+        loop {
+            terminal.draw(|frame| {
+                ui::draw(frame, &self.app);
+
+                let area = frame.area();
+                let width = 90.min(area.width.saturating_sub(4));
+                let height = (diff.lines().count() as u16 + 4).min(area.height.saturating_sub(2));
+                let rect = Rect {
+                    x: (area.width.saturating_sub(width)) / 2,
+                    y: (area.height.saturating_sub(height)) / 2,
+                    width,
+                    height,
+                };
+                frame.render_widget(Clear, rect);
+
+                let mut lines: Vec<Line> = diff.lines().map(|line| {
+                    let color = match line.chars().next() {
+                        Some('+') if !line.starts_with("+++") => Color::Green,
+                        Some('-') if !line.starts_with("---") => Color::Red,
+                        Some('@') => Color::Cyan,
+                        _ => Color::Gray,
+                    };
+                    Line::from(Span::styled(line.to_string(), Style::default().fg(color)))
+                }).collect();
+                lines.push(Line::from(""));
+                lines.push(Line::from(Span::styled(
+                    " o/Enter: ajouter à memory.md • n/Échap: ignorer • ↑↓: défiler",
+                    Style::default().fg(Color::DarkGray).add_modifier(Modifier::ITALIC),
+                )));
+                let block = Block::default()
+                    .title(format!(" 🧠 memory.md : {} ", change.description))
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(Color::Cyan));
+                frame.render_widget(Paragraph::new(lines).block(block).scroll((scroll, 0)), rect);
+            }).map_err(|e| e.to_string())?;
+
+            if let Event::Key(key) = event::read().map_err(|e| e.to_string())? {
+                match key.code {
+                    KeyCode::Enter | KeyCode::Char('o') | KeyCode::Char('O') | KeyCode::Char('y') => return Ok(true),
+                    KeyCode::Esc | KeyCode::Char('n') | KeyCode::Char('N') => return Ok(false),
+                    KeyCode::Up => scroll = scroll.saturating_sub(1),
+                    KeyCode::Down => scroll = scroll.saturating_add(1),
+                    _ => {}
+                }
+            }
         }
     }
 
@@ -738,9 +1340,7 @@ impl TuiRunner {
         use crossterm::terminal::LeaveAlternateScreen;
         
         // Create directory if needed
-        if let Some(parent) = self.memory_file.parent() {
-            let _ = std::fs::create_dir_all(parent);
-        }
+        let _ = crate::project_dir::ensure(&self.app.project_path);
         
         // Create file with template if it doesn't exist
         if !self.memory_file.exists() {
@@ -776,19 +1376,51 @@ Ces instructions sont lues avec chaque prompt pour ce projet.
         }
     }
 
-    async fn show_resume_menu(&mut self, terminal: &mut Terminal<CrosstermBackend<io::Stdout>>) -> Result<(), String> {
-        use crate::chat_storage::ChatStorage;
-        use ratatui::layout::{Constraint, Direction, Layout, Rect};
-        use ratatui::style::{Color, Modifier, Style};
-        use ratatui::text::{Line, Span};
-        use ratatui::widgets::{Block, Borders, Clear, List, ListItem};
-        
-        let storage = ChatStorage::new()?;
-        let chats = storage.list()?;
-        
-        if chats.is_empty() {
-            self.app.add_ai_message("📭 Aucune conversation sauvegardée".to_string());
-            return Ok(());
+    /// Leave the TUI to resolve failed hunks in the editor, then record what was applied
+    fn resolve_conflicts(&mut self, terminal: &mut Terminal<CrosstermBackend<io::Stdout>>) -> Result<(), String> {
+        let mut changes = ChangeSet {
+            patch_failures: std::mem::take(&mut self.pending_conflicts),
+            ..Default::default()
+        };
+
+        disable_raw_mode().map_err(|e| e.to_string())?;
+        execute!(terminal.backend_mut(), DisableBracketedPaste, LeaveAlternateScreen).map_err(|e| e.to_string())?;
+        changes.resolve_failures_interactive();
+        enable_raw_mode().map_err(|e| e.to_string())?;
+        execute!(terminal.backend_mut(), EnterAlternateScreen, EnableBracketedPaste).map_err(|e| e.to_string())?;
+        terminal.clear().map_err(|e| e.to_string())?;
+        while event::poll(Duration::from_millis(10)).unwrap_or(false) {
+            let _ = event::read();
+        }
+
+        for change in &changes.resolved {
+            self.app.add_ai_message(format!("✏️ Changement placé manuellement: {}", change.path));
+            self.record(SessionEvent::Diff {
+                path: change.path.clone(),
+                diff: change.unified_diff(),
+                applied: true,
+            });
+        }
+        Ok(())
+    }
+
+    /// Saved conversations, or the trash for `/trash`: Tab switches between the two, Suppr moves
+    /// a conversation to the trash, Enter resumes it or restores it from the trash
+    async fn show_resume_menu(&mut self, terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, mut trash: bool) -> Result<(), String> {
+        use crate::chat_storage::{time_ago, ChatSource, ChatStorage, TRASH_RETENTION_DAYS};
+        use ratatui::layout::{Constraint, Direction, Layout, Rect};
+        use ratatui::style::{Color, Modifier, Style};
+        use ratatui::text::{Line, Span};
+        use ratatui::widgets::{Block, Borders, Clear, List, ListItem};
+        
+        let storage = ChatStorage::new()?;
+        let load = |trash: bool| if trash { storage.list_trash() } else { storage.list() };
+        let mut chats = load(trash)?;
+        
+        if chats.is_empty() {
+            let empty = if trash { "🗑 Corbeille vide" } else { "📭 Aucune conversation sauvegardée" };
+            self.app.add_ai_message(empty.to_string());
+            return Ok(());
         }
         
         let mut selected: usize = 0;
@@ -800,8 +1432,8 @@ Ces instructions sont lues avec chaque prompt pour ce projet.
                 
                 // Draw overlay menu
                 let area = frame.area();
-                let menu_width = 60.min(area.width.saturating_sub(4));
-                let menu_height = (chats.len() + 2).min(15) as u16;
+                let menu_width = 70.min(area.width.saturating_sub(4));
+                let menu_height = (chats.len().max(1) + 3).min(16) as u16;
                 
                 let menu_area = Rect {
                     x: (area.width - menu_width) / 2,
@@ -812,15 +1444,21 @@ Ces instructions sont lues avec chaque prompt pour ce projet.
                 
                 frame.render_widget(Clear, menu_area);
                 
+                let (title, keys) = if trash {
+                    (format!(" Corbeille ({} jours) ", TRASH_RETENTION_DAYS), " Entrée: restaurer · V: vider · Tab: conversations ")
+                } else {
+                    (" Reprendre une conversation ".to_string(), " Entrée: reprendre · Suppr: corbeille · Tab: corbeille ")
+                };
                 let block = Block::default()
-                    .title(" Reprendre une conversation ")
+                    .title(title)
+                    .title_bottom(Line::from(Span::styled(keys, Style::default().fg(Color::DarkGray))))
                     .borders(Borders::ALL)
-                    .border_style(Style::default().fg(Color::Cyan));
+                    .border_style(Style::default().fg(if trash { Color::Red } else { Color::Cyan }));
                 
                 let inner = block.inner(menu_area);
                 frame.render_widget(block, menu_area);
                 
-                let items: Vec<ListItem> = chats.iter()
+                let mut items: Vec<ListItem> = chats.iter()
                     .enumerate()
                     .map(|(i, chat)| {
                         let style = if i == selected {
@@ -829,13 +1467,27 @@ Ces instructions sont lues avec chaque prompt pour ce projet.
                             Style::default()
                         };
                         let prefix = if i == selected { "▶ " } else { "  " };
+                        let origin = match chat.source {
+                            ChatSource::Gui => "[GUI] ",
+                            ChatSource::Cli => "",
+                        };
+                        let when = match chat.deleted_at {
+                            Some(deleted_at) => format!(
+                                " (supprimée {}, encore {} j)", time_ago(deleted_at), chat.days_left_in_trash()
+                            ),
+                            None => format!(" ({})", chat.time_ago()),
+                        };
                         ListItem::new(Line::from(vec![
                             Span::raw(prefix),
+                            Span::styled(origin, Style::default().fg(Color::Magenta)),
                             Span::styled(&chat.title, style),
-                            Span::styled(format!(" ({})", chat.time_ago()), Style::default().fg(Color::DarkGray)),
+                            Span::styled(when, Style::default().fg(Color::DarkGray)),
                         ]))
                     })
                     .collect();
+                if items.is_empty() {
+                    items.push(ListItem::new(Span::styled("  (vide)", Style::default().fg(Color::DarkGray))));
+                }
                 
                 let list = List::new(items);
                 frame.render_widget(list, inner);
@@ -855,30 +1507,49 @@ Ces instructions sont lues avec chaque prompt pour ce projet.
                                 selected += 1;
                             }
                         }
-                        KeyCode::Enter => {
-                            // Load selected chat
-                            if let Some(chat) = chats.get(selected) {
-                                self.app.messages.clear();
-                                for msg in &chat.messages {
-                                    self.app.messages.push(crate::tui::app::ChatMessage {
-                                        role: msg.role.clone(),
-                                        content: msg.content.clone(),
-                                        is_user: msg.role == "user",
-                                    });
+                        KeyCode::Tab => {
+                            trash = !trash;
+                            chats = load(trash)?;
+                            selected = 0;
+                        }
+                        KeyCode::Delete if !trash => {
+                            let Some(chat) = chats.get(selected) else { continue };
+                            if self.current_chat.as_ref().map(|current| current.id == chat.id).unwrap_or(false) {
+                                self.app.add_ai_message("⚠️ Conversation en cours : /new avant de la mettre à la corbeille.".to_string());
+                                continue;
+                            }
+                            if let Err(e) = storage.delete(&chat.id) {
+                                self.app.add_ai_message(format!("❌ {}", e));
+                            }
+                            chats = load(trash)?;
+                            selected = selected.min(chats.len().saturating_sub(1));
+                        }
+                        KeyCode::Char('v') | KeyCode::Char('V') if trash => {
+                            match storage.empty_trash() {
+                                Ok(count) => self.app.add_ai_message(format!("🗑 Corbeille vidée ({} conversation(s) supprimée(s))", count)),
+                                Err(e) => self.app.add_ai_message(format!("❌ {}", e)),
+                            }
+                            chats = load(trash)?;
+                            selected = 0;
+                        }
+                        KeyCode::Enter if trash => {
+                            let Some(chat) = chats.get(selected) else { continue };
+                            match storage.restore(&chat.id) {
+                                Ok(restored) => {
+                                    self.app.add_ai_message(format!("♻️ Conversation « {} » restaurée", restored.title));
+                                    // Back to the conversations, on the restored one
+                                    trash = false;
+                                    chats = load(trash)?;
+                                    selected = chats.iter().position(|chat| chat.id == restored.id).unwrap_or(0);
                                 }
-                                // Reset app state after loading
-                                self.app.scroll = 0;
-                                self.app.loading = false;
-                                self.app.input.clear();
-                                self.app.cursor_pos = 0;
-                                // Recalculate tokens
-                                self.app.tokens = self.app.messages.iter()
-                                    .map(|m| m.content.len() / 4)
-                                    .sum();
-                                
-                                // Add UI message for the user (context is understood from history)
-                                self.app.add_ai_message("📜 Conversation reprise. L'historique a été chargé.".to_string());
+                                Err(e) => self.app.add_ai_message(format!("❌ {}", e)),
                             }
+                        }
+                        KeyCode::Enter => {
+                                // Load selected chat
+                                if let Some(chat) = chats.get(selected) {
+                                    self.load_chat(chat.clone());
+                                }
                             break;
                         }
                         _ => {}
@@ -894,155 +1565,715 @@ Ces instructions sont lues avec chaque prompt pour ce projet.
     }
 }
 
-enum CommandAction {
-    New,
-    Resume,
-    Save,
-    Memory,
-    Questions,
-    Exit,
-    Reindex,
-}
+impl TuiRunner {
+    /// Ask for each dangerous tool call (once, for the session, or deny), log the decisions,
+    /// then send the results back to the AI
+    async fn confirm_dangerous_tools(&mut self, terminal: &mut Terminal<CrosstermBackend<io::Stdout>>) -> Result<(), String> {
+        use ratatui::layout::Rect;
+        use ratatui::style::{Color, Modifier, Style};
+        use ratatui::text::{Line, Span};
+        use ratatui::widgets::{Block, Borders, Clear, Paragraph, Wrap};
 
-/// Multi-question form with Tab navigation and optional choices
-pub struct QuestionForm {
-    pub questions: Vec<String>,
-    pub choices: Vec<Vec<String>>,  // Optional choices for each question
-    pub selected_choice: Vec<Option<usize>>,  // Selected choice index (None = custom input mode)
-    pub answers: Vec<String>,
-    pub current_field: usize,
-    pub cursor_pos: usize,
-    pub in_choice_mode: bool,  // true = selecting from choices, false = typing custom
-}
+        for pending in std::mem::take(&mut self.pending_approvals) {
+            let decision = if self.audit.is_remembered(&pending.call.name, &pending.command) {
+                Decision::Remembered
+            } else {
+                loop {
+                    terminal.draw(|frame| {
+                        ui::draw(frame, &self.app);
+
+                        let area = frame.area();
+                        let width = 70.min(area.width.saturating_sub(4));
+                        let height = 9.min(area.height);
+                        let rect = Rect {
+                            x: (area.width.saturating_sub(width)) / 2,
+                            y: (area.height.saturating_sub(height)) / 2,
+                            width,
+                            height,
+                        };
+                        frame.render_widget(Clear, rect);
+
+                        let lines = vec![
+                            Line::from(vec![
+                                Span::styled(" Outil: ", Style::default().add_modifier(Modifier::BOLD)),
+                                Span::raw(pending.call.name.clone()),
+                            ]),
+                            Line::from(Span::styled(format!(" $ {}", pending.command), Style::default().fg(Color::Yellow))),
+                            Line::from(""),
+                            Line::from(Span::styled(
+                                " o/Enter: exécuter • t: toujours (cette session) • n/Échap: refuser",
+                                Style::default().fg(Color::DarkGray).add_modifier(Modifier::ITALIC),
+                            )),
+                        ];
+                        let title = if pending.call.name == "rename_symbol" { " ✏️ Renommage à confirmer " } else { " ⚠️ Commande dangereuse " };
+                        let block = Block::default()
+                            .title(title)
+                            .borders(Borders::ALL)
+                            .border_style(Style::default().fg(Color::Red));
+                        frame.render_widget(Paragraph::new(lines).block(block).wrap(Wrap { trim: false }), rect);
+                    }).map_err(|e| e.to_string())?;
+
+                    if let Event::Key(key) = event::read().map_err(|e| e.to_string())? {
+                        match key.code {
+                            KeyCode::Enter | KeyCode::Char('o') | KeyCode::Char('O') | KeyCode::Char('y') => break Decision::Approved,
+                            KeyCode::Char('t') | KeyCode::Char('T') => break Decision::ApprovedForSession,
+                            KeyCode::Esc | KeyCode::Char('n') | KeyCode::Char('N') => break Decision::Denied,
+                            _ => {}
+                        }
+                    }
+                }
+            };
 
-impl QuestionForm {
-    pub fn new(questions: Vec<String>) -> Self {
-        let count = questions.len();
-        Self {
-            questions,
-            choices: vec![Vec::new(); count],
-            selected_choice: vec![None; count],
-            answers: vec![String::new(); count],
-            current_field: 0,
-            cursor_pos: 0,
-            in_choice_mode: false,
+            let result = if decision == Decision::Denied {
+                let _ = self.audit.log(&self.app.mode.to_string(), &pending.call.name, &pending.command, decision, None);
+                tools::ToolResult {
+                    name: pending.call.name.clone(),
+                    success: false,
+                    output: format!("Commande refusée par l'utilisateur: {}", pending.command),
+                    needs_confirmation: false,
+                }
+            } else {
+                if decision == Decision::ApprovedForSession {
+                    self.audit.remember(&pending.call.name, &pending.command);
+                }
+                self.run_approved_tool(&pending.call, &pending.command, decision)
+            };
+            self.app.attach_tool_results(pending.message_index, vec![tools::format_tool_result(&result)]);
         }
+
+        self.app.loading = true;
+        terminal.draw(|f| ui::draw(f, &self.app)).map_err(|e| e.to_string())?;
+        self.send_message_internal(String::new(), terminal).await
     }
 
-    pub fn with_choices(questions: Vec<String>, choices: Vec<Vec<String>>) -> Self {
-        let count = questions.len();
-        Self {
-            questions,
-            choices: choices.into_iter().chain(std::iter::repeat(Vec::new())).take(count).collect(),
-            selected_choice: vec![None; count],
-            answers: vec![String::new(); count],
-            current_field: 0,
-            cursor_pos: 0,
-            in_choice_mode: true,  // Start in choice mode if choices available
+    /// Full-screen diff viewer over the files proposed in CODE mode: each hunk is accepted
+    /// (y) or rejected (n), then only the accepted hunks are written and journaled together
+    async fn review_changes(&mut self, terminal: &mut Terminal<CrosstermBackend<io::Stdout>>) -> Result<(), String> {
+        use ratatui::style::{Color, Modifier, Style};
+        use ratatui::text::{Line, Span};
+        use ratatui::widgets::{Block, Borders, Clear, Paragraph};
+
+        let mut review = HunkReview::new(&self.app.project_path, std::mem::take(&mut self.pending_reviews));
+        // Some(scroll) once the user scrolled away from the current hunk
+        let mut scroll: Option<usize> = None;
+        while !review.is_done() {
+            let (lines, current) = review.lines();
+            let file = &review.files[review.file];
+            let title = format!(
+                " 🔍 {}{} — hunk {}/{} • fichier {}/{} ",
+                file.display,
+                if file.new_file { " (nouveau)" } else { "" },
+                review.hunk + 1,
+                file.hunks.len(),
+                review.file + 1,
+                review.files.len(),
+            );
+            let top = scroll.unwrap_or(current.saturating_sub(2)).min(lines.len().saturating_sub(1));
+            terminal.draw(|frame| {
+                let area = frame.area();
+                frame.render_widget(Clear, area);
+                let block = Block::default()
+                    .title(Span::styled(title.clone(), Style::default().add_modifier(Modifier::BOLD)))
+                    .title_bottom(Line::from(Span::styled(
+                        " y: accepter • n: rejeter • a: accepter le reste du fichier • Échap: rejeter le reste • ↑↓/PageUp/PageDown: défiler ",
+                        Style::default().fg(Color::DarkGray),
+                    )))
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(Color::Cyan));
+                frame.render_widget(Paragraph::new(lines[top..].to_vec()).block(block), area);
+            }).map_err(|e| e.to_string())?;
+
+            let Event::Key(key) = event::read().map_err(|e| e.to_string())? else {
+                continue;
+            };
+            match key.code {
+                KeyCode::Char('y') | KeyCode::Char('o') | KeyCode::Enter => review.decide(true),
+                KeyCode::Char('n') => review.decide(false),
+                KeyCode::Char('a') => review.decide_file(true),
+                KeyCode::Esc | KeyCode::Char('q') => review.reject_rest(),
+                KeyCode::Up => scroll = Some(top.saturating_sub(1)),
+                KeyCode::Down => scroll = Some(top + 1),
+                KeyCode::PageUp => scroll = Some(top.saturating_sub(10)),
+                KeyCode::PageDown => scroll = Some(top + 10),
+                _ => continue,
+            }
+            if !matches!(key.code, KeyCode::Up | KeyCode::Down | KeyCode::PageUp | KeyCode::PageDown) {
+                scroll = None;
+            }
+        }
+
+        let mut journal = ChangeJournal::new();
+        let mut written = Vec::new();
+        for file in &review.files {
+            let path = self.app.project_path.join(&file.change.path);
+            let applied = match file.accepted_change() {
+                None => {
+                    self.app.add_ai_message(format!("✗ {}: modifications rejetées", file.display));
+                    false
+                }
+                // Written meanwhile (another tool, the user): the reviewed hunks no longer match
+                Some(_) if std::fs::read_to_string(&path).ok() != (!file.new_file).then(|| file.change.original.clone()) => {
+                    self.app.add_ai_message(format!("⚠️ {} a changé pendant la revue: fichier ignoré", file.display));
+                    false
+                }
+                Some(change) => {
+                    let result = if file.new_file {
+                        NewFile { path: change.path.clone(), content: change.modified.clone(), description: String::new() }.apply()
+                    } else {
+                        change.apply()
+                    };
+                    match result {
+                        Ok(()) => {
+                            let before = (!file.new_file).then(|| change.original.clone());
+                            journal.record(&self.app.project_path, &path, before.clone(), Some(change.modified.clone()));
+                            self.session_changes.record(&self.app.project_path, &path, before, Some(change.modified.clone()));
+                            written.push(path);
+                            self.app.add_ai_message(format!("✓ {}: {}/{} hunk(s) appliqué(s)", file.display, file.accepted_count(), file.hunks.len()));
+                            true
+                        }
+                        Err(e) => {
+                            self.app.add_ai_message(format!("❌ {}", e));
+                            false
+                        }
+                    }
+                }
+            };
+            let change = file.accepted_change().unwrap_or_else(|| file.change.clone());
+            self.record(SessionEvent::Diff {
+                path: file.change.path.clone(),
+                diff: change.unified_diff(),
+                applied,
+            });
+        }
+
+        if let Err(e) = journal.save(&self.app.project_path) {
+            self.app.add_ai_message(format!("⚠️ {}", e));
+        }
+        if !journal.is_empty() && auto_commit::enabled(&self.app.project_path) {
+            match auto_commit::commit(&self.app.project_path, &journal.paths(&self.app.project_path), &journal.stats(), self.client.as_ref()).await {
+                Ok(Some(commit)) => self.app.add_ai_message(format!("📝 Commit {}", commit)),
+                Ok(None) => {}
+                Err(e) => self.app.add_ai_message(format!("⚠️ {}", e)),
+            }
         }
+        self.sync_changed_files(&written);
+        Ok(())
     }
 
-    pub fn current_choices(&self) -> &[String] {
-        &self.choices[self.current_field]
+    /// Ask for each deletion proposed in CODE mode; the approved files are moved to
+    /// `.codestral/trash/` and journaled together, so /undo restores them
+    async fn confirm_deletions(&mut self, terminal: &mut Terminal<CrosstermBackend<io::Stdout>>) -> Result<(), String> {
+        use ratatui::layout::Rect;
+        use ratatui::style::{Color, Modifier, Style};
+        use ratatui::text::{Line, Span};
+        use ratatui::widgets::{Block, Borders, Clear, Paragraph, Wrap};
+
+        /// Lines of the file shown in the dialog
+        const PREVIEW_LINES: usize = 8;
+
+        let mut journal = ChangeJournal::new();
+        let mut deleted = Vec::new();
+        for deletion in std::mem::take(&mut self.pending_deletions) {
+            let approved = loop {
+                terminal.draw(|frame| {
+                    ui::draw(frame, &self.app);
+
+                    let area = frame.area();
+                    let width = 70.min(area.width.saturating_sub(4));
+                    let height = (PREVIEW_LINES as u16 + 7).min(area.height);
+                    let rect = Rect {
+                        x: (area.width.saturating_sub(width)) / 2,
+                        y: (area.height.saturating_sub(height)) / 2,
+                        width,
+                        height,
+                    };
+                    frame.render_widget(Clear, rect);
+
+                    let mut lines = vec![
+                        Line::from(vec![
+                            Span::styled(" Fichier: ", Style::default().add_modifier(Modifier::BOLD)),
+                            Span::raw(deletion.path.clone()),
+                            Span::styled(format!(" ({} lignes)", deletion.content.lines().count()), Style::default().fg(Color::DarkGray)),
+                        ]),
+                        Line::from(""),
+                    ];
+                    lines.extend(deletion.content.lines().take(PREVIEW_LINES)
+                        .map(|line| Line::from(Span::styled(format!(" -{}", line), Style::default().fg(Color::Red)))));
+                    lines.push(Line::from(""));
+                    lines.push(Line::from(Span::styled(
+                        " o/Enter: supprimer (déplacé dans .codestral/trash) • n/Échap: garder",
+                        Style::default().fg(Color::DarkGray).add_modifier(Modifier::ITALIC),
+                    )));
+                    let block = Block::default()
+                        .title(" 🗑 Supprimer ce fichier ? ")
+                        .borders(Borders::ALL)
+                        .border_style(Style::default().fg(Color::Red));
+                    frame.render_widget(Paragraph::new(lines).block(block).wrap(Wrap { trim: false }), rect);
+                }).map_err(|e| e.to_string())?;
+
+                if let Event::Key(key) = event::read().map_err(|e| e.to_string())? {
+                    match key.code {
+                        KeyCode::Enter | KeyCode::Char('o') | KeyCode::Char('O') | KeyCode::Char('y') => break true,
+                        KeyCode::Esc | KeyCode::Char('n') | KeyCode::Char('N') => break false,
+                        _ => {}
+                    }
+                }
+            };
+
+            let applied = approved && match deletion.trash(&self.app.project_path) {
+                Ok(trashed) => {
+                    let path = self.app.project_path.join(&deletion.path);
+                    journal.record(&self.app.project_path, &path, Some(deletion.content.clone()), None);
+                    self.session_changes.record(&self.app.project_path, &path, Some(deletion.content.clone()), None);
+                    deleted.push(path);
+                    self.app.add_ai_message(format!("🗑 {} supprimé (corbeille: {})", deletion.path, trashed.display()));
+                    true
+                }
+                Err(e) => {
+                    self.app.add_ai_message(format!("❌ {}", e));
+                    false
+                }
+            };
+            if !approved {
+                self.app.add_ai_message(format!("🗑 Suppression refusée: {}", deletion.path));
+            }
+            self.record(SessionEvent::Diff {
+                path: deletion.path.clone(),
+                diff: deletion.unified_diff(),
+                applied,
+            });
+        }
+
+        if let Err(e) = journal.save(&self.app.project_path) {
+            self.app.add_ai_message(format!("⚠️ {}", e));
+        }
+        if !journal.is_empty() && auto_commit::enabled(&self.app.project_path) {
+            match auto_commit::commit(&self.app.project_path, &journal.paths(&self.app.project_path), &journal.stats(), self.client.as_ref()).await {
+                Ok(Some(commit)) => self.app.add_ai_message(format!("📝 Commit {}", commit)),
+                Ok(None) => {}
+                Err(e) => self.app.add_ai_message(format!("⚠️ {}", e)),
+            }
+        }
+        self.sync_changed_files(&deleted);
+        Ok(())
     }
 
-    pub fn has_choices(&self) -> bool {
-        !self.choices[self.current_field].is_empty()
+    /// Overlay listing the files changed in the session: view the diff, revert a file, or open it in $EDITOR
+    fn show_changes(&mut self, terminal: &mut Terminal<CrosstermBackend<io::Stdout>>) -> Result<(), String> {
+        use ratatui::layout::Rect;
+        use ratatui::style::{Color, Modifier, Style};
+        use ratatui::text::{Line, Span};
+        use ratatui::widgets::{Block, Borders, Clear, Paragraph};
+
+        if self.session_changes.is_empty() {
+            self.app.add_ai_message("📭 Aucun fichier modifié dans cette session".to_string());
+            return Ok(());
+        }
+
+        let mut selected = 0;
+        // Some(scroll) while the diff of the selected file is shown
+        let mut diff_scroll: Option<u16> = None;
+        let mut status: Option<String> = None;
+        let mut touched = Vec::new();
+
+        loop {
+            terminal.draw(|frame| {
+                ui::draw(frame, &self.app);
+
+                let area = frame.area();
+                let width = 90.min(area.width.saturating_sub(4));
+                let height = area.height.saturating_sub(4).min(30);
+                let rect = Rect {
+                    x: (area.width.saturating_sub(width)) / 2,
+                    y: (area.height.saturating_sub(height)) / 2,
+                    width,
+                    height,
+                };
+                frame.render_widget(Clear, rect);
+
+                let file = &self.session_changes.files[selected];
+                let (title, mut lines, scroll) = match diff_scroll {
+                    Some(scroll) => {
+                        let lines: Vec<Line> = file.diff().lines().map(|line| {
+                            let color = match line.chars().next() {
+                                Some('+') if !line.starts_with("+++") => Color::Green,
+                                Some('-') if !line.starts_with("---") => Color::Red,
+                                Some('@') => Color::Cyan,
+                                _ => Color::Reset,
+                            };
+                            Line::from(Span::styled(line.to_string(), Style::default().fg(color)))
+                        }).collect();
+                        (format!(" Diff: {} ", file.display), lines, scroll)
+                    }
+                    None => {
+                        let lines = self.session_changes.files.iter().enumerate().map(|(i, f)| {
+                            let style = if i == selected {
+                                Style::default().bg(Color::Rgb(60, 60, 100)).add_modifier(Modifier::BOLD)
+                            } else {
+                                Style::default()
+                            };
+                            Line::from(vec![
+                                Span::raw(if i == selected { "▶ " } else { "  " }),
+                                Span::styled(format!("{:<9}", f.status()), Style::default().fg(Color::DarkGray)),
+                                Span::styled(f.display.clone(), style),
+                            ])
+                        }).collect();
+                        (" Fichiers modifiés ".to_string(), lines, 0)
+                    }
+                };
+
+                lines.push(Line::from(""));
+                lines.push(Line::from(Span::styled(
+                    status.clone().unwrap_or_else(|| match diff_scroll {
+                        Some(_) => " ↑↓/PageUp/PageDown: défiler • Échap: retour à la liste".to_string(),
+                        None => " Enter: diff • r: annuler le changement • e: ouvrir dans $EDITOR • Échap: fermer".to_string(),
+                    }),
+                    Style::default().fg(Color::DarkGray).add_modifier(Modifier::ITALIC),
+                )));
+
+                let block = Block::default()
+                    .title(title)
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(Color::Cyan));
+                frame.render_widget(Paragraph::new(lines).block(block).scroll((scroll, 0)), rect);
+            }).map_err(|e| e.to_string())?;
+
+            let Event::Key(key) = event::read().map_err(|e| e.to_string())? else {
+                continue;
+            };
+            status = None;
+            match (diff_scroll, key.code) {
+                (Some(_), KeyCode::Esc | KeyCode::Enter) => diff_scroll = None,
+                (Some(scroll), KeyCode::Up) => diff_scroll = Some(scroll.saturating_sub(1)),
+                (Some(scroll), KeyCode::Down) => diff_scroll = Some(scroll.saturating_add(1)),
+                (Some(scroll), KeyCode::PageUp) => diff_scroll = Some(scroll.saturating_sub(10)),
+                (Some(scroll), KeyCode::PageDown) => diff_scroll = Some(scroll.saturating_add(10)),
+                (Some(_), _) => {}
+                (None, KeyCode::Esc) => break,
+                (None, KeyCode::Up) => selected = selected.saturating_sub(1),
+                (None, KeyCode::Down) => selected = (selected + 1).min(self.session_changes.files.len() - 1),
+                (None, KeyCode::Enter) => diff_scroll = Some(0),
+                (None, KeyCode::Char('r')) => {
+                    let display = self.session_changes.files[selected].display.clone();
+                    status = Some(match self.session_changes.revert(selected) {
+                        Ok(()) => {
+                            touched.push(self.session_changes.files[selected].path.clone());
+                            self.app.add_ai_message(format!("↩️ Changement annulé: {}", display));
+                            format!(" ↩️ {} restauré", display)
+                        }
+                        Err(e) => format!(" ❌ {}", e),
+                    });
+                }
+                (None, KeyCode::Char('e')) => {
+                    let path = self.session_changes.files[selected].path.clone();
+                    disable_raw_mode().map_err(|e| e.to_string())?;
+                    execute!(terminal.backend_mut(), DisableBracketedPaste, LeaveAlternateScreen).map_err(|e| e.to_string())?;
+                    let editor = std::env::var("EDITOR").unwrap_or_else(|_| "vi".to_string());
+                    let opened = std::process::Command::new(&editor).arg(&path).status();
+                    enable_raw_mode().map_err(|e| e.to_string())?;
+                    execute!(terminal.backend_mut(), EnterAlternateScreen, EnableBracketedPaste).map_err(|e| e.to_string())?;
+                    terminal.clear().map_err(|e| e.to_string())?;
+                    match opened {
+                        Ok(_) => {
+                            let after = std::fs::read_to_string(&path).ok();
+                            self.session_changes.record(&self.app.project_path, &path, None, after);
+                            touched.push(path);
+                        }
+                        Err(e) => status = Some(format!(" ❌ {}: {}", editor, e)),
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        self.sync_changed_files(&touched);
+        Ok(())
     }
 
-    pub fn next_field(&mut self) {
-        if self.current_field < self.questions.len() - 1 {
-            self.current_field += 1;
-            self.cursor_pos = self.answers[self.current_field].chars().count();
-            self.in_choice_mode = self.has_choices() && self.selected_choice[self.current_field].is_some();
+    /// File shown by `/open`, resolved like an `@mention` among the indexed files, else as a path
+    /// of the workspace. Without a path, the last file mentioned in the conversation
+    fn preview_target(&self, argument: &str) -> Result<(String, String), String> {
+        let indexed: Vec<String> = self.persistent_index.as_ref()
+            .and_then(|index| index.list_files().ok())
+            .map(|files| files.into_iter().map(|file| file.relative_path).collect())
+            .unwrap_or_default();
+        let argument = argument.trim_start_matches('@');
+        let relative = if argument.is_empty() {
+            self.app.messages.iter().rev()
+                .filter(|message| message.is_user)
+                .find_map(|message| referenced_paths(&message.content, &indexed).last().map(|path| path.to_string()))
+                .ok_or("Usage: /open <chemin> (aucun fichier mentionné dans la conversation)")?
+        } else {
+            referenced_paths(&format!("@{}", argument), &indexed).first()
+                .map(|path| path.to_string())
+                .unwrap_or_else(|| argument.to_string())
+        };
+
+        let path = self.workspace.resolve(&relative);
+        if !self.workspace.contains(&path) {
+            return Err(format!("{} est hors du projet", relative));
+        }
+        match std::fs::read_to_string(&path) {
+            Ok(content) => Ok((self.workspace.display(&path), content)),
+            // Deleted or unreadable since indexing: the indexed copy is still worth showing
+            Err(e) => self.persistent_index.as_ref()
+                .and_then(|index| index.get_content(&relative).ok().flatten())
+                .map(|content| (format!("{} (copie de l'index)", relative), content))
+                .ok_or_else(|| format!("Impossible de lire {}: {}", relative, e)),
         }
     }
 
-    pub fn prev_field(&mut self) {
-        if self.current_field > 0 {
-            self.current_field -= 1;
-            self.cursor_pos = self.answers[self.current_field].chars().count();
-            self.in_choice_mode = self.has_choices() && self.selected_choice[self.current_field].is_some();
+    /// Read-only, syntax-highlighted view of a file over the chat
+    fn show_file_preview(&mut self, argument: &str, terminal: &mut Terminal<CrosstermBackend<io::Stdout>>) -> Result<(), String> {
+        use ratatui::layout::Rect;
+        use ratatui::style::{Color, Style};
+        use ratatui::text::{Line, Span};
+        use ratatui::widgets::{Block, Borders, Clear, Paragraph};
+
+        let (title, content) = match self.preview_target(argument) {
+            Ok(target) => target,
+            Err(e) => {
+                self.app.add_ai_message(format!("❌ {}", e));
+                return Ok(());
+            }
+        };
+        let extension = title.rsplit('.').next().unwrap_or("").to_lowercase();
+        let total = content.lines().count();
+        let number_width = total.max(1).to_string().len();
+        let mut highlighter = CodeHighlighter::new(&extension);
+        let lines: Vec<Line> = content.lines().enumerate().map(|(i, line)| {
+            let mut code = highlighter.line(line);
+            code.spans.insert(0, Span::styled(
+                format!("{:>width$} │ ", i + 1, width = number_width),
+                Style::default().fg(Color::DarkGray),
+            ));
+            code
+        }).collect();
+
+        let mut scroll: usize = 0;
+        loop {
+            let mut page = 1;
+            terminal.draw(|frame| {
+                ui::draw(frame, &self.app);
+
+                let area = frame.area();
+                let rect = Rect {
+                    x: 1,
+                    y: 1,
+                    width: area.width.saturating_sub(2),
+                    height: area.height.saturating_sub(2),
+                };
+                frame.render_widget(Clear, rect);
+                page = rect.height.saturating_sub(2).max(1) as usize;
+
+                let block = Block::default()
+                    .title(format!(" 📄 {} ({} lignes) ", title, total))
+                    .title_bottom(" ↑↓/PageUp/PageDown/Début/Fin: défiler • Échap: fermer ")
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(Color::Cyan));
+                let visible: Vec<Line> = lines.iter().skip(scroll).take(page).cloned().collect();
+                frame.render_widget(Paragraph::new(visible).block(block), rect);
+            }).map_err(|e| e.to_string())?;
+
+            let Event::Key(key) = event::read().map_err(|e| e.to_string())? else {
+                continue;
+            };
+            let last = total.saturating_sub(page);
+            scroll = match key.code {
+                KeyCode::Esc | KeyCode::Char('q') => break,
+                KeyCode::Up => scroll.saturating_sub(1),
+                KeyCode::Down => scroll + 1,
+                KeyCode::PageUp => scroll.saturating_sub(page),
+                KeyCode::PageDown => scroll + page,
+                KeyCode::Home => 0,
+                KeyCode::End => last,
+                _ => scroll,
+            }
+            .min(last);
         }
+        Ok(())
     }
 
-    pub fn select_choice_up(&mut self) {
-        if !self.has_choices() { return; }
-        self.in_choice_mode = true;
-        let choices = &self.choices[self.current_field];
-        match self.selected_choice[self.current_field] {
-            None => self.selected_choice[self.current_field] = Some(0),
-            Some(i) if i > 0 => self.selected_choice[self.current_field] = Some(i - 1),
-            _ => {}
+    /// Check the provider (latency, model, quota headers) and show the report until Échap
+    async fn show_health(&mut self, terminal: &mut Terminal<CrosstermBackend<io::Stdout>>) -> Result<(), String> {
+        use ratatui::layout::Rect;
+        use ratatui::style::{Color, Style};
+        use ratatui::text::Line;
+        use ratatui::widgets::{Block, Borders, Clear, Paragraph};
+
+        self.app.loading = true;
+        terminal.draw(|f| ui::draw(f, &self.app)).map_err(|e| e.to_string())?;
+        let health = crate::health::check(self.client.as_ref()).await;
+        self.app.loading = false;
+        if health.chat_latency_ms.is_some() {
+            self.app.offline = false;
         }
-        // Update answer to selected choice
-        if let Some(i) = self.selected_choice[self.current_field] {
-            self.answers[self.current_field] = choices[i].clone();
+
+        let lines: Vec<Line> = health.lines().into_iter().map(Line::from).collect();
+        let color = if health.is_healthy() { Color::Green } else { Color::Yellow };
+        loop {
+            terminal.draw(|frame| {
+                ui::draw(frame, &self.app);
+
+                let area = frame.area();
+                let width = 70.min(area.width.saturating_sub(4));
+                let height = (lines.len() as u16 + 2).min(area.height.saturating_sub(2));
+                let rect = Rect {
+                    x: (area.width - width) / 2,
+                    y: (area.height - height) / 2,
+                    width,
+                    height,
+                };
+                frame.render_widget(Clear, rect);
+                let block = Block::default()
+                    .title(" 🩺 État du fournisseur ")
+                    .title_bottom(" Échap: fermer ")
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(color));
+                frame.render_widget(Paragraph::new(lines.clone()).block(block), rect);
+            }).map_err(|e| e.to_string())?;
+
+            if let Event::Key(key) = event::read().map_err(|e| e.to_string())? {
+                if matches!(key.code, KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q')) {
+                    break;
+                }
+            }
         }
+        Ok(())
     }
 
-    pub fn select_choice_down(&mut self) {
-        if !self.has_choices() { return; }
-        self.in_choice_mode = true;
-        let choices = &self.choices[self.current_field];
-        match self.selected_choice[self.current_field] {
-            None => self.selected_choice[self.current_field] = Some(0),
-            Some(i) if i < choices.len() - 1 => self.selected_choice[self.current_field] = Some(i + 1),
-            _ => {}
+    /// Send the same prompt to each model of `tui.compare_models` at once and show the answers
+    /// side by side. Answers are text only: tool calls and edits they contain are not run
+    async fn compare_models(&mut self, prompt: String, terminal: &mut Terminal<CrosstermBackend<io::Stdout>>) -> Result<(), String> {
+        if prompt.is_empty() {
+            self.app.add_ai_message("Usage: /compare <question>".to_string());
+            return Ok(());
         }
-        // Update answer to selected choice
-        if let Some(i) = self.selected_choice[self.current_field] {
-            self.answers[self.current_field] = choices[i].clone();
+        if self.compare_models.len() < 2 {
+            self.app.add_ai_message("⚠️ Indiquez au moins deux modèles dans tui.compare_models (settings.json).".to_string());
+            return Ok(());
         }
-    }
 
-    pub fn insert_char(&mut self, c: char) {
-        // Switch to custom input mode
-        self.in_choice_mode = false;
-        self.selected_choice[self.current_field] = None;
-        
-        let answer = &mut self.answers[self.current_field];
-        let byte_pos = answer.char_indices()
-            .nth(self.cursor_pos)
-            .map(|(i, _)| i)
-            .unwrap_or(answer.len());
-        answer.insert(byte_pos, c);
-        self.cursor_pos += 1;
-    }
+        self.app.add_user_message(prompt.clone());
+        self.record(SessionEvent::Prompt { content: prompt.clone() });
+        self.app.loading = true;
+        self.app.scroll = 0;
+        terminal.draw(|f| ui::draw(f, &self.app)).map_err(|e| e.to_string())?;
+
+        let file_context = self.inject_file_contents(&prompt);
+        let language_rule = if self.auto_language { crate::language::reply_rule(&prompt) } else { None };
+        let (system_prompt, warning) = self.system_prompt(&file_context, language_rule.as_deref());
+        let mut messages = vec![Message {
+            role: Role::System,
+            content: system_prompt,
+        }];
+        messages.extend(self.app.to_api_messages());
+        if let Some(warning) = warning {
+            self.app.add_ai_message(warning);
+        }
 
-    pub fn delete_char(&mut self) {
-        if self.cursor_pos > 0 {
-            self.in_choice_mode = false;
-            self.selected_choice[self.current_field] = None;
-            
-            self.cursor_pos -= 1;
-            let answer = &mut self.answers[self.current_field];
-            let byte_pos = answer.char_indices()
-                .nth(self.cursor_pos)
-                .map(|(i, _)| i)
-                .unwrap_or(0);
-            answer.remove(byte_pos);
+        let requests: Vec<_> = self.compare_models.iter()
+            .map(|model| {
+                let client = self.client.for_model(model);
+                let messages = messages.clone();
+                tokio::spawn(async move {
+                    let started = std::time::Instant::now();
+                    let response = client.chat(messages).await.map_err(|e| e.to_string());
+                    (response, started.elapsed().as_millis() as u64)
+                })
+            })
+            .collect();
+
+        let mut answers = Vec::new();
+        for (model, request) in self.compare_models.clone().into_iter().zip(requests) {
+            let (response, duration_ms) = request.await.map_err(|e| e.to_string())?;
+            let answer = match response {
+                Ok(content) => {
+                    self.record(SessionEvent::Response {
+                        content: content.clone(),
+                        duration_ms,
+                        model: Some(model.clone()),
+                    });
+                    content
+                }
+                Err(e) => format!("❌ {}", e),
+            };
+            answers.push((model, answer));
         }
+        self.app.loading = false;
+        self.app.add_comparison(answers);
+        Ok(())
     }
 
-    pub fn format_responses(&self) -> String {
-        self.questions.iter()
-            .zip(self.answers.iter())
-            .map(|(q, a)| format!("**{}**\n{}", q, a))
-            .collect::<Vec<_>>()
-            .join("\n\n")
+    /// Run an approved dangerous tool call, then record it in the audit log and the session
+    fn run_approved_tool(&mut self, call: &tools::ToolCall, command: &str, decision: Decision) -> tools::ToolResult {
+        // A confirmed rename writes its files: snapshotted for /changes
+        let renamed = if call.name == "rename_symbol" {
+            tools::rename_changes(call, &self.workspace).map(|changes| changes.modifications).unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+        let result = tools::execute_approved_tool(call, &self.workspace, &self.custom_tools);
+        if result.success && !renamed.is_empty() {
+            let mut changed_files = Vec::new();
+            for change in renamed {
+                let path = PathBuf::from(&change.path);
+                self.session_changes.record(&self.app.project_path, &path, Some(change.original), Some(change.modified));
+                changed_files.push(path);
+            }
+            self.sync_changed_files(&changed_files);
+        }
+        if let Err(e) = self.audit.log(&self.app.mode.to_string(), &call.name, command, decision, Some(&result)) {
+            self.app.add_ai_message(format!("⚠️ Journal d'audit: {}", e));
+        }
+        self.record(SessionEvent::ToolCall {
+            name: call.name.clone(),
+            params: call.params.clone(),
+            success: result.success,
+            output: result.output.clone(),
+        });
+        result
     }
 }
 
+enum CommandAction {
+    New,
+    /// `/resume`, or `/trash` to start on the deleted conversations
+    Resume(bool),
+    Save,
+    Memory,
+    Questions,
+    Exit,
+    Reindex,
+    Changes,
+    /// `/health`: latency, model and quota of the provider
+    Health,
+    /// `/open <path>`, empty for the last mentioned file
+    Open(String),
+    /// `/compare <prompt>`
+    Compare(String),
+    /// `/remember`: memory.md additions from the conversation
+    Remember,
+}
+
 impl TuiRunner {
     /// Show a tabbed form for multiple questions
-    pub async fn show_question_form(&mut self, questions: Vec<String>, terminal: &mut Terminal<CrosstermBackend<io::Stdout>>) -> Result<Option<String>, String> {
-        use ratatui::layout::{Constraint, Direction, Layout, Rect};
-        use ratatui::style::{Color, Modifier, Style};
+    pub async fn show_question_form(&mut self, fields: Vec<FormField>, terminal: &mut Terminal<CrosstermBackend<io::Stdout>>) -> Result<Option<String>, String> {
+        use ratatui::layout::Rect;
+        use ratatui::style::{Color, Style};
         use ratatui::text::{Line, Span};
         use ratatui::widgets::{Block, Borders, Clear, Paragraph};
 
-        let mut form = QuestionForm::new(questions);
+        // File-path fields complete from the SQLite index
+        let files = if fields.iter().any(|f| f.kind == FieldKind::FilePath) {
+            self.persistent_index.as_ref()
+                .and_then(|index| index.list_files().ok())
+                .map(|files| files.into_iter().map(|f| f.relative_path).collect())
+                .unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+        let mut form = QuestionForm::new(fields, files);
 
         loop {
             terminal.draw(|frame| {
@@ -1051,8 +2282,11 @@ impl TuiRunner {
 
                 // Draw form overlay
                 let area = frame.area();
+                let field_heights: Vec<u16> = form.fields.iter()
+                    .map(|f| if f.has_options_line() { 4 } else { 3 })
+                    .collect();
                 let form_width = 70.min(area.width.saturating_sub(4));
-                let form_height = ((form.questions.len() * 3) + 4).min(20) as u16;
+                let form_height = (field_heights.iter().sum::<u16>() + 3).min(area.height.saturating_sub(2));
 
                 let form_area = Rect {
                     x: (area.width - form_width) / 2,
@@ -1072,17 +2306,16 @@ impl TuiRunner {
                 frame.render_widget(block, form_area);
 
                 // Draw each question/answer field
-                let field_height = if form.has_choices() { 5u16 } else { 3u16 };
-                for (i, (question, answer)) in form.questions.iter().zip(form.answers.iter()).enumerate() {
-                    let y = inner.y + (i as u16 * field_height);
-                    if y + field_height > inner.y + inner.height {
+                let mut y = inner.y;
+                for (i, field) in form.fields.iter().enumerate() {
+                    let field_height = field_heights[i];
+                    if y + field_height > inner.y + inner.height.saturating_sub(1) {
                         break;
                     }
 
                     let is_current = i == form.current_field;
                     let border_color = if is_current { Color::Yellow } else { Color::DarkGray };
-                    let choices = &form.choices[i];
-                    let has_choices = !choices.is_empty();
+                    let required = if field.required { " *" } else { "" };
 
                     let field_area = Rect {
                         x: inner.x,
@@ -1090,10 +2323,10 @@ impl TuiRunner {
                         width: inner.width,
                         height: field_height,
                     };
+                    y += field_height;
 
-                    let title_suffix = if has_choices { " (↑↓: choix, ou tapez)" } else { "" };
                     let field_block = Block::default()
-                        .title(format!(" {} ({}/{}){}  ", question, i + 1, form.questions.len(), title_suffix))
+                        .title(format!(" {}{} ({}/{}){}  ", field.question, required, i + 1, form.fields.len(), field.hint()))
                         .borders(Borders::ALL)
                         .border_style(Style::default().fg(border_color));
 
@@ -1101,47 +2334,74 @@ impl TuiRunner {
                     frame.render_widget(field_block, field_area);
 
                     // Draw answer with cursor if current (UTF-8 safe)
+                    let answer = &form.answers[i];
                     let chars: Vec<char> = answer.chars().collect();
-                    let text = if is_current {
-                        let before: String = chars[..form.cursor_pos.min(chars.len())].iter().collect();
-                        let cursor_char: String = chars.get(form.cursor_pos).map(|c| c.to_string()).unwrap_or_else(|| " ".to_string());
-                        let after: String = if form.cursor_pos + 1 < chars.len() {
-                            chars[form.cursor_pos + 1..].iter().collect()
-                        } else {
-                            String::new()
-                        };
-                        Line::from(vec![
-                            Span::raw(before),
-                            Span::styled(cursor_char, Style::default().bg(Color::White).fg(Color::Black)),
-                            Span::raw(after),
-                        ])
-                    } else {
-                        Line::from(answer.as_str())
+                    let text = match field.kind {
+                        FieldKind::MultiSelect => Line::from(Span::styled(
+                            form.answer(i),
+                            Style::default().fg(Color::Green),
+                        )),
+                        FieldKind::Boolean => Line::from(answer.as_str()),
+                        _ if is_current => {
+                            let before: String = chars[..form.cursor_pos.min(chars.len())].iter().collect();
+                            let cursor_char: String = chars.get(form.cursor_pos).map(|c| c.to_string()).unwrap_or_else(|| " ".to_string());
+                            let after: String = if form.cursor_pos + 1 < chars.len() {
+                                chars[form.cursor_pos + 1..].iter().collect()
+                            } else {
+                                String::new()
+                            };
+                            Line::from(vec![
+                                Span::raw(before),
+                                Span::styled(cursor_char, Style::default().bg(Color::White).fg(Color::Black)),
+                                Span::raw(after),
+                            ])
+                        }
+                        _ => Line::from(answer.as_str()),
                     };
 
                     let para = Paragraph::new(text);
                     frame.render_widget(para, field_inner);
 
-                    // Draw choices below if available
-                    if has_choices && is_current {
-                        let choices_text: String = choices.iter().enumerate()
-                            .map(|(idx, c)| {
-                                let prefix = if form.selected_choice[i] == Some(idx) { "▶ " } else { "  " };
-                                format!("{}{}", prefix, c)
+                    // Draw options below the answer
+                    if field.has_options_line() && field_inner.height > 1 {
+                        let options = form.options(i);
+                        let options_text: String = options.iter().enumerate()
+                            .map(|(idx, option)| {
+                                let prefix = if is_current && form.selected_choice[i] == Some(idx) { "▶ " } else { "  " };
+                                match field.kind {
+                                    FieldKind::MultiSelect => {
+                                        let mark = if form.checked[i].get(idx).copied().unwrap_or(false) { "[x]" } else { "[ ]" };
+                                        format!("{}{} {}", prefix, mark, option)
+                                    }
+                                    _ => format!("{}{}", prefix, option),
+                                }
                             })
                             .collect::<Vec<_>>()
                             .join(" │ ");
-                        let choices_line = Line::from(Span::styled(choices_text, Style::default().fg(Color::DarkGray)));
-                        let choices_area = Rect {
+                        let options_line = Line::from(Span::styled(options_text, Style::default().fg(Color::DarkGray)));
+                        let options_area = Rect {
                             x: field_inner.x,
                             y: field_inner.y + 1,
                             width: field_inner.width,
                             height: 1,
                         };
-                        let choices_para = Paragraph::new(choices_line);
-                        frame.render_widget(choices_para, choices_area);
+                        frame.render_widget(Paragraph::new(options_line), options_area);
                     }
                 }
+
+                // Validation error on the last line of the form
+                if let Some(error) = &form.error {
+                    let error_area = Rect {
+                        x: inner.x,
+                        y: inner.y + inner.height.saturating_sub(1),
+                        width: inner.width,
+                        height: 1,
+                    };
+                    frame.render_widget(
+                        Paragraph::new(Line::from(Span::styled(format!("⚠ {}", error), Style::default().fg(Color::Red)))),
+                        error_area,
+                    );
+                }
             }).map_err(|e| e.to_string())?;
 
             if event::poll(Duration::from_millis(100)).map_err(|e| e.to_string())? {
@@ -1149,8 +2409,10 @@ impl TuiRunner {
                     match key.code {
                         KeyCode::Esc => return Ok(None),
                         KeyCode::Enter => {
-                            // Submit all answers
-                            return Ok(Some(form.format_responses()));
+                            // Submit all answers once they are valid
+                            if form.validate() {
+                                return Ok(Some(form.format_responses()));
+                            }
                         }
                         KeyCode::Tab => form.next_field(),
                         KeyCode::BackTab => form.prev_field(),
@@ -1259,67 +2521,143 @@ impl TuiRunner {
     }
 
     /// Internal method called after user message is already added and displayed
-    async fn send_message_internal(&mut self, input: String) -> Result<(), String> {
+    async fn send_message_internal(&mut self, input: String, terminal: &mut Terminal<CrosstermBackend<io::Stdout>>) -> Result<(), String> {
+        // Offline: check again instead of waiting for every retry to fail
+        if self.app.offline {
+            if let Err(e) = check_connectivity(self.client.provider(), CONNECTIVITY_TIMEOUT) {
+                self.app.loading = false;
+                self.app.add_ai_message(format!("{}\n{}", OFFLINE_MESSAGE, e));
+                return Ok(());
+            }
+            self.app.offline = false;
+        }
+
         // Detect file contents from SQLite if user mentions files (will be added to system prompt)
         let file_context = self.inject_file_contents(&input);
+        if let Some(skipped) = self.persistent_index.as_ref().and_then(|index| index.skipped_files().ok()) {
+            for (path, reason) in mentioned_skips(&input, &skipped) {
+                self.app.add_ai_message(reason.warning(path));
+            }
+        }
+
+        // Continuations (empty input) keep the language of the last user message
+        let language_rule = if !self.auto_language {
+            None
+        } else if input.is_empty() {
+            self.app.messages.iter().rev()
+                .find(|m| m.is_user)
+                .and_then(|m| crate::language::reply_rule(&m.content))
+        } else {
+            crate::language::reply_rule(&input)
+        };
+        
+        let mut patch_retries = 0;
+        // Files written or deleted during this exchange, reindexed at the end
+        let mut changed_files: Vec<PathBuf> = Vec::new();
+        let mut iteration = std::mem::take(&mut self.resumed_iteration);
+        // Error of the request that stopped the AUTO loop, left for the next prompt to resume
+        let mut interrupted: Option<String> = None;
         
-        // AUTO mode loop - continue until [TERMINÉ] or user cancels
+        let auto_markers = AutoMarkers::load();
+        
+        // AUTO mode loop - continue until the done marker or user cancels
         loop {
             self.app.loading = true;
+
+            // Esc or Ctrl+C pressed while the previous step's tools ran stops the loop here
+            if self.poll_while_waiting()? {
+                self.cancel_request();
+                break;
+            }
             
             // Check if we need to compact context
-            if self.app.tokens > COMPACT_THRESHOLD {
-                self.compact_context().await?;
+            if self.app.tokens > COMPACT_THRESHOLD && !self.compact_context(terminal).await? {
+                self.cancel_request();
+                break;
             }
 
-            // Build messages with project memory and file context
-            let mut base_prompt = if !self.project_memory.is_empty() {
-                format!("{}\n\nPROJECT MEMORY:\n{}", self.system_prompt, self.project_memory)
-            } else {
-                self.system_prompt.clone()
-            };
-            
-            // Add file context if any files were mentioned
-            if !file_context.is_empty() {
-                base_prompt = format!("{}\n\n{}", base_prompt, file_context);
+            // Saved before each iteration, so a failure or a closed TUI can be resumed
+            if self.app.mode == ChatMode::Auto {
+                iteration += 1;
+                self.save_conversation();
+                let pending = self.app.messages.iter().rev()
+                    .find(|m| m.is_user)
+                    .map(|m| m.content.clone())
+                    .unwrap_or_default();
+                let run = AutoRun::new(self.current_chat.as_ref().map(|chat| chat.id.clone()), iteration, &pending);
+                if let Err(e) = run.save(&self.app.project_path) {
+                    self.app.add_ai_message(format!("⚠️ {}", e));
+                }
             }
+
+            // Build messages with project memory and file context
+            let (system_prompt, warning) = self.system_prompt(&file_context, language_rule.as_deref());
             
             let mut messages = vec![Message {
-                role: "system".to_string(),
-                content: if self.app.mode == ChatMode::Auto {
-                    format!("{}{}", base_prompt, AUTO_MODE_SUFFIX)
-                } else {
-                    base_prompt
-                },
+                role: Role::System,
+                content: system_prompt,
             }];
             messages.extend(self.app.to_api_messages());
+            if let Some(warning) = warning {
+                self.app.add_ai_message(warning);
+            }
 
             // Send to API with retry
             let mut last_error = String::new();
+            let mut hint = None;
+            let mut unreachable = false;
             let mut api_response: Option<String> = None;
+            let request_started = std::time::Instant::now();
+            let mut attempt = 1;
+            let mut cancelled = false;
             
-            for attempt in 0..4 {
-                if attempt > 0 {
-                    // Exponential backoff: 1s, 2s, 4s
-                    let delay = std::time::Duration::from_secs(1 << (attempt - 1));
-                    tokio::time::sleep(delay).await;
-                }
-                
-                match self.client.chat(messages.clone()).await {
-                    Ok(response) => {
+            loop {
+                let error = match self.chat_in_background(messages.clone(), terminal).await? {
+                    Some(Ok(response)) => {
                         api_response = Some(response);
                         break;
                     }
-                    Err(e) => {
-                        last_error = e.to_string();
-                        // Continue to retry
+                    Some(Err(e)) => e,
+                    None => {
+                        cancelled = true;
+                        break;
                     }
+                };
+                let error = crate::error::Error::from(error);
+                last_error = error.to_string();
+                hint = error.hint();
+                unreachable = matches!(error, crate::error::Error::Offline(_));
+                let reason = match error.retry_reason() {
+                    Some(reason) if attempt < self.retry_policy.attempts => reason,
+                    _ => break,
+                };
+                attempt += 1;
+                if !self.wait_for_retry(terminal, attempt, reason).await? {
+                    cancelled = true;
+                    break;
                 }
             }
+            self.app.retry_status = None;
+            if cancelled {
+                self.cancel_request();
+                break;
+            }
             
             match api_response {
                 Some(response) => {
                     self.app.loading = false;
+                    let model = self.client.get_model().to_string();
+                    self.record(SessionEvent::Response {
+                        content: response.clone(),
+                        duration_ms: request_started.elapsed().as_millis() as u64,
+                        model: Some(model.clone()),
+                    });
+                    
+                    // Record the assistant message once; tool results get attached to it
+                    self.app.add_ai_message(response.clone());
+                    let response_index = self.app.messages.len() - 1;
+                    self.app.messages[response_index].meta.model = Some(model);
+                    self.app.scroll = 0;
                     
                     // Parse tool calls from response
                     let tool_calls = tools::parse_tool_calls(&response);
@@ -1327,12 +2665,50 @@ impl TuiRunner {
                     // If there are tool calls, execute them
                     if !tool_calls.is_empty() {
                         let mut tool_results = Vec::new();
-                        let mut has_dangerous = false;
-                        let mut dangerous_commands: Vec<String> = Vec::new();
                         
-                        for tool_call in &tool_calls {
+                        let mut asked_user = false;
+                        
+                        // Leading read-only calls (read_file x5...) run concurrently, up to the first other call
+                        let mut prefetched = tools::execute_parallel(
+                            &tool_calls,
+                            |call| tools::is_parallel_safe(&call.name) && self.tool_allowed(&call.name),
+                            &self.workspace,
+                            &self.custom_tools,
+                        );
+                        
+                        for (call_index, tool_call) in tool_calls.iter().enumerate() {
+                            if !self.tool_allowed(&tool_call.name) {
+                                // Filtered out of the documentation too, but the model may still try
+                                let output = self.tool_access().denial(&tool_call.name, self.app.mode);
+                                self.record(SessionEvent::ToolCall {
+                                    name: tool_call.name.clone(),
+                                    params: tool_call.params.clone(),
+                                    success: false,
+                                    output: output.clone(),
+                                });
+                                tool_results.push(tools::format_tool_result(&tools::ToolResult {
+                                    name: tool_call.name.clone(),
+                                    success: false,
+                                    output,
+                                    needs_confirmation: false,
+                                }));
+                            } else if tool_call.name == "ask_user" {
+                                // Typed question form, shown once the response is displayed
+                                let fields = tool_call.params.get("fields").map(String::as_str).unwrap_or("");
+                                let parsed = tools::validate_params(tool_call, &self.custom_tools)
+                                    .and_then(|_| FormField::parse_list(fields));
+                                match parsed {
+                                    Ok(fields) => {
+                                        self.app.pending_questions = fields;
+                                        asked_user = true;
+                                    }
+                                    Err(e) => tool_results.push(format!(
+                                        "<tool_result>\n<name>ask_user</name>\n<success>false</success>\n<output>\n{}\n</output>\n</tool_result>",
+                                        e
+                                    )),
+                                }
                             // Check if it's an MCP tool (starts with mcp_)
-                            if tool_call.name.starts_with("mcp_") {
+                            } else if tool_call.name.starts_with("mcp_") {
                                 // Parse: mcp_servername_toolname
                                 let parts: Vec<&str> = tool_call.name.strip_prefix("mcp_").unwrap_or("").splitn(2, '_').collect();
                                 if parts.len() == 2 {
@@ -1342,7 +2718,14 @@ impl TuiRunner {
                                     // Convert params to JSON Value
                                     let args = serde_json::json!(tool_call.params);
                                     
-                                    match self.mcp_manager.call_tool(server_name, mcp_tool_name, args) {
+                                    let call_result = self.mcp_manager.call_tool(server_name, mcp_tool_name, args);
+                                    self.record(SessionEvent::ToolCall {
+                                        name: tool_call.name.clone(),
+                                        params: tool_call.params.clone(),
+                                        success: call_result.is_ok(),
+                                        output: call_result.clone().unwrap_or_else(|e| e),
+                                    });
+                                    match call_result {
                                         Ok(output) => {
                                             tool_results.push(format!(
                                                 "<tool_result>\n<name>{}</name>\n<success>true</success>\n<output>\n{}\n</output>\n</tool_result>",
@@ -1357,14 +2740,50 @@ impl TuiRunner {
                                         }
                                     }
                                 }
+                            } else if self.app.dry_run && tools::is_write_tool(&tool_call.name, &self.custom_tools) {
+                                // PLAN+: show what the tool would do instead of running it
+                                let result = tools::preview_tool(tool_call, &self.workspace, &self.custom_tools);
+                                self.record(SessionEvent::ToolCall {
+                                    name: tool_call.name.clone(),
+                                    params: tool_call.params.clone(),
+                                    success: result.success,
+                                    output: result.output.clone(),
+                                });
+                                tool_results.push(tools::format_tool_result(&result));
                             } else {
-                                // Regular local tool
-                                let result = tools::execute_tool(tool_call, &self.app.project_path);
+                                // Regular local tool; written files are snapshotted for /changes
+                                let written = tool_call.params.get("path")
+                                    .filter(|_| tool_call.name == "write_file")
+                                    .map(|path| self.workspace.resolve(path));
+                                let before = written.as_ref().and_then(|path| std::fs::read_to_string(path).ok());
+                                let result = prefetched[call_index].take()
+                                    .unwrap_or_else(|| tools::execute_tool(tool_call, &self.workspace, &self.custom_tools));
+                                self.record(SessionEvent::ToolCall {
+                                    name: tool_call.name.clone(),
+                                    params: tool_call.params.clone(),
+                                    success: result.success,
+                                    output: result.output.clone(),
+                                });
+                                
+                                if let Some(path) = written.filter(|_| result.success) {
+                                    let after = std::fs::read_to_string(&path).ok();
+                                    self.session_changes.record(&self.app.project_path, &path, before, after);
+                                    changed_files.push(path);
+                                }
                                 
                                 if result.needs_confirmation {
-                                    has_dangerous = true;
-                                    if let Some(cmd) = tool_call.params.get("command") {
-                                        dangerous_commands.push(cmd.clone());
+                                    // Custom tools report their rendered command as output
+                                    let command = tool_call.params.get("command").cloned()
+                                        .unwrap_or_else(|| result.output.clone());
+                                    if self.audit.is_remembered(&tool_call.name, &command) {
+                                        let result = self.run_approved_tool(tool_call, &command, Decision::Remembered);
+                                        tool_results.push(tools::format_tool_result(&result));
+                                    } else {
+                                        self.pending_approvals.push(PendingApproval {
+                                            call: tool_call.clone(),
+                                            command,
+                                            message_index: response_index,
+                                        });
                                     }
                                 } else {
                                     tool_results.push(tools::format_tool_result(&result));
@@ -1372,71 +2791,195 @@ impl TuiRunner {
                             }
                         }
                         
-                        // Show response with tool calls to user
-                        self.app.add_ai_message(response.clone());
-                        self.app.scroll = 0;
+                        let has_results = !tool_results.is_empty();
+                        self.app.attach_tool_results(response_index, tool_results);
                         
-                        // If we have results, add them and continue the loop
-                        if !tool_results.is_empty() {
-                            let results_message = tool_results.join("\n\n");
-                            self.app.add_user_message(format!("Résultats des outils:\n{}", results_message));
-                            // Continue loop to let AI process results
-                            continue;
+                        // The user's answers will be sent as the next message
+                        if asked_user {
+                            break;
                         }
                         
-                        // If dangerous commands, show warning (user must manually respond)
-                        if has_dangerous {
-                            self.app.add_ai_message(format!(
-                                "⚠️ Commandes dangereuses détectées. Tapez 'oui' pour confirmer l'exécution de:\n{}",
-                                dangerous_commands.join("\n")
-                            ));
+                        // Wait for the approvals; the AI continues once they are answered
+                        if !self.pending_approvals.is_empty() {
                             break;
                         }
+                        
+                        // Continue loop to let AI process results
+                        if has_results {
+                            continue;
+                        }
                     }
                     
                     // Parse and apply changes if applicable
-                    let changes = parse_ai_response(&response, &self.app.project_path);
+                    let changes = parse_ai_response_with(&response, |path| self.workspace.resolve(path));
+                    if !changes.is_empty() {
+                        self.app.messages[response_index].diff_summary = Some(changes.stats().header());
+                    }
                     
+                    // What AUTO applies from this response, for /undo
+                    let mut journal = ChangeJournal::new();
                     if !changes.is_empty() && self.app.mode != ChatMode::Ask {
                         // In AUTO or CODE mode with confirmation
-                        if self.app.mode == ChatMode::Auto {
-                            for change in &changes.modifications {
-                                let _ = change.apply();
+                        let auto_apply = self.app.mode == ChatMode::Auto && !self.app.dry_run;
+                        // Moves first: the other changes of a moved file target its new path
+                        for rename in &changes.renames {
+                            let applied = auto_apply && match rename.apply(&self.app.project_path) {
+                                Ok(()) => true,
+                                Err(e) => {
+                                    self.app.add_ai_message(format!("❌ {}", e));
+                                    false
+                                }
+                            };
+                            if applied {
+                                rename.record(&self.app.project_path, &mut journal);
+                                let (from, to) = (self.app.project_path.join(&rename.from), self.app.project_path.join(&rename.to));
+                                self.session_changes.record(&self.app.project_path, &from, Some(rename.content.clone()), None);
+                                self.session_changes.record(&self.app.project_path, &to, None, Some(rename.content.clone()));
+                                changed_files.extend([from, to]);
                             }
-                            for new_file in &changes.new_files {
-                                let _ = new_file.apply();
+                            self.record(SessionEvent::Diff {
+                                path: rename.to.clone(),
+                                diff: rename.unified_diff(),
+                                applied,
+                            });
+                        }
+                        // CODE reviews the files hunk by hunk in the diff viewer before writing them
+                        let review = self.app.mode == ChatMode::Code && !self.app.dry_run;
+                        for change in &changes.modifications {
+                            if review {
+                                match change.keeping_local_edits() {
+                                    Ok(merged) => self.pending_reviews.push((merged, false)),
+                                    Err(e) => self.app.add_ai_message(format!("⚠️ {}", e)),
+                                }
+                                continue;
                             }
+                            // Uncommitted edits are merged into, never overwritten
+                            let merged = match auto_apply.then(|| change.keeping_local_edits()) {
+                                Some(Err(e)) => {
+                                    self.app.add_ai_message(format!("⚠️ {}", e));
+                                    None
+                                }
+                                Some(Ok(merged)) => Some(merged),
+                                None => None,
+                            };
+                            let applied = merged.as_ref().is_some_and(|merged| merged.apply().is_ok());
+                            let change = merged.as_ref().unwrap_or(change);
+                            if applied {
+                                let path = self.app.project_path.join(&change.path);
+                                journal.record(&self.app.project_path, &path, Some(change.original.clone()), Some(change.modified.clone()));
+                                self.session_changes.record(&self.app.project_path, &path, Some(change.original.clone()), Some(change.modified.clone()));
+                                changed_files.push(path);
+                            }
+                            self.record(SessionEvent::Diff {
+                                path: change.path.clone(),
+                                diff: change.unified_diff(),
+                                applied,
+                            });
+                        }
+                        for new_file in &changes.new_files {
+                            let path = self.app.project_path.join(&new_file.path);
+                            let before = std::fs::read_to_string(&path).ok();
+                            if review {
+                                self.pending_reviews.push((FileChange {
+                                    path: new_file.path.clone(),
+                                    original: before.clone().unwrap_or_default(),
+                                    modified: new_file.content.clone(),
+                                    description: new_file.description.clone(),
+                                }, before.is_none()));
+                                continue;
+                            }
+                            let applied = auto_apply && new_file.apply().is_ok();
+                            if applied {
+                                journal.record(&self.app.project_path, &path, before.clone(), Some(new_file.content.clone()));
+                                self.session_changes.record(&self.app.project_path, &path, before, Some(new_file.content.clone()));
+                                changed_files.push(path);
+                            }
+                            self.record(SessionEvent::Diff {
+                                path: new_file.path.clone(),
+                                diff: new_file.unified_diff(),
+                                applied,
+                            });
                         }
                     }
                     
-                    self.app.add_ai_message(response.clone());
-                    self.app.scroll = 0;
+                    if self.app.mode != ChatMode::Ask {
+                        for deletion in &changes.deletions {
+                            // AUTO moves the file to the trash, CODE asks first, other modes only show it
+                            if self.app.mode == ChatMode::Code && !self.app.dry_run {
+                                self.pending_deletions.push(deletion.clone());
+                                continue;
+                            }
+                            let (message, applied) = if self.app.mode == ChatMode::Auto && !self.app.dry_run {
+                                match deletion.trash(&self.app.project_path) {
+                                    Ok(trashed) => {
+                                        let path = self.app.project_path.join(&deletion.path);
+                                        journal.record(&self.app.project_path, &path, Some(deletion.content.clone()), None);
+                                        self.session_changes.record(&self.app.project_path, &path, Some(deletion.content.clone()), None);
+                                        changed_files.push(path);
+                                        (format!("🗑 {} supprimé (corbeille: {})", deletion.path, trashed.display()), true)
+                                    }
+                                    Err(e) => (format!("❌ {}", e), false),
+                                }
+                            } else {
+                                (format!("🗑 Suppression proposée: {}\n{}", deletion.path, deletion.unified_diff()), false)
+                            };
+                            self.app.add_ai_message(message);
+                            self.record(SessionEvent::Diff {
+                                path: deletion.path.clone(),
+                                diff: deletion.unified_diff(),
+                                applied,
+                            });
+                        }
+                    }
+                    if let Err(e) = journal.save(&self.app.project_path) {
+                        self.app.add_ai_message(format!("⚠️ {}", e));
+                    }
+                    if !journal.is_empty() && auto_commit::enabled(&self.app.project_path) {
+                        match auto_commit::commit(&self.app.project_path, &journal.paths(&self.app.project_path), &journal.stats(), self.client.as_ref()).await {
+                            Ok(Some(commit)) => self.app.add_ai_message(format!("📝 Commit {}", commit)),
+                            Ok(None) => {}
+                            Err(e) => self.app.add_ai_message(format!("⚠️ {}", e)),
+                        }
+                    }
+                    
+                    // No patch matched the files: send the mismatch back and ask for a corrected one
+                    if matches!(self.app.mode, ChatMode::Code | ChatMode::Auto) {
+                        if let Some(retry_prompt) = changes.patch_retry_prompt() {
+                            if patch_retries < MAX_PATCH_RETRIES {
+                                patch_retries += 1;
+                                self.app.add_user_message(retry_prompt);
+                                continue;
+                            }
+                            self.app.add_ai_message(format!(
+                                "⚠️ Patch toujours non applicable après {} tentatives.",
+                                MAX_PATCH_RETRIES
+                            ));
+                            if self.app.mode == ChatMode::Code && !self.app.dry_run {
+                                self.pending_conflicts = changes.patch_failures.clone();
+                            }
+                        }
+                    }
                     
                     // Detect questions in response (lines ending with ?)
-                    let detected_questions: Vec<String> = response
+                    let detected_questions: Vec<FormField> = response
                         .lines()
                         .filter(|line| {
                             let trimmed = line.trim();
                             trimmed.ends_with('?') && trimmed.len() > 10
                         })
-                        .map(|line| line.trim().to_string())
+                        .map(|line| FormField::text(line.trim()))
                         .collect();
                     
                     if !detected_questions.is_empty() {
                         self.app.pending_questions = detected_questions;
                     }
                     
-                    // In AUTO mode, check if we should continue
+                    // In AUTO mode, check if we should continue. Tool calls looped above: without
+                    // file changes either, a "continue" would only nudge a model with nothing left to do
                     if self.app.mode == ChatMode::Auto {
-                        if response.contains("[TERMINÉ]") || response.contains("[TERMINE]") {
-                            // Task complete
-                            break;
-                        } else if response.contains("[CONTINUE]") {
-                            // Continue automatically - add a "continue" message
+                        if auto_markers.should_continue(&response, !changes.is_empty()) {
                             self.app.add_user_message("Continue.".to_string());
-                            // Don't break, loop again
                         } else {
-                            // No marker, assume done
                             break;
                         }
                     } else {
@@ -1446,16 +2989,169 @@ impl TuiRunner {
                 }
                 None => {
                     self.app.loading = false;
-                    self.app.add_ai_message(format!("Erreur après 4 tentatives: {}", last_error));
+                    self.record(SessionEvent::Error { message: last_error.clone() });
+                    self.app.add_ai_message(format!("Erreur après {} tentative(s): {}", attempt, last_error));
+                    if unreachable {
+                        self.app.offline = true;
+                        self.app.add_ai_message(OFFLINE_MESSAGE.to_string());
+                    } else if let Some(hint) = hint {
+                        self.app.add_ai_message(hint.to_string());
+                    }
+                    if self.app.mode == ChatMode::Auto {
+                        interrupted = Some(last_error);
+                    }
                     break;
                 }
             }
         }
 
+        if self.app.mode == ChatMode::Auto {
+            match (interrupted, AutoRun::load(&self.app.project_path)) {
+                (Some(error), Some(mut run)) => {
+                    run.error = Some(error);
+                    let _ = run.save(&self.app.project_path);
+                    self.app.add_ai_message(run.offer());
+                    self.interrupted_auto = Some(run);
+                }
+                _ => AutoRun::clear(&self.app.project_path),
+            }
+        }
+        self.sync_changed_files(&changed_files);
         Ok(())
     }
 
-    async fn compact_context(&mut self) -> Result<(), String> {
+    /// Send the request on its own task and keep drawing until it answers: the spinner animates,
+    /// typed keys go to the input and Esc or Ctrl+C cancels. None when cancelled: aborting the
+    /// task drops the pending HTTP request
+    async fn chat_in_background(&mut self, messages: Vec<Message>, terminal: &mut Terminal<CrosstermBackend<io::Stdout>>) -> Result<Option<anyhow::Result<String>>, String> {
+        let client = self.client.clone();
+        let response = until_answered(async move { client.chat(messages).await }, || {
+            self.app.spinner_frame = self.app.spinner_frame.wrapping_add(1);
+            terminal.draw(|f| ui::draw(f, &self.app)).map_err(|e| e.to_string())?;
+            self.poll_while_waiting()
+        }).await?;
+        Ok(response.map(|response| response.unwrap_or_else(|_| Err(anyhow::anyhow!("Request task stopped")))))
+    }
+
+    /// Keys pressed while a request runs; true when one asked to cancel it
+    fn poll_while_waiting(&mut self) -> Result<bool, String> {
+        while event::poll(Duration::ZERO).map_err(|e| e.to_string())? {
+            let Event::Key(key) = event::read().map_err(|e| e.to_string())? else {
+                continue;
+            };
+            if self.app.key_while_waiting(key) {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Stop the exchange after Esc or Ctrl+C; the messages already received are kept
+    fn cancel_request(&mut self) {
+        self.app.loading = false;
+        self.app.retry_status = None;
+        self.record(SessionEvent::Error { message: "Requête annulée".to_string() });
+        self.app.add_ai_message("⏹ Requête annulée.".to_string());
+    }
+
+    /// Show a saved conversation in place of the current one and continue it
+    fn load_chat(&mut self, chat: SavedChat) {
+        self.app.load_saved(&chat);
+        self.current_chat = Some(chat);
+
+        // The project may have changed since: catch up the index and rebuild the prompt
+        self.incremental_reindex();
+        self.refresh_system_prompt();
+    }
+
+    /// Send the pending message of an interrupted AUTO loop again, in its conversation
+    async fn resume_auto_run(&mut self, run: AutoRun, terminal: &mut Terminal<CrosstermBackend<io::Stdout>>) -> Result<(), String> {
+        // Interrupted in an earlier session: back to its conversation first
+        let current = self.current_chat.as_ref().map(|chat| chat.id.clone());
+        if let Some(id) = run.chat_id.as_ref().filter(|id| current.as_ref() != Some(*id)) {
+            let chat = crate::chat_storage::ChatStorage::new().and_then(|storage| storage.load(id));
+            match chat {
+                Ok(chat) => self.load_chat(chat),
+                Err(e) => {
+                    AutoRun::clear(&self.app.project_path);
+                    self.app.add_ai_message(format!("❌ Conversation de la boucle AUTO introuvable: {}", e));
+                    return Ok(());
+                }
+            }
+        }
+        if self.app.mode != ChatMode::Auto {
+            self.app.mode = ChatMode::Auto;
+            self.record(SessionEvent::Mode { mode: self.app.mode.to_string() });
+        }
+        self.app.add_ai_message(format!("▶ Reprise de la boucle AUTO (itération {})", run.iteration));
+        self.resumed_iteration = run.iteration.saturating_sub(1);
+        self.app.add_user_message(run.pending.clone());
+        self.record(SessionEvent::Prompt { content: run.pending.clone() });
+        self.app.loading = true;
+        self.app.scroll = 0;
+        terminal.draw(|f| ui::draw(f, &self.app)).map_err(|e| e.to_string())?;
+        self.send_message_internal(run.pending, terminal).await
+    }
+
+    /// Backoff before a retry, counted down in the status bar; false when it was cancelled
+    async fn wait_for_retry(&mut self, terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, attempt: u32, reason: &'static str) -> Result<bool, String> {
+        let deadline = std::time::Instant::now() + self.retry_policy.delay(attempt);
+        while let Some(left) = deadline.checked_duration_since(std::time::Instant::now()) {
+            let notice = RetryNotice {
+                attempt,
+                attempts: self.retry_policy.attempts,
+                delay: std::time::Duration::from_secs(left.as_secs_f64().ceil() as u64),
+                reason,
+            };
+            self.app.retry_status = Some(notice.to_string());
+            self.app.spinner_frame = self.app.spinner_frame.wrapping_add(1);
+            terminal.draw(|f| ui::draw(f, &self.app)).map_err(|e| e.to_string())?;
+            if self.poll_while_waiting()? {
+                return Ok(false);
+            }
+            tokio::time::sleep(left.min(std::time::Duration::from_millis(250))).await;
+        }
+        Ok(true)
+    }
+
+    /// `/undo`: roll back the last change set applied in the project, AUTO or CLI
+    fn undo_last_change_set(&mut self) {
+        let Some(mut journal) = ChangeJournal::last(&self.app.project_path) else {
+            self.app.add_ai_message("📭 Aucune modification à annuler".to_string());
+            return;
+        };
+        match journal.rollback(&self.app.project_path) {
+            Ok(paths) => {
+                for (file, path) in journal.files.iter().zip(&paths) {
+                    self.session_changes.record(&self.app.project_path, path, file.after.clone(), file.before.clone());
+                }
+                self.sync_changed_files(&paths);
+                self.app.add_ai_message(format!("↩️ Modifications annulées: {}", journal.summary()));
+            }
+            Err(e) => self.app.add_ai_message(format!("❌ {}", e)),
+        }
+    }
+
+    /// Reindex files changed by the AI and refresh the file list of the system prompt
+    fn sync_changed_files(&mut self, files: &[PathBuf]) {
+        if files.is_empty() {
+            return;
+        }
+        if let Some(ref pindex) = self.persistent_index {
+            // The SQLite index only covers the primary root
+            let primary: Vec<PathBuf> = files.iter()
+                .filter(|path| path.starts_with(&self.app.project_path))
+                .cloned()
+                .collect();
+            if let Err(e) = pindex.sync_files(&primary, None, usize::MAX) {
+                self.app.add_ai_message(format!("⚠️ Synchronisation de l'index échouée: {}", e));
+            }
+        }
+        self.refresh_system_prompt();
+    }
+
+    /// False when the summary request was cancelled
+    async fn compact_context(&mut self, terminal: &mut Terminal<CrosstermBackend<io::Stdout>>) -> Result<bool, String> {
         // Pop the last message (current user input) to preserve it
         let last_message = self.app.messages.pop();
         
@@ -1466,44 +3162,120 @@ impl TuiRunner {
             .join("\n");
         
         // Ask AI to summarize
-        let compact_messages = vec![
-            Message {
-                role: "system".to_string(),
-                content: COMPACT_PROMPT.to_string(),
-            },
-            Message {
-                role: "user".to_string(),
-                content: format!("Historique à résumer:\n{}", history),
-            },
-        ];
-        
-        if let Ok(summary) = self.client.chat(compact_messages).await {
-            self.app.messages.clear();
-            self.app.messages.push(crate::tui::app::ChatMessage {
-                role: "assistant".to_string(),
-                content: format!("📝 Contexte compacté:\n{}", summary),
-                is_user: false,
-            });
-            
-            // Restore the last message if it existed
-            if let Some(msg) = last_message {
-                self.app.messages.push(msg);
+        let summary = match self.chat_in_background(prompt::compact_messages(&history), terminal).await? {
+            Some(Ok(summary)) => summary,
+            // No summary: the history stays as it was
+            Some(Err(_)) => {
+                self.app.messages.extend(last_message);
+                return Ok(true);
             }
-            
-            // Recalculate tokens
-            self.app.tokens = self.app.messages.iter()
-                .map(|m| m.content.len() / 4)
-                .sum();
-            
-            // Force scroll to bottom to show new context/user message
-            self.app.scroll = 0;
+            None => {
+                self.app.messages.extend(last_message);
+                return Ok(false);
+            }
+        };
+        self.app.clear_messages();
+        self.app.messages.push(crate::tui::app::ChatMessage::new(
+            Role::Assistant,
+            format!("📝 Contexte compacté:\n{}", summary),
+        ));
+        
+        // Restore the last message if it existed
+        if let Some(msg) = last_message {
+            self.app.messages.push(msg);
         }
         
-        Ok(())
+        // Recalculate tokens
+        self.app.update_tokens();
+        
+        // Force scroll to bottom to show new context/user message
+        self.app.scroll = 0;
+        
+        Ok(true)
+    }
+}
+
+/// Run `request` on its own task, calling `waiting` about every 80 ms until it answers.
+/// None when `waiting` asked to cancel: the task is aborted, dropping e.g. its HTTP request
+async fn until_answered<T: Send + 'static>(
+    request: impl std::future::Future<Output = T> + Send + 'static,
+    mut waiting: impl FnMut() -> Result<bool, String>,
+) -> Result<Option<Result<T, tokio::task::JoinError>>, String> {
+    let mut task = tokio::spawn(request);
+    loop {
+        if waiting()? {
+            task.abort();
+            return Ok(None);
+        }
+        tokio::select! {
+            result = &mut task => return Ok(Some(result)),
+            _ = tokio::time::sleep(Duration::from_millis(80)) => {}
+        }
+    }
+}
+
+/// Symbol map of the SQLite index for the system prompt: every file with its declarations
+/// and their lines, most important files first (git churn, recent edits, symbol count)
+fn sqlite_summary(persistent_index: Option<&PersistentIndex>) -> String {
+    let Some(pindex) = persistent_index else {
+        return String::new();
+    };
+    let (Ok(map), Ok((total, _))) = (pindex.symbol_map(SYMBOL_MAP_TOKENS), pindex.stats()) else {
+        return String::new();
+    };
+    if map.is_empty() {
+        return String::new();
     }
+    format!(
+        "\n\nINDEX SQLITE ({} fichiers, déclarations avec leurs lignes):\n{}",
+        total, map.trim_end()
+    )
 }
 
-pub async fn run_tui(project_path: PathBuf) -> Result<(), String> {
-    let mut runner = TuiRunner::new(project_path)?;
+pub async fn run_tui(config: ChatConfig) -> Result<(), String> {
+    // Neither --cwd nor --workspace: pick the project instead of assuming the current directory
+    let workspace = if config.pick_project { None } else { Some(config.workspace()?) };
+    let mut runner = TuiRunner::new(workspace, &config)?;
+    if config.record {
+        let path = SessionRecorder::default_path(&runner.app.project_path);
+        runner.start_recording(&path)?;
+    }
     runner.run().await
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    #[tokio::test]
+    async fn test_request_runs_until_answered_or_cancelled() {
+        let mut polls = 0;
+        let answer = until_answered(async { 42 }, || {
+            polls += 1;
+            Ok(false)
+        }).await.unwrap();
+        assert_eq!(answer.unwrap().unwrap(), 42);
+        assert!(polls >= 1);
+
+        // Cancelled on the third poll: the request never completes
+        let finished = Arc::new(AtomicBool::new(false));
+        let request = {
+            let finished = finished.clone();
+            async move {
+                tokio::time::sleep(Duration::from_millis(500)).await;
+                finished.store(true, Ordering::SeqCst);
+            }
+        };
+        let mut polls = 0;
+        let cancelled = until_answered(request, || {
+            polls += 1;
+            Ok(polls == 3)
+        }).await.unwrap();
+        assert!(cancelled.is_none());
+        tokio::time::sleep(Duration::from_millis(600)).await;
+        assert!(!finished.load(Ordering::SeqCst));
+
+        assert!(until_answered(async {}, || Err("terminal closed".to_string())).await.is_err());
+    }
+}
@@ -0,0 +1,439 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use crossterm::{
+    event::{self, Event, KeyCode, KeyModifiers},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph},
+    Terminal,
+};
+use crate::tui::logo::MISTRAL_COLOR;
+use crate::mistral_client::ApiProvider;
+
+const SPINNER_FRAMES: &[&str] = &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+
+const PROVIDERS: [(ApiProvider, &str); 3] = [
+    (ApiProvider::MistralAi, "Mistral AI (api.mistral.ai)"),
+    (ApiProvider::Codestral, "Codestral (codestral.mistral.ai)"),
+    (ApiProvider::Ollama, "Ollama (serveur local, sans clé)"),
+];
+
+/// Rows of the project picker shown at once
+const PICKER_ROWS: usize = 12;
+
+#[derive(Debug, Clone)]
+enum StageStatus {
+    Running,
+    Done(String),
+    Skipped,
+    Failed(String),
+}
+
+struct Stage {
+    label: String,
+    status: StageStatus,
+    skippable: bool,
+}
+
+/// Detail line updated by a stage running in the background (e.g. "12/50 fichiers")
+#[derive(Clone, Default)]
+pub struct StageProgress(Arc<Mutex<String>>);
+
+impl StageProgress {
+    pub fn set(&self, detail: impl Into<String>) {
+        *self.0.lock().unwrap() = detail.into();
+    }
+
+    fn get(&self) -> String {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+/// Progress screen shown while the TUI starts (settings, indexing, MCP servers...)
+pub struct StartupScreen {
+    terminal: Terminal<CrosstermBackend<io::Stdout>>,
+    stages: Vec<Stage>,
+    progress: StageProgress,
+    frame: usize,
+}
+
+impl StartupScreen {
+    pub fn start() -> Result<Self, String> {
+        enable_raw_mode().map_err(|e| e.to_string())?;
+        let mut stdout = io::stdout();
+        execute!(stdout, EnterAlternateScreen).map_err(|e| e.to_string())?;
+        let terminal = Terminal::new(CrosstermBackend::new(stdout)).map_err(|e| e.to_string())?;
+
+        Ok(Self {
+            terminal,
+            stages: Vec::new(),
+            progress: StageProgress::default(),
+            frame: 0,
+        })
+    }
+
+    /// Start a quick stage, completed with `finish`
+    pub fn begin(&mut self, label: impl Into<String>) {
+        self.push_stage(label.into(), false);
+        self.draw();
+    }
+
+    /// Mark the current stage as done, with an optional detail
+    pub fn finish(&mut self, detail: impl Into<String>) {
+        self.set_status(StageStatus::Done(detail.into()));
+    }
+
+    /// Mark the current stage as failed
+    pub fn fail(&mut self, error: impl Into<String>) {
+        self.set_status(StageStatus::Failed(error.into()));
+    }
+
+    /// Run a slow stage in the background; returns None if the user skipped it (Esc or s).
+    /// A skipped stage keeps running detached and its result is dropped.
+    pub fn run_stage<T, F>(&mut self, label: impl Into<String>, work: F) -> Option<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(StageProgress) -> T + Send + 'static,
+    {
+        self.push_stage(label.into(), true);
+        let progress = self.progress.clone();
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let _ = tx.send(work(progress));
+        });
+
+        loop {
+            if let Ok(result) = rx.try_recv() {
+                self.finish(String::new());
+                return Some(result);
+            }
+
+            self.frame = self.frame.wrapping_add(1);
+            self.draw();
+
+            if event::poll(Duration::from_millis(80)).unwrap_or(false) {
+                if let Ok(Event::Key(key)) = event::read() {
+                    if matches!(key.code, KeyCode::Esc | KeyCode::Char('s') | KeyCode::Char('S')) {
+                        self.set_status(StageStatus::Skipped);
+                        return None;
+                    }
+                }
+            }
+        }
+    }
+
+    /// First-run form: provider choice and masked API key input, kept open until
+    /// `validate` accepts the key. Esc cancels
+    pub fn setup_api_key(
+        &mut self,
+        validate: impl Fn(&str, &ApiProvider) -> Result<(), String>,
+    ) -> Result<(String, ApiProvider), String> {
+        let mut provider = 0;
+        let mut api_key = String::new();
+        let mut on_key = false;
+        let mut error: Option<String> = None;
+
+        loop {
+            self.draw_setup(provider, &api_key, on_key, error.as_deref().map(|e| (e, Color::Red)));
+
+            let Event::Key(key) = event::read().map_err(|e| e.to_string())? else {
+                continue;
+            };
+            match key.code {
+                KeyCode::Esc => return Err("Configuration annulée.".to_string()),
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    return Err("Configuration annulée.".to_string());
+                }
+                KeyCode::Tab | KeyCode::BackTab => on_key = !on_key,
+                KeyCode::Up if !on_key => {
+                    provider = (provider + PROVIDERS.len() - 1) % PROVIDERS.len();
+                    error = None;
+                }
+                KeyCode::Down if !on_key => {
+                    provider = (provider + 1) % PROVIDERS.len();
+                    error = None;
+                }
+                // A local server needs no key: Enter checks it right away
+                KeyCode::Enter if !on_key && PROVIDERS[provider].0.needs_api_key() => on_key = true,
+                KeyCode::Enter => {
+                    let selected = &PROVIDERS[provider].0;
+                    let trimmed = api_key.trim();
+                    if trimmed.is_empty() && selected.needs_api_key() {
+                        error = Some("❌ Clé API vide.".to_string());
+                        continue;
+                    }
+                    let checking = if selected.needs_api_key() { "⏳ Vérification de la clé..." } else { "⏳ Connexion au serveur Ollama..." };
+                    self.draw_setup(provider, &api_key, on_key, Some((checking, Color::Yellow)));
+                    match validate(trimmed, selected) {
+                        Ok(()) => return Ok((trimmed.to_string(), selected.clone())),
+                        Err(e) if selected.needs_api_key() => error = Some(format!("❌ Clé refusée: {}", e)),
+                        Err(e) => error = Some(format!("❌ Serveur Ollama injoignable: {}", e)),
+                    }
+                }
+                KeyCode::Backspace if on_key => {
+                    api_key.pop();
+                }
+                KeyCode::Char(c) if on_key => {
+                    api_key.push(c);
+                    error = None;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Yes/no question in the startup frame. n, Esc and Ctrl+C answer no
+    pub fn confirm(&mut self, title: &str, question: &str) -> Result<bool, String> {
+        loop {
+            let lines = vec![
+                Line::from(""),
+                Line::from(Span::styled(format!(" {}", question), Style::default().fg(Color::Yellow))),
+                Line::from(""),
+                Line::from(Span::styled(
+                    " o/y: oui • n/Échap: non",
+                    Style::default().fg(Color::DarkGray).add_modifier(Modifier::ITALIC),
+                )),
+            ];
+            self.render(title, lines);
+
+            let Event::Key(key) = event::read().map_err(|e| e.to_string())? else {
+                continue;
+            };
+            match key.code {
+                KeyCode::Char('o' | 'O' | 'y' | 'Y') => return Ok(true),
+                KeyCode::Char('n' | 'N') | KeyCode::Esc => return Ok(false),
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Ok(false),
+                _ => {}
+            }
+        }
+    }
+
+    /// Project picker shown when no directory was given: recent projects, the current
+    /// directory, and a browser to pick any other one. Esc cancels
+    pub fn pick_project(&mut self, recent: &[PathBuf], current_dir: &Path) -> Result<PathBuf, String> {
+        let mut choices = recent.to_vec();
+        if !choices.iter().any(|path| path == current_dir) {
+            choices.push(current_dir.to_path_buf());
+        }
+        let browse_row = choices.len();
+        let mut selected = 0;
+        // Directory being browsed and its subdirectories
+        let mut browsing: Option<(PathBuf, Vec<PathBuf>)> = None;
+
+        loop {
+            let rows: Vec<String> = match &browsing {
+                None => choices.iter()
+                    .map(|path| if path == current_dir {
+                        format!("{}  (dossier courant)", path.display())
+                    } else {
+                        path.display().to_string()
+                    })
+                    .chain(std::iter::once("🔎 Parcourir...".to_string()))
+                    .collect(),
+                Some((_, subdirs)) => std::iter::once("..".to_string())
+                    .chain(subdirs.iter().map(|dir| format!("{}/", dir.file_name().unwrap_or_default().to_string_lossy())))
+                    .collect(),
+            };
+            match &browsing {
+                None => self.draw_picker(" 📂 Choix du projet ", None, &rows, selected,
+                    "↑↓: choisir • Enter: ouvrir • Échap: annuler"),
+                Some((dir, _)) => self.draw_picker(" 🔎 Parcourir ", Some(dir), &rows, selected,
+                    "Enter: entrer • Espace: choisir ce dossier • Échap: retour"),
+            }
+
+            let Event::Key(key) = event::read().map_err(|e| e.to_string())? else {
+                continue;
+            };
+            match key.code {
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    return Err("Aucun projet choisi.".to_string());
+                }
+                KeyCode::Esc if browsing.is_some() => {
+                    browsing = None;
+                    selected = browse_row;
+                }
+                KeyCode::Esc => return Err("Aucun projet choisi.".to_string()),
+                KeyCode::Up => selected = (selected + rows.len() - 1) % rows.len(),
+                KeyCode::Down => selected = (selected + 1) % rows.len(),
+                KeyCode::Char(' ') => {
+                    if let Some((dir, _)) = browsing {
+                        return Ok(dir);
+                    }
+                }
+                KeyCode::Enter => {
+                    let next = match &browsing {
+                        None if selected < browse_row => return Ok(choices[selected].clone()),
+                        None => current_dir.to_path_buf(),
+                        Some((dir, _)) if selected == 0 => dir.parent().unwrap_or(dir).to_path_buf(),
+                        Some((_, subdirs)) => subdirs[selected - 1].clone(),
+                    };
+                    let subdirs = subdirectories(&next);
+                    browsing = Some((next, subdirs));
+                    selected = 0;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn draw_picker(&mut self, title: &str, dir: Option<&Path>, rows: &[String], selected: usize, help: &str) {
+        let mut lines = Vec::new();
+        if let Some(dir) = dir {
+            lines.push(Line::from(Span::styled(format!(" 📁 {}", dir.display()), Style::default().fg(Color::DarkGray))));
+            lines.push(Line::from(""));
+        }
+        let start = (selected + 1).saturating_sub(PICKER_ROWS);
+        for (i, row) in rows.iter().enumerate().skip(start).take(PICKER_ROWS) {
+            lines.push(if i == selected {
+                Line::from(Span::styled(format!(" ▶ {}", row), Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)))
+            } else {
+                Line::from(format!("   {}", row))
+            });
+        }
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            format!(" {}", help),
+            Style::default().fg(Color::DarkGray).add_modifier(Modifier::ITALIC),
+        )));
+        self.render(title, lines);
+    }
+
+    fn draw_setup(&mut self, provider: usize, api_key: &str, on_key: bool, message: Option<(&str, Color)>) {
+        let focus = |focused: bool| if focused {
+            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().add_modifier(Modifier::BOLD)
+        };
+
+        let mut lines = vec![
+            Line::from(Span::styled(" Aucune clé API configurée.", Style::default().fg(Color::DarkGray))),
+            Line::from(""),
+            Line::from(Span::styled(" Endpoint", focus(!on_key))),
+        ];
+        for (i, (_, label)) in PROVIDERS.iter().enumerate() {
+            let (mark, style) = if i == provider {
+                ("●", Style::default().fg(Color::Green))
+            } else {
+                ("○", Style::default())
+            };
+            lines.push(Line::from(Span::styled(format!("   {} {}", mark, label), style)));
+        }
+        lines.push(Line::from(""));
+        lines.push(Line::from(vec![
+            Span::styled(" Clé API ", focus(on_key)),
+            Span::styled(
+                if PROVIDERS[provider].0.needs_api_key() { "(https://console.mistral.ai)" } else { "(facultative pour Ollama)" },
+                Style::default().fg(Color::DarkGray),
+            ),
+        ]));
+        let cursor = if on_key { "▏" } else { "" };
+        lines.push(Line::from(format!("   {}{}", "•".repeat(api_key.chars().count()), cursor)));
+        lines.push(Line::from(""));
+        match message {
+            Some((text, color)) => lines.push(Line::from(Span::styled(format!(" {}", text), Style::default().fg(color)))),
+            None => lines.push(Line::from(Span::styled(
+                " ↑↓: endpoint • Tab: champ suivant • Enter: valider • Échap: annuler",
+                Style::default().fg(Color::DarkGray).add_modifier(Modifier::ITALIC),
+            ))),
+        }
+
+        self.render(" 🔑 Configuration de l'API ", lines);
+    }
+
+    fn push_stage(&mut self, label: String, skippable: bool) {
+        self.progress.set(String::new());
+        self.stages.push(Stage { label, status: StageStatus::Running, skippable });
+    }
+
+    fn set_status(&mut self, status: StageStatus) {
+        if let Some(stage) = self.stages.last_mut() {
+            stage.status = status;
+        }
+        self.draw();
+    }
+
+    fn draw(&mut self) {
+        let spinner = SPINNER_FRAMES[self.frame % SPINNER_FRAMES.len()];
+        let detail = self.progress.get();
+
+        let mut lines: Vec<Line> = self.stages.iter().map(|stage| {
+            let (icon, color, suffix) = match &stage.status {
+                StageStatus::Running => (spinner.to_string(), Color::Yellow, detail.clone()),
+                StageStatus::Done(d) => ("✓".to_string(), Color::Green, d.clone()),
+                StageStatus::Skipped => ("⏭".to_string(), Color::DarkGray, "ignoré".to_string()),
+                StageStatus::Failed(e) => ("✗".to_string(), Color::Red, e.clone()),
+            };
+            let mut spans = vec![
+                Span::styled(format!(" {} ", icon), Style::default().fg(color).add_modifier(Modifier::BOLD)),
+                Span::raw(stage.label.clone()),
+            ];
+            if !suffix.is_empty() {
+                spans.push(Span::styled(format!("  {}", suffix), Style::default().fg(Color::DarkGray)));
+            }
+            Line::from(spans)
+        }).collect();
+
+        let running_skippable = self.stages.last()
+            .map(|s| s.skippable && matches!(s.status, StageStatus::Running))
+            .unwrap_or(false);
+        if running_skippable {
+            lines.push(Line::from(""));
+            lines.push(Line::from(Span::styled(
+                " Échap / s : passer cette étape",
+                Style::default().fg(Color::DarkGray).add_modifier(Modifier::ITALIC),
+            )));
+        }
+
+        self.render(" Démarrage de Codestral Companion ", lines);
+    }
+
+    /// Draw the lines in a centered box
+    fn render(&mut self, title: &str, lines: Vec<Line>) {
+        let _ = self.terminal.draw(|frame| {
+            let area = frame.area();
+            let width = 70.min(area.width.saturating_sub(4));
+            let height = (lines.len() as u16 + 2).min(area.height);
+            let rect = Rect {
+                x: (area.width.saturating_sub(width)) / 2,
+                y: (area.height.saturating_sub(height)) / 2,
+                width,
+                height,
+            };
+            let block = Block::default()
+                .title(title)
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(MISTRAL_COLOR));
+            frame.render_widget(Paragraph::new(lines).block(block), rect);
+        });
+    }
+}
+
+/// Visible subdirectories of a directory, sorted by name
+fn subdirectories(dir: &Path) -> Vec<PathBuf> {
+    let mut subdirs: Vec<PathBuf> = fs::read_dir(dir)
+        .map(|entries| entries.flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.is_dir())
+            .filter(|path| !path.file_name().is_some_and(|name| name.to_string_lossy().starts_with('.')))
+            .collect())
+        .unwrap_or_default();
+    subdirs.sort();
+    subdirs
+}
+
+impl Drop for StartupScreen {
+    fn drop(&mut self) {
+        let _ = disable_raw_mode();
+        let _ = execute!(self.terminal.backend_mut(), LeaveAlternateScreen);
+    }
+}
@@ -6,8 +6,9 @@ use ratatui::{
     Frame,
 };
 use crate::tui::app::App;
+use crate::tui::markdown;
 use crate::tui::logo::{MISTRAL_ICON, MISTRAL_COLOR};
-use crate::chat::ChatMode;
+use crate::mode::ChatMode;
 
 const MAX_TOKENS: usize = 32000;
 
@@ -17,9 +18,11 @@ pub fn draw(frame: &mut Frame, app: &App) {
     // Calculate input height based on content (wrap text)
     // Account for borders (2) and "> " prefix (2)
     let available_width = size.width.saturating_sub(6) as usize;
-    let input_len = app.input.chars().count() + 2; // +2 for "> " prefix
-    let input_lines = if available_width > 0 && input_len > 0 {
-        ((input_len + available_width - 1) / available_width).max(1)
+    // Pasted blocks keep their line breaks: each line wraps on its own
+    let input_lines = if available_width > 0 {
+        app.input.split('\n')
+            .map(|line| (line.chars().count() + 2).div_ceil(available_width).max(1)) // +2 for "> " prefix
+            .sum()
     } else {
         1
     };
@@ -96,7 +99,7 @@ fn draw_chat(frame: &mut Frame, app: &App, area: Rect) {
             )),
             Line::from(""),
             Line::from(Span::styled(
-                "  Alt+Shift: mode | /: commandes | ↑↓: historique",
+                "  Alt+Shift: mode | /: commandes | ↑↓: historique | Ctrl+E: déplier",
                 Style::default().fg(Color::DarkGray),
             )),
         ]);
@@ -106,28 +109,59 @@ fn draw_chat(frame: &mut Frame, app: &App, area: Rect) {
 
     let mut items: Vec<ListItem> = Vec::new();
     
-    for msg in &app.messages {
+    let divider = |count: usize| ListItem::new(Line::from(Span::styled(
+        format!("── Conversation reprise ({} messages) · contexte du projet mis à jour ──", count),
+        Style::default().fg(Color::DarkGray).add_modifier(Modifier::ITALIC),
+    )));
+
+    for (i, msg) in app.messages.iter().enumerate() {
+        if app.resumed_at == Some(i) {
+            items.push(divider(i));
+            items.push(ListItem::new(Line::from("")));
+        }
+
         let (prefix, style) = if msg.is_user {
             ("> ", Style::default().fg(Color::Cyan))
         } else {
             ("● ", Style::default().fg(Color::Green))
         };
         
+        // Long responses only show their first lines until unfolded
+        let hidden = app.folded_lines(msg);
+        let shown = msg.content.lines().count() - hidden;
+
         // Wrap content to fit area
-        let content_lines: Vec<Line> = msg.content
-            .lines()
-            .flat_map(|line| {
-                if line.is_empty() {
-                    vec![Line::from("")]
-                } else {
-                    // Simple word wrapping
-                    let max_width = (inner.width as usize).saturating_sub(4);
-                    wrap_line(line, max_width)
-                }
-            })
-            .collect();
+        let content_lines: Vec<Line> = if msg.comparison.is_empty() {
+            let max_width = (inner.width as usize).saturating_sub(4);
+            if msg.is_user {
+                msg.content
+                    .lines()
+                    .take(shown)
+                    .flat_map(|line| {
+                        if line.is_empty() {
+                            vec![Line::from("")]
+                        } else {
+                            // Simple word wrapping
+                            wrap_line(line, max_width)
+                        }
+                    })
+                    .collect()
+            } else {
+                markdown::render(&msg.content, shown, max_width)
+            }
+        } else {
+            comparison_lines(&msg.comparison, (inner.width as usize).saturating_sub(4))
+        };
 
-        // First line with prefix
+        if let Some(summary) = &msg.diff_summary {
+            items.push(ListItem::new(Line::from(vec![
+                Span::styled(prefix, style),
+                Span::styled(format!("📊 {}", summary), Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            ])));
+        }
+
+        // First line with prefix (indented under the diff summary)
+        let prefix = if msg.diff_summary.is_some() { "  " } else { prefix };
         if let Some(first) = content_lines.first() {
             let mut spans = vec![Span::styled(prefix, style)];
             spans.extend(first.spans.clone());
@@ -140,10 +174,37 @@ fn draw_chat(frame: &mut Frame, app: &App, area: Rect) {
             spans.extend(line.spans.clone());
             items.push(ListItem::new(Line::from(spans)));
         }
+        if hidden > 0 {
+            items.push(ListItem::new(Line::from(Span::styled(
+                format!("  ▸ {} lignes masquées · Ctrl+E pour déplier", hidden),
+                Style::default().fg(Color::Yellow).add_modifier(Modifier::ITALIC),
+            ))));
+        }
+
+        // Tool results attached to the message, dimmed
+        let max_width = (inner.width as usize).saturating_sub(6);
+        for result in &msg.tool_results {
+            for line in result.lines().flat_map(|line| wrap_line(line, max_width)) {
+                let text: String = line.spans.iter().map(|s| s.content.as_ref()).collect();
+                items.push(ListItem::new(Line::from(vec![
+                    Span::styled("  ┊ ", Style::default().fg(Color::DarkGray)),
+                    Span::styled(text, Style::default().fg(Color::DarkGray)),
+                ])));
+            }
+        }
+
+        // Time, size and model of the message
+        items.push(ListItem::new(Line::from(Span::styled(
+            format!("  {}", msg.meta.summary()),
+            Style::default().fg(Color::DarkGray).add_modifier(Modifier::ITALIC),
+        ))));
 
         // Empty line between messages
         items.push(ListItem::new(Line::from("")));
     }
+    if app.resumed_at.map(|r| r >= app.messages.len()).unwrap_or(false) {
+        items.push(divider(app.messages.len()));
+    }
 
     // Loading indicator with animation
     if app.loading {
@@ -163,7 +224,7 @@ fn draw_chat(frame: &mut Frame, app: &App, area: Rect) {
         items.push(ListItem::new(Line::from(vec![
             Span::styled(format!("{} ", spinner), Style::default().fg(color).add_modifier(Modifier::BOLD)),
             Span::styled("Réflexion en cours", Style::default().fg(color).add_modifier(Modifier::ITALIC)),
-            Span::styled("...", Style::default().fg(Color::DarkGray)),
+            Span::styled("...  (Échap / Ctrl+C : annuler)", Style::default().fg(Color::DarkGray)),
         ])));
     }
 
@@ -215,14 +276,31 @@ fn draw_input(frame: &mut Frame, app: &App, area: Rect) {
         String::new()
     };
 
-    let input_text = Text::from(Line::from(vec![
-        Span::raw("> "),
-        Span::raw(before_cursor),
-        Span::styled(cursor_char, Style::default().bg(Color::White).fg(Color::Black)),
-        Span::raw(after_cursor),
-    ]));
+    // On a line break the cursor shows as a space at the end of the line
+    let cursor_style = Style::default().bg(Color::White).fg(Color::Black);
+    let (cursor_char, after_cursor) = match cursor_char.as_str() {
+        "\n" => (" ".to_string(), format!("\n{}", after_cursor)),
+        _ => (cursor_char, after_cursor),
+    };
+    let mut lines = vec![Line::from(Span::raw("> "))];
+    for (i, part) in before_cursor.split('\n').enumerate() {
+        if i > 0 {
+            lines.push(Line::from(Span::raw("  ")));
+        }
+        lines.last_mut().unwrap().push_span(Span::raw(part.to_string()));
+    }
+    lines.last_mut().unwrap().push_span(Span::styled(cursor_char, cursor_style));
+    for (i, part) in after_cursor.split('\n').enumerate() {
+        if i > 0 {
+            lines.push(Line::from(Span::raw("  ")));
+        }
+        lines.last_mut().unwrap().push_span(Span::raw(part.to_string()));
+    }
 
-    let input = Paragraph::new(input_text).wrap(Wrap { trim: false });
+    // Keep the cursor's line in view when the block is taller than the input area
+    let cursor_line = before_cursor.matches('\n').count() as u16;
+    let scroll = cursor_line.saturating_sub(input_area.height.saturating_sub(1));
+    let input = Paragraph::new(Text::from(lines)).wrap(Wrap { trim: false }).scroll((scroll, 0));
     frame.render_widget(input, input_area);
 }
 
@@ -243,9 +321,17 @@ fn draw_status_bar(frame: &mut Frame, app: &App, area: Rect) {
 
     let remaining_pct = ((MAX_TOKENS.saturating_sub(app.tokens)) * 100) / MAX_TOKENS;
     
-    let status = Line::from(vec![
+    let mut status = Line::from(vec![
+        Span::styled(
+            if app.offline { " 📴 HORS LIGNE " } else { "" },
+            Style::default().fg(Color::White).bg(Color::Red).add_modifier(Modifier::BOLD),
+        ),
         Span::styled(" -- ", Style::default().fg(Color::DarkGray)),
         Span::styled(mode_name, mode_style),
+        Span::styled(
+            if app.dry_run { " PLAN+" } else { "" },
+            Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD),
+        ),
         Span::styled(" [Alt+⇧] ", Style::default().fg(Color::DarkGray)),
         Span::styled("│ ", Style::default().fg(Color::DarkGray)),
         Span::raw(format!("{} tok", app.tokens)),
@@ -254,6 +340,14 @@ fn draw_status_bar(frame: &mut Frame, app: &App, area: Rect) {
         Span::styled(" │ ", Style::default().fg(Color::DarkGray)),
         Span::styled("/: menu", Style::default().fg(Color::DarkGray)),
     ]);
+    if !app.pinned.is_empty() {
+        status.spans.push(Span::styled(" │ ", Style::default().fg(Color::DarkGray)));
+        status.spans.push(Span::styled(format!("📌 {}", app.pinned.join(", ")), Style::default().fg(Color::Cyan)));
+    }
+    if let Some(retry) = &app.retry_status {
+        status.spans.push(Span::styled(" │ ", Style::default().fg(Color::DarkGray)));
+        status.spans.push(Span::styled(format!("⟳ {}", retry), Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)));
+    }
 
     let status_bar = Paragraph::new(status)
         .style(Style::default().bg(Color::Rgb(30, 30, 30)));
@@ -261,6 +355,43 @@ fn draw_status_bar(frame: &mut Frame, app: &App, area: Rect) {
     frame.render_widget(status_bar, area);
 }
 
+/// Answers of `/compare` in columns, one per model, under their model name
+fn comparison_lines(answers: &[(String, String)], width: usize) -> Vec<Line<'static>> {
+    const SEPARATOR: &str = " │ ";
+    let column = width.saturating_sub(SEPARATOR.chars().count() * answers.len().saturating_sub(1)) / answers.len().max(1);
+    let columns: Vec<Vec<String>> = answers.iter()
+        .map(|(model, answer)| {
+            let mut cells = vec![model.clone(), "─".repeat(column)];
+            for line in answer.lines() {
+                // Words longer than the column are cut so the columns stay aligned
+                cells.extend(wrap_line(line, column).into_iter().map(|wrapped| {
+                    wrapped.spans.iter().map(|s| s.content.as_ref()).collect::<String>().chars().take(column).collect()
+                }));
+            }
+            cells
+        })
+        .collect();
+    let height = columns.iter().map(Vec::len).max().unwrap_or(0);
+
+    (0..height)
+        .map(|row| {
+            let mut spans = Vec::new();
+            for (i, cells) in columns.iter().enumerate() {
+                if i > 0 {
+                    spans.push(Span::styled(SEPARATOR, Style::default().fg(Color::DarkGray)));
+                }
+                let cell = format!("{:<width$}", cells.get(row).map(String::as_str).unwrap_or(""), width = column);
+                spans.push(match row {
+                    0 => Span::styled(cell, Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD)),
+                    1 => Span::styled(cell, Style::default().fg(Color::DarkGray)),
+                    _ => Span::raw(cell),
+                });
+            }
+            Line::from(spans)
+        })
+        .collect()
+}
+
 fn wrap_line(line: &str, max_width: usize) -> Vec<Line<'static>> {
     if line.len() <= max_width {
         return vec![Line::from(line.to_string())];
@@ -0,0 +1,84 @@
+#[path = "../core/tests/common/mod.rs"]
+mod common;
+
+use common::{fixture, read, ScriptedClient};
+use companion_chat_lib::agent::Agent;
+use companion_chat_lib::cli::{AgentConfig, ContextScope, ExecutionMode, OutputFormat};
+use companion_chat_lib::settings::{ApiSettings, ProjectSettings};
+use companion_core::backend::LlmBackend;
+use companion_core::mistral_client::Role;
+use std::path::Path;
+use std::sync::Arc;
+
+fn agent(root: &Path, mode: ExecutionMode, client: &ScriptedClient) -> Agent {
+    let config = AgentConfig {
+        cwd: root.to_path_buf(),
+        instruction: "Corrige subtract".to_string(),
+        mode,
+        include_extensions: None,
+        exclude_dirs: Vec::new(),
+        max_files: 100,
+        context: ContextScope::Full,
+        dry_run: mode == ExecutionMode::Plan,
+        format: OutputFormat::Text,
+        with_history: false,
+    };
+    let settings = ApiSettings { api_key: String::new(), provider: client.provider().clone(), project: ProjectSettings::default() };
+    Agent::new(config, &settings).with_client(Arc::new(client.clone()))
+}
+
+const STALE_PATCH: &str = "<file path=\"src/lib.rs\">
+<<<<<<< ORIGINAL
+pub fn subtract(a: i64, b: i64) -> i64 {
+    a + b
+}
+=======
+pub fn subtract(a: i64, b: i64) -> i64 {
+    a - b
+}
+>>>>>>> MODIFIED
+</file>";
+
+const PATCH: &str = "<file path=\"src/lib.rs\">
+<<<<<<< ORIGINAL
+pub fn subtract(a: i32, b: i32) -> i32 {
+    a + b
+}
+=======
+pub fn subtract(a: i32, b: i32) -> i32 {
+    a - b
+}
+>>>>>>> MODIFIED
+</file>";
+
+#[tokio::test]
+async fn test_auto_mode_retries_a_stale_patch_then_applies_it() {
+    let project = fixture("rust_app");
+    let root = project.path();
+    let client = ScriptedClient::new(&[STALE_PATCH, PATCH]);
+
+    let report = agent(root, ExecutionMode::Auto, &client).run().await.unwrap();
+
+    assert_eq!((report.failed, report.exit_code()), (0, 0));
+    assert!(read(root, "src/lib.rs").contains("    a - b\n"));
+    let requests = client.requests();
+    assert_eq!(requests.len(), 2);
+    assert!(requests[0][0].content.contains("src/lib.rs"), "the codebase is in the system prompt");
+    let retry = requests[1].last().unwrap();
+    assert_eq!(retry.role, Role::User);
+    assert!(retry.content.contains("Aucune modification n'a pu être appliquée"));
+}
+
+#[tokio::test]
+async fn test_plan_mode_writes_nothing() {
+    let project = fixture("rust_app");
+    let root = project.path();
+    let original = read(root, "src/lib.rs");
+    let client = ScriptedClient::new(&[&format!("<plan>\n1. Corriger subtract\n</plan>\n{}", STALE_PATCH)]);
+
+    let report = agent(root, ExecutionMode::Plan, &client).run().await.unwrap();
+
+    assert_eq!(report.plan, ["Corriger subtract"]);
+    assert_eq!(read(root, "src/lib.rs"), original);
+    assert_eq!(client.requests().len(), 1, "no retry in plan mode");
+}
@@ -3,23 +3,40 @@ import { useChatStore } from './stores/useChatStore';
 import Sidebar from './components/Layout/Sidebar';
 import ChatWindow from './components/Chat/ChatWindow';
 import SettingsModal from './components/Settings/SettingsModal';
+import OnboardingModal from './components/Settings/OnboardingModal';
 import { listen } from '@tauri-apps/api/event';
 
 function App() {
-  const { fetchConversations, fetchSettings } = useChatStore();
+  const { fetchConversations, fetchSettings, fetchTemplates, checkApiKey } = useChatStore();
   const [isSidebarOpen, setIsSidebarOpen] = useState(false);
   const [isSettingsOpen, setIsSettingsOpen] = useState(false);
+  const [isOnboardingOpen, setIsOnboardingOpen] = useState(false);
 
   useEffect(() => {
     // Initial fetch
     fetchSettings();
+    fetchTemplates();
     fetchConversations();
 
+    // First run: walk through the key, language and model instead of a blank app
+    checkApiKey()
+      .then(status => setIsOnboardingOpen(!status.has_api_key))
+      .catch(e => console.error('Failed to check the API key', e));
+
     // Listen for tray events
     const unlistenSettings = listen('open-settings', () => {
       setIsSettingsOpen(true);
     });
 
+    // Recent project picked in the tray menu
+    const unlistenOpenProject = listen<string>('open-project', (event) => {
+      const { projects, selectProject } = useChatStore.getState();
+      if (!projects.includes(event.payload)) {
+        useChatStore.setState({ projects: [event.payload, ...projects] });
+      }
+      selectProject(event.payload);
+    });
+
     const unlistenClearHistory = listen('request-clear-history', () => {
       // Maybe show confirmation modal? For now just log
       console.log("History clear requested from tray (backend handled store clear, frontend needs refresh)");
@@ -28,6 +45,7 @@ function App() {
 
     return () => {
       unlistenSettings.then(f => f());
+      unlistenOpenProject.then(f => f());
       unlistenClearHistory.then(f => f());
     };
   }, []);
@@ -48,6 +66,11 @@ function App() {
         isOpen={isSettingsOpen}
         onClose={() => setIsSettingsOpen(false)}
       />
+
+      <OnboardingModal
+        isOpen={isOnboardingOpen}
+        onClose={() => setIsOnboardingOpen(false)}
+      />
     </div>
   );
 }
@@ -0,0 +1,161 @@
+import React, { useState } from 'react';
+import { useChatStore, ApplyReport, FileDiff, ParsedChanges } from '../../stores/useChatStore';
+import { FileDiff as FileDiffIcon, Trash2, FilePlus, FilePen, FileSymlink, Check, X } from 'lucide-react';
+
+/** Blocks a response proposes file changes with */
+const CHANGE_PATTERN = /<(file|new_file|replace_file|delete_file)\s+path=|<rename_file\s+from=|```(diff|patch)\n/;
+
+export const proposesChanges = (content: string) => CHANGE_PATTERN.test(content);
+
+const KIND_STYLE: Record<FileDiff['kind'], { label: string; className: string; icon: React.ReactNode }> = {
+    modify: { label: 'Modify', className: 'bg-blue-100 text-blue-800', icon: <FilePen size={12} /> },
+    create: { label: 'Create', className: 'bg-green-100 text-green-800', icon: <FilePlus size={12} /> },
+    delete: { label: 'Delete', className: 'bg-red-100 text-red-800', icon: <Trash2 size={12} /> },
+    rename: { label: 'Move', className: 'bg-purple-100 text-purple-800', icon: <FileSymlink size={12} /> },
+};
+
+const diffLineClass = (line: string) => {
+    if (line.startsWith('+') && !line.startsWith('+++')) return 'text-green-700 bg-green-50';
+    if (line.startsWith('-') && !line.startsWith('---')) return 'text-red-700 bg-red-50';
+    if (line.startsWith('@@')) return 'text-purple-700';
+    return 'text-gray-600';
+};
+
+/**
+ * Changes proposed by an assistant message, applied only once the user ticked them.
+ * Deletions start unticked and ask for a confirmation: the files go to .codestral/trash
+ */
+const ChangesReview: React.FC<{ content: string }> = ({ content }) => {
+    const { parseChanges, applyChanges } = useChatStore();
+    const [parsed, setParsed] = useState<ParsedChanges | null>(null);
+    const [approved, setApproved] = useState<Set<string>>(new Set());
+    const [expanded, setExpanded] = useState<string | null>(null);
+    const [report, setReport] = useState<ApplyReport | null>(null);
+    const [error, setError] = useState<string | null>(null);
+    const [busy, setBusy] = useState(false);
+
+    const review = async () => {
+        setError(null);
+        try {
+            const changes = await parseChanges(content);
+            setParsed(changes);
+            setApproved(new Set(changes.diffs.filter((diff) => diff.kind !== 'delete').map((diff) => diff.path)));
+        } catch (e) {
+            setError(String(e));
+        }
+    };
+
+    const toggle = (path: string) => {
+        const next = new Set(approved);
+        if (next.has(path)) next.delete(path); else next.add(path);
+        setApproved(next);
+    };
+
+    const apply = async () => {
+        if (!parsed) return;
+        const deletions = parsed.diffs.filter((diff) => diff.kind === 'delete' && approved.has(diff.path));
+        if (deletions.length > 0 && !window.confirm(
+            `Delete ${deletions.map((diff) => diff.display).join(', ')}?\n\nThe files are moved to .codestral/trash and can be restored with "companion-chat rollback".`
+        )) {
+            return;
+        }
+        setBusy(true);
+        setError(null);
+        try {
+            setReport(await applyChanges(parsed.change_set, [...approved]));
+        } catch (e) {
+            setError(String(e));
+        } finally {
+            setBusy(false);
+        }
+    };
+
+    if (!parsed) {
+        return (
+            <div className="mt-2">
+                <button
+                    onClick={review}
+                    className="flex items-center gap-1 text-xs px-2 py-1 rounded-md border border-gray-200 text-gray-600 hover:bg-gray-50"
+                >
+                    <FileDiffIcon size={12} /> Review changes
+                </button>
+                {error && <p className="text-xs text-red-600 mt-1">{error}</p>}
+            </div>
+        );
+    }
+
+    if (report) {
+        return (
+            <div className="mt-3 text-xs space-y-1 border-t pt-2">
+                {report.renamed.map(([from, to]) => (
+                    <p key={to} className="text-purple-700 flex items-center gap-1"><FileSymlink size={12} /> {from} → {to}</p>
+                ))}
+                {report.applied.map((path) => <p key={path} className="text-green-700 flex items-center gap-1"><Check size={12} /> {path}</p>)}
+                {report.deleted.map((path, i) => (
+                    <p key={path} className="text-red-700 flex items-center gap-1">
+                        <Trash2 size={12} /> {path} <span className="text-gray-400">→ {report.trashed[i]}</span>
+                    </p>
+                ))}
+                {report.errors.map((e) => <p key={e} className="text-red-600 flex items-center gap-1"><X size={12} /> {e}</p>)}
+                {report.commit && <p className="text-gray-500">Committed {report.commit}</p>}
+            </div>
+        );
+    }
+
+    return (
+        <div className="mt-3 border-t pt-2 space-y-2">
+            {parsed.diffs.length === 0 && parsed.change_set.patch_failures.length === 0 && (
+                <p className="text-xs text-gray-500">No file change in this response.</p>
+            )}
+            {parsed.diffs.map((diff) => {
+                const style = KIND_STYLE[diff.kind];
+                const key = `${diff.kind}:${diff.path}`;
+                return (
+                    <div key={key} className={`rounded-md border ${diff.kind === 'delete' ? 'border-red-200' : 'border-gray-200'}`}>
+                        <label className="flex items-center gap-2 px-2 py-1 text-xs cursor-pointer">
+                            <input type="checkbox" checked={approved.has(diff.path)} onChange={() => toggle(diff.path)} />
+                            <span className={`flex items-center gap-1 px-1.5 rounded ${style.className}`}>{style.icon} {style.label}</span>
+                            <span className="font-mono truncate flex-1">{diff.display}</span>
+                            <button
+                                type="button"
+                                onClick={(e) => { e.preventDefault(); setExpanded(expanded === key ? null : key); }}
+                                className="text-gray-400 hover:text-gray-700"
+                            >
+                                {expanded === key ? 'Hide' : 'Diff'}
+                            </button>
+                        </label>
+                        {expanded === key && (
+                            <pre className="text-xs font-mono overflow-x-auto max-h-64 border-t">
+                                {diff.diff.split('\n').map((line, i) => (
+                                    <div key={i} className={`px-2 ${diffLineClass(line)}`}>{line || ' '}</div>
+                                ))}
+                            </pre>
+                        )}
+                    </div>
+                );
+            })}
+            {parsed.change_set.patch_failures.map((failure) => (
+                <p key={failure.full_path} className="text-xs text-amber-700">
+                    Patch does not match {failure.path}: ask the model for a corrected one
+                </p>
+            ))}
+            {error && <p className="text-xs text-red-600">{error}</p>}
+            {parsed.diffs.length > 0 && (
+                <div className="flex gap-2">
+                    <button
+                        onClick={apply}
+                        disabled={busy || approved.size === 0}
+                        className="text-xs px-3 py-1 rounded-md bg-blue-600 text-white hover:bg-blue-700 disabled:opacity-50"
+                    >
+                        Apply {approved.size} change{approved.size === 1 ? '' : 's'}
+                    </button>
+                    <button onClick={() => setParsed(null)} className="text-xs px-3 py-1 rounded-md text-gray-600 hover:bg-gray-100">
+                        Cancel
+                    </button>
+                </div>
+            )}
+        </div>
+    );
+};
+
+export default ChangesReview;
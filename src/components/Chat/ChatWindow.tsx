@@ -1,12 +1,13 @@
 import React, { useEffect, useRef } from 'react';
-import { useChatStore } from '../../stores/useChatStore';
+import { useChatStore, ContextBudget, hasCredentials } from '../../stores/useChatStore';
 import { marked } from 'marked';
 import { markedHighlight } from "marked-highlight";
 import DOMPurify from 'dompurify';
 import hljs from 'highlight.js';
 import 'highlight.js/styles/github-dark.css';
 import MessageInput from './MessageInput';
-import { Settings } from 'lucide-react';
+import ChangesReview, { proposesChanges } from './ChangesReview';
+import { Settings, AlertTriangle, WifiOff } from 'lucide-react';
 
 // Configure marked with highlight extension
 marked.use(
@@ -19,13 +20,37 @@ marked.use(
     })
 );
 
+/** Share of the context window above which the indicator turns into a warning */
+const CONTEXT_WARNING_RATIO = 0.75;
+
+const ContextIndicator: React.FC<{ budget: ContextBudget; autoCompact: boolean }> = ({ budget, autoCompact }) => {
+    const ratio = budget.used_tokens / budget.context_window;
+    const warning = ratio >= CONTEXT_WARNING_RATIO;
+    const remaining = `${Math.round(budget.remaining_tokens / 1000)}k tokens left`;
+    const title = budget.compacted
+        ? 'Older messages were summarized to free the context'
+        : warning && !autoCompact
+            ? 'Context almost full: start a new conversation or enable auto-compact in Settings'
+            : `${budget.used_tokens} / ${budget.context_window} tokens used`;
+
+    return (
+        <span
+            title={title}
+            className={`flex items-center gap-1 text-xs px-2 py-1 rounded-full ${warning ? 'bg-amber-100 text-amber-800' : 'bg-gray-100 text-gray-600'}`}
+        >
+            {warning && <AlertTriangle size={12} />}
+            {Math.round(ratio * 100)}% · {remaining}{budget.compacted && ' · compacted'}
+        </span>
+    );
+};
+
 interface Props {
     onOpenSettings: () => void;
     onToggleSidebar: () => void;
 }
 
 const ChatWindow: React.FC<Props> = ({ onOpenSettings, onToggleSidebar }) => {
-    const { currentConversationId, conversations, sendMessage, isLoading, error, settings, createConversation } = useChatStore();
+    const { currentConversationId, conversations, sendMessage, runAndAttach, isLoading, error, settings, createConversation, contextBudget, isOffline, checkConnectivity } = useChatStore();
     const currentConversation = conversations.find((c) => c.id === currentConversationId);
     const messagesEndRef = useRef<HTMLDivElement>(null);
 
@@ -47,7 +72,7 @@ const ChatWindow: React.FC<Props> = ({ onOpenSettings, onToggleSidebar }) => {
             <div className="flex-1 flex flex-col items-center justify-center p-8 bg-gray-50 text-center">
                 <h1 className="text-3xl font-bold text-gray-800 mb-4">Companion Chat</h1>
 
-                {!settings.api_key ? (
+                {!hasCredentials(settings) ? (
                     <>
                         <p className="text-gray-600 mb-8 max-w-md">
                             Welcome! To get started, please configure your API Settings.
@@ -93,11 +118,31 @@ const ChatWindow: React.FC<Props> = ({ onOpenSettings, onToggleSidebar }) => {
                     </h2>
                 </div>
 
-                <button onClick={onOpenSettings} className="p-2 text-gray-500 hover:text-gray-700 hover:bg-gray-100 rounded-full transition">
-                    <Settings size={20} />
-                </button>
+                <div className="flex items-center gap-3">
+                    {contextBudget && (
+                        <ContextIndicator budget={contextBudget} autoCompact={settings.auto_compact} />
+                    )}
+                    <button onClick={onOpenSettings} className="p-2 text-gray-500 hover:text-gray-700 hover:bg-gray-100 rounded-full transition">
+                        <Settings size={20} />
+                    </button>
+                </div>
             </div>
 
+            {isOffline && (
+                <div className="flex items-center gap-2 px-4 py-2 bg-amber-50 border-b border-amber-200 text-amber-800 text-sm shrink-0">
+                    <WifiOff size={16} />
+                    <span className="flex-1">
+                        Offline — saved conversations, the project index and exports remain available. Messages will not be sent.
+                    </span>
+                    <button
+                        onClick={() => checkConnectivity()}
+                        className="px-2 py-1 rounded-md hover:bg-amber-100 font-medium"
+                    >
+                        Retry
+                    </button>
+                </div>
+            )}
+
             {/* Messages */}
             <div className="flex-1 overflow-y-auto p-4 space-y-6">
                 {currentConversation ? (
@@ -116,10 +161,13 @@ const ChatWindow: React.FC<Props> = ({ onOpenSettings, onToggleSidebar }) => {
                                 {msg.role === 'user' ? (
                                     <p className="whitespace-pre-wrap">{msg.content}</p>
                                 ) : (
-                                    <div
-                                        className="prose prose-sm prose-slate max-w-none text-gray-800"
-                                        dangerouslySetInnerHTML={renderContent(msg.content)}
-                                    />
+                                    <>
+                                        <div
+                                            className="prose prose-sm prose-slate max-w-none text-gray-800"
+                                            dangerouslySetInnerHTML={renderContent(msg.content)}
+                                        />
+                                        {proposesChanges(msg.content) && <ChangesReview content={msg.content} />}
+                                    </>
                                 )}
                             </div>
                         </div>
@@ -152,7 +200,7 @@ const ChatWindow: React.FC<Props> = ({ onOpenSettings, onToggleSidebar }) => {
             </div>
 
             {/* Input */}
-            <MessageInput onSend={sendMessage} disabled={isLoading || !currentConversationId} />
+            <MessageInput onSend={sendMessage} onRunCommand={runAndAttach} disabled={isLoading || !currentConversationId} />
         </div>
     );
 };
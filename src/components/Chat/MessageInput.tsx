@@ -1,19 +1,32 @@
 import React, { useState, useRef, useEffect } from 'react';
-import { Send } from 'lucide-react';
+import { Send, Terminal } from 'lucide-react';
 
 interface Props {
     onSend: (content: string) => void;
+    /** Run a shell command in the project and attach its output */
+    onRunCommand: (command: string) => void;
     disabled: boolean;
 }
 
-const MessageInput: React.FC<Props> = ({ onSend, disabled }) => {
+const MessageInput: React.FC<Props> = ({ onSend, onRunCommand, disabled }) => {
     const [content, setContent] = useState('');
     const textareaRef = useRef<HTMLTextAreaElement>(null);
 
     const handleSubmit = (e?: React.FormEvent) => {
         e?.preventDefault();
         if (!content.trim() || disabled) return;
-        onSend(content);
+        // "!cargo build" runs the command instead of sending a message
+        if (content.startsWith('!')) {
+            onRunCommand(content.slice(1).trim());
+        } else {
+            onSend(content);
+        }
+        setContent('');
+    };
+
+    const handleRunCommand = () => {
+        if (!content.trim() || disabled) return;
+        onRunCommand(content.replace(/^!/, '').trim());
         setContent('');
     };
 
@@ -39,11 +52,23 @@ const MessageInput: React.FC<Props> = ({ onSend, disabled }) => {
                     value={content}
                     onChange={(e) => setContent(e.target.value)}
                     onKeyDown={handleKeyDown}
-                    placeholder="Type a message..."
+                    placeholder="Type a message, or !command to attach its output..."
                     disabled={disabled}
                     className="w-full resize-none outline-none max-h-32 bg-transparent text-gray-800 py-2 px-2"
                     rows={1}
                 />
+                <button
+                    type="button"
+                    onClick={handleRunCommand}
+                    disabled={disabled || !content.trim()}
+                    title="Run as a shell command in the project and attach its output"
+                    className={`p-2 rounded-lg transition-colors ${disabled || !content.trim()
+                            ? 'text-gray-300 cursor-not-allowed'
+                            : 'text-gray-600 hover:bg-gray-100'
+                        }`}
+                >
+                    <Terminal size={20} />
+                </button>
                 <button
                     type="submit"
                     disabled={disabled || !content.trim()}
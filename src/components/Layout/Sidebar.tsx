@@ -1,6 +1,6 @@
-import React, { useState } from 'react';
+import React, { useEffect, useState } from 'react';
 import { useChatStore } from '../../stores/useChatStore';
-import { Plus, MessageSquare, Trash2, Edit2, X, Check } from 'lucide-react';
+import { Plus, MessageSquare, Trash2, Edit2, X, Check, Database, RefreshCw, Upload, RotateCcw } from 'lucide-react';
 
 interface Props {
     isOpen: boolean;
@@ -8,9 +8,14 @@ interface Props {
 }
 
 const Sidebar: React.FC<Props> = ({ isOpen, onClose }) => {
-    const { conversations, currentConversationId, createConversation, selectConversation, deleteConversation, renameConversation } = useChatStore();
+    const { conversations, currentConversationId, projects, currentProject, indexStats, isIndexing, fetchProjects, selectProject, indexProject, importExternal, templates, createConversation, selectConversation, deleteConversation, renameConversation, trash, fetchTrash, restoreConversation, emptyTrash } = useChatStore();
     const [editingId, setEditingId] = useState<string | null>(null);
     const [editTitle, setEditTitle] = useState('');
+    const [showTrash, setShowTrash] = useState(false);
+
+    useEffect(() => {
+        fetchProjects();
+    }, []);
 
     const handleCreate = async () => {
         await createConversation();
@@ -36,10 +41,32 @@ const Sidebar: React.FC<Props> = ({ isOpen, onClose }) => {
         setEditingId(null);
     }
 
+    // Deleted conversations go to the trash, so no confirmation
     const handleDelete = async (e: React.MouseEvent, id: string) => {
         e.stopPropagation();
-        if (confirm('Delete this conversation?')) {
-            await deleteConversation(id);
+        await deleteConversation(id);
+    };
+
+    const toggleTrash = async () => {
+        if (!showTrash) await fetchTrash();
+        setShowTrash(!showTrash);
+    };
+
+    const handleEmptyTrash = async () => {
+        if (confirm(`Permanently delete ${trash.length} conversation(s)?`)) {
+            await emptyTrash();
+        }
+    };
+
+    const handleImport = async () => {
+        const path = prompt('Path of the conversations.json file from a ChatGPT or Claude export');
+        if (!path) return;
+        const format = confirm('Is it a ChatGPT export? (Cancel for Claude)') ? 'chatgpt' : 'claude';
+        try {
+            const imported = await importExternal(path, format);
+            alert(`${imported} conversation(s) imported`);
+        } catch (e: any) {
+            alert(`Import failed: ${e}`);
         }
     };
 
@@ -63,8 +90,92 @@ const Sidebar: React.FC<Props> = ({ isOpen, onClose }) => {
                             <Plus size={18} />
                             New Chat
                         </button>
+                        {templates.length > 0 && (
+                            <select
+                                value=""
+                                onChange={(e) => e.target.value && createConversation(undefined, e.target.value)}
+                                className="mt-2 w-full bg-gray-800 text-gray-300 text-sm py-1.5 px-2 rounded border border-gray-700 outline-none"
+                            >
+                                <option value="">New from template…</option>
+                                {templates.map((template) => (
+                                    <option key={template.name} value={template.name} title={template.system_prompt}>
+                                        {template.name}{template.model ? ` (${template.model})` : ''}
+                                    </option>
+                                ))}
+                            </select>
+                        )}
+                        <button
+                            onClick={handleImport}
+                            className="mt-2 w-full flex items-center justify-center gap-2 text-gray-400 hover:text-white text-sm py-1 transition-colors"
+                        >
+                            <Upload size={14} />
+                            Import ChatGPT / Claude
+                        </button>
+                        {projects.length > 0 && (
+                            <select
+                                value={currentProject ?? ''}
+                                onChange={(e) => selectProject(e.target.value || null)}
+                                className="mt-3 w-full bg-gray-800 text-gray-300 text-sm py-1.5 px-2 rounded border border-gray-700 outline-none"
+                            >
+                                <option value="">Tous les projets</option>
+                                {projects.map((project) => (
+                                    <option key={project} value={project} title={project}>
+                                        {project.split('/').filter(Boolean).pop() ?? project}
+                                    </option>
+                                ))}
+                            </select>
+                        )}
+                        {currentProject && (
+                            <div className="mt-2 flex items-center gap-2 text-xs text-gray-400">
+                                <Database size={14} className="shrink-0" />
+                                <span className="flex-1 truncate">
+                                    {indexStats
+                                        ? `Indexed: ${indexStats.files} files, ${Math.round(indexStats.size_bytes / 1024)} KB`
+                                        : 'Indexed: …'}
+                                </span>
+                                <button
+                                    onClick={indexProject}
+                                    disabled={isIndexing}
+                                    title="Reindex project"
+                                    className="p-1 text-gray-500 hover:text-blue-400 rounded disabled:opacity-50"
+                                >
+                                    <RefreshCw size={14} className={isIndexing ? 'animate-spin' : ''} />
+                                </button>
+                            </div>
+                        )}
                     </div>
 
+                    {showTrash ? (
+                    <div className="flex-1 overflow-y-auto py-2">
+                        {trash.length === 0 && (
+                            <p className="px-4 py-3 text-sm text-gray-500">The trash is empty.</p>
+                        )}
+                        {trash.map((conv) => (
+                            <div key={conv.id} className="group flex items-center gap-3 px-4 py-3 hover:bg-gray-800/50">
+                                <Trash2 size={18} className="text-gray-500 shrink-0" />
+                                <span className="flex-1 truncate text-sm text-gray-400" title={conv.title}>
+                                    {conv.title}
+                                    <span className="block text-xs text-gray-600">{conv.days_left} day(s) left</span>
+                                </span>
+                                <button
+                                    onClick={async () => { await restoreConversation(conv.id); setShowTrash(false); }}
+                                    title="Restore"
+                                    className="p-1 text-gray-500 hover:text-green-400 rounded"
+                                >
+                                    <RotateCcw size={14} />
+                                </button>
+                            </div>
+                        ))}
+                        {trash.length > 0 && (
+                            <button
+                                onClick={handleEmptyTrash}
+                                className="mx-4 mt-2 text-xs text-red-400 hover:text-red-300"
+                            >
+                                Empty trash
+                            </button>
+                        )}
+                    </div>
+                    ) : (
                     <div className="flex-1 overflow-y-auto py-2">
                         {conversations.map((conv) => (
                             <div
@@ -91,6 +202,9 @@ const Sidebar: React.FC<Props> = ({ isOpen, onClose }) => {
                                 ) : (
                                     <span className="flex-1 truncate text-sm text-gray-300 group-hover:text-white">
                                         {conv.title}
+                                        {conv.source === 'cli' && (
+                                            <span className="ml-1 text-xs text-purple-400">[CLI]</span>
+                                        )}
                                     </span>
                                 )}
 
@@ -113,8 +227,16 @@ const Sidebar: React.FC<Props> = ({ isOpen, onClose }) => {
                             </div>
                         ))}
                     </div>
+                    )}
 
                     <div className="p-4 border-t border-gray-700 text-xs text-center text-gray-500">
+                        <button
+                            onClick={toggleTrash}
+                            className="mb-2 w-full flex items-center justify-center gap-2 text-gray-400 hover:text-white transition-colors"
+                        >
+                            {showTrash ? <MessageSquare size={14} /> : <Trash2 size={14} />}
+                            {showTrash ? 'Back to conversations' : 'Trash'}
+                        </button>
                         v0.1.0 • Tauri + Mistral
                     </div>
                 </div>
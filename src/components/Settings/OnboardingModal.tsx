@@ -0,0 +1,149 @@
+import React, { useState } from 'react';
+import { useChatStore, ApiProvider, needsApiKey } from '../../stores/useChatStore';
+import { KeyRound, Languages, Cpu, AlertCircle, Loader2 } from 'lucide-react';
+
+interface Props {
+    isOpen: boolean;
+    onClose: () => void;
+}
+
+type Step = 'key' | 'language' | 'model';
+
+/** Answer languages offered at first run; '' keeps the French default */
+const LANGUAGES: { value: string; label: string }[] = [
+    { value: '', label: 'French (default)' },
+    { value: 'English', label: 'English' },
+    { value: 'Spanish', label: 'Spanish' },
+    { value: 'German', label: 'German' },
+    { value: 'auto', label: 'Same language as my messages' },
+];
+
+const OnboardingModal: React.FC<Props> = ({ isOpen, onClose }) => {
+    const { settings, updateSettings, validateKeyLive, setLanguage, chooseDefaultModel, checkConnectivity } = useChatStore();
+    const [step, setStep] = useState<Step>('key');
+    const [apiKey, setApiKey] = useState('');
+    const [provider, setProvider] = useState<ApiProvider>(settings.provider);
+    const [language, setLanguageChoice] = useState('');
+    const [models, setModels] = useState<string[]>([]);
+    const [model, setModel] = useState('');
+    const [isBusy, setIsBusy] = useState(false);
+    const [error, setError] = useState('');
+
+    if (!isOpen) return null;
+
+    const handleValidateKey = async () => {
+        setIsBusy(true);
+        setError('');
+        try {
+            const available = await validateKeyLive(apiKey, provider);
+            await updateSettings({ ...settings, api_key: apiKey.trim(), provider });
+            setModels(available);
+            setStep('language');
+        } catch (e: any) {
+            setError(e.toString());
+        } finally {
+            setIsBusy(false);
+        }
+    };
+
+    const handleFinish = async () => {
+        setIsBusy(true);
+        setError('');
+        try {
+            await setLanguage(language);
+            await chooseDefaultModel(model);
+            await checkConnectivity();
+            onClose();
+        } catch (e: any) {
+            setError(e.toString());
+        } finally {
+            setIsBusy(false);
+        }
+    };
+
+    return (
+        <div className="fixed inset-0 bg-black/50 z-50 flex items-center justify-center">
+            <div className="bg-white rounded-lg shadow-xl w-full max-w-md p-6">
+                <h2 className="text-xl font-semibold mb-1">Welcome to Codestral Companion</h2>
+                <p className="text-sm text-gray-500 mb-4">
+                    Step {step === 'key' ? 1 : step === 'language' ? 2 : 3} of 3
+                </p>
+
+                {step === 'key' && (
+                    <div className="space-y-4">
+                        <div className="flex items-center gap-2 font-medium"><KeyRound size={18} /> Connect your API key</div>
+                        <select
+                            value={typeof provider === 'string' ? provider : 'Custom'}
+                            onChange={(e) => setProvider(e.target.value as ApiProvider)}
+                            className="w-full border border-gray-300 rounded-md p-2 focus:ring-2 focus:ring-blue-500 outline-none"
+                        >
+                            <option value="MistralAi">Mistral AI (api.mistral.ai)</option>
+                            <option value="Codestral">Codestral (codestral.mistral.ai)</option>
+                            <option value="Ollama">Ollama (local server, no key)</option>
+                        </select>
+                        <input
+                            type="password"
+                            value={apiKey}
+                            onChange={(e) => setApiKey(e.target.value)}
+                            placeholder="Enter your API Key"
+                            className="w-full border border-gray-300 rounded-md p-2 focus:ring-2 focus:ring-blue-500 outline-none"
+                        />
+                    </div>
+                )}
+
+                {step === 'language' && (
+                    <div className="space-y-4">
+                        <div className="flex items-center gap-2 font-medium"><Languages size={18} /> Answer language</div>
+                        <select
+                            value={language}
+                            onChange={(e) => setLanguageChoice(e.target.value)}
+                            className="w-full border border-gray-300 rounded-md p-2 focus:ring-2 focus:ring-blue-500 outline-none"
+                        >
+                            {LANGUAGES.map(l => <option key={l.value} value={l.value}>{l.label}</option>)}
+                        </select>
+                    </div>
+                )}
+
+                {step === 'model' && (
+                    <div className="space-y-4">
+                        <div className="flex items-center gap-2 font-medium"><Cpu size={18} /> Default model</div>
+                        <select
+                            value={model}
+                            onChange={(e) => setModel(e.target.value)}
+                            className="w-full border border-gray-300 rounded-md p-2 focus:ring-2 focus:ring-blue-500 outline-none"
+                        >
+                            <option value="">Provider default</option>
+                            {models.map(m => <option key={m} value={m}>{m}</option>)}
+                        </select>
+                        <p className="text-xs text-gray-500">Projects can still pick their own in .codestral/settings.json.</p>
+                    </div>
+                )}
+
+                {error && (
+                    <div className="mt-4 text-red-600 flex items-center gap-1 text-sm"><AlertCircle size={14} /> {error}</div>
+                )}
+
+                <div className="mt-6 flex justify-end gap-3">
+                    {step !== 'key' && (
+                        <button
+                            onClick={() => setStep(step === 'model' ? 'language' : 'key')}
+                            className="px-4 py-2 text-gray-700 hover:bg-gray-100 rounded-md"
+                        >
+                            Back
+                        </button>
+                    )}
+                    <button
+                        disabled={isBusy || (step === 'key' && !apiKey.trim() && needsApiKey(provider))}
+                        onClick={step === 'key' ? handleValidateKey : step === 'language' ? () => setStep('model') : handleFinish}
+                        className="px-4 py-2 bg-blue-600 text-white rounded-md hover:bg-blue-700 disabled:opacity-50 flex items-center gap-2"
+                    >
+                        {isBusy && <Loader2 size={18} className="animate-spin" />}
+                        {step === 'key' ? 'Check key' : step === 'language' ? 'Next' : 'Finish'}
+                    </button>
+                </div>
+            </div>
+        </div>
+    );
+};
+
+export default OnboardingModal;
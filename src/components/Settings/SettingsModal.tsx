@@ -1,6 +1,6 @@
 import React, { useState, useEffect } from 'react';
-import { useChatStore, ApiProvider } from '../../stores/useChatStore';
-import { X, Save, CheckCircle, AlertCircle } from 'lucide-react';
+import { useChatStore, ApiProvider, ProviderHealth } from '../../stores/useChatStore';
+import { X, Save, CheckCircle, AlertCircle, Activity } from 'lucide-react';
 
 interface Props {
     isOpen: boolean;
@@ -8,34 +8,43 @@ interface Props {
 }
 
 const SettingsModal: React.FC<Props> = ({ isOpen, onClose }) => {
-    const { settings, updateSettings, testConnection } = useChatStore();
+    const { settings, updateSettings, providerHealth } = useChatStore();
     const [apiKey, setApiKey] = useState(settings.api_key);
     const [provider, setProvider] = useState<ApiProvider>(settings.provider);
-    const [testStatus, setTestStatus] = useState<'idle' | 'success' | 'error'>('idle');
+    const [autoCompact, setAutoCompact] = useState(settings.auto_compact);
+    const [reindex, setReindex] = useState(settings.scheduled_reindex);
+    const [testStatus, setTestStatus] = useState<'idle' | 'checking' | 'success' | 'error'>('idle');
     const [testMessage, setTestMessage] = useState('');
+    const [health, setHealth] = useState<ProviderHealth | null>(null);
 
     useEffect(() => {
         if (isOpen) {
             setApiKey(settings.api_key);
             setProvider(settings.provider);
+            setAutoCompact(settings.auto_compact);
+            setReindex(settings.scheduled_reindex);
             setTestStatus('idle');
             setTestMessage('');
+            setHealth(null);
         }
     }, [isOpen, settings]);
 
     if (!isOpen) return null;
 
     const handleSave = async () => {
-        await updateSettings({ api_key: apiKey, provider });
+        await updateSettings({ api_key: apiKey, provider, auto_compact: autoCompact, scheduled_reindex: reindex });
         onClose();
     };
 
     const handleTest = async () => {
-        setTestStatus('idle');
+        setTestStatus('checking');
+        setHealth(null);
         try {
-            const msg = await testConnection(apiKey, provider);
-            setTestStatus('success');
-            setTestMessage(msg);
+            const result = await providerHealth(apiKey, provider);
+            setHealth(result);
+            const healthy = result.chat_latency_ms !== null && result.model_available !== false;
+            setTestStatus(healthy ? 'success' : 'error');
+            setTestMessage(result.chat_error ?? (result.model_available === false ? `${result.model} is not served by the provider` : ''));
         } catch (e: any) {
             setTestStatus('error');
             setTestMessage(e.toString());
@@ -56,12 +65,16 @@ const SettingsModal: React.FC<Props> = ({ isOpen, onClose }) => {
                     <div>
                         <label className="block text-sm font-medium text-gray-700 mb-1">API Provider</label>
                         <select
-                            value={provider}
-                            onChange={(e) => setProvider(e.target.value as ApiProvider)}
+                            value={typeof provider === 'string' ? provider : 'Custom'}
+                            onChange={(e) => e.target.value !== 'Custom' && setProvider(e.target.value as ApiProvider)}
                             className="w-full border border-gray-300 rounded-md p-2 focus:ring-2 focus:ring-blue-500 outline-none"
                         >
                             <option value="MistralAi">Mistral AI (api.mistral.ai)</option>
                             <option value="Codestral">Codestral (codestral.mistral.ai)</option>
+                            <option value="Ollama">Ollama (local server, no key)</option>
+                            {typeof provider !== 'string' && (
+                                <option value="Custom">Custom ({provider.Custom.base_url})</option>
+                            )}
                         </select>
                     </div>
 
@@ -76,16 +89,68 @@ const SettingsModal: React.FC<Props> = ({ isOpen, onClose }) => {
                         />
                     </div>
 
+                    <label className="flex items-center gap-2 text-sm text-gray-700">
+                        <input
+                            type="checkbox"
+                            checked={autoCompact}
+                            onChange={(e) => setAutoCompact(e.target.checked)}
+                        />
+                        Auto-compact: summarize older messages when the context is 90% full
+                    </label>
+
+                    <div className="flex items-center gap-2 text-sm text-gray-700">
+                        <input
+                            type="checkbox"
+                            checked={reindex.enabled}
+                            onChange={(e) => setReindex({ ...reindex, enabled: e.target.checked })}
+                        />
+                        <span>Reindex projects every night after</span>
+                        <input
+                            type="number"
+                            min={0}
+                            max={23}
+                            value={reindex.hour}
+                            onChange={(e) => setReindex({ ...reindex, hour: Math.min(23, Math.max(0, Number(e.target.value))) })}
+                            className="w-14 border border-gray-300 rounded-md px-1 py-0.5"
+                        />
+                        <span>h</span>
+                    </div>
+
                     <div className="flex items-center gap-2">
                         <button
                             onClick={handleTest}
-                            className="text-sm text-blue-600 hover:underline"
+                            disabled={testStatus === 'checking'}
+                            className="text-sm text-blue-600 hover:underline flex items-center gap-1 disabled:opacity-50"
                         >
-                            Test Connection
+                            <Activity size={14} /> {testStatus === 'checking' ? 'Checking...' : 'Check provider'}
                         </button>
                         {testStatus === 'success' && <span className="text-green-600 flex items-center gap-1 text-sm"><CheckCircle size={14} /> Connected</span>}
-                        {testStatus === 'error' && <span className="text-red-600 flex items-center gap-1 text-sm"><AlertCircle size={14} /> Failed: {testMessage}</span>}
+                        {testStatus === 'error' && <span className="text-red-600 flex items-center gap-1 text-sm"><AlertCircle size={14} /> Failed{testMessage && `: ${testMessage}`}</span>}
                     </div>
+
+                    {health && (
+                        <dl className="text-xs text-gray-600 grid grid-cols-[auto_1fr] gap-x-3 gap-y-0.5 border rounded-md p-2">
+                            <dt className="font-medium">Provider</dt>
+                            <dd>{health.provider} ({health.host})</dd>
+                            <dt className="font-medium">Model</dt>
+                            <dd>
+                                {health.model}{' '}
+                                {health.model_available === true && <span className="text-green-600">available</span>}
+                                {health.model_available === false && <span className="text-red-600">not listed by the provider</span>}
+                                {health.model_available === null && <span className="text-gray-400">(no model list)</span>}
+                            </dd>
+                            <dt className="font-medium">Model list</dt>
+                            <dd>{health.list_latency_ms !== null ? `${health.list_latency_ms} ms` : <span className="text-red-600">{health.list_error}</span>}</dd>
+                            <dt className="font-medium">Chat request</dt>
+                            <dd>{health.chat_latency_ms !== null ? `${health.chat_latency_ms} ms` : <span className="text-red-600">{health.chat_error}</span>}</dd>
+                            <dt className="font-medium">Quota</dt>
+                            <dd>
+                                {health.rate_limits.length === 0
+                                    ? <span className="text-gray-400">no rate limit headers</span>
+                                    : health.rate_limits.map(([name, value]) => <div key={name} className="font-mono">{name}: {value}</div>)}
+                            </dd>
+                        </dl>
+                    )}
                 </div>
 
                 <div className="mt-6 flex justify-end gap-3">
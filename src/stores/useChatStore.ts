@@ -1,10 +1,23 @@
 import { create } from 'zustand';
 import { invoke } from '@tauri-apps/api/core';
 
-export type ApiProvider = 'Codestral' | 'MistralAi';
+/** OpenAI-compatible endpoint, set in settings.json or with --base-url */
+export interface CustomProvider {
+    Custom: { base_url: string; model: string };
+}
+
+export type ApiProvider = 'Codestral' | 'MistralAi' | 'Ollama' | CustomProvider;
+
+/** Ollama and custom endpoints take requests without a key */
+export const needsApiKey = (provider: ApiProvider) => provider === 'Codestral' || provider === 'MistralAi';
+
+export const hasCredentials = (settings: AppSettings) => !!settings.api_key || !needsApiKey(settings.provider);
+
+/** Serialized like the backend's Role enum */
+export type Role = 'system' | 'user' | 'assistant';
 
 export interface Message {
-    role: string;
+    role: Role;
     content: string;
 }
 
@@ -13,48 +26,203 @@ export interface Conversation {
     title: string;
     messages: Message[];
     created_at: number;
+    project_path: string;
+    source: 'cli' | 'gui';
+    /** Set when created from a template */
+    system_prompt?: string | null;
+    model?: string | null;
+}
+
+/** Deleted conversation, kept 30 days in the trash */
+export interface TrashedConversation extends Conversation {
+    deleted_at: number;
+    days_left: number;
+}
+
+/** System prompt, starter messages and model of a new conversation */
+export interface ConversationTemplate {
+    name: string;
+    system_prompt: string;
+    starter_messages: Message[];
+    model?: string | null;
+}
+
+export interface IndexStats {
+    files: number;
+    size_bytes: number;
+    updated: number;
+}
+
+/** Export file of another assistant */
+export type ExternalFormat = 'chatgpt' | 'claude';
+
+/** Daily reindex of the projects (empty list: the recent projects) */
+export interface ReindexSchedule {
+    enabled: boolean;
+    hour: number;
+    projects: string[];
 }
 
 export interface AppSettings {
     api_key: string;
     provider: ApiProvider;
+    auto_compact: boolean;
+    scheduled_reindex: ReindexSchedule;
+}
+
+/** What the first-run wizard still has to ask */
+export interface OnboardingStatus {
+    has_api_key: boolean;
+    provider: ApiProvider;
+    language: string | null;
+    model: string | null;
+}
+
+/** Result of provider_health: latency, model and quota headers of the provider */
+export interface ProviderHealth {
+    provider: string;
+    host: string;
+    model: string;
+    list_latency_ms: number | null;
+    list_error: string | null;
+    /** null when the provider lists no models */
+    model_available: boolean | null;
+    chat_latency_ms: number | null;
+    chat_error: string | null;
+    /** [header, value] of the rate limit and quota headers of the last response */
+    rate_limits: [string, string][];
+}
+
+export interface ContextBudget {
+    used_tokens: number;
+    context_window: number;
+    remaining_tokens: number;
+    compacted: boolean;
+}
+
+/** Changes parsed from a model response; passed back unchanged to apply_changes */
+export interface ChangeSet {
+    plan: string[];
+    modifications: { path: string; original: string; modified: string; description: string }[];
+    new_files: { path: string; content: string; description: string }[];
+    deletions: { path: string; content: string; description: string }[];
+    /** Moved files; the other changes of a moved file target its new path */
+    renames: { from: string; to: string; content: string; description: string }[];
+    /** Patches whose ORIGINAL block does not match the file */
+    patch_failures: { path: string; full_path: string; expected: string; proposed: string; excerpt: string }[];
+    resolved: { path: string; original: string; modified: string; description: string }[];
+}
+
+export interface FileDiff {
+    /** Absolute path, used to approve the change (the new path of a rename) */
+    path: string;
+    display: string;
+    kind: 'modify' | 'create' | 'delete' | 'rename';
+    diff: string;
+}
+
+export interface ParsedChanges {
+    change_set: ChangeSet;
+    diffs: FileDiff[];
+}
+
+export interface ApplyReport {
+    applied: string[];
+    deleted: string[];
+    /** [old path, new path] of the moved files */
+    renamed: [string, string][];
+    /** Where the deleted files were moved, under .codestral/trash/ */
+    trashed: string[];
+    errors: string[];
+    /** "<short hash> <subject>" of the commit of the applied files, with auto_commit on */
+    commit: string | null;
+}
+
+interface AttachResult {
+    needs_confirmation: boolean;
+    message: Message | null;
+}
+
+interface SendMessageResponse {
+    content: string;
+    budget: ContextBudget;
 }
 
 interface ChatState {
     conversations: Conversation[];
     currentConversationId: string | null;
+    projects: string[];
+    currentProject: string | null;
+    indexStats: IndexStats | null;
+    isIndexing: boolean;
+    contextBudget: ContextBudget | null;
     settings: AppSettings;
+    templates: ConversationTemplate[];
+    trash: TrashedConversation[];
+    isOffline: boolean;
     isLoading: boolean;
     error: string | null;
 
     // Actions
     fetchConversations: () => Promise<void>;
+    fetchProjects: () => Promise<void>;
+    selectProject: (project: string | null) => Promise<void>;
+    fetchIndexStats: () => Promise<void>;
+    indexProject: () => Promise<void>;
     fetchSettings: () => Promise<void>;
-    createConversation: (title?: string) => Promise<void>;
+    checkConnectivity: () => Promise<boolean>;
+    fetchTemplates: () => Promise<void>;
+    saveTemplates: (templates: ConversationTemplate[]) => Promise<void>;
+    createConversation: (title?: string, template?: string) => Promise<void>;
     selectConversation: (id: string) => void;
     deleteConversation: (id: string) => Promise<void>;
+    fetchTrash: () => Promise<void>;
+    restoreConversation: (id: string) => Promise<void>;
+    emptyTrash: () => Promise<void>;
     renameConversation: (id: string, title: string) => Promise<void>;
     sendMessage: (content: string) => Promise<void>;
+    runAndAttach: (command: string) => Promise<void>;
     updateSettings: (settings: AppSettings) => Promise<void>;
     clearHistory: () => Promise<void>;
+    importExternal: (path: string, format: ExternalFormat) => Promise<number>;
+    parseChanges: (responseText: string) => Promise<ParsedChanges>;
+    applyChanges: (changeSet: ChangeSet, approved: string[]) => Promise<ApplyReport>;
     testConnection: (apiKey: string, provider: ApiProvider) => Promise<string>;
+    providerHealth: (apiKey: string, provider: ApiProvider) => Promise<ProviderHealth>;
+    checkApiKey: () => Promise<OnboardingStatus>;
+    validateKeyLive: (apiKey: string, provider: ApiProvider) => Promise<string[]>;
+    setLanguage: (language: string) => Promise<void>;
+    chooseDefaultModel: (model: string) => Promise<void>;
 }
 
 export const useChatStore = create<ChatState>((set, get) => ({
     conversations: [],
     currentConversationId: null,
+    projects: [],
+    currentProject: null,
+    indexStats: null,
+    isIndexing: false,
+    contextBudget: null,
     settings: {
         api_key: '',
         provider: 'MistralAi', // Default
+        auto_compact: false,
+        scheduled_reindex: { enabled: false, hour: 3, projects: [] },
     },
+    templates: [],
+    trash: [],
+    isOffline: false,
     isLoading: false,
     error: null,
 
     fetchConversations: async () => {
         try {
-            const conversations = await invoke<Conversation[]>('get_conversations');
+            const conversations = await invoke<Conversation[]>('get_conversations', {
+                projectPath: get().currentProject,
+            });
+            const currentId = get().currentConversationId;
             set({ conversations });
-            if (conversations.length > 0 && !get().currentConversationId) {
+            if (conversations.length > 0 && !conversations.some((c) => c.id === currentId)) {
                 set({ currentConversationId: conversations[0].id });
             }
         } catch (e) {
@@ -62,21 +230,95 @@ export const useChatStore = create<ChatState>((set, get) => ({
         }
     },
 
+    fetchProjects: async () => {
+        try {
+            const projects = await invoke<string[]>('get_projects');
+            set({ projects });
+        } catch (e) {
+            console.error('Failed to fetch projects', e);
+        }
+    },
+
+    selectProject: async (project) => {
+        set({ currentProject: project, indexStats: null });
+        await Promise.all([get().fetchConversations(), get().fetchIndexStats()]);
+    },
+
+    fetchIndexStats: async () => {
+        const project = get().currentProject;
+        if (!project) return;
+        try {
+            const indexStats = await invoke<IndexStats>('get_index_stats', { path: project });
+            set({ indexStats });
+        } catch (e) {
+            console.error('Failed to fetch index stats', e);
+        }
+    },
+
+    indexProject: async () => {
+        const project = get().currentProject;
+        if (!project) return;
+        set({ isIndexing: true });
+        try {
+            const indexStats = await invoke<IndexStats>('index_project', { path: project });
+            set({ indexStats });
+        } catch (e: any) {
+            set({ error: e.toString() });
+        } finally {
+            set({ isIndexing: false });
+        }
+    },
+
     fetchSettings: async () => {
         try {
             const settings = await invoke<AppSettings>('get_app_settings');
             set({ settings });
+            if (hasCredentials(settings)) {
+                await get().checkConnectivity();
+            }
         } catch (e) {
             console.error('Failed to fetch settings', e);
         }
     },
 
-    createConversation: async (title) => {
+    checkConnectivity: async () => {
+        try {
+            await invoke('check_connectivity', { provider: get().settings.provider });
+            set({ isOffline: false });
+            return true;
+        } catch (e) {
+            // Saved conversations, the index and exports stay available offline
+            console.warn('API unreachable', e);
+            set({ isOffline: true });
+            return false;
+        }
+    },
+
+    fetchTemplates: async () => {
         try {
-            const newConv = await invoke<Conversation>('create_conversation', { title });
+            const templates = await invoke<ConversationTemplate[]>('get_conversation_templates');
+            set({ templates });
+        } catch (e) {
+            console.error('Failed to fetch templates', e);
+        }
+    },
+
+    saveTemplates: async (templates) => {
+        await invoke('save_conversation_templates', { templates });
+        await get().fetchTemplates();
+    },
+
+    createConversation: async (title, template) => {
+        try {
+            const newConv = await invoke<Conversation>('create_conversation', {
+                title,
+                projectPath: get().currentProject,
+                template,
+            });
             set((state) => ({
                 conversations: [newConv, ...state.conversations],
                 currentConversationId: newConv.id,
+                contextBudget: null,
             }));
         } catch (e) {
             console.error('Failed to create conversation', e);
@@ -84,7 +326,8 @@ export const useChatStore = create<ChatState>((set, get) => ({
     },
 
     selectConversation: (id) => {
-        set({ currentConversationId: id });
+        // The budget is only known after the next response in this conversation
+        set({ currentConversationId: id, contextBudget: null });
     },
 
     deleteConversation: async (id) => {
@@ -104,6 +347,35 @@ export const useChatStore = create<ChatState>((set, get) => ({
         }
     },
 
+    fetchTrash: async () => {
+        try {
+            const trash = await invoke<TrashedConversation[]>('get_trash');
+            set({ trash });
+        } catch (e) {
+            console.error('Failed to fetch trash', e);
+        }
+    },
+
+    restoreConversation: async (id) => {
+        try {
+            const restored = await invoke<Conversation>('restore_conversation', { conversationId: id });
+            set((state) => ({ trash: state.trash.filter((c) => c.id !== id) }));
+            await get().fetchConversations();
+            set({ currentConversationId: restored.id });
+        } catch (e) {
+            console.error('Failed to restore conversation', e);
+        }
+    },
+
+    emptyTrash: async () => {
+        try {
+            await invoke('empty_trash');
+            set({ trash: [] });
+        } catch (e) {
+            console.error('Failed to empty trash', e);
+        }
+    },
+
     renameConversation: async (id, title) => {
         try {
             await invoke('rename_conversation', { conversationId: id, newTitle: title });
@@ -117,12 +389,17 @@ export const useChatStore = create<ChatState>((set, get) => ({
 
     sendMessage: async (content) => {
         const { currentConversationId, settings } = get();
-        if (!currentConversationId || !settings.api_key) return;
+        if (!currentConversationId || !hasCredentials(settings)) return;
 
         set({ isLoading: true, error: null });
 
+        if (get().isOffline && !(await get().checkConnectivity())) {
+            set({ isLoading: false, error: 'Offline: the message was not sent. Check your connection and retry.' });
+            return;
+        }
+
         // Optimistic update
-        const userMsg = { role: 'user', content };
+        const userMsg: Message = { role: 'user', content };
         set((state) => ({
             conversations: state.conversations.map(c =>
                 c.id === currentConversationId
@@ -132,14 +409,22 @@ export const useChatStore = create<ChatState>((set, get) => ({
         }));
 
         try {
-            const response = await invoke<string>('send_message', {
+            const response = await invoke<SendMessageResponse>('send_message', {
                 conversationId: currentConversationId,
                 content,
                 apiKey: settings.api_key,
                 provider: settings.provider,
+                autoCompact: settings.auto_compact,
             });
+            set({ contextBudget: response.budget });
+
+            if (response.budget.compacted) {
+                // Older messages were replaced by a summary on disk
+                await get().fetchConversations();
+                return;
+            }
 
-            const assistantMsg = { role: 'assistant', content: response };
+            const assistantMsg: Message = { role: 'assistant', content: response.content };
             set((state) => ({
                 conversations: state.conversations.map(c =>
                     c.id === currentConversationId
@@ -149,12 +434,51 @@ export const useChatStore = create<ChatState>((set, get) => ({
             }));
         } catch (e: any) {
             set({ error: e.toString() });
+            if (e.toString().includes('No network')) {
+                set({ isOffline: true });
+            }
             // Remove optimistic message if needed, or just show error
         } finally {
             set({ isLoading: false });
         }
     },
 
+    runAndAttach: async (command) => {
+        const { currentConversationId } = get();
+        if (!currentConversationId) return;
+
+        set({ isLoading: true, error: null });
+        try {
+            let result = await invoke<AttachResult>('run_and_attach', {
+                conversationId: currentConversationId,
+                command,
+            });
+            if (result.needs_confirmation) {
+                if (!confirm(`This command is potentially dangerous:\n\n${command}\n\nRun it anyway?`)) return;
+                result = await invoke<AttachResult>('run_and_attach', {
+                    conversationId: currentConversationId,
+                    command,
+                    confirmed: true,
+                });
+            }
+
+            const attached = result.message;
+            if (attached) {
+                set((state) => ({
+                    conversations: state.conversations.map(c =>
+                        c.id === currentConversationId
+                            ? { ...c, messages: [...c.messages, attached] }
+                            : c
+                    )
+                }));
+            }
+        } catch (e: any) {
+            set({ error: e.toString() });
+        } finally {
+            set({ isLoading: false });
+        }
+    },
+
     updateSettings: async (settings) => {
         try {
             await invoke('update_settings', { settings });
@@ -173,8 +497,51 @@ export const useChatStore = create<ChatState>((set, get) => ({
         }
     },
 
+    importExternal: async (path, format) => {
+        const imported = await invoke<number>('import_external', { path, format });
+        await get().fetchConversations();
+        return imported;
+    },
+
+    parseChanges: async (responseText) => {
+        const project = get().currentProject;
+        if (!project) throw new Error('Select a project first');
+        return await invoke<ParsedChanges>('parse_changes', { responseText, projectPath: project });
+    },
+
+    applyChanges: async (changeSet, approved) => {
+        const project = get().currentProject;
+        if (!project) throw new Error('Select a project first');
+        const report = await invoke<ApplyReport>('apply_changes', {
+            changeSet,
+            options: { project_path: project, approved },
+        });
+        await get().fetchIndexStats();
+        return report;
+    },
+
     testConnection: async (apiKey, provider) => {
         return await invoke('test_api_connection', { apiKey, provider });
+    },
+
+    providerHealth: async (apiKey, provider) => {
+        return await invoke<ProviderHealth>('provider_health', { apiKey, provider });
+    },
+
+    checkApiKey: async () => {
+        return await invoke<OnboardingStatus>('check_api_key');
+    },
+
+    validateKeyLive: async (apiKey, provider) => {
+        return await invoke<string[]>('validate_key_live', { apiKey, provider });
+    },
+
+    setLanguage: async (language) => {
+        await invoke('set_language', { language });
+    },
+
+    chooseDefaultModel: async (model) => {
+        await invoke('choose_default_model', { model });
     }
 
 }));